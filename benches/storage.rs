@@ -0,0 +1,76 @@
+//! Encode/decode benchmarks for the storage formats.
+//!
+//! Run with `cargo bench --bench storage`. The binary format exists to make
+//! loading large tables cheap, so the benchmark fails if it stops being
+//! both smaller and faster to decode than JSON.
+
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+use mini_rust_sgbd::core::row::Row;
+use mini_rust_sgbd::core::schema::{Column, Schema};
+use mini_rust_sgbd::core::table::Table;
+use mini_rust_sgbd::core::types::{ColumnName, DataType, TableName, Value};
+use mini_rust_sgbd::storage::storage::{decode_table, encode_table, StorageFormat};
+
+const ROWS: i64 = 50_000;
+const RUNS: u32 = 5;
+
+fn sample_table() -> Table {
+    let schema = Schema::try_new(vec![
+        Column::new(ColumnName::new("id").unwrap(), DataType::Int),
+        Column::new(ColumnName::new("name").unwrap(), DataType::Text),
+        Column::new(ColumnName::new("score").unwrap(), DataType::Float),
+        Column::new(ColumnName::new("active").unwrap(), DataType::Bool),
+    ])
+    .unwrap();
+    let mut table = Table::create(TableName::new("bench").unwrap(), schema);
+    let rows = (0..ROWS)
+        .map(|i| {
+            let values = vec![
+                Value::Int(i),
+                Value::Text(format!("user number {}", i)),
+                Value::Float(i as f64 / 7.0),
+                if i % 5 == 0 { Value::Null } else { Value::Bool(i % 2 == 0) },
+            ];
+            Row::from_values(values, table.schema()).unwrap()
+        })
+        .collect();
+    table.insert_many(rows).unwrap();
+    table
+}
+
+/// Returns the fastest of `RUNS` timings of `f`.
+fn time<T>(mut f: impl FnMut() -> T) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            black_box(f());
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let table = sample_table();
+    let mut decode_times = Vec::new();
+    for format in [StorageFormat::Json, StorageFormat::Binary, StorageFormat::Heap] {
+        let bytes = encode_table(&table, format).unwrap();
+        let encode = time(|| encode_table(&table, format).unwrap());
+        let decode = time(|| decode_table(&bytes).unwrap());
+        println!(
+            "{:?}: {} rows, {} bytes, encode {:?}, decode {:?}",
+            format,
+            ROWS,
+            bytes.len(),
+            encode,
+            decode
+        );
+        decode_times.push((bytes.len(), decode));
+    }
+
+    let (json, binary) = (decode_times[0], decode_times[1]);
+    assert!(binary.0 < json.0, "binary encoding is larger than JSON");
+    assert!(binary.1 < json.1, "binary decoding is slower than JSON");
+}
@@ -0,0 +1,226 @@
+//! Namespaces of a database: its schemas, and the other databases
+//! attached to it.
+//!
+//! Tables are named `table` or `schema.table`. A plain name, or one in the
+//! schema [`DEFAULT_SCHEMA`] (`main.table`), is a table of the default
+//! schema; other schemas are created with `CREATE SCHEMA`, so that tables
+//! with the same name can live side by side:
+//!
+//! ```sql
+//! CREATE SCHEMA sales;
+//! CREATE TABLE sales.orders (id Int PRIMARY KEY, amount Float);
+//! SELECT o.amount FROM sales.orders o JOIN main.users u ON u.id = o.id;
+//! ```
+//!
+//! `ATTACH 'path' AS archive` loads the database stored in `path` and adds
+//! it to the [`Catalog`] of the current one under the alias `archive`.
+//! Its tables can then be read as `archive.table`, next to the tables of
+//! the current database, which keep their plain names:
+//!
+//! ```sql
+//! ATTACH 'archive_2023' AS archive;
+//! SELECT u.name, o.amount FROM users u JOIN archive.orders o ON o.user_id = u.id;
+//! DETACH archive;
+//! ```
+//!
+//! An attached database is a snapshot taken when it was attached: it can
+//! only be read. Its directory stays locked until it is detached, so that
+//! no other process changes it meanwhile. Attachments last as long as the
+//! database they were made on; they are not saved with it. Schemas are.
+//!
+//! Schemas and aliases share one namespace: a name cannot be both.
+//!
+//! The catalog also keeps the views of the database, by name: the text of
+//! the query each one stands for, which the
+//! [`Planner`](crate::planner::planner::Planner) expands wherever the view
+//! is read.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+use std::sync::Arc;
+
+use crate::core::db::Database;
+use crate::core::error::{SqlError, SqlResult};
+use crate::core::table::Table;
+use crate::core::types::{validate_schema_name, TableName};
+
+/// Name of the schema of tables named without one.
+pub const DEFAULT_SCHEMA: &str = "main";
+
+/// The schemas of a [`Database`] besides [`DEFAULT_SCHEMA`], its views,
+/// and the databases attached to it, by alias.
+///
+/// Schema names are matched exactly, like table names. Aliases are matched
+/// ignoring case, and kept in lowercase. The attached databases are shared
+/// by the clones of the catalog.
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    schemas: BTreeSet<String>,
+    views: BTreeMap<String, String>,
+    attached: BTreeMap<String, Arc<Attached>>,
+}
+
+/// An attached database, and what must live as long as it stays attached,
+/// such as the lock on the directory it was loaded from.
+#[derive(Debug)]
+struct Attached {
+    db: Database,
+    _guard: Option<Box<dyn fmt::Debug + Send + Sync>>,
+}
+
+impl Catalog {
+    /// Creates a catalog with no schema and nothing attached.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates the schema `name`.
+    ///
+    /// # Returns
+    /// `Ok(())`, or `Err(SqlError)` if the name is invalid, or already
+    /// names a schema or an attached database.
+    pub fn create_schema(&mut self, name: &str) -> SqlResult<()> {
+        validate_schema_name(name).map_err(|e| SqlError::new_semantic(&e))?;
+        if self.is_taken(name) {
+            return Err(SqlError::new_semantic(&format!("Schema '{}' already exists", name)));
+        }
+        self.schemas.insert(name.to_string());
+        Ok(())
+    }
+
+    /// Returns `true` if the schema `name` was created.
+    pub fn has_schema(&self, name: &str) -> bool {
+        self.schemas.contains(name)
+    }
+
+    /// Returns the names of the created schemas, sorted alphabetically.
+    pub fn schemas(&self) -> Vec<&str> {
+        self.schemas.iter().map(String::as_str).collect()
+    }
+
+    /// Adds the view `name`, standing for the query `sql`. Nothing is
+    /// checked: see [`Database::create_view`].
+    pub fn add_view(&mut self, name: &TableName, sql: &str) {
+        self.views.insert(name.as_str().to_string(), sql.to_string());
+    }
+
+    /// Returns the query of the view `name`, if there is one.
+    pub fn view(&self, name: &TableName) -> Option<&str> {
+        self.views.get(name.as_str()).map(String::as_str)
+    }
+
+    /// Returns the names and queries of the views, sorted by name.
+    pub fn views(&self) -> Vec<(&str, &str)> {
+        self.views.iter().map(|(name, sql)| (name.as_str(), sql.as_str())).collect()
+    }
+
+    /// Returns `true` if `name` is the default schema, a created schema or
+    /// the alias of an attached database, ignoring case.
+    fn is_taken(&self, name: &str) -> bool {
+        name.eq_ignore_ascii_case(DEFAULT_SCHEMA)
+            || self.schemas.iter().any(|schema| schema.eq_ignore_ascii_case(name))
+            || self.database(name).is_some()
+    }
+
+    /// Attaches `db` under `alias`.
+    ///
+    /// # Returns
+    /// `Ok(())`, or `Err(SqlError)` if a database is already attached
+    /// under `alias`, or `alias` names a schema.
+    pub fn attach(&mut self, alias: &str, db: Database) -> SqlResult<()> {
+        self.insert_attached(alias, Attached { db, _guard: None })
+    }
+
+    /// Attaches `db` under `alias` as [`attach`](Self::attach) does,
+    /// keeping `guard` until the database is detached and no clone of the
+    /// catalog (e.g. saved by a transaction) refers to it anymore.
+    pub fn attach_guarded<G>(&mut self, alias: &str, db: Database, guard: G) -> SqlResult<()>
+    where
+        G: fmt::Debug + Send + Sync + 'static,
+    {
+        self.insert_attached(alias, Attached { db, _guard: Some(Box::new(guard)) })
+    }
+
+    fn insert_attached(&mut self, alias: &str, attached: Attached) -> SqlResult<()> {
+        if self.database(alias).is_some() {
+            return Err(SqlError::new_semantic(&format!("A database is already attached as '{}'", alias)));
+        }
+        if self.is_taken(alias) {
+            return Err(SqlError::new_semantic(&format!("'{}' already names a schema", alias)));
+        }
+        self.attached.insert(alias.to_ascii_lowercase(), Arc::new(attached));
+        Ok(())
+    }
+
+    /// Detaches the database attached under `alias`.
+    ///
+    /// # Returns
+    /// `Ok(())`, or `Err(SqlError)` if none is.
+    pub fn detach(&mut self, alias: &str) -> SqlResult<()> {
+        self.attached
+            .remove(&alias.to_ascii_lowercase())
+            .map(|_| ())
+            .ok_or_else(|| SqlError::new_semantic(&format!("No database is attached as '{}'", alias)))
+    }
+
+    /// Returns the database attached under `alias`, if any.
+    pub fn database(&self, alias: &str) -> Option<&Database> {
+        self.attached.get(&alias.to_ascii_lowercase()).map(|attached| &attached.db)
+    }
+
+    /// Returns the aliases of the attached databases, sorted
+    /// alphabetically.
+    pub fn aliases(&self) -> Vec<&str> {
+        self.attached.keys().map(String::as_str).collect()
+    }
+
+    /// Returns the table that the qualified name `alias.table` refers to,
+    /// or `None` if `name` is not qualified by the alias of an attached
+    /// database holding such a table.
+    pub fn table(&self, name: &TableName) -> Option<&Table> {
+        let alias = name.schema()?;
+        self.database(alias)?.table(&TableName::new(name.table()).ok()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::schema::{Column, Schema};
+    use crate::core::types::{ColumnName, DataType};
+
+    #[test]
+    fn attached_tables_are_found_by_qualified_name() {
+        let mut archive = Database::new();
+        let schema = Schema::try_new(vec![Column::new(ColumnName::new("id").unwrap(), DataType::Int)]).unwrap();
+        archive.create_table(TableName::new("orders").unwrap(), schema).unwrap();
+
+        let mut catalog = Catalog::new();
+        catalog.attach("Archive", archive).unwrap();
+        assert!(catalog.attach("archive", Database::new()).is_err());
+        assert_eq!(catalog.aliases(), vec!["archive"]);
+
+        assert!(catalog.table(&TableName::qualified("ARCHIVE", "orders").unwrap()).is_some());
+        assert!(catalog.table(&TableName::qualified("archive", "users").unwrap()).is_none());
+        assert!(catalog.table(&TableName::new("orders").unwrap()).is_none());
+
+        catalog.detach("archive").unwrap();
+        assert!(catalog.detach("archive").is_err());
+        assert!(catalog.table(&TableName::qualified("archive", "orders").unwrap()).is_none());
+    }
+
+    #[test]
+    fn schemas_and_aliases_share_a_namespace() {
+        let mut catalog = Catalog::new();
+        catalog.create_schema("sales").unwrap();
+        assert!(catalog.has_schema("sales"));
+        assert!(catalog.create_schema("Sales").is_err());
+        assert!(catalog.create_schema("main").is_err());
+        assert!(catalog.create_schema("1st").is_err());
+        assert!(catalog.attach("SALES", Database::new()).is_err());
+
+        catalog.attach("archive", Database::new()).unwrap();
+        assert!(catalog.create_schema("archive").is_err());
+        assert_eq!(catalog.schemas(), vec!["sales"]);
+    }
+}
@@ -0,0 +1,188 @@
+//! Calendar helpers for the `Date` and `Timestamp` types.
+//!
+//! Dates are stored as days since `1970-01-01` and timestamps as seconds
+//! since `1970-01-01 00:00:00` UTC. This module converts between those
+//! numbers and the ISO-8601 text forms accepted in SQL literals
+//! (`YYYY-MM-DD` and `YYYY-MM-DD HH:MM:SS`, with `T` also allowed as the
+//! separator and an optional trailing `Z`).
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// Parses an ISO-8601 date (`YYYY-MM-DD`) into days since the Unix epoch.
+///
+/// # Examples
+/// ```
+/// use mini_rust_sgbd::core::datetime::parse_date;
+///
+/// assert_eq!(parse_date("1970-01-02"), Ok(1));
+/// assert!(parse_date("2023-02-29").is_err());
+/// ```
+pub fn parse_date(text: &str) -> Result<i32, String> {
+    let invalid = || format!("Invalid date '{}', expected YYYY-MM-DD", text);
+    let bytes = text.as_bytes();
+    if bytes.len() != 10 || bytes[4] != b'-' || bytes[7] != b'-' {
+        return Err(invalid());
+    }
+
+    let year = parse_digits(&text[0..4]).ok_or_else(invalid)?;
+    let month = parse_digits(&text[5..7]).ok_or_else(invalid)?;
+    let day = parse_digits(&text[8..10]).ok_or_else(invalid)?;
+    if !(1..=12).contains(&month) || day < 1 || day > days_in_month(year, month) {
+        return Err(invalid());
+    }
+
+    Ok(days_from_civil(year, month, day) as i32)
+}
+
+/// Parses an ISO-8601 timestamp into seconds since the Unix epoch (UTC).
+///
+/// Accepts `YYYY-MM-DD HH:MM:SS` or `YYYY-MM-DDTHH:MM:SS`, optionally
+/// followed by `Z`. A bare date is read as midnight of that day.
+///
+/// # Examples
+/// ```
+/// use mini_rust_sgbd::core::datetime::parse_timestamp;
+///
+/// assert_eq!(parse_timestamp("1970-01-01 00:01:00"), Ok(60));
+/// assert_eq!(parse_timestamp("1970-01-02"), Ok(86_400));
+/// ```
+pub fn parse_timestamp(text: &str) -> Result<i64, String> {
+    let invalid = || format!("Invalid timestamp '{}', expected YYYY-MM-DD HH:MM:SS", text);
+    if text.len() == 10 {
+        return parse_date(text).map(|days| days as i64 * SECONDS_PER_DAY);
+    }
+
+    let body = text.strip_suffix('Z').unwrap_or(text);
+    let bytes = body.as_bytes();
+    if bytes.len() != 19
+        || !matches!(bytes[10], b' ' | b'T')
+        || bytes[13] != b':'
+        || bytes[16] != b':'
+    {
+        return Err(invalid());
+    }
+
+    let days = parse_date(&body[0..10]).map_err(|_| invalid())? as i64;
+    let hour = parse_digits(&body[11..13]).ok_or_else(invalid)?;
+    let minute = parse_digits(&body[14..16]).ok_or_else(invalid)?;
+    let second = parse_digits(&body[17..19]).ok_or_else(invalid)?;
+    if hour > 23 || minute > 59 || second > 59 {
+        return Err(invalid());
+    }
+
+    Ok(days * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second)
+}
+
+/// Formats days since the Unix epoch as `YYYY-MM-DD`.
+pub fn format_date(days: i32) -> String {
+    let (year, month, day) = civil_from_days(days as i64);
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Formats seconds since the Unix epoch as `YYYY-MM-DD HH:MM:SS`.
+pub fn format_timestamp(seconds: i64) -> String {
+    let days = seconds.div_euclid(SECONDS_PER_DAY);
+    let secs = seconds.rem_euclid(SECONDS_PER_DAY);
+    format!(
+        "{} {:02}:{:02}:{:02}",
+        format_date(days as i32),
+        secs / 3600,
+        secs % 3600 / 60,
+        secs % 60
+    )
+}
+
+/// Converts days since the Unix epoch to the timestamp of that midnight.
+pub fn date_to_timestamp(days: i32) -> i64 {
+    days as i64 * SECONDS_PER_DAY
+}
+
+/// Returns the current time as seconds since the Unix epoch.
+pub fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+/// Parses a fixed-width run of ASCII digits.
+fn parse_digits(text: &str) -> Option<i64> {
+    if text.bytes().all(|b| b.is_ascii_digit()) {
+        text.parse().ok()
+    } else {
+        None
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since the Unix epoch for a proleptic Gregorian date
+/// (Howard Hinnant's `days_from_civil`).
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let y = if month <= 2 { year - 1 } else { year };
+    let era = y.div_euclid(400);
+    let yoe = y - era * 400;
+    let mp = (month + 9) % 12;
+    let doy = (153 * mp + 2) / 5 + day - 1;
+    let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+    era * 146_097 + doe - 719_468
+}
+
+/// Inverse of [`days_from_civil`].
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z - era * 146_097;
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn date_roundtrip() {
+        for text in ["1970-01-01", "2000-02-29", "1969-12-31", "2024-12-31", "1600-03-01"] {
+            assert_eq!(format_date(parse_date(text).unwrap()), text);
+        }
+        assert_eq!(parse_date("1969-12-31"), Ok(-1));
+    }
+
+    #[test]
+    fn invalid_dates_are_rejected() {
+        assert!(parse_date("2024-13-01").is_err());
+        assert!(parse_date("2024-04-31").is_err());
+        assert!(parse_date("1900-02-29").is_err());
+        assert!(parse_date("2024-1-01").is_err());
+        assert!(parse_date("yesterday").is_err());
+    }
+
+    #[test]
+    fn timestamp_roundtrip_and_separators() {
+        let ts = parse_timestamp("2024-03-15 08:30:05").unwrap();
+        assert_eq!(format_timestamp(ts), "2024-03-15 08:30:05");
+        assert_eq!(parse_timestamp("2024-03-15T08:30:05Z"), Ok(ts));
+        assert_eq!(format_timestamp(-1), "1969-12-31 23:59:59");
+        assert!(parse_timestamp("2024-03-15 24:00:00").is_err());
+        assert!(parse_timestamp("2024-03-15 08:30").is_err());
+    }
+}
@@ -3,20 +3,80 @@
 //! This module defines the `Database` type, which manages all tables
 //! in the system. It ensures that table names are unique and provides
 //! convenient access to tables, both immutable and mutable.
+//!
+//! The database also manages transactions: [`Database::begin`] saves a copy
+//! of every table and of the [`Catalog`] (schemas, views and attached
+//! databases), which [`Database::rollback`] restores and
+//! [`Database::commit`] discards. Inside a transaction,
+//! [`Database::savepoint`] saves another copy, so that
+//! [`Database::rollback_to`] can undo part of the transaction.
+//!
+//! A database can record the rows each of its tables inserts, updates and
+//! deletes (see [`Database::record_changes`]), for a write-ahead log or to
+//! make the same changes on another version of the database.
+//!
+//! Tables are shared copy-on-write between a database and its clones: a
+//! clone is cheap, and a table is copied the first time one side changes
+//! it. This makes `BEGIN` cheap too, and lets
+//! [`SharedDatabase`](crate::executor::shared::SharedDatabase) keep
+//! versions of the database for its readers.
+//!
+//! A database opened from a directory with [`Database::open`] has a
+//! [`Backing`] attached:
+//! [`Database::flush`] saves the committed tables to it,
+//! [`Database::close`] saves them and releases the directory, and so does
+//! dropping the database. Clones are not attached: only the database that
+//! was opened saves, so a stale copy never overwrites newer data.
+//!
+//! Table names may be qualified by a schema of the [`Catalog`] of the
+//! database (`sales.orders`), or by the alias of a database attached to it,
+//! whose tables [`Database::table`] reads too. `main.users` and `users`
+//! name the same table. Views share the names of tables.
 
+use std::borrow::Cow;
 use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+use crate::core::catalog::{Catalog, DEFAULT_SCHEMA};
 use crate::core::types::TableName;
 use crate::core::schema::Schema;
-use crate::core::table::Table;
+use crate::core::table::{RowChange, Table};
 use crate::core::error::{SqlError, SqlResult};
 
 /// Represents a database containing multiple tables.
 ///
 /// Ensures that table names are unique and provides methods
 /// to create and access tables.
+///
+/// `saved` holds the tables and catalog as they were at `BEGIN` while a
+/// transaction is open, and `savepoints` as they were at each savepoint of
+/// the transaction, oldest first. `backing` is where the database is
+/// saved, if it was opened from disk, and `catalog` holds the schemas,
+/// views and databases attached to it. `recording` tells whether the
+/// tables changed record their changes.
 #[derive(Debug)]
 pub struct Database {
-    tables: HashMap<TableName, Table>,
+    tables: HashMap<TableName, Arc<Table>>,
+    saved: Option<Snapshot>,
+    savepoints: Vec<(String, Snapshot)>,
+    backing: Option<Box<dyn Backing>>,
+    catalog: Catalog,
+    recording: bool,
+}
+
+/// The state a transaction or savepoint restores: the tables, shared with
+/// the database until changed, and the catalog.
+#[derive(Debug, Clone)]
+struct Snapshot {
+    tables: HashMap<TableName, Arc<Table>>,
+    catalog: Catalog,
+}
+
+/// Where a [`Database`] is kept between runs, such as the directory it was
+/// opened from.
+pub trait Backing: fmt::Debug + Send + Sync {
+    /// Saves `db`, which has no transaction open, in full.
+    fn save(&mut self, db: &Database) -> SqlResult<()>;
 }
 
 impl Database {
@@ -24,9 +84,71 @@ impl Database {
     pub fn new() -> Self {
         Database {
             tables: HashMap::new(),
+            saved: None,
+            savepoints: Vec::new(),
+            backing: None,
+            catalog: Catalog::new(),
+            recording: false,
         }
     }
 
+    /// Attaches `backing`, which [`flush`](Self::flush),
+    /// [`close`](Self::close) and dropping the database then save to,
+    /// replacing the backing attached before.
+    pub fn attach(&mut self, backing: Box<dyn Backing>) {
+        self.backing = Some(backing);
+    }
+
+    /// Detaches the backing, if any: the database is then only kept in
+    /// memory.
+    pub fn detach(&mut self) -> Option<Box<dyn Backing>> {
+        self.backing.take()
+    }
+
+    /// Returns `true` if a backing is attached.
+    pub fn is_attached(&self) -> bool {
+        self.backing.is_some()
+    }
+
+    /// Saves the database to its backing; does nothing without one.
+    ///
+    /// # Returns
+    /// `Ok(())`, or `Err(SqlError)` if a transaction is open or saving
+    /// fails.
+    pub fn flush(&mut self) -> SqlResult<()> {
+        let Some(mut backing) = self.backing.take() else { return Ok(()) };
+        let result = if self.in_transaction() {
+            Err(SqlError::new_execution("Cannot save the database during a transaction"))
+        } else {
+            backing.save(self)
+        };
+        self.backing = Some(backing);
+        result
+    }
+
+    /// Closes the database: rolls back an open transaction, saves the
+    /// database to its backing and releases the backing.
+    ///
+    /// Dropping the database does the same, but cannot report a failure.
+    ///
+    /// # Returns
+    /// `Ok(())`, or `Err(SqlError)` if saving fails.
+    pub fn close(mut self) -> SqlResult<()> {
+        self.save_on_close()
+    }
+
+    fn save_on_close(&mut self) -> SqlResult<()> {
+        if self.backing.is_none() {
+            return Ok(());
+        }
+        if self.in_transaction() {
+            self.rollback()?;
+        }
+        let result = self.flush();
+        self.backing = None;
+        result
+    }
+
     /// Creates a new table in the database.
     ///
     /// # Arguments
@@ -37,40 +159,324 @@ impl Database {
     /// `Ok(&Table)` if creation succeeds, otherwise `Err(SqlError)` if a table
     /// with the same name already exists.
     pub fn create_table(&mut self, name: TableName, schema: Schema) -> SqlResult<&Table> {
-        if self.tables.contains_key(&name) {
-            return Err(SqlError::new_core(&format!(
-                "Table with name '{}' already exists",
-                name.as_str()
-            )));
-        }
+        let name = self.local_name(&name)?.into_owned();
+        self.check_free(&name)?;
 
         let table = Table::create(name.clone(), schema);
-        self.tables.insert(name.clone(), table);
+        self.tables.insert(name.clone(), Arc::new(table));
         Ok(self.tables.get(&name).unwrap())
     }
 
-    /// Returns an immutable reference to a table by name.
+    /// Returns an immutable reference to a table by name: a table of this
+    /// database, or `alias.table` of a database attached to its catalog.
     pub fn table(&self, name: &TableName) -> Option<&Table> {
-        self.tables.get(name)
+        match self.local_name(name) {
+            Ok(local) => self.tables.get(&*local).map(|table| &**table),
+            Err(_) => self.catalog.table(name),
+        }
     }
 
-    /// Returns a mutable reference to a table by name.
+    /// Returns a mutable reference to a table by name, copying the table
+    /// first if a clone of the database still shares it.
     pub fn table_mut(&mut self, name: &TableName) -> Option<&mut Table> {
-        self.tables.get_mut(name)
+        let name = self.local_name(name).ok()?.into_owned();
+        let table = Arc::make_mut(self.tables.get_mut(&name)?);
+        table.record_changes(self.recording);
+        Some(table)
+    }
+
+    /// Starts or stops recording the rows inserted, updated and deleted in
+    /// the tables, which [`take_changes`](Self::take_changes) returns.
+    /// Changes to the tables themselves, such as `CREATE TABLE` or
+    /// `TRUNCATE`, are not recorded.
+    pub fn record_changes(&mut self, record: bool) {
+        self.recording = record;
+    }
+
+    /// Returns the row changes recorded since they were last taken, per
+    /// table in name order, and forgets them.
+    ///
+    /// Taking them after each statement keeps the changes of a statement
+    /// together, and out of the savepoints set after it.
+    pub fn take_changes(&mut self) -> Vec<(TableName, Vec<RowChange>)> {
+        let mut names: Vec<TableName> =
+            self.tables.iter().filter(|(_, table)| table.has_changes()).map(|(name, _)| name.clone()).collect();
+        names.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        names
+            .into_iter()
+            .map(|name| {
+                let changes = Arc::make_mut(self.tables.get_mut(&name).expect("listed table")).take_changes();
+                (name, changes)
+            })
+            .collect()
+    }
+
+    /// Renames the table `from` to `to`. A plain `to` keeps the table in
+    /// its schema; a qualified one moves it to that schema.
+    ///
+    /// Like any other change, a rename made in a transaction is undone by
+    /// [`Database::rollback`].
+    ///
+    /// # Returns
+    /// `Ok(())`, or `Err(SqlError)` if there is no table `from`, or `to` is
+    /// taken by a table or view or names a schema that does not exist.
+    pub fn rename_table(&mut self, from: &TableName, to: &TableName) -> SqlResult<()> {
+        let from = self.local_name(from)?.into_owned();
+        if !self.tables.contains_key(&from) {
+            return Err(SqlError::unknown_table(&from));
+        }
+        let to = match (from.schema(), to.schema()) {
+            (Some(schema), None) => TableName::qualified(schema, to.as_str()).map_err(|e| SqlError::new_semantic(&e))?,
+            _ => self.local_name(to)?.into_owned(),
+        };
+        if to == from {
+            return Ok(());
+        }
+        self.check_free(&to)?;
+        let mut table = self.tables.remove(&from).expect("checked above");
+        Arc::make_mut(&mut table).rename(to.clone());
+        self.tables.insert(to, table);
+        Ok(())
+    }
+
+    /// Removes every row of a table, keeping its schema and indexes (see
+    /// [`Table::emptied`]). A copy shared with a clone of the database, such
+    /// as the one saved by `BEGIN`, is left untouched, and is not copied.
+    ///
+    /// # Returns
+    /// The number of rows removed, or `None` if there is no such table.
+    pub fn truncate_table(&mut self, name: &TableName) -> Option<usize> {
+        let name = self.local_name(name).ok()?.into_owned();
+        let table = self.tables.get_mut(&name)?;
+        let removed = table.rows().len();
+        *table = Arc::new(table.emptied());
+        Some(removed)
+    }
+
+    /// Adds an existing table, such as one loaded from disk.
+    ///
+    /// # Returns
+    /// `Ok(())`, or `Err(SqlError)` if a table with the same name exists,
+    /// or its schema does not.
+    pub fn add_table(&mut self, table: Table) -> SqlResult<()> {
+        self.local_name(table.name())?;
+        self.check_free(table.name())?;
+        self.tables.insert(table.name().clone(), Arc::new(table));
+        Ok(())
+    }
+
+    /// Returns the names of all tables, sorted alphabetically.
+    pub fn table_names(&self) -> Vec<&TableName> {
+        let mut names: Vec<&TableName> = self.tables.keys().collect();
+        names.sort_by(|a, b| a.as_str().cmp(b.as_str()));
+        names
+    }
+
+    /// Creates the view `name`, standing for the query `sql`, which the
+    /// caller has checked.
+    ///
+    /// # Returns
+    /// `Ok(())`, or `Err(SqlError)` if a table or view with the same name
+    /// exists, or its schema does not.
+    pub fn create_view(&mut self, name: &TableName, sql: &str) -> SqlResult<()> {
+        let name = self.local_name(name)?.into_owned();
+        self.check_free(&name)?;
+        self.catalog.add_view(&name, sql);
+        Ok(())
+    }
+
+    /// Returns the query of the view `name`, if there is one.
+    pub fn view(&self, name: &TableName) -> Option<&str> {
+        self.catalog.view(&*self.local_name(name).ok()?)
+    }
+
+    /// Returns an error if the local name `name` is taken by a table or a
+    /// view.
+    fn check_free(&self, name: &TableName) -> SqlResult<()> {
+        if self.tables.contains_key(name) {
+            return Err(SqlError::new_semantic(&format!("Table with name '{}' already exists", name.as_str())));
+        }
+        if self.catalog.view(name).is_some() {
+            return Err(SqlError::new_semantic(&format!("View with name '{}' already exists", name.as_str())));
+        }
+        Ok(())
+    }
+
+    /// Returns the name under which the table or view `name` of this
+    /// database is stored: `name` itself, or the plain name for `main.table`.
+    ///
+    /// # Returns
+    /// The name, or `Err(SqlError)` if it is qualified by a schema that
+    /// was not created.
+    fn local_name<'a>(&self, name: &'a TableName) -> SqlResult<Cow<'a, TableName>> {
+        match name.schema() {
+            None => Ok(Cow::Borrowed(name)),
+            Some(schema) if schema.eq_ignore_ascii_case(DEFAULT_SCHEMA) => {
+                Ok(Cow::Owned(TableName::new(name.table()).expect("the table part of a name is valid")))
+            }
+            Some(schema) if self.catalog.has_schema(schema) => Ok(Cow::Borrowed(name)),
+            Some(schema) => Err(SqlError::new_semantic(&format!("Schema '{}' does not exist", schema))),
+        }
+    }
+
+    /// Returns the schemas of this database and the databases attached to
+    /// it.
+    pub fn catalog(&self) -> &Catalog {
+        &self.catalog
+    }
+
+    /// Returns the schemas of this database and the databases attached to
+    /// it, to add some.
+    pub fn catalog_mut(&mut self) -> &mut Catalog {
+        &mut self.catalog
     }
 
     /// Returns the number of tables in the database.
     pub fn table_count(&self) -> usize {
         self.tables.len()
     }
+
+    /// Starts a transaction, remembering the current state of every table
+    /// and of the catalog.
+    ///
+    /// # Returns
+    /// `Ok(())`, or `Err(SqlError)` if a transaction is already open.
+    pub fn begin(&mut self) -> SqlResult<()> {
+        if self.saved.is_some() {
+            return Err(SqlError::new_execution("A transaction is already in progress"));
+        }
+        self.saved = Some(self.snapshot());
+        Ok(())
+    }
+
+    /// Ends the open transaction, keeping its changes.
+    ///
+    /// # Returns
+    /// `Ok(())`, or `Err(SqlError)` if no transaction is open.
+    pub fn commit(&mut self) -> SqlResult<()> {
+        self.savepoints.clear();
+        match self.saved.take() {
+            Some(_) => Ok(()),
+            None => Err(SqlError::new_execution("No transaction in progress")),
+        }
+    }
+
+    /// Ends the open transaction, restoring every table (including created
+    /// ones) and the catalog to their state at [`Database::begin`].
+    ///
+    /// # Returns
+    /// `Ok(())`, or `Err(SqlError)` if no transaction is open.
+    pub fn rollback(&mut self) -> SqlResult<()> {
+        self.savepoints.clear();
+        match self.saved.take() {
+            Some(snapshot) => {
+                self.restore(snapshot);
+                Ok(())
+            }
+            None => Err(SqlError::new_execution("No transaction in progress")),
+        }
+    }
+
+    /// Returns whether a transaction is open.
+    pub fn in_transaction(&self) -> bool {
+        self.saved.is_some()
+    }
+
+    /// Marks the current state of the open transaction as savepoint
+    /// `name`. A savepoint with the same name hides the older one until it
+    /// is released.
+    ///
+    /// # Returns
+    /// `Ok(())`, or `Err(SqlError)` if no transaction is open.
+    pub fn savepoint(&mut self, name: &str) -> SqlResult<()> {
+        if self.saved.is_none() {
+            return Err(SqlError::new_execution("SAVEPOINT can only be used in a transaction"));
+        }
+        let snapshot = self.snapshot();
+        self.savepoints.push((name.to_string(), snapshot));
+        Ok(())
+    }
+
+    /// Restores every table and the catalog to their state at savepoint
+    /// `name`, forgetting the savepoints set after it. The savepoint itself
+    /// is kept, so that it can be rolled back to again.
+    ///
+    /// # Returns
+    /// `Ok(())`, or `Err(SqlError)` if there is no such savepoint.
+    pub fn rollback_to(&mut self, name: &str) -> SqlResult<()> {
+        let position = self.savepoint_position(name)?;
+        self.savepoints.truncate(position + 1);
+        let snapshot = self.savepoints[position].1.clone();
+        self.restore(snapshot);
+        Ok(())
+    }
+
+    /// Forgets savepoint `name` and the savepoints set after it, keeping
+    /// the changes made since.
+    ///
+    /// # Returns
+    /// `Ok(())`, or `Err(SqlError)` if there is no such savepoint.
+    pub fn release(&mut self, name: &str) -> SqlResult<()> {
+        let position = self.savepoint_position(name)?;
+        self.savepoints.truncate(position);
+        Ok(())
+    }
+
+    /// Returns the tables and catalog as they are now.
+    fn snapshot(&self) -> Snapshot {
+        Snapshot { tables: self.tables.clone(), catalog: self.catalog.clone() }
+    }
+
+    /// Puts the tables and catalog back as they were in `snapshot`.
+    fn restore(&mut self, snapshot: Snapshot) {
+        self.tables = snapshot.tables;
+        self.catalog = snapshot.catalog;
+    }
+
+    /// Returns the position of the latest savepoint named `name`, ignoring
+    /// case.
+    fn savepoint_position(&self, name: &str) -> SqlResult<usize> {
+        if self.saved.is_none() {
+            return Err(SqlError::new_execution("No transaction in progress"));
+        }
+        self.savepoints
+            .iter()
+            .rposition(|(saved, _)| saved.eq_ignore_ascii_case(name))
+            .ok_or_else(|| SqlError::new_semantic(&format!("Savepoint '{}' does not exist", name)))
+    }
+}
+
+impl Default for Database {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Clones share the tables, but not the backing (see the module docs).
+impl Clone for Database {
+    fn clone(&self) -> Self {
+        Database {
+            tables: self.tables.clone(),
+            saved: self.saved.clone(),
+            savepoints: self.savepoints.clone(),
+            backing: None,
+            catalog: self.catalog.clone(),
+            recording: self.recording,
+        }
+    }
+}
+
+impl Drop for Database {
+    fn drop(&mut self) {
+        let _ = self.save_on_close();
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::types::{TableName, ColumnName, DataType, Value};
+    use crate::core::types::{TableName, ColumnName, DataType};
     use crate::core::schema::{Column, Schema};
-    use crate::core::row::Row;
 
     #[test]
     fn create_table_success() {
@@ -114,4 +520,168 @@ mod tests {
         let table_mut_ref = db.table_mut(&table_name).unwrap();
         assert_eq!(table_mut_ref.name().as_str(), "users");
     }
+
+    #[test]
+    fn rollback_restores_tables_and_commit_keeps_them() {
+        let mut db = Database::new();
+        let schema = Schema::try_new(vec![Column::new(ColumnName::new("id").unwrap(), DataType::Int)]).unwrap();
+        let users = TableName::new("users").unwrap();
+        let orders = TableName::new("orders").unwrap();
+        db.create_table(users.clone(), schema.clone()).unwrap();
+
+        assert!(db.commit().is_err());
+        db.begin().unwrap();
+        assert!(db.in_transaction());
+        assert!(db.begin().is_err());
+        db.create_table(orders.clone(), schema.clone()).unwrap();
+        db.rollback().unwrap();
+        assert!(!db.in_transaction());
+        assert!(db.table(&orders).is_none());
+        assert!(db.rollback().is_err());
+
+        db.begin().unwrap();
+        db.create_table(orders.clone(), schema).unwrap();
+        db.commit().unwrap();
+        assert!(db.table(&orders).is_some());
+        assert_eq!(db.table_count(), 2);
+    }
+
+    #[test]
+    fn savepoints_undo_part_of_a_transaction() {
+        let mut db = Database::new();
+        let schema = Schema::try_new(vec![Column::new(ColumnName::new("id").unwrap(), DataType::Int)]).unwrap();
+        let create = |db: &mut Database, name: &str| db.create_table(TableName::new(name).unwrap(), schema.clone()).map(|_| ());
+        let names = |db: &Database| db.table_names().iter().map(|name| name.as_str().to_string()).collect::<Vec<_>>();
+
+        assert!(db.savepoint("a").is_err());
+        db.begin().unwrap();
+        create(&mut db, "t1").unwrap();
+        db.savepoint("a").unwrap();
+        create(&mut db, "t2").unwrap();
+        db.savepoint("b").unwrap();
+        create(&mut db, "t3").unwrap();
+
+        // Rolling back to a keeps it, and forgets b
+        db.rollback_to("A").unwrap();
+        assert_eq!(names(&db), ["t1"]);
+        assert!(db.rollback_to("b").is_err());
+        create(&mut db, "t4").unwrap();
+        db.rollback_to("a").unwrap();
+        assert_eq!(names(&db), ["t1"]);
+
+        // Releasing keeps the changes
+        create(&mut db, "t5").unwrap();
+        db.release("a").unwrap();
+        assert!(db.release("a").is_err());
+        assert_eq!(names(&db), ["t1", "t5"]);
+        assert!(db.in_transaction());
+
+        db.savepoint("c").unwrap();
+        db.rollback().unwrap();
+        assert_eq!(db.table_count(), 0);
+        assert!(matches!(db.rollback_to("c"), Err(SqlError::Execution { .. })));
+    }
+
+    #[test]
+    fn transactions_restore_the_catalog() {
+        let mut db = Database::new();
+        let view = TableName::new("v").unwrap();
+        db.catalog_mut().create_schema("kept").unwrap();
+
+        db.begin().unwrap();
+        db.catalog_mut().create_schema("sales").unwrap();
+        db.savepoint("a").unwrap();
+        db.create_view(&view, "SELECT 1").unwrap();
+        db.catalog_mut().attach("archive", Database::new()).unwrap();
+        db.rollback_to("a").unwrap();
+        assert!(db.view(&view).is_none());
+        assert!(db.catalog().database("archive").is_none());
+        assert_eq!(db.catalog().schemas(), ["kept", "sales"]);
+        db.rollback().unwrap();
+        assert_eq!(db.catalog().schemas(), ["kept"]);
+
+        db.begin().unwrap();
+        db.create_view(&view, "SELECT 1").unwrap();
+        db.commit().unwrap();
+        assert_eq!(db.view(&view), Some("SELECT 1"));
+    }
+
+    #[test]
+    fn clones_share_tables_until_changed() {
+        let mut db = Database::new();
+        let schema = Schema::try_new(vec![Column::new(ColumnName::new("id").unwrap(), DataType::Int)]).unwrap();
+        let users = TableName::new("users").unwrap();
+        db.create_table(users.clone(), schema.clone()).unwrap();
+
+        let copy = db.clone();
+        assert!(std::ptr::eq(db.table(&users).unwrap(), copy.table(&users).unwrap()));
+        let row = crate::core::row::Row::from_values(vec![crate::core::types::Value::Int(1)], &schema).unwrap();
+        db.table_mut(&users).unwrap().insert_checked(row).unwrap();
+        assert!(!std::ptr::eq(db.table(&users).unwrap(), copy.table(&users).unwrap()));
+        assert_eq!(db.table(&users).unwrap().rows().len(), 1);
+        assert!(copy.table(&users).unwrap().rows().is_empty());
+    }
+
+    #[test]
+    fn recorded_changes_are_taken_per_table() {
+        use crate::core::row::Row;
+        use crate::core::table::RowChange;
+        use crate::core::types::Value;
+        let mut db = Database::new();
+        let schema = Schema::try_new(vec![Column::new(ColumnName::new("id").unwrap(), DataType::Int)]).unwrap();
+        let row = |n: i64| Row::from_values(vec![Value::Int(n)], &schema).unwrap();
+        let (a, b) = (TableName::new("a").unwrap(), TableName::new("b").unwrap());
+        db.create_table(b.clone(), schema.clone()).unwrap();
+        db.create_table(a.clone(), schema.clone()).unwrap();
+
+        db.table_mut(&a).unwrap().insert_checked(row(1)).unwrap();
+        db.record_changes(true);
+        let copy = db.clone();
+        db.table_mut(&b).unwrap().insert_checked(row(2)).unwrap();
+        db.table_mut(&a).unwrap().delete_where(|_| Ok(true)).unwrap();
+        let changes = db.take_changes();
+        assert_eq!(changes, vec![(a.clone(), vec![RowChange::Delete(1)]), (b, vec![RowChange::Insert(1, vec![Value::Int(2)])])]);
+        assert!(db.take_changes().is_empty());
+        assert_eq!(copy.table(&a).unwrap().rows().len(), 1);
+
+        db.record_changes(false);
+        db.table_mut(&a).unwrap().insert_checked(row(3)).unwrap();
+        assert!(db.take_changes().is_empty());
+    }
+
+    /// Records the table count of each database it saves.
+    #[derive(Debug, Default)]
+    struct Recorder(Arc<std::sync::Mutex<Vec<usize>>>);
+
+    impl Backing for Recorder {
+        fn save(&mut self, db: &Database) -> SqlResult<()> {
+            self.0.lock().unwrap().push(db.table_count());
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn an_attached_database_saves_on_flush_close_and_drop() {
+        let schema = || Schema::try_new(vec![Column::new(ColumnName::new("id").unwrap(), DataType::Int)]).unwrap();
+        let saves = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let mut db = Database::new();
+        db.attach(Box::new(Recorder(Arc::clone(&saves))));
+        db.create_table(TableName::new("a").unwrap(), schema()).unwrap();
+        db.flush().unwrap();
+
+        // Clones and open transactions are never saved
+        drop(db.clone());
+        db.begin().unwrap();
+        db.create_table(TableName::new("b").unwrap(), schema()).unwrap();
+        assert!(db.flush().is_err());
+        drop(db);
+        assert_eq!(*saves.lock().unwrap(), vec![1, 1]);
+
+        let mut db = Database::new();
+        db.attach(Box::new(Recorder(Arc::clone(&saves))));
+        assert!(db.detach().is_some() && !db.is_attached());
+        db.attach(Box::new(Recorder(Arc::clone(&saves))));
+        db.close().unwrap();
+        assert_eq!(*saves.lock().unwrap(), vec![1, 1, 0]);
+    }
 }
@@ -2,48 +2,141 @@
 //!
 //! This module defines the core error types and conventions used throughout
 //! the database engine. It provides:
-//! - [`SqlError`]: represents errors in the SQL engine (currently only core errors).
+//! - [`SqlError`]: represents errors in the SQL engine, one variant per
+//!   kind of failure, each with a stable error code.
 //! - [`SqlResult<T>`]: a type alias for `Result<T, SqlError>` to standardize return types.
 //!
 //! # Design Goals
 //! - Uniform error reporting across modules (core, parser, executor, storage).
-//! - Easy propagation of errors using `?` operator.
-//! - Extensible for future error categories.
+//! - Easy propagation of errors using `?` operator, including from
+//!   [`std::io::Error`].
+//! - Errors say which stage failed, so callers can tell a typo from a
+//!   broken file.
+//! - Lexical and syntax errors keep the [`Span`] of the input they are
+//!   about, so the offending text can be shown.
+
+use crate::core::span::Span;
+use crate::core::types::TableName;
 
 /// Represents errors that can occur in the SQL engine.
 ///
-/// Currently contains a single variant `Core` for core subsystem errors.
-/// Can be extended in the future with parser, executor, or storage errors.
+/// Each variant is a kind of failure, with a stable [`code`](SqlError::code):
+///
+/// | Variant      | Code    | Raised when                                          |
+/// |--------------|---------|------------------------------------------------------|
+/// | `Lex`        | `E1001` | the input holds an invalid token                     |
+/// | `Parse`      | `E1002` | the tokens do not form a statement                   |
+/// | `Semantic`   | `E2001` | a statement names a missing or duplicate object, or is ill-typed |
+/// | `Constraint` | `E2002` | a `NOT NULL`, `UNIQUE` or `PRIMARY KEY` constraint would break |
+/// | `Execution`  | `E3001` | a valid statement fails while running                |
+/// | `Deadlock`   | `E3002` | transactions wait for each other's row locks; one is aborted |
+/// | `Conflict`   | `E3003` | a transaction changes a row another one changed since it began; it is aborted |
+/// | `Io`         | `E4001` | a file cannot be read or written, or is malformed    |
+/// | `Internal`   | `E5001` | the engine broke one of its own invariants           |
 ///
 /// # Example
 /// ```
 /// use mini_rust_sgbd::core::error::SqlError;
 ///
-/// let error = SqlError::Core { message: "Critical failure".to_string() };
-/// assert_eq!(error.message(), "Critical failure");
+/// let error = SqlError::Execution { message: "Division by zero".to_string() };
+/// assert_eq!(error.message(), "Division by zero");
+/// assert_eq!(error.code(), "E3001");
 /// ```
 #[derive(Debug, PartialEq)]
 pub enum SqlError {
-    /// Core-level error with a descriptive message.
-    Core { message: String },
+    /// The lexer met an invalid token.
+    Lex { message: String, span: Option<Span> },
+    /// The parser met an unexpected token.
+    Parse { message: String, span: Option<Span> },
+    /// A statement refers to a missing or duplicate table, column or
+    /// index, or mixes incompatible types.
+    Semantic { message: String },
+    /// A column constraint rejected a value.
+    Constraint { message: String },
+    /// A statement failed while running, e.g. on a division by zero.
+    Execution { message: String },
+    /// A transaction would have waited for a row lock forever, and was
+    /// rolled back.
+    Deadlock { message: String },
+    /// A transaction changed a row that another transaction committed a
+    /// change to after it began, and was rolled back.
+    Conflict { message: String },
+    /// Reading or writing a file failed, or a file is malformed.
+    Io { message: String },
+    /// The engine was used in a way it does not support, such as tokens
+    /// without their end-of-input sentinel: a bug, not a user error.
+    Internal { message: String },
 }
 
 impl SqlError {
-    /// Creates a new core error with a specific message.
+    /// Creates a lexical error.
+    ///
+    /// # Example
+    /// ```
+    /// use mini_rust_sgbd::core::error::SqlError;
+    /// let err = SqlError::new_lex("Unterminated string");
+    /// assert!(matches!(err, SqlError::Lex { .. }));
+    /// ```
+    pub fn new_lex(message: &str) -> Self {
+        SqlError::Lex { message: message.to_string(), span: None }
+    }
+
+    /// Creates a syntax error.
+    pub fn new_parse(message: &str) -> Self {
+        SqlError::Parse { message: message.to_string(), span: None }
+    }
+
+    /// Creates an error for a statement that is well-formed but invalid
+    /// against the database, such as one naming an unknown column.
+    pub fn new_semantic(message: &str) -> Self {
+        SqlError::Semantic { message: message.to_string() }
+    }
+
+    /// Creates a constraint violation error.
     ///
     /// # Arguments
-    /// * `message` - Description of the error.
+    /// * `message` - Description of the violated constraint.
     ///
     /// # Example
     /// ```
     /// use mini_rust_sgbd::core::error::SqlError;
-    /// let err = SqlError::new_core("critical failure");
-    /// assert_eq!(err.message(), "critical failure");
+    /// let err = SqlError::new_constraint("duplicate value");
+    /// assert!(matches!(err, SqlError::Constraint { .. }));
     /// ```
-    pub fn new_core(message: &str) -> Self {
-        SqlError::Core {
-            message: message.to_string(),
-        }
+    pub fn new_constraint(message: &str) -> Self {
+        SqlError::Constraint { message: message.to_string() }
+    }
+
+    /// Creates an error raised while running a statement.
+    pub fn new_execution(message: &str) -> Self {
+        SqlError::Execution { message: message.to_string() }
+    }
+
+    /// Creates the error aborting a transaction caught in a deadlock.
+    pub fn new_deadlock(message: &str) -> Self {
+        SqlError::Deadlock { message: message.to_string() }
+    }
+
+    /// Creates the error aborting a transaction whose change conflicts
+    /// with one committed after it began.
+    pub fn new_conflict(message: &str) -> Self {
+        SqlError::Conflict { message: message.to_string() }
+    }
+
+    /// Creates a file or storage error.
+    pub fn new_io(message: &str) -> Self {
+        SqlError::Io { message: message.to_string() }
+    }
+
+    /// Creates the error for a broken invariant of the engine.
+    pub fn new_internal(message: &str) -> Self {
+        SqlError::Internal { message: message.to_string() }
+    }
+
+    /// Creates the error returned when a statement references a missing
+    /// table.
+    pub fn unknown_table(name: &TableName) -> Self {
+        SqlError::new_semantic(&format!("Unknown table '{}'", name.as_str()))
     }
 
     /// Returns the error message.
@@ -54,14 +147,94 @@ impl SqlError {
     /// # Example
     /// ```
     /// use mini_rust_sgbd::core::error::SqlError;
-    /// let err = SqlError::new_core("test error");
+    /// let err = SqlError::new_parse("test error");
     /// assert_eq!(err.message(), "test error");
     /// ```
     pub fn message(&self) -> &str {
         match self {
-            SqlError::Core { message } => message,
+            SqlError::Lex { message, .. }
+            | SqlError::Parse { message, .. }
+            | SqlError::Semantic { message }
+            | SqlError::Constraint { message }
+            | SqlError::Execution { message }
+            | SqlError::Deadlock { message }
+            | SqlError::Conflict { message }
+            | SqlError::Io { message }
+            | SqlError::Internal { message } => message,
         }
     }
+
+    /// Attaches the span of the input the error is about. Only lexical and
+    /// syntax errors keep it; other errors are returned unchanged.
+    ///
+    /// # Example
+    /// ```
+    /// use mini_rust_sgbd::core::error::SqlError;
+    /// use mini_rust_sgbd::core::span::Span;
+    ///
+    /// let err = SqlError::new_parse("Expected FROM").with_span(Span::new(9, 13));
+    /// assert_eq!(err.span(), Some(Span::new(9, 13)));
+    /// ```
+    pub fn with_span(self, span: Span) -> Self {
+        match self {
+            SqlError::Lex { message, .. } => SqlError::Lex { message, span: Some(span) },
+            SqlError::Parse { message, .. } => SqlError::Parse { message, span: Some(span) },
+            other => other,
+        }
+    }
+
+    /// Returns the span of the input the error is about, if known.
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            SqlError::Lex { span, .. } | SqlError::Parse { span, .. } => *span,
+            _ => None,
+        }
+    }
+
+    /// Returns the stable code of the kind of error, e.g. `E1002` for a
+    /// syntax error.
+    pub fn code(&self) -> &'static str {
+        match self {
+            SqlError::Lex { .. } => "E1001",
+            SqlError::Parse { .. } => "E1002",
+            SqlError::Semantic { .. } => "E2001",
+            SqlError::Constraint { .. } => "E2002",
+            SqlError::Execution { .. } => "E3001",
+            SqlError::Deadlock { .. } => "E3002",
+            SqlError::Conflict { .. } => "E3003",
+            SqlError::Io { .. } => "E4001",
+            SqlError::Internal { .. } => "E5001",
+        }
+    }
+
+    /// Returns a short name of the kind of error, e.g. `Parse`.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            SqlError::Lex { .. } => "Lex",
+            SqlError::Parse { .. } => "Parse",
+            SqlError::Semantic { .. } => "Semantic",
+            SqlError::Constraint { .. } => "Constraint",
+            SqlError::Execution { .. } => "Execution",
+            SqlError::Deadlock { .. } => "Deadlock",
+            SqlError::Conflict { .. } => "Conflict",
+            SqlError::Io { .. } => "I/O",
+            SqlError::Internal { .. } => "Internal",
+        }
+    }
+}
+
+impl std::fmt::Display for SqlError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message())
+    }
+}
+
+impl std::error::Error for SqlError {}
+
+impl From<std::io::Error> for SqlError {
+    fn from(e: std::io::Error) -> Self {
+        SqlError::new_io(&e.to_string())
+    }
 }
 
 /// Type alias for results in the SQL engine.
@@ -73,7 +246,7 @@ impl SqlError {
 /// use mini_rust_sgbd::core::error::{SqlError, SqlResult};
 ///
 /// fn operation() -> SqlResult<i32> {
-///     Err(SqlError::new_core("operation failed"))
+///     Err(SqlError::new_execution("operation failed"))
 /// }
 ///
 /// let result = operation();
@@ -86,19 +259,46 @@ mod tests {
     use super::*;
 
     #[test]
-    fn test_new_core_stores_message() {
-        let error = SqlError::new_core("core subsystem crashed");
-        assert_eq!(error.message(), "core subsystem crashed");
+    fn test_constructors_store_message_and_kind() {
+        let errors = [
+            SqlError::new_lex("m"),
+            SqlError::new_parse("m"),
+            SqlError::new_semantic("m"),
+            SqlError::new_constraint("m"),
+            SqlError::new_execution("m"),
+            SqlError::new_deadlock("m"),
+            SqlError::new_conflict("m"),
+            SqlError::new_io("m"),
+            SqlError::new_internal("m"),
+        ];
+        let codes: Vec<&str> = errors.iter().map(SqlError::code).collect();
+        assert_eq!(codes, ["E1001", "E1002", "E2001", "E2002", "E3001", "E3002", "E3003", "E4001", "E5001"]);
+        assert!(errors.iter().all(|e| e.message() == "m"));
+        assert_eq!(errors[1].kind(), "Parse");
+    }
+
+    #[test]
+    fn test_span_is_kept_by_lex_and_parse_errors_only() {
+        let span = Span::new(2, 5);
+        assert_eq!(SqlError::new_lex("m").span(), None);
+        assert_eq!(SqlError::new_lex("m").with_span(span).span(), Some(span));
+        assert_eq!(SqlError::new_parse("m").with_span(span).span(), Some(span));
+        assert_eq!(SqlError::new_execution("m").with_span(span).span(), None);
+    }
+
+    #[test]
+    fn test_display_shows_message() {
+        assert_eq!(SqlError::new_parse("bad input").to_string(), "bad input");
     }
 
     #[test]
-    fn test_core_error_equality() {
-        let e1 = SqlError::new_core("same message");
-        let e2 = SqlError::new_core("same message");
-        assert_eq!(e1, e2);
+    fn test_error_equality() {
+        assert_eq!(SqlError::new_semantic("same message"), SqlError::new_semantic("same message"));
+        assert_ne!(SqlError::new_semantic("same message"), SqlError::new_execution("same message"));
     }
 
     #[test]
+    #[allow(clippy::unnecessary_literal_unwrap)]
     fn test_sqlresult_ok() {
         let result: SqlResult<i32> = Ok(42);
         assert!(result.is_ok());
@@ -106,15 +306,16 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::unnecessary_literal_unwrap)]
     fn test_sqlresult_err() {
-        let result: SqlResult<i32> = Err(SqlError::new_core("failure"));
+        let result: SqlResult<i32> = Err(SqlError::new_execution("failure"));
         assert!(result.is_err());
         assert_eq!(result.unwrap_err().message(), "failure");
     }
 
     /// Helper function to test propagation with `?` operator.
     fn failable_function() -> SqlResult<i32> {
-        Err(SqlError::new_core("propagation test"))
+        Err(SqlError::new_execution("propagation test"))
     }
 
     #[test]
@@ -128,4 +329,12 @@ mod tests {
         assert!(result.is_err());
         assert_eq!(result.unwrap_err().message(), "propagation test");
     }
+
+    #[test]
+    fn test_io_errors_convert() {
+        fn open() -> SqlResult<std::fs::File> {
+            Ok(std::fs::File::open("/nonexistent/mini_rust_sgbd")?)
+        }
+        assert!(matches!(open(), Err(SqlError::Io { .. })));
+    }
 }
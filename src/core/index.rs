@@ -0,0 +1,260 @@
+//! Secondary indexes over one column of a table.
+//!
+//! An [`Index`] maps each non-`NULL` value of its column to the positions of
+//! the rows holding it, so lookups do not need to scan the table. An
+//! ordered index keeps values sorted and serves equality and range lookups;
+//! a hash index only serves equality, at a lower cost per lookup (see
+//! [`IndexKind`]). The [`Table`](crate::core::table::Table) owning the index
+//! keeps it in sync with its rows.
+
+use std::cmp::Ordering;
+use std::collections::{BTreeMap, HashMap};
+use std::ops::Bound;
+use std::str::FromStr;
+
+use crate::core::row::Row;
+use crate::core::types::Value;
+
+/// A value used as an index key.
+///
+/// All keys of an index come from one column, so they share a type and
+/// [`Value::compare`] orders them totally.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct IndexKey(Value);
+
+impl Ord for IndexKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.compare(&other.0).unwrap_or(Ordering::Equal)
+    }
+}
+
+impl PartialOrd for IndexKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// The structure of an index, chosen with `CREATE INDEX ... USING`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum IndexKind {
+    /// A B-tree: equality and range lookups (`USING BTREE`, the default).
+    #[default]
+    BTree,
+    /// A hash table: equality lookups only (`USING HASH`).
+    Hash,
+}
+
+impl IndexKind {
+    /// Returns the name used in SQL and in the catalog.
+    pub fn name(&self) -> &'static str {
+        match self {
+            IndexKind::BTree => "btree",
+            IndexKind::Hash => "hash",
+        }
+    }
+}
+
+impl FromStr for IndexKind {
+    type Err = String;
+
+    /// Parses an index kind name, ignoring case.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "btree" => Ok(IndexKind::BTree),
+            "hash" => Ok(IndexKind::Hash),
+            _ => Err(format!("Unknown index type '{}'", s)),
+        }
+    }
+}
+
+/// Row positions per value, each list in increasing order.
+#[derive(Debug, Clone)]
+enum Entries {
+    Ordered(BTreeMap<IndexKey, Vec<usize>>),
+    Hashed(HashMap<Value, Vec<usize>>),
+}
+
+/// An index on one column.
+///
+/// # Fields
+/// - `name`: the index name, unique within a database
+/// - `column`: position of the indexed column in the table schema
+/// - `entries`: row positions per value, stored according to the kind
+#[derive(Debug, Clone)]
+pub struct Index {
+    name: String,
+    column: usize,
+    entries: Entries,
+}
+
+impl Index {
+    /// Builds an index of kind `kind` on column `column` of `rows`.
+    pub fn build(name: &str, column: usize, kind: IndexKind, rows: &[Row]) -> Self {
+        let entries = match kind {
+            IndexKind::BTree => Entries::Ordered(BTreeMap::new()),
+            IndexKind::Hash => Entries::Hashed(HashMap::new()),
+        };
+        let mut index = Index { name: name.to_string(), column, entries };
+        for (pos, row) in rows.iter().enumerate() {
+            index.insert(&row.values()[column], pos);
+        }
+        index
+    }
+
+    /// Returns the index name.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the position of the indexed column.
+    pub fn column(&self) -> usize {
+        self.column
+    }
+
+    /// Returns the kind of the index.
+    pub fn kind(&self) -> IndexKind {
+        match self.entries {
+            Entries::Ordered(_) => IndexKind::BTree,
+            Entries::Hashed(_) => IndexKind::Hash,
+        }
+    }
+
+    /// Returns the number of distinct indexed values.
+    pub fn len(&self) -> usize {
+        match &self.entries {
+            Entries::Ordered(map) => map.len(),
+            Entries::Hashed(map) => map.len(),
+        }
+    }
+
+    /// Returns `true` if no value is indexed.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Records that the row at `pos` holds `value`. `NULL` is not indexed.
+    pub fn insert(&mut self, value: &Value, pos: usize) {
+        if value.is_null() {
+            return;
+        }
+        let positions = match &mut self.entries {
+            Entries::Ordered(map) => map.entry(IndexKey(value.clone())).or_default(),
+            Entries::Hashed(map) => map.entry(value.clone()).or_default(),
+        };
+        let at = positions.partition_point(|&p| p < pos);
+        positions.insert(at, pos);
+    }
+
+    /// Forgets that the row at `pos` holds `value`.
+    pub fn remove(&mut self, value: &Value, pos: usize) {
+        match &mut self.entries {
+            Entries::Ordered(map) => {
+                let key = IndexKey(value.clone());
+                if let Some(positions) = map.get_mut(&key) {
+                    positions.retain(|&p| p != pos);
+                    if positions.is_empty() {
+                        map.remove(&key);
+                    }
+                }
+            }
+            Entries::Hashed(map) => {
+                if let Some(positions) = map.get_mut(value) {
+                    positions.retain(|&p| p != pos);
+                    if positions.is_empty() {
+                        map.remove(value);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the positions of the rows holding `value`, in increasing order.
+    /// `NULL` matches no row.
+    pub fn lookup(&self, value: &Value) -> &[usize] {
+        if value.is_null() {
+            return &[];
+        }
+        let positions = match &self.entries {
+            Entries::Ordered(map) => map.get(&IndexKey(value.clone())),
+            Entries::Hashed(map) => map.get(value),
+        };
+        positions.map_or(&[], Vec::as_slice)
+    }
+
+    /// Returns the positions of the rows whose value lies between `low` and
+    /// `high`, in increasing order.
+    ///
+    /// A hash index has no order and checks every indexed value.
+    pub fn range(&self, low: Bound<&Value>, high: Bound<&Value>) -> Vec<usize> {
+        let map = match &self.entries {
+            Entries::Ordered(map) => map,
+            Entries::Hashed(map) => {
+                let above = |v: &Value| match low {
+                    Bound::Included(l) => v.compare(l) != Some(Ordering::Less),
+                    Bound::Excluded(l) => v.compare(l) == Some(Ordering::Greater),
+                    Bound::Unbounded => true,
+                };
+                let below = |v: &Value| match high {
+                    Bound::Included(h) => v.compare(h) != Some(Ordering::Greater),
+                    Bound::Excluded(h) => v.compare(h) == Some(Ordering::Less),
+                    Bound::Unbounded => true,
+                };
+                let mut positions: Vec<usize> = map
+                    .iter()
+                    .filter(|(v, _)| above(v) && below(v))
+                    .flat_map(|(_, p)| p.iter().copied())
+                    .collect();
+                positions.sort_unstable();
+                return positions;
+            }
+        };
+        let key = |bound: Bound<&Value>| bound.map(|v| IndexKey(v.clone()));
+        let (low, high) = (key(low), key(high));
+        // BTreeMap::range panics on an empty or inverted range
+        let empty = match (&low, &high) {
+            (Bound::Included(l), Bound::Included(h)) => l > h,
+            (Bound::Included(l) | Bound::Excluded(l), Bound::Included(h) | Bound::Excluded(h)) => l >= h,
+            _ => false,
+        };
+        if empty {
+            return Vec::new();
+        }
+        let mut positions: Vec<usize> = map.range((low, high)).flat_map(|(_, p)| p.iter().copied()).collect();
+        positions.sort_unstable();
+        positions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::schema::{Column, Schema};
+    use crate::core::types::{ColumnName, DataType};
+
+    #[test]
+    fn lookup_and_range() {
+        let schema = Schema::try_new(vec![Column::new(ColumnName::new("n").unwrap(), DataType::Int)]).unwrap();
+        let rows: Vec<Row> = [Value::Int(5), Value::Int(1), Value::Null, Value::Int(5), Value::Int(3)]
+            .into_iter()
+            .map(|v| Row::from_values(vec![v], &schema).unwrap())
+            .collect();
+        let mut index = Index::build("idx", 0, IndexKind::BTree, &rows);
+
+        assert_eq!(index.len(), 3);
+        assert_eq!(index.lookup(&Value::Int(5)), &[0, 3]);
+        assert!(index.lookup(&Value::Null).is_empty());
+        assert_eq!(index.range(Bound::Included(&Value::Int(2)), Bound::Unbounded), vec![0, 3, 4]);
+        assert_eq!(index.range(Bound::Excluded(&Value::Int(1)), Bound::Excluded(&Value::Int(5))), vec![4]);
+        assert!(index.range(Bound::Excluded(&Value::Int(3)), Bound::Excluded(&Value::Int(3))).is_empty());
+
+        index.remove(&Value::Int(5), 0);
+        index.insert(&Value::Int(5), 1);
+        assert_eq!(index.lookup(&Value::Int(5)), &[1, 3]);
+
+        let hash = Index::build("idx", 0, IndexKind::Hash, &rows);
+        assert_eq!(hash.kind(), IndexKind::Hash);
+        assert_eq!(hash.lookup(&Value::Int(5)), &[0, 3]);
+        assert!(hash.lookup(&Value::Null).is_empty());
+        assert_eq!(hash.range(Bound::Included(&Value::Int(2)), Bound::Unbounded), vec![0, 3, 4]);
+    }
+}
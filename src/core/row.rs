@@ -23,13 +23,17 @@ impl Row {
     /// # Validation
     /// - Number of values must equal number of columns.
     /// - Each value's type must match the corresponding column's type.
+    /// - `NULL` is only allowed in nullable columns.
+    ///
+    /// `Int` values destined for `Float` columns are widened first
+    /// (see [`DataType::coerce`](crate::core::types::DataType::coerce)).
     ///
     /// # Arguments
     /// - `values`: vector of [`Value`]s
     /// - `schema`: reference to the corresponding [`Schema`]
     ///
     /// # Returns
-    /// `Ok(Row)` if valid, otherwise `Err(SqlError::Core)` describing the problem.
+    /// `Ok(Row)` if valid, otherwise `Err(SqlError::Execution)` describing the problem.
     ///
     /// # Example
     /// ```
@@ -45,22 +49,21 @@ impl Row {
     /// ```
     pub fn from_values(values: Vec<Value>, schema: &Schema) -> Result<Self, SqlError> {
         if values.len() != schema.columns().len() {
-            return Err(SqlError::new_core(&format!(
+            return Err(SqlError::new_execution(&format!(
                 "Row has {} values but schema has {} columns",
                 values.len(),
                 schema.columns().len()
             )));
         }
 
+        let values: Vec<Value> = values
+            .into_iter()
+            .zip(schema.columns())
+            .map(|(value, column)| column.dtype.coerce(value))
+            .collect();
+
         for (i, (value, column)) in values.iter().zip(schema.columns()).enumerate() {
-            if !column.dtype.matches(value) {
-                return Err(SqlError::new_core(&format!(
-                    "Type mismatch at column {}: expected {:?}, got {:?}",
-                    i,
-                    column.dtype,
-                    value
-                )));
-            }
+            column.check(i, value)?;
         }
 
         Ok(Row { values })
@@ -98,7 +101,7 @@ mod tests {
 
         let values = vec![Value::Int(1)];
         let result = Row::from_values(values, &schema);
-        assert!(matches!(result, Err(SqlError::Core(_))));
+        assert!(matches!(result, Err(SqlError::Execution { .. })));
     }
 
     #[test]
@@ -109,6 +112,26 @@ mod tests {
 
         let values = vec![Value::Text("1".to_string()), Value::Text("Alice".to_string())];
         let result = Row::from_values(values, &schema);
-        assert!(matches!(result, Err(SqlError::Core(_))));
+        assert!(matches!(result, Err(SqlError::Execution { .. })));
+    }
+
+    #[test]
+    fn row_widens_int_into_float_column() {
+        let col = Column::new(ColumnName::new("price").unwrap(), DataType::Float);
+        let schema = Schema::try_new(vec![col]).unwrap();
+
+        let row = Row::from_values(vec![Value::Int(3)], &schema).unwrap();
+        assert_eq!(row.values(), &vec![Value::Float(3.0)]);
+    }
+
+    #[test]
+    fn row_null_honors_nullability() {
+        let col1 = Column::new(ColumnName::new("id").unwrap(), DataType::Int).with_nullable(false);
+        let col2 = Column::new(ColumnName::new("name").unwrap(), DataType::Text);
+        let schema = Schema::try_new(vec![col1, col2]).unwrap();
+
+        assert!(Row::from_values(vec![Value::Int(1), Value::Null], &schema).is_ok());
+        let result = Row::from_values(vec![Value::Null, Value::Null], &schema);
+        assert!(matches!(result, Err(SqlError::Constraint { .. })));
     }
 }
@@ -7,7 +7,8 @@
 //! - **Column**: couples a [`ColumnName`] with a [`DataType`], representing
 //!   a single column in a table schema.
 //! - **Schema**: holds an ordered list of columns and a fast lookup map
-//!   (`index_by_name`) to retrieve the index of a column by its name.
+//!   (`index_by_name`) to retrieve the index of a column by its name, plus
+//!   the optional primary key column.
 //!
 //! # Usage
 //! ```
@@ -23,7 +24,7 @@
 //! ```
 
 use std::collections::HashMap;
-use crate::core::types::{ColumnName, DataType};
+use crate::core::types::{ColumnName, DataType, Value};
 use crate::core::error::SqlError;
 
 /// Represents a single column in a table schema.
@@ -31,14 +32,18 @@ use crate::core::error::SqlError;
 /// Each `Column` consists of:
 /// - `name`: a [`ColumnName`] for type-safe identification
 /// - `dtype`: a [`DataType`] specifying allowed values
+/// - `nullable`: whether the column accepts [`Value::Null`] (`NOT NULL` clears it)
+/// - `unique`: whether two rows may not share a non-`NULL` value (`UNIQUE`)
 #[derive(Debug, Clone, PartialEq)]
 pub struct Column {
     pub name: ColumnName,
     pub dtype: DataType,
+    pub nullable: bool,
+    pub unique: bool,
 }
 
 impl Column {
-    /// Constructs a new `Column` from a name and data type.
+    /// Constructs a new nullable, non-unique `Column` from a name and data type.
     ///
     /// # Arguments
     /// - `name`: type-safe column name
@@ -47,7 +52,68 @@ impl Column {
     /// # Returns
     /// A `Column` instance.
     pub fn new(name: ColumnName, dtype: DataType) -> Self {
-        Column { name, dtype }
+        Column { name, dtype, nullable: true, unique: false }
+    }
+
+    /// Returns the column with the given nullability.
+    ///
+    /// # Example
+    /// ```
+    /// use mini_rust_sgbd::core::types::{ColumnName, DataType, Value};
+    /// use mini_rust_sgbd::core::schema::Column;
+    ///
+    /// let col = Column::new(ColumnName::new("id").unwrap(), DataType::Int).with_nullable(false);
+    /// assert!(!col.accepts(&Value::Null));
+    /// ```
+    pub fn with_nullable(mut self, nullable: bool) -> Self {
+        self.nullable = nullable;
+        self
+    }
+
+    /// Returns the column with the given uniqueness.
+    ///
+    /// Uniqueness is enforced by the [`Table`](crate::core::table::Table)
+    /// holding the rows; any number of rows may hold `NULL`.
+    pub fn with_unique(mut self, unique: bool) -> Self {
+        self.unique = unique;
+        self
+    }
+
+    /// Checks whether a value can be stored in this column.
+    ///
+    /// `NULL` is accepted only by nullable columns; any other value must
+    /// match the column's [`DataType`].
+    pub fn accepts(&self, value: &Value) -> bool {
+        match value {
+            Value::Null => self.nullable,
+            _ => self.dtype.matches(value),
+        }
+    }
+
+    /// Validates a value for this column, describing the problem on failure.
+    ///
+    /// A `NULL` in a `NOT NULL` column is reported as a
+    /// [`SqlError::Constraint`], a wrong type as an execution error.
+    ///
+    /// # Arguments
+    /// - `index`: position of the column, used in the error message
+    /// - `value`: the value to check
+    pub fn check(&self, index: usize, value: &Value) -> Result<(), SqlError> {
+        if self.accepts(value) {
+            return Ok(());
+        }
+        if value.is_null() {
+            return Err(SqlError::new_constraint(&format!(
+                "NOT NULL constraint violated: column '{}' does not accept NULL",
+                self.name.as_str()
+            )));
+        }
+        Err(SqlError::new_execution(&format!(
+            "Type mismatch at column {}: expected {:?}, got {:?}",
+            index,
+            self.dtype,
+            value
+        )))
     }
 }
 
@@ -56,10 +122,12 @@ impl Column {
 /// The `Schema` maintains:
 /// - `columns`: ordered list of columns for row validation
 /// - `index_by_name`: map for O(1) lookup of column positions by name
+/// - `primary_key`: position of the primary key column, if any
 #[derive(Debug, Clone)]
 pub struct Schema {
     columns: Vec<Column>,
     index_by_name: HashMap<ColumnName, usize>,
+    primary_key: Option<usize>,
 }
 
 impl Schema {
@@ -73,13 +141,13 @@ impl Schema {
     /// - `columns`: ordered vector of [`Column`]s
     ///
     /// # Returns
-    /// `Ok(Schema)` if valid, otherwise `Err(SqlError::Core)` for duplicates.
+    /// `Ok(Schema)` if valid, otherwise `Err(SqlError::Semantic)` for duplicates.
     pub fn try_new(columns: Vec<Column>) -> Result<Self, SqlError> {
         let mut index_by_name = HashMap::new();
 
         for (i, column) in columns.iter().enumerate() {
             if index_by_name.contains_key(&column.name) {
-                return Err(SqlError::new_core(&format!(
+                return Err(SqlError::new_semantic(&format!(
                     "Duplicate column name: {}",
                     column.name.as_str()
                 )));
@@ -87,7 +155,51 @@ impl Schema {
             index_by_name.insert(column.name.clone(), i);
         }
 
-        Ok(Schema { columns, index_by_name })
+        Ok(Schema { columns, index_by_name, primary_key: None })
+    }
+
+    /// Makes `name` the primary key column of the schema.
+    ///
+    /// The key column becomes `NOT NULL`; uniqueness of its values is
+    /// enforced by the [`Table`](crate::core::table::Table) holding the rows.
+    ///
+    /// # Returns
+    /// The updated `Schema`, or `Err(SqlError::Semantic)` if the column does not
+    /// exist.
+    ///
+    /// # Example
+    /// ```
+    /// use mini_rust_sgbd::core::types::{ColumnName, DataType};
+    /// use mini_rust_sgbd::core::schema::{Column, Schema};
+    ///
+    /// let id = ColumnName::new("id").unwrap();
+    /// let schema = Schema::try_new(vec![Column::new(id.clone(), DataType::Int)])
+    ///     .unwrap()
+    ///     .with_primary_key(&id)
+    ///     .unwrap();
+    /// assert_eq!(schema.primary_key(), Some(0));
+    /// assert!(!schema.columns()[0].nullable);
+    /// ```
+    pub fn with_primary_key(mut self, name: &ColumnName) -> Result<Self, SqlError> {
+        let idx = self.index_of(name).ok_or_else(|| {
+            SqlError::new_semantic(&format!("Unknown primary key column '{}'", name.as_str()))
+        })?;
+        self.columns[idx].nullable = false;
+        self.primary_key = Some(idx);
+        Ok(self)
+    }
+
+    /// Returns the index of the primary key column, if the schema has one.
+    pub fn primary_key(&self) -> Option<usize> {
+        self.primary_key
+    }
+
+    /// Returns the indexes of the columns whose values must be distinct:
+    /// the `UNIQUE` columns and the primary key.
+    pub fn unique_columns(&self) -> Vec<usize> {
+        (0..self.columns.len())
+            .filter(|&i| self.columns[i].unique || self.primary_key == Some(i))
+            .collect()
     }
 
     /// Returns the index of a column by name.
@@ -110,7 +222,7 @@ impl Schema {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::core::types::{ColumnName, DataType};
+    use crate::core::types::{ColumnName, DataType, Value};
     use crate::core::error::SqlError;
 
     /// Test creation of a valid schema and index lookup
@@ -132,7 +244,46 @@ mod tests {
         let col2 = Column::new(ColumnName::new("id").unwrap(), DataType::Text);
         let result = Schema::try_new(vec![col1, col2]);
 
-        assert!(matches!(result, Err(SqlError::Core(_))));
+        assert!(matches!(result, Err(SqlError::Semantic { .. })));
+    }
+
+    /// Test nullability in column value checks
+    #[test]
+    fn column_accepts_null_only_when_nullable() {
+        let nullable = Column::new(ColumnName::new("a").unwrap(), DataType::Int);
+        let not_null = nullable.clone().with_nullable(false);
+
+        assert!(nullable.accepts(&Value::Null));
+        assert!(nullable.accepts(&Value::Int(1)));
+        assert!(!nullable.accepts(&Value::Text("x".to_string())));
+        assert!(!not_null.accepts(&Value::Null));
+        assert!(not_null.check(0, &Value::Null).unwrap_err().message().contains("NULL"));
+        assert!(matches!(not_null.check(0, &Value::Null), Err(SqlError::Constraint { .. })));
+    }
+
+    /// Test that unique columns and the primary key are reported together
+    #[test]
+    fn unique_columns_include_primary_key() {
+        let id = ColumnName::new("id").unwrap();
+        let schema = Schema::try_new(vec![
+            Column::new(id.clone(), DataType::Int),
+            Column::new(ColumnName::new("name").unwrap(), DataType::Text),
+            Column::new(ColumnName::new("email").unwrap(), DataType::Text).with_unique(true),
+        ])
+        .unwrap();
+
+        assert_eq!(schema.unique_columns(), vec![2]);
+        assert_eq!(schema.with_primary_key(&id).unwrap().unique_columns(), vec![0, 2]);
+    }
+
+    /// Test that the primary key must name an existing column
+    #[test]
+    fn primary_key_must_exist() {
+        let col1 = Column::new(ColumnName::new("id").unwrap(), DataType::Int);
+        let schema = Schema::try_new(vec![col1]).unwrap();
+
+        assert_eq!(schema.primary_key(), None);
+        assert!(schema.with_primary_key(&ColumnName::new("missing").unwrap()).is_err());
     }
 
     /// Test index lookup for a non-existent column
@@ -0,0 +1,47 @@
+//! Locations in SQL source text.
+//!
+//! A [`Span`] marks the characters of the input a token was read from. The
+//! lexer gives every token one, and lexical and syntax errors keep the span
+//! of the token they are about, so the REPL can point at it in the input.
+
+/// A range of characters of the SQL source, counted from 0.
+///
+/// `start` is the first character and `end` is one past the last one; an
+/// empty span (`start == end`) marks a place between characters, such as
+/// the end of the input.
+///
+/// # Example
+/// ```
+/// use mini_rust_sgbd::core::span::Span;
+///
+/// let span = Span::new(7, 11);
+/// assert_eq!(span.len(), 4);
+/// assert!(Span::at(3).is_empty());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+impl Span {
+    /// Creates the span from `start` up to, but excluding, `end`.
+    pub fn new(start: usize, end: usize) -> Self {
+        Span { start, end }
+    }
+
+    /// Creates the empty span at `pos`.
+    pub fn at(pos: usize) -> Self {
+        Span { start: pos, end: pos }
+    }
+
+    /// Returns the number of characters covered.
+    pub fn len(&self) -> usize {
+        self.end - self.start
+    }
+
+    /// Returns `true` if the span covers no character.
+    pub fn is_empty(&self) -> bool {
+        self.start == self.end
+    }
+}
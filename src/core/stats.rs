@@ -0,0 +1,173 @@
+//! Table statistics gathered by `ANALYZE`.
+//!
+//! [`TableStats`] records how many rows a table had when it was analyzed
+//! and, for each column, how many values were `NULL`, roughly how many were
+//! distinct, and an equi-depth [`Histogram`] of the others. The access path
+//! selection uses them to estimate how many rows a condition keeps, and so
+//! whether an index is worth using.
+//!
+//! Statistics are a snapshot: later changes to the table do not update
+//! them until the next `ANALYZE`, and they are not saved with the table.
+
+use std::cmp::Ordering;
+use std::ops::Bound;
+
+use crate::core::row::Row;
+use crate::core::sketch::HyperLogLog;
+use crate::core::types::Value;
+
+/// Maximum number of buckets of a [`Histogram`].
+const BUCKETS: usize = 16;
+
+/// Statistics of a whole table.
+#[derive(Debug, Clone)]
+pub struct TableStats {
+    /// Number of rows when the table was analyzed.
+    pub row_count: usize,
+    /// Statistics of each column, in schema order.
+    pub columns: Vec<ColumnStats>,
+}
+
+/// Statistics of one column.
+#[derive(Debug, Clone)]
+pub struct ColumnStats {
+    /// Number of `NULL` values.
+    pub nulls: usize,
+    /// Estimated number of distinct non-`NULL` values.
+    pub distinct: usize,
+    /// Distribution of the non-`NULL` values, if there are any.
+    pub histogram: Option<Histogram>,
+}
+
+/// An equi-depth histogram: the sorted non-`NULL` values of a column cut
+/// into buckets holding about as many values each.
+///
+/// # Fields
+/// - `min`: the smallest value
+/// - `bounds`: the largest value of each bucket, in increasing order
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    min: Value,
+    bounds: Vec<Value>,
+}
+
+impl TableStats {
+    /// Computes the statistics of `rows`, which have `width` columns.
+    pub fn collect(rows: &[Row], width: usize) -> Self {
+        let columns = (0..width)
+            .map(|idx| ColumnStats::collect(rows.iter().map(|row| &row.values()[idx])))
+            .collect();
+        TableStats { row_count: rows.len(), columns }
+    }
+
+    /// Estimates the fraction of rows whose column `column` equals `value`.
+    pub fn eq_selectivity(&self, column: usize, value: &Value) -> f64 {
+        let stats = &self.columns[column];
+        if self.row_count == 0 || value.is_null() || stats.distinct == 0 {
+            return 0.0;
+        }
+        // Values are assumed to be evenly spread over the distinct ones
+        self.non_null_fraction(column) / stats.distinct as f64
+    }
+
+    /// Estimates the fraction of rows whose column `column` lies between
+    /// `low` and `high`.
+    pub fn range_selectivity(&self, column: usize, low: Bound<&Value>, high: Bound<&Value>) -> f64 {
+        let Some(histogram) = &self.columns[column].histogram else { return 0.0 };
+        let below = |bound: Bound<&Value>, unbounded: f64| match bound {
+            Bound::Included(v) | Bound::Excluded(v) => histogram.fraction_below(v),
+            Bound::Unbounded => unbounded,
+        };
+        let fraction = (below(high, 1.0) - below(low, 0.0)).max(0.0);
+        fraction * self.non_null_fraction(column)
+    }
+
+    /// Returns the fraction of rows whose column `column` is not `NULL`.
+    fn non_null_fraction(&self, column: usize) -> f64 {
+        if self.row_count == 0 {
+            return 0.0;
+        }
+        1.0 - self.columns[column].nulls as f64 / self.row_count as f64
+    }
+}
+
+impl ColumnStats {
+    /// Computes the statistics of the values of one column.
+    fn collect<'a>(values: impl Iterator<Item = &'a Value>) -> Self {
+        let mut nulls = 0;
+        let mut sketch = HyperLogLog::new();
+        let mut sorted = Vec::new();
+        for value in values {
+            if value.is_null() {
+                nulls += 1;
+            } else {
+                sketch.insert(value);
+                sorted.push(value.clone());
+            }
+        }
+        sorted.sort_by(|a, b| a.compare(b).unwrap_or(Ordering::Equal));
+        // The sketch may overshoot on few values
+        let distinct = sketch.estimate().min(sorted.len());
+        ColumnStats { nulls, distinct, histogram: Histogram::build(&sorted) }
+    }
+}
+
+impl Histogram {
+    /// Builds the histogram of `sorted`, or returns `None` if it is empty.
+    fn build(sorted: &[Value]) -> Option<Self> {
+        let min = sorted.first()?.clone();
+        let buckets = BUCKETS.min(sorted.len());
+        let bounds = (1..=buckets).map(|b| sorted[b * sorted.len() / buckets - 1].clone()).collect();
+        Some(Histogram { min, bounds })
+    }
+
+    /// Estimates the fraction of values smaller than `value`.
+    ///
+    /// Buckets entirely below `value` count fully and the bucket holding it
+    /// counts for half.
+    pub fn fraction_below(&self, value: &Value) -> f64 {
+        if value.compare(&self.min) != Some(Ordering::Greater) {
+            return 0.0;
+        }
+        let full = self.bounds.iter().filter(|b| b.compare(value) == Some(Ordering::Less)).count();
+        if full == self.bounds.len() {
+            return 1.0;
+        }
+        (full as f64 + 0.5) / self.bounds.len() as f64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::schema::{Column, Schema};
+    use crate::core::types::{ColumnName, DataType};
+
+    #[test]
+    fn estimates_equality_and_range_selectivity() {
+        let schema = Schema::try_new(vec![Column::new(ColumnName::new("n").unwrap(), DataType::Int)]).unwrap();
+        // 0..100, plus 100 NULLs
+        let rows: Vec<Row> = (0..200)
+            .map(|i| Row::from_values(vec![if i < 100 { Value::Int(i) } else { Value::Null }], &schema).unwrap())
+            .collect();
+        let stats = TableStats::collect(&rows, 1);
+
+        assert_eq!(stats.row_count, 200);
+        assert_eq!(stats.columns[0].nulls, 100);
+        assert!((95..=100).contains(&stats.columns[0].distinct));
+        assert!((stats.eq_selectivity(0, &Value::Int(5)) - 0.005).abs() < 0.001);
+        assert_eq!(stats.eq_selectivity(0, &Value::Null), 0.0);
+
+        let range = |low, high| stats.range_selectivity(0, low, high);
+        assert_eq!(range(Bound::Unbounded, Bound::Unbounded), 0.5);
+        assert_eq!(range(Bound::Excluded(&Value::Int(500)), Bound::Unbounded), 0.0);
+        let quarter = range(Bound::Included(&Value::Int(50)), Bound::Unbounded);
+        assert!((0.2..0.3).contains(&quarter), "{}", quarter);
+        let tenth = range(Bound::Included(&Value::Int(10)), Bound::Excluded(&Value::Int(30)));
+        assert!((0.05..0.15).contains(&tenth), "{}", tenth);
+
+        let empty = TableStats::collect(&[], 1);
+        assert_eq!(empty.eq_selectivity(0, &Value::Int(1)), 0.0);
+        assert_eq!(empty.range_selectivity(0, Bound::Unbounded, Bound::Unbounded), 0.0);
+    }
+}
@@ -4,26 +4,71 @@
 //! A `Table` consists of:
 //! - a [`TableName`] for identification
 //! - a [`Schema`] defining its columns
-//! - a list of [`Row`]s storing the actual data
+//! - a list of [`Row`]s storing the actual data, each with a [`Rowid`]
 //!
-//! The `Table` enforces that all inserted rows match the schema exactly.
+//! The `Table` enforces that all inserted rows match the schema exactly and
+//! that no two rows share a value in a `UNIQUE` or primary key column. It
+//! also owns the table's secondary [`Index`]es and keeps them up to date,
+//! and the [`TableStats`] last collected by `ANALYZE`.
+//!
+//! A table can keep a journal of the rows it inserts, updates and deletes,
+//! as [`RowChange`]s (see [`Table::record_changes`]). [`Table::apply`]
+//! makes the same changes again on another copy of the table.
 
-use crate::core::types::TableName;
-use crate::core::schema::Schema;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use crate::core::types::{TableName, ColumnName, Value};
+use crate::core::index::{Index, IndexKind};
+use crate::core::schema::{Column, Schema};
 use crate::core::row::Row;
+use crate::core::sketch::HyperLogLog;
+use crate::core::stats::TableStats;
 use crate::core::error::{SqlError, SqlResult};
 
+/// Identifies a row of a table for as long as it is stored.
+///
+/// A row gets the next rowid of its table when it is inserted and keeps it
+/// when it is updated, unlike its position, which shifts when rows before it
+/// are deleted. A table never gives a rowid out twice, even once the row is
+/// deleted or the table truncated.
+pub type Rowid = u64;
+
+/// A change made to one row of a table.
+#[derive(Debug, Clone, PartialEq)]
+pub enum RowChange {
+    /// A row was inserted with this rowid and these values.
+    Insert(Rowid, Vec<Value>),
+    /// The row with this rowid now has these values.
+    Update(Rowid, Vec<Value>),
+    /// The row with this rowid was deleted.
+    Delete(Rowid),
+}
+
 /// Represents a database table.
 ///
 /// Each `Table` contains:
 /// - `name`: a [`TableName`] identifying the table
 /// - `schema`: the table's [`Schema`]
 /// - `rows`: the list of [`Row`]s stored
+/// - `rowids`: the [`Rowid`] of each row, in increasing order
+/// - `next_rowid`: the rowid the next inserted row gets
+/// - `unique`: for each `UNIQUE` or primary key column, the non-`NULL`
+///   values in use, kept in sync with `rows`
+/// - `indexes`: secondary indexes created with `CREATE INDEX`, kept in sync
+///   with `rows`
+/// - `stats`: statistics from the last [`analyze`](Table::analyze), if any
+/// - `journal`: the row changes made since they were last taken, while
+///   they are recorded
 #[derive(Debug, Clone)]
 pub struct Table {
     name: TableName,
     schema: Schema,
     rows: Vec<Row>,
+    rowids: Vec<Rowid>,
+    next_rowid: Rowid,
+    unique: HashMap<usize, HashSet<Value>>,
+    indexes: Vec<Index>,
+    stats: Option<TableStats>,
+    journal: Option<Vec<RowChange>>,
 }
 
 impl Table {
@@ -36,10 +81,17 @@ impl Table {
     /// # Returns
     /// A new empty `Table`.
     pub fn create(name: TableName, schema: Schema) -> Self {
+        let unique = schema.unique_columns().into_iter().map(|idx| (idx, HashSet::new())).collect();
         Table {
             name,
             schema,
             rows: Vec::new(),
+            rowids: Vec::new(),
+            next_rowid: 1,
+            unique,
+            indexes: Vec::new(),
+            stats: None,
+            journal: None,
         }
     }
 
@@ -48,16 +100,262 @@ impl Table {
     /// # Validation
     /// - Number of values must match number of columns
     /// - Each value type must match the corresponding column type
+    /// - `NULL` is only allowed in nullable columns
+    /// - Values of `UNIQUE` and primary key columns must not be in use
     ///
     /// # Arguments
     /// - `row`: the row to insert
     ///
     /// # Returns
-    /// `Ok(())` if insertion succeeds, otherwise `Err(SqlError)` describing the problem.
+    /// `Ok(())` if insertion succeeds, otherwise `Err(SqlError)` describing
+    /// the problem ([`SqlError::Constraint`] for a broken constraint).
     pub fn insert_checked(&mut self, row: Row) -> SqlResult<()> {
-        // Validate row against schema
+        self.validate_row(&row)?;
+        let added = self.check_unique(std::slice::from_ref(&row))?;
+        self.add_unique(added);
+        for index in &mut self.indexes {
+            index.insert(&row.values()[index.column()], self.rows.len());
+        }
+        if let Some(journal) = &mut self.journal {
+            journal.push(RowChange::Insert(self.next_rowid, row.values().clone()));
+        }
+        self.rows.push(row);
+        self.rowids.push(self.next_rowid);
+        self.next_rowid += 1;
+        Ok(())
+    }
+
+    /// Inserts several rows atomically.
+    ///
+    /// Every row is validated as in [`Table::insert_checked`] before any is
+    /// stored, so an error leaves the table unchanged.
+    ///
+    /// # Returns
+    /// The number of rows inserted.
+    pub fn insert_many(&mut self, rows: Vec<Row>) -> SqlResult<usize> {
+        for row in &rows {
+            self.validate_row(row)?;
+        }
+        let added = self.check_unique(&rows)?;
+        self.add_unique(added);
+        let count = rows.len();
+        self.append(rows);
+        Ok(count)
+    }
+
+    /// Inserts several rows atomically, as [`Table::insert_many`] does,
+    /// skipping those that would repeat a value of `column`, or of any
+    /// `UNIQUE` or primary key column when `column` is `None`.
+    ///
+    /// # Returns
+    /// The number of rows inserted.
+    pub fn insert_or_ignore(&mut self, rows: Vec<Row>, column: Option<usize>) -> SqlResult<usize> {
+        let columns = match column {
+            Some(column) => vec![column],
+            None => self.schema.unique_columns(),
+        };
+        let mut seen: HashMap<usize, HashSet<Value>> = HashMap::new();
+        let mut kept = Vec::with_capacity(rows.len());
+        for row in rows {
+            let repeats = columns.iter().any(|idx| {
+                let value = &row.values()[*idx];
+                !value.is_null()
+                    && (self.unique.get(idx).is_some_and(|in_use| in_use.contains(value))
+                        || seen.get(idx).is_some_and(|values| values.contains(value)))
+            });
+            if repeats {
+                continue;
+            }
+            for &idx in &columns {
+                seen.entry(idx).or_default().insert(row.values()[idx].clone());
+            }
+            kept.push(row);
+        }
+        self.insert_many(kept)
+    }
+
+    /// Inserts several rows atomically, first deleting the rows holding a
+    /// value of a `UNIQUE` or primary key column that one of them repeats.
+    /// A row also replaces the earlier ones of `rows` it conflicts with.
+    ///
+    /// # Returns
+    /// The number of rows inserted.
+    pub fn insert_or_replace(&mut self, rows: Vec<Row>) -> SqlResult<usize> {
+        for row in &rows {
+            self.validate_row(row)?;
+        }
+        let columns = self.schema.unique_columns();
+
+        // Position in `kept` of the row holding each unique value
+        let mut holders: HashMap<usize, HashMap<Value, usize>> = HashMap::new();
+        let mut kept: Vec<Option<Row>> = Vec::with_capacity(rows.len());
+        for row in rows {
+            for &idx in &columns {
+                let replaced = holders.get(&idx).and_then(|held| held.get(&row.values()[idx])).copied();
+                if let Some(old) = replaced.and_then(|pos| kept[pos].take()) {
+                    for &column in &columns {
+                        if let Some(held) = holders.get_mut(&column) {
+                            held.remove(&old.values()[column]);
+                        }
+                    }
+                }
+            }
+            for &idx in &columns {
+                let value = &row.values()[idx];
+                if !value.is_null() {
+                    holders.entry(idx).or_default().insert(value.clone(), kept.len());
+                }
+            }
+            kept.push(Some(row));
+        }
+
+        let conflicts = holders
+            .iter()
+            .any(|(idx, held)| held.keys().any(|value| self.unique.get(idx).is_some_and(|in_use| in_use.contains(value))));
+        if conflicts {
+            self.delete_where(|row| {
+                Ok(holders.iter().any(|(idx, held)| held.contains_key(&row.values()[*idx])))
+            })?;
+        }
+        self.insert_many(kept.into_iter().flatten().collect())
+    }
+
+    /// Inserts several rows atomically; a row whose value of the unique
+    /// `column` is already in use updates the row holding it instead.
+    ///
+    /// # Arguments
+    /// - `rows`: the rows to insert
+    /// - `column`: a `UNIQUE` or primary key column
+    /// - `merge`: given the stored row and the one that was to be inserted,
+    ///   returns the values of the updated row
+    ///
+    /// Every row is validated before anything is stored, so an error leaves
+    /// the table unchanged.
+    ///
+    /// # Returns
+    /// The number of rows inserted or updated, or `Err(SqlError)` if two of
+    /// `rows` share their value of `column`, since the second would change
+    /// a row the statement already inserted or updated.
+    pub fn upsert<F>(&mut self, rows: Vec<Row>, column: usize, merge: F) -> SqlResult<usize>
+    where
+        F: Fn(&Row, &Row) -> SqlResult<Vec<Value>>,
+    {
+        let count = rows.len();
+        let mut positions: Option<HashMap<Value, usize>> = None;
+        let mut updated: BTreeMap<usize, Row> = BTreeMap::new();
+        let mut inserted: Vec<Row> = Vec::new();
+        let mut pending: HashSet<Value> = HashSet::new();
+        for row in rows {
+            let key = row.values()[column].clone();
+            if key.is_null() {
+                inserted.push(row);
+            } else if self.unique.get(&column).is_some_and(|in_use| in_use.contains(&key)) {
+                // The stored rows are looked up only once there is a conflict
+                let positions = positions.get_or_insert_with(|| self.positions(column));
+                let pos = positions[&key];
+                if updated.contains_key(&pos) {
+                    return Err(affected_twice(&self.schema, column, &key));
+                }
+                let merged = Row::from_values(merge(&self.rows[pos], &row)?, &self.schema)?;
+                updated.insert(pos, merged);
+            } else if pending.insert(key.clone()) {
+                inserted.push(row);
+            } else {
+                return Err(affected_twice(&self.schema, column, &key));
+            }
+        }
+        for row in &inserted {
+            self.validate_row(row)?;
+        }
+
+        if updated.is_empty() {
+            let added = self.check_unique(&inserted)?;
+            self.add_unique(added);
+        } else {
+            let mut after: Vec<&Row> = self.rows.iter().collect();
+            for (&pos, row) in &updated {
+                after[pos] = row;
+            }
+            self.unique = index_unique(&self.schema, after.into_iter().chain(&inserted))?;
+        }
+        for (pos, row) in updated {
+            self.replace_row(pos, row);
+        }
+        self.append(inserted);
+        Ok(count)
+    }
+
+    /// Returns the position of the row holding each non-`NULL` value of
+    /// `column`, which must be unique.
+    fn positions(&self, column: usize) -> HashMap<Value, usize> {
+        self.rows
+            .iter()
+            .enumerate()
+            .filter(|(_, row)| !row.values()[column].is_null())
+            .map(|(pos, row)| (row.values()[column].clone(), pos))
+            .collect()
+    }
+
+    /// Adds rows already checked against the schema and the unique
+    /// columns, with new rowids, keeping the indexes in sync.
+    fn append(&mut self, rows: Vec<Row>) {
+        let first = self.next_rowid;
+        self.append_with_rowids((first..).zip(rows).collect());
+    }
+
+    /// Adds rows as [`append`](Table::append) does, with the given rowids,
+    /// which are increasing and not below the next rowid.
+    fn append_with_rowids(&mut self, rows: Vec<(Rowid, Row)>) {
+        for (offset, (rowid, row)) in rows.iter().enumerate() {
+            for index in &mut self.indexes {
+                index.insert(&row.values()[index.column()], self.rows.len() + offset);
+            }
+            if let Some(journal) = &mut self.journal {
+                journal.push(RowChange::Insert(*rowid, row.values().clone()));
+            }
+        }
+        for (rowid, row) in rows {
+            self.rows.push(row);
+            self.rowids.push(rowid);
+            self.next_rowid = rowid + 1;
+        }
+    }
+
+    /// Replaces the row at `pos` with `row`, already checked, keeping the
+    /// indexes in sync. The row keeps its rowid.
+    fn replace_row(&mut self, pos: usize, row: Row) {
+        for index in &mut self.indexes {
+            let column = index.column();
+            index.remove(&self.rows[pos].values()[column], pos);
+            index.insert(&row.values()[column], pos);
+        }
+        if let Some(journal) = &mut self.journal {
+            journal.push(RowChange::Update(self.rowids[pos], row.values().clone()));
+        }
+        self.rows[pos] = row;
+    }
+
+    /// Removes the rows whose flag in `keep` is `false`, keeping the
+    /// indexes in sync. The unique values they held are left to the caller.
+    fn remove_rows(&mut self, keep: &[bool]) {
+        if let Some(journal) = &mut self.journal {
+            let deleted = self.rowids.iter().zip(keep).filter(|(_, &keep)| !keep);
+            journal.extend(deleted.map(|(&rowid, _)| RowChange::Delete(rowid)));
+        }
+        let mut flags = keep.iter();
+        self.rows.retain(|_| *flags.next().unwrap_or(&true));
+        let mut flags = keep.iter();
+        self.rowids.retain(|_| *flags.next().unwrap_or(&true));
+        if keep.contains(&false) {
+            // Row positions after the first deleted row have shifted
+            self.rebuild_indexes();
+        }
+    }
+
+    /// Checks that a row has the right arity and values for the schema.
+    fn validate_row(&self, row: &Row) -> SqlResult<()> {
         if row.values().len() != self.schema.columns().len() {
-            return Err(SqlError::new_core(&format!(
+            return Err(SqlError::new_execution(&format!(
                 "Row has {} values but schema has {} columns",
                 row.values().len(),
                 self.schema.columns().len()
@@ -65,25 +363,486 @@ impl Table {
         }
 
         for (i, (value, column)) in row.values().iter().zip(self.schema.columns()).enumerate() {
-            if !column.dtype.matches(value) {
-                return Err(SqlError::new_core(&format!(
-                    "Type mismatch at column {}: expected {:?}, got {:?}",
-                    i,
-                    column.dtype,
-                    value
+            column.check(i, value)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that `rows` can be added without repeating a value of a unique
+    /// column, either among themselves or with the stored rows.
+    ///
+    /// # Returns
+    /// The new values per unique column, to be passed to [`Table::add_unique`].
+    fn check_unique(&self, rows: &[Row]) -> SqlResult<HashMap<usize, HashSet<Value>>> {
+        let mut added: HashMap<usize, HashSet<Value>> = HashMap::new();
+        for row in rows {
+            for (&idx, in_use) in &self.unique {
+                let value = &row.values()[idx];
+                if value.is_null() {
+                    continue;
+                }
+                if in_use.contains(value) || !added.entry(idx).or_default().insert(value.clone()) {
+                    return Err(duplicate_value(&self.schema, idx, value));
+                }
+            }
+        }
+        Ok(added)
+    }
+
+    /// Records values returned by [`Table::check_unique`] as in use.
+    fn add_unique(&mut self, added: HashMap<usize, HashSet<Value>>) {
+        for (idx, values) in added {
+            self.unique.entry(idx).or_default().extend(values);
+        }
+    }
+
+    /// Updates every row matching `predicate` with the given assignments.
+    ///
+    /// All assignments are type-checked against the schema and the predicate
+    /// is evaluated on every row before anything is modified, so an error
+    /// leaves the table unchanged.
+    ///
+    /// # Arguments
+    /// - `assignments`: `(column, new value)` pairs
+    /// - `predicate`: decides which rows are updated
+    ///
+    /// # Returns
+    /// `Ok(count)` with the number of updated rows, otherwise `Err(SqlError)`
+    /// for an unknown column, a type mismatch, a failing predicate, or a
+    /// `UNIQUE` or primary key value used by more than one row afterwards.
+    pub fn update_where<F>(&mut self, assignments: &[(ColumnName, Value)], predicate: F) -> SqlResult<usize>
+    where
+        F: Fn(&Row) -> SqlResult<bool>,
+    {
+        let mut resolved = Vec::with_capacity(assignments.len());
+        for (col, value) in assignments {
+            let idx = self.schema.index_of(col).ok_or_else(|| {
+                SqlError::new_semantic(&format!("Unknown column '{}'", col.as_str()))
+            })?;
+            let column = &self.schema.columns()[idx];
+            let value = column.dtype.coerce(value.clone());
+            column.check(idx, &value)?;
+            resolved.push((idx, value));
+        }
+
+        self.update_each(|row| {
+            if !predicate(row)? {
+                return Ok(None);
+            }
+            let mut values = row.values().clone();
+            for (idx, value) in &resolved {
+                values[*idx] = value.clone();
+            }
+            Ok(Some(values))
+        })
+    }
+
+    /// Replaces the values of every row for which `update` returns some.
+    ///
+    /// `update` is called on every row, and its results are checked against
+    /// the schema, before anything is modified, so an error leaves the
+    /// table unchanged.
+    ///
+    /// # Returns
+    /// `Ok(count)` with the number of updated rows, otherwise `Err(SqlError)`
+    /// if `update` fails, returns values the schema rejects, or makes a
+    /// `UNIQUE` or primary key value used by more than one row.
+    pub fn update_each<F>(&mut self, update: F) -> SqlResult<usize>
+    where
+        F: Fn(&Row) -> SqlResult<Option<Vec<Value>>>,
+    {
+        let mut updated = Vec::new();
+        for (i, row) in self.rows.iter().enumerate() {
+            if let Some(values) = update(row)? {
+                updated.push((i, Row::from_values(values, &self.schema)?));
+            }
+        }
+
+        let changes_unique = updated
+            .iter()
+            .any(|(i, row)| self.unique.keys().any(|&idx| row.values()[idx] != self.rows[*i].values()[idx]));
+        if changes_unique {
+            let mut after: Vec<&Row> = self.rows.iter().collect();
+            for (i, row) in &updated {
+                after[*i] = row;
+            }
+            self.unique = index_unique(&self.schema, after)?;
+        }
+
+        let count = updated.len();
+        for (i, row) in updated {
+            self.replace_row(i, row);
+        }
+        Ok(count)
+    }
+
+    /// Deletes every row matching `predicate`.
+    ///
+    /// The predicate is evaluated on every row before anything is removed,
+    /// so an error leaves the table unchanged.
+    ///
+    /// # Returns
+    /// `Ok(count)` with the number of deleted rows, otherwise `Err(SqlError)`
+    /// if the predicate fails.
+    pub fn delete_where<F>(&mut self, predicate: F) -> SqlResult<usize>
+    where
+        F: Fn(&Row) -> SqlResult<bool>,
+    {
+        let mut keep = Vec::with_capacity(self.rows.len());
+        for row in &self.rows {
+            keep.push(!predicate(row)?);
+        }
+
+        for (row, _) in self.rows.iter().zip(&keep).filter(|(_, &keep)| !keep) {
+            for (&idx, in_use) in self.unique.iter_mut() {
+                in_use.remove(&row.values()[idx]);
+            }
+        }
+        self.remove_rows(&keep);
+        Ok(keep.iter().filter(|&&keep| !keep).count())
+    }
+
+    /// Makes `changes`, such as those recorded by another copy of the
+    /// table, as one atomic step.
+    ///
+    /// Inserted rows get the rowid given with them, which must be higher
+    /// than those of the table; updates and deletes name their row by its
+    /// rowid. Every change is checked as the other mutators check theirs
+    /// before anything is modified, so an error leaves the table unchanged.
+    ///
+    /// # Returns
+    /// `Ok(())`, otherwise `Err(SqlError)` if a row does not match the
+    /// schema, a rowid is unknown or already used, or a `UNIQUE` or primary
+    /// key value would be used by more than one row.
+    pub fn apply(&mut self, changes: Vec<RowChange>) -> SqlResult<()> {
+        // Stored rows changed, by position (`None` once deleted), and rows
+        // inserted
+        let mut changed: BTreeMap<usize, Option<Row>> = BTreeMap::new();
+        let mut inserted: Vec<(Rowid, Option<Row>)> = Vec::new();
+        let mut next = self.next_rowid;
+        for change in changes {
+            let (rowid, row) = match change {
+                RowChange::Insert(rowid, values) => {
+                    if rowid < next {
+                        return Err(SqlError::new_execution(&format!(
+                            "Rowid {} is already used in table '{}'",
+                            rowid,
+                            self.name.as_str()
+                        )));
+                    }
+                    next = rowid + 1;
+                    inserted.push((rowid, Some(Row::from_values(values, &self.schema)?)));
+                    continue;
+                }
+                RowChange::Update(rowid, values) => (rowid, Some(Row::from_values(values, &self.schema)?)),
+                RowChange::Delete(rowid) => (rowid, None),
+            };
+            // A change may follow another one to the same row
+            let slot = match self.position(rowid) {
+                Some(pos) => Some(changed.entry(pos).or_insert_with(|| Some(self.rows[pos].clone()))),
+                None => inserted.binary_search_by_key(&rowid, |(id, _)| *id).ok().map(|at| &mut inserted[at].1),
+            };
+            match slot.filter(|slot| slot.is_some()) {
+                Some(slot) => *slot = row,
+                None => {
+                    return Err(SqlError::new_execution(&format!(
+                        "Table '{}' has no row with rowid {}",
+                        self.name.as_str(),
+                        rowid
+                    )));
+                }
+            }
+        }
+        let inserted: Vec<(Rowid, Row)> = inserted.into_iter().filter_map(|(rowid, row)| Some((rowid, row?))).collect();
+
+        // Values the changed rows give up may be taken by the new ones
+        let mut added: HashMap<usize, HashSet<Value>> = HashMap::new();
+        let mut freed: HashMap<usize, HashSet<&Value>> = HashMap::new();
+        for (&idx, in_use) in &self.unique {
+            let freed = freed.entry(idx).or_default();
+            freed.extend(changed.keys().map(|&pos| &self.rows[pos].values()[idx]));
+            let new_rows = changed.values().flatten().chain(inserted.iter().map(|(_, row)| row));
+            for value in new_rows.map(|row| &row.values()[idx]).filter(|value| !value.is_null()) {
+                let taken = in_use.contains(value) && !freed.contains(value);
+                if taken || !added.entry(idx).or_default().insert(value.clone()) {
+                    return Err(duplicate_value(&self.schema, idx, value));
+                }
+            }
+        }
+        let freed: Vec<(usize, Vec<Value>)> =
+            freed.into_iter().map(|(idx, values)| (idx, values.into_iter().cloned().collect())).collect();
+
+        for (idx, values) in freed {
+            let in_use = self.unique.get_mut(&idx).expect("a unique column");
+            for value in values {
+                in_use.remove(&value);
+            }
+        }
+        self.add_unique(added);
+        let mut keep = vec![true; self.rows.len()];
+        for (pos, row) in changed {
+            match row {
+                Some(row) => self.replace_row(pos, row),
+                None => keep[pos] = false,
+            }
+        }
+        self.remove_rows(&keep);
+        self.append_with_rowids(inserted);
+        Ok(())
+    }
+
+    /// Starts or stops recording the changes made to rows, as
+    /// [`RowChange`]s that [`take_changes`](Table::take_changes) returns.
+    /// Stopping forgets the changes not taken.
+    ///
+    /// Changes to the columns, such as [`add_column`](Table::add_column),
+    /// are not recorded.
+    pub fn record_changes(&mut self, record: bool) {
+        match (record, &self.journal) {
+            (true, None) => self.journal = Some(Vec::new()),
+            (false, Some(_)) => self.journal = None,
+            _ => {}
+        }
+    }
+
+    /// Returns `true` if changes were recorded since they were last taken.
+    pub fn has_changes(&self) -> bool {
+        self.journal.as_ref().is_some_and(|journal| !journal.is_empty())
+    }
+
+    /// Returns the changes recorded since they were last taken, oldest
+    /// first, and forgets them.
+    pub fn take_changes(&mut self) -> Vec<RowChange> {
+        self.journal.as_mut().map(std::mem::take).unwrap_or_default()
+    }
+
+    /// Renames the table. Its rows, indexes and statistics are kept.
+    pub fn rename(&mut self, name: TableName) {
+        self.name = name;
+    }
+
+    /// Returns an empty copy of the table: same name, schema and indexes,
+    /// no rows and no statistics. Unlike deleting every row, the rows are
+    /// neither looked at nor copied. Rowids go on from those of the table.
+    pub fn emptied(&self) -> Table {
+        let mut table = Table::create(self.name.clone(), self.schema.clone());
+        table.next_rowid = self.next_rowid;
+        table.indexes = self.indexes.iter().map(|index| Index::build(index.name(), index.column(), index.kind(), &[])).collect();
+        table
+    }
+
+    /// Adds a column at the end of the schema.
+    ///
+    /// Existing rows are backfilled with `NULL` for nullable columns, and
+    /// with the column type's
+    /// [`default_value`](crate::core::types::DataType::default_value) otherwise.
+    ///
+    /// # Returns
+    /// `Ok(())` on success, otherwise `Err(SqlError)` if a column with the
+    /// same name already exists, or if the column is `UNIQUE` and the
+    /// backfill would repeat a value.
+    pub fn add_column(&mut self, column: Column) -> SqlResult<()> {
+        let fill = if column.nullable { Value::Null } else { column.dtype.default_value() };
+        let mut columns = self.schema.columns().clone();
+        columns.push(column);
+        self.rebuild(columns, self.schema.primary_key(), |values| {
+            let mut values = values.to_vec();
+            values.push(fill.clone());
+            values
+        })
+    }
+
+    /// Removes a column and its values from every row.
+    ///
+    /// # Returns
+    /// `Ok(())` on success, otherwise `Err(SqlError)` if the column does not
+    /// exist, is the table's only column, or is the primary key.
+    pub fn drop_column(&mut self, name: &ColumnName) -> SqlResult<()> {
+        let idx = self.schema.index_of(name).ok_or_else(|| {
+            SqlError::new_semantic(&format!("Unknown column '{}'", name.as_str()))
+        })?;
+        if self.schema.columns().len() == 1 {
+            return Err(SqlError::new_semantic(&format!(
+                "Cannot drop '{}': a table must keep at least one column",
+                name.as_str()
+            )));
+        }
+        let primary_key = match self.schema.primary_key() {
+            Some(pk) if pk == idx => {
+                return Err(SqlError::new_semantic(&format!(
+                    "Cannot drop '{}': it is the primary key",
+                    name.as_str()
                 )));
             }
+            Some(pk) if pk > idx => Some(pk - 1),
+            other => other,
+        };
+
+        // Indexes on the dropped column go with it; the others are rebuilt
+        // on the shifted positions
+        let kept = self
+            .indexes
+            .iter()
+            .filter(|index| index.column() != idx)
+            .map(|index| {
+                let column = if index.column() > idx { index.column() - 1 } else { index.column() };
+                Index::build(index.name(), column, index.kind(), &[])
+            })
+            .collect();
+        let previous = std::mem::replace(&mut self.indexes, kept);
+
+        let mut columns = self.schema.columns().clone();
+        columns.remove(idx);
+        let result = self.rebuild(columns, primary_key, |values| {
+            let mut values = values.to_vec();
+            values.remove(idx);
+            values
+        });
+        if result.is_err() {
+            self.indexes = previous;
         }
+        result
+    }
 
-        self.rows.push(row);
+    /// Renames a column, keeping its position, type and data.
+    ///
+    /// # Returns
+    /// `Ok(())` on success, otherwise `Err(SqlError)` if `from` does not exist
+    /// or `to` is already taken.
+    pub fn rename_column(&mut self, from: &ColumnName, to: ColumnName) -> SqlResult<()> {
+        let idx = self.schema.index_of(from).ok_or_else(|| {
+            SqlError::new_semantic(&format!("Unknown column '{}'", from.as_str()))
+        })?;
+
+        let mut columns = self.schema.columns().clone();
+        columns[idx].name = to;
+        self.rebuild(columns, self.schema.primary_key(), |values| values.to_vec())
+    }
+
+    /// Replaces the schema and rewrites every row with `f`.
+    ///
+    /// The new schema is validated (duplicate names) and each rewritten row
+    /// is checked against it before the table is modified. `primary_key` is
+    /// the position of the key column in `columns`; key values must not be
+    /// changed by `f`.
+    fn rebuild<F>(&mut self, columns: Vec<Column>, primary_key: Option<usize>, f: F) -> SqlResult<()>
+    where
+        F: Fn(&[Value]) -> Vec<Value>,
+    {
+        let key_name = primary_key.map(|idx| columns[idx].name.clone());
+        let mut schema = Schema::try_new(columns)?;
+        if let Some(name) = key_name {
+            schema = schema.with_primary_key(&name)?;
+        }
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| Row::from_values(f(row.values()), &schema))
+            .collect::<SqlResult<Vec<Row>>>()?;
+        let unique = index_unique(&schema, &rows)?;
+
+        self.schema = schema;
+        self.rows = rows;
+        self.unique = unique;
+        self.rebuild_indexes();
+        // Column positions may have changed
+        self.stats = None;
         Ok(())
     }
 
+    /// Creates an index of kind `kind` named `name` on `column`.
+    ///
+    /// # Returns
+    /// `Ok(())` on success, otherwise `Err(SqlError)` if the column does not
+    /// exist or the table already has an index with that name.
+    pub fn create_index(&mut self, name: &str, column: &ColumnName, kind: IndexKind) -> SqlResult<()> {
+        let idx = self.schema.index_of(column).ok_or_else(|| {
+            SqlError::new_semantic(&format!("Unknown column '{}'", column.as_str()))
+        })?;
+        if self.index(name).is_some() {
+            return Err(SqlError::new_semantic(&format!("Index '{}' already exists", name)));
+        }
+        self.indexes.push(Index::build(name, idx, kind, &self.rows));
+        Ok(())
+    }
+
+    /// Returns the table's indexes, in creation order.
+    pub fn indexes(&self) -> &[Index] {
+        &self.indexes
+    }
+
+    /// Returns the index named `name`, if any.
+    pub fn index(&self, name: &str) -> Option<&Index> {
+        self.indexes.iter().find(|index| index.name() == name)
+    }
+
+    /// Recomputes every index from the current rows.
+    fn rebuild_indexes(&mut self) {
+        for index in &mut self.indexes {
+            *index = Index::build(index.name(), index.column(), index.kind(), &self.rows);
+        }
+    }
+
+    /// Collects statistics on the current rows, replacing earlier ones.
+    pub fn analyze(&mut self) {
+        self.stats = Some(TableStats::collect(&self.rows, self.schema.columns().len()));
+    }
+
+    /// Returns the statistics from the last [`analyze`](Table::analyze),
+    /// or `None` if the table was never analyzed since its columns last
+    /// changed.
+    pub fn stats(&self) -> Option<&TableStats> {
+        self.stats.as_ref()
+    }
+
     /// Returns a reference to the table's rows.
     pub fn rows(&self) -> &Vec<Row> {
         &self.rows
     }
 
+    /// Returns the rowid of each row, in the order of [`rows`](Table::rows),
+    /// which is increasing.
+    pub fn rowids(&self) -> &[Rowid] {
+        &self.rowids
+    }
+
+    /// Returns the position in [`rows`](Table::rows) of the row with rowid
+    /// `rowid`, or `None` if the table has no such row.
+    pub fn position(&self, rowid: Rowid) -> Option<usize> {
+        self.rowids.binary_search(&rowid).ok()
+    }
+
+    /// Returns the rowid the next inserted row will get.
+    pub fn next_rowid(&self) -> Rowid {
+        self.next_rowid
+    }
+
+    /// Gives the rows back the rowids they were saved with, so that a
+    /// table loaded from a snapshot numbers its rows as before.
+    ///
+    /// # Arguments
+    /// * `rowids` - The rowid of each row, in increasing order
+    /// * `next_rowid` - The rowid the next inserted row gets, higher than
+    ///   every rowid in `rowids`
+    ///
+    /// # Returns
+    /// An execution error if there is not one rowid per row, or the rowids
+    /// are not increasing and below `next_rowid`.
+    pub fn restore_rowids(&mut self, rowids: Vec<Rowid>, next_rowid: Rowid) -> SqlResult<()> {
+        let increasing = rowids.windows(2).all(|pair| pair[0] < pair[1]);
+        let below = rowids.last().is_none_or(|&last| last < next_rowid);
+        if rowids.len() != self.rows.len() || !increasing || !below || next_rowid == 0 {
+            return Err(SqlError::new_execution(&format!(
+                "Table '{}' has {} rows, which cannot take the saved rowids",
+                self.name.as_str(),
+                self.rows.len()
+            )));
+        }
+        self.rowids = rowids;
+        self.next_rowid = next_rowid;
+        Ok(())
+    }
+
     /// Returns the table name.
     pub fn name(&self) -> &TableName {
         &self.name
@@ -93,13 +852,120 @@ impl Table {
     pub fn schema(&self) -> &Schema {
         &self.schema
     }
+
+    /// Returns the column names in declaration order.
+    ///
+    /// Renderers use this as the header line of a table.
+    pub fn header(&self) -> Vec<String> {
+        self.schema
+            .columns()
+            .iter()
+            .map(|c| c.name.as_str().to_string())
+            .collect()
+    }
+
+    /// Returns every row as a map from column name to value.
+    ///
+    /// A `BTreeMap` is used so the key order is deterministic.
+    pub fn to_records(&self) -> Vec<BTreeMap<String, Value>> {
+        let header = self.header();
+        self.rows
+            .iter()
+            .map(|row| header.iter().cloned().zip(row.values().iter().cloned()).collect())
+            .collect()
+    }
+
+    /// Estimates the number of distinct values in a column.
+    ///
+    /// The count is **approximate**: it comes from a [`HyperLogLog`] sketch
+    /// and is meant for cheap statistics, not exact results.
+    ///
+    /// # Returns
+    /// `Ok(estimate)`, or `Err(SqlError)` if the column does not exist.
+    pub fn approx_distinct(&self, col: &ColumnName) -> SqlResult<usize> {
+        let idx = self.schema.index_of(col).ok_or_else(|| {
+            SqlError::new_semantic(&format!("Unknown column '{}'", col.as_str()))
+        })?;
+
+        let mut sketch = HyperLogLog::new();
+        for row in &self.rows {
+            sketch.insert(&row.values()[idx]);
+        }
+        Ok(sketch.estimate())
+    }
+
+    /// Builds a new table by mapping every row through `f`.
+    ///
+    /// Rows for which `f` returns `None` are dropped; the other results are
+    /// validated against `new_schema` before being stored. The new table keeps
+    /// the name of the source table.
+    ///
+    /// # Arguments
+    /// - `new_schema`: the schema of the resulting table
+    /// - `f`: the row mapping, returning the new values or `None` to drop the row
+    ///
+    /// # Returns
+    /// `Ok(Table)` with the transformed rows, otherwise `Err(SqlError)` if a
+    /// produced row does not match `new_schema`.
+    pub fn transform<F>(&self, new_schema: Schema, f: F) -> SqlResult<Table>
+    where
+        F: Fn(&Row) -> Option<Vec<Value>>,
+    {
+        let mut table = Table::create(self.name.clone(), new_schema);
+        for row in &self.rows {
+            if let Some(values) = f(row) {
+                let new_row = Row::from_values(values, &table.schema)?;
+                table.insert_checked(new_row)?;
+            }
+        }
+        Ok(table)
+    }
+}
+
+/// Collects the values of every unique column of `schema` over `rows`,
+/// failing on the first repeated value.
+fn index_unique<'a, I>(schema: &Schema, rows: I) -> SqlResult<HashMap<usize, HashSet<Value>>>
+where
+    I: IntoIterator<Item = &'a Row>,
+{
+    let mut unique: HashMap<usize, HashSet<Value>> =
+        schema.unique_columns().into_iter().map(|idx| (idx, HashSet::new())).collect();
+    for row in rows {
+        for (&idx, in_use) in unique.iter_mut() {
+            let value = &row.values()[idx];
+            if !value.is_null() && !in_use.insert(value.clone()) {
+                return Err(duplicate_value(schema, idx, value));
+            }
+        }
+    }
+    Ok(unique)
+}
+
+/// Builds the error for an upsert whose rows repeat the value `value` of
+/// its conflict column.
+fn affected_twice(schema: &Schema, idx: usize, value: &Value) -> SqlError {
+    SqlError::new_execution(&format!(
+        "ON CONFLICT DO UPDATE cannot affect a row a second time: value {} of column '{}' is repeated",
+        value,
+        schema.columns()[idx].name.as_str()
+    ))
+}
+
+/// Builds the error for a value repeated in a unique column.
+fn duplicate_value(schema: &Schema, idx: usize, value: &Value) -> SqlError {
+    let constraint = if schema.primary_key() == Some(idx) { "PRIMARY KEY" } else { "UNIQUE" };
+    SqlError::new_constraint(&format!(
+        "{} constraint violated: duplicate value {} for column '{}'",
+        constraint,
+        value,
+        schema.columns()[idx].name.as_str()
+    ))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
     use crate::core::types::{TableName, ColumnName, DataType, Value};
-    use crate::core::schema::{Column, Schema};
 
     #[test]
     fn insert_valid_row_increases_rows() {
@@ -123,7 +989,9 @@ mod tests {
         let table_name = TableName::new("users").unwrap();
         let mut table = Table::create(table_name, schema);
 
-        let row = Row::from_values(vec![Value::Int(1)], table.schema()).unwrap();
+        // Build the row against a narrower schema so it reaches the table check
+        let other = Schema::try_new(vec![Column::new(ColumnName::new("id").unwrap(), DataType::Int)]).unwrap();
+        let row = Row::from_values(vec![Value::Int(1)], &other).unwrap();
         let result = table.insert_checked(row);
         assert!(result.is_err());
     }
@@ -136,8 +1004,453 @@ mod tests {
         let table_name = TableName::new("users").unwrap();
         let mut table = Table::create(table_name, schema);
 
-        let row = Row::from_values(vec![Value::Text("1".to_string()), Value::Text("Alice".to_string())], table.schema()).unwrap();
+        // Build the row against a text-only schema so it reaches the table check
+        let other = Schema::try_new(vec![
+            Column::new(ColumnName::new("id").unwrap(), DataType::Text),
+            Column::new(ColumnName::new("name").unwrap(), DataType::Text),
+        ]).unwrap();
+        let row = Row::from_values(vec![Value::Text("1".to_string()), Value::Text("Alice".to_string())], &other).unwrap();
         let result = table.insert_checked(row);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn insert_many_is_all_or_nothing() {
+        let mut table = users_table();
+        let before = table.rows().len();
+        let schema = table.schema().clone();
+        let good = Row::from_values(vec![Value::Int(10), Value::Text("Zed".to_string())], &schema).unwrap();
+        let short = Row::from_values(vec![Value::Int(11)], &Schema::try_new(vec![
+            Column::new(ColumnName::new("id").unwrap(), DataType::Int),
+        ]).unwrap()).unwrap();
+
+        assert!(table.insert_many(vec![good.clone(), short]).is_err());
+        assert_eq!(table.rows().len(), before);
+
+        assert_eq!(table.insert_many(vec![good.clone(), good]).unwrap(), 2);
+        assert_eq!(table.rows().len(), before + 2);
+    }
+
+    #[test]
+    fn update_where_changes_matching_rows() {
+        let col1 = Column::new(ColumnName::new("id").unwrap(), DataType::Int);
+        let col2 = Column::new(ColumnName::new("name").unwrap(), DataType::Text);
+        let schema = Schema::try_new(vec![col1, col2]).unwrap();
+        let mut table = Table::create(TableName::new("users").unwrap(), schema);
+        for (id, name) in [(1, "Alice"), (2, "Bob")] {
+            let row = Row::from_values(vec![Value::Int(id), Value::Text(name.to_string())], table.schema()).unwrap();
+            table.insert_checked(row).unwrap();
+        }
+
+        let name = ColumnName::new("name").unwrap();
+        let updated = table
+            .update_where(&[(name, Value::Text("Robert".to_string()))], |row| Ok(row.values()[0] == Value::Int(2)))
+            .unwrap();
+
+        assert_eq!(updated, 1);
+        assert_eq!(table.rows()[0].values()[1], Value::Text("Alice".to_string()));
+        assert_eq!(table.rows()[1].values()[1], Value::Text("Robert".to_string()));
+    }
+
+    #[test]
+    fn update_where_type_mismatch_leaves_table_unchanged() {
+        let col = Column::new(ColumnName::new("id").unwrap(), DataType::Int);
+        let schema = Schema::try_new(vec![col]).unwrap();
+        let mut table = Table::create(TableName::new("t").unwrap(), schema);
+        let row = Row::from_values(vec![Value::Int(1)], table.schema()).unwrap();
+        table.insert_checked(row).unwrap();
+
+        let id = ColumnName::new("id").unwrap();
+        assert!(table.update_where(&[(id, Value::Text("x".to_string()))], |_| Ok(true)).is_err());
+        let missing = ColumnName::new("missing").unwrap();
+        assert!(table.update_where(&[(missing, Value::Int(2))], |_| Ok(true)).is_err());
+        assert_eq!(table.rows()[0].values()[0], Value::Int(1));
+    }
+
+    #[test]
+    fn delete_where_removes_matching_rows() {
+        let col = Column::new(ColumnName::new("n").unwrap(), DataType::Int);
+        let schema = Schema::try_new(vec![col]).unwrap();
+        let mut table = Table::create(TableName::new("numbers").unwrap(), schema);
+        for n in 1..=5 {
+            let row = Row::from_values(vec![Value::Int(n)], table.schema()).unwrap();
+            table.insert_checked(row).unwrap();
+        }
+
+        let deleted = table
+            .delete_where(|row| Ok(matches!(row.values()[0], Value::Int(n) if n % 2 == 1)))
+            .unwrap();
+
+        assert_eq!(deleted, 3);
+        let values: Vec<&Value> = table.rows().iter().map(|r| &r.values()[0]).collect();
+        assert_eq!(values, vec![&Value::Int(2), &Value::Int(4)]);
+    }
+
+    #[test]
+    fn delete_where_error_leaves_table_unchanged() {
+        let col = Column::new(ColumnName::new("n").unwrap(), DataType::Int);
+        let schema = Schema::try_new(vec![col]).unwrap();
+        let mut table = Table::create(TableName::new("numbers").unwrap(), schema);
+        for n in 1..=3 {
+            let row = Row::from_values(vec![Value::Int(n)], table.schema()).unwrap();
+            table.insert_checked(row).unwrap();
+        }
+
+        let result = table.delete_where(|row| match row.values()[0] {
+            Value::Int(3) => Err(SqlError::new_execution("boom")),
+            _ => Ok(true),
+        });
+
+        assert!(result.is_err());
+        assert_eq!(table.rows().len(), 3);
+    }
+
+    fn keyed_table() -> Table {
+        let id = ColumnName::new("id").unwrap();
+        let schema = Schema::try_new(vec![
+            Column::new(id.clone(), DataType::Int),
+            Column::new(ColumnName::new("name").unwrap(), DataType::Text),
+        ])
+        .unwrap()
+        .with_primary_key(&id)
+        .unwrap();
+        let mut table = Table::create(TableName::new("users").unwrap(), schema);
+        for (id, name) in [(1, "Alice"), (2, "Bob")] {
+            let row = Row::from_values(vec![Value::Int(id), Value::Text(name.to_string())], table.schema()).unwrap();
+            table.insert_checked(row).unwrap();
+        }
+        table
+    }
+
+    #[test]
+    fn rowids_follow_rows_and_are_never_reused() {
+        let mut table = keyed_table();
+        let schema = table.schema().clone();
+        let row = |id: i64| Row::from_values(vec![Value::Int(id), Value::Text("X".to_string())], &schema).unwrap();
+        assert_eq!(table.rowids(), &[1, 2]);
+
+        // Updates keep the rowid, deletes free the position but not the id
+        let assignments = [(ColumnName::new("name").unwrap(), Value::Text("Carol".to_string()))];
+        table.update_where(&assignments, |row| Ok(row.values()[0] == Value::Int(2))).unwrap();
+        table.delete_where(|row| Ok(row.values()[0] == Value::Int(1))).unwrap();
+        table.insert_many(vec![row(3), row(4)]).unwrap();
+        assert_eq!(table.rowids(), &[2, 3, 4]);
+        assert_eq!((table.position(2), table.position(1)), (Some(0), None));
+        assert_eq!(table.rows()[0].values()[1], Value::Text("Carol".to_string()));
+
+        // A replaced row is a new row, and truncating keeps counting
+        table.insert_or_replace(vec![row(3)]).unwrap();
+        assert_eq!(table.rowids(), &[2, 4, 5]);
+        let mut table = table.emptied();
+        table.insert_checked(row(1)).unwrap();
+        assert_eq!((table.rowids(), table.next_rowid()), (&[6][..], 7));
+    }
+
+    #[test]
+    fn recorded_changes_can_be_applied_to_a_copy() {
+        let mut table = keyed_table();
+        let mut copy = table.clone();
+        let schema = table.schema().clone();
+        let row = |id: i64, name: &str| vec![Value::Int(id), Value::Text(name.to_string())];
+        table.record_changes(true);
+        table.insert_many(vec![Row::from_values(row(3, "Carol"), &schema).unwrap()]).unwrap();
+        let assignments = [(ColumnName::new("name").unwrap(), Value::Text("Dan".to_string()))];
+        table.update_where(&assignments, |r| Ok(r.values()[0] == Value::Int(1))).unwrap();
+        table.delete_where(|r| Ok(r.values()[0] == Value::Int(2))).unwrap();
+        let changes = table.take_changes();
+        assert_eq!(changes, vec![RowChange::Insert(3, row(3, "Carol")), RowChange::Update(1, row(1, "Dan")), RowChange::Delete(2)]);
+        assert!(!table.has_changes());
+
+        copy.apply(changes).unwrap();
+        assert_eq!(copy.to_records(), table.to_records());
+        assert_eq!(copy.rowids(), table.rowids());
+
+        // A key freed by the same changes can be taken again; otherwise
+        // nothing is changed
+        assert!(copy.apply(vec![RowChange::Delete(3), RowChange::Insert(9, row(1, "Eve"))]).is_err());
+        assert!(copy.apply(vec![RowChange::Delete(2)]).is_err());
+        assert!(copy.apply(vec![RowChange::Insert(3, row(4, "Eve"))]).is_err());
+        assert_eq!(copy.rowids(), &[1, 3]);
+        copy.apply(vec![RowChange::Delete(1), RowChange::Insert(9, row(1, "Eve")), RowChange::Update(9, row(1, "Fay"))]).unwrap();
+        assert_eq!((copy.rowids(), copy.next_rowid()), (&[3, 9][..], 10));
+        assert_eq!(copy.rows()[1].values(), &row(1, "Fay"));
+    }
+
+    #[test]
+    fn primary_key_rejects_duplicate_inserts() {
+        let mut table = keyed_table();
+        let schema = table.schema().clone();
+        let row = |id: i64| Row::from_values(vec![Value::Int(id), Value::Text("X".to_string())], &schema).unwrap();
+
+        let err = table.insert_checked(row(1)).unwrap_err();
+        assert!(err.message().contains("PRIMARY KEY constraint violated"));
+
+        // Duplicates inside one batch are caught too, and nothing is stored
+        assert!(table.insert_many(vec![row(3), row(3)]).is_err());
+        assert_eq!(table.rows().len(), 2);
+        assert_eq!(table.insert_many(vec![row(3), row(4)]).unwrap(), 2);
+        assert!(table.insert_checked(row(4)).is_err());
+    }
+
+    #[test]
+    fn primary_key_follows_updates_and_deletes() {
+        let mut table = keyed_table();
+        let id = ColumnName::new("id").unwrap();
+        let schema = table.schema().clone();
+
+        // Moving Bob onto Alice's key fails and leaves the table unchanged
+        assert!(table.update_where(&[(id.clone(), Value::Int(1))], |row| Ok(row.values()[0] == Value::Int(2))).is_err());
+        assert_eq!(table.rows()[1].values()[0], Value::Int(2));
+
+        // A freed key can be reused
+        assert_eq!(table.update_where(&[(id.clone(), Value::Int(5))], |row| Ok(row.values()[0] == Value::Int(2))).unwrap(), 1);
+        let bob = Row::from_values(vec![Value::Int(2), Value::Text("Bob".to_string())], &schema).unwrap();
+        table.insert_checked(bob.clone()).unwrap();
+        table.delete_where(|row| Ok(row.values()[0] == Value::Int(2))).unwrap();
+        table.insert_checked(bob).unwrap();
+
+        assert!(table.drop_column(&id).is_err());
+        table.rename_column(&id, ColumnName::new("user_id").unwrap()).unwrap();
+        assert_eq!(table.schema().primary_key(), Some(0));
+    }
+
+    #[test]
+    fn unique_column_allows_repeated_nulls_only() {
+        let mut table = users_table();
+        table.add_column(Column::new(ColumnName::new("email").unwrap(), DataType::Text).with_unique(true)).unwrap();
+        let schema = table.schema().clone();
+        let row = |id: i64, email: Value| Row::from_values(vec![Value::Int(id), Value::Text("X".to_string()), email], &schema).unwrap();
+
+        table.insert_many(vec![row(2, Value::Null), row(3, Value::Text("a@x".to_string()))]).unwrap();
+        let err = table.insert_checked(row(4, Value::Text("a@x".to_string()))).unwrap_err();
+        assert!(matches!(err, SqlError::Constraint { .. }));
+        assert!(err.message().contains("UNIQUE"));
+
+        let email = ColumnName::new("email").unwrap();
+        assert!(table.update_where(&[(email.clone(), Value::Text("b@x".to_string()))], |_| Ok(true)).is_err());
+        assert_eq!(table.update_where(&[(email, Value::Text("b@x".to_string()))], |row| Ok(row.values()[0] == Value::Int(2))).unwrap(), 1);
+
+        // A NOT NULL unique column cannot be backfilled with one default value
+        let code = Column::new(ColumnName::new("code").unwrap(), DataType::Int).with_nullable(false).with_unique(true);
+        assert!(table.add_column(code).is_err());
+    }
+
+    fn users_table() -> Table {
+        let col1 = Column::new(ColumnName::new("id").unwrap(), DataType::Int);
+        let col2 = Column::new(ColumnName::new("name").unwrap(), DataType::Text);
+        let schema = Schema::try_new(vec![col1, col2]).unwrap();
+        let mut table = Table::create(TableName::new("users").unwrap(), schema);
+        let row = Row::from_values(vec![Value::Int(1), Value::Text("Alice".to_string())], table.schema()).unwrap();
+        table.insert_checked(row).unwrap();
+        table
+    }
+
+    #[test]
+    fn add_column_backfills_existing_rows() {
+        let mut table = users_table();
+        table.add_column(Column::new(ColumnName::new("age").unwrap(), DataType::Int)).unwrap();
+
+        assert_eq!(table.header(), vec!["id", "name", "age"]);
+        assert_eq!(table.schema().index_of(&ColumnName::new("age").unwrap()), Some(2));
+        assert_eq!(table.rows()[0].values()[2], Value::Null);
+
+        let score = Column::new(ColumnName::new("score").unwrap(), DataType::Int).with_nullable(false);
+        table.add_column(score).unwrap();
+        assert_eq!(table.rows()[0].values()[3], Value::Int(0));
+
+        let duplicate = Column::new(ColumnName::new("id").unwrap(), DataType::Int);
+        assert!(table.add_column(duplicate).is_err());
+    }
+
+    #[test]
+    fn drop_column_removes_values_and_reindexes() {
+        let mut table = users_table();
+        table.drop_column(&ColumnName::new("id").unwrap()).unwrap();
+
+        assert_eq!(table.header(), vec!["name"]);
+        assert_eq!(table.schema().index_of(&ColumnName::new("name").unwrap()), Some(0));
+        assert_eq!(table.schema().index_of(&ColumnName::new("id").unwrap()), None);
+        assert_eq!(table.rows()[0].values(), &vec![Value::Text("Alice".to_string())]);
+
+        assert!(table.drop_column(&ColumnName::new("name").unwrap()).is_err());
+        assert!(table.drop_column(&ColumnName::new("missing").unwrap()).is_err());
+    }
+
+    #[test]
+    fn rename_column_keeps_data() {
+        let mut table = users_table();
+        table.rename_column(&ColumnName::new("name").unwrap(), ColumnName::new("full_name").unwrap()).unwrap();
+
+        assert_eq!(table.header(), vec!["id", "full_name"]);
+        assert_eq!(table.schema().index_of(&ColumnName::new("full_name").unwrap()), Some(1));
+        assert_eq!(table.schema().index_of(&ColumnName::new("name").unwrap()), None);
+        assert_eq!(table.rows()[0].values()[1], Value::Text("Alice".to_string()));
+
+        let taken = table.rename_column(&ColumnName::new("id").unwrap(), ColumnName::new("full_name").unwrap());
+        assert!(taken.is_err());
+        assert_eq!(table.header(), vec!["id", "full_name"]);
+    }
+
+    #[test]
+    fn header_lists_column_names_in_order() {
+        let col1 = Column::new(ColumnName::new("id").unwrap(), DataType::Int);
+        let col2 = Column::new(ColumnName::new("name").unwrap(), DataType::Text);
+        let schema = Schema::try_new(vec![col1, col2]).unwrap();
+        let table = Table::create(TableName::new("users").unwrap(), schema);
+
+        assert_eq!(table.header(), vec!["id".to_string(), "name".to_string()]);
+    }
+
+    #[test]
+    fn to_records_maps_column_names_to_values() {
+        let col1 = Column::new(ColumnName::new("id").unwrap(), DataType::Int);
+        let col2 = Column::new(ColumnName::new("name").unwrap(), DataType::Text);
+        let schema = Schema::try_new(vec![col1, col2]).unwrap();
+        let mut table = Table::create(TableName::new("users").unwrap(), schema);
+        for (id, name) in [(1, "Alice"), (2, "Bob")] {
+            let row = Row::from_values(vec![Value::Int(id), Value::Text(name.to_string())], table.schema()).unwrap();
+            table.insert_checked(row).unwrap();
+        }
+
+        let records = table.to_records();
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0]["id"], Value::Int(1));
+        assert_eq!(records[0]["name"], Value::Text("Alice".to_string()));
+        assert_eq!(records[1]["id"], Value::Int(2));
+        assert_eq!(records[1]["name"], Value::Text("Bob".to_string()));
+        assert_eq!(records[1].keys().collect::<Vec<_>>(), vec!["id", "name"]);
+    }
+
+    #[test]
+    fn approx_distinct_close_to_exact_count() {
+        let col = Column::new(ColumnName::new("n").unwrap(), DataType::Int);
+        let schema = Schema::try_new(vec![col]).unwrap();
+        let mut table = Table::create(TableName::new("numbers").unwrap(), schema);
+        // 5000 distinct values, each inserted twice
+        for n in 0..10_000 {
+            let row = Row::from_values(vec![Value::Int(n % 5000)], table.schema()).unwrap();
+            table.insert_checked(row).unwrap();
+        }
+
+        let estimate = table.approx_distinct(&ColumnName::new("n").unwrap()).unwrap() as f64;
+        assert!((estimate - 5000.0).abs() / 5000.0 < 0.1, "estimate {}", estimate);
+        assert!(table.approx_distinct(&ColumnName::new("missing").unwrap()).is_err());
+    }
+
+    #[test]
+    fn transform_doubles_and_drops_odd_rows() {
+        let col = Column::new(ColumnName::new("n").unwrap(), DataType::Int);
+        let schema = Schema::try_new(vec![col]).unwrap();
+        let mut table = Table::create(TableName::new("numbers").unwrap(), schema.clone());
+        for n in 1..=4 {
+            let row = Row::from_values(vec![Value::Int(n)], table.schema()).unwrap();
+            table.insert_checked(row).unwrap();
+        }
+
+        let doubled = table
+            .transform(schema, |row| match row.values()[0] {
+                Value::Int(n) if n % 2 == 0 => Some(vec![Value::Int(n * 2)]),
+                _ => None,
+            })
+            .unwrap();
+
+        let values: Vec<&Value> = doubled.rows().iter().map(|r| &r.values()[0]).collect();
+        assert_eq!(values, vec![&Value::Int(4), &Value::Int(8)]);
+        assert_eq!(table.rows().len(), 4);
+    }
+
+    #[test]
+    fn transform_rejects_rows_not_matching_new_schema() {
+        let col = Column::new(ColumnName::new("n").unwrap(), DataType::Int);
+        let schema = Schema::try_new(vec![col]).unwrap();
+        let mut table = Table::create(TableName::new("numbers").unwrap(), schema);
+        let row = Row::from_values(vec![Value::Int(1)], table.schema()).unwrap();
+        table.insert_checked(row).unwrap();
+
+        let text_schema = Schema::try_new(vec![Column::new(ColumnName::new("s").unwrap(), DataType::Text)]).unwrap();
+        let result = table.transform(text_schema, |row| Some(row.values().clone()));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn indexes_follow_row_changes() {
+        let schema = Schema::try_new(vec![
+            Column::new(ColumnName::new("a").unwrap(), DataType::Int),
+            Column::new(ColumnName::new("b").unwrap(), DataType::Int),
+        ])
+        .unwrap();
+        let row = |a: i64, b: i64| Row::from_values(vec![Value::Int(a), Value::Int(b)], &schema).unwrap();
+        let mut table = Table::create(TableName::new("t").unwrap(), schema.clone());
+        let rows = vec![row(1, 10), row(2, 20), row(3, 10)];
+        table.insert_many(rows).unwrap();
+        table.create_index("by_b", &ColumnName::new("b").unwrap(), IndexKind::BTree).unwrap();
+        assert!(table.create_index("by_b", &ColumnName::new("a").unwrap(), IndexKind::Hash).is_err());
+        assert_eq!(table.index("by_b").unwrap().lookup(&Value::Int(10)), &[0, 2]);
+
+        table.delete_where(|r| Ok(r.values()[0] == Value::Int(1))).unwrap();
+        assert_eq!(table.index("by_b").unwrap().lookup(&Value::Int(10)), &[1]);
+
+        table.update_where(&[(ColumnName::new("b").unwrap(), Value::Int(20))], |_| Ok(true)).unwrap();
+        assert_eq!(table.index("by_b").unwrap().lookup(&Value::Int(20)), &[0, 1]);
+
+        table.insert_checked(row(4, 30)).unwrap();
+        table.drop_column(&ColumnName::new("a").unwrap()).unwrap();
+        let index = table.index("by_b").unwrap();
+        assert_eq!((index.column(), index.lookup(&Value::Int(30))), (0, &[2][..]));
+    }
+
+    #[test]
+    fn conflicting_rows_are_ignored_replaced_or_merged() {
+        let schema = Schema::try_new(vec![
+            Column::new(ColumnName::new("id").unwrap(), DataType::Int),
+            Column::new(ColumnName::new("code").unwrap(), DataType::Text).with_unique(true),
+            Column::new(ColumnName::new("hits").unwrap(), DataType::Int),
+        ])
+        .unwrap()
+        .with_primary_key(&ColumnName::new("id").unwrap())
+        .unwrap();
+        let row = |id: i64, code: &str, hits: i64| {
+            Row::from_values(vec![Value::Int(id), Value::Text(code.to_string()), Value::Int(hits)], &schema).unwrap()
+        };
+        let hits = |table: &Table| table.rows().iter().map(|r| r.values()[2].clone()).collect::<Vec<_>>();
+        let mut table = Table::create(TableName::new("t").unwrap(), schema.clone());
+        table.insert_many(vec![row(1, "a", 1), row(2, "b", 1)]).unwrap();
+        table.create_index("by_code", &ColumnName::new("code").unwrap(), IndexKind::Hash).unwrap();
+
+        // Repeated keys are skipped, among the new rows too
+        assert_eq!(table.insert_or_ignore(vec![row(1, "z", 5), row(3, "c", 1), row(4, "c", 9)], None).unwrap(), 1);
+        assert_eq!(hits(&table), vec![Value::Int(1), Value::Int(1), Value::Int(1)]);
+        // Only conflicts on the given column are ignored
+        assert!(table.insert_or_ignore(vec![row(5, "a", 1)], Some(0)).is_err());
+
+        // A replaced row is deleted, even when it conflicts on another column
+        assert_eq!(table.insert_or_replace(vec![row(2, "a", 7), row(6, "f", 1), row(7, "f", 2)]).unwrap(), 2);
+        assert_eq!(table.rows().len(), 3);
+        assert_eq!(table.index("by_code").unwrap().lookup(&Value::Text("a".to_string())).len(), 1);
+        assert!(table.insert_checked(row(9, "f", 0)).is_err());
+
+        // A repeated key adds the new hits to the stored row
+        let add = |stored: &Row, new: &Row| {
+            let mut values = stored.values().clone();
+            if let (Value::Int(a), Value::Int(b)) = (&values[2], &new.values()[2]) {
+                values[2] = Value::Int(a + b);
+            }
+            Ok(values)
+        };
+        assert_eq!(table.upsert(vec![row(3, "c", 10), row(8, "h", 1)], 0, add).unwrap(), 2);
+        let by_id = |table: &Table, id: i64| table.rows().iter().find(|r| r.values()[0] == Value::Int(id)).unwrap().values()[2].clone();
+        assert_eq!((by_id(&table, 3), by_id(&table, 8)), (Value::Int(11), Value::Int(1)));
+
+        // A row cannot be changed twice by one statement
+        for twice in [vec![row(3, "c", 1), row(3, "c", 1)], vec![row(9, "i", 1), row(9, "i", 1)]] {
+            assert!(table.upsert(twice, 0, add).unwrap_err().message().contains("a second time"));
+        }
+        assert_eq!((by_id(&table, 3), table.rows().len()), (Value::Int(11), 4));
+
+        // A merge repeating another unique value changes nothing
+        let rename = |stored: &Row, _: &Row| Ok(vec![stored.values()[0].clone(), Value::Text("h".to_string()), Value::Int(0)]);
+        assert!(table.upsert(vec![row(3, "x", 0)], 0, rename).is_err());
+        assert_eq!(table.rows().len(), 4);
+        assert_eq!(table.index("by_code").unwrap().lookup(&Value::Text("c".to_string())).len(), 1);
+    }
 }
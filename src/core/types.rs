@@ -6,6 +6,13 @@
 //! - [`Value`]: runtime representation of stored values.
 //! - [`TableName`] and [`ColumnName`]: type-safe wrappers for names to prevent misuse.
 
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::hash::{Hash, Hasher};
+use std::str::FromStr;
+
+use crate::core::datetime;
+
 /// Schema-level type of a database column.
 ///
 /// A `DataType` is declarative: it specifies the kind of values allowed
@@ -24,12 +31,24 @@ pub enum DataType {
     Int,
     /// UTF-8 text string.
     Text,
+    /// 64-bit floating point number.
+    Float,
+    /// Boolean (`TRUE` / `FALSE`).
+    Bool,
+    /// Calendar date without a time of day.
+    Date,
+    /// Date and time of day, in UTC with one-second precision.
+    Timestamp,
+    /// Raw binary data.
+    Blob,
 }
 
 impl DataType {
     /// Checks if a runtime [`Value`] matches this schema [`DataType`].
     ///
-    /// Returns `true` if compatible, `false` otherwise.
+    /// Returns `true` if compatible, `false` otherwise. [`Value::Null`] never
+    /// matches a type on its own; whether a column accepts it depends on the
+    /// column's nullability (see [`Column::accepts`](crate::core::schema::Column::accepts)).
     ///
     /// # Examples
     /// ```
@@ -42,15 +61,213 @@ impl DataType {
     pub fn matches(&self, value: &Value) -> bool {
         matches!((self, value),
             (DataType::Int, Value::Int(_)) |
-            (DataType::Text, Value::Text(_))
+            (DataType::Text, Value::Text(_)) |
+            (DataType::Float, Value::Float(_)) |
+            (DataType::Bool, Value::Bool(_)) |
+            (DataType::Date, Value::Date(_)) |
+            (DataType::Timestamp, Value::Timestamp(_)) |
+            (DataType::Blob, Value::Blob(_))
         )
     }
 
+    /// Converts a value to this type where the conversion is lossless.
+    ///
+    /// `Int` widens to `Float`, `Date` widens to `Timestamp` (midnight), and
+    /// ISO-8601 text is read as a `Date` or `Timestamp` for columns of those
+    /// types. Every other value, including text that does not parse, is
+    /// returned unchanged.
+    ///
+    /// # Examples
+    /// ```
+    /// use mini_rust_sgbd::core::types::{DataType, Value};
+    ///
+    /// assert_eq!(DataType::Float.coerce(Value::Int(2)), Value::Float(2.0));
+    /// assert_eq!(DataType::Int.coerce(Value::Int(2)), Value::Int(2));
+    /// assert_eq!(DataType::Date.coerce(Value::Text("1970-01-03".to_string())), Value::Date(2));
+    /// ```
+    pub fn coerce(&self, value: Value) -> Value {
+        match (self, value) {
+            (DataType::Float, Value::Int(i)) => Value::Float(i as f64),
+            (DataType::Timestamp, Value::Date(d)) => Value::Timestamp(datetime::date_to_timestamp(d)),
+            (DataType::Date, Value::Text(s)) => match datetime::parse_date(&s) {
+                Ok(days) => Value::Date(days),
+                Err(_) => Value::Text(s),
+            },
+            (DataType::Timestamp, Value::Text(s)) => match datetime::parse_timestamp(&s) {
+                Ok(secs) => Value::Timestamp(secs),
+                Err(_) => Value::Text(s),
+            },
+            (_, value) => value,
+        }
+    }
+
+    /// Checks if every value of type `other` can be stored in a column of
+    /// this type: values of this type, and those [`DataType::coerce`] widens
+    /// to it. Text is not included, since not all of it reads as a date.
+    ///
+    /// # Examples
+    /// ```
+    /// use mini_rust_sgbd::core::types::DataType;
+    ///
+    /// assert!(DataType::Float.can_hold(&DataType::Int));
+    /// assert!(!DataType::Int.can_hold(&DataType::Float));
+    /// assert!(!DataType::Date.can_hold(&DataType::Text));
+    /// ```
+    pub fn can_hold(&self, other: &DataType) -> bool {
+        self == other || matches!((self, other), (DataType::Float, DataType::Int) | (DataType::Timestamp, DataType::Date))
+    }
+
+    /// Returns the value used to fill this type when no value is given,
+    /// e.g. when a column is added to a table that already has rows.
+    ///
+    /// # Examples
+    /// ```
+    /// use mini_rust_sgbd::core::types::{DataType, Value};
+    ///
+    /// assert_eq!(DataType::Int.default_value(), Value::Int(0));
+    /// assert_eq!(DataType::Text.default_value(), Value::Text(String::new()));
+    /// ```
+    pub fn default_value(&self) -> Value {
+        match self {
+            DataType::Int => Value::Int(0),
+            DataType::Text => Value::Text(String::new()),
+            DataType::Float => Value::Float(0.0),
+            DataType::Bool => Value::Bool(false),
+            DataType::Date => Value::Date(0),
+            DataType::Timestamp => Value::Timestamp(0),
+            DataType::Blob => Value::Blob(Vec::new()),
+        }
+    }
+
     /// Convenience constructor for `DataType::Int`.
     pub fn new_int() -> Self { DataType::Int }
 
     /// Convenience constructor for `DataType::Text`.
     pub fn new_text() -> Self { DataType::Text }
+
+    /// Convenience constructor for `DataType::Float`.
+    pub fn new_float() -> Self { DataType::Float }
+
+    /// Convenience constructor for `DataType::Bool`.
+    pub fn new_bool() -> Self { DataType::Bool }
+
+    /// Convenience constructor for `DataType::Date`.
+    pub fn new_date() -> Self { DataType::Date }
+
+    /// Convenience constructor for `DataType::Timestamp`.
+    pub fn new_timestamp() -> Self { DataType::Timestamp }
+
+    /// Convenience constructor for `DataType::Blob`.
+    pub fn new_blob() -> Self { DataType::Blob }
+}
+
+/// Dialect type names accepted by default as aliases of a canonical
+/// [`DataType`] (see [`TypeAliases`]).
+const BUILTIN_TYPE_ALIASES: &[(&str, DataType)] = &[
+    ("INTEGER", DataType::Int),
+    ("VARCHAR", DataType::Text),
+    ("STRING", DataType::Text),
+    ("REAL", DataType::Float),
+    ("DOUBLE", DataType::Float),
+    ("BOOLEAN", DataType::Bool),
+    ("DATETIME", DataType::Timestamp),
+    ("BYTEA", DataType::Blob),
+];
+
+/// Canonical type names, as written by [`DataType`]'s `Debug`.
+const CANONICAL_TYPES: &[(&str, DataType)] = &[
+    ("INT", DataType::Int),
+    ("TEXT", DataType::Text),
+    ("FLOAT", DataType::Float),
+    ("BOOL", DataType::Bool),
+    ("DATE", DataType::Date),
+    ("TIMESTAMP", DataType::Timestamp),
+    ("BLOB", DataType::Blob),
+];
+
+/// A registry of type names resolving to a canonical [`DataType`], used by
+/// the [`Parser`](crate::frontend::parser::Parser) to read column types.
+///
+/// It starts with the canonical names and a few aliases from other
+/// databases (`INTEGER`, `VARCHAR`, `DOUBLE`, ...); more can be
+/// [registered](TypeAliases::register). Names are matched ignoring case.
+///
+/// # Examples
+/// ```
+/// use mini_rust_sgbd::core::types::{DataType, TypeAliases};
+///
+/// let mut aliases = TypeAliases::default();
+/// assert!(aliases.resolve("NUMBER").is_err());
+/// aliases.register("number", DataType::Float).unwrap();
+/// assert_eq!(aliases.resolve("Number"), Ok(DataType::Float));
+/// assert_eq!(aliases.resolve("varchar"), Ok(DataType::Text));
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct TypeAliases {
+    aliases: BTreeMap<String, DataType>,
+}
+
+impl Default for TypeAliases {
+    fn default() -> Self {
+        let aliases = BUILTIN_TYPE_ALIASES.iter().map(|(name, dtype)| (name.to_string(), dtype.clone())).collect();
+        TypeAliases { aliases }
+    }
+}
+
+impl TypeAliases {
+    /// Makes `alias` name `dtype`, replacing what it named before.
+    ///
+    /// # Returns
+    /// `Ok(())`, or `Err(String)` if `alias` is not a valid identifier or
+    /// is a canonical type name.
+    pub fn register(&mut self, alias: &str, dtype: DataType) -> Result<(), String> {
+        validate_name("Type", alias)?;
+        let upper = alias.to_uppercase();
+        if CANONICAL_TYPES.iter().any(|(name, _)| *name == upper) {
+            return Err(format!("'{}' is a type, not an alias", alias));
+        }
+        self.aliases.insert(upper, dtype);
+        Ok(())
+    }
+
+    /// Returns the type named `name`, canonical (`Int`, `Text`, `Float`,
+    /// `Bool`, `Date`, `Timestamp`, `Blob`) or a registered alias.
+    pub fn resolve(&self, name: &str) -> Result<DataType, String> {
+        let upper = name.to_uppercase();
+        CANONICAL_TYPES
+            .iter()
+            .find(|(canonical, _)| *canonical == upper)
+            .map(|(_, dtype)| dtype)
+            .or_else(|| self.aliases.get(&upper))
+            .cloned()
+            .ok_or_else(|| format!("Unknown type '{}'", name))
+    }
+
+    /// Returns the aliases and the types they name, sorted by alias in
+    /// upper case.
+    pub fn aliases(&self) -> Vec<(&str, &DataType)> {
+        self.aliases.iter().map(|(alias, dtype)| (alias.as_str(), dtype)).collect()
+    }
+}
+
+impl FromStr for DataType {
+    type Err = String;
+
+    /// Parses a type name, either canonical (`Int`, `Text`, `Float`, `Bool`, `Date`,
+    /// `Timestamp`, `Blob`) or one of the default [`TypeAliases`], ignoring
+    /// case.
+    ///
+    /// # Examples
+    /// ```
+    /// use mini_rust_sgbd::core::types::DataType;
+    ///
+    /// assert_eq!("Int".parse::<DataType>(), Ok(DataType::Int));
+    /// assert_eq!("VARCHAR".parse::<DataType>(), Ok(DataType::Text));
+    /// assert!("Blobby".parse::<DataType>().is_err());
+    /// ```
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        TypeAliases::default().resolve(name)
+    }
 }
 
 /// Runtime value stored in the database.
@@ -63,13 +280,77 @@ impl DataType {
 ///
 /// let int_val = Value::Int(100);
 /// let txt_val = Value::Text("Alice".to_string());
+/// let float_val = Value::Float(3.14);
+/// let bool_val = Value::Bool(true);
+/// let date_val = Value::Date(19_723); // 2024-01-01
+/// let blob_val = Value::Blob(vec![0xDE, 0xAD]);
+/// let missing = Value::Null;
 /// ```
-#[derive(Debug, Clone, PartialEq)]
+///
+/// `Value` implements `Eq` and `Hash` so it can be used as a map key.
+/// For floats, all NaNs are equal to each other and `-0.0` equals `0.0`.
+#[derive(Debug, Clone)]
 pub enum Value {
     /// 64-bit signed integer.
     Int(i64),
     /// UTF-8 text string.
     Text(String),
+    /// 64-bit floating point number.
+    Float(f64),
+    /// Boolean value.
+    Bool(bool),
+    /// Date, as days since `1970-01-01`.
+    Date(i32),
+    /// Timestamp, as seconds since `1970-01-01 00:00:00` UTC.
+    Timestamp(i64),
+    /// Raw binary data.
+    Blob(Vec<u8>),
+    /// Absence of a value (SQL `NULL`).
+    Null,
+}
+
+impl PartialEq for Value {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => a == b,
+            (Value::Text(a), Value::Text(b)) => a == b,
+            (Value::Float(a), Value::Float(b)) => a == b || (a.is_nan() && b.is_nan()),
+            (Value::Bool(a), Value::Bool(b)) => a == b,
+            (Value::Date(a), Value::Date(b)) => a == b,
+            (Value::Timestamp(a), Value::Timestamp(b)) => a == b,
+            (Value::Blob(a), Value::Blob(b)) => a == b,
+            (Value::Null, Value::Null) => true,
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Value {}
+
+impl Hash for Value {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        std::mem::discriminant(self).hash(state);
+        match self {
+            Value::Int(i) => i.hash(state),
+            Value::Text(s) => s.hash(state),
+            Value::Float(f) => {
+                // Keep hashing consistent with equality: one NaN, one zero
+                let bits = if f.is_nan() {
+                    f64::NAN.to_bits()
+                } else if *f == 0.0 {
+                    0.0f64.to_bits()
+                } else {
+                    f.to_bits()
+                };
+                bits.hash(state);
+            }
+            Value::Bool(b) => b.hash(state),
+            Value::Date(d) => d.hash(state),
+            Value::Timestamp(t) => t.hash(state),
+            Value::Blob(b) => b.hash(state),
+            Value::Null => {}
+        }
+    }
 }
 
 impl Value {
@@ -78,6 +359,97 @@ impl Value {
 
     /// Creates a new text value.
     pub fn new_text(val: String) -> Self { Value::Text(val) }
+
+    /// Creates a new float value.
+    pub fn new_float(val: f64) -> Self { Value::Float(val) }
+
+    /// Creates a new boolean value.
+    pub fn new_bool(val: bool) -> Self { Value::Bool(val) }
+
+    /// Creates a new date value from days since the Unix epoch.
+    pub fn new_date(days: i32) -> Self { Value::Date(days) }
+
+    /// Creates a new timestamp value from seconds since the Unix epoch.
+    pub fn new_timestamp(seconds: i64) -> Self { Value::Timestamp(seconds) }
+
+    /// Creates a new binary value.
+    pub fn new_blob(bytes: Vec<u8>) -> Self { Value::Blob(bytes) }
+
+    /// Returns `true` if the value is [`Value::Null`].
+    pub fn is_null(&self) -> bool { matches!(self, Value::Null) }
+
+    /// Returns the type of the value, or `None` for [`Value::Null`].
+    pub fn data_type(&self) -> Option<DataType> {
+        match self {
+            Value::Int(_) => Some(DataType::Int),
+            Value::Text(_) => Some(DataType::Text),
+            Value::Float(_) => Some(DataType::Float),
+            Value::Bool(_) => Some(DataType::Bool),
+            Value::Date(_) => Some(DataType::Date),
+            Value::Timestamp(_) => Some(DataType::Timestamp),
+            Value::Blob(_) => Some(DataType::Blob),
+            Value::Null => None,
+        }
+    }
+
+    /// Compares two values of the same type.
+    ///
+    /// Numbers compare numerically (an `Int` and a `Float` can be compared
+    /// with each other), text compares lexicographically, `FALSE` sorts
+    /// before `TRUE`, and dates and timestamps compare chronologically (a
+    /// date counts as midnight when compared with a timestamp). Blobs compare
+    /// byte by byte. Returns `None`
+    /// when the values have incompatible types or either one is `NULL`.
+    ///
+    /// # Examples
+    /// ```
+    /// use std::cmp::Ordering;
+    /// use mini_rust_sgbd::core::types::Value;
+    ///
+    /// assert_eq!(Value::Int(1).compare(&Value::Int(2)), Some(Ordering::Less));
+    /// assert_eq!(Value::Int(2).compare(&Value::Float(1.5)), Some(Ordering::Greater));
+    /// assert_eq!(Value::Int(1).compare(&Value::Text("1".to_string())), None);
+    /// ```
+    pub fn compare(&self, other: &Value) -> Option<Ordering> {
+        match (self, other) {
+            (Value::Int(a), Value::Int(b)) => Some(a.cmp(b)),
+            (Value::Text(a), Value::Text(b)) => Some(a.cmp(b)),
+            (Value::Float(a), Value::Float(b)) => Some(a.total_cmp(b)),
+            (Value::Int(a), Value::Float(b)) => Some((*a as f64).total_cmp(b)),
+            (Value::Float(a), Value::Int(b)) => Some(a.total_cmp(&(*b as f64))),
+            (Value::Bool(a), Value::Bool(b)) => Some(a.cmp(b)),
+            (Value::Date(a), Value::Date(b)) => Some(a.cmp(b)),
+            (Value::Timestamp(a), Value::Timestamp(b)) => Some(a.cmp(b)),
+            (Value::Date(a), Value::Timestamp(b)) => Some(datetime::date_to_timestamp(*a).cmp(b)),
+            (Value::Timestamp(a), Value::Date(b)) => Some(a.cmp(&datetime::date_to_timestamp(*b))),
+            (Value::Blob(a), Value::Blob(b)) => Some(a.cmp(b)),
+            _ => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Value {
+    /// Formats the value the way it is shown to users: text without quotes,
+    /// dates and timestamps in ISO-8601, blobs as hex literals and `NULL`
+    /// in upper case.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Int(i) => write!(f, "{}", i),
+            Value::Text(s) => write!(f, "{}", s),
+            Value::Float(x) => write!(f, "{}", x),
+            Value::Bool(b) => write!(f, "{}", if *b { "TRUE" } else { "FALSE" }),
+            Value::Date(d) => write!(f, "{}", datetime::format_date(*d)),
+            Value::Timestamp(t) => write!(f, "{}", datetime::format_timestamp(*t)),
+            Value::Blob(bytes) => {
+                write!(f, "X'")?;
+                for b in bytes {
+                    write!(f, "{:02X}", b)?;
+                }
+                write!(f, "'")
+            }
+            Value::Null => write!(f, "NULL"),
+        }
+    }
 }
 
 /// Type-safe wrapper for table names.
@@ -87,6 +459,9 @@ impl Value {
 /// - Start with a letter
 /// - Contain only ASCII letters, digits, or underscores
 /// - Not contain spaces
+///
+/// A name may also be qualified by a schema, as in
+/// `information_schema.tables` (see [`TableName::qualified`]).
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct TableName(String);
 
@@ -101,6 +476,41 @@ impl TableName {
         Ok(Self(name.to_string()))
     }
 
+    /// Creates the name `schema.name` of a table in another schema, if
+    /// both parts are valid names.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error string if either part is invalid.
+    pub fn qualified(schema: &str, name: &str) -> Result<Self, String> {
+        validate_name("Schema", schema)?;
+        validate_name("Table", name)?;
+        Ok(Self(format!("{}.{}", schema, name)))
+    }
+
+    /// Creates a `TableName` from `table` or `schema.table`, as written by
+    /// [`TableName::as_str`].
+    ///
+    /// # Errors
+    ///
+    /// Returns an error string if either part is invalid.
+    pub fn parse(name: &str) -> Result<Self, String> {
+        match name.split_once('.') {
+            Some((schema, name)) => Self::qualified(schema, name),
+            None => Self::new(name),
+        }
+    }
+
+    /// Returns the schema part of a qualified name, if there is one.
+    pub fn schema(&self) -> Option<&str> {
+        self.0.split_once('.').map(|(schema, _)| schema)
+    }
+
+    /// Returns the name without its schema part.
+    pub fn table(&self) -> &str {
+        self.0.split_once('.').map_or(&self.0, |(_, name)| name)
+    }
+
     /// Returns the table name as a string slice.
     pub fn as_str(&self) -> &str { &self.0 }
 }
@@ -130,6 +540,16 @@ impl ColumnName {
     pub fn as_str(&self) -> &str { &self.0 }
 }
 
+/// Checks that `name` can name a schema, with the same rules as table
+/// names.
+///
+/// # Errors
+///
+/// Returns an error string if the name is invalid.
+pub fn validate_schema_name(name: &str) -> Result<(), String> {
+    validate_name("Schema", name)
+}
+
 /// Validates a table or column name.
 ///
 /// Rules:
@@ -167,6 +587,22 @@ mod tests {
         assert!(TableName::new("1users").is_err());
         assert!(TableName::new("user name").is_err());
         assert!(TableName::new("user!").is_err());
+        assert!(TableName::new("a.b").is_err());
+
+        let qualified = TableName::parse("sales.orders").unwrap();
+        assert_eq!((qualified.schema(), qualified.table()), (Some("sales"), "orders"));
+        assert_eq!(TableName::parse("orders").unwrap().table(), "orders");
+        assert!(TableName::parse("sales.").is_err());
+        assert!(TableName::parse("a.b.c").is_err());
+    }
+
+    #[test]
+    fn qualified_table_name() {
+        let name = TableName::qualified("information_schema", "tables").unwrap();
+        assert_eq!(name.as_str(), "information_schema.tables");
+        assert_eq!(name.schema(), Some("information_schema"));
+        assert_eq!(TableName::new("users").unwrap().schema(), None);
+        assert!(TableName::qualified("1s", "t").is_err());
     }
 
     #[test]
@@ -184,5 +620,119 @@ mod tests {
         assert!(!DataType::Int.matches(&Value::Text("foo".to_string())));
         assert!(DataType::Text.matches(&Value::Text("foo".to_string())));
         assert!(!DataType::Text.matches(&Value::Int(0)));
+        assert!(!DataType::Int.matches(&Value::Null));
+    }
+
+    #[test]
+    fn value_compare_same_type_only() {
+        assert_eq!(Value::Int(3).compare(&Value::Int(3)), Some(Ordering::Equal));
+        assert_eq!(Value::Text("b".to_string()).compare(&Value::Text("a".to_string())), Some(Ordering::Greater));
+        assert_eq!(Value::Text("1".to_string()).compare(&Value::Int(1)), None);
+        assert_eq!(Value::Null.compare(&Value::Null), None);
+    }
+
+    #[test]
+    fn float_compare_and_coercion() {
+        assert_eq!(Value::Float(1.5).compare(&Value::Float(2.5)), Some(Ordering::Less));
+        assert_eq!(Value::Int(2).compare(&Value::Float(2.0)), Some(Ordering::Equal));
+        assert_eq!(Value::Float(2.5).compare(&Value::Int(2)), Some(Ordering::Greater));
+        assert!(DataType::Float.matches(&Value::Float(1.0)));
+        assert!(!DataType::Float.matches(&Value::Int(1)));
+        assert_eq!(DataType::Float.coerce(Value::Int(3)), Value::Float(3.0));
+        assert_eq!(DataType::Int.coerce(Value::Float(3.0)), Value::Float(3.0));
+    }
+
+    #[test]
+    fn float_equality_and_hash_are_consistent() {
+        use std::collections::hash_map::DefaultHasher;
+
+        let hash = |v: &Value| {
+            let mut h = DefaultHasher::new();
+            v.hash(&mut h);
+            h.finish()
+        };
+        assert_eq!(Value::Float(f64::NAN), Value::Float(f64::NAN));
+        assert_eq!(hash(&Value::Float(f64::NAN)), hash(&Value::Float(-f64::NAN)));
+        assert_eq!(Value::Float(0.0), Value::Float(-0.0));
+        assert_eq!(hash(&Value::Float(0.0)), hash(&Value::Float(-0.0)));
+        assert_ne!(Value::Float(1.0), Value::Int(1));
+    }
+
+    #[test]
+    fn bool_matches_and_compares() {
+        assert!(DataType::Bool.matches(&Value::Bool(true)));
+        assert!(!DataType::Bool.matches(&Value::Int(1)));
+        assert_eq!(Value::Bool(false).compare(&Value::Bool(true)), Some(Ordering::Less));
+        assert_eq!(Value::Bool(true).compare(&Value::Int(1)), None);
+        assert_ne!(Value::Bool(true), Value::Int(1));
+        assert_eq!(DataType::Bool.default_value(), Value::Bool(false));
+    }
+
+    #[test]
+    fn date_and_timestamp_compare_and_coerce() {
+        let day = Value::Date(1);
+        assert_eq!(day.compare(&Value::Date(2)), Some(Ordering::Less));
+        assert_eq!(day.compare(&Value::Timestamp(86_400)), Some(Ordering::Equal));
+        assert_eq!(Value::Timestamp(86_401).compare(&day), Some(Ordering::Greater));
+        assert_eq!(day.compare(&Value::Int(1)), None);
+        assert_eq!(DataType::Timestamp.coerce(Value::Date(1)), Value::Timestamp(86_400));
+        assert_eq!(
+            DataType::Timestamp.coerce(Value::Text("1970-01-01 00:00:10".to_string())),
+            Value::Timestamp(10)
+        );
+        assert_eq!(DataType::Date.coerce(Value::Text("soon".to_string())), Value::Text("soon".to_string()));
+    }
+
+    #[test]
+    fn blob_matches_and_compares_bytewise() {
+        let blob = Value::Blob(vec![0xDE, 0xAD]);
+        assert!(DataType::Blob.matches(&blob));
+        assert!(!DataType::Blob.matches(&Value::Text("DEAD".to_string())));
+        assert_eq!(blob.compare(&Value::Blob(vec![0xDE, 0xAD, 0x00])), Some(Ordering::Less));
+        assert_eq!(blob.compare(&Value::Blob(vec![0x01])), Some(Ordering::Greater));
+        assert_eq!(blob.compare(&Value::Text("DEAD".to_string())), None);
+    }
+
+    #[test]
+    fn value_display_is_user_facing() {
+        assert_eq!(Value::Int(-3).to_string(), "-3");
+        assert_eq!(Value::Text("hi".to_string()).to_string(), "hi");
+        assert_eq!(Value::Float(1.5).to_string(), "1.5");
+        assert_eq!(Value::Bool(true).to_string(), "TRUE");
+        assert_eq!(Value::Date(0).to_string(), "1970-01-01");
+        assert_eq!(Value::Timestamp(61).to_string(), "1970-01-01 00:01:01");
+        assert_eq!(Value::Blob(vec![0x0A, 0xFF]).to_string(), "X'0AFF'");
+        assert_eq!(Value::Null.to_string(), "NULL");
+    }
+
+    #[test]
+    fn datatype_from_str_resolves_aliases() {
+        assert_eq!("Int".parse::<DataType>(), Ok(DataType::Int));
+        assert_eq!("INTEGER".parse::<DataType>(), Ok(DataType::Int));
+        assert_eq!("varchar".parse::<DataType>(), Ok(DataType::Text));
+        assert_eq!("STRING".parse::<DataType>(), Ok(DataType::Text));
+        assert_eq!("Float".parse::<DataType>(), Ok(DataType::Float));
+        assert_eq!("DOUBLE".parse::<DataType>(), Ok(DataType::Float));
+        assert_eq!("bool".parse::<DataType>(), Ok(DataType::Bool));
+        assert_eq!("BOOLEAN".parse::<DataType>(), Ok(DataType::Bool));
+        assert_eq!("date".parse::<DataType>(), Ok(DataType::Date));
+        assert_eq!("DATETIME".parse::<DataType>(), Ok(DataType::Timestamp));
+        assert_eq!("blob".parse::<DataType>(), Ok(DataType::Blob));
+        assert_eq!("BYTEA".parse::<DataType>(), Ok(DataType::Blob));
+        assert!("DOUBLE_TROUBLE".parse::<DataType>().is_err());
+    }
+
+    #[test]
+    fn type_aliases_can_be_registered() {
+        let mut aliases = TypeAliases::default();
+        aliases.register("int4", DataType::Int).unwrap();
+        aliases.register("STRING", DataType::Blob).unwrap();
+        assert_eq!(aliases.resolve("INT4"), Ok(DataType::Int));
+        assert_eq!(aliases.resolve("string"), Ok(DataType::Blob));
+        assert!(aliases.register("Text", DataType::Int).is_err());
+        assert!(aliases.register("big int", DataType::Int).is_err());
+        assert_eq!(aliases.resolve("text"), Ok(DataType::Text));
+        // The default registry is left as it was
+        assert_eq!("STRING".parse::<DataType>(), Ok(DataType::Text));
     }
 }
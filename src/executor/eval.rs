@@ -0,0 +1,310 @@
+//! Expression evaluation for the executor.
+//!
+//! Expressions and conditions arrive resolved by the
+//! [`planner`](crate::planner::planner): a column is a position in the row
+//! being evaluated. [`eval_scalar`] computes the value of a [`ScalarExpr`]
+//! for such a row and [`eval_predicate`] the truth of a [`Predicate`].
+
+use std::cmp::Ordering;
+
+use crate::core::datetime;
+use crate::core::error::{SqlError, SqlResult};
+use crate::core::types::Value;
+use crate::frontend::ast::{BinaryOp, CompareOp, Expr};
+use crate::planner::logical::{BoundColumn, Predicate, ScalarExpr};
+use crate::planner::planner::{plan_expr, Scope};
+
+/// Evaluates an expression against one row.
+///
+/// Arithmetic on two `Int`s stays integral (division truncates); mixing in a
+/// `Float` gives a `Float`. `||` concatenates the text form of both operands.
+/// Any `NULL` operand makes the result `NULL`. Overflow and division by zero
+/// are errors.
+///
+/// Supported functions:
+/// - `NOW()`: the current time as a [`Value::Timestamp`]
+pub fn eval_scalar(expr: &ScalarExpr, values: &[Value]) -> SqlResult<Value> {
+    match expr {
+        ScalarExpr::Literal(value) => Ok(value.clone()),
+        ScalarExpr::Column(idx) => Ok(values[*idx].clone()),
+        ScalarExpr::Now => Ok(Value::Timestamp(datetime::now())),
+        ScalarExpr::Negate(inner) => match eval_scalar(inner, values)? {
+            Value::Int(n) => n
+                .checked_neg()
+                .map(Value::Int)
+                .ok_or_else(|| SqlError::new_execution("Integer overflow in negation")),
+            Value::Float(f) => Ok(Value::Float(-f)),
+            Value::Null => Ok(Value::Null),
+            other => Err(SqlError::new_execution(&format!("Cannot negate {:?}", other))),
+        },
+        ScalarExpr::Binary { op, left, right } => {
+            let left = eval_scalar(left, values)?;
+            let right = eval_scalar(right, values)?;
+            eval_binary(*op, left, right)
+        }
+    }
+}
+
+/// Evaluates an expression that does not depend on a row, such as an
+/// INSERT value or an UPDATE assignment.
+pub fn eval_const(expr: &Expr) -> SqlResult<Value> {
+    eval_scalar(&plan_expr(expr, &Scope::default())?, &[])
+}
+
+/// Evaluates a WHERE (or ON) condition against one row; the row matches
+/// only when the condition is definitely true.
+pub fn eval_where(predicate: &Predicate, values: &[Value]) -> SqlResult<bool> {
+    Ok(eval_predicate(predicate, values)? == Some(true))
+}
+
+/// Evaluates a condition with SQL's three-valued logic, where `None` stands
+/// for UNKNOWN.
+///
+/// Comparisons involving `NULL` (on either side) are UNKNOWN, and so is
+/// their negation; use `IS [NOT] NULL` to test for missing values.
+/// `AND`/`OR` skip their right operand once the result is decided.
+pub fn eval_predicate(predicate: &Predicate, values: &[Value]) -> SqlResult<Option<bool>> {
+    match predicate {
+        Predicate::IsNull { column, negated } => Ok(Some(values[column.index].is_null() != *negated)),
+        Predicate::Compare { column, op, value } => {
+            Ok(compare_column(column, value, values)?.map(|ordering| op_matches(*op, ordering)))
+        }
+        Predicate::In { column, values: list, negated } => {
+            // TRUE on any match; otherwise UNKNOWN if a NULL was involved
+            let mut result = Some(false);
+            for value in list {
+                match compare_column(column, value, values)? {
+                    Some(Ordering::Equal) => {
+                        result = Some(true);
+                        break;
+                    }
+                    Some(_) => {}
+                    None => result = None,
+                }
+            }
+            Ok(result.map(|b| b != *negated))
+        }
+        Predicate::Between { column, low, high, negated } => {
+            let above = compare_column(column, low, values)?.map(|o| o != Ordering::Less);
+            let below = compare_column(column, high, values)?.map(|o| o != Ordering::Greater);
+            let result = match (above, below) {
+                (Some(false), _) | (_, Some(false)) => Some(false),
+                (above, below) => above.and(below),
+            };
+            Ok(result.map(|b| b != *negated))
+        }
+        Predicate::Like { column, pattern, negated } => match (&values[column.index], eval_scalar(pattern, values)?) {
+            (Value::Null, _) | (_, Value::Null) => Ok(None),
+            (Value::Text(text), Value::Text(pattern)) => Ok(Some(like_matches(text, &pattern) != *negated)),
+            (_, other) => Err(SqlError::new_execution(&format!("LIKE pattern must be text, got {:?}", other))),
+        },
+        Predicate::And(left, right) => match eval_predicate(left, values)? {
+            Some(false) => Ok(Some(false)),
+            left => match eval_predicate(right, values)? {
+                Some(false) => Ok(Some(false)),
+                right => Ok(left.and(right)),
+            },
+        },
+        Predicate::Or(left, right) => match eval_predicate(left, values)? {
+            Some(true) => Ok(Some(true)),
+            left => match eval_predicate(right, values)? {
+                Some(true) => Ok(Some(true)),
+                right => Ok(left.and(right)),
+            },
+        },
+        Predicate::Not(inner) => Ok(eval_predicate(inner, values)?.map(|b| !b)),
+    }
+}
+
+/// Compares a column of the row with a value, or returns `None` when
+/// either side is `NULL`.
+///
+/// The value is first coerced to the column type; a value of another type
+/// is an error.
+fn compare_column(column: &BoundColumn, value: &ScalarExpr, values: &[Value]) -> SqlResult<Option<Ordering>> {
+    let cell = &values[column.index];
+    let value = column.dtype.coerce(eval_scalar(value, values)?);
+    if cell.is_null() || value.is_null() {
+        return Ok(None);
+    }
+
+    cell.compare(&value).map(Some).ok_or_else(|| {
+        SqlError::new_execution(&format!(
+            "Type mismatch in WHERE: column '{}' is {:?}, got {:?}",
+            column.name, column.dtype, value
+        ))
+    })
+}
+
+/// Returns whether `ordering` (of left versus right operand) satisfies `op`.
+pub fn op_matches(op: CompareOp, ordering: Ordering) -> bool {
+    match op {
+        CompareOp::Eq => ordering == Ordering::Equal,
+        CompareOp::NotEq => ordering != Ordering::Equal,
+        CompareOp::Lt => ordering == Ordering::Less,
+        CompareOp::Gt => ordering == Ordering::Greater,
+        CompareOp::LtEq => ordering != Ordering::Greater,
+        CompareOp::GtEq => ordering != Ordering::Less,
+    }
+}
+
+fn eval_binary(op: BinaryOp, left: Value, right: Value) -> SqlResult<Value> {
+    if left.is_null() || right.is_null() {
+        return Ok(Value::Null);
+    }
+    if op == BinaryOp::Concat {
+        return Ok(Value::Text(format!("{}{}", left, right)));
+    }
+
+    let divides = matches!(op, BinaryOp::Div | BinaryOp::Mod);
+    if divides && as_float(&left).is_some() && as_float(&right) == Some(0.0) {
+        return Err(SqlError::new_execution("Division by zero"));
+    }
+
+    let overflow = || SqlError::new_execution(&format!("Integer overflow in {}", op.symbol()));
+    match (&left, &right) {
+        (Value::Int(a), Value::Int(b)) => match op {
+            BinaryOp::Add => a.checked_add(*b),
+            BinaryOp::Sub => a.checked_sub(*b),
+            BinaryOp::Mul => a.checked_mul(*b),
+            BinaryOp::Div => a.checked_div(*b),
+            BinaryOp::Mod => a.checked_rem(*b),
+            BinaryOp::Concat => unreachable!("handled above"),
+        }
+        .map(Value::Int)
+        .ok_or_else(overflow),
+        _ => match (as_float(&left), as_float(&right)) {
+            (Some(a), Some(b)) => Ok(Value::Float(match op {
+                BinaryOp::Add => a + b,
+                BinaryOp::Sub => a - b,
+                BinaryOp::Mul => a * b,
+                BinaryOp::Div => a / b,
+                BinaryOp::Mod => a % b,
+                BinaryOp::Concat => unreachable!("handled above"),
+            })),
+            _ => Err(SqlError::new_execution(&format!(
+                "Cannot apply '{}' to {:?} and {:?}",
+                op.symbol(),
+                left,
+                right
+            ))),
+        },
+    }
+}
+
+/// Matches `text` against a LIKE `pattern`, where `%` stands for any run of
+/// characters (including none) and `_` for exactly one. Matching is
+/// case-sensitive.
+pub fn like_matches(text: &str, pattern: &str) -> bool {
+    let text: Vec<char> = text.chars().collect();
+    let pattern: Vec<char> = pattern.chars().collect();
+    let (mut t, mut p) = (0, 0);
+    // Position of the last `%` seen and the text position it was tried at
+    let mut backtrack: Option<(usize, usize)> = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('%') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '_' || c == text[t] => {
+                t += 1;
+                p += 1;
+            }
+            _ => match backtrack {
+                // Let the last `%` swallow one more character and retry
+                Some((star, start)) => {
+                    backtrack = Some((star, start + 1));
+                    p = star + 1;
+                    t = start + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '%')
+}
+
+/// Numeric value of an `Int` or `Float`.
+fn as_float(value: &Value) -> Option<f64> {
+    match value {
+        Value::Int(n) => Some(*n as f64),
+        Value::Float(f) => Some(*f),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::schema::{Column, Schema};
+    use crate::core::types::{ColumnName, DataType, TableName};
+    use crate::planner::planner::expr_type;
+
+    fn binary(op: BinaryOp, left: Expr, right: Expr) -> Expr {
+        Expr::Binary { op, left: Box::new(left), right: Box::new(right) }
+    }
+
+    fn scope() -> Scope {
+        let mut scope = Scope::default();
+        let schema = Schema::try_new(vec![
+            Column::new(ColumnName::new("n").unwrap(), DataType::Int),
+            Column::new(ColumnName::new("name").unwrap(), DataType::Text),
+        ])
+        .unwrap();
+        scope.push(&TableName::new("t").unwrap(), &schema).unwrap();
+        scope
+    }
+
+    #[test]
+    fn arithmetic_follows_operand_types() {
+        let int = |n| Expr::Literal(Value::Int(n));
+        assert_eq!(eval_const(&binary(BinaryOp::Div, int(7), int(2))).unwrap(), Value::Int(3));
+        assert_eq!(eval_const(&binary(BinaryOp::Mod, int(7), int(2))).unwrap(), Value::Int(1));
+        assert_eq!(
+            eval_const(&binary(BinaryOp::Mul, int(3), Expr::Literal(Value::Float(0.5)))).unwrap(),
+            Value::Float(1.5)
+        );
+        assert_eq!(eval_const(&binary(BinaryOp::Add, int(1), Value::Null.into())).unwrap(), Value::Null);
+        assert_eq!(eval_const(&Expr::Negate(Box::new(int(4)))).unwrap(), Value::Int(-4));
+
+        assert!(eval_const(&binary(BinaryOp::Div, int(1), int(0))).is_err());
+        assert!(eval_const(&binary(BinaryOp::Add, int(i64::MAX), int(1))).is_err());
+        assert!(eval_const(&binary(BinaryOp::Sub, int(1), Value::Text("x".into()).into())).is_err());
+    }
+
+    #[test]
+    fn expressions_read_columns_of_the_row() {
+        let scope = scope();
+        let row = [Value::Int(21), Value::Text("Ann".into())];
+        let n = Expr::Column(ColumnName::new("n").unwrap().into());
+        let name = Expr::Column(ColumnName::new("name").unwrap().into());
+
+        let double = binary(BinaryOp::Mul, n.clone(), Value::Int(2).into());
+        assert_eq!(eval_scalar(&plan_expr(&double, &scope).unwrap(), &row).unwrap(), Value::Int(42));
+        assert_eq!(expr_type(&double, &scope).unwrap(), Some(DataType::Int));
+
+        let greeting = binary(BinaryOp::Concat, name.clone(), Value::Text("!".into()).into());
+        assert_eq!(eval_scalar(&plan_expr(&greeting, &scope).unwrap(), &row).unwrap(), Value::Text("Ann!".into()));
+        assert_eq!(expr_type(&greeting, &scope).unwrap(), Some(DataType::Text));
+
+        assert!(expr_type(&binary(BinaryOp::Add, name, n), &scope).is_err());
+        assert!(eval_const(&Expr::Column(ColumnName::new("n").unwrap().into())).is_err());
+    }
+
+    #[test]
+    fn like_patterns_match_wildcards() {
+        assert!(like_matches("Alice", "A%"));
+        assert!(like_matches("Alice", "%li%"));
+        assert!(like_matches("Alice", "_lic_"));
+        assert!(like_matches("", "%"));
+        assert!(like_matches("a%b", "a%b"));
+        assert!(like_matches("abcbd", "a%b_"));
+
+        assert!(!like_matches("Alice", "a%"));
+        assert!(!like_matches("Alice", "_lic"));
+        assert!(!like_matches("Al", "A__"));
+        assert!(!like_matches("", "_"));
+    }
+}
@@ -1,45 +1,55 @@
 //! Executor: applies parsed AST nodes onto the database core.
 //!
 //! Responsibilities:
-//! - Translate AST commands into calls on the [`Database`] and [`Table`] APIs
+//! - Translate AST commands into calls on the [`Database`] and
+//!   [`Table`](crate::core::table::Table) APIs
 //! - Enforce basic semantic checks before delegating to the core
-//! - Return an [`Output`] for SELECT queries
-//!
-//! Expected Core API (to be provided elsewhere):
-//! ```ignore
-//! pub struct Database { /* holds tables */ }
-//! pub struct Table { /* holds schema + rows */ }
-//! pub struct Row { pub values: Vec<Value> }
-//!
-//! impl Database {
-//!     pub fn create_table(&mut self, name: &str, cols: Vec<Column>) -> Result<(), SqlError>;
-//!     pub fn get_table_mut(&mut self, name: &str) -> Option<&mut Table>;
-//!     pub fn get_table(&self, name: &str) -> Option<&Table>;
-//! }
-//!
-//! impl Table {
-//!     pub fn insert_checked(&mut self, values: Vec<Value>) -> Result<(), SqlError>;
-//!     pub fn rows(&self) -> &[Row];
-//! }
-//! ```
+//! - Return an [`Output`] for SELECT queries, whose
+//!   [`LogicalPlan`] from the [`Planner`] runs as a pipeline of
+//!   [`operator`](crate::executor::operator)s
+
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+use std::path::Path;
 
+use crate::core::db::Database;
+use crate::core::error::{SqlError, SqlResult};
+use crate::core::row::Row;
+use crate::core::schema::{Column, Schema};
+use crate::core::table::Table;
+use crate::core::types::{ColumnName, DataType, TableName, Value};
+use crate::executor::eval::{eval_const, eval_scalar, eval_where, op_matches};
+use crate::executor::information_schema;
+use crate::executor::operator::{
+    BoxedOperator, Distinct, Filter, Limit, NestedLoopJoin, Project, RowPredicate, Scan, Sort, Tuple, Values,
+};
+use crate::executor::plan::Access;
 use crate::frontend::ast::*;
-use crate::frontend::token::SqlError;
+use crate::planner::logical::{GroupItem, Having, LogicalPlan, Predicate};
+use crate::planner::optimizer::optimize;
+use crate::planner::planner::{expr_type, plan_expr, plan_predicate, source_schema, Planner, Scope};
+use crate::storage::csv::{field_to_value, value_to_field, write_record, CsvReader};
+use crate::storage::recovery::{recover, DirLock};
 
 /// Output returned by the executor.
 ///
-/// - For `CREATE TABLE` and `INSERT`: typically just confirmation.
-/// - For `SELECT`: rows of values.
+/// - For `CREATE TABLE`, `INSERT` and transaction control: typically just confirmation.
+/// - For `UPDATE`, `DELETE` and `TRUNCATE`: the number of affected rows.
+/// - For `SELECT`, `SHOW TABLES` and `DESCRIBE`: rows of values, and the columns they hold.
+/// - For `COPY`: the number of rows copied and the lines that failed.
+/// - For `EXPLAIN`: one line per step of the query plan.
 #[derive(Debug, Clone, PartialEq)]
 pub enum Output {
-    None,           // e.g. CREATE or INSERT
-    Rows(Vec<Row>), // SELECT results
-}
-
-/// Simplified row type (in Core this would be more detailed).
-#[derive(Debug, Clone, PartialEq)]
-pub struct Row {
-    pub values: Vec<Value>,
+    None,            // e.g. CREATE or INSERT
+    Affected(usize), // UPDATE, DELETE, TRUNCATE, INSERT ... SELECT or ON CONFLICT
+    /// SELECT results, with the name and type of each of their columns
+    Rows { columns: Vec<Column>, rows: Vec<Row> },
+    /// COPY results; `failed` holds `(line, error)` for each rejected record
+    Copied { rows: usize, failed: Vec<(usize, String)> },
+    /// EXPLAIN results
+    Plan(Vec<String>),
 }
 
 /// Executor translates AST into Core calls.
@@ -47,245 +57,1997 @@ pub struct Executor;
 
 impl Executor {
     /// Main entry point: execute one AST node on the database.
-    pub fn execute(ast: ASTNode, db: &mut Database) -> Result<Output, SqlError> {
+    pub fn execute(ast: ASTNode, db: &mut Database) -> SqlResult<Output> {
         match ast {
             ASTNode::CreateTable(stmt) => Self::exec_create(stmt, db),
+            ASTNode::CreateSchema(name) => Self::exec_create_schema(&name, db),
+            ASTNode::CreateView(stmt) => Self::exec_create_view(stmt, db),
+            ASTNode::CreateIndex(stmt) => Self::exec_create_index(stmt, db),
             ASTNode::Insert(stmt) => Self::exec_insert(stmt, db),
+            ASTNode::Update(stmt) => Self::exec_update(stmt, db),
+            ASTNode::Delete(stmt) => Self::exec_delete(stmt, db),
+            ASTNode::Truncate(name) => Self::exec_truncate(&name, db),
+            ASTNode::AlterTable(stmt) => Self::exec_alter(stmt, db),
+            ASTNode::Transaction(stmt) => Self::exec_transaction(stmt, db),
+            ASTNode::Copy(CopyStmt::From { table, path }) => Self::exec_copy_from(&table, &path, db),
+            ASTNode::Analyze(name) => Self::exec_analyze(&name, db),
+            ASTNode::Attach { path, alias } => Self::exec_attach(&path, &alias, db),
+            ASTNode::Detach(alias) => Self::exec_detach(&alias, db),
+            // Run by the frontends, which know where the database is stored
+            ASTNode::Checkpoint => Err(SqlError::new_execution("CHECKPOINT needs a database stored in a directory")),
+            ast => Self::execute_read(ast, db),
+        }
+    }
+
+    /// Executes a statement that only reads the database, for callers
+    /// holding a shared reference, such as a read lock of a
+    /// [`SharedDatabase`](crate::executor::shared::SharedDatabase).
+    ///
+    /// # Returns
+    /// The output of the statement, or an error if it needs write access
+    /// (see [`reads_only`](Self::reads_only)).
+    pub fn execute_read(ast: ASTNode, db: &Database) -> SqlResult<Output> {
+        match ast {
             ASTNode::Select(stmt) => Self::exec_select(stmt, db),
+            ASTNode::Copy(CopyStmt::To { query, path }) => Self::exec_copy_to(&query, &path, db),
+            ASTNode::Explain(stmt) => Self::exec_explain(&stmt, db),
+            ASTNode::ShowTables => Ok(Self::exec_show_tables(db)),
+            ASTNode::Describe(name) => Self::exec_describe(&name, db),
+            // Settings of the store, run by the frontends that have one
+            ASTNode::Pragma(stmt) => {
+                Err(SqlError::new_execution(&format!("PRAGMA {} needs a database stored in a directory", stmt.name)))
+            }
+            _ => Err(SqlError::new_execution("Statement needs write access to the database")),
+        }
+    }
+
+    /// Returns `true` if `ast` can run with [`execute_read`](Self::execute_read).
+    ///
+    /// Unlike [`ASTNode::is_read_only`], `ANALYZE`, `ATTACH` and `DETACH`
+    /// are excluded: they change no data, but change the catalog.
+    pub fn reads_only(ast: &ASTNode) -> bool {
+        ast.is_read_only() && !matches!(ast, ASTNode::Analyze(_) | ASTNode::Attach { .. } | ASTNode::Detach(_))
+    }
+
+    /// Executes the statements of a script in order, stopping at the first
+    /// one that fails. Statements run before it keep their effects.
+    ///
+    /// # Returns
+    /// The result of each statement run; if one failed, its error is the
+    /// last result.
+    pub fn execute_script(statements: Vec<ASTNode>, db: &mut Database) -> Vec<SqlResult<Output>> {
+        let mut results = Vec::with_capacity(statements.len());
+        for ast in statements {
+            let result = Self::execute(ast, db);
+            let failed = result.is_err();
+            results.push(result);
+            if failed {
+                break;
+            }
         }
+        results
     }
 
-    fn exec_create(stmt: CreateTableStmt, db: &mut Database) -> Result<Output, SqlError> {
-        db.create_table(&stmt.name.0, stmt.columns)?;
+    fn exec_create(stmt: CreateTableStmt, db: &mut Database) -> SqlResult<Output> {
+        let mut schema = Schema::try_new(stmt.columns)?;
+        if let Some(key) = &stmt.primary_key {
+            schema = schema.with_primary_key(key)?;
+        }
+        db.create_table(stmt.name, schema)?;
         Ok(Output::None)
     }
 
-    fn exec_insert(stmt: InsertStmt, db: &mut Database) -> Result<Output, SqlError> {
+    fn exec_create_index(stmt: CreateIndexStmt, db: &mut Database) -> SqlResult<Output> {
+        // Index names are unique across the database
+        for name in db.table_names() {
+            if db.table(name).is_some_and(|t| t.index(&stmt.name).is_some()) {
+                return Err(SqlError::new_semantic(&format!("Index '{}' already exists", stmt.name)));
+            }
+        }
         let table = db
-            .get_table_mut(&stmt.table.0)
-            .ok_or_else(|| SqlError::parse(format!("unknown table: {}", stmt.table.0), dummy_span()))?;
-        table.insert_checked(stmt.values)?;
+            .table_mut(&stmt.table)
+            .ok_or_else(|| SqlError::unknown_table(&stmt.table))?;
+        table.create_index(&stmt.name, &stmt.column, stmt.kind)?;
         Ok(Output::None)
     }
 
-    fn exec_select(stmt: SelectStmt, db: &mut Database) -> Result<Output, SqlError> {
+    fn exec_insert(stmt: InsertStmt, db: &mut Database) -> SqlResult<Output> {
+        // The query of INSERT ... SELECT runs before the table is changed,
+        // so that it can read the table it inserts into
+        let selected = match &stmt.query {
+            Some(query) => Some(Self::query(query, db)?),
+            None => None,
+        };
         let table = db
-            .get_table(&stmt.table.0)
-            .ok_or_else(|| SqlError::parse(format!("unknown table: {}", stmt.table.0), dummy_span()))?;
-        let mut rows_out = Vec::new();
-        for row in table.rows() {
-            // projection: either all columns or a subset
-            let projected = if let Some(cols) = &stmt.columns {
-                let mut vals = Vec::new();
-                for col in cols {
-                    // here we assume column index lookup by name
-                    let idx = table
-                        .column_index(&col.0)
-                        .ok_or_else(|| SqlError::parse(format!("unknown column: {}", col.0), dummy_span()))?;
-                    vals.push(row.values[idx].clone());
+            .table_mut(&stmt.table)
+            .ok_or_else(|| SqlError::unknown_table(&stmt.table))?;
+
+        // Build and check every row first so a bad tuple inserts nothing
+        let mut rows = Vec::with_capacity(stmt.rows.len());
+        if let Some((source, selected)) = selected {
+            check_insertable(&source, table.schema(), stmt.columns.as_deref())?;
+            for row in selected {
+                let mut values = row.values().clone();
+                if let Some(columns) = &stmt.columns {
+                    values = reorder_values(table.schema(), columns, values)?;
                 }
-                Row { values: vals }
+                rows.push(Row::from_values(values, table.schema())?);
+            }
+        }
+        for exprs in &stmt.rows {
+            let mut values = exprs.iter().map(eval_const).collect::<SqlResult<Vec<_>>>()?;
+            if let Some(columns) = &stmt.columns {
+                values = reorder_values(table.schema(), columns, values)?;
+            }
+            rows.push(Row::from_values(values, table.schema())?);
+        }
+
+        let count = match &stmt.on_conflict {
+            None => table.insert_many(rows)?,
+            Some(OnConflict::Ignore(column)) => {
+                let column = column.as_ref().map(|column| conflict_column(table.schema(), column)).transpose()?;
+                table.insert_or_ignore(rows, column)?
+            }
+            Some(OnConflict::Replace) => table.insert_or_replace(rows)?,
+            Some(OnConflict::Update { column, assignments }) => {
+                let column = conflict_column(table.schema(), column)?;
+                // Assignments read the stored row, then the new one as `excluded`
+                let mut scope = Scope::default();
+                scope.push(&stmt.table, table.schema())?;
+                scope.push(&TableName::new("excluded").expect("valid table name"), table.schema())?;
+                let assignments = assignments
+                    .iter()
+                    .map(|(target, expr)| Ok((column_index(table.schema(), target)?, plan_expr(expr, &scope)?)))
+                    .collect::<SqlResult<Vec<_>>>()?;
+                table.upsert(rows, column, |stored, new| {
+                    let both = [stored.values().as_slice(), new.values().as_slice()].concat();
+                    let mut values = stored.values().clone();
+                    for (idx, expr) in &assignments {
+                        values[*idx] = eval_scalar(expr, &both)?;
+                    }
+                    Ok(values)
+                })?
+            }
+        };
+        if stmt.query.is_some() || stmt.on_conflict.is_some() {
+            Ok(Output::Affected(count))
+        } else {
+            Ok(Output::None)
+        }
+    }
+
+    fn exec_select(stmt: SelectStmt, db: &Database) -> SqlResult<Output> {
+        let (schema, rows) = Self::query(&stmt, db)?;
+        Ok(Output::Rows { columns: schema.columns().clone(), rows })
+    }
+
+    /// Plans a SELECT and optimizes the plan.
+    fn plan(stmt: &SelectStmt, db: &Database) -> SqlResult<LogicalPlan> {
+        Ok(optimize(Planner::plan_select(stmt, db)?))
+    }
+
+    /// Returns the rows of stored tables a SELECT reads: for each table it
+    /// scans, the positions of the rows matching the conditions the
+    /// optimizer moved onto the scan, or of every row without any.
+    ///
+    /// # Returns
+    /// A `(table, positions)` pair per scan, or `Err(SqlError)` if the
+    /// SELECT cannot be planned.
+    pub fn rows_read<'a>(stmt: &SelectStmt, db: &'a Database) -> SqlResult<Vec<(&'a Table, Vec<usize>)>> {
+        fn walk<'a>(plan: &LogicalPlan, filter: Option<&Predicate>, db: &'a Database, out: &mut Vec<(&'a Table, Vec<usize>)>) {
+            match plan {
+                LogicalPlan::Scan { table: name, .. } => {
+                    if let Some(table) = db.table(name) {
+                        let mut positions = Access::choose(table, filter).positions(table);
+                        if let Some(filter) = filter {
+                            // A row the condition cannot be evaluated on counts as read
+                            positions.retain(|&pos| !matches!(eval_where(filter, table.rows()[pos].values()), Ok(false)));
+                        }
+                        out.push((table, positions));
+                    }
+                }
+                LogicalPlan::Filter { input, predicate } if matches!(**input, LogicalPlan::Scan { .. }) => {
+                    walk(input, Some(predicate), db, out)
+                }
+                LogicalPlan::Join { left, right, .. } => {
+                    walk(left, None, db, out);
+                    walk(right, None, db, out);
+                }
+                LogicalPlan::Filter { input, .. }
+                | LogicalPlan::Aggregate { input, .. }
+                | LogicalPlan::Sort { input, .. }
+                | LogicalPlan::Project { input, .. }
+                | LogicalPlan::Distinct { input }
+                | LogicalPlan::Limit { input, .. } => walk(input, None, db, out),
+            }
+        }
+        let mut out = Vec::new();
+        walk(&Self::plan(stmt, db)?, None, db, &mut out);
+        Ok(out)
+    }
+
+    /// Runs a SELECT, returning the schema of its output and its rows.
+    fn query(stmt: &SelectStmt, db: &Database) -> SqlResult<(Schema, Vec<Row>)> {
+        let plan = Self::plan(stmt, db)?;
+        let schema = Schema::try_new(plan.columns())?;
+        let mut pipeline = Self::build(&plan, db, None)?;
+        let mut rows = Vec::new();
+        while let Some(values) = pipeline.next()? {
+            rows.push(Row::from_values(values, &schema)?);
+        }
+        Ok((schema, rows))
+    }
+
+    /// Builds the operator pipeline running `plan`.
+    ///
+    /// `filter` is a predicate that will be applied to the rows of `plan`,
+    /// passed down so that a scan can read its table through an index (see
+    /// [`Access::choose`]).
+    fn build<'a>(plan: &'a LogicalPlan, db: &'a Database, filter: Option<&'a Predicate>) -> SqlResult<BoxedOperator<'a>> {
+        Ok(match plan {
+            LogicalPlan::Scan { table: name, .. } => match db.table(name) {
+                Some(table) => {
+                    let access = Access::choose(table, filter);
+                    Box::new(Scan::new(table, access.positions(table)))
+                }
+                // A virtual table is built for this query and read whole
+                None => {
+                    let table = information_schema::table(name, db).ok_or_else(|| SqlError::unknown_table(name))?;
+                    Box::new(Values::new(table.rows().iter().map(|row| row.values().clone()).collect()))
+                }
+            },
+            LogicalPlan::Join { left, right, on } => {
+                // The right side is read once and joined with every left row
+                let left = Self::build(left, db, filter)?;
+                let mut right = Self::build(right, db, None)?;
+                let mut tuples = Vec::new();
+                while let Some(tuple) = right.next()? {
+                    tuples.push(tuple);
+                }
+                let on: RowPredicate = match on {
+                    Some(on) => Box::new(move |values| eval_where(on, values)),
+                    None => Box::new(|_| Ok(true)),
+                };
+                Box::new(NestedLoopJoin::new(left, tuples, on))
+            }
+            LogicalPlan::Filter { input, predicate } => {
+                let input = Self::build(input, db, Some(predicate))?;
+                Box::new(Filter::new(input, Box::new(move |values| eval_where(predicate, values))))
+            }
+            LogicalPlan::Aggregate { input, group_by, items, having, .. } => {
+                // Grouping needs every input row before emitting any group
+                let mut input = Self::build(input, db, None)?;
+                let mut rows = Vec::new();
+                while let Some(values) = input.next()? {
+                    rows.push(values);
+                }
+                Box::new(Values::new(aggregate(&rows, group_by, items, having.as_ref())?))
+            }
+            LogicalPlan::Sort { input, keys } => {
+                let input = Self::build(input, db, None)?;
+                let directions = keys.iter().enumerate().map(|(i, (_, dir))| (i, *dir)).collect();
+                let key = move |values: &[Value]| keys.iter().map(|(expr, _)| eval_scalar(expr, values)).collect();
+                Box::new(Sort::new(input, Box::new(key), directions))
+            }
+            LogicalPlan::Project { input, exprs, .. } => {
+                let input = Self::build(input, db, None)?;
+                let project = move |values: &[Value]| exprs.iter().map(|expr| eval_scalar(expr, values)).collect();
+                Box::new(Project::new(input, Box::new(project)))
+            }
+            LogicalPlan::Distinct { input } => Box::new(Distinct::new(Self::build(input, db, None)?)),
+            LogicalPlan::Limit { input, offset, limit } => {
+                let input = Self::build(input, db, None)?;
+                Box::new(Limit::new(input, *offset, limit.unwrap_or(usize::MAX)))
+            }
+        })
+    }
+
+    /// Describes how `stmt` would read its tables, without running it.
+    fn exec_explain(stmt: &SelectStmt, db: &Database) -> SqlResult<Output> {
+        let plan = Self::plan(stmt, db)?;
+        let mut lines = Vec::new();
+        explain(&plan, db, None, &mut lines)?;
+        Ok(Output::Plan(lines))
+    }
+
+    fn exec_truncate(name: &TableName, db: &mut Database) -> SqlResult<Output> {
+        db.truncate_table(name).map(Output::Affected).ok_or_else(|| SqlError::unknown_table(name))
+    }
+
+    fn exec_analyze(name: &TableName, db: &mut Database) -> SqlResult<Output> {
+        db.table_mut(name).ok_or_else(|| SqlError::unknown_table(name))?.analyze();
+        Ok(Output::None)
+    }
+
+    fn exec_create_schema(name: &str, db: &mut Database) -> SqlResult<Output> {
+        if name.eq_ignore_ascii_case(information_schema::SCHEMA) {
+            return Err(SqlError::new_semantic(&format!("'{}' is a reserved schema name", name)));
+        }
+        db.catalog_mut().create_schema(name)?;
+        Ok(Output::None)
+    }
+
+    /// Creates a view, once its query plans: its tables and columns must
+    /// exist.
+    fn exec_create_view(stmt: CreateViewStmt, db: &mut Database) -> SqlResult<Output> {
+        Planner::plan_select(&stmt.query, db)?;
+        db.create_view(&stmt.name, &stmt.sql)?;
+        Ok(Output::None)
+    }
+
+    /// Loads the database stored in the directory `path` and attaches it
+    /// under `alias` (see [`Catalog`](crate::core::catalog::Catalog)).
+    ///
+    /// The directory is locked before it is read, as when it is opened,
+    /// and stays locked until the database is detached.
+    fn exec_attach(path: &str, alias: &str, db: &mut Database) -> SqlResult<Output> {
+        if [information_schema::SCHEMA, information_schema::DEFAULT_SCHEMA].iter().any(|name| name.eq_ignore_ascii_case(alias)) {
+            return Err(SqlError::new_semantic(&format!("'{}' is a reserved schema name", alias)));
+        }
+        if db.catalog().database(alias).is_some() {
+            return Err(SqlError::new_semantic(&format!("A database is already attached as '{}'", alias)));
+        }
+        if !Path::new(path).is_dir() {
+            return Err(SqlError::new_io(&format!("Cannot attach '{}': no database is stored there", path)));
+        }
+        let lock = DirLock::acquire(Path::new(path))?;
+        db.catalog_mut().attach_guarded(alias, recover(Path::new(path))?, lock)?;
+        Ok(Output::None)
+    }
+
+    fn exec_detach(alias: &str, db: &mut Database) -> SqlResult<Output> {
+        db.catalog_mut().detach(alias)?;
+        Ok(Output::None)
+    }
+
+    fn exec_show_tables(db: &Database) -> Output {
+        let columns = vec![Column::new(ColumnName::new("table_name").expect("valid column name"), DataType::Text)];
+        let schema = Schema::try_new(columns.clone()).expect("one column");
+        let rows = db
+            .table_names()
+            .into_iter()
+            .map(|name| Row::from_values(vec![Value::Text(name.as_str().to_string())], &schema).expect("a name is text"))
+            .collect();
+        Output::Rows { columns, rows }
+    }
+
+    /// Lists the columns of a stored or virtual table, as
+    /// [`information_schema::describe`] does.
+    fn exec_describe(name: &TableName, db: &Database) -> SqlResult<Output> {
+        let described = source_schema(name, db)?;
+        let columns = information_schema::description_columns();
+        let schema = Schema::try_new(columns.clone())?;
+        let rows = information_schema::describe(&described)
+            .into_iter()
+            .map(|values| Row::from_values(values, &schema))
+            .collect::<SqlResult<Vec<_>>>()?;
+        Ok(Output::Rows { columns, rows })
+    }
+
+    fn exec_update(stmt: UpdateStmt, db: &mut Database) -> SqlResult<Output> {
+        let table = db
+            .table_mut(&stmt.table)
+            .ok_or_else(|| SqlError::unknown_table(&stmt.table))?;
+        let predicate = table_predicate(&stmt.table, table.schema(), stmt.where_clause.as_ref())?;
+        // Assignments read the row as it was before the update
+        let mut scope = Scope::default();
+        scope.push(&stmt.table, table.schema())?;
+        let assignments = stmt
+            .assignments
+            .iter()
+            .map(|(column, expr)| {
+                let idx = column_index(table.schema(), column)?;
+                check_assignable(&table.schema().columns()[idx], expr_type(expr, &scope)?)?;
+                Ok((idx, plan_expr(expr, &scope)?))
+            })
+            .collect::<SqlResult<Vec<_>>>()?;
+        let count = table.update_each(|row| {
+            let matches = match &predicate {
+                Some(predicate) => eval_where(predicate, row.values())?,
+                None => true,
+            };
+            if !matches {
+                return Ok(None);
+            }
+            let mut values = row.values().clone();
+            for (idx, expr) in &assignments {
+                values[*idx] = eval_scalar(expr, row.values())?;
+            }
+            Ok(Some(values))
+        })?;
+        Ok(Output::Affected(count))
+    }
+
+    fn exec_delete(stmt: DeleteStmt, db: &mut Database) -> SqlResult<Output> {
+        let table = db
+            .table_mut(&stmt.table)
+            .ok_or_else(|| SqlError::unknown_table(&stmt.table))?;
+        let predicate = table_predicate(&stmt.table, table.schema(), stmt.where_clause.as_ref())?;
+        let count = table.delete_where(|row| match &predicate {
+            Some(predicate) => eval_where(predicate, row.values()),
+            None => Ok(true),
+        })?;
+        Ok(Output::Affected(count))
+    }
+
+    fn exec_transaction(stmt: TransactionStmt, db: &mut Database) -> SqlResult<Output> {
+        match stmt {
+            TransactionStmt::Begin => db.begin()?,
+            TransactionStmt::Commit => db.commit()?,
+            TransactionStmt::Rollback => db.rollback()?,
+            TransactionStmt::Savepoint(name) => db.savepoint(&name)?,
+            TransactionStmt::RollbackTo(name) => db.rollback_to(&name)?,
+            TransactionStmt::Release(name) => db.release(&name)?,
+        }
+        Ok(Output::None)
+    }
+
+    /// Writes the result of `query` to the CSV file at `path`, replacing it,
+    /// with the output column names as header row.
+    ///
+    /// Rows are written as the query produces them, without collecting the
+    /// result first.
+    fn exec_copy_to(query: &SelectStmt, path: &str, db: &Database) -> SqlResult<Output> {
+        let plan = Self::plan(query, db)?;
+        let schema = Schema::try_new(plan.columns())?;
+        let mut pipeline = Self::build(&plan, db, None)?;
+        let io_error = |e: std::io::Error| SqlError::new_io(&format!("COPY: cannot write '{}': {}", path, e));
+
+        let mut out = BufWriter::new(File::create(path).map_err(io_error)?);
+        let header: Vec<_> = schema.columns().iter().map(|c| Some(c.name.as_str().to_string())).collect();
+        write_record(&mut out, &header).map_err(io_error)?;
+        let mut rows = 0;
+        while let Some(values) = pipeline.next()? {
+            let fields: Vec<_> = values.iter().map(value_to_field).collect();
+            write_record(&mut out, &fields).map_err(io_error)?;
+            rows += 1;
+        }
+        out.flush().map_err(io_error)?;
+        Ok(Output::Copied { rows, failed: Vec::new() })
+    }
+
+    /// Loads the CSV file at `path` into `name`, one record per row.
+    ///
+    /// When the first record lists the table's columns, in any order, it is
+    /// a header and maps fields to columns; otherwise fields are in schema
+    /// order. Unlike INSERT, a bad record does not abort the load: it is
+    /// reported in [`Output::Copied`] and the other rows are kept.
+    fn exec_copy_from(name: &TableName, path: &str, db: &mut Database) -> SqlResult<Output> {
+        let table = db.table_mut(name).ok_or_else(|| SqlError::unknown_table(name))?;
+        let file = File::open(path).map_err(|e| SqlError::new_io(&format!("COPY: cannot open '{}': {}", path, e)))?;
+        let mut reader = CsvReader::new(BufReader::new(file));
+
+        let columns = table.schema().columns().clone();
+        // order[i] is the field holding column i
+        let mut order: Vec<usize> = (0..columns.len()).collect();
+        let mut next = reader.next();
+        if let Some(Ok(first)) = &next {
+            let header: Option<Vec<usize>> = columns
+                .iter()
+                .map(|c| {
+                    first.fields.iter().position(|f| {
+                        f.as_deref().is_some_and(|f| f.trim().eq_ignore_ascii_case(c.name.as_str()))
+                    })
+                })
+                .collect();
+            if let Some(header) = header.filter(|_| first.fields.len() == columns.len()) {
+                order = header;
+                next = reader.next();
+            }
+        }
+
+        let mut rows = 0;
+        let mut failed = Vec::new();
+        while let Some(record) = next.take() {
+            next = reader.next();
+            let record = match record {
+                Ok(record) => record,
+                Err(e) => {
+                    failed.push((reader.record_line(), e.message().to_string()));
+                    continue;
+                }
+            };
+            let loaded = if record.fields.len() != columns.len() {
+                Err(SqlError::new_execution(&format!(
+                    "Expected {} fields, found {}",
+                    columns.len(),
+                    record.fields.len()
+                )))
             } else {
-                row.clone()
+                order
+                    .iter()
+                    .zip(&columns)
+                    .map(|(&field, column)| field_to_value(&record.fields[field], &column.dtype))
+                    .collect::<SqlResult<Vec<Value>>>()
+                    .and_then(|values| Row::from_values(values, table.schema()))
+                    .and_then(|row| table.insert_checked(row))
             };
-            rows_out.push(projected);
+            match loaded {
+                Ok(()) => rows += 1,
+                Err(e) => failed.push((record.line, e.message().to_string())),
+            }
+        }
+        Ok(Output::Copied { rows, failed })
+    }
+
+    fn exec_alter(stmt: AlterTableStmt, db: &mut Database) -> SqlResult<Output> {
+        if let AlterAction::RenameTable(to) = &stmt.action {
+            db.rename_table(&stmt.table, to)?;
+            return Ok(Output::None);
         }
-        Ok(Output::Rows(rows_out))
+        let table = db
+            .table_mut(&stmt.table)
+            .ok_or_else(|| SqlError::unknown_table(&stmt.table))?;
+        match stmt.action {
+            AlterAction::AddColumn(column) => table.add_column(column)?,
+            AlterAction::DropColumn(name) => table.drop_column(&name)?,
+            AlterAction::RenameColumn { from, to } => table.rename_column(&from, to)?,
+            AlterAction::RenameTable(_) => unreachable!("renamed above"),
+        }
+        Ok(Output::None)
     }
 }
 
-/// Dummy span used for semantic errors (outside parsing).
-fn dummy_span() -> crate::frontend::token::Span {
-    crate::frontend::token::Span::new(0, 0, 0)
+/// Appends the EXPLAIN lines of `plan` to `lines`: the access path of each
+/// table it reads, in join order, with the tables joined to it indented
+/// below a join step.
+///
+/// `filter` is the predicate applied above `plan`, as in
+/// [`Executor::build`].
+fn explain(plan: &LogicalPlan, db: &Database, filter: Option<&Predicate>, lines: &mut Vec<String>) -> SqlResult<()> {
+    match plan {
+        LogicalPlan::Scan { table: name, .. } => match db.table(name) {
+            Some(table) => lines.push(Access::choose(table, filter).describe(table)),
+            None => lines.push(format!("Scan {}", name.as_str())),
+        },
+        LogicalPlan::Join { left, right, .. } => {
+            explain(left, db, filter, lines)?;
+            lines.push("Nested loop join".to_string());
+            let mut inner = Vec::new();
+            explain(right, db, None, &mut inner)?;
+            lines.extend(inner.into_iter().map(|line| format!("  {}", line)));
+        }
+        LogicalPlan::Filter { input, predicate } => explain(input, db, Some(predicate), lines)?,
+        LogicalPlan::Aggregate { input, .. }
+        | LogicalPlan::Sort { input, .. }
+        | LogicalPlan::Project { input, .. }
+        | LogicalPlan::Distinct { input }
+        | LogicalPlan::Limit { input, .. } => explain(input, db, None, lines)?,
+    }
+    Ok(())
 }
 
-/// --- Stub Core API for testing only ---
-/// In real code these should be in `src/core/`.
-pub struct Database {
-    tables: std::collections::HashMap<String, Table>,
+/// Plans the WHERE clause of an UPDATE or DELETE on a single table.
+fn table_predicate(name: &TableName, schema: &Schema, clause: Option<&WhereClause>) -> SqlResult<Option<Predicate>> {
+    let mut scope = Scope::default();
+    scope.push(name, schema)?;
+    clause.map(|clause| plan_predicate(clause, &scope)).transpose()
 }
 
-impl Database {
-    pub fn new() -> Self {
-        Self { tables: std::collections::HashMap::new() }
-    }
-    pub fn create_table(&mut self, name: &str, cols: Vec<Column>) -> Result<(), SqlError> {
-        if self.tables.contains_key(name) {
-            return Err(SqlError::parse(format!("table already exists: {}", name), dummy_span()));
+/// Groups `rows` by the `group_by` columns (hash aggregation), keeps the
+/// groups satisfying `having` and computes `items` for each of them.
+///
+/// Groups are emitted in order of first appearance. Without GROUP BY all
+/// rows form a single group, so `SELECT COUNT(*)` on an empty table yields
+/// one row.
+fn aggregate(rows: &[Tuple], group_by: &[usize], items: &[GroupItem], having: Option<&Having>) -> SqlResult<Vec<Tuple>> {
+    // Hash the group keys, remembering first-appearance order
+    let mut positions: HashMap<Vec<Value>, usize> = HashMap::new();
+    let mut groups: Vec<Vec<&[Value]>> = Vec::new();
+    if group_by.is_empty() {
+        groups.push(rows.iter().map(Vec::as_slice).collect());
+    } else {
+        for row in rows {
+            let key: Vec<Value> = group_by.iter().map(|&i| row[i].clone()).collect();
+            let pos = *positions.entry(key).or_insert_with(|| {
+                groups.push(Vec::new());
+                groups.len() - 1
+            });
+            groups[pos].push(row);
         }
-        self.tables.insert(name.to_string(), Table { columns: cols, rows: Vec::new() });
-        Ok(())
     }
-    pub fn get_table_mut(&mut self, name: &str) -> Option<&mut Table> {
-        self.tables.get_mut(name)
+
+    let mut out = Vec::with_capacity(groups.len());
+    for group in &groups {
+        if let Some(Having { item, op, value }) = having {
+            let actual = eval_group_item(item, group)?;
+            if actual.is_null() || value.is_null() {
+                continue;
+            }
+            let ordering = actual.compare(value).ok_or_else(|| {
+                SqlError::new_execution(&format!("Type mismatch in HAVING: got {:?} and {:?}", actual, value))
+            })?;
+            if !op_matches(*op, ordering) {
+                continue;
+            }
+        }
+        out.push(items.iter().map(|item| eval_group_item(item, group)).collect::<SqlResult<_>>()?);
     }
-    pub fn get_table(&self, name: &str) -> Option<&Table> {
-        self.tables.get(name)
+    Ok(out)
+}
+
+/// Evaluates a resolved item over the rows of one group.
+///
+/// `NULL` inputs are ignored by every aggregate except `COUNT(*)`; `SUM`,
+/// `AVG`, `MIN` and `MAX` of no values are `NULL`.
+fn eval_group_item(item: &GroupItem, group: &[&[Value]]) -> SqlResult<Value> {
+    let (func, idx) = match item {
+        GroupItem::Column(idx) => return Ok(group[0][*idx].clone()),
+        GroupItem::Aggregate(func, None) => {
+            debug_assert_eq!(*func, AggregateFunc::Count);
+            return Ok(Value::Int(group.len() as i64));
+        }
+        GroupItem::Aggregate(func, Some(idx)) => (*func, *idx),
+    };
+
+    let mut values = group.iter().map(|row| &row[idx]).filter(|v| !v.is_null());
+    match func {
+        AggregateFunc::Count => Ok(Value::Int(values.count() as i64)),
+        AggregateFunc::Sum => values.try_fold(Value::Null, |acc, v| match (acc, v) {
+            (Value::Null, v) => Ok(v.clone()),
+            (Value::Int(a), Value::Int(b)) => a
+                .checked_add(*b)
+                .map(Value::Int)
+                .ok_or_else(|| SqlError::new_execution("Integer overflow in SUM")),
+            (Value::Float(a), Value::Float(b)) => Ok(Value::Float(a + b)),
+            (acc, v) => Err(SqlError::new_execution(&format!("Cannot add {:?} and {:?}", acc, v))),
+        }),
+        AggregateFunc::Avg => {
+            let (mut sum, mut count) = (0.0, 0usize);
+            for v in values {
+                sum += match v {
+                    Value::Int(i) => *i as f64,
+                    Value::Float(f) => *f,
+                    other => return Err(SqlError::new_execution(&format!("Cannot average {:?}", other))),
+                };
+                count += 1;
+            }
+            Ok(if count == 0 { Value::Null } else { Value::Float(sum / count as f64) })
+        }
+        AggregateFunc::Min | AggregateFunc::Max => {
+            let wanted = if func == AggregateFunc::Min { Ordering::Less } else { Ordering::Greater };
+            Ok(values
+                .fold(None, |best: Option<&Value>, v| match best {
+                    Some(b) if v.compare(b) != Some(wanted) => Some(b),
+                    _ => Some(v),
+                })
+                .cloned()
+                .unwrap_or(Value::Null))
+        }
     }
 }
 
-pub struct Table {
-    columns: Vec<Column>,
-    rows: Vec<Row>,
+/// Rearranges values given for an explicit INSERT column list into schema
+/// order. Columns that are not listed are filled with `NULL`, which the row
+/// check then rejects for non-nullable columns.
+fn reorder_values(schema: &Schema, columns: &[ColumnName], values: Vec<Value>) -> SqlResult<Vec<Value>> {
+    let mut ordered: Vec<Option<Value>> = vec![None; schema.columns().len()];
+    for (column, value) in columns.iter().zip(values) {
+        let idx = column_index(schema, column)?;
+        if ordered[idx].is_some() {
+            return Err(SqlError::new_semantic(&format!(
+                "Column '{}' specified more than once",
+                column.as_str()
+            )));
+        }
+        ordered[idx] = Some(value);
+    }
+    Ok(ordered.into_iter().map(|v| v.unwrap_or(Value::Null)).collect())
 }
 
-impl Table {
-    pub fn insert_checked(&mut self, values: Vec<Value>) -> Result<(), SqlError> {
-        if values.len() != self.columns.len() {
-            return Err(SqlError::parse(
-                format!("insert arity mismatch: expected {}, got {}", self.columns.len(), values.len()),
-                dummy_span(),
-            ));
+/// Checks that the rows of a query whose output is `source` can be
+/// inserted into `columns` of a table with `schema`, or into all of its
+/// columns in order when `columns` is `None`: the query must give one
+/// column per target column, of a type the target can hold (see
+/// [`DataType::can_hold`]).
+fn check_insertable(source: &Schema, schema: &Schema, columns: Option<&[ColumnName]>) -> SqlResult<()> {
+    let targets = match columns {
+        Some(columns) => columns
+            .iter()
+            .map(|column| column_index(schema, column).map(|idx| &schema.columns()[idx]))
+            .collect::<SqlResult<Vec<_>>>()?,
+        None => schema.columns().iter().collect(),
+    };
+    if source.columns().len() != targets.len() {
+        return Err(SqlError::new_semantic(&format!(
+            "INSERT targets {} columns but the query returns {}",
+            targets.len(),
+            source.columns().len()
+        )));
+    }
+    for (from, to) in source.columns().iter().zip(targets) {
+        if !to.dtype.can_hold(&from.dtype) {
+            return Err(SqlError::new_semantic(&format!(
+                "Column '{}' is {:?} but the query returns {:?} for it ('{}')",
+                to.name.as_str(),
+                to.dtype,
+                from.dtype,
+                from.name.as_str()
+            )));
         }
-        self.rows.push(Row { values });
-        Ok(())
     }
+    Ok(())
+}
 
-    pub fn rows(&self) -> &[Row] {
-        &self.rows
+/// Checks that values of type `dtype`, the type an UPDATE assignment
+/// gives (`None` for `NULL`), can be stored in `column`: values of the
+/// column's type, those it widens (see [`DataType::can_hold`]), and text
+/// for dates. Nullability is checked on each row.
+fn check_assignable(column: &Column, dtype: Option<DataType>) -> SqlResult<()> {
+    let holds = |dtype: &DataType| {
+        column.dtype.can_hold(dtype)
+            || (*dtype == DataType::Text && matches!(column.dtype, DataType::Date | DataType::Timestamp))
+    };
+    match dtype {
+        Some(dtype) if !holds(&dtype) => {
+            Err(SqlError::new_semantic(&format!(
+                "Type mismatch in SET: column '{}' is {:?}, got {:?}",
+                column.name.as_str(),
+                column.dtype,
+                dtype
+            )))
+        }
+        _ => Ok(()),
     }
+}
 
-    pub fn column_index(&self, name: &str) -> Option<usize> {
-        self.columns.iter().position(|c| c.name.0 == name)
+/// Looks up the column an INSERT's conflicts are detected on, which must
+/// be `UNIQUE` or the primary key.
+fn conflict_column(schema: &Schema, col: &ColumnName) -> SqlResult<usize> {
+    let idx = column_index(schema, col)?;
+    if !schema.unique_columns().contains(&idx) {
+        return Err(SqlError::new_semantic(&format!(
+            "ON CONFLICT column '{}' is neither UNIQUE nor the primary key",
+            col.as_str()
+        )));
     }
+    Ok(idx)
+}
+
+/// Looks up a column index in a single table's schema.
+fn column_index(schema: &Schema, col: &ColumnName) -> SqlResult<usize> {
+    schema
+        .index_of(col)
+        .ok_or_else(|| SqlError::new_semantic(&format!("Unknown column '{}'", col.as_str())))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::frontend::ast::*;
+    use crate::core::datetime;
+    use crate::core::schema::Column;
+    use crate::core::types::{DataType, Value};
 
     fn setup_db() -> Database {
         Database::new()
     }
 
+    fn create(db: &mut Database, name: &str, cols: &[(&str, DataType)]) {
+        Executor::execute(
+            ASTNode::CreateTable(CreateTableStmt {
+                name: TableName::new(name).unwrap(),
+                columns: cols
+                    .iter()
+                    .map(|(c, t)| Column::new(ColumnName::new(c).unwrap(), t.clone()))
+                    .collect(),
+                primary_key: None,
+            }),
+            db,
+        ).unwrap();
+    }
+
+    fn insert(db: &mut Database, name: &str, values: Vec<Value>) {
+        Executor::execute(
+            ASTNode::Insert(InsertStmt {
+                table: TableName::new(name).unwrap(),
+                columns: None,
+                rows: vec![values.into_iter().map(Expr::Literal).collect()],
+                query: None,
+                on_conflict: None,
+            }),
+            db,
+        ).unwrap();
+    }
+
+    fn select(db: &mut Database, name: &str, where_clause: Option<WhereClause>) -> Vec<Row> {
+        let out = Executor::execute(
+            ASTNode::Select(SelectStmt {
+                table: TableName::new(name).unwrap(),
+                alias: None,
+                joins: vec![],
+                distinct: false,
+                columns: None,
+                where_clause,
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
+            }),
+            db,
+        ).unwrap();
+        match out {
+            Output::Rows { rows, .. } => rows,
+            _ => panic!("expected rows"),
+        }
+    }
+
+    #[test]
+    fn execute_script_stops_at_first_error() {
+        use crate::frontend::{lexer::lexer, parser::Parser};
+
+        let mut db = setup_db();
+        let script = "CREATE TABLE t (id INT); INSERT INTO t VALUES (1); SELECT * FROM nope; INSERT INTO t VALUES (2)";
+        let statements = Parser::default().parse_script(&lexer(script).unwrap()).unwrap();
+        let results = Executor::execute_script(statements, &mut db);
+
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0], Ok(Output::None));
+        assert!(results[2].is_err());
+        assert_eq!(db.table(&TableName::new("t").unwrap()).unwrap().rows().len(), 1);
+    }
+
     #[test]
     fn create_table_adds_table() {
         let mut db = setup_db();
         let stmt = CreateTableStmt {
-            name: TableName("users".into()),
+            name: TableName::new("users").unwrap(),
             columns: vec![
-                Column { name: ColumnName("id".into()), ty: DataType::Int },
-                Column { name: ColumnName("name".into()), ty: DataType::Text },
+                Column::new(ColumnName::new("id").unwrap(), DataType::Int),
+                Column::new(ColumnName::new("name").unwrap(), DataType::Text),
             ],
+            primary_key: None,
         };
         let out = Executor::execute(ASTNode::CreateTable(stmt), &mut db).unwrap();
         assert_eq!(out, Output::None);
-        assert!(db.get_table("users").is_some());
+        assert!(db.table(&TableName::new("users").unwrap()).is_some());
     }
 
     #[test]
     fn insert_and_select_roundtrip() {
         let mut db = setup_db();
-        // create table
-        Executor::execute(
-            ASTNode::CreateTable(CreateTableStmt {
-                name: TableName("users".into()),
-                columns: vec![
-                    Column { name: ColumnName("id".into()), ty: DataType::Int },
-                    Column { name: ColumnName("name".into()), ty: DataType::Text },
-                ],
+        create(&mut db, "users", &[("id", DataType::Int), ("name", DataType::Text)]);
+        insert(&mut db, "users", vec![Value::Int(1), Value::Text("Alice".into())]);
+
+        let rows = select(&mut db, "users", None);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].values(), &vec![Value::Int(1), Value::Text("Alice".into())]);
+    }
+
+    #[test]
+    fn select_projection_by_column_names() {
+        let mut db = setup_db();
+        create(&mut db, "t", &[("a", DataType::Int), ("b", DataType::Int)]);
+        insert(&mut db, "t", vec![Value::Int(10), Value::Int(20)]);
+
+        let out = Executor::execute(
+            ASTNode::Select(SelectStmt {
+                table: TableName::new("t").unwrap(),
+                alias: None,
+                joins: vec![],
+                distinct: false,
+                columns: Some(vec![(SelectItem::Column(ColumnName::new("b").unwrap().into()), None)]),
+                where_clause: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
             }),
             &mut db,
         ).unwrap();
 
-        // insert
-        Executor::execute(
+        match out {
+            Output::Rows { rows, .. } => {
+                assert_eq!(rows[0].values(), &vec![Value::Int(20)]);
+            }
+            _ => panic!("expected rows"),
+        }
+    }
+
+    #[test]
+    fn errors_on_unknown_table_or_column() {
+        let mut db = setup_db();
+        let e = Executor::execute(
             ASTNode::Insert(InsertStmt {
-                table: TableName("users".into()),
+                table: TableName::new("nosuch").unwrap(),
                 columns: None,
-                values: vec![Value::Int(1), Value::Str("Alice".into())],
+                rows: vec![vec![]],
+                query: None,
+                on_conflict: None,
+            }),
+            &mut db,
+        ).unwrap_err();
+        assert!(matches!(e, SqlError::Semantic { .. }));
+
+        // create table with 1 column
+        create(&mut db, "t", &[("a", DataType::Int)]);
+
+        // select non-existing column
+        let e = Executor::execute(
+            ASTNode::Select(SelectStmt {
+                table: TableName::new("t").unwrap(),
+                alias: None,
+                joins: vec![],
+                distinct: false,
+                columns: Some(vec![(SelectItem::Column(ColumnName::new("b").unwrap().into()), None)]),
+                where_clause: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
+            }),
+            &mut db,
+        ).unwrap_err();
+        assert!(matches!(e, SqlError::Semantic { .. }));
+    }
+
+    #[test]
+    fn select_where_filters_rows() {
+        let mut db = setup_db();
+        create(&mut db, "users", &[("id", DataType::Int), ("name", DataType::Text)]);
+        for (id, name) in [(1, "Alice"), (2, "Bob"), (3, "Carol")] {
+            insert(&mut db, "users", vec![Value::Int(id), Value::Text(name.into())]);
+        }
+
+        let ids = |rows: Vec<Row>| rows.iter().map(|r| r.values()[0].clone()).collect::<Vec<_>>();
+        let clause = |op, value: Value| Some(WhereClause::Compare { column: ColumnName::new("id").unwrap().into(), op, value: value.into() });
+
+        assert_eq!(ids(select(&mut db, "users", clause(CompareOp::Eq, Value::Int(2)))), vec![Value::Int(2)]);
+        assert_eq!(ids(select(&mut db, "users", clause(CompareOp::NotEq, Value::Int(2)))), vec![Value::Int(1), Value::Int(3)]);
+        assert_eq!(ids(select(&mut db, "users", clause(CompareOp::Lt, Value::Int(2)))), vec![Value::Int(1)]);
+        assert_eq!(ids(select(&mut db, "users", clause(CompareOp::Gt, Value::Int(1)))), vec![Value::Int(2), Value::Int(3)]);
+
+        let by_name = Some(WhereClause::Compare {
+            column: ColumnName::new("name").unwrap().into(),
+            op: CompareOp::Eq,
+            value: Value::Text("Carol".into()).into(),
+        });
+        assert_eq!(ids(select(&mut db, "users", by_name)), vec![Value::Int(3)]);
+    }
+
+    #[test]
+    fn update_changes_only_matching_rows() {
+        let mut db = setup_db();
+        create(&mut db, "users", &[("id", DataType::Int), ("name", DataType::Text)]);
+        insert(&mut db, "users", vec![Value::Int(1), Value::Text("Alice".into())]);
+        insert(&mut db, "users", vec![Value::Int(2), Value::Text("Bob".into())]);
+
+        let out = Executor::execute(
+            ASTNode::Update(UpdateStmt {
+                table: TableName::new("users").unwrap(),
+                assignments: vec![(ColumnName::new("name").unwrap(), Value::Text("Robert".into()).into())],
+                where_clause: Some(WhereClause::Compare {
+                    column: ColumnName::new("id").unwrap().into(),
+                    op: CompareOp::Eq,
+                    value: Value::Int(2).into(),
+                }),
             }),
             &mut db,
         ).unwrap();
+        assert_eq!(out, Output::Affected(1));
+
+        let rows = select(&mut db, "users", None);
+        assert_eq!(rows[0].values()[1], Value::Text("Alice".into()));
+        assert_eq!(rows[1].values()[1], Value::Text("Robert".into()));
+    }
+
+    #[test]
+    fn update_assignments_read_the_current_row() {
+        use crate::frontend::{lexer::lexer, parser::Parser};
+
+        let run = |sql: &str, db: &mut Database| {
+            let mut results = Executor::execute_script(Parser::default().parse_script(&lexer(sql).unwrap()).unwrap(), db);
+            results.pop().unwrap()
+        };
+        let mut db = setup_db();
+        run("CREATE TABLE t (id Int PRIMARY KEY, a Int, b Int, f Float)", &mut db).unwrap();
+        run("INSERT INTO t VALUES (1, 10, 20, 0.5), (2, 30, 40, 1.5)", &mut db).unwrap();
+
+        // Every assignment reads the row as it was: a and b are swapped
+        assert_eq!(run("UPDATE t SET a = b, b = a, f = f * a WHERE id = 1", &mut db), Ok(Output::Affected(1)));
+        assert_eq!(run("UPDATE t SET id = id + 10", &mut db), Ok(Output::Affected(2)));
+        let rows = select(&mut db, "t", None);
+        assert_eq!(rows[0].values(), &vec![Value::Int(11), Value::Int(20), Value::Int(10), Value::Float(5.0)]);
+        assert_eq!(rows[1].values()[0], Value::Int(12));
+
+        // Types are checked even when no row matches
+        let error = |sql: &str, db: &mut Database| run(sql, db).unwrap_err().message().to_string();
+        assert!(error("UPDATE t SET a = f WHERE id = 0", &mut db).contains("Type mismatch in SET: column 'a' is Int, got Float"));
+        assert!(error("UPDATE t SET a = nope + 1", &mut db).contains("Unknown column 'nope'"));
+        assert!(run("UPDATE t SET id = id - id", &mut db).unwrap_err().message().contains("PRIMARY KEY"));
+        assert_eq!(select(&mut db, "t", None)[0].values()[0], Value::Int(11));
+    }
+
+    #[test]
+    fn update_type_mismatch_fails() {
+        let mut db = setup_db();
+        create(&mut db, "t", &[("a", DataType::Int)]);
+        insert(&mut db, "t", vec![Value::Int(1)]);
+
+        let e = Executor::execute(
+            ASTNode::Update(UpdateStmt {
+                table: TableName::new("t").unwrap(),
+                assignments: vec![(ColumnName::new("a").unwrap(), Value::Text("x".into()).into())],
+                where_clause: None,
+            }),
+            &mut db,
+        ).unwrap_err();
+        assert!(e.message().contains("Type mismatch"));
+        assert_eq!(select(&mut db, "t", None)[0].values()[0], Value::Int(1));
+    }
+
+    #[test]
+    fn delete_reports_affected_rows() {
+        let mut db = setup_db();
+        create(&mut db, "t", &[("a", DataType::Int)]);
+        for n in 1..=4 {
+            insert(&mut db, "t", vec![Value::Int(n)]);
+        }
 
-        // select *
         let out = Executor::execute(
-            ASTNode::Select(SelectStmt {
-                table: TableName("users".into()),
-                columns: None,
+            ASTNode::Delete(DeleteStmt {
+                table: TableName::new("t").unwrap(),
+                where_clause: Some(WhereClause::Compare {
+                    column: ColumnName::new("a").unwrap().into(),
+                    op: CompareOp::Gt,
+                    value: Value::Int(2).into(),
+                }),
             }),
             &mut db,
         ).unwrap();
+        assert_eq!(out, Output::Affected(2));
+        assert_eq!(select(&mut db, "t", None).len(), 2);
 
-        match out {
-            Output::Rows(rows) => {
-                assert_eq!(rows.len(), 1);
-                assert_eq!(rows[0].values, vec![Value::Int(1), Value::Str("Alice".into())]);
-            }
-            _ => panic!("expected rows"),
+        let out = Executor::execute(
+            ASTNode::Delete(DeleteStmt { table: TableName::new("t").unwrap(), where_clause: None }),
+            &mut db,
+        ).unwrap();
+        assert_eq!(out, Output::Affected(2));
+        assert!(select(&mut db, "t", None).is_empty());
+    }
+
+    #[test]
+    fn alter_table_evolves_schema() {
+        let mut db = setup_db();
+        create(&mut db, "users", &[("id", DataType::Int)]);
+        insert(&mut db, "users", vec![Value::Int(1)]);
+
+        let alter = |db: &mut Database, action| {
+            Executor::execute(
+                ASTNode::AlterTable(AlterTableStmt { table: TableName::new("users").unwrap(), action }),
+                db,
+            )
+        };
+
+        alter(&mut db, AlterAction::AddColumn(Column::new(ColumnName::new("name").unwrap(), DataType::Text))).unwrap();
+        alter(&mut db, AlterAction::RenameColumn {
+            from: ColumnName::new("name").unwrap(),
+            to: ColumnName::new("nick").unwrap(),
+        }).unwrap();
+        alter(&mut db, AlterAction::DropColumn(ColumnName::new("id").unwrap())).unwrap();
+
+        let table = db.table(&TableName::new("users").unwrap()).unwrap();
+        assert_eq!(table.header(), vec!["nick"]);
+        assert_eq!(table.rows()[0].values(), &vec![Value::Null]);
+
+        assert!(alter(&mut db, AlterAction::DropColumn(ColumnName::new("id").unwrap())).is_err());
+    }
+
+    #[test]
+    fn null_values_and_is_null_predicates() {
+        let mut db = setup_db();
+        create(&mut db, "users", &[("id", DataType::Int), ("email", DataType::Text)]);
+        insert(&mut db, "users", vec![Value::Int(1), Value::Text("a@x".into())]);
+        insert(&mut db, "users", vec![Value::Int(2), Value::Null]);
+
+        let ids = |rows: Vec<Row>| rows.iter().map(|r| r.values()[0].clone()).collect::<Vec<_>>();
+        let email = || ColumnRef::from(ColumnName::new("email").unwrap());
+
+        let is_null = Some(WhereClause::IsNull { column: email(), negated: false });
+        assert_eq!(ids(select(&mut db, "users", is_null)), vec![Value::Int(2)]);
+
+        let not_null = Some(WhereClause::IsNull { column: email(), negated: true });
+        assert_eq!(ids(select(&mut db, "users", not_null)), vec![Value::Int(1)]);
+
+        // NULL never compares equal or unequal
+        let ne = Some(WhereClause::Compare { column: email(), op: CompareOp::NotEq, value: Value::Text("zzz".into()).into() });
+        assert_eq!(ids(select(&mut db, "users", ne)), vec![Value::Int(1)]);
+        let eq_null = Some(WhereClause::Compare { column: email(), op: CompareOp::Eq, value: Value::Null.into() });
+        assert!(select(&mut db, "users", eq_null).is_empty());
+    }
+
+    #[test]
+    fn logical_operators_use_three_valued_logic() {
+        let mut db = setup_db();
+        create(&mut db, "users", &[("id", DataType::Int), ("email", DataType::Text)]);
+        insert(&mut db, "users", vec![Value::Int(1), Value::Text("a@x".into())]);
+        insert(&mut db, "users", vec![Value::Int(2), Value::Null]);
+        insert(&mut db, "users", vec![Value::Int(3), Value::Text("c@x".into())]);
+
+        let ids = |rows: Vec<Row>| rows.iter().map(|r| r.values()[0].clone()).collect::<Vec<_>>();
+        let id = |op, n| WhereClause::Compare { column: ColumnName::new("id").unwrap().into(), op, value: Value::Int(n).into() };
+        let email = |s: &str| WhereClause::Compare {
+            column: ColumnName::new("email").unwrap().into(),
+            op: CompareOp::Eq,
+            value: Value::Text(s.into()).into(),
+        };
+        let not = |c| WhereClause::Not(Box::new(c));
+
+        let range = WhereClause::And(Box::new(id(CompareOp::GtEq, 2)), Box::new(id(CompareOp::LtEq, 3)));
+        assert_eq!(ids(select(&mut db, "users", Some(range))), vec![Value::Int(2), Value::Int(3)]);
+
+        // NOT of an UNKNOWN comparison is still UNKNOWN, so row 2 never matches
+        assert_eq!(ids(select(&mut db, "users", Some(not(email("a@x"))))), vec![Value::Int(3)]);
+
+        // UNKNOWN OR TRUE is TRUE
+        let either = WhereClause::Or(Box::new(email("a@x")), Box::new(id(CompareOp::Eq, 2)));
+        assert_eq!(ids(select(&mut db, "users", Some(either))), vec![Value::Int(1), Value::Int(2)]);
+    }
+
+    #[test]
+    fn in_and_between_check_values_against_the_column() {
+        let mut db = setup_db();
+        create(&mut db, "items", &[("id", DataType::Int), ("price", DataType::Float)]);
+        for (id, price) in [(1, Value::Float(5.0)), (2, Value::Float(10.0)), (3, Value::Null), (4, Value::Float(20.5))] {
+            insert(&mut db, "items", vec![Value::Int(id), price]);
         }
+
+        let ids = |rows: Vec<Row>| rows.iter().map(|r| r.values()[0].clone()).collect::<Vec<_>>();
+        let col = |name: &str| ColumnRef::from(ColumnName::new(name).unwrap());
+        let between = |low: Value, high: Value, negated| Some(WhereClause::Between {
+            column: col("price"),
+            low: low.into(),
+            high: high.into(),
+            negated,
+        });
+
+        assert_eq!(ids(select(&mut db, "items", between(Value::Int(5), Value::Int(10), false))), vec![Value::Int(1), Value::Int(2)]);
+        assert_eq!(ids(select(&mut db, "items", between(Value::Int(5), Value::Int(10), true))), vec![Value::Int(4)]);
+
+        let in_list = |list: Vec<Value>, negated| Some(WhereClause::In {
+            column: col("id"),
+            values: list.into_iter().map(Expr::from).collect(),
+            negated,
+        });
+        assert_eq!(ids(select(&mut db, "items", in_list(vec![Value::Int(4), Value::Int(2)], false))), vec![Value::Int(2), Value::Int(4)]);
+        assert_eq!(ids(select(&mut db, "items", in_list(vec![Value::Int(4), Value::Int(2)], true))), vec![Value::Int(1), Value::Int(3)]);
+        // A NULL in the list makes NOT IN unknown for every non-matching row
+        assert!(select(&mut db, "items", in_list(vec![Value::Int(1), Value::Null], true)).is_empty());
     }
 
     #[test]
-    fn select_projection_by_column_names() {
+    fn insert_null_into_not_null_column_fails() {
         let mut db = setup_db();
         Executor::execute(
             ASTNode::CreateTable(CreateTableStmt {
-                name: TableName("t".into()),
-                columns: vec![
-                    Column { name: ColumnName("a".into()), ty: DataType::Int },
-                    Column { name: ColumnName("b".into()), ty: DataType::Int },
-                ],
+                name: TableName::new("t").unwrap(),
+                columns: vec![Column::new(ColumnName::new("a").unwrap(), DataType::Int).with_nullable(false)],
+                primary_key: None,
             }),
             &mut db,
         ).unwrap();
 
-        Executor::execute(
+        let e = Executor::execute(
+            ASTNode::Insert(InsertStmt { table: TableName::new("t").unwrap(), columns: None, rows: vec![vec![Value::Null.into()]], query: None, on_conflict: None }),
+            &mut db,
+        ).unwrap_err();
+        assert!(e.message().contains("NULL"));
+    }
+
+    #[test]
+    fn float_columns_compare_with_int_and_float_literals() {
+        let mut db = setup_db();
+        create(&mut db, "items", &[("id", DataType::Int), ("price", DataType::Float)]);
+        insert(&mut db, "items", vec![Value::Int(1), Value::Float(9.5)]);
+        insert(&mut db, "items", vec![Value::Int(2), Value::Int(12)]);
+
+        let ids = |rows: Vec<Row>| rows.iter().map(|r| r.values()[0].clone()).collect::<Vec<_>>();
+        let price = |op, value: Value| Some(WhereClause::Compare { column: ColumnName::new("price").unwrap().into(), op, value: value.into() });
+
+        assert_eq!(ids(select(&mut db, "items", price(CompareOp::Gt, Value::Int(10)))), vec![Value::Int(2)]);
+        assert_eq!(ids(select(&mut db, "items", price(CompareOp::Lt, Value::Float(9.75)))), vec![Value::Int(1)]);
+        assert_eq!(ids(select(&mut db, "items", price(CompareOp::Eq, Value::Float(12.0)))), vec![Value::Int(2)]);
+        assert_eq!(select(&mut db, "items", None)[1].values()[1], Value::Float(12.0));
+    }
+
+    #[test]
+    fn bool_columns_filter_on_true_and_false() {
+        let mut db = setup_db();
+        create(&mut db, "flags", &[("id", DataType::Int), ("active", DataType::Bool)]);
+        insert(&mut db, "flags", vec![Value::Int(1), Value::Bool(true)]);
+        insert(&mut db, "flags", vec![Value::Int(2), Value::Bool(false)]);
+
+        let active = |value: Value| Some(WhereClause::Compare { column: ColumnName::new("active").unwrap().into(), op: CompareOp::Eq, value: value.into() });
+        let rows = select(&mut db, "flags", active(Value::Bool(true)));
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].values()[0], Value::Int(1));
+        assert_eq!(select(&mut db, "flags", active(Value::Bool(false)))[0].values()[0], Value::Int(2));
+    }
+
+    #[test]
+    fn date_columns_compare_with_iso_text_and_now_fills_timestamps() {
+        let mut db = setup_db();
+        create(&mut db, "events", &[("day", DataType::Date), ("at", DataType::Timestamp)]);
+        let now = Expr::Function { name: "NOW".to_string(), args: vec![] };
+        for day in ["2024-01-31", "2024-03-01"] {
+            Executor::execute(
+                ASTNode::Insert(InsertStmt {
+                    table: TableName::new("events").unwrap(),
+                    columns: None,
+                    rows: vec![vec![Value::Text(day.into()).into(), now.clone()]],
+                    query: None,
+                    on_conflict: None,
+                }),
+                &mut db,
+            ).unwrap();
+        }
+
+        let after_feb = Some(WhereClause::Compare {
+            column: ColumnName::new("day").unwrap().into(),
+            op: CompareOp::Gt,
+            value: Value::Text("2024-02-01".into()).into(),
+        });
+        let rows = select(&mut db, "events", after_feb);
+        assert_eq!(rows.len(), 1);
+        assert_eq!(rows[0].values()[0], Value::Date(datetime::parse_date("2024-03-01").unwrap()));
+        match rows[0].values()[1] {
+            Value::Timestamp(ts) => assert!((ts - datetime::now()).abs() < 60),
+            ref other => panic!("Expected timestamp, got {:?}", other),
+        }
+
+        let unknown = Executor::execute(
             ASTNode::Insert(InsertStmt {
-                table: TableName("t".into()),
+                table: TableName::new("events").unwrap(),
                 columns: None,
-                values: vec![Value::Int(10), Value::Int(20)],
+                rows: vec![vec![Expr::Function { name: "YESTERDAY".to_string(), args: vec![] }, now]],
+                query: None,
+                on_conflict: None,
             }),
             &mut db,
-        ).unwrap();
+        );
+        assert!(unknown.is_err());
+    }
+
+    #[test]
+    fn insert_with_column_list_reorders_and_fills_null() {
+        let mut db = setup_db();
+        create(&mut db, "users", &[("id", DataType::Int), ("name", DataType::Text), ("age", DataType::Int)]);
+        let insert_cols = |db: &mut Database, cols: &[&str], values: Vec<Value>| {
+            Executor::execute(
+                ASTNode::Insert(InsertStmt {
+                    table: TableName::new("users").unwrap(),
+                    columns: Some(cols.iter().map(|c| ColumnName::new(c).unwrap()).collect()),
+                    rows: vec![values.into_iter().map(Expr::Literal).collect()],
+                    query: None,
+                    on_conflict: None,
+                }),
+                db,
+            )
+        };
+
+        insert_cols(&mut db, &["name", "id"], vec![Value::Text("Ann".into()), Value::Int(7)]).unwrap();
+        let rows = select(&mut db, "users", None);
+        assert_eq!(rows[0].values(), &[Value::Int(7), Value::Text("Ann".into()), Value::Null]);
+
+        assert!(insert_cols(&mut db, &["id", "id"], vec![Value::Int(1), Value::Int(2)]).is_err());
+        assert!(insert_cols(&mut db, &["nope"], vec![Value::Int(1)]).is_err());
+        assert_eq!(select(&mut db, "users", None).len(), 1);
+    }
+
+    #[test]
+    fn multi_row_insert_is_atomic() {
+        let mut db = setup_db();
+        create(&mut db, "users", &[("id", DataType::Int), ("name", DataType::Text)]);
+        let insert_rows = |db: &mut Database, rows: Vec<Vec<Value>>| {
+            Executor::execute(
+                ASTNode::Insert(InsertStmt {
+                    table: TableName::new("users").unwrap(),
+                    columns: None,
+                    rows: rows.into_iter().map(|r| r.into_iter().map(Expr::Literal).collect()).collect(),
+                    query: None,
+                    on_conflict: None,
+                }),
+                db,
+            )
+        };
+
+        insert_rows(&mut db, vec![
+            vec![Value::Int(1), Value::Text("a".into())],
+            vec![Value::Int(2), Value::Text("b".into())],
+        ]).unwrap();
+        assert_eq!(select(&mut db, "users", None).len(), 2);
+
+        let bad = insert_rows(&mut db, vec![
+            vec![Value::Int(3), Value::Text("c".into())],
+            vec![Value::Text("oops".into()), Value::Text("d".into())],
+        ]);
+        assert!(bad.is_err());
+        assert_eq!(select(&mut db, "users", None).len(), 2);
+    }
+
+    #[test]
+    fn select_order_by_multiple_keys_before_projection() {
+        let mut db = setup_db();
+        create(&mut db, "users", &[("id", DataType::Int), ("name", DataType::Text), ("age", DataType::Int)]);
+        insert(&mut db, "users", vec![Value::Int(1), Value::Text("Bob".into()), Value::Int(30)]);
+        insert(&mut db, "users", vec![Value::Int(2), Value::Text("Ann".into()), Value::Null]);
+        insert(&mut db, "users", vec![Value::Int(3), Value::Text("Cid".into()), Value::Int(30)]);
+        insert(&mut db, "users", vec![Value::Int(4), Value::Text("Dee".into()), Value::Int(25)]);
+
+        let ordered = |db: &mut Database, order_by: Vec<(&str, SortDirection)>| -> SqlResult<Vec<String>> {
+            let out = Executor::execute(
+                ASTNode::Select(SelectStmt {
+                    table: TableName::new("users").unwrap(),
+                    alias: None,
+                    joins: vec![],
+                    distinct: false,
+                    columns: Some(vec![(SelectItem::Column(ColumnName::new("name").unwrap().into()), None)]),
+                    where_clause: None,
+                    group_by: vec![],
+                    having: None,
+                    order_by: order_by.into_iter().map(|(c, d)| (ColumnName::new(c).unwrap().into(), d)).collect(),
+                    limit: None,
+                    offset: None,
+                }),
+                db,
+            )?;
+            match out {
+                Output::Rows { rows, .. } => Ok(rows.iter().map(|r| r.values()[0].to_string()).collect()),
+                _ => panic!("Expected rows"),
+            }
+        };
+
+        assert_eq!(
+            ordered(&mut db, vec![("age", SortDirection::Desc), ("name", SortDirection::Desc)]).unwrap(),
+            vec!["Cid", "Bob", "Dee", "Ann"]
+        );
+        assert_eq!(
+            ordered(&mut db, vec![("age", SortDirection::Asc), ("id", SortDirection::Asc)]).unwrap(),
+            vec!["Ann", "Dee", "Bob", "Cid"]
+        );
+        assert!(ordered(&mut db, vec![("nope", SortDirection::Asc)]).is_err());
+    }
+
+    #[test]
+    fn select_limit_and_offset_page_through_sorted_rows() {
+        let mut db = setup_db();
+        create(&mut db, "t", &[("n", DataType::Int)]);
+        for n in [5, 3, 1, 4, 2] {
+            insert(&mut db, "t", vec![Value::Int(n)]);
+        }
+
+        let page = |db: &mut Database, limit, offset| {
+            let out = Executor::execute(
+                ASTNode::Select(SelectStmt {
+                    table: TableName::new("t").unwrap(),
+                    alias: None,
+                    joins: vec![],
+                    distinct: false,
+                    columns: None,
+                    where_clause: None,
+                    group_by: vec![],
+                    having: None,
+                    order_by: vec![(ColumnName::new("n").unwrap().into(), SortDirection::Asc)],
+                    limit,
+                    offset,
+                }),
+                db,
+            ).unwrap();
+            match out {
+                Output::Rows { rows, .. } => rows.iter().map(|r| r.values()[0].clone()).collect::<Vec<_>>(),
+                _ => panic!("Expected rows"),
+            }
+        };
+
+        assert_eq!(page(&mut db, Some(2), None), vec![Value::Int(1), Value::Int(2)]);
+        assert_eq!(page(&mut db, Some(2), Some(3)), vec![Value::Int(4), Value::Int(5)]);
+        assert_eq!(page(&mut db, None, Some(4)), vec![Value::Int(5)]);
+        assert!(page(&mut db, Some(0), None).is_empty());
+        assert!(page(&mut db, Some(10), Some(10)).is_empty());
+    }
+
+    #[test]
+    fn select_distinct_removes_duplicates_before_limit() {
+        let mut db = setup_db();
+        create(&mut db, "users", &[("id", DataType::Int), ("name", DataType::Text)]);
+        for (id, name) in [(1, "Ann"), (2, "Bob"), (3, "Ann"), (4, "Cid"), (5, "Bob")] {
+            insert(&mut db, "users", vec![Value::Int(id), Value::Text(name.into())]);
+        }
+
+        let distinct_names = |db: &mut Database, limit| {
+            let out = Executor::execute(
+                ASTNode::Select(SelectStmt {
+                    table: TableName::new("users").unwrap(),
+                    alias: None,
+                    joins: vec![],
+                    distinct: true,
+                    columns: Some(vec![(SelectItem::Column(ColumnName::new("name").unwrap().into()), None)]),
+                    where_clause: None,
+                    group_by: vec![],
+                    having: None,
+                    order_by: vec![],
+                    limit,
+                    offset: None,
+                }),
+                db,
+            ).unwrap();
+            match out {
+                Output::Rows { rows, .. } => rows.iter().map(|r| r.values()[0].to_string()).collect::<Vec<_>>(),
+                _ => panic!("Expected rows"),
+            }
+        };
+
+        assert_eq!(distinct_names(&mut db, None), vec!["Ann", "Bob", "Cid"]);
+        assert_eq!(distinct_names(&mut db, Some(2)), vec!["Ann", "Bob"]);
+    }
 
+    fn grouped(db: &mut Database, columns: Vec<SelectItem>, group_by: &[&str], having: Option<HavingClause>) -> SqlResult<Vec<Row>> {
         let out = Executor::execute(
             ASTNode::Select(SelectStmt {
-                table: TableName("t".into()),
-                columns: Some(vec![ColumnName("b".into())]),
+                table: TableName::new("staff").unwrap(),
+                alias: None,
+                joins: vec![],
+                distinct: false,
+                columns: Some(columns.into_iter().map(|item| (item, None)).collect()),
+                where_clause: None,
+                group_by: group_by.iter().map(|c| ColumnName::new(c).unwrap().into()).collect(),
+                having,
+                order_by: vec![],
+                limit: None,
+                offset: None,
+            }),
+            db,
+        )?;
+        match out {
+            Output::Rows { rows, .. } => Ok(rows),
+            _ => panic!("Expected rows"),
+        }
+    }
+
+    fn staff_db() -> Database {
+        let mut db = setup_db();
+        create(&mut db, "staff", &[("dept", DataType::Text), ("salary", DataType::Int), ("bonus", DataType::Float)]);
+        for (dept, salary, bonus) in [("eng", 100, Value::Float(1.5)), ("ops", 50, Value::Null), ("eng", 120, Value::Float(2.5)), ("ops", 70, Value::Null), ("hr", 60, Value::Float(1.0))] {
+            insert(&mut db, "staff", vec![Value::Text(dept.into()), Value::Int(salary), bonus]);
+        }
+        db
+    }
+
+    fn agg(func: AggregateFunc, column: Option<&str>) -> SelectItem {
+        SelectItem::Aggregate { func, column: column.map(|c| ColumnName::new(c).unwrap().into()) }
+    }
+
+    #[test]
+    fn group_by_computes_per_group_aggregates() {
+        let mut db = staff_db();
+        let dept = SelectItem::Column(ColumnName::new("dept").unwrap().into());
+        let rows = grouped(&mut db, vec![
+            dept,
+            agg(AggregateFunc::Count, None),
+            agg(AggregateFunc::Sum, Some("salary")),
+            agg(AggregateFunc::Avg, Some("salary")),
+            agg(AggregateFunc::Max, Some("bonus")),
+            agg(AggregateFunc::Count, Some("bonus")),
+        ], &["dept"], None).unwrap();
+
+        let values: Vec<Vec<String>> = rows.iter().map(|r| r.values().iter().map(|v| v.to_string()).collect()).collect();
+        assert_eq!(values, vec![
+            vec!["eng", "2", "220", "110", "2.5", "2"],
+            vec!["ops", "2", "120", "60", "NULL", "0"],
+            vec!["hr", "1", "60", "60", "1", "1"],
+        ]);
+    }
+
+    #[test]
+    fn aggregates_without_group_by_form_one_group() {
+        let mut db = staff_db();
+        let rows = grouped(&mut db, vec![agg(AggregateFunc::Count, None), agg(AggregateFunc::Min, Some("salary"))], &[], None).unwrap();
+        assert_eq!(rows[0].values(), &[Value::Int(5), Value::Int(50)]);
+
+        create(&mut db, "empty", &[("n", DataType::Int)]);
+        let out = Executor::execute(
+            ASTNode::Select(SelectStmt {
+                table: TableName::new("empty").unwrap(),
+                alias: None,
+                joins: vec![],
+                distinct: false,
+                columns: Some(vec![(agg(AggregateFunc::Count, None), None), (agg(AggregateFunc::Sum, Some("n")), None)]),
+                where_clause: None,
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
             }),
             &mut db,
         ).unwrap();
+        let columns = vec![
+            Column::new(ColumnName::new("count").unwrap(), DataType::Int),
+            Column::new(ColumnName::new("sum_n").unwrap(), DataType::Int),
+        ];
+        let schema = Schema::try_new(columns.clone()).unwrap();
+        assert_eq!(out, Output::Rows {
+            columns,
+            rows: vec![Row::from_values(vec![Value::Int(0), Value::Null], &schema).unwrap()],
+        });
+    }
+
+    #[test]
+    fn having_filters_groups() {
+        let mut db = staff_db();
+        let having = HavingClause { item: agg(AggregateFunc::Sum, Some("salary")), op: CompareOp::Gt, value: Value::Int(100).into() };
+        let rows = grouped(&mut db, vec![SelectItem::Column(ColumnName::new("dept").unwrap().into())], &["dept"], Some(having)).unwrap();
+        let depts: Vec<String> = rows.iter().map(|r| r.values()[0].to_string()).collect();
+        assert_eq!(depts, vec!["eng", "ops"]);
+    }
+
+    #[test]
+    fn grouping_rejects_invalid_select_lists() {
+        let mut db = staff_db();
+        let salary = SelectItem::Column(ColumnName::new("salary").unwrap().into());
+        assert!(grouped(&mut db, vec![salary], &["dept"], None).is_err());
+        assert!(grouped(&mut db, vec![agg(AggregateFunc::Sum, Some("dept"))], &[], None).is_err());
+        assert!(grouped(&mut db, vec![agg(AggregateFunc::Count, None)], &["nope"], None).is_err());
+    }
+
+    fn column_ref(name: &str) -> ColumnRef {
+        match name.split_once('.') {
+            Some((table, column)) => ColumnRef {
+                table: Some(TableName::new(table).unwrap()),
+                column: ColumnName::new(column).unwrap(),
+            },
+            None => ColumnName::new(name).unwrap().into(),
+        }
+    }
+
+    fn shop_db() -> Database {
+        let mut db = setup_db();
+        create(&mut db, "users", &[("id", DataType::Int), ("name", DataType::Text)]);
+        create(&mut db, "orders", &[("id", DataType::Int), ("user_id", DataType::Int), ("total", DataType::Int)]);
+        for (id, name) in [(1, "Ann"), (2, "Bob"), (3, "Cid")] {
+            insert(&mut db, "users", vec![Value::Int(id), Value::Text(name.into())]);
+        }
+        for (id, user_id, total) in [(10, 1, 5), (11, 1, 7), (12, 3, 9), (13, 9, 1)] {
+            insert(&mut db, "orders", vec![Value::Int(id), Value::Int(user_id), Value::Int(total)]);
+        }
+        db
+    }
 
+    fn join_users_orders(db: &mut Database, columns: Option<Vec<SelectItem>>, group_by: &[&str], order_by: &[&str]) -> SqlResult<Vec<Vec<String>>> {
+        let out = Executor::execute(
+            ASTNode::Select(SelectStmt {
+                table: TableName::new("users").unwrap(),
+                alias: None,
+                joins: vec![Join {
+                    table: TableName::new("orders").unwrap(),
+                    alias: None,
+                    on: Some(WhereClause::Compare {
+                        column: column_ref("users.id"),
+                        op: CompareOp::Eq,
+                        value: Expr::Column(column_ref("orders.user_id")),
+                    }),
+                }],
+                distinct: false,
+                columns: columns.map(|items| items.into_iter().map(|item| (item, None)).collect()),
+                where_clause: None,
+                group_by: group_by.iter().map(|c| column_ref(c)).collect(),
+                having: None,
+                order_by: order_by.iter().map(|c| (column_ref(c), SortDirection::Asc)).collect(),
+                limit: None,
+                offset: None,
+            }),
+            db,
+        )?;
         match out {
-            Output::Rows(rows) => {
-                assert_eq!(rows[0].values, vec![Value::Int(20)]);
+            Output::Rows { rows, .. } => Ok(rows.iter().map(|r| r.values().iter().map(|v| v.to_string()).collect()).collect()),
+            _ => panic!("Expected rows"),
+        }
+    }
+
+    #[test]
+    fn inner_join_combines_matching_rows() {
+        let mut db = shop_db();
+
+        let all = join_users_orders(&mut db, None, &[], &[]).unwrap();
+        assert_eq!(all, vec![
+            vec!["1", "Ann", "10", "1", "5"],
+            vec!["1", "Ann", "11", "1", "7"],
+            vec!["3", "Cid", "12", "3", "9"],
+        ]);
+
+        let columns = vec![SelectItem::Column(column_ref("name")), SelectItem::Column(column_ref("orders.id"))];
+        let rows = join_users_orders(&mut db, Some(columns), &[], &["total"]).unwrap();
+        assert_eq!(rows, vec![vec!["Ann", "10"], vec!["Ann", "11"], vec!["Cid", "12"]]);
+
+        let columns = vec![SelectItem::Column(column_ref("users.name")), agg(AggregateFunc::Sum, Some("total"))];
+        let rows = join_users_orders(&mut db, Some(columns), &["users.name"], &["sum_total"]).unwrap();
+        assert_eq!(rows, vec![vec!["Cid", "9"], vec!["Ann", "12"]]);
+    }
+
+    #[test]
+    fn join_rejects_ambiguous_and_unknown_references() {
+        let mut db = shop_db();
+        let select = |db: &mut Database, name: &str| {
+            join_users_orders(db, Some(vec![SelectItem::Column(column_ref(name))]), &[], &[]).unwrap_err()
+        };
+
+        assert!(select(&mut db, "id").message().contains("ambiguous"));
+        assert!(select(&mut db, "users.total").message().contains("Unknown column"));
+        assert!(select(&mut db, "items.id").message().contains("Unknown table"));
+    }
+
+    #[test]
+    fn show_tables_and_describe_return_rows() {
+        let mut db = setup_db();
+        create(&mut db, "users", &[("id", DataType::Int), ("name", DataType::Text)]);
+        create(&mut db, "orders", &[("id", DataType::Int)]);
+
+        match Executor::execute(ASTNode::ShowTables, &mut db).unwrap() {
+            Output::Rows { columns, rows } => {
+                assert_eq!(columns[0].name.as_str(), "table_name");
+                let names: Vec<_> = rows.iter().map(|row| row.values()[0].clone()).collect();
+                assert_eq!(names, vec![Value::Text("orders".into()), Value::Text("users".into())]);
             }
-            _ => panic!("expected rows"),
+            other => panic!("Expected rows, got {:?}", other),
         }
+
+        match Executor::execute(ASTNode::Describe(TableName::new("users").unwrap()), &mut db).unwrap() {
+            Output::Rows { columns, rows } => {
+                let headers: Vec<_> = columns.iter().map(|c| c.name.as_str()).collect();
+                assert_eq!(headers, ["column_name", "data_type", "is_nullable", "column_key"]);
+                let text = |s: &str| Value::Text(s.into());
+                assert_eq!(rows[1].values(), &vec![text("name"), text("Text"), text("YES"), Value::Null]);
+            }
+            other => panic!("Expected rows, got {:?}", other),
+        }
+        let virtual_table = TableName::qualified("information_schema", "tables").unwrap();
+        assert!(Executor::execute(ASTNode::Describe(virtual_table), &mut db).is_ok());
+        assert!(Executor::execute(ASTNode::Describe(TableName::new("nope").unwrap()), &mut db).is_err());
     }
 
     #[test]
-    fn errors_on_unknown_table_or_column() {
+    fn select_where_type_mismatch_fails() {
         let mut db = setup_db();
+        create(&mut db, "t", &[("a", DataType::Int)]);
+        insert(&mut db, "t", vec![Value::Int(1)]);
+
         let e = Executor::execute(
-            ASTNode::Insert(InsertStmt {
-                table: TableName("nosuch".into()),
+            ASTNode::Select(SelectStmt {
+                table: TableName::new("t").unwrap(),
+                alias: None,
+                joins: vec![],
+                distinct: false,
                 columns: None,
-                values: vec![],
+                where_clause: Some(WhereClause::Compare {
+                    column: ColumnName::new("a").unwrap().into(),
+                    op: CompareOp::Eq,
+                    value: Value::Text("1".into()).into(),
+                }),
+                group_by: vec![],
+                having: None,
+                order_by: vec![],
+                limit: None,
+                offset: None,
             }),
             &mut db,
         ).unwrap_err();
-        assert!(matches!(e, SqlError::ParseError { .. }));
+        assert!(e.message().contains("Type mismatch"));
+    }
 
-        // create table with 1 column
-        Executor::execute(
-            ASTNode::CreateTable(CreateTableStmt {
-                name: TableName("t".into()),
-                columns: vec![Column { name: ColumnName("a".into()), ty: DataType::Int }],
-            }),
+    #[test]
+    fn attached_tables_are_read_as_alias_table() {
+        use crate::frontend::{lexer::lexer, parser::Parser};
+        use crate::storage::recovery::checkpoint;
+        use crate::testing::TempDir;
+
+        let run = |sql: &str, db: &mut Database| {
+            let mut results = Executor::execute_script(Parser::default().parse_script(&lexer(sql).unwrap()).unwrap(), db);
+            results.pop().unwrap()
+        };
+        let tmp = TempDir::new("attach");
+        let dir = tmp.path();
+        let mut archive = setup_db();
+        run("CREATE TABLE orders (user_id Int, amount Int); INSERT INTO orders VALUES (1, 10), (2, 20), (1, 5)", &mut archive).unwrap();
+        checkpoint(&archive, dir).unwrap();
+
+        let mut db = setup_db();
+        run("CREATE TABLE users (id Int, name Text); INSERT INTO users VALUES (1, 'Alice'), (2, 'Bob')", &mut db).unwrap();
+        let attach = format!("ATTACH '{}' AS archive", dir.display());
+        assert_eq!(run(&attach, &mut db), Ok(Output::None));
+        assert!(run(&attach, &mut db).unwrap_err().message().contains("already attached"));
+        // The directory stays locked while attached
+        assert!(DirLock::acquire(dir).unwrap_err().message().contains("locked"));
+        assert!(run(&format!("ATTACH '{}' AS again", dir.display()), &mut db).unwrap_err().message().contains("locked"));
+
+        let totals = run(
+            "SELECT u.name, SUM(o.amount) FROM users u JOIN archive.orders o ON o.user_id = u.id GROUP BY u.name ORDER BY u.name",
             &mut db,
-        ).unwrap();
+        );
+        match totals.unwrap() {
+            Output::Rows { rows, .. } => {
+                let values: Vec<_> = rows.iter().map(|row| row.values().clone()).collect();
+                assert_eq!(
+                    values,
+                    vec![vec![Value::Text("Alice".into()), Value::Int(15)], vec![Value::Text("Bob".into()), Value::Int(20)]]
+                );
+            }
+            other => panic!("Expected rows, got {:?}", other),
+        }
+        match run("SELECT table_schema, table_name FROM information_schema.tables ORDER BY table_name", &mut db).unwrap() {
+            Output::Rows { rows, .. } => assert_eq!(rows[0].values(), &vec![Value::Text("archive".into()), Value::Text("orders".into())]),
+            other => panic!("Expected rows, got {:?}", other),
+        }
 
-        // select non-existing column
-        let e = Executor::execute(
-            ASTNode::Select(SelectStmt {
-                table: TableName("t".into()),
-                columns: Some(vec![ColumnName("b".into())]),
-            }),
+        // Reserved names and missing directories are refused
+        assert!(run(&format!("ATTACH '{}' AS main", dir.display()), &mut db).is_err());
+        assert!(run("ATTACH 'no/such/dir' AS other", &mut db).is_err());
+
+        // Rolling back undoes a DETACH
+        run("BEGIN; DETACH archive", &mut db).unwrap();
+        assert!(run("SELECT * FROM archive.orders", &mut db).is_err());
+        run("ROLLBACK", &mut db).unwrap();
+        assert!(run("SELECT * FROM archive.orders", &mut db).is_ok());
+
+        assert_eq!(run("DETACH archive", &mut db), Ok(Output::None));
+        assert!(run("SELECT * FROM archive.orders", &mut db).is_err());
+        assert!(run("DETACH archive", &mut db).is_err());
+        DirLock::acquire(dir).unwrap();
+    }
+
+    #[test]
+    fn tables_with_the_same_name_live_in_different_schemas() {
+        use crate::frontend::{lexer::lexer, parser::Parser};
+
+        let run = |sql: &str, db: &mut Database| {
+            let mut results = Executor::execute_script(Parser::default().parse_script(&lexer(sql).unwrap()).unwrap(), db);
+            results.pop().unwrap()
+        };
+        let rows = |output: SqlResult<Output>| match output.unwrap() {
+            Output::Rows { rows, .. } => rows.iter().map(|row| row.values().clone()).collect::<Vec<_>>(),
+            other => panic!("Expected rows, got {:?}", other),
+        };
+        let mut db = setup_db();
+        assert!(run("CREATE TABLE sales.orders (id Int)", &mut db).unwrap_err().message().contains("Schema 'sales' does not exist"));
+        run("CREATE SCHEMA sales; CREATE TABLE sales.orders (id Int, amount Int); CREATE TABLE orders (id Int)", &mut db).unwrap();
+        run("INSERT INTO sales.orders VALUES (1, 10), (2, 20); INSERT INTO main.orders VALUES (3)", &mut db).unwrap();
+        run("UPDATE sales.orders SET amount = 15 WHERE id = 1", &mut db).unwrap();
+
+        assert_eq!(rows(run("SELECT id FROM orders", &mut db)), vec![vec![Value::Int(3)]]);
+        assert_eq!(rows(run("SELECT id FROM main.orders", &mut db)), vec![vec![Value::Int(3)]]);
+        assert_eq!(
+            rows(run("SELECT orders.amount FROM sales.orders WHERE orders.id = 1", &mut db)),
+            vec![vec![Value::Int(15)]]
+        );
+        assert!(run("SELECT orders.id FROM sales.orders CROSS JOIN main.orders", &mut db).is_err());
+        assert_eq!(
+            rows(run("SELECT table_schema, table_name FROM information_schema.tables ORDER BY table_schema", &mut db)),
+            vec![
+                vec![Value::Text("main".into()), Value::Text("orders".into())],
+                vec![Value::Text("sales".into()), Value::Text("orders".into())],
+            ]
+        );
+
+        assert!(run("CREATE SCHEMA sales", &mut db).is_err());
+        assert!(run("CREATE SCHEMA information_schema", &mut db).is_err());
+
+        // A schema created in a transaction goes away with it
+        run("BEGIN; CREATE SCHEMA hr; CREATE TABLE hr.staff (id Int); ROLLBACK", &mut db).unwrap();
+        assert!(run("CREATE TABLE hr.staff (id Int)", &mut db).is_err());
+        run("BEGIN; CREATE SCHEMA hr; COMMIT", &mut db).unwrap();
+        assert!(run("CREATE TABLE hr.staff (id Int)", &mut db).is_ok());
+    }
+
+    #[test]
+    fn views_are_expanded_where_they_are_read() {
+        use crate::frontend::{lexer::lexer, parser::Parser};
+
+        let run = |sql: &str, db: &mut Database| {
+            let mut results = Executor::execute_script(Parser::default().parse_script(&lexer(sql).unwrap()).unwrap(), db);
+            results.pop().unwrap()
+        };
+        let rows = |output: SqlResult<Output>| match output.unwrap() {
+            Output::Rows { rows, .. } => rows.iter().map(|row| row.values().clone()).collect::<Vec<_>>(),
+            other => panic!("Expected rows, got {:?}", other),
+        };
+        let mut db = setup_db();
+        run(
+            "CREATE TABLE users (id Int, name Text); INSERT INTO users VALUES (1, 'Alice'), (2, 'Bob');
+             CREATE TABLE orders (user_id Int, amount Int); INSERT INTO orders VALUES (1, 10), (2, 50), (2, 70)",
             &mut db,
-        ).unwrap_err();
-        assert!(matches!(e, SqlError::ParseError { .. }));
+        )
+        .unwrap();
+        run("CREATE VIEW big AS SELECT user_id, amount FROM orders WHERE amount > 20", &mut db).unwrap();
+        run("CREATE VIEW spenders AS SELECT u.name, b.amount FROM users u JOIN big b ON b.user_id = u.id", &mut db).unwrap();
+
+        assert_eq!(
+            rows(run("SELECT name, SUM(amount) FROM spenders GROUP BY name", &mut db)),
+            vec![vec![Value::Text("Bob".into()), Value::Int(120)]]
+        );
+        // A view reads the tables as they are now
+        run("INSERT INTO orders VALUES (1, 30)", &mut db).unwrap();
+        assert_eq!(
+            rows(run("SELECT u.name FROM users u JOIN big ON big.user_id = u.id WHERE big.amount < 40", &mut db)),
+            vec![vec![Value::Text("Alice".into())]]
+        );
+        match run("DESCRIBE spenders", &mut db).unwrap() {
+            Output::Rows { rows, .. } => assert_eq!(rows.len(), 2),
+            other => panic!("Expected rows, got {:?}", other),
+        }
+
+        assert!(run("CREATE VIEW big AS SELECT * FROM users", &mut db).unwrap_err().message().contains("already exists"));
+        assert!(run("CREATE TABLE big (id Int)", &mut db).is_err());
+        assert!(run("CREATE VIEW broken AS SELECT nope FROM users", &mut db).is_err());
+        assert!(run("INSERT INTO big VALUES (1, 1)", &mut db).is_err());
+
+        // A view created in a transaction goes away with it
+        run("BEGIN; CREATE VIEW ids AS SELECT id FROM users; SAVEPOINT s; CREATE VIEW names AS SELECT name FROM users", &mut db).unwrap();
+        run("ROLLBACK TO s", &mut db).unwrap();
+        assert!(run("SELECT * FROM names", &mut db).is_err());
+        assert!(run("SELECT * FROM ids", &mut db).is_ok());
+        run("ROLLBACK", &mut db).unwrap();
+        assert!(run("SELECT * FROM ids", &mut db).is_err());
+
+        // Renaming a column the view reads breaks it, with a clear error
+        run("ALTER TABLE orders RENAME COLUMN amount TO total", &mut db).unwrap();
+        assert!(run("SELECT * FROM spenders", &mut db).unwrap_err().message().contains("View 'big' is no longer valid"));
+    }
+
+    #[test]
+    fn truncate_empties_a_table_but_keeps_its_definition() {
+        use crate::frontend::{lexer::lexer, parser::Parser};
+
+        let run = |sql: &str, db: &mut Database| {
+            let mut results = Executor::execute_script(Parser::default().parse_script(&lexer(sql).unwrap()).unwrap(), db);
+            results.pop().unwrap()
+        };
+        let mut db = setup_db();
+        run("CREATE TABLE t (id Int PRIMARY KEY, tag Text); CREATE INDEX by_tag ON t (tag)", &mut db).unwrap();
+        run("INSERT INTO t VALUES (1, 'a'), (2, 'b'); ANALYZE t", &mut db).unwrap();
+        let name = TableName::new("t").unwrap();
+
+        // Undone by ROLLBACK like any other change
+        assert_eq!(run("BEGIN; TRUNCATE TABLE t", &mut db), Ok(Output::Affected(2)));
+        run("ROLLBACK", &mut db).unwrap();
+        assert_eq!(db.table(&name).unwrap().rows().len(), 2);
+
+        assert_eq!(run("TRUNCATE t", &mut db), Ok(Output::Affected(2)));
+        let table = db.table(&name).unwrap();
+        assert!(table.rows().is_empty());
+        assert!(table.stats().is_none());
+        assert!(table.index("by_tag").is_some());
+        // Keys in use before are free again
+        run("INSERT INTO t VALUES (1, 'c')", &mut db).unwrap();
+        assert!(run("INSERT INTO t VALUES (1, 'd')", &mut db).is_err());
+        assert!(run("TRUNCATE nope", &mut db).is_err());
+    }
+
+    #[test]
+    fn rename_table_keeps_rows_and_indexes() {
+        use crate::frontend::{lexer::lexer, parser::Parser};
+
+        let run = |sql: &str, db: &mut Database| {
+            let mut results = Executor::execute_script(Parser::default().parse_script(&lexer(sql).unwrap()).unwrap(), db);
+            results.pop().unwrap()
+        };
+        let mut db = setup_db();
+        run("CREATE TABLE users (id Int PRIMARY KEY, name Text); CREATE INDEX by_name ON users (name)", &mut db).unwrap();
+        run("INSERT INTO users VALUES (1, 'Alice'); CREATE TABLE orders (id Int); CREATE SCHEMA old", &mut db).unwrap();
+
+        run("ALTER TABLE users RENAME TO customers", &mut db).unwrap();
+        assert!(db.table(&TableName::new("users").unwrap()).is_none());
+        let customers = db.table(&TableName::new("customers").unwrap()).unwrap();
+        assert_eq!(customers.name().as_str(), "customers");
+        assert_eq!(customers.rows().len(), 1);
+        assert!(customers.index("by_name").is_some());
+        assert!(run("INSERT INTO customers VALUES (1, 'Bob')", &mut db).is_err());
+
+        // A rollback puts the old name back
+        run("BEGIN; ALTER TABLE customers RENAME TO clients; ROLLBACK", &mut db).unwrap();
+        assert!(db.table(&TableName::new("customers").unwrap()).is_some());
+        assert!(db.table(&TableName::new("clients").unwrap()).is_none());
+
+        // A plain new name stays in the schema, a qualified one moves the table
+        run("ALTER TABLE orders RENAME TO old.orders; ALTER TABLE old.orders RENAME TO orders_2020", &mut db).unwrap();
+        assert!(db.table(&TableName::qualified("old", "orders_2020").unwrap()).is_some());
+
+        assert!(run("ALTER TABLE customers RENAME TO old.orders_2020", &mut db).unwrap_err().message().contains("already exists"));
+        assert!(run("ALTER TABLE customers RENAME TO nope.customers", &mut db).is_err());
+        assert!(run("ALTER TABLE users RENAME TO people", &mut db).is_err());
+    }
+
+    #[test]
+    fn insert_select_copies_the_rows_of_a_query() {
+        use crate::frontend::{lexer::lexer, parser::Parser};
+
+        let run = |sql: &str, db: &mut Database| {
+            let mut results = Executor::execute_script(Parser::default().parse_script(&lexer(sql).unwrap()).unwrap(), db);
+            results.pop().unwrap()
+        };
+        let mut db = setup_db();
+        run("CREATE TABLE logs (id Int PRIMARY KEY, level Text, took Int)", &mut db).unwrap();
+        run("INSERT INTO logs VALUES (1, 'info', 3), (2, 'error', 40), (3, 'error', 7)", &mut db).unwrap();
+        run("CREATE TABLE archive (id Int PRIMARY KEY, level Text NOT NULL, took Float)", &mut db).unwrap();
+
+        assert_eq!(run("INSERT INTO archive SELECT * FROM logs WHERE level = 'error'", &mut db), Ok(Output::Affected(2)));
+        let archive = TableName::new("archive").unwrap();
+        assert_eq!(db.table(&archive).unwrap().rows()[0].values()[2], Value::Float(40.0));
+
+        // Listed columns take the query's columns in order, the others are NULL
+        assert_eq!(run("INSERT INTO archive (level, id) SELECT level, id + 10 FROM logs WHERE id = 1", &mut db), Ok(Output::Affected(1)));
+        match run("SELECT took FROM archive WHERE id = 11", &mut db).unwrap() {
+            Output::Rows { rows, .. } => assert_eq!(rows[0].values(), &vec![Value::Null]),
+            other => panic!("Expected rows, got {:?}", other),
+        }
+
+        // The projection must fit the target columns
+        let error = |sql: &str, db: &mut Database| run(sql, db).unwrap_err().message().to_string();
+        assert!(error("INSERT INTO archive SELECT id, level FROM logs", &mut db).contains("targets 3 columns but the query returns 2"));
+        assert!(error("INSERT INTO archive (id, took) SELECT id, level FROM logs", &mut db).contains("Column 'took' is Float"));
+        assert!(error("INSERT INTO archive (nope) SELECT id FROM logs", &mut db).contains("Unknown column 'nope'"));
+
+        // A row that breaks a constraint inserts nothing
+        assert!(run("INSERT INTO archive SELECT * FROM logs", &mut db).is_err());
+        assert_eq!(db.table(&archive).unwrap().rows().len(), 3);
+
+        // The query reads the table as it was before the insert
+        run("INSERT INTO logs SELECT id + 3, level, took FROM logs", &mut db).unwrap();
+        assert_eq!(db.table(&TableName::new("logs").unwrap()).unwrap().rows().len(), 6);
+    }
+
+    #[test]
+    fn insert_conflicts_are_ignored_replaced_or_updated() {
+        use crate::frontend::{lexer::lexer, parser::Parser};
+
+        let run = |sql: &str, db: &mut Database| {
+            let mut results = Executor::execute_script(Parser::default().parse_script(&lexer(sql).unwrap()).unwrap(), db);
+            results.pop().unwrap()
+        };
+        let rows = |sql: &str, db: &mut Database| match run(sql, db).unwrap() {
+            Output::Rows { rows, .. } => rows.into_iter().map(|row| row.values().clone()).collect::<Vec<_>>(),
+            other => panic!("Expected rows, got {:?}", other),
+        };
+        let mut db = setup_db();
+        run("CREATE TABLE counts (word Text PRIMARY KEY, n Int, source Text)", &mut db).unwrap();
+        run("INSERT INTO counts VALUES ('a', 1, 'x'), ('b', 1, 'x')", &mut db).unwrap();
+        assert!(run("INSERT INTO counts VALUES ('a', 5, 'y')", &mut db).is_err());
+
+        // Loading the same rows twice leaves the table as it was
+        assert_eq!(run("INSERT OR IGNORE INTO counts VALUES ('a', 5, 'y'), ('c', 1, 'y')", &mut db), Ok(Output::Affected(1)));
+        assert_eq!(run("INSERT INTO counts VALUES ('c', 9, 'z') ON CONFLICT (word) DO NOTHING", &mut db), Ok(Output::Affected(0)));
+        assert_eq!(rows("SELECT n, source FROM counts WHERE word = 'c'", &mut db), vec![vec![Value::Int(1), Value::Text("y".into())]]);
+
+        assert_eq!(run("INSERT OR REPLACE INTO counts VALUES ('b', 7, 'z')", &mut db), Ok(Output::Affected(1)));
+        assert_eq!(rows("SELECT n, source FROM counts WHERE word = 'b'", &mut db), vec![vec![Value::Int(7), Value::Text("z".into())]]);
+
+        // The stored row is read by its columns, the new one through `excluded`
+        let upsert = "INSERT INTO counts VALUES ('a', 2, 'w'), ('d', 4, 'w') \
+                      ON CONFLICT (word) DO UPDATE SET n = counts.n + excluded.n, source = excluded.source";
+        assert_eq!(run(upsert, &mut db), Ok(Output::Affected(2)));
+        assert_eq!(
+            rows("SELECT word, n, source FROM counts WHERE word IN ('a', 'd') ORDER BY word", &mut db),
+            vec![
+                vec![Value::Text("a".into()), Value::Int(3), Value::Text("w".into())],
+                vec![Value::Text("d".into()), Value::Int(4), Value::Text("w".into())],
+            ]
+        );
+        run("INSERT INTO counts SELECT word, 10, 'q' FROM counts WHERE word = 'd' ON CONFLICT (word) DO UPDATE SET n = excluded.n", &mut db).unwrap();
+        assert_eq!(rows("SELECT n FROM counts WHERE word = 'd'", &mut db), vec![vec![Value::Int(10)]]);
+
+        // One statement cannot update the same row twice
+        let twice = "INSERT INTO counts VALUES ('a', 1, 'x'), ('a', 1, 'y') ON CONFLICT (word) DO UPDATE SET n = counts.n + excluded.n";
+        assert!(run(twice, &mut db).unwrap_err().message().contains("cannot affect a row a second time"));
+        let twice = "INSERT INTO counts VALUES ('e', 1, 'x'), ('e', 1, 'y') ON CONFLICT (word) DO UPDATE SET n = excluded.n";
+        assert!(run(twice, &mut db).is_err());
+        assert_eq!(rows("SELECT n FROM counts WHERE word IN ('a', 'e')", &mut db), vec![vec![Value::Int(3)]]);
+
+        let error = |sql: &str, db: &mut Database| run(sql, db).unwrap_err().message().to_string();
+        let conflict = "INSERT INTO counts VALUES ('a', 1, 'x') ON CONFLICT";
+        assert!(error(&format!("{} (n) DO NOTHING", conflict), &mut db).contains("neither UNIQUE nor the primary key"));
+        assert!(error(&format!("{} (word) DO UPDATE SET n = n + 1", conflict), &mut db).contains("ambiguous"));
+        assert!(error(&format!("{} (word) DO UPDATE SET nope = 1", conflict), &mut db).contains("Unknown column 'nope'"));
+        assert!(run(&format!("{} (word) DO UPDATE SET n = 'many'", conflict), &mut db).is_err());
+        assert_eq!(rows("SELECT n FROM counts WHERE word = 'a'", &mut db), vec![vec![Value::Int(3)]]);
     }
 }
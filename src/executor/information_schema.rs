@@ -0,0 +1,212 @@
+//! Virtual tables describing the database: `information_schema`.
+//!
+//! `information_schema.tables` and `information_schema.columns` are not
+//! stored anywhere. Each query reading them gets a [`Table`] built from the
+//! catalog of the [`Database`] at that moment, so that schemas can be
+//! inspected with plain SQL:
+//!
+//! ```sql
+//! SELECT column_name, data_type FROM information_schema.columns
+//! WHERE table_name = 'users' ORDER BY ordinal_position;
+//! ```
+//!
+//! Stored tables belong to the schema they were created in, or to
+//! [`DEFAULT_SCHEMA`], and the tables of an attached database to the schema
+//! named by its alias. The virtual tables can only be read.
+
+use crate::core::db::Database;
+use crate::core::row::Row;
+use crate::core::schema::{Column, Schema};
+use crate::core::table::Table;
+use crate::core::types::{ColumnName, DataType, TableName, Value};
+
+/// Name of the schema holding the virtual tables.
+pub const SCHEMA: &str = "information_schema";
+
+pub use crate::core::catalog::DEFAULT_SCHEMA;
+
+/// Returns the virtual table `name`, built from the current catalog of
+/// `db`, or `None` if `name` is not one. Names are matched ignoring case.
+///
+/// # Columns
+/// - `tables`: `table_schema`, `table_name`, `table_type` (always
+///   `BASE TABLE`) and `table_rows`, one row per table
+/// - `columns`: `table_schema`, `table_name`, `column_name`,
+///   `ordinal_position` (from 1), `data_type`, `is_nullable` (`YES` or
+///   `NO`) and `column_key` (`PRI`, `UNI` or `NULL`), one row per column
+pub fn table(name: &TableName, db: &Database) -> Option<Table> {
+    let (schema, table) = name.as_str().split_once('.')?;
+    if !schema.eq_ignore_ascii_case(SCHEMA) {
+        return None;
+    }
+    let (columns, rows) = if table.eq_ignore_ascii_case("tables") {
+        tables(db)
+    } else if table.eq_ignore_ascii_case("columns") {
+        columns(db)
+    } else {
+        return None;
+    };
+    let schema = Schema::try_new(columns).expect("virtual table columns are distinct");
+    let rows = rows
+        .into_iter()
+        .map(|values| Row::from_values(values, &schema).expect("values match the virtual schema"))
+        .collect();
+    let mut table = Table::create(name.clone(), schema);
+    table.insert_many(rows).expect("virtual tables have no constraints");
+    Some(table)
+}
+
+/// Columns and rows of `information_schema.tables`.
+fn tables(db: &Database) -> (Vec<Column>, Vec<Vec<Value>>) {
+    let columns = vec![
+        column("table_schema", DataType::Text, false),
+        column("table_name", DataType::Text, false),
+        column("table_type", DataType::Text, false),
+        column("table_rows", DataType::Int, false),
+    ];
+    let mut rows = Vec::new();
+    for (namespace, db) in namespaces(db) {
+        for name in db.table_names() {
+            let table = db.table(name).expect("listed table exists");
+            rows.push(vec![
+                text(name.schema().unwrap_or(namespace)),
+                text(name.table()),
+                text("BASE TABLE"),
+                Value::Int(table.rows().len() as i64),
+            ]);
+        }
+    }
+    (columns, rows)
+}
+
+/// Columns and rows of `information_schema.columns`.
+fn columns(db: &Database) -> (Vec<Column>, Vec<Vec<Value>>) {
+    let mut description = description_columns();
+    description.insert(1, column("ordinal_position", DataType::Int, false));
+    let mut columns = vec![column("table_schema", DataType::Text, false), column("table_name", DataType::Text, false)];
+    columns.extend(description);
+    let mut rows = Vec::new();
+    for (namespace, db) in namespaces(db) {
+        for name in db.table_names() {
+            let schema = db.table(name).expect("listed table exists").schema();
+            for (i, mut description) in describe(schema).into_iter().enumerate() {
+                description.insert(1, Value::Int(i as i64 + 1));
+                let mut row = vec![text(name.schema().unwrap_or(namespace)), text(name.table())];
+                row.extend(description);
+                rows.push(row);
+            }
+        }
+    }
+    (columns, rows)
+}
+
+/// Returns `db` under [`DEFAULT_SCHEMA`], then each database attached to
+/// it under its alias.
+fn namespaces(db: &Database) -> Vec<(&str, &Database)> {
+    let catalog = db.catalog();
+    let attached = catalog.aliases().into_iter().map(|alias| (alias, catalog.database(alias).expect("listed alias is attached")));
+    std::iter::once((DEFAULT_SCHEMA, db)).chain(attached).collect()
+}
+
+/// Returns the columns of [`describe`] rows: `column_name`, `data_type`,
+/// `is_nullable` and `column_key`, as in `information_schema.columns`.
+pub fn description_columns() -> Vec<Column> {
+    vec![
+        column("column_name", DataType::Text, false),
+        column("data_type", DataType::Text, false),
+        column("is_nullable", DataType::Text, false),
+        column("column_key", DataType::Text, true),
+    ]
+}
+
+/// Describes each column of `schema`, in order, with the values of
+/// [`description_columns`].
+pub fn describe(schema: &Schema) -> Vec<Vec<Value>> {
+    schema
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(i, col)| {
+            let key = if schema.primary_key() == Some(i) {
+                text("PRI")
+            } else if col.unique {
+                text("UNI")
+            } else {
+                Value::Null
+            };
+            vec![
+                text(col.name.as_str()),
+                text(&format!("{:?}", col.dtype)),
+                text(if col.nullable { "YES" } else { "NO" }),
+                key,
+            ]
+        })
+        .collect()
+}
+
+fn column(name: &str, dtype: DataType, nullable: bool) -> Column {
+    Column::new(ColumnName::new(name).expect("valid column name"), dtype).with_nullable(nullable)
+}
+
+fn text(value: &str) -> Value {
+    Value::Text(value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::executor::{Executor, Output};
+    use crate::frontend::lexer::lexer;
+    use crate::frontend::parser::Parser;
+
+    fn run(db: &mut Database, sql: &str) -> Output {
+        let mut outputs: Vec<Output> = Executor::execute_script(Parser::default().parse_script(&lexer(sql).unwrap()).unwrap(), db)
+            .into_iter()
+            .map(|result| result.unwrap())
+            .collect();
+        outputs.pop().unwrap()
+    }
+
+    fn values(output: Output) -> Vec<Vec<Value>> {
+        match output {
+            Output::Rows { rows, .. } => rows.into_iter().map(|row| row.values().clone()).collect(),
+            other => panic!("Expected rows, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn describes_tables_and_columns_with_sql() {
+        let mut db = Database::new();
+        run(&mut db, "CREATE TABLE users (id Int PRIMARY KEY, email Text UNIQUE, age Int); INSERT INTO users VALUES (1, 'a@b.c', NULL)");
+        run(&mut db, "CREATE TABLE orders (id Int)");
+
+        assert_eq!(
+            values(run(&mut db, "SELECT table_name, table_rows FROM information_schema.tables")),
+            vec![vec![text("orders"), Value::Int(0)], vec![text("users"), Value::Int(1)]]
+        );
+        assert_eq!(
+            values(run(
+                &mut db,
+                "SELECT column_name, data_type, is_nullable, column_key FROM INFORMATION_SCHEMA.COLUMNS \
+                 WHERE table_name = 'users' ORDER BY ordinal_position DESC"
+            )),
+            vec![
+                vec![text("age"), text("Int"), text("YES"), Value::Null],
+                vec![text("email"), text("Text"), text("YES"), text("UNI")],
+                vec![text("id"), text("Int"), text("NO"), text("PRI")],
+            ]
+        );
+
+        // The tables follow the catalog, and join like stored ones
+        run(&mut db, "ALTER TABLE orders ADD COLUMN total Float");
+        assert_eq!(
+            values(run(
+                &mut db,
+                "SELECT c.column_name FROM information_schema.tables t \
+                 JOIN information_schema.columns c ON t.table_name = c.table_name WHERE t.table_rows = 0"
+            )),
+            vec![vec![text("id")], vec![text("total")]]
+        );
+        assert!(table(&TableName::qualified(SCHEMA, "views").unwrap(), &db).is_none());
+    }
+}
@@ -0,0 +1,242 @@
+//! Row locks for transactions, with deadlock detection.
+//!
+//! A [`LockManager`] grants locks on rows, named by a [`RowId`], to
+//! transactions named by a [`TxId`]. A row can be locked in
+//! [`LockMode::Shared`] by any number of transactions, or in
+//! [`LockMode::Exclusive`] by one. A transaction asking for a lock that
+//! others hold in a conflicting mode waits until they release it; it keeps
+//! its locks until [`LockManager::release_all`], at the end of the
+//! transaction.
+//!
+//! While a transaction waits, the manager records the transactions it waits
+//! for: together, these records form the waits-for graph. A request that
+//! would close a cycle in the graph, such as two transactions each waiting
+//! for a row the other has locked, can never be granted. It is refused with
+//! [`SqlError::Deadlock`], and the transaction that made it should be rolled
+//! back, releasing its locks so that the others can go on.
+
+use std::collections::{HashMap, HashSet};
+use std::sync::{Condvar, Mutex, MutexGuard, PoisonError};
+
+use crate::core::error::{SqlError, SqlResult};
+use crate::core::table::{Rowid, Table};
+use crate::core::types::TableName;
+
+/// Identifies a transaction.
+pub type TxId = u64;
+
+/// Identifies a row of a table by its [`Rowid`], which the row keeps when
+/// it is updated: rows with the same values are still different rows.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct RowId {
+    pub table: TableName,
+    pub rowid: Rowid,
+}
+
+impl RowId {
+    /// Returns the identifier of the row at position `pos` of `table`.
+    pub fn at(table: &Table, pos: usize) -> RowId {
+        RowId { table: table.name().clone(), rowid: table.rowids()[pos] }
+    }
+}
+
+/// How a row is locked.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockMode {
+    /// To read it: other transactions may read it too, but not change it.
+    Shared,
+    /// To change it: other transactions may neither read nor change it.
+    Exclusive,
+}
+
+/// Grants row locks to transactions (see the module docs).
+#[derive(Debug, Default)]
+pub struct LockManager {
+    state: Mutex<LockTable>,
+    released: Condvar,
+}
+
+/// The locks held, and who waits for whom.
+#[derive(Debug, Default)]
+struct LockTable {
+    /// The transactions holding each locked row, and their mode.
+    rows: HashMap<RowId, HashMap<TxId, LockMode>>,
+    /// The rows each transaction has locked.
+    held: HashMap<TxId, HashSet<RowId>>,
+    /// The waits-for graph: the transactions each waiting one waits for.
+    waits_for: HashMap<TxId, HashSet<TxId>>,
+}
+
+impl LockManager {
+    /// Creates a manager with no locks.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Locks `row` for `tx` in `mode`, waiting while other transactions
+    /// hold it in a conflicting mode.
+    ///
+    /// A transaction already holding the lock keeps it; asking for
+    /// [`LockMode::Exclusive`] while holding [`LockMode::Shared`] upgrades it.
+    ///
+    /// # Returns
+    /// `Ok(true)` if the lock was granted after waiting, `Ok(false)` if at
+    /// once, or `Err(SqlError::Deadlock)` if waiting would never end. On
+    /// error, `tx` keeps the locks it already holds.
+    pub fn lock(&self, tx: TxId, row: &RowId, mode: LockMode) -> SqlResult<bool> {
+        let mut state = self.state();
+        let mut waited = false;
+        loop {
+            let blockers = state.blockers(tx, row, mode);
+            if blockers.is_empty() {
+                state.waits_for.remove(&tx);
+                state.grant(tx, row, mode);
+                return Ok(waited);
+            }
+            if blockers.iter().any(|&other| state.reaches(other, tx)) {
+                state.waits_for.remove(&tx);
+                return Err(SqlError::new_deadlock(&format!(
+                    "Deadlock detected: transaction {} and the transactions it waits for wait for each other; it was rolled back",
+                    tx
+                )));
+            }
+            state.waits_for.insert(tx, blockers);
+            state = self.released.wait(state).unwrap_or_else(PoisonError::into_inner);
+            waited = true;
+        }
+    }
+
+    /// Releases every lock held by `tx`, waking the transactions waiting
+    /// for them.
+    pub fn release_all(&self, tx: TxId) {
+        let mut state = self.state();
+        for row in state.held.remove(&tx).unwrap_or_default() {
+            if let Some(holders) = state.rows.get_mut(&row) {
+                holders.remove(&tx);
+                if holders.is_empty() {
+                    state.rows.remove(&row);
+                }
+            }
+        }
+        state.waits_for.remove(&tx);
+        for waited in state.waits_for.values_mut() {
+            waited.remove(&tx);
+        }
+        self.released.notify_all();
+    }
+
+    /// Returns the mode in which `tx` holds `row`, if it does.
+    pub fn mode(&self, tx: TxId, row: &RowId) -> Option<LockMode> {
+        self.state().rows.get(row).and_then(|holders| holders.get(&tx).copied())
+    }
+
+    fn state(&self) -> MutexGuard<'_, LockTable> {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl LockTable {
+    /// Returns the other transactions holding `row` in a mode conflicting
+    /// with `mode`.
+    fn blockers(&self, tx: TxId, row: &RowId, mode: LockMode) -> HashSet<TxId> {
+        let Some(holders) = self.rows.get(row) else { return HashSet::new() };
+        holders
+            .iter()
+            .filter(|&(&holder, &held)| holder != tx && (mode == LockMode::Exclusive || held == LockMode::Exclusive))
+            .map(|(&holder, _)| holder)
+            .collect()
+    }
+
+    fn grant(&mut self, tx: TxId, row: &RowId, mode: LockMode) {
+        let held = self.rows.entry(row.clone()).or_default().entry(tx).or_insert(mode);
+        if mode == LockMode::Exclusive {
+            *held = LockMode::Exclusive;
+        }
+        self.held.entry(tx).or_default().insert(row.clone());
+    }
+
+    /// Returns `true` if `from` is `to` or waits, directly or through other
+    /// transactions, for `to`.
+    fn reaches(&self, from: TxId, to: TxId) -> bool {
+        let mut seen = HashSet::new();
+        let mut stack = vec![from];
+        while let Some(tx) = stack.pop() {
+            if tx == to {
+                return true;
+            }
+            if seen.insert(tx) {
+                stack.extend(self.waits_for.get(&tx).into_iter().flatten());
+            }
+        }
+        false
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::mpsc;
+    use std::sync::Arc;
+    use std::thread;
+    use std::time::Duration;
+
+    fn row(rowid: Rowid) -> RowId {
+        RowId { table: TableName::new("t").unwrap(), rowid }
+    }
+
+    #[test]
+    fn shared_locks_coexist_and_exclusive_ones_wait() {
+        let locks = Arc::new(LockManager::new());
+        assert_eq!(locks.lock(1, &row(1), LockMode::Shared), Ok(false));
+        assert_eq!(locks.lock(2, &row(1), LockMode::Shared), Ok(false));
+        assert_eq!(locks.lock(1, &row(2), LockMode::Exclusive), Ok(false));
+        assert_eq!(locks.lock(1, &row(2), LockMode::Shared), Ok(false));
+        assert_eq!(locks.mode(1, &row(2)), Some(LockMode::Exclusive));
+
+        // Transaction 3 waits for both readers of row 1
+        let (sender, receiver) = mpsc::channel();
+        let waiting = Arc::clone(&locks);
+        let writer = thread::spawn(move || sender.send(waiting.lock(3, &row(1), LockMode::Exclusive)).unwrap());
+        assert!(receiver.recv_timeout(Duration::from_millis(50)).is_err());
+        locks.release_all(1);
+        assert!(receiver.recv_timeout(Duration::from_millis(50)).is_err());
+        locks.release_all(2);
+        assert_eq!(receiver.recv().unwrap(), Ok(true));
+        writer.join().unwrap();
+
+        assert_eq!(locks.mode(3, &row(1)), Some(LockMode::Exclusive));
+        assert_eq!(locks.mode(1, &row(2)), None);
+        assert_eq!(locks.lock(4, &row(2), LockMode::Exclusive), Ok(false));
+    }
+
+    #[test]
+    fn a_cycle_of_waits_aborts_the_transaction_closing_it() {
+        let locks = Arc::new(LockManager::new());
+        locks.lock(1, &row(1), LockMode::Exclusive).unwrap();
+        locks.lock(2, &row(2), LockMode::Exclusive).unwrap();
+
+        let waiting = Arc::clone(&locks);
+        let first = thread::spawn(move || waiting.lock(1, &row(2), LockMode::Exclusive));
+        while !locks.state().waits_for.contains_key(&1) {
+            thread::yield_now();
+        }
+        let error = locks.lock(2, &row(1), LockMode::Exclusive).unwrap_err();
+        assert_eq!(error.code(), "E3002");
+
+        // Rolling back the victim lets the other transaction go on
+        locks.release_all(2);
+        assert_eq!(first.join().unwrap(), Ok(true));
+
+        // Two readers upgrading the same row deadlock too
+        locks.lock(5, &row(3), LockMode::Shared).unwrap();
+        locks.lock(6, &row(3), LockMode::Shared).unwrap();
+        let waiting = Arc::clone(&locks);
+        let upgrade = thread::spawn(move || waiting.lock(5, &row(3), LockMode::Exclusive));
+        while !locks.state().waits_for.contains_key(&5) {
+            thread::yield_now();
+        }
+        assert!(matches!(locks.lock(6, &row(3), LockMode::Exclusive), Err(SqlError::Deadlock { .. })));
+        locks.release_all(6);
+        assert_eq!(upgrade.join().unwrap(), Ok(true));
+    }
+}
@@ -0,0 +1,310 @@
+//! Pull-based query operators (the "Volcano" model).
+//!
+//! A SELECT runs as a pipeline of [`Operator`]s: each one pulls tuples from
+//! its input with [`Operator::next`] and hands them on one at a time, so a
+//! query only holds the rows it is working on. A [`Sort`] must see all its
+//! input before producing anything, and buffers it; every other operator
+//! streams.
+//!
+//! Operators only move tuples around. What a predicate or a projection
+//! means is decided by the executor, which passes it in as a closure.
+
+use std::cmp::Ordering;
+use std::collections::HashSet;
+
+use crate::core::error::SqlResult;
+use crate::core::table::Table;
+use crate::core::types::Value;
+use crate::frontend::ast::SortDirection;
+
+/// A row flowing through a pipeline: the values of one combined row or of
+/// one output row.
+pub type Tuple = Vec<Value>;
+
+/// A node of a query pipeline.
+pub trait Operator {
+    /// Returns the next tuple, or `None` once the input is exhausted.
+    fn next(&mut self) -> SqlResult<Option<Tuple>>;
+}
+
+/// An operator owned by the pipeline it belongs to.
+pub type BoxedOperator<'a> = Box<dyn Operator + 'a>;
+
+/// A condition evaluated on each tuple.
+pub type RowPredicate<'a> = Box<dyn FnMut(&[Value]) -> SqlResult<bool> + 'a>;
+
+/// A function computing one tuple from another.
+pub type Mapper<'a> = Box<dyn FnMut(&[Value]) -> SqlResult<Tuple> + 'a>;
+
+/// Reads rows of a table, in the order of the given positions.
+pub struct Scan<'a> {
+    table: &'a Table,
+    positions: std::vec::IntoIter<usize>,
+}
+
+impl<'a> Scan<'a> {
+    /// Creates a scan of the rows of `table` at `positions`, such as those
+    /// returned by an index.
+    pub fn new(table: &'a Table, positions: Vec<usize>) -> Self {
+        Scan { table, positions: positions.into_iter() }
+    }
+}
+
+impl Operator for Scan<'_> {
+    fn next(&mut self) -> SqlResult<Option<Tuple>> {
+        Ok(self.positions.next().map(|pos| self.table.rows()[pos].values().clone()))
+    }
+}
+
+/// Yields tuples that were computed beforehand, e.g. by an aggregation.
+pub struct Values {
+    tuples: std::vec::IntoIter<Tuple>,
+}
+
+impl Values {
+    pub fn new(tuples: Vec<Tuple>) -> Self {
+        Values { tuples: tuples.into_iter() }
+    }
+}
+
+impl Operator for Values {
+    fn next(&mut self) -> SqlResult<Option<Tuple>> {
+        Ok(self.tuples.next())
+    }
+}
+
+/// Nested-loop join: extends each input tuple with every tuple of the
+/// right side, keeping the combinations accepted by the join condition.
+pub struct NestedLoopJoin<'a> {
+    input: BoxedOperator<'a>,
+    right: Vec<Tuple>,
+    on: RowPredicate<'a>,
+    left: Option<Tuple>,
+    next_right: usize,
+}
+
+impl<'a> NestedLoopJoin<'a> {
+    /// Joins `input` with the tuples of `right`, read once beforehand; `on`
+    /// sees the combined tuple.
+    pub fn new(input: BoxedOperator<'a>, right: Vec<Tuple>, on: RowPredicate<'a>) -> Self {
+        NestedLoopJoin { input, right, on, left: None, next_right: 0 }
+    }
+}
+
+impl Operator for NestedLoopJoin<'_> {
+    fn next(&mut self) -> SqlResult<Option<Tuple>> {
+        loop {
+            let Some(left) = &self.left else {
+                match self.input.next()? {
+                    Some(tuple) => {
+                        self.left = Some(tuple);
+                        self.next_right = 0;
+                        continue;
+                    }
+                    None => return Ok(None),
+                }
+            };
+            let Some(tuple) = self.right.get(self.next_right) else {
+                self.left = None;
+                continue;
+            };
+            self.next_right += 1;
+            let mut combined = left.clone();
+            combined.extend(tuple.iter().cloned());
+            if (self.on)(&combined)? {
+                return Ok(Some(combined));
+            }
+        }
+    }
+}
+
+/// Keeps the tuples accepted by a predicate.
+pub struct Filter<'a> {
+    input: BoxedOperator<'a>,
+    predicate: RowPredicate<'a>,
+}
+
+impl<'a> Filter<'a> {
+    pub fn new(input: BoxedOperator<'a>, predicate: RowPredicate<'a>) -> Self {
+        Filter { input, predicate }
+    }
+}
+
+impl Operator for Filter<'_> {
+    fn next(&mut self) -> SqlResult<Option<Tuple>> {
+        while let Some(tuple) = self.input.next()? {
+            if (self.predicate)(&tuple)? {
+                return Ok(Some(tuple));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Replaces each tuple with the one computed from it.
+pub struct Project<'a> {
+    input: BoxedOperator<'a>,
+    mapper: Mapper<'a>,
+}
+
+impl<'a> Project<'a> {
+    pub fn new(input: BoxedOperator<'a>, mapper: Mapper<'a>) -> Self {
+        Project { input, mapper }
+    }
+}
+
+impl Operator for Project<'_> {
+    fn next(&mut self) -> SqlResult<Option<Tuple>> {
+        match self.input.next()? {
+            Some(tuple) => (self.mapper)(&tuple).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Sorts its whole input on keys computed once per tuple.
+pub struct Sort<'a> {
+    input: BoxedOperator<'a>,
+    key: Mapper<'a>,
+    directions: Vec<(usize, SortDirection)>,
+    sorted: Option<std::vec::IntoIter<Tuple>>,
+}
+
+impl<'a> Sort<'a> {
+    /// Sorts `input` on the tuples computed by `key`, compared by
+    /// `directions` (see [`compare_rows`]).
+    pub fn new(input: BoxedOperator<'a>, key: Mapper<'a>, directions: Vec<(usize, SortDirection)>) -> Self {
+        Sort { input, key, directions, sorted: None }
+    }
+}
+
+impl Operator for Sort<'_> {
+    fn next(&mut self) -> SqlResult<Option<Tuple>> {
+        if self.sorted.is_none() {
+            let mut keyed = Vec::new();
+            while let Some(tuple) = self.input.next()? {
+                keyed.push(((self.key)(&tuple)?, tuple));
+            }
+            keyed.sort_by(|a, b| compare_rows(&a.0, &b.0, &self.directions));
+            self.sorted = Some(keyed.into_iter().map(|(_, tuple)| tuple).collect::<Vec<_>>().into_iter());
+        }
+        Ok(self.sorted.as_mut().and_then(Iterator::next))
+    }
+}
+
+/// Drops tuples equal to an earlier one.
+pub struct Distinct<'a> {
+    input: BoxedOperator<'a>,
+    seen: HashSet<Tuple>,
+}
+
+impl<'a> Distinct<'a> {
+    pub fn new(input: BoxedOperator<'a>) -> Self {
+        Distinct { input, seen: HashSet::new() }
+    }
+}
+
+impl Operator for Distinct<'_> {
+    fn next(&mut self) -> SqlResult<Option<Tuple>> {
+        while let Some(tuple) = self.input.next()? {
+            if self.seen.insert(tuple.clone()) {
+                return Ok(Some(tuple));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Skips `offset` tuples, then passes at most `limit` on. The input is not
+/// pulled any further once the limit is reached.
+pub struct Limit<'a> {
+    input: BoxedOperator<'a>,
+    offset: usize,
+    remaining: usize,
+}
+
+impl<'a> Limit<'a> {
+    pub fn new(input: BoxedOperator<'a>, offset: usize, limit: usize) -> Self {
+        Limit { input, offset, remaining: limit }
+    }
+}
+
+impl Operator for Limit<'_> {
+    fn next(&mut self) -> SqlResult<Option<Tuple>> {
+        while self.offset > 0 {
+            if self.input.next()?.is_none() {
+                return Ok(None);
+            }
+            self.offset -= 1;
+        }
+        if self.remaining == 0 {
+            return Ok(None);
+        }
+        self.remaining -= 1;
+        self.input.next()
+    }
+}
+
+/// Orders two rows by the given `(column index, direction)` keys.
+///
+/// Values are compared with [`Value::compare`]; `NULL` sorts before every
+/// other value (so it comes last under `DESC`). The sort is stable, so rows
+/// that tie on every key keep their insertion order.
+pub fn compare_rows(a: &[Value], b: &[Value], keys: &[(usize, SortDirection)]) -> Ordering {
+    for &(idx, direction) in keys {
+        let (x, y) = (&a[idx], &b[idx]);
+        let ordering = match (x.is_null(), y.is_null()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => x.compare(y).unwrap_or(Ordering::Equal),
+        };
+        let ordering = match direction {
+            SortDirection::Asc => ordering,
+            SortDirection::Desc => ordering.reverse(),
+        };
+        if ordering != Ordering::Equal {
+            return ordering;
+        }
+    }
+    Ordering::Equal
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn drain(mut op: impl Operator) -> Vec<Tuple> {
+        let mut out = Vec::new();
+        while let Some(tuple) = op.next().unwrap() {
+            out.push(tuple);
+        }
+        out
+    }
+
+    #[test]
+    fn pipeline_streams_until_limit() {
+        let pulled = Cell::new(0);
+        let source = Values::new((0..100).map(|i| vec![Value::Int(i % 10)]).collect());
+        let counted = Project::new(
+            Box::new(source),
+            Box::new(|t| {
+                pulled.set(pulled.get() + 1);
+                Ok(t.to_vec())
+            }),
+        );
+        let odd = Filter::new(Box::new(counted), Box::new(|t| Ok(t[0] != Value::Int(0))));
+        let limited = Limit::new(Box::new(Distinct::new(Box::new(odd))), 1, 3);
+
+        assert_eq!(drain(limited), vec![vec![Value::Int(2)], vec![Value::Int(3)], vec![Value::Int(4)]]);
+        assert_eq!(pulled.get(), 5);
+    }
+
+    #[test]
+    fn sort_orders_on_keys_with_nulls_first() {
+        let source = Values::new(vec![vec![Value::Int(2)], vec![Value::Null], vec![Value::Int(1)]]);
+        let sorted = Sort::new(Box::new(source), Box::new(|t| Ok(t.to_vec())), vec![(0, SortDirection::Desc)]);
+        assert_eq!(drain(sorted), vec![vec![Value::Int(2)], vec![Value::Int(1)], vec![Value::Null]]);
+    }
+}
@@ -0,0 +1,340 @@
+//! Access path selection for SELECT.
+//!
+//! Before reading a table, a query looks for a condition of the filter
+//! applied to its rows that an index of that table can answer: an equality
+//! (`col = v`) or a range (`col < v`, `col BETWEEN a AND b`, ...) on an
+//! indexed column, combined with the rest of the clause by `AND`. Hash
+//! indexes only answer equalities, and are preferred for them. The index
+//! then yields the candidate rows instead of a full scan. The whole clause
+//! is still evaluated on every candidate, so the access path only changes
+//! how many rows are read, never the result.
+//!
+//! Once a table has been analyzed (`ANALYZE t`), its statistics decide
+//! between its candidate indexes and a full scan: an index that would
+//! return a large share of the rows costs more than scanning them.
+
+use std::ops::Bound;
+
+use crate::core::index::{Index, IndexKind};
+use crate::core::stats::TableStats;
+use crate::core::table::Table;
+use crate::core::types::Value;
+use crate::frontend::ast::CompareOp;
+use crate::planner::logical::{BoundColumn, Predicate, ScalarExpr};
+
+/// Estimated cost of reading one row through an index, relative to reading
+/// one row in a scan: the index is searched and its positions collected
+/// before any row is read.
+pub const INDEX_ROW_COST: f64 = 3.0;
+
+/// How the rows of a table are read.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Access {
+    /// Read every row.
+    Scan,
+    /// Read the rows holding `value` through the index `index`.
+    IndexLookup { index: String, value: Value },
+    /// Read the rows whose value lies between `low` and `high` through the
+    /// index `index`.
+    IndexRange { index: String, low: Bound<Value>, high: Bound<Value> },
+}
+
+impl Access {
+    /// Chooses how to read `table` for rows filtered by `predicate`, whose
+    /// columns start with those of `table`.
+    ///
+    /// Each equality on an indexed column, and the range conditions on each
+    /// ordered index (intersected), give a candidate index read. Conditions
+    /// that cannot be checked against an index, such as a comparison with
+    /// another column, fall back to a scan.
+    ///
+    /// Once the table has been analyzed, the candidate reading the fewest
+    /// rows is chosen if it is cheaper than a scan (see [`INDEX_ROW_COST`]);
+    /// without statistics, the first equality is chosen, or else the first
+    /// range.
+    pub fn choose(table: &Table, predicate: Option<&Predicate>) -> Access {
+        let mut conditions = Vec::new();
+        if let Some(predicate) = predicate {
+            collect_conditions(predicate, table, &mut conditions);
+        }
+        let candidates = candidates(&conditions);
+
+        let Some(stats) = table.stats() else {
+            return candidates.into_iter().next().unwrap_or(Access::Scan);
+        };
+        let mut best = (stats.row_count as f64, Access::Scan);
+        for candidate in candidates {
+            let cost = candidate.estimate_rows(table, stats) * INDEX_ROW_COST;
+            if cost < best.0 {
+                best = (cost, candidate);
+            }
+        }
+        best.1
+    }
+
+    /// Estimates from `stats` how many rows of `table` this access reads.
+    pub fn estimate_rows(&self, table: &Table, stats: &TableStats) -> f64 {
+        let rows = stats.row_count as f64;
+        let column = |index: &str| table.index(index).map(Index::column);
+        match self {
+            Access::Scan => rows,
+            Access::IndexLookup { index, value } => {
+                column(index).map_or(rows, |c| rows * stats.eq_selectivity(c, value))
+            }
+            Access::IndexRange { index, low, high } => {
+                column(index).map_or(rows, |c| rows * stats.range_selectivity(c, low.as_ref(), high.as_ref()))
+            }
+        }
+    }
+
+    /// Returns the positions of the rows of `table` to read, in table order.
+    pub fn positions(&self, table: &Table) -> Vec<usize> {
+        match self {
+            Access::Scan => (0..table.rows().len()).collect(),
+            Access::IndexLookup { index, value } => table.index(index).map_or(Vec::new(), |i| i.lookup(value).to_vec()),
+            Access::IndexRange { index, low, high } => {
+                table.index(index).map_or(Vec::new(), |i| i.range(low.as_ref(), high.as_ref()))
+            }
+        }
+    }
+
+    /// Describes the access path for EXPLAIN, e.g.
+    /// `Index lookup on users using users_name (name = 'Ann')`.
+    pub fn describe(&self, table: &Table) -> String {
+        let column = |index: &str| {
+            table
+                .index(index)
+                .map_or("?", |i| table.schema().columns()[i.column()].name.as_str())
+                .to_string()
+        };
+        match self {
+            Access::Scan => format!("Scan {}", table.name().as_str()),
+            Access::IndexLookup { index, value } => format!(
+                "{} lookup on {} using {} ({} = {})",
+                match table.index(index).map(Index::kind) {
+                    Some(IndexKind::Hash) => "Hash index",
+                    _ => "Index",
+                },
+                table.name().as_str(),
+                index,
+                column(index),
+                literal(value)
+            ),
+            Access::IndexRange { index, low, high } => {
+                let column = column(index);
+                let mut bounds = Vec::new();
+                match low {
+                    Bound::Included(v) => bounds.push(format!("{} >= {}", column, literal(v))),
+                    Bound::Excluded(v) => bounds.push(format!("{} > {}", column, literal(v))),
+                    Bound::Unbounded => {}
+                }
+                match high {
+                    Bound::Included(v) => bounds.push(format!("{} <= {}", column, literal(v))),
+                    Bound::Excluded(v) => bounds.push(format!("{} < {}", column, literal(v))),
+                    Bound::Unbounded => {}
+                }
+                format!("Index range scan on {} using {} ({})", table.name().as_str(), index, bounds.join(" AND "))
+            }
+        }
+    }
+}
+
+/// Builds the candidate index reads for `conditions`: one lookup per
+/// equality, in order, then one range read per index with range conditions.
+fn candidates(conditions: &[(&Index, CompareOp, Value)]) -> Vec<Access> {
+    let mut lookups = Vec::new();
+    let mut ranges: Vec<(&str, Bound<Value>, Bound<Value>)> = Vec::new();
+    for (index, op, value) in conditions {
+        if *op == CompareOp::Eq {
+            lookups.push(Access::IndexLookup { index: index.name().to_string(), value: value.clone() });
+            continue;
+        }
+        let at = match ranges.iter().position(|(name, _, _)| *name == index.name()) {
+            Some(at) => at,
+            None => {
+                ranges.push((index.name(), Bound::Unbounded, Bound::Unbounded));
+                ranges.len() - 1
+            }
+        };
+        let (_, low, high) = &mut ranges[at];
+        match op {
+            CompareOp::Gt => *low = tighter(low.clone(), Bound::Excluded(value.clone()), true),
+            CompareOp::GtEq => *low = tighter(low.clone(), Bound::Included(value.clone()), true),
+            CompareOp::Lt => *high = tighter(high.clone(), Bound::Excluded(value.clone()), false),
+            CompareOp::LtEq => *high = tighter(high.clone(), Bound::Included(value.clone()), false),
+            CompareOp::Eq | CompareOp::NotEq => {}
+        }
+    }
+    lookups.extend(ranges.into_iter().map(|(index, low, high)| Access::IndexRange { index: index.to_string(), low, high }));
+    lookups
+}
+
+/// Collects the conditions of `predicate`, reached through `AND` only, that
+/// an index of `table` can answer, as `(index, operator, value)`.
+fn collect_conditions<'t>(predicate: &Predicate, table: &'t Table, out: &mut Vec<(&'t Index, CompareOp, Value)>) {
+    match predicate {
+        Predicate::And(left, right) => {
+            collect_conditions(left, table, out);
+            collect_conditions(right, table, out);
+        }
+        Predicate::Compare { column, op, value } if *op != CompareOp::NotEq => {
+            if let Some((index, value)) = indexed_value(column, value, *op == CompareOp::Eq, table) {
+                out.push((index, *op, value));
+            }
+        }
+        Predicate::Between { column, low, high, negated: false } => {
+            if let (Some((index, low)), Some((_, high))) =
+                (indexed_value(column, low, false, table), indexed_value(column, high, false, table))
+            {
+                out.push((index, CompareOp::GtEq, low));
+                out.push((index, CompareOp::LtEq, high));
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the index on `column` and the value of `expr`, if `column`
+/// belongs to `table` and is indexed and `expr` is a non-`NULL` literal of
+/// the column type (the planner folds constants into such literals).
+///
+/// For an equality (`equality`), a hash index is preferred; otherwise only
+/// ordered indexes qualify.
+fn indexed_value<'t>(
+    column: &BoundColumn,
+    expr: &ScalarExpr,
+    equality: bool,
+    table: &'t Table,
+) -> Option<(&'t Index, Value)> {
+    let idx = column.index;
+    // Columns past the table's own belong to tables joined to it
+    if idx >= table.schema().columns().len() {
+        return None;
+    }
+    let on_column = |kind: IndexKind| table.indexes().iter().find(|i| i.column() == idx && i.kind() == kind);
+    let index = if equality {
+        on_column(IndexKind::Hash).or_else(|| on_column(IndexKind::BTree))?
+    } else {
+        on_column(IndexKind::BTree)?
+    };
+    let ScalarExpr::Literal(value) = expr else { return None };
+    (!value.is_null() && column.dtype.matches(value)).then(|| (index, value.clone()))
+}
+
+/// Returns the more selective of two bounds on the same side: the greater
+/// one for a lower bound, the smaller one for an upper bound.
+fn tighter(current: Bound<Value>, new: Bound<Value>, lower: bool) -> Bound<Value> {
+    let (a, b) = match (&current, &new) {
+        (Bound::Unbounded, _) => return new,
+        (_, Bound::Unbounded) => return current,
+        (Bound::Included(a) | Bound::Excluded(a), Bound::Included(b) | Bound::Excluded(b)) => (a, b),
+    };
+    match a.compare(b) {
+        Some(std::cmp::Ordering::Less) => if lower { new } else { current },
+        Some(std::cmp::Ordering::Greater) => if lower { current } else { new },
+        // On equal values an exclusive bound is the tighter one
+        _ if matches!(new, Bound::Excluded(_)) => new,
+        _ => current,
+    }
+}
+
+/// Formats a value as a SQL literal.
+fn literal(value: &Value) -> String {
+    match value {
+        Value::Text(s) => format!("'{}'", s.replace('\'', "''")),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::row::Row;
+    use crate::core::schema::{Column, Schema};
+    use crate::core::types::{ColumnName, DataType, TableName};
+    use crate::frontend::ast::ASTNode;
+    use crate::frontend::lexer::lexer;
+    use crate::frontend::parser::Parser;
+    use crate::planner::planner::{plan_predicate, Scope};
+
+    fn predicate(sql: &str, table: &Table) -> Predicate {
+        let mut scope = Scope::default();
+        scope.push(table.name(), table.schema()).unwrap();
+        match Parser::default().parse(&lexer(sql).unwrap()).unwrap() {
+            ASTNode::Select(stmt) => plan_predicate(&stmt.where_clause.unwrap(), &scope).unwrap(),
+            other => panic!("not a SELECT: {:?}", other),
+        }
+    }
+
+    #[test]
+    fn chooses_index_for_equality_and_ranges() {
+        let schema = Schema::try_new(vec![
+            Column::new(ColumnName::new("n").unwrap(), DataType::Int),
+            Column::new(ColumnName::new("s").unwrap(), DataType::Text),
+        ])
+        .unwrap();
+        let mut table = Table::create(TableName::new("t").unwrap(), schema);
+        for n in 0..10 {
+            let row = Row::from_values(vec![Value::Int(n), Value::Text(n.to_string())], table.schema()).unwrap();
+            table.insert_checked(row).unwrap();
+        }
+        table.create_index("t_n", &ColumnName::new("n").unwrap(), IndexKind::BTree).unwrap();
+        let choose = |sql: &str| Access::choose(&table, Some(&predicate(sql, &table)));
+
+        let access = choose("SELECT * FROM t WHERE s = 'x' AND n = 4");
+        assert_eq!(access.positions(&table), vec![4]);
+        assert_eq!(access.describe(&table), "Index lookup on t using t_n (n = 4)");
+
+        let access = choose("SELECT * FROM t WHERE n > 2 AND n <= 8 AND n < 5");
+        assert_eq!(access.positions(&table), vec![3, 4]);
+        assert_eq!(access.describe(&table), "Index range scan on t using t_n (n > 2 AND n < 5)");
+        assert_eq!(choose("SELECT * FROM t WHERE n BETWEEN 7 AND 20").positions(&table), vec![7, 8, 9]);
+
+        assert_eq!(choose("SELECT * FROM t WHERE n = 1 OR n = 2"), Access::Scan);
+        assert_eq!(choose("SELECT * FROM t WHERE n != 1"), Access::Scan);
+        assert_eq!(choose("SELECT * FROM t WHERE n = n + 0"), Access::Scan);
+        assert_eq!(choose("SELECT * FROM t WHERE s = 'x'").describe(&table), "Scan t");
+
+        table.create_index("t_n_hash", &ColumnName::new("n").unwrap(), IndexKind::Hash).unwrap();
+        table.create_index("t_s", &ColumnName::new("s").unwrap(), IndexKind::Hash).unwrap();
+        let choose = |sql: &str| Access::choose(&table, Some(&predicate(sql, &table)));
+        assert_eq!(
+            choose("SELECT * FROM t WHERE n = 4").describe(&table),
+            "Hash index lookup on t using t_n_hash (n = 4)"
+        );
+        assert_eq!(choose("SELECT * FROM t WHERE s = '7'").positions(&table), vec![7]);
+        assert_eq!(choose("SELECT * FROM t WHERE s > '7'"), Access::Scan);
+    }
+
+    #[test]
+    fn statistics_choose_between_scan_and_index() {
+        let schema = Schema::try_new(vec![
+            Column::new(ColumnName::new("id").unwrap(), DataType::Int),
+            Column::new(ColumnName::new("flag").unwrap(), DataType::Int),
+        ])
+        .unwrap();
+        let mut table = Table::create(TableName::new("t").unwrap(), schema);
+        for id in 0..100 {
+            let row = Row::from_values(vec![Value::Int(id), Value::Int(id % 2)], table.schema()).unwrap();
+            table.insert_checked(row).unwrap();
+        }
+        table.create_index("t_id", &ColumnName::new("id").unwrap(), IndexKind::BTree).unwrap();
+        table.create_index("t_flag", &ColumnName::new("flag").unwrap(), IndexKind::Hash).unwrap();
+        let describe = |table: &Table, sql: &str| Access::choose(table, Some(&predicate(sql, table))).describe(table);
+
+        // Without statistics any usable index is taken
+        assert_eq!(describe(&table, "SELECT * FROM t WHERE flag = 1"), "Hash index lookup on t using t_flag (flag = 1)");
+        assert_eq!(describe(&table, "SELECT * FROM t WHERE id > 10"), "Index range scan on t using t_id (id > 10)");
+
+        table.analyze();
+        // Half the rows: cheaper to scan
+        assert_eq!(describe(&table, "SELECT * FROM t WHERE flag = 1"), "Scan t");
+        assert_eq!(describe(&table, "SELECT * FROM t WHERE id > 10"), "Scan t");
+        // A few rows: the index wins, and the more selective one of two
+        assert_eq!(describe(&table, "SELECT * FROM t WHERE id >= 95"), "Index range scan on t using t_id (id >= 95)");
+        assert_eq!(
+            describe(&table, "SELECT * FROM t WHERE flag = 0 AND id = 42"),
+            "Index lookup on t using t_id (id = 42)"
+        );
+    }
+}
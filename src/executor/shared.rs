@@ -0,0 +1,876 @@
+//! A database shared between threads, with multi-version concurrency
+//! control and row locks.
+//!
+//! [`Database`] is changed through `&mut` and cannot be used from several
+//! threads at once. [`SharedDatabase`] can be cloned and sent to other
+//! threads. It keeps the last committed version of the database as a
+//! [`Snapshot`]:
+//!
+//! - Statements that only read (see [`Executor::reads_only`]) run outside
+//!   transactions on the snapshot taken when they start, without any lock:
+//!   a long `SELECT` sees a consistent view of the database while writers
+//!   change it, and does not hold them up.
+//! - Every other statement runs in a transaction: the one opened by
+//!   `BEGIN`, or one of its own that commits when it ends. A transaction
+//!   works on a private copy of the version it began on, so that readers
+//!   never see its changes before `COMMIT`, and `ROLLBACK` throws them
+//!   away.
+//!
+//! Versions share the tables they have in common (see [`Database`]): a
+//! write copies only the tables it changes.
+//!
+//! # Row locks
+//! Transactions run side by side, and lock the rows they use with a
+//! [`LockManager`], by [`Rowid`](crate::core::table::Rowid): an exclusive
+//! lock on each row a statement changes or deletes, and a shared lock on
+//! each row a `SELECT` reads, that is the rows matching the conditions of
+//! its `WHERE` on their table (see [`Executor::rows_read`]). Inserted rows
+//! are not locked: other transactions cannot see them before `COMMIT`. A
+//! transaction needing a row that another one locked
+//! waits for it to end. If transactions would wait for each other forever,
+//! one of them is rolled back with
+//! [`SqlError::Deadlock`](crate::core::error::SqlError).
+//!
+//! # Conflicts
+//! A transaction reads the version it began on, with its own changes. One
+//! that changes or deletes a row that another transaction changed and
+//! committed after it began is rolled back with
+//! [`SqlError::Conflict`](crate::core::error::SqlError): the first
+//! transaction to commit a row wins. A statement run outside a transaction
+//! has done nothing else yet, and is run again on the new version instead.
+//!
+//! At `COMMIT`, if no other transaction committed since it began, the
+//! transaction's copy becomes the new version. Otherwise the changes it
+//! made to rows are made on the last version (see [`Table::apply`]), where
+//! the rows it inserted get new rowids; a transaction that changed more
+//! than rows, e.g. created a table, conflicts instead.
+//!
+//! # Durability
+//! A shared database opened with [`SharedDatabase::with_store`] logs each
+//! commit to the write-ahead log of its [`Store`], as the REPL does (see
+//! [`wal`](crate::storage::wal)): the rows the transaction changed, and
+//! the text of its other statements, given with
+//! [`Session::execute_sql`]. A transaction that ran a statement changing
+//! more than rows without its text, and [`SharedDatabase::write`], are
+//! saved with a checkpoint instead. Once the log is larger than the
+//! checkpoint size of the store, a commit checkpoints too.
+//!
+//! Each thread executes SQL through its own [`Session`].
+//!
+//! ```
+//! use mini_rust_sgbd::core::db::Database;
+//! use mini_rust_sgbd::executor::shared::SharedDatabase;
+//! use mini_rust_sgbd::frontend::lexer::lexer;
+//! use mini_rust_sgbd::frontend::parser::Parser;
+//!
+//! let db = SharedDatabase::new(Database::new());
+//! let parse = |sql: &str| Parser::default().parse_script(&lexer(sql).unwrap()).unwrap();
+//! db.session().execute_script(parse("CREATE TABLE t (n Int); INSERT INTO t VALUES (1)"));
+//!
+//! let other = db.clone();
+//! let count = std::thread::spawn(move || other.read(|db| db.table_count())).join().unwrap();
+//! assert_eq!(count, 1);
+//! ```
+
+use std::collections::{BTreeSet, HashMap, HashSet};
+use std::ops::Deref;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use crate::core::db::{Backing, Database};
+use crate::core::error::{SqlError, SqlResult};
+use crate::core::table::{RowChange, Rowid, Table};
+use crate::core::types::{TableName, Value};
+use crate::executor::executor::{Executor, Output};
+use crate::executor::lock::{LockManager, LockMode, RowId, TxId};
+use crate::frontend::ast::{ASTNode, CopyStmt, InsertStmt, TransactionStmt};
+use crate::storage::store::Store;
+use crate::storage::wal::effect_records;
+
+/// A handle to a database usable from several threads. Clones share the
+/// same database.
+#[derive(Clone, Default)]
+pub struct SharedDatabase {
+    inner: Arc<Inner>,
+}
+
+/// What the clones of a [`SharedDatabase`] share.
+#[derive(Default)]
+struct Inner {
+    /// The last committed version.
+    current: Mutex<Snapshot>,
+    /// Held while committing, so that commits happen one at a time, with
+    /// the store commits are logged to, if any.
+    commit: Mutex<Option<Store>>,
+    /// `true` if there is a store, so that transactions keep their log.
+    logged: bool,
+    locks: LockManager,
+    next_tx: AtomicU64,
+    /// The backing detached from the shared database, saved to on drop.
+    backing: Mutex<Option<Box<dyn Backing>>>,
+}
+
+/// A committed version of a [`SharedDatabase`], read through `Deref`.
+///
+/// A snapshot never changes: transactions committed after it was taken make
+/// new versions.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    version: u64,
+    /// The version of the last commit that changed more than rows, such as
+    /// the columns of a table: rowids name the same rows from there on.
+    schema_version: u64,
+    db: Arc<Database>,
+}
+
+impl Snapshot {
+    /// Returns the number of this version, which grows with each write
+    /// committed.
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+}
+
+impl Deref for Snapshot {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl SharedDatabase {
+    /// Shares `db`, kept in memory. A transaction open in `db` is rolled
+    /// back.
+    ///
+    /// If `db` has a backing (see [`Database::open`]), the last committed
+    /// version is saved to it once the last clone of the handle is dropped.
+    pub fn new(db: Database) -> Self {
+        Self::shared(db, None)
+    }
+
+    /// Shares `db`, opened with `store` (see [`Store::open`]), and logs
+    /// every commit to the store (see the module docs). A transaction open
+    /// in `db` is rolled back.
+    ///
+    /// The store is dropped, and its directory unlocked, with the last
+    /// clone of the shared database.
+    pub fn with_store(db: Database, store: Store) -> Self {
+        Self::shared(db, Some(store))
+    }
+
+    fn shared(mut db: Database, store: Option<Store>) -> Self {
+        if db.in_transaction() {
+            let _ = db.rollback();
+        }
+        // Transactions record the changes of their own copies
+        db.record_changes(false);
+        let backing = Mutex::new(db.detach());
+        let current = Mutex::new(Snapshot { version: 0, schema_version: 0, db: Arc::new(db) });
+        let logged = store.is_some();
+        let inner = Inner {
+            current,
+            commit: Mutex::new(store),
+            logged,
+            locks: LockManager::new(),
+            next_tx: AtomicU64::default(),
+            backing,
+        };
+        SharedDatabase { inner: Arc::new(inner) }
+    }
+
+    /// Returns the last committed version of the database.
+    pub fn snapshot(&self) -> Snapshot {
+        self.inner.snapshot()
+    }
+
+    /// Runs `f` on the last committed version of the database.
+    pub fn read<T>(&self, f: impl FnOnce(&Database) -> T) -> T {
+        f(&self.snapshot())
+    }
+
+    /// Runs `f` on the last committed version of the database, keeping
+    /// transactions from committing until it returns, e.g. to save it.
+    pub fn freeze<T>(&self, f: impl FnOnce(&Database) -> T) -> T {
+        let _turn = self.inner.commit.lock().unwrap_or_else(PoisonError::into_inner);
+        f(&self.snapshot())
+    }
+
+    /// Runs `f` on a copy of the last committed version of the database,
+    /// and commits what it changed, with a checkpoint if there is a store.
+    ///
+    /// `f` takes no row locks: transactions open meanwhile see its changes
+    /// only once they run again on a newer version. A transaction that `f`
+    /// leaves open is rolled back: use a [`Session`] to keep one.
+    ///
+    /// # Returns
+    /// What `f` returned, or `Err(SqlError)` if the checkpoint failed, in
+    /// which case nothing is committed.
+    pub fn write<T>(&self, f: impl FnOnce(&mut Database) -> T) -> SqlResult<T> {
+        let mut store = self.inner.commit.lock().unwrap_or_else(PoisonError::into_inner);
+        let mut db = Database::clone(&self.snapshot());
+        let result = f(&mut db);
+        if db.in_transaction() {
+            let _ = db.rollback();
+        }
+        self.inner.save(&mut store, db, false, None)?;
+        Ok(result)
+    }
+
+    /// Writes the last committed version of the database as a new
+    /// snapshot of its store, for `CHECKPOINT`.
+    ///
+    /// # Returns
+    /// `Err(SqlError)` if there is no store, or on I/O failure.
+    pub fn checkpoint(&self) -> SqlResult<()> {
+        let mut store = self.inner.commit.lock().unwrap_or_else(PoisonError::into_inner);
+        match store.as_mut() {
+            Some(store) => store.checkpoint(&self.snapshot()),
+            None => Err(SqlError::new_execution("CHECKPOINT needs a database stored in a directory")),
+        }
+    }
+
+    /// Starts a session, to execute statements from one thread.
+    pub fn session(&self) -> Session<'_> {
+        Session { shared: self, tx: None }
+    }
+}
+
+impl Inner {
+    fn snapshot(&self) -> Snapshot {
+        self.current.lock().unwrap_or_else(PoisonError::into_inner).clone()
+    }
+
+    /// Makes `db` the last committed version, which changed only rows if
+    /// `rows_only`. The caller holds the turn to commit.
+    fn publish(&self, db: Database, rows_only: bool) {
+        let mut current = self.current.lock().unwrap_or_else(PoisonError::into_inner);
+        let version = current.version + 1;
+        let schema_version = if rows_only { current.schema_version } else { version };
+        *current = Snapshot { version, schema_version, db: Arc::new(db) };
+    }
+
+    /// Saves `db`, a version about to be committed, to `store` if there is
+    /// one, then makes it the last committed version (see
+    /// [`publish`](Self::publish)). The caller holds the turn to commit.
+    ///
+    /// `log` holds the records of the transaction that made it; without
+    /// them, the store writes `db` as a checkpoint instead.
+    ///
+    /// # Returns
+    /// `Err(SqlError)` on I/O failure. `db` is committed unless logging it
+    /// or writing the checkpoint failed.
+    fn save(&self, store: &mut Option<Store>, db: Database, rows_only: bool, log: Option<Vec<String>>) -> SqlResult<()> {
+        let Some(store) = store else {
+            self.publish(db, rows_only);
+            return Ok(());
+        };
+        match log {
+            Some(records) => {
+                store.record_transaction(records)?;
+                let checkpointed = store.checkpoint_if_large(&db);
+                self.publish(db, rows_only);
+                checkpointed.map(|_| ())
+            }
+            None => {
+                store.checkpoint(&db)?;
+                self.publish(db, rows_only);
+                Ok(())
+            }
+        }
+    }
+
+    /// Starts a transaction on the last committed version.
+    fn begin(&self) -> Transaction {
+        let base = self.snapshot();
+        let mut db = Database::clone(&base);
+        db.begin().expect("committed versions have no transaction open");
+        db.record_changes(true);
+        Transaction {
+            id: self.next_tx.fetch_add(1, Ordering::Relaxed),
+            base,
+            db,
+            touched: HashMap::new(),
+            rows_only: true,
+            log: self.logged.then(Vec::new),
+        }
+    }
+}
+
+impl Drop for Inner {
+    fn drop(&mut self) {
+        let snapshot = self.snapshot();
+        if let Some(backing) = self.backing.get_mut().unwrap_or_else(PoisonError::into_inner) {
+            let _ = backing.save(&snapshot);
+        }
+    }
+}
+
+/// A transaction in progress in a [`Session`].
+struct Transaction {
+    id: TxId,
+    /// The version the transaction began on.
+    base: Snapshot,
+    /// The transaction's private copy of the database, with its changes, a
+    /// transaction open and row changes recorded.
+    db: Database,
+    /// The rowids of the rows the transaction inserted, changed or
+    /// deleted, by table.
+    touched: HashMap<TableName, BTreeSet<Rowid>>,
+    /// `false` once a statement changed more than rows, e.g. created a
+    /// table.
+    rows_only: bool,
+    /// The log records of the statements run, if the database has a store
+    /// and none of them lacked the text it needed logged.
+    log: Option<Vec<String>>,
+}
+
+impl Transaction {
+    /// Returns `true` if the transaction changed nothing.
+    fn is_empty(&self) -> bool {
+        self.touched.is_empty() && self.rows_only
+    }
+
+    /// Makes the changes the transaction made to rows on `current`, a
+    /// version committed after its base with the same tables. A row is
+    /// changed or deleted only if `current` has it as the base had it;
+    /// inserted rows get new rowids.
+    ///
+    /// # Returns
+    /// The new version, with the effect records of the changes made on it,
+    /// or `Err(SqlError)` if a row changed in `current` too, or a change
+    /// breaks a constraint of the table.
+    fn merge_into(&self, current: &Database) -> SqlResult<(Database, Vec<String>)> {
+        let mut db = current.clone();
+        db.record_changes(true);
+        for (name, rowids) in &self.touched {
+            let (Some(base), Some(mine)) = (self.base.table(name), self.db.table(name)) else {
+                unreachable!("a table whose rows changed exists in the base and in the copy of a rows-only transaction")
+            };
+            let table = db.table_mut(name).ok_or_else(|| SqlError::unknown_table(name))?;
+            let mut next = table.next_rowid();
+            let mut changes = Vec::new();
+            for &rowid in rowids {
+                match (row_values(base, rowid), row_values(mine, rowid)) {
+                    (Some(before), after) if after != Some(before) => {
+                        if row_values(table, rowid) != Some(before) {
+                            return Err(conflict(self.id, name));
+                        }
+                        changes.push(match after {
+                            Some(values) => RowChange::Update(rowid, values.clone()),
+                            None => RowChange::Delete(rowid),
+                        });
+                    }
+                    (None, Some(values)) => {
+                        changes.push(RowChange::Insert(next, values.clone()));
+                        next += 1;
+                    }
+                    _ => {}
+                }
+            }
+            table.apply(changes)?;
+        }
+        let records = effect_records(&db.take_changes());
+        db.record_changes(false);
+        Ok((db, records))
+    }
+}
+
+/// One thread's access to a [`SharedDatabase`].
+///
+/// Reads see the last committed version, or the session's own changes
+/// while it has a transaction open. Dropping the session, e.g. when a
+/// client disconnects, rolls back a transaction left open.
+pub struct Session<'a> {
+    shared: &'a SharedDatabase,
+    tx: Option<Transaction>,
+}
+
+impl Session<'_> {
+    /// Executes the statements of a script in order, stopping at the first
+    /// one that fails, as [`Executor::execute_script`] does.
+    ///
+    /// Each statement run outside a transaction is committed on its own
+    /// (see [`execute`](Self::execute)).
+    ///
+    /// # Returns
+    /// The result of each statement run; if one failed, its error is the
+    /// last result.
+    pub fn execute_script(&mut self, statements: Vec<ASTNode>) -> Vec<SqlResult<Output>> {
+        let mut results = Vec::with_capacity(statements.len());
+        for ast in statements {
+            let result = self.execute(ast);
+            let failed = result.is_err();
+            results.push(result);
+            if failed {
+                break;
+            }
+        }
+        results
+    }
+
+    /// Executes one statement.
+    ///
+    /// Outside a transaction, a statement that only reads runs on the last
+    /// committed version, and any other in a transaction of its own, run
+    /// again if it conflicts. In a transaction, a statement waits for the
+    /// rows it needs (see the module docs); if it fails, the transaction
+    /// goes on without it, unless the failure was a deadlock or a conflict,
+    /// which roll the transaction back.
+    ///
+    /// With a store, a transaction in which the statement changes more
+    /// than rows is saved with a checkpoint (see the module docs): use
+    /// [`execute_sql`](Self::execute_sql) to log it instead.
+    pub fn execute(&mut self, ast: ASTNode) -> SqlResult<Output> {
+        self.execute_logged(ast, None)
+    }
+
+    /// Executes one statement as [`execute`](Self::execute) does, with
+    /// `sql`, its source text, logged if it changes more than rows.
+    pub fn execute_sql(&mut self, ast: ASTNode, sql: &str) -> SqlResult<Output> {
+        self.execute_logged(ast, Some(sql))
+    }
+
+    /// Executes one statement, with its source text if known.
+    fn execute_logged(&mut self, ast: ASTNode, sql: Option<&str>) -> SqlResult<Output> {
+        let inner = &self.shared.inner;
+        if self.tx.is_none() {
+            return match ast {
+                ast if Executor::reads_only(&ast) => Executor::execute_read(ast, &inner.snapshot()),
+                ASTNode::Transaction(TransactionStmt::Begin) => {
+                    self.tx = Some(inner.begin());
+                    Ok(Output::None)
+                }
+                // Ending a transaction that is not open fails as it does on
+                // a plain database
+                ast @ ASTNode::Transaction(_) => Executor::execute(ast, &mut Database::new()),
+                ast => loop {
+                    self.tx = Some(inner.begin());
+                    let result = self.run(ast.clone(), sql).and_then(|out| self.commit().map(|_| out));
+                    self.rollback();
+                    if !matches!(result, Err(SqlError::Conflict { .. })) {
+                        return result;
+                    }
+                },
+            };
+        }
+        match ast {
+            ASTNode::Transaction(TransactionStmt::Commit) => self.commit().map(|_| Output::None),
+            ASTNode::Transaction(TransactionStmt::Rollback) => {
+                self.rollback();
+                Ok(Output::None)
+            }
+            ast => self.run(ast, sql),
+        }
+    }
+
+    /// Runs `f` on the session's transaction if it has one open, or on the
+    /// last committed version.
+    pub fn read<T>(&self, f: impl FnOnce(&Database) -> T) -> T {
+        match &self.tx {
+            Some(tx) => f(&tx.db),
+            None => self.shared.read(f),
+        }
+    }
+
+    /// Returns the database the session runs on.
+    pub fn database(&self) -> &SharedDatabase {
+        self.shared
+    }
+
+    /// Returns `true` if the session has a transaction open.
+    pub fn in_transaction(&self) -> bool {
+        self.tx.is_some()
+    }
+
+    /// Runs `ast` in the open transaction, with locks on the rows it uses.
+    ///
+    /// A statement that reads locks the rows it reads first. One that
+    /// changes the database runs on the transaction's copy, then locks the
+    /// rows of the base version it changed or deleted, and rolls the
+    /// transaction back if another one committed a change to them since it
+    /// began. Its log records are kept for the commit.
+    fn run(&mut self, ast: ASTNode, sql: Option<&str>) -> SqlResult<Output> {
+        let tx = self.tx.as_mut().expect("a transaction is open");
+        if Executor::reads_only(&ast) {
+            let rows = read_rows(&ast, &tx.db);
+            self.lock(&rows, LockMode::Shared)?;
+            return Executor::execute_read(ast, &self.tx.as_ref().expect("a transaction is open").db);
+        }
+
+        // Savepoints restore rows the transaction touched, and only those
+        let logs_effects = ast.changes_only_rows();
+        let rows_only = logs_effects || matches!(ast, ASTNode::Transaction(_));
+        let result = Executor::execute(ast, &mut tx.db);
+        let changes = tx.db.take_changes();
+        let mut rows = HashSet::new();
+        for (name, changes) in &changes {
+            let base = tx.base.table(name);
+            let touched = tx.touched.entry(name.clone()).or_default();
+            for change in changes {
+                let (RowChange::Insert(rowid, _) | RowChange::Update(rowid, _) | RowChange::Delete(rowid)) = *change;
+                touched.insert(rowid);
+                // Rows the transaction inserted are its own
+                if base.is_some_and(|base| base.position(rowid).is_some()) {
+                    rows.insert(RowId { table: name.clone(), rowid });
+                }
+            }
+        }
+        let out = result?;
+        tx.rows_only &= rows_only;
+        // Logged as the store's log records a statement (see Wal::record)
+        match (&mut tx.log, sql) {
+            (Some(log), _) if logs_effects => log.extend(effect_records(&changes)),
+            (Some(log), Some(sql)) => log.push(sql.to_string()),
+            (log, _) => *log = None,
+        }
+        let rows: Vec<RowId> = rows.into_iter().collect();
+        self.lock(&rows, LockMode::Exclusive)?;
+        self.check_conflicts(&rows)?;
+        Ok(out)
+    }
+
+    /// Locks `rows` in `mode` for the open transaction, rolling it back on
+    /// a deadlock.
+    fn lock(&mut self, rows: &[RowId], mode: LockMode) -> SqlResult<()> {
+        let id = self.tx.as_ref().expect("a transaction is open").id;
+        for row in rows {
+            if let Err(e) = self.shared.inner.locks.lock(id, row, mode) {
+                self.rollback();
+                return Err(e);
+            }
+        }
+        Ok(())
+    }
+
+    /// Rolls the open transaction back if one of `rows`, which it has
+    /// locked, was changed by another transaction committed since it
+    /// began.
+    fn check_conflicts(&mut self, rows: &[RowId]) -> SqlResult<()> {
+        let current = self.shared.snapshot();
+        let tx = self.tx.as_ref().expect("a transaction is open");
+        let values = |db: &Database, row: &RowId| db.table(&row.table).and_then(|table| row_values(table, row.rowid).cloned());
+        let changed = |row: &&RowId| {
+            // Rowids name other rows once tables changed
+            current.schema_version != tx.base.schema_version || values(&current, row) != values(&tx.base, row)
+        };
+        if let Some(row) = rows.iter().find(changed) {
+            let error = conflict(tx.id, &row.table);
+            self.rollback();
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    /// Commits the open transaction and releases its locks.
+    ///
+    /// If other transactions committed since it began, its changes are
+    /// made on the last version (see the module docs).
+    fn commit(&mut self) -> SqlResult<()> {
+        let inner = &self.shared.inner;
+        let mut tx = self.tx.take().expect("a transaction is open");
+        let id = tx.id;
+        let committed = if tx.is_empty() {
+            Ok(())
+        } else {
+            let mut store = inner.commit.lock().unwrap_or_else(PoisonError::into_inner);
+            let current = inner.snapshot();
+            if current.version == tx.base.version {
+                tx.db.commit().and_then(|_| {
+                    tx.db.record_changes(false);
+                    inner.save(&mut store, tx.db, tx.rows_only, tx.log)
+                })
+            } else if !tx.rows_only || current.schema_version != tx.base.schema_version {
+                Err(SqlError::new_conflict(&format!(
+                    "Transaction {} changed tables while other transactions committed; it was rolled back",
+                    id
+                )))
+            } else {
+                tx.merge_into(&current).and_then(|(db, records)| inner.save(&mut store, db, true, Some(records)))
+            }
+        };
+        inner.locks.release_all(id);
+        committed
+    }
+
+    /// Throws away the open transaction, if any, and releases its locks.
+    fn rollback(&mut self) {
+        if let Some(tx) = self.tx.take() {
+            self.shared.inner.locks.release_all(tx.id);
+        }
+    }
+}
+
+impl Drop for Session<'_> {
+    fn drop(&mut self) {
+        self.rollback();
+    }
+}
+
+/// Returns the rows of the stored tables `ast` reads. A query that cannot
+/// be planned reads nothing: it fails when it runs.
+fn read_rows(ast: &ASTNode, db: &Database) -> Vec<RowId> {
+    let query = match ast {
+        ASTNode::Select(query) | ASTNode::Copy(CopyStmt::To { query, .. }) => query,
+        ASTNode::Insert(InsertStmt { query: Some(query), .. }) => &**query,
+        _ => return Vec::new(),
+    };
+    Executor::rows_read(query, db)
+        .unwrap_or_default()
+        .into_iter()
+        .flat_map(|(table, positions)| positions.into_iter().map(move |pos| RowId::at(table, pos)))
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect()
+}
+
+/// Returns the values of the row with rowid `rowid` in `table`, if it has
+/// one.
+fn row_values(table: &Table, rowid: Rowid) -> Option<&Vec<Value>> {
+    table.position(rowid).map(|pos| table.rows()[pos].values())
+}
+
+/// Builds the error rolling back transaction `tx`, which changed a row of
+/// `table` that another transaction changed and committed since it began.
+fn conflict(tx: TxId, table: &TableName) -> SqlError {
+    SqlError::new_conflict(&format!(
+        "Transaction {} changed a row of '{}' that another transaction changed since it began; it was rolled back",
+        tx,
+        table.as_str()
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::types::{TableName, Value};
+    use crate::frontend::ast::TransactionStmt;
+    use crate::frontend::lexer::lexer;
+    use crate::frontend::parser::Parser;
+    use crate::core::error::SqlError;
+    use crate::testing::TempDir;
+    use std::sync::{mpsc, Barrier};
+    use std::thread;
+    use std::time::Duration;
+
+    fn run(session: &mut Session, sql: &str) -> Output {
+        let mut results = session.execute_script(Parser::default().parse_script(&lexer(sql).unwrap()).unwrap());
+        results.pop().unwrap().unwrap()
+    }
+
+    fn count(db: &Database) -> usize {
+        db.table(&TableName::new("t").unwrap()).unwrap().rows().len()
+    }
+
+    #[test]
+    fn sessions_run_from_several_threads() {
+        let db = SharedDatabase::new(Database::new());
+        run(&mut db.session(), "CREATE TABLE t (n Int)");
+
+        let writers: Vec<_> = (0..4)
+            .map(|i| {
+                let db = db.clone();
+                thread::spawn(move || {
+                    let mut session = db.session();
+                    for j in 0..25 {
+                        run(&mut session, &format!("INSERT INTO t VALUES ({})", i * 25 + j));
+                        assert!(matches!(run(&mut session, "SELECT n FROM t"), Output::Rows { .. }));
+                    }
+                })
+            })
+            .collect();
+        for writer in writers {
+            writer.join().unwrap();
+        }
+
+        assert_eq!(db.read(count), 100);
+        match run(&mut db.session(), "SELECT n FROM t WHERE n = 99") {
+            Output::Rows { rows, .. } => assert_eq!(rows[0].values(), &vec![Value::Int(99)]),
+            other => panic!("Expected rows, got {:?}", other),
+        }
+        // Statements needing write access are refused on a read lock
+        let begin = ASTNode::Transaction(TransactionStmt::Begin);
+        assert!(db.read(|db| Executor::execute_read(begin, db)).is_err());
+    }
+
+    #[test]
+    fn a_snapshot_keeps_its_version_while_writers_commit() {
+        let db = SharedDatabase::new(Database::new());
+        run(&mut db.session(), "CREATE TABLE t (n Int); INSERT INTO t VALUES (1), (2), (3)");
+        let snapshot = db.snapshot();
+
+        let writer = db.clone();
+        thread::spawn(move || run(&mut writer.session(), "DELETE FROM t WHERE n > 1; INSERT INTO t VALUES (4)"))
+            .join()
+            .unwrap();
+
+        assert_eq!(count(&snapshot), 3);
+        assert_eq!(db.read(count), 2);
+        assert_eq!(db.snapshot().version(), snapshot.version() + 2);
+    }
+
+    fn values(db: &Database) -> Vec<Vec<Value>> {
+        db.table(&TableName::new("t").unwrap()).unwrap().rows().iter().map(|row| row.values().clone()).collect()
+    }
+
+    fn pairs(rows: &[(i64, i64)]) -> Vec<Vec<Value>> {
+        rows.iter().map(|&(id, n)| vec![Value::Int(id), Value::Int(n)]).collect()
+    }
+
+    #[test]
+    fn a_transaction_is_invisible_until_committed_and_locks_its_rows() {
+        let db = SharedDatabase::new(Database::new());
+        run(&mut db.session(), "CREATE TABLE t (id Int PRIMARY KEY, n Int); INSERT INTO t VALUES (1, 0), (2, 0)");
+        let mut first = db.session();
+        run(&mut first, "BEGIN; UPDATE t SET n = 1 WHERE id = 1");
+        assert!(first.in_transaction());
+        assert_eq!(first.read(values), pairs(&[(1, 1), (2, 0)]));
+        assert_eq!(db.read(values), pairs(&[(1, 0), (2, 0)]));
+
+        // Other rows can be changed at once, the locked one once it commits
+        let other = db.clone();
+        thread::spawn(move || run(&mut other.session(), "UPDATE t SET n = 2 WHERE id = 2")).join().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let other = db.clone();
+        let writer = thread::spawn(move || sender.send(run(&mut other.session(), "UPDATE t SET n = 10 WHERE id = 1")).unwrap());
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+        run(&mut first, "COMMIT");
+        assert!(!first.in_transaction());
+        assert_eq!(receiver.recv().unwrap(), Output::Affected(1));
+        writer.join().unwrap();
+        assert_eq!(db.read(values), pairs(&[(1, 10), (2, 2)]));
+
+        // A transaction left open is rolled back with its session
+        run(&mut first, "BEGIN; DELETE FROM t");
+        drop(first);
+        assert_eq!(db.read(count), 2);
+        run(&mut db.session(), "DELETE FROM t WHERE id = 1");
+        db.write(|db| db.begin()).unwrap().unwrap();
+        assert!(!db.read(Database::in_transaction));
+        assert_eq!(db.read(values), pairs(&[(2, 2)]));
+    }
+
+    #[test]
+    fn readers_lock_only_the_rows_they_match() {
+        let db = SharedDatabase::new(Database::new());
+        run(&mut db.session(), "CREATE TABLE t (id Int PRIMARY KEY, n Int); INSERT INTO t VALUES (1, 0), (2, 0)");
+        let mut first = db.session();
+        run(&mut first, "BEGIN; SELECT n FROM t WHERE id = 1; INSERT INTO t VALUES (3, 0)");
+
+        // Neither the row left out by WHERE nor the inserted one is locked
+        let other = db.clone();
+        thread::spawn(move || run(&mut other.session(), "UPDATE t SET n = 2 WHERE id = 2")).join().unwrap();
+        let (sender, receiver) = mpsc::channel();
+        let other = db.clone();
+        let writer = thread::spawn(move || sender.send(run(&mut other.session(), "UPDATE t SET n = 1 WHERE id = 1")).unwrap());
+        assert!(receiver.recv_timeout(Duration::from_millis(100)).is_err());
+        run(&mut first, "COMMIT");
+        assert_eq!(receiver.recv().unwrap(), Output::Affected(1));
+        writer.join().unwrap();
+        assert_eq!(db.read(values), pairs(&[(1, 1), (2, 2), (3, 0)]));
+    }
+
+    #[test]
+    fn the_first_transaction_to_commit_a_row_wins() {
+        let db = SharedDatabase::new(Database::new());
+        run(&mut db.session(), "CREATE TABLE t (id Int PRIMARY KEY, n Int); INSERT INTO t VALUES (1, 0), (2, 0)");
+        let fail = |session: &mut Session, sql: &str| {
+            session.execute_script(Parser::default().parse_script(&lexer(sql).unwrap()).unwrap()).pop().unwrap().unwrap_err()
+        };
+
+        // Row 1 changes after the transaction began: changing it conflicts
+        let mut first = db.session();
+        run(&mut first, "BEGIN; INSERT INTO t VALUES (3, 0)");
+        run(&mut db.session(), "UPDATE t SET n = 5 WHERE id = 1");
+        assert_eq!(first.read(values), pairs(&[(1, 0), (2, 0), (3, 0)]));
+        assert!(matches!(fail(&mut first, "UPDATE t SET n = n + 1 WHERE id = 1"), SqlError::Conflict { .. }));
+        assert!(!first.in_transaction());
+        assert_eq!(db.read(values), pairs(&[(1, 5), (2, 0)]));
+
+        // Other changes are made on the last version, with new rowids for
+        // the inserted rows
+        run(&mut first, "BEGIN; UPDATE t SET n = 1 WHERE id = 2; INSERT INTO t VALUES (3, 3)");
+        run(&mut db.session(), "INSERT INTO t VALUES (4, 4)");
+        run(&mut first, "COMMIT");
+        assert_eq!(db.read(values), pairs(&[(1, 5), (2, 1), (4, 4), (3, 3)]));
+        let rowids = db.read(|db| db.table(&TableName::new("t").unwrap()).unwrap().rowids().to_vec());
+        assert_eq!(rowids, [1, 2, 3, 4]);
+
+        // A transaction that changed tables cannot be made on a newer version
+        run(&mut first, "BEGIN; CREATE TABLE u (n Int)");
+        run(&mut db.session(), "DELETE FROM t WHERE id = 4");
+        assert!(matches!(fail(&mut first, "COMMIT"), SqlError::Conflict { .. }));
+        assert_eq!(db.read(Database::table_count), 1);
+    }
+
+    #[test]
+    fn a_deadlock_rolls_back_one_of_the_transactions() {
+        let db = SharedDatabase::new(Database::new());
+        run(&mut db.session(), "CREATE TABLE t (id Int PRIMARY KEY, n Int); INSERT INTO t VALUES (1, 0), (2, 0)");
+        let both_locked = Arc::new(Barrier::new(2));
+
+        // Each transaction updates one row, then the row of the other
+        let threads: Vec<_> = [(1, 2), (2, 1)]
+            .into_iter()
+            .map(|(mine, theirs)| {
+                let db = db.clone();
+                let both_locked = Arc::clone(&both_locked);
+                thread::spawn(move || {
+                    let mut session = db.session();
+                    run(&mut session, &format!("BEGIN; UPDATE t SET n = {} WHERE id = {}", mine, mine));
+                    both_locked.wait();
+                    let sql = format!("UPDATE t SET n = {} WHERE id = {}", mine, theirs);
+                    let result = session.execute_script(Parser::default().parse_script(&lexer(&sql).unwrap()).unwrap()).pop().unwrap();
+                    match result {
+                        Ok(_) => run(&mut session, "COMMIT"),
+                        Err(e) => {
+                            assert!(!session.in_transaction());
+                            return Err(e);
+                        }
+                    };
+                    Ok(mine)
+                })
+            })
+            .collect();
+        let results: Vec<_> = threads.into_iter().map(|thread| thread.join().unwrap()).collect();
+
+        let winners: Vec<i64> = results.iter().filter_map(|result| result.as_ref().ok().copied()).collect();
+        assert_eq!(winners.len(), 1, "{:?}", results);
+        assert!(results.iter().any(|result| matches!(result, Err(SqlError::Deadlock { .. }))));
+        assert_eq!(db.read(values), pairs(&[(1, winners[0]), (2, winners[0])]));
+    }
+
+    #[test]
+    fn commits_are_logged_to_the_store() {
+        let tmp = TempDir::new("shared");
+        let dir = tmp.path();
+        let (db, store) = Store::open(dir).unwrap();
+        let db = SharedDatabase::with_store(db, store);
+        let logged = |session: &mut Session, sql: &str| {
+            for (ast, span) in Parser::default().parse_script_spans(&lexer(sql).unwrap()).unwrap() {
+                let span = span.unwrap();
+                session.execute_sql(ast, &sql[span.start..span.start + span.len()]).unwrap();
+            }
+        };
+        let generation = || std::fs::read_to_string(dir.join("CURRENT")).unwrap_or_default();
+        let before = generation();
+
+        // Rows committed on a newer version are logged with their new rowids
+        logged(&mut db.session(), "CREATE TABLE t (id Int PRIMARY KEY, n Int); INSERT INTO t VALUES (1, 0), (2, 0)");
+        let mut first = db.session();
+        logged(&mut first, "BEGIN; INSERT INTO t VALUES (3, 3)");
+        logged(&mut db.session(), "INSERT INTO t VALUES (4, 4)");
+        logged(&mut first, "COMMIT");
+        logged(&mut db.session(), "DELETE FROM t WHERE id = 3");
+        let rowids = |db: &Database| db.table(&TableName::new("t").unwrap()).unwrap().rowids().to_vec();
+        let recovered = crate::storage::recovery::recover(dir).unwrap();
+        assert_eq!((values(&recovered), rowids(&recovered)), (db.read(values), db.read(rowids)));
+        assert_eq!(generation(), before);
+
+        // A statement without its text is saved with a checkpoint
+        run(&mut db.session(), "CREATE TABLE u (n Int)");
+        assert_ne!(generation(), before);
+        assert_eq!(crate::storage::recovery::recover(dir).unwrap().table_count(), 2);
+        drop(first);
+    }
+}
@@ -6,46 +6,475 @@
 
 use crate::core::types::{TableName, ColumnName, Value};
 use crate::core::schema::Column;
+use crate::core::index::IndexKind;
 
 /// Represents a CREATE TABLE statement.
 ///
 /// # Fields
 /// - `name`: the table being created
 /// - `columns`: the list of columns with names and types
+/// - `primary_key`: the column declared `PRIMARY KEY`, if any
 #[derive(Debug, Clone, PartialEq)]
 pub struct CreateTableStmt {
     pub name: TableName,
     pub columns: Vec<Column>,
+    pub primary_key: Option<ColumnName>,
+}
+
+/// Represents a CREATE INDEX statement:
+/// `CREATE INDEX name ON table (column) [USING BTREE | HASH]`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateIndexStmt {
+    pub name: String,
+    pub table: TableName,
+    pub column: ColumnName,
+    pub kind: IndexKind,
+}
+
+/// A column reference, optionally qualified by its table (`t.col`).
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ColumnRef {
+    pub table: Option<TableName>,
+    pub column: ColumnName,
+}
+
+impl From<ColumnName> for ColumnRef {
+    fn from(column: ColumnName) -> Self {
+        ColumnRef { table: None, column }
+    }
+}
+
+impl std::fmt::Display for ColumnRef {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.table {
+            Some(table) => write!(f, "{}.{}", table.as_str(), self.column.as_str()),
+            None => write!(f, "{}", self.column.as_str()),
+        }
+    }
+}
+
+/// Operator of a binary [`Expr`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BinaryOp {
+    /// `+`
+    Add,
+    /// `-`
+    Sub,
+    /// `*`
+    Mul,
+    /// `/`
+    Div,
+    /// `%`
+    Mod,
+    /// `||` (string concatenation)
+    Concat,
+}
+
+impl BinaryOp {
+    /// SQL spelling of the operator, for error messages.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            BinaryOp::Add => "+",
+            BinaryOp::Sub => "-",
+            BinaryOp::Mul => "*",
+            BinaryOp::Div => "/",
+            BinaryOp::Mod => "%",
+            BinaryOp::Concat => "||",
+        }
+    }
+}
+
+/// A scalar expression that the executor evaluates to a [`Value`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum Expr {
+    /// A literal value.
+    Literal(Value),
+    /// The value of a column in the current row.
+    Column(ColumnRef),
+    /// A function call such as `NOW()`; `name` is upper-cased.
+    Function { name: String, args: Vec<Expr> },
+    /// Arithmetic negation: `-expr`.
+    Negate(Box<Expr>),
+    /// `left <op> right`.
+    Binary {
+        op: BinaryOp,
+        left: Box<Expr>,
+        right: Box<Expr>,
+    },
+}
+
+impl From<Value> for Expr {
+    fn from(value: Value) -> Self {
+        Expr::Literal(value)
+    }
 }
 
 /// Represents an INSERT statement.
 ///
 /// # Fields
 /// - `table`: the table into which values are inserted
-/// - `values`: the row values to insert
+/// - `columns`: optional target column list; `None` means all columns in
+///   schema order
+/// - `rows`: one value tuple per row to insert, each in the order of `columns`
+/// - `query`: for `INSERT ... SELECT`, the query whose rows are inserted,
+///   in the order of `columns`; `rows` is then empty
+/// - `on_conflict`: what to do with rows repeating a unique value, or
+///   `None` to fail
 #[derive(Debug, Clone, PartialEq)]
 pub struct InsertStmt {
     pub table: TableName,
-    pub values: Vec<Value>,
+    pub columns: Option<Vec<ColumnName>>,
+    pub rows: Vec<Vec<Expr>>,
+    pub query: Option<Box<SelectStmt>>,
+    pub on_conflict: Option<OnConflict>,
+}
+
+/// What an INSERT does with a row that would repeat the value of a
+/// `UNIQUE` or primary key column.
+#[derive(Debug, Clone, PartialEq)]
+pub enum OnConflict {
+    /// `INSERT OR IGNORE`, or `ON CONFLICT [(column)] DO NOTHING`: the row
+    /// is skipped. With a column, only conflicts on that column are.
+    Ignore(Option<ColumnName>),
+    /// `INSERT OR REPLACE`: the rows holding one of its unique values are
+    /// deleted first.
+    Replace,
+    /// `ON CONFLICT (column) DO UPDATE SET ...`: the row holding its value
+    /// of `column` is updated instead. The assignments read that row's
+    /// columns, and those of the row that was to be inserted as
+    /// `excluded.column`.
+    Update {
+        column: ColumnName,
+        assignments: Vec<(ColumnName, Expr)>,
+    },
+}
+
+/// Comparison operator used in a WHERE clause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CompareOp {
+    /// `=`
+    Eq,
+    /// `!=` or `<>`
+    NotEq,
+    /// `<`
+    Lt,
+    /// `>`
+    Gt,
+    /// `<=`
+    LtEq,
+    /// `>=`
+    GtEq,
+}
+
+/// Represents a WHERE filter: a predicate on one column, or a combination
+/// of filters with `AND`, `OR` and `NOT`.
+///
+/// `NOT` binds tighter than `AND`, which binds tighter than `OR`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum WhereClause {
+    /// `column <op> value`; a comparison involving `NULL` never matches.
+    /// `value` may itself name a column, as in `a.id = b.a_id`.
+    Compare {
+        column: ColumnRef,
+        op: CompareOp,
+        value: Expr,
+    },
+    /// `column IS NULL`, or `column IS NOT NULL` when `negated` is set.
+    IsNull {
+        column: ColumnRef,
+        negated: bool,
+    },
+    /// `column LIKE pattern`, or `column NOT LIKE pattern` when `negated`
+    /// is set. In the pattern `%` matches any run of characters and `_`
+    /// exactly one.
+    Like {
+        column: ColumnRef,
+        pattern: Expr,
+        negated: bool,
+    },
+    /// `column [NOT] IN (value, ...)`
+    In {
+        column: ColumnRef,
+        values: Vec<Expr>,
+        negated: bool,
+    },
+    /// `column [NOT] BETWEEN low AND high`, bounds included.
+    Between {
+        column: ColumnRef,
+        low: Expr,
+        high: Expr,
+        negated: bool,
+    },
+    /// `left AND right`
+    And(Box<WhereClause>, Box<WhereClause>),
+    /// `left OR right`
+    Or(Box<WhereClause>, Box<WhereClause>),
+    /// `NOT clause`
+    Not(Box<WhereClause>),
+}
+
+/// Aggregate function usable in a SELECT list or HAVING clause.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AggregateFunc {
+    /// `COUNT(*)` counts rows, `COUNT(col)` counts non-`NULL` values
+    Count,
+    /// `SUM(col)` of a numeric column
+    Sum,
+    /// `MIN(col)`
+    Min,
+    /// `MAX(col)`
+    Max,
+    /// `AVG(col)` of a numeric column, as a float
+    Avg,
+}
+
+/// One item of a SELECT list.
+#[derive(Debug, Clone, PartialEq)]
+pub enum SelectItem {
+    /// A plain column reference.
+    Column(ColumnRef),
+    /// An aggregate over a column; `column` is `None` for `COUNT(*)`.
+    Aggregate {
+        func: AggregateFunc,
+        column: Option<ColumnRef>,
+    },
+    /// Any other expression, such as `price * 2` or `name || '!'`.
+    Expr(Expr),
+}
+
+/// Represents a HAVING filter: `<item> <op> <value>`, evaluated once per group.
+///
+/// `item` is usually an aggregate (`HAVING COUNT(*) > 1`) but may also be a
+/// GROUP BY column.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HavingClause {
+    pub item: SelectItem,
+    pub op: CompareOp,
+    pub value: Expr,
+}
+
+/// A table joined onto the rows produced so far: `JOIN table [AS alias] ON
+/// condition`, or a cross join (`FROM a, b` or `CROSS JOIN b`) when `on` is
+/// `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Join {
+    pub table: TableName,
+    pub alias: Option<TableName>,
+    pub on: Option<WhereClause>,
+}
+
+/// Sort direction of an ORDER BY key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    /// `ASC` (the default)
+    Asc,
+    /// `DESC`
+    Desc,
 }
 
 /// Represents a SELECT statement.
 ///
 /// # Fields
-/// - `table`: the table being queried
-/// - `columns`: optional list of column names to select; `None` means all columns
+/// - `table`: the (first) table being queried
+/// - `alias`: optional alias of `table` (`FROM users AS u`); when set, columns
+///   are qualified with the alias instead of the table name
+/// - `joins`: tables joined onto `table`, in order
+/// - `distinct`: whether duplicate result rows are removed (`SELECT DISTINCT`)
+/// - `columns`: optional select list of items with their optional output
+///   name (`id AS user_id`); `None` means all columns (`*`)
+/// - `where_clause`: optional row filter
+/// - `group_by`: grouping columns; empty means no grouping (a select list with
+///   aggregates then forms a single group)
+/// - `having`: optional filter on groups
+/// - `order_by`: sort keys, most significant first; empty keeps insertion order.
+///   A key may name a select-list alias; for grouped queries the keys name
+///   output columns.
+/// - `limit`: optional maximum number of rows to return
+/// - `offset`: optional number of rows to skip before returning any
 #[derive(Debug, Clone, PartialEq)]
 pub struct SelectStmt {
     pub table: TableName,
-    pub columns: Option<Vec<ColumnName>>,
+    pub alias: Option<TableName>,
+    pub joins: Vec<Join>,
+    pub distinct: bool,
+    pub columns: Option<Vec<(SelectItem, Option<ColumnName>)>>,
+    pub where_clause: Option<WhereClause>,
+    pub group_by: Vec<ColumnRef>,
+    pub having: Option<HavingClause>,
+    pub order_by: Vec<(ColumnRef, SortDirection)>,
+    pub limit: Option<usize>,
+    pub offset: Option<usize>,
+}
+
+/// `CREATE VIEW name AS SELECT ...`: names a query, which can then be
+/// read like a table.
+///
+/// `sql` is the text of the query, kept in the catalog and parsed again
+/// each time the view is read.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CreateViewStmt {
+    pub name: TableName,
+    pub query: SelectStmt,
+    pub sql: String,
+}
+
+/// Represents an UPDATE statement.
+///
+/// # Fields
+/// - `table`: the table being updated
+/// - `assignments`: the `column = value` pairs from the SET clause
+/// - `where_clause`: optional row filter; `None` updates every row
+#[derive(Debug, Clone, PartialEq)]
+pub struct UpdateStmt {
+    pub table: TableName,
+    pub assignments: Vec<(ColumnName, Expr)>,
+    pub where_clause: Option<WhereClause>,
+}
+
+/// Represents a DELETE statement.
+///
+/// # Fields
+/// - `table`: the table rows are deleted from
+/// - `where_clause`: optional row filter; `None` deletes every row
+#[derive(Debug, Clone, PartialEq)]
+pub struct DeleteStmt {
+    pub table: TableName,
+    pub where_clause: Option<WhereClause>,
+}
+
+/// Schema change performed by an ALTER TABLE statement.
+#[derive(Debug, Clone, PartialEq)]
+pub enum AlterAction {
+    /// `ADD [COLUMN] name type`
+    AddColumn(Column),
+    /// `DROP [COLUMN] name`
+    DropColumn(ColumnName),
+    /// `RENAME [COLUMN] from TO to`
+    RenameColumn { from: ColumnName, to: ColumnName },
+    /// `RENAME TO name`: renames the table itself
+    RenameTable(TableName),
+}
+
+/// Represents an ALTER TABLE statement.
+///
+/// # Fields
+/// - `table`: the table being altered
+/// - `action`: the schema change to apply
+#[derive(Debug, Clone, PartialEq)]
+pub struct AlterTableStmt {
+    pub table: TableName,
+    pub action: AlterAction,
+}
+
+/// A transaction control statement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TransactionStmt {
+    /// `BEGIN [TRANSACTION]`
+    Begin,
+    /// `COMMIT`: keep every change made since BEGIN
+    Commit,
+    /// `ROLLBACK`: undo every change made since BEGIN
+    Rollback,
+    /// `SAVEPOINT name`: mark the current state of the transaction
+    Savepoint(String),
+    /// `ROLLBACK TO [SAVEPOINT] name`: undo every change made since the
+    /// savepoint, which is kept
+    RollbackTo(String),
+    /// `RELEASE [SAVEPOINT] name`: forget the savepoint, keeping the
+    /// changes made since
+    Release(String),
+}
+
+/// `PRAGMA name` or `PRAGMA name = value`: reads or changes a setting of
+/// the session, such as `synchronous`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PragmaStmt {
+    pub name: String,
+    /// The new value, as written; `None` to read the setting
+    pub value: Option<String>,
+}
+
+/// A statement moving rows between a table and a file.
+// Like ASTNode, built once per statement
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone, PartialEq)]
+pub enum CopyStmt {
+    /// `COPY table FROM 'path'`: load CSV rows into `table`
+    From { table: TableName, path: String },
+    /// `COPY (SELECT ...) TO 'path'` or `COPY table TO 'path'`: write the
+    /// query result to a CSV file with a header row
+    To { query: SelectStmt, path: String },
 }
 
 /// Enum grouping all SQL statements into a single AST node.
+// One node is built per statement, so the size of SELECT does not matter
+#[allow(clippy::large_enum_variant)]
 #[derive(Debug, Clone, PartialEq)]
 pub enum ASTNode {
     CreateTable(CreateTableStmt),
+    /// `CREATE SCHEMA name`: adds a schema that tables can be created in
+    CreateSchema(String),
+    CreateView(CreateViewStmt),
+    CreateIndex(CreateIndexStmt),
     Insert(InsertStmt),
     Select(SelectStmt),
+    Update(UpdateStmt),
+    Delete(DeleteStmt),
+    /// `TRUNCATE [TABLE] table`: removes every row at once
+    Truncate(TableName),
+    AlterTable(AlterTableStmt),
+    Transaction(TransactionStmt),
+    Copy(CopyStmt),
+    /// `EXPLAIN SELECT ...`: describes how the query would read its tables
+    Explain(SelectStmt),
+    /// `ANALYZE table`: collects statistics used to choose access paths
+    Analyze(TableName),
+    /// `SHOW TABLES`: lists the tables
+    ShowTables,
+    /// `DESCRIBE table` (or `DESC table`): lists the columns of a table
+    Describe(TableName),
+    /// `CHECKPOINT`: writes the database to its directory and empties the
+    /// write-ahead log
+    Checkpoint,
+    /// `PRAGMA name [= value]`: reads or changes a setting
+    Pragma(PragmaStmt),
+    /// `ATTACH [DATABASE] 'path' AS alias`: makes the tables of the
+    /// database stored in `path` readable as `alias.table`
+    Attach { path: String, alias: String },
+    /// `DETACH [DATABASE] alias`: forgets an attached database
+    Detach(String),
+}
+
+impl ASTNode {
+    /// Returns `true` if the statement leaves the stored data unchanged:
+    /// queries, `EXPLAIN`, `SHOW TABLES`, `DESCRIBE`, `COPY ... TO`,
+    /// `PRAGMA`, and `ANALYZE` and `ATTACH`/`DETACH`, whose effects are not
+    /// saved.
+    ///
+    /// Transaction control is not read-only: `COMMIT` makes changes final.
+    pub fn is_read_only(&self) -> bool {
+        matches!(
+            self,
+            ASTNode::Select(_)
+                | ASTNode::Explain(_)
+                | ASTNode::Analyze(_)
+                | ASTNode::ShowTables
+                | ASTNode::Describe(_)
+                | ASTNode::Pragma(_)
+                | ASTNode::Attach { .. }
+                | ASTNode::Detach(_)
+                | ASTNode::Copy(CopyStmt::To { .. })
+        )
+    }
+
+    /// Returns `true` if the statement can change the rows of tables, and
+    /// nothing else: `INSERT`, `UPDATE`, `DELETE` and `COPY ... FROM`.
+    pub fn changes_only_rows(&self) -> bool {
+        matches!(
+            self,
+            ASTNode::Insert(_) | ASTNode::Update(_) | ASTNode::Delete(_) | ASTNode::Copy(CopyStmt::From { .. })
+        )
+    }
 }
 
 #[cfg(test)]
@@ -62,6 +491,7 @@ mod tests {
         let stmt = CreateTableStmt {
             name: TableName::new("users").unwrap(),
             columns: vec![col1.clone(), col2.clone()],
+            primary_key: None,
         };
         assert_eq!(stmt.columns.len(), 2);
         assert_eq!(stmt.name.as_str(), "users");
@@ -71,9 +501,12 @@ mod tests {
     fn insert_stmt_struct() {
         let stmt = InsertStmt {
             table: TableName::new("users").unwrap(),
-            values: vec![Value::Int(1), Value::Text("Alice".to_string())],
+            columns: None,
+            rows: vec![vec![Value::Int(1).into(), Value::Text("Alice".to_string()).into()]],
+            query: None,
+            on_conflict: None,
         };
-        assert_eq!(stmt.values.len(), 2);
+        assert_eq!(stmt.rows[0].len(), 2);
         assert_eq!(stmt.table.as_str(), "users");
     }
 
@@ -81,25 +514,62 @@ mod tests {
     fn select_stmt_struct() {
         let stmt = SelectStmt {
             table: TableName::new("users").unwrap(),
-            columns: Some(vec![ColumnName::new("id").unwrap()]),
+            alias: None,
+            joins: vec![],
+            distinct: false,
+            columns: Some(vec![(SelectItem::Column(ColumnName::new("id").unwrap().into()), None)]),
+            where_clause: Some(WhereClause::Compare {
+                column: ColumnName::new("id").unwrap().into(),
+                op: CompareOp::Eq,
+                value: Value::Int(1).into(),
+            }),
+            group_by: vec![],
+            having: None,
+            order_by: vec![],
+            limit: None,
+            offset: None,
         };
         assert_eq!(stmt.columns.as_ref().unwrap().len(), 1);
         assert_eq!(stmt.table.as_str(), "users");
     }
 
+    #[test]
+    fn update_stmt_struct() {
+        let stmt = UpdateStmt {
+            table: TableName::new("users").unwrap(),
+            assignments: vec![(ColumnName::new("name").unwrap(), Value::Text("Bob".to_string()).into())],
+            where_clause: None,
+        };
+        assert_eq!(stmt.assignments.len(), 1);
+        assert_eq!(stmt.table.as_str(), "users");
+    }
+
     #[test]
     fn ast_node_enum() {
         let create = ASTNode::CreateTable(CreateTableStmt {
             name: TableName::new("users").unwrap(),
             columns: vec![],
+            primary_key: None,
         });
         let insert = ASTNode::Insert(InsertStmt {
             table: TableName::new("users").unwrap(),
-            values: vec![],
+            columns: None,
+            rows: vec![],
+            query: None,
+            on_conflict: None,
         });
         let select = ASTNode::Select(SelectStmt {
             table: TableName::new("users").unwrap(),
+            alias: None,
+            joins: vec![],
+            distinct: false,
             columns: None,
+            where_clause: None,
+            group_by: vec![],
+            having: None,
+            order_by: vec![],
+            limit: None,
+            offset: None,
         });
 
         match create {
@@ -2,19 +2,28 @@
 //!
 //! This module provides a simple lexer that transforms an input SQL string
 //! into a sequence of [`Token`]s. It recognizes keywords, identifiers,
-//! numbers, string literals, and symbols. Spaces and simple comments
-//! (starting with --) are ignored.
+//! numbers, string literals, symbols, and comparison operators. Spaces and simple comments
+//! (starting with --) are ignored. The token stream always ends with a
+//! single [`Token::Eof`] sentinel.
 
 use crate::core::error::{SqlError, SqlResult};
+use crate::core::span::Span;
 use crate::frontend::token::Token;
 
 /// List of SQL keywords recognized by the lexer.
-const KEYWORDS: &[&str] = &[
+pub const KEYWORDS: &[&str] = &[
     "SELECT", "INSERT", "UPDATE", "DELETE", "CREATE", "TABLE", "VALUES",
+    "FROM", "WHERE", "SET", "ALTER", "ADD", "DROP", "RENAME", "COLUMN", "TO",
+    "INTO", "NULL", "IS", "NOT", "TRUE", "FALSE", "ORDER", "BY", "ASC", "DESC",
+    "LIMIT", "OFFSET", "DISTINCT", "GROUP", "HAVING", "JOIN", "INNER", "CROSS", "ON", "AS",
+    "AND", "OR", "LIKE", "IN", "BETWEEN", "PRIMARY", "KEY", "UNIQUE",
+    "BEGIN", "TRANSACTION", "COMMIT", "ROLLBACK", "COPY", "INDEX", "EXPLAIN", "USING", "ANALYZE",
+    "SHOW", "DESCRIBE", "SAVEPOINT", "RELEASE", "CHECKPOINT", "PRAGMA",
+    "ATTACH", "DETACH", "SCHEMA", "VIEW", "TRUNCATE",
 ];
 
 /// Symbols recognized in SQL.
-const SYMBOLS: &[char] = &['(', ')', ',', ';'];
+const SYMBOLS: &[char] = &['(', ')', ',', ';', '*', '.'];
 
 /// Lexical analysis: transform input SQL string into a vector of tokens.
 ///
@@ -22,7 +31,7 @@ const SYMBOLS: &[char] = &['(', ')', ',', ';'];
 /// * `input` - SQL query string.
 ///
 /// # Returns
-/// * `Ok(Vec<Token>)` on success
+/// * `Ok(Vec<Token>)` on success, terminated by [`Token::Eof`]
 /// * `Err(SqlError)` if an invalid token is found
 pub fn lexer(input: &str) -> SqlResult<Vec<Token>> {
     let mut tokens = Vec::new();
@@ -39,28 +48,118 @@ pub fn lexer(input: &str) -> SqlResult<Vec<Token>> {
 
             // Symbols
             c if SYMBOLS.contains(&c) => {
-                tokens.push(Token::Symbol { value: c, pos: Some(pos) });
+                tokens.push(Token::Symbol { value: c, span: Some(Span::new(pos, pos + 1)) });
                 chars.next();
                 pos += 1;
             }
 
-            // Number literal
-            '0'..='9' => {
+            // Comparison operators, including the two-character `<=`, `>=`
+            // and `<>`
+            '<' | '>' => {
                 let start = pos;
-                let mut num_str = String::new();
-                while let Some(&c) = chars.peek() {
-                    if c.is_ascii_digit() {
-                        num_str.push(c);
+                chars.next();
+                pos += 1;
+                let mut op = ch.to_string();
+                if let Some(&next) = chars.peek() {
+                    if next == '=' || (ch == '<' && next == '>') {
+                        op.push(next);
                         chars.next();
                         pos += 1;
-                    } else {
+                    }
+                }
+                tokens.push(Token::Operator { value: op, span: Some(Span::new(start, pos)) });
+            }
+
+            // Line comments run from `--` to the end of the line
+            '-' if chars.clone().nth(1) == Some('-') => {
+                for c in chars.by_ref() {
+                    pos += 1;
+                    if c == '\n' {
                         break;
                     }
                 }
-                let value = num_str.parse::<i64>().map_err(|_| {
-                    SqlError::new_core(&format!("Invalid number at position {}", start))
-                })?;
-                tokens.push(Token::Number { value, pos: Some(start) });
+            }
+
+            // Equality and arithmetic operators ('*' is a symbol)
+            '=' | '+' | '-' | '/' | '%' => {
+                tokens.push(Token::Operator { value: ch.to_string(), span: Some(Span::new(pos, pos + 1)) });
+                chars.next();
+                pos += 1;
+            }
+            '!' => {
+                let start = pos;
+                chars.next();
+                pos += 1;
+                if chars.peek() != Some(&'=') {
+                    return Err(SqlError::new_lex(&format!("Expected '=' after '!' at position {}", start))
+                        .with_span(Span::new(start, pos)));
+                }
+                chars.next();
+                pos += 1;
+                tokens.push(Token::Operator { value: "!=".to_string(), span: Some(Span::new(start, pos)) });
+            }
+            '|' => {
+                let start = pos;
+                chars.next();
+                pos += 1;
+                if chars.peek() != Some(&'|') {
+                    return Err(SqlError::new_lex(&format!("Expected '|' after '|' at position {}", start))
+                        .with_span(Span::new(start, pos)));
+                }
+                chars.next();
+                pos += 1;
+                tokens.push(Token::Operator { value: "||".to_string(), span: Some(Span::new(start, pos)) });
+            }
+
+            // Number literal: digits, optional fraction, optional exponent
+            '0'..='9' => {
+                let start = pos;
+                let mut num_str = String::new();
+                let mut is_float = false;
+                read_digits(&mut chars, &mut num_str, &mut pos);
+
+                if chars.peek() == Some(&'.') {
+                    is_float = true;
+                    num_str.push('.');
+                    chars.next();
+                    pos += 1;
+                    read_digits(&mut chars, &mut num_str, &mut pos);
+                }
+
+                if matches!(chars.peek(), Some('e') | Some('E')) {
+                    is_float = true;
+                    num_str.push('e');
+                    chars.next();
+                    pos += 1;
+                    if let Some(&sign) = chars.peek() {
+                        if sign == '+' || sign == '-' {
+                            num_str.push(sign);
+                            chars.next();
+                            pos += 1;
+                        }
+                    }
+                    if !read_digits(&mut chars, &mut num_str, &mut pos) {
+                        return Err(SqlError::new_lex(&format!(
+                            "Malformed exponent in number at position {}",
+                            start
+                        ))
+                        .with_span(Span::new(start, pos)));
+                    }
+                }
+
+                if is_float {
+                    let value = num_str.parse::<f64>().map_err(|_| {
+                        SqlError::new_lex(&format!("Invalid number at position {}", start))
+                            .with_span(Span::new(start, pos))
+                    })?;
+                    tokens.push(Token::Float { value, span: Some(Span::new(start, pos)) });
+                } else {
+                    let value = num_str.parse::<i64>().map_err(|_| {
+                        SqlError::new_lex(&format!("Invalid number at position {}", start))
+                            .with_span(Span::new(start, pos))
+                    })?;
+                    tokens.push(Token::Number { value, span: Some(Span::new(start, pos)) });
+                }
             }
 
             // String literal
@@ -80,7 +179,40 @@ pub fn lexer(input: &str) -> SqlResult<Vec<Token>> {
                         pos += 1;
                     }
                 }
-                tokens.push(Token::String { value: s, pos: Some(start) });
+                tokens.push(Token::String { value: s, span: Some(Span::new(start, pos)) });
+            }
+
+            // Hex blob literal: X'DEADBEEF'
+            'x' | 'X' if chars.clone().nth(1) == Some('\'') => {
+                let start = pos;
+                chars.next(); // skip X
+                chars.next(); // skip opening quote
+                pos += 2;
+                let mut hex = String::new();
+                loop {
+                    match chars.next() {
+                        Some('\'') => {
+                            pos += 1;
+                            break;
+                        }
+                        Some(c) => {
+                            hex.push(c);
+                            pos += 1;
+                        }
+                        None => {
+                            return Err(SqlError::new_lex(&format!(
+                                "Unterminated hex literal at position {}",
+                                start
+                            ))
+                            .with_span(Span::new(start, pos)));
+                        }
+                    }
+                }
+                let value = decode_hex(&hex).ok_or_else(|| {
+                    SqlError::new_lex(&format!("Invalid hex literal at position {}", start))
+                        .with_span(Span::new(start, pos))
+                })?;
+                tokens.push(Token::Blob { value, span: Some(Span::new(start, pos)) });
             }
 
             // Identifier or keyword
@@ -98,21 +230,54 @@ pub fn lexer(input: &str) -> SqlResult<Vec<Token>> {
                 }
                 let word_upper = word.to_uppercase();
                 if KEYWORDS.contains(&word_upper.as_str()) {
-                    tokens.push(Token::Keyword { value: word_upper, pos: Some(start) });
+                    tokens.push(Token::Keyword { value: word_upper, span: Some(Span::new(start, pos)) });
                 } else {
-                    tokens.push(Token::Identifier { value: word, pos: Some(start) });
+                    tokens.push(Token::Identifier { value: word, span: Some(Span::new(start, pos)) });
                 }
             }
 
             _ => {
-                return Err(SqlError::new_core(&format!("Unexpected character '{}' at position {}", ch, pos)));
+                return Err(SqlError::new_lex(&format!("Unexpected character '{}' at position {}", ch, pos))
+                    .with_span(Span::new(pos, pos + 1)));
             }
         }
     }
 
+    tokens.push(Token::Eof { span: Some(Span::at(pos)) });
     Ok(tokens)
 }
 
+/// Decodes an even-length string of hex digits into bytes.
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(&hex[i..i + 2], 16).ok())
+        .collect()
+}
+
+/// Appends consecutive ASCII digits to `buf`, advancing `pos`.
+///
+/// Returns `true` if at least one digit was read.
+fn read_digits<I>(chars: &mut std::iter::Peekable<I>, buf: &mut String, pos: &mut usize) -> bool
+where
+    I: Iterator<Item = char>,
+{
+    let mut any = false;
+    while let Some(&c) = chars.peek() {
+        if !c.is_ascii_digit() {
+            break;
+        }
+        buf.push(c);
+        chars.next();
+        *pos += 1;
+        any = true;
+    }
+    any
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -124,16 +289,123 @@ mod tests {
         let tokens = lexer(sql).unwrap();
 
         let expected = vec![
-            Token::Keyword { value: "CREATE".to_string(), pos: Some(0) },
-            Token::Keyword { value: "TABLE".to_string(), pos: Some(7) },
-            Token::Identifier { value: "users".to_string(), pos: Some(13) },
-            Token::Symbol { value: '(', pos: Some(19) },
-            Token::Identifier { value: "id".to_string(), pos: Some(20) },
-            Token::Identifier { value: "Int".to_string(), pos: Some(23) },
-            Token::Symbol { value: ')', pos: Some(26) },
-            Token::Symbol { value: ';', pos: Some(27) },
+            Token::Keyword { value: "CREATE".to_string(), span: Some(Span::new(0, 6)) },
+            Token::Keyword { value: "TABLE".to_string(), span: Some(Span::new(7, 12)) },
+            Token::Identifier { value: "users".to_string(), span: Some(Span::new(13, 18)) },
+            Token::Symbol { value: '(', span: Some(Span::new(19, 20)) },
+            Token::Identifier { value: "id".to_string(), span: Some(Span::new(20, 22)) },
+            Token::Identifier { value: "Int".to_string(), span: Some(Span::new(23, 26)) },
+            Token::Symbol { value: ')', span: Some(Span::new(26, 27)) },
+            Token::Symbol { value: ';', span: Some(Span::new(27, 28)) },
+            Token::Eof { span: Some(Span::at(28)) },
         ];
 
         assert_eq!(tokens, expected);
     }
+
+    #[test]
+    fn lexer_select_with_where() {
+        let tokens = lexer("SELECT * FROM t WHERE a != 'x'").unwrap();
+
+        let expected = vec![
+            Token::Keyword { value: "SELECT".to_string(), span: Some(Span::new(0, 6)) },
+            Token::Symbol { value: '*', span: Some(Span::new(7, 8)) },
+            Token::Keyword { value: "FROM".to_string(), span: Some(Span::new(9, 13)) },
+            Token::Identifier { value: "t".to_string(), span: Some(Span::new(14, 15)) },
+            Token::Keyword { value: "WHERE".to_string(), span: Some(Span::new(16, 21)) },
+            Token::Identifier { value: "a".to_string(), span: Some(Span::new(22, 23)) },
+            Token::Operator { value: "!=".to_string(), span: Some(Span::new(24, 26)) },
+            Token::String { value: "x".to_string(), span: Some(Span::new(27, 30)) },
+            Token::Eof { span: Some(Span::at(30)) },
+        ];
+
+        assert_eq!(tokens, expected);
+        assert!(lexer("a ! b").is_err());
+    }
+
+    #[test]
+    fn lexer_skips_line_comments() {
+        let tokens = lexer("-- users\nSELECT 1 - -2; -- done").unwrap();
+        let values: Vec<String> = tokens.iter().map(Token::value).collect();
+        assert_eq!(values, ["SELECT", "1", "-", "-", "2", ";", ""]);
+        assert_eq!(tokens[0].pos(), Some(9));
+    }
+
+    #[test]
+    fn lexer_float_literals() {
+        let lex_one = |sql: &str| lexer(sql).unwrap().remove(0);
+
+        assert_eq!(lex_one("2.75"), Token::Float { value: 2.75, span: Some(Span::new(0, 4)) });
+        assert_eq!(lex_one("1e-5"), Token::Float { value: 1e-5, span: Some(Span::new(0, 4)) });
+        assert_eq!(lex_one("1.5e3"), Token::Float { value: 1500.0, span: Some(Span::new(0, 5)) });
+        assert_eq!(lex_one("2E+2"), Token::Float { value: 200.0, span: Some(Span::new(0, 4)) });
+        assert_eq!(lex_one("42"), Token::Number { value: 42, span: Some(Span::new(0, 2)) });
+        assert!(lexer("1e").is_err());
+        assert!(lexer("1e+").is_err());
+    }
+
+    #[test]
+    fn lexer_hex_blob_literals() {
+        let tokens = lexer("x'DEADbeef' X'' xray").unwrap();
+        assert_eq!(tokens[0], Token::Blob { value: vec![0xDE, 0xAD, 0xBE, 0xEF], span: Some(Span::new(0, 11)) });
+        assert_eq!(tokens[1], Token::Blob { value: vec![], span: Some(Span::new(12, 15)) });
+        assert_eq!(tokens[2], Token::Identifier { value: "xray".to_string(), span: Some(Span::new(16, 20)) });
+        assert!(lexer("X'ABC'").is_err());
+        assert!(lexer("X'ZZ'").is_err());
+        assert!(lexer("X'AB").is_err());
+    }
+
+    #[test]
+    fn lexer_errors_carry_the_span_of_the_bad_input() {
+        assert_eq!(lexer("SELECT #").unwrap_err().span(), Some(Span::new(7, 8)));
+        assert_eq!(lexer("a ! b").unwrap_err().span(), Some(Span::new(2, 3)));
+        assert_eq!(lexer("x = 1e+").unwrap_err().span(), Some(Span::new(4, 7)));
+    }
+
+    #[test]
+    fn lexer_appends_single_eof() {
+        let tokens = lexer("SELECT").unwrap();
+        let eofs = tokens.iter().filter(|t| matches!(t, Token::Eof { .. })).count();
+        assert_eq!(eofs, 1);
+        assert_eq!(tokens.last(), Some(&Token::Eof { span: Some(Span::at(6)) }));
+
+        assert_eq!(lexer("").unwrap(), vec![Token::Eof { span: Some(Span::at(0)) }]);
+    }
+
+    #[test]
+    fn lexer_two_character_comparisons() {
+        let ops: Vec<String> = lexer("a <= 1 AND b >= 2 OR c <> 3 AND d<e")
+            .unwrap()
+            .iter()
+            .filter(|t| matches!(t, Token::Operator { .. }))
+            .map(Token::value)
+            .collect();
+        assert_eq!(ops, vec!["<=", ">=", "<>", "<"]);
+        assert_eq!(lexer("x >= 1").unwrap()[1], Token::Operator { value: ">=".to_string(), span: Some(Span::new(2, 4)) });
+    }
+
+    #[test]
+    fn lexer_arithmetic_operators() {
+        let ops: Vec<String> = lexer("a + b - c / d % e || f")
+            .unwrap()
+            .iter()
+            .filter(|t| matches!(t, Token::Operator { .. }))
+            .map(Token::value)
+            .collect();
+        assert_eq!(ops, vec!["+", "-", "/", "%", "||"]);
+        assert_eq!(lexer("-1").unwrap()[1], Token::Number { value: 1, span: Some(Span::new(1, 2)) });
+        assert!(lexer("a | b").is_err());
+    }
+
+    #[test]
+    fn lexer_qualified_column_and_join() {
+        let tokens = lexer("a JOIN b ON a.id").unwrap();
+        assert_eq!(tokens[1], Token::Keyword { value: "JOIN".to_string(), span: Some(Span::new(2, 6)) });
+        assert_eq!(tokens[3], Token::Keyword { value: "ON".to_string(), span: Some(Span::new(9, 11)) });
+        assert_eq!(&tokens[4..7], &[
+            Token::Identifier { value: "a".to_string(), span: Some(Span::new(12, 13)) },
+            Token::Symbol { value: '.', span: Some(Span::new(13, 14)) },
+            Token::Identifier { value: "id".to_string(), span: Some(Span::new(14, 16)) },
+        ]);
+    }
 }
@@ -6,15 +6,73 @@
 //! correct number of values for INSERT statements.
 
 use crate::core::error::{SqlError, SqlResult};
-use crate::core::types::{TableName, ColumnName, DataType};
+use crate::core::datetime;
+use crate::core::types::{TableName, ColumnName, DataType, TypeAliases, Value};
 use crate::core::schema::Column;
+use crate::core::span::Span;
+use crate::core::index::IndexKind;
 use crate::frontend::token::Token;
-use crate::frontend::ast::{ASTNode, CreateTableStmt};
+use crate::frontend::lexer::KEYWORDS;
+use crate::frontend::ast::{
+    ASTNode, CreateTableStmt, CreateIndexStmt, InsertStmt, SelectStmt, UpdateStmt, DeleteStmt, AlterTableStmt, AlterAction,
+    WhereClause, CompareOp, Expr, SortDirection, SelectItem, AggregateFunc, HavingClause, ColumnRef, Join,
+    BinaryOp, TransactionStmt, CopyStmt, PragmaStmt, CreateViewStmt, OnConflict,
+};
 
 /// Parser struct with associated methods.
-pub struct Parser;
+///
+/// A `Parser` reads column types through its own [`TypeAliases`], the
+/// default ones unless more type names are
+/// [registered](Parser::register_alias). The REPL, the server and WAL
+/// replay each take the parser to read their input with.
+///
+/// # Examples
+/// ```
+/// use mini_rust_sgbd::core::types::DataType;
+/// use mini_rust_sgbd::frontend::{lexer::lexer, parser::Parser};
+///
+/// let mut parser = Parser::default();
+/// let tokens = lexer("CREATE TABLE t (n NUMBER)").unwrap();
+/// assert!(parser.parse(&tokens).is_err());
+/// parser.register_alias("NUMBER", DataType::Float).unwrap();
+/// assert!(parser.parse(&tokens).is_ok());
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Parser {
+    aliases: TypeAliases,
+}
+
+/// The error for a token stream that does not end with [`Token::Eof`], as
+/// every stream from [`lexer`](crate::frontend::lexer::lexer) does.
+fn missing_sentinel() -> SqlError {
+    SqlError::new_internal("Token stream does not end with the end-of-input sentinel")
+}
 
 impl Parser {
+    /// Creates a parser reading column types with `aliases`.
+    pub fn with_aliases(aliases: TypeAliases) -> Self {
+        Parser { aliases }
+    }
+
+    /// Returns the type names this parser resolves.
+    pub fn aliases(&self) -> &TypeAliases {
+        &self.aliases
+    }
+
+    /// Makes `alias` a name of `dtype` in column definitions parsed by this
+    /// parser.
+    ///
+    /// # Returns
+    /// `Ok(())`, or a semantic error if `alias` is a canonical type name, a
+    /// keyword, or not a valid identifier.
+    pub fn register_alias(&mut self, alias: &str, dtype: DataType) -> SqlResult<()> {
+        // A keyword never lexes as an identifier, so it could not be used
+        if KEYWORDS.contains(&alias.to_uppercase().as_str()) {
+            return Err(SqlError::new_semantic(&format!("'{}' is a keyword and cannot name a type", alias)));
+        }
+        self.aliases.register(alias, dtype).map_err(|e| SqlError::new_semantic(&e))
+    }
+
     /// Parse a sequence of tokens into a single AST node.
     ///
     /// # Arguments
@@ -23,22 +81,201 @@ impl Parser {
     /// # Returns
     /// * `Ok(ASTNode)` on success.
     /// * `Err(SqlError)` on syntax error or invalid structure.
-    pub fn parse(tokens: &[Token]) -> SqlResult<ASTNode> {
+    pub fn parse(&self, tokens: &[Token]) -> SqlResult<ASTNode> {
+        Self::parse_with(tokens, &self.aliases)
+    }
+
+    fn parse_with(tokens: &[Token], aliases: &TypeAliases) -> SqlResult<ASTNode> {
+        // Sub-parsers rely on the sentinel to report the end of input
+        if !matches!(tokens.last(), Some(Token::Eof { .. })) {
+            return Err(missing_sentinel());
+        }
         let mut iter = tokens.iter().peekable();
 
-        match iter.peek() {
-            Some(Token::Keyword { value, .. }) => match value.as_str() {
-                "CREATE" => Self::parse_create_table(&mut iter),
+        let ast = match iter.peek() {
+            Some(first @ Token::Keyword { value, .. }) => match value.as_str() {
+                "CREATE" => match tokens.get(1) {
+                    Some(Token::Keyword { value, .. }) if value == "INDEX" => Self::parse_create_index(&mut iter),
+                    Some(Token::Keyword { value, .. }) if value == "SCHEMA" => Self::parse_create_schema(&mut iter),
+                    Some(Token::Keyword { value, .. }) if value == "VIEW" => Self::parse_create_view(&mut iter),
+                    _ => Self::parse_create_table(&mut iter, aliases),
+                },
                 "INSERT" => Self::parse_insert(&mut iter),
                 "SELECT" => Self::parse_select(&mut iter),
-                _ => Err(SqlError::new_core(&format!("Unexpected keyword '{}'", value))),
+                "UPDATE" => Self::parse_update(&mut iter),
+                "DELETE" => Self::parse_delete(&mut iter),
+                "TRUNCATE" => Self::parse_truncate(&mut iter),
+                "ALTER" => Self::parse_alter_table(&mut iter, aliases),
+                "BEGIN" | "COMMIT" | "ROLLBACK" | "SAVEPOINT" | "RELEASE" => Self::parse_transaction(&mut iter),
+                "COPY" => Self::parse_copy(&mut iter),
+                "EXPLAIN" => Self::parse_explain(&mut iter),
+                "ANALYZE" => Self::parse_analyze(&mut iter),
+                "SHOW" => Self::parse_show(&mut iter),
+                "DESCRIBE" | "DESC" => Self::parse_describe(&mut iter),
+                "PRAGMA" => Self::parse_pragma(&mut iter),
+                "ATTACH" => Self::parse_attach(&mut iter),
+                "DETACH" => Self::parse_detach(&mut iter),
+                "CHECKPOINT" => {
+                    iter.next();
+                    Self::skip_semicolon(&mut iter);
+                    Ok(ASTNode::Checkpoint)
+                }
+                _ => Err(Self::at(SqlError::new_parse(&format!("Unexpected keyword '{}'", value)), first)),
+            },
+            Some(Token::Eof { .. }) => Err(SqlError::new_parse("Empty token stream")),
+            None => Err(missing_sentinel()),
+            Some(first) => Err(Self::at(SqlError::new_parse("Expected a keyword at the beginning"), first)),
+        }?;
+
+        // The statement must be followed by the end-of-input sentinel
+        match iter.next() {
+            Some(Token::Eof { .. }) => Ok(ast),
+            None => Err(missing_sentinel()),
+            other => Err(Self::expected("end of input", other)),
+        }
+    }
+
+    /// Parses a script: statements separated by `;`.
+    ///
+    /// Empty statements, such as the one after a trailing `;`, are skipped.
+    /// The whole script is parsed before anything runs, so a syntax error
+    /// anywhere rejects all of it.
+    ///
+    /// # Returns
+    /// The statements in order, or the first syntax error.
+    pub fn parse_script(&self, tokens: &[Token]) -> SqlResult<Vec<ASTNode>> {
+        Ok(self.parse_script_spans(tokens)?.into_iter().map(|(ast, _)| ast).collect())
+    }
+
+    /// Parses a script as [`parse_script`](Self::parse_script) does, also
+    /// returning where each statement is in the source: from its first
+    /// token to its last one, without the `;`.
+    ///
+    /// # Returns
+    /// The statements and their spans in order, or the first syntax error.
+    /// The span is `None` for tokens built without one.
+    pub fn parse_script_spans(&self, tokens: &[Token]) -> SqlResult<Vec<(ASTNode, Option<Span>)>> {
+        Self::script_spans_with(tokens, &self.aliases)
+    }
+
+    fn script_spans_with(tokens: &[Token], aliases: &TypeAliases) -> SqlResult<Vec<(ASTNode, Option<Span>)>> {
+        let mut statements = Vec::new();
+        let mut start = 0;
+        for (i, token) in tokens.iter().enumerate() {
+            // A statement ends at its ';', which then reads as end of input
+            let end = match token {
+                Token::Symbol { value: ';', span } => Token::Eof { span: *span },
+                Token::Eof { .. } => token.clone(),
+                _ => continue,
+            };
+            if i > start {
+                let mut statement = tokens[start..i].to_vec();
+                statement.push(end);
+                statements.push((Self::parse_with(&statement, aliases)?, Self::span_of(&tokens[start..i])));
+            }
+            start = i + 1;
+        }
+        if start < tokens.len() {
+            statements.push((Self::parse_with(&tokens[start..], aliases)?, Self::span_of(&tokens[start..])));
+        }
+        Ok(statements)
+    }
+
+    /// Returns the span from the first of `tokens` to the last one, leaving
+    /// out the end-of-input sentinel.
+    fn span_of(tokens: &[Token]) -> Option<Span> {
+        let tokens = match tokens.last() {
+            Some(Token::Eof { .. }) => &tokens[..tokens.len() - 1],
+            _ => tokens,
+        };
+        let first = tokens.first()?.span()?;
+        let last = tokens.last()?.span()?;
+        Some(Span::new(first.start, last.end))
+    }
+
+    /// Builds a syntax error describing what was expected and what was found.
+    ///
+    /// End of input is reported with the position of the [`Token::Eof`]
+    /// sentinel when available. The error keeps the span of `found`.
+    fn expected(what: &str, found: Option<&Token>) -> SqlError {
+        let description = match found {
+            Some(Token::Eof { span: Some(span) }) => format!("end of input at position {}", span.start),
+            Some(Token::Eof { span: None }) | None => "end of input".to_string(),
+            Some(token) => match token.pos() {
+                Some(p) => format!("'{}' at position {}", token.value(), p),
+                None => format!("'{}'", token.value()),
             },
-            Some(_) => Err(SqlError::new_core("Expected a keyword at the beginning")),
-            None => Err(SqlError::new_core("Empty token stream")),
+        };
+        let error = SqlError::new_parse(&format!("Expected {}, found {}", what, description));
+        match found {
+            Some(token) => Self::at(error, token),
+            None => error,
+        }
+    }
+
+    /// Attaches the span of `token`, if it has one, to `error`.
+    fn at(error: SqlError, token: &Token) -> SqlError {
+        match token.span() {
+            Some(span) => error.with_span(span),
+            None => error,
+        }
+    }
+
+    fn parse_create_schema<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        // Consume CREATE SCHEMA
+        iter.next();
+        iter.next();
+
+        let name = match iter.next() {
+            Some(Token::Identifier { value, .. }) => value.clone(),
+            other => return Err(Self::expected("schema name after SCHEMA", other)),
+        };
+        Self::skip_semicolon(iter);
+        Ok(ASTNode::CreateSchema(name))
+    }
+
+    fn parse_create_view<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        // Consume CREATE VIEW
+        iter.next();
+        iter.next();
+
+        let name = Self::parse_table_name(iter, "view name after VIEW")?;
+        match iter.next() {
+            Some(Token::Keyword { value, .. }) if value == "AS" => {}
+            other => return Err(Self::expected("AS after view name", other)),
+        }
+
+        // The rest of the statement is the query, kept as text too. It is
+        // parsed as a statement of its own, ending with the same sentinel,
+        // which is left for the caller
+        let mut rest: Vec<Token> = Vec::new();
+        while let Some(token) = iter.next_if(|token| !matches!(token, Token::Eof { .. })) {
+            rest.push(token.clone());
         }
+        rest.extend(iter.peek().map(|eof| (*eof).clone()));
+        let query = match rest.first() {
+            Some(Token::Keyword { value, .. }) if value == "SELECT" => match Self::parse_with(&rest, &TypeAliases::default())? {
+                ASTNode::Select(query) => query,
+                _ => unreachable!("a statement starting with SELECT is a query"),
+            },
+            other => return Err(Self::expected("SELECT after AS", other)),
+        };
+        let sql = rest
+            .iter()
+            .filter(|token| !matches!(token, Token::Eof { .. } | Token::Symbol { value: ';', .. }))
+            .map(Token::to_sql)
+            .collect::<Vec<_>>()
+            .join(" ");
+        Ok(ASTNode::CreateView(CreateViewStmt { name, query, sql }))
     }
 
-    fn parse_create_table<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    fn parse_create_table<'a, I>(iter: &mut std::iter::Peekable<I>, aliases: &TypeAliases) -> SqlResult<ASTNode>
     where
         I: Iterator<Item = &'a Token>,
     {
@@ -48,109 +285,2385 @@ impl Parser {
         // Expect TABLE
         match iter.next() {
             Some(Token::Keyword { value, .. }) if value == "TABLE" => {}
-            _ => return Err(SqlError::new_core("Expected TABLE after CREATE")),
+            other => return Err(Self::expected("TABLE after CREATE", other)),
         }
 
         // Table name
-        let table_name = match iter.next() {
-            Some(Token::Identifier { value, .. }) => TableName::new(value)
-                .map_err(|e| SqlError::new_core(&e))?,
-            _ => return Err(SqlError::new_core("Expected table name after TABLE")),
-        };
+        let table_name = Self::parse_table_name(iter, "table name after TABLE")?;
 
 
         // Expect '('
         match iter.next() {
             Some(Token::Symbol { value, .. }) if *value == '(' => {}
-            _ => return Err(SqlError::new_core("Expected '(' after table name")),
+            other => return Err(Self::expected("'(' after table name", other)),
         }
 
         let mut columns = Vec::new();
+        let mut primary_key = None;
         loop {
-            // Column name
-            let col_name = match iter.next() {
-                Some(Token::Identifier { value, .. }) => ColumnName::new(value)
-                    .map_err(|e| SqlError::new_core(&e))?,
-                _ => return Err(SqlError::new_core("Expected column name"))
-            };
-
-            // Column type
-            let col_type = match iter.next() {
-                Some(Token::Identifier { value, .. }) => match value.as_str() {
-                    "Int" => DataType::Int,
-                    "Text" => DataType::Text,
-                    _ => return Err(SqlError::new_core(&format!("Unknown type '{}'", value))),
-                },
-                _ => return Err(SqlError::new_core("Expected column type")),
-            };
-
-            columns.push(Column::new(col_name, col_type));
+            let (column, is_key) = Self::parse_column_def(iter, aliases)?;
+            if is_key {
+                if primary_key.is_some() {
+                    return Err(SqlError::new_parse(&format!(
+                        "Table '{}' has more than one PRIMARY KEY",
+                        table_name.as_str()
+                    )));
+                }
+                primary_key = Some(column.name.clone());
+            }
+            columns.push(column);
 
             // Comma or closing parenthesis
             match iter.next() {
                 Some(Token::Symbol { value, .. }) if *value == ',' => continue,
                 Some(Token::Symbol { value, .. }) if *value == ')' => break,
-                _ => return Err(SqlError::new_core("Expected ',' or ')' after column definition")),
+                other => return Err(Self::expected("',' or ')' after column definition", other)),
             }
         }
 
-        // Optional ';'
-        if let Some(Token::Symbol { value, .. }) = iter.peek() {
-            if *value == ';' {
+        Self::skip_semicolon(iter);
+
+        Ok(ASTNode::CreateTable(CreateTableStmt { name: table_name, columns, primary_key }))
+    }
+
+    /// Parses a column definition: `name type` followed by any of the
+    /// constraints `NOT NULL`, `NULL`, `UNIQUE` and `PRIMARY KEY`.
+    ///
+    /// The type is resolved through `aliases`.
+    ///
+    /// # Returns
+    /// The column, and whether it was declared as the primary key.
+    fn parse_column_def<'a, I>(iter: &mut std::iter::Peekable<I>, aliases: &TypeAliases) -> SqlResult<(Column, bool)>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        let col_name = Self::parse_column_name(iter)?;
+
+        let col_type = match iter.next() {
+            Some(Token::Identifier { value, .. }) => aliases.resolve(value).map_err(|e| SqlError::new_parse(&e))?,
+            other => return Err(Self::expected("column type", other)),
+        };
+
+        let mut column = Column::new(col_name, col_type);
+        let mut is_key = false;
+        while let Some(Token::Keyword { value, .. }) = iter.peek() {
+            match value.as_str() {
+                "NOT" => {
+                    iter.next();
+                    match iter.next() {
+                        Some(Token::Keyword { value, .. }) if value == "NULL" => column.nullable = false,
+                        other => return Err(Self::expected("NULL after NOT", other)),
+                    }
+                }
+                "NULL" => {
+                    iter.next();
+                    column.nullable = true;
+                }
+                "UNIQUE" => {
+                    iter.next();
+                    column.unique = true;
+                }
+                "PRIMARY" => {
+                    iter.next();
+                    match iter.next() {
+                        Some(Token::Keyword { value, .. }) if value == "KEY" => is_key = true,
+                        other => return Err(Self::expected("KEY after PRIMARY", other)),
+                    }
+                }
+                _ => break,
+            }
+        }
+
+        Ok((column, is_key))
+    }
+
+    /// Parses `BEGIN [TRANSACTION]`, `COMMIT`, `ROLLBACK`, `SAVEPOINT name`,
+    /// `ROLLBACK TO [SAVEPOINT] name` or `RELEASE [SAVEPOINT] name`.
+    fn parse_transaction<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        let stmt = match iter.next() {
+            Some(Token::Keyword { value, .. }) if value == "BEGIN" => {
+                Self::skip_keyword(iter, "TRANSACTION");
+                TransactionStmt::Begin
+            }
+            Some(Token::Keyword { value, .. }) if value == "COMMIT" => TransactionStmt::Commit,
+            Some(Token::Keyword { value, .. }) if value == "ROLLBACK" => {
+                if matches!(iter.peek(), Some(Token::Keyword { value, .. }) if value == "TO") {
+                    iter.next();
+                    Self::skip_keyword(iter, "SAVEPOINT");
+                    TransactionStmt::RollbackTo(Self::parse_savepoint_name(iter)?)
+                } else {
+                    TransactionStmt::Rollback
+                }
+            }
+            Some(Token::Keyword { value, .. }) if value == "SAVEPOINT" => {
+                TransactionStmt::Savepoint(Self::parse_savepoint_name(iter)?)
+            }
+            Some(Token::Keyword { value, .. }) if value == "RELEASE" => {
+                Self::skip_keyword(iter, "SAVEPOINT");
+                TransactionStmt::Release(Self::parse_savepoint_name(iter)?)
+            }
+            other => return Err(Self::expected("BEGIN, COMMIT or ROLLBACK", other)),
+        };
+
+        Self::skip_semicolon(iter);
+
+        Ok(ASTNode::Transaction(stmt))
+    }
+
+    fn parse_savepoint_name<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<String>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        match iter.next() {
+            Some(Token::Identifier { value, .. }) => Ok(value.clone()),
+            other => Err(Self::expected("savepoint name", other)),
+        }
+    }
+
+    fn parse_create_index<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        // Consume CREATE INDEX
+        iter.next();
+        iter.next();
+
+        let name = match iter.next() {
+            Some(Token::Identifier { value, .. }) => value.clone(),
+            other => return Err(Self::expected("index name after INDEX", other)),
+        };
+
+        match iter.next() {
+            Some(Token::Keyword { value, .. }) if value == "ON" => {}
+            other => return Err(Self::expected("ON after index name", other)),
+        }
+
+        let table = Self::parse_table_name(iter, "table name after ON")?;
+
+        match iter.next() {
+            Some(Token::Symbol { value: '(', .. }) => {}
+            other => return Err(Self::expected("'(' after table name", other)),
+        }
+        let column = match iter.next() {
+            Some(Token::Identifier { value, .. }) => ColumnName::new(value)
+                .map_err(|e| SqlError::new_parse(&e))?,
+            other => return Err(Self::expected("column name", other)),
+        };
+        match iter.next() {
+            Some(Token::Symbol { value: ')', .. }) => {}
+            other => return Err(Self::expected("')' after column name", other)),
+        }
+
+        // Optional USING BTREE | HASH
+        let mut kind = IndexKind::default();
+        if matches!(iter.peek(), Some(Token::Keyword { value, .. }) if value == "USING") {
+            iter.next();
+            kind = match iter.next() {
+                Some(Token::Identifier { value, .. }) => value.parse().map_err(|e: String| SqlError::new_parse(&e))?,
+                other => return Err(Self::expected("BTREE or HASH after USING", other)),
+            };
+        }
+
+        Self::skip_semicolon(iter);
+
+        Ok(ASTNode::CreateIndex(CreateIndexStmt { name, table, column, kind }))
+    }
+
+    fn parse_explain<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        // Consume EXPLAIN
+        iter.next();
+
+        match iter.peek() {
+            Some(Token::Keyword { value, .. }) if value == "SELECT" => match Self::parse_select(iter)? {
+                ASTNode::Select(stmt) => Ok(ASTNode::Explain(stmt)),
+                _ => unreachable!("parse_select returns a SELECT"),
+            },
+            other => Err(Self::expected("SELECT after EXPLAIN", other.copied())),
+        }
+    }
+
+    fn parse_analyze<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        // Consume ANALYZE
+        iter.next();
+
+        let table = Self::parse_table_name(iter, "table name after ANALYZE")?;
+        Self::skip_semicolon(iter);
+        Ok(ASTNode::Analyze(table))
+    }
+
+    fn parse_truncate<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        // Consume TRUNCATE, and TABLE if present
+        iter.next();
+        if matches!(iter.peek(), Some(Token::Keyword { value, .. }) if value == "TABLE") {
+            iter.next();
+        }
+
+        let table = Self::parse_table_name(iter, "table name after TRUNCATE")?;
+        Self::skip_semicolon(iter);
+        Ok(ASTNode::Truncate(table))
+    }
+
+    fn parse_pragma<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        // Consume PRAGMA
+        iter.next();
+
+        let name = match iter.next() {
+            Some(Token::Identifier { value, .. }) => value.to_ascii_lowercase(),
+            other => return Err(Self::expected("setting name after PRAGMA", other)),
+        };
+        let value = match iter.peek() {
+            Some(Token::Operator { value, .. }) if value == "=" => {
                 iter.next();
+                match iter.next() {
+                    Some(Token::Identifier { value, .. } | Token::Keyword { value, .. } | Token::String { value, .. }) => {
+                        Some(value.clone())
+                    }
+                    Some(Token::Number { value, .. }) => Some(value.to_string()),
+                    other => return Err(Self::expected("value after '='", other)),
+                }
             }
+            _ => None,
+        };
+        Self::skip_semicolon(iter);
+        Ok(ASTNode::Pragma(PragmaStmt { name, value }))
+    }
+
+    fn parse_attach<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        // Consume ATTACH, and DATABASE if present
+        iter.next();
+        if matches!(iter.peek(), Some(Token::Identifier { value, .. }) if value.eq_ignore_ascii_case("DATABASE")) {
+            iter.next();
         }
 
-        Ok(ASTNode::CreateTable(CreateTableStmt { name: table_name, columns }))
+        let path = match iter.next() {
+            Some(Token::String { value, .. }) => value.clone(),
+            other => return Err(Self::expected("database path string after ATTACH", other)),
+        };
+        match iter.next() {
+            Some(Token::Keyword { value, .. }) if value == "AS" => {}
+            other => return Err(Self::expected("AS after database path", other)),
+        }
+        let alias = match iter.next() {
+            Some(Token::Identifier { value, .. }) => value.clone(),
+            other => return Err(Self::expected("alias after AS", other)),
+        };
+        Self::skip_semicolon(iter);
+        Ok(ASTNode::Attach { path, alias })
     }
 
-    fn parse_insert<'a, I>(_iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    fn parse_detach<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
     where
         I: Iterator<Item = &'a Token>,
     {
-        // Simplified: placeholder
-        Err(SqlError::new_core("INSERT parsing not implemented yet"))
+        // Consume DETACH
+        iter.next();
+
+        let mut alias = match iter.next() {
+            Some(Token::Identifier { value, .. }) => value.clone(),
+            other => return Err(Self::expected("alias after DETACH", other)),
+        };
+        // DETACH DATABASE alias, unless the alias itself is "database"
+        if alias.eq_ignore_ascii_case("DATABASE") {
+            if let Some(Token::Identifier { value, .. }) = iter.peek() {
+                alias = value.clone();
+                iter.next();
+            }
+        }
+        Self::skip_semicolon(iter);
+        Ok(ASTNode::Detach(alias))
     }
 
-    fn parse_select<'a, I>(_iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    fn parse_show<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
     where
         I: Iterator<Item = &'a Token>,
     {
-        // Simplified: placeholder
-        Err(SqlError::new_core("SELECT parsing not implemented yet"))
+        // Consume SHOW
+        iter.next();
+
+        // TABLES is not a keyword, so that it can name columns and tables
+        match iter.next() {
+            Some(Token::Identifier { value, .. }) if value.eq_ignore_ascii_case("TABLES") => {}
+            other => return Err(Self::expected("TABLES after SHOW", other)),
+        }
+        Self::skip_semicolon(iter);
+        Ok(ASTNode::ShowTables)
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::frontend::token::Token;
+    fn parse_describe<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        // Consume DESCRIBE or DESC
+        iter.next();
 
-    #[test]
-    fn parse_create_table_example() {
-        let sql_tokens = vec![
-            Token::Keyword { value: "CREATE".to_string(), pos: Some(0) },
-            Token::Keyword { value: "TABLE".to_string(), pos: Some(7) },
-            Token::Identifier { value: "users".to_string(), pos: Some(13) },
-            Token::Symbol { value: '(', pos: Some(19) },
-            Token::Identifier { value: "id".to_string(), pos: Some(20) },
-            Token::Identifier { value: "Int".to_string(), pos: Some(23) },
-            Token::Symbol { value: ',', pos: Some(26) },
-            Token::Identifier { value: "name".to_string(), pos: Some(28) },
-            Token::Identifier { value: "Text".to_string(), pos: Some(33) },
-            Token::Symbol { value: ')', pos: Some(37) },
-            Token::Symbol { value: ';', pos: Some(38) },
-        ];
+        let table = Self::parse_table_name(iter, "table name after DESCRIBE")?;
+        Self::skip_semicolon(iter);
+        Ok(ASTNode::Describe(table))
+    }
 
-        let ast = Parser::parse(&sql_tokens).unwrap();
-        match ast {
-            ASTNode::CreateTable(stmt) => {
-                assert_eq!(stmt.name.as_str(), "users");
-                assert_eq!(stmt.columns.len(), 2);
-                assert_eq!(stmt.columns[0].name.as_str(), "id");
-                assert_eq!(stmt.columns[1].name.as_str(), "name");
+    fn parse_copy<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        // Consume COPY
+        iter.next();
+
+        // COPY (SELECT ...) TO 'path'
+        if let Some(Token::Symbol { value: '(', .. }) = iter.peek() {
+            iter.next();
+            let query = match iter.peek() {
+                Some(Token::Keyword { value, .. }) if value == "SELECT" => match Self::parse_select(iter)? {
+                    ASTNode::Select(query) => query,
+                    _ => unreachable!("parse_select returns a SELECT"),
+                },
+                other => return Err(Self::expected("SELECT after '('", other.copied())),
+            };
+            match iter.next() {
+                Some(Token::Symbol { value: ')', .. }) => {}
+                other => return Err(Self::expected("')' after query", other)),
             }
-            _ => panic!("Expected CreateTable ASTNode"),
+            match iter.next() {
+                Some(Token::Keyword { value, .. }) if value == "TO" => {}
+                other => return Err(Self::expected("TO after query", other)),
+            }
+            let path = Self::parse_copy_path(iter)?;
+            return Ok(ASTNode::Copy(CopyStmt::To { query, path }));
+        }
+
+        let table = Self::parse_table_name(iter, "table name after COPY")?;
+
+        let stmt = match iter.next() {
+            Some(Token::Keyword { value, .. }) if value == "FROM" => {
+                CopyStmt::From { table, path: Self::parse_copy_path(iter)? }
+            }
+            Some(Token::Keyword { value, .. }) if value == "TO" => {
+                let query = SelectStmt {
+                    table,
+                    alias: None,
+                    joins: Vec::new(),
+                    distinct: false,
+                    columns: None,
+                    where_clause: None,
+                    group_by: Vec::new(),
+                    having: None,
+                    order_by: Vec::new(),
+                    limit: None,
+                    offset: None,
+                };
+                CopyStmt::To { query, path: Self::parse_copy_path(iter)? }
+            }
+            other => return Err(Self::expected("FROM or TO after table name", other)),
+        };
+
+        Ok(ASTNode::Copy(stmt))
+    }
+
+    /// Parses the quoted file path ending a COPY statement.
+    fn parse_copy_path<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<String>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        let path = match iter.next() {
+            Some(Token::String { value, .. }) => value.clone(),
+            other => return Err(Self::expected("file path string", other)),
+        };
+        Self::skip_semicolon(iter);
+        Ok(path)
+    }
+
+    fn parse_alter_table<'a, I>(iter: &mut std::iter::Peekable<I>, aliases: &TypeAliases) -> SqlResult<ASTNode>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        // Consume ALTER
+        iter.next();
+
+        // Expect TABLE
+        match iter.next() {
+            Some(Token::Keyword { value, .. }) if value == "TABLE" => {}
+            other => return Err(Self::expected("TABLE after ALTER", other)),
         }
+
+        // Table name
+        let table = Self::parse_table_name(iter, "table name after TABLE")?;
+
+        let action = match iter.next() {
+            Some(Token::Keyword { value, .. }) if value == "ADD" => {
+                Self::skip_keyword(iter, "COLUMN");
+                let (column, is_key) = Self::parse_column_def(iter, aliases)?;
+                if is_key {
+                    return Err(SqlError::new_parse("ALTER TABLE cannot add a PRIMARY KEY column"));
+                }
+                AlterAction::AddColumn(column)
+            }
+            Some(Token::Keyword { value, .. }) if value == "DROP" => {
+                Self::skip_keyword(iter, "COLUMN");
+                AlterAction::DropColumn(Self::parse_column_name(iter)?)
+            }
+            Some(Token::Keyword { value, .. }) if value == "RENAME" => {
+                if matches!(iter.peek(), Some(Token::Keyword { value, .. }) if value == "TO") {
+                    iter.next();
+                    let name = Self::parse_table_name(iter, "new table name after TO")?;
+                    Self::skip_semicolon(iter);
+                    return Ok(ASTNode::AlterTable(AlterTableStmt { table, action: AlterAction::RenameTable(name) }));
+                }
+                Self::skip_keyword(iter, "COLUMN");
+                let from = Self::parse_column_name(iter)?;
+                match iter.next() {
+                    Some(Token::Keyword { value, .. }) if value == "TO" => {}
+                    other => return Err(Self::expected("TO after column name", other)),
+                }
+                let to = Self::parse_column_name(iter)?;
+                AlterAction::RenameColumn { from, to }
+            }
+            other => return Err(Self::expected("ADD, DROP or RENAME", other)),
+        };
+
+        Self::skip_semicolon(iter);
+
+        Ok(ASTNode::AlterTable(AlterTableStmt { table, action }))
+    }
+
+    /// Consumes the given keyword if it is the next token.
+    fn skip_keyword<'a, I>(iter: &mut std::iter::Peekable<I>, keyword: &str)
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        if let Some(Token::Keyword { value, .. }) = iter.peek() {
+            if value == keyword {
+                iter.next();
+            }
+        }
+    }
+
+    fn parse_insert<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        // Consume INSERT
+        iter.next();
+
+        // INSERT OR IGNORE / INSERT OR REPLACE; neither word is a keyword
+        let mut on_conflict = None;
+        if let Some(Token::Keyword { value, .. }) = iter.peek() {
+            if value == "OR" {
+                iter.next();
+                on_conflict = Some(match iter.next() {
+                    Some(Token::Identifier { value, .. }) if value.eq_ignore_ascii_case("IGNORE") => OnConflict::Ignore(None),
+                    Some(Token::Identifier { value, .. }) if value.eq_ignore_ascii_case("REPLACE") => OnConflict::Replace,
+                    other => return Err(Self::expected("IGNORE or REPLACE after OR", other)),
+                });
+            }
+        }
+
+        // Expect INTO
+        match iter.next() {
+            Some(Token::Keyword { value, .. }) if value == "INTO" => {}
+            other => return Err(Self::expected("INTO after INSERT", other)),
+        }
+
+        // Table name
+        let table = Self::parse_table_name(iter, "table name after INTO")?;
+
+        // Optional column list: (a, b, ...)
+        let columns = match iter.peek() {
+            Some(Token::Symbol { value, .. }) if *value == '(' => {
+                iter.next();
+                let mut columns = Vec::new();
+                loop {
+                    columns.push(Self::parse_column_name(iter)?);
+                    match iter.next() {
+                        Some(Token::Symbol { value, .. }) if *value == ',' => continue,
+                        Some(Token::Symbol { value, .. }) if *value == ')' => break,
+                        other => return Err(Self::expected("',' or ')' after column name", other)),
+                    }
+                }
+                Some(columns)
+            }
+            _ => None,
+        };
+
+        // INSERT ... SELECT: the rows of a query
+        if let Some(Token::Keyword { value, .. }) = iter.peek() {
+            if value == "SELECT" {
+                let query = match Self::parse_select(iter)? {
+                    ASTNode::Select(query) => query,
+                    _ => unreachable!("parse_select returns a SELECT"),
+                };
+                let on_conflict = Self::parse_on_conflict(iter, on_conflict)?;
+                Self::skip_semicolon(iter);
+                let query = Some(Box::new(query));
+                return Ok(ASTNode::Insert(InsertStmt { table, columns, rows: Vec::new(), query, on_conflict }));
+            }
+        }
+
+        // Expect VALUES
+        match iter.next() {
+            Some(Token::Keyword { value, .. }) if value == "VALUES" => {}
+            other => return Err(Self::expected("VALUES or SELECT after table name", other)),
+        }
+
+        // One or more value tuples: (...), (...)
+        let mut rows = Vec::new();
+        loop {
+            let values = Self::parse_value_tuple(iter)?;
+            if let Some(columns) = &columns {
+                if columns.len() != values.len() {
+                    return Err(SqlError::new_parse(&format!(
+                        "INSERT lists {} columns but {} values",
+                        columns.len(),
+                        values.len()
+                    )));
+                }
+            }
+            rows.push(values);
+
+            match iter.peek() {
+                Some(Token::Symbol { value, .. }) if *value == ',' => {
+                    iter.next();
+                }
+                _ => break,
+            }
+        }
+
+        let on_conflict = Self::parse_on_conflict(iter, on_conflict)?;
+        Self::skip_semicolon(iter);
+
+        Ok(ASTNode::Insert(InsertStmt { table, columns, rows, query: None, on_conflict }))
+    }
+
+    /// Parses the optional `ON CONFLICT [(column)] DO NOTHING` or
+    /// `ON CONFLICT (column) DO UPDATE SET ...` clause ending an INSERT.
+    /// CONFLICT, DO and NOTHING are not keywords.
+    ///
+    /// # Arguments
+    /// - `or`: the conflict handling given by `INSERT OR ...`, if any,
+    ///   which cannot be combined with the clause
+    fn parse_on_conflict<'a, I>(iter: &mut std::iter::Peekable<I>, or: Option<OnConflict>) -> SqlResult<Option<OnConflict>>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        match iter.peek() {
+            Some(Token::Keyword { value, .. }) if value == "ON" => {}
+            _ => return Ok(or),
+        }
+        iter.next();
+        if or.is_some() {
+            return Err(SqlError::new_parse("INSERT OR ... cannot also have ON CONFLICT"));
+        }
+        let word = |token: Option<&Token>, word: &str| matches!(token, Some(Token::Identifier { value, .. }) if value.eq_ignore_ascii_case(word));
+        let token = iter.next();
+        if !word(token, "CONFLICT") {
+            return Err(Self::expected("CONFLICT after ON", token));
+        }
+
+        let column = match iter.peek() {
+            Some(Token::Symbol { value: '(', .. }) => {
+                iter.next();
+                let column = Self::parse_column_name(iter)?;
+                match iter.next() {
+                    Some(Token::Symbol { value: ')', .. }) => {}
+                    other => return Err(Self::expected("')' after conflict column", other)),
+                }
+                Some(column)
+            }
+            _ => None,
+        };
+        let token = iter.next();
+        if !word(token, "DO") {
+            return Err(Self::expected("DO after ON CONFLICT", token));
+        }
+
+        match iter.next() {
+            token if word(token, "NOTHING") => Ok(Some(OnConflict::Ignore(column))),
+            Some(Token::Keyword { value, .. }) if value == "UPDATE" => {
+                let column = column.ok_or_else(|| {
+                    SqlError::new_parse("ON CONFLICT DO UPDATE needs a conflict column, as in ON CONFLICT (id)")
+                })?;
+                match iter.next() {
+                    Some(Token::Keyword { value, .. }) if value == "SET" => {}
+                    other => return Err(Self::expected("SET after DO UPDATE", other)),
+                }
+                let assignments = Self::parse_assignments(iter, true)?;
+                Ok(Some(OnConflict::Update { column, assignments }))
+            }
+            other => Err(Self::expected("NOTHING or UPDATE after DO", other)),
+        }
+    }
+
+    /// Parses one parenthesized, comma-separated value tuple of an INSERT.
+    fn parse_value_tuple<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<Vec<Expr>>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        match iter.next() {
+            Some(Token::Symbol { value, .. }) if *value == '(' => {}
+            other => return Err(Self::expected("'(' before values", other)),
+        }
+
+        let mut values = Vec::new();
+        loop {
+            values.push(Self::parse_expr(iter)?);
+
+            // Comma or closing parenthesis
+            match iter.next() {
+                Some(Token::Symbol { value, .. }) if *value == ',' => continue,
+                Some(Token::Symbol { value, .. }) if *value == ')' => break,
+                other => return Err(Self::expected("',' or ')' after value", other)),
+            }
+        }
+        Ok(values)
+    }
+
+    fn parse_select<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        // Consume SELECT
+        iter.next();
+
+        let distinct = match iter.peek() {
+            Some(Token::Keyword { value, .. }) if value == "DISTINCT" => {
+                iter.next();
+                true
+            }
+            _ => false,
+        };
+
+        // Projection: '*' or a comma-separated column list
+        let columns = match iter.peek() {
+            Some(Token::Symbol { value, .. }) if *value == '*' => {
+                iter.next();
+                None
+            }
+            _ => {
+                let mut cols = Vec::new();
+                loop {
+                    let item = Self::parse_select_item(iter)?;
+                    let alias = Self::parse_optional_alias(iter)?
+                        .map(|name| ColumnName::new(name).map_err(|e| SqlError::new_parse(&e)))
+                        .transpose()?;
+                    cols.push((item, alias));
+                    match iter.peek() {
+                        Some(Token::Symbol { value, .. }) if *value == ',' => {
+                            iter.next();
+                        }
+                        _ => break,
+                    }
+                }
+                Some(cols)
+            }
+        };
+
+        // Expect FROM
+        match iter.next() {
+            Some(Token::Keyword { value, .. }) if value == "FROM" => {}
+            other => return Err(Self::expected("FROM after column list", other)),
+        }
+
+        // Table name
+        let table = Self::parse_table_name(iter, "table name after FROM")?;
+        let alias = Self::parse_table_alias(iter)?;
+
+        let joins = Self::parse_joins(iter)?;
+        let where_clause = Self::parse_optional_where(iter)?;
+        let group_by = Self::parse_optional_group_by(iter)?;
+        let having = Self::parse_optional_having(iter)?;
+        let order_by = Self::parse_optional_order_by(iter)?;
+        let limit = Self::parse_optional_count(iter, "LIMIT")?;
+        let offset = Self::parse_optional_count(iter, "OFFSET")?;
+
+        Self::skip_semicolon(iter);
+
+        Ok(ASTNode::Select(SelectStmt {
+            table,
+            alias,
+            joins,
+            distinct,
+            columns,
+            where_clause,
+            group_by,
+            having,
+            order_by,
+            limit,
+            offset,
+        }))
+    }
+
+    fn parse_update<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        // Consume UPDATE
+        iter.next();
+
+        // Table name
+        let table = Self::parse_table_name(iter, "table name after UPDATE")?;
+
+        // Expect SET
+        match iter.next() {
+            Some(Token::Keyword { value, .. }) if value == "SET" => {}
+            other => return Err(Self::expected("SET after table name", other)),
+        }
+
+        let assignments = Self::parse_assignments(iter, true)?;
+        let where_clause = Self::parse_optional_where(iter)?;
+        Self::skip_semicolon(iter);
+
+        Ok(ASTNode::Update(UpdateStmt { table, assignments, where_clause }))
+    }
+
+    /// Parses the assignments of a SET clause: `col = value [, col = value]*`.
+    /// The values may reference columns when `allow_columns` is set.
+    fn parse_assignments<'a, I>(iter: &mut std::iter::Peekable<I>, allow_columns: bool) -> SqlResult<Vec<(ColumnName, Expr)>>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        let mut assignments = Vec::new();
+        loop {
+            let column = Self::parse_column_name(iter)?;
+            match iter.next() {
+                Some(Token::Operator { value, .. }) if value == "=" => {}
+                other => return Err(Self::expected("'=' in SET clause", other)),
+            }
+            let value = Self::parse_term(iter, allow_columns)?;
+            assignments.push((column, value));
+
+            match iter.peek() {
+                Some(Token::Symbol { value, .. }) if *value == ',' => {
+                    iter.next();
+                }
+                _ => break,
+            }
+        }
+        Ok(assignments)
+    }
+
+    fn parse_delete<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ASTNode>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        // Consume DELETE
+        iter.next();
+
+        // Expect FROM
+        match iter.next() {
+            Some(Token::Keyword { value, .. }) if value == "FROM" => {}
+            other => return Err(Self::expected("FROM after DELETE", other)),
+        }
+
+        // Table name
+        let table = Self::parse_table_name(iter, "table name after FROM")?;
+
+        let where_clause = Self::parse_optional_where(iter)?;
+        Self::skip_semicolon(iter);
+
+        Ok(ASTNode::Delete(DeleteStmt { table, where_clause }))
+    }
+
+    /// Parses a WHERE clause if the next token is the WHERE keyword.
+    fn parse_optional_where<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<Option<WhereClause>>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        match iter.peek() {
+            Some(Token::Keyword { value, .. }) if value == "WHERE" => {
+                iter.next();
+                Ok(Some(Self::parse_where(iter)?))
+            }
+            _ => Ok(None),
+        }
+    }
+
+    /// Parses the tables following the first one in FROM: any number of
+    /// `[INNER] JOIN table ON condition`, `CROSS JOIN table` or `, table`.
+    fn parse_joins<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<Vec<Join>>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        let mut joins = Vec::new();
+        loop {
+            let cross = match iter.peek() {
+                Some(Token::Symbol { value, .. }) if *value == ',' => {
+                    iter.next();
+                    true
+                }
+                Some(Token::Keyword { value, .. }) if value == "JOIN" => {
+                    iter.next();
+                    false
+                }
+                Some(Token::Keyword { value, .. }) if value == "INNER" || value == "CROSS" => {
+                    let cross = value == "CROSS";
+                    iter.next();
+                    match iter.next() {
+                        Some(Token::Keyword { value, .. }) if value == "JOIN" => {}
+                        other if cross => return Err(Self::expected("JOIN after CROSS", other)),
+                        other => return Err(Self::expected("JOIN after INNER", other)),
+                    }
+                    cross
+                }
+                _ => return Ok(joins),
+            };
+
+            let table = Self::parse_table_name(iter, "table name")?;
+            let alias = Self::parse_table_alias(iter)?;
+            let on = if cross {
+                None
+            } else {
+                match iter.next() {
+                    Some(Token::Keyword { value, .. }) if value == "ON" => {}
+                    other => return Err(Self::expected("ON after joined table", other)),
+                }
+                Some(Self::parse_where(iter)?)
+            };
+            joins.push(Join { table, alias, on });
+        }
+    }
+
+    /// Parses a table name: `table`, or `schema.table` such as
+    /// `information_schema.columns`. `what` describes the expected name in
+    /// errors.
+    fn parse_table_name<'a, I>(iter: &mut std::iter::Peekable<I>, what: &str) -> SqlResult<TableName>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        let name = match iter.next() {
+            Some(Token::Identifier { value, .. }) => value,
+            other => return Err(Self::expected(what, other)),
+        };
+        if !matches!(iter.peek(), Some(Token::Symbol { value: '.', .. })) {
+            return TableName::new(name).map_err(|e| SqlError::new_parse(&e));
+        }
+        iter.next();
+        match iter.next() {
+            Some(Token::Identifier { value, .. }) => {
+                TableName::qualified(name, value).map_err(|e| SqlError::new_parse(&e))
+            }
+            other => Err(Self::expected("table name after '.'", other)),
+        }
+    }
+
+    /// Parses an optional alias: `AS name`, or just `name`.
+    fn parse_optional_alias<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<Option<&'a String>>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        match iter.peek() {
+            Some(Token::Keyword { value, .. }) if value == "AS" => {
+                iter.next();
+                match iter.next() {
+                    Some(Token::Identifier { value, .. }) => Ok(Some(value)),
+                    other => Err(Self::expected("alias after AS", other)),
+                }
+            }
+            Some(Token::Identifier { .. }) => match iter.next() {
+                Some(Token::Identifier { value, .. }) => Ok(Some(value)),
+                _ => unreachable!("peeked an identifier"),
+            },
+            _ => Ok(None),
+        }
+    }
+
+    /// Parses an optional table alias after a table name in FROM or JOIN.
+    fn parse_table_alias<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<Option<TableName>>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        Self::parse_optional_alias(iter)?
+            .map(|name| TableName::new(name).map_err(|e| SqlError::new_parse(&e)))
+            .transpose()
+    }
+
+    /// Parses `ORDER BY col [ASC|DESC] [, ...]` if present.
+    fn parse_optional_order_by<'a, I>(
+        iter: &mut std::iter::Peekable<I>,
+    ) -> SqlResult<Vec<(ColumnRef, SortDirection)>>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        match iter.peek() {
+            Some(Token::Keyword { value, .. }) if value == "ORDER" => {
+                iter.next();
+            }
+            _ => return Ok(Vec::new()),
+        }
+        match iter.next() {
+            Some(Token::Keyword { value, .. }) if value == "BY" => {}
+            other => return Err(Self::expected("BY after ORDER", other)),
+        }
+
+        let mut keys = Vec::new();
+        loop {
+            let column = Self::parse_column_ref(iter)?;
+            let direction = match iter.peek() {
+                Some(Token::Keyword { value, .. }) if value == "ASC" => {
+                    iter.next();
+                    SortDirection::Asc
+                }
+                Some(Token::Keyword { value, .. }) if value == "DESC" => {
+                    iter.next();
+                    SortDirection::Desc
+                }
+                _ => SortDirection::Asc,
+            };
+            keys.push((column, direction));
+
+            match iter.peek() {
+                Some(Token::Symbol { value, .. }) if *value == ',' => {
+                    iter.next();
+                }
+                _ => break,
+            }
+        }
+        Ok(keys)
+    }
+
+    /// Parses `<keyword> n` (e.g. `LIMIT 10`) if present, where `n` is a
+    /// non-negative integer.
+    fn parse_optional_count<'a, I>(iter: &mut std::iter::Peekable<I>, keyword: &str) -> SqlResult<Option<usize>>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        match iter.peek() {
+            Some(Token::Keyword { value, .. }) if value == keyword => {
+                iter.next();
+            }
+            _ => return Ok(None),
+        }
+        match iter.next() {
+            Some(Token::Number { value, .. }) => usize::try_from(*value)
+                .map(Some)
+                .map_err(|_| SqlError::new_parse(&format!("{} must be a non-negative integer", keyword))),
+            other => Err(Self::expected(&format!("row count after {}", keyword), other)),
+        }
+    }
+
+    /// Consumes an optional trailing ';'.
+    fn skip_semicolon<'a, I>(iter: &mut std::iter::Peekable<I>)
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        if let Some(Token::Symbol { value, .. }) = iter.peek() {
+            if *value == ';' {
+                iter.next();
+            }
+        }
+    }
+
+    /// Parses the condition after the WHERE or ON keyword: predicates
+    /// combined with `OR`, `AND` and `NOT` (in increasing order of
+    /// precedence) and grouped with parentheses.
+    fn parse_where<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<WhereClause>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        let mut clause = Self::parse_and(iter)?;
+        while let Some(Token::Keyword { value, .. }) = iter.peek() {
+            if value != "OR" {
+                break;
+            }
+            iter.next();
+            clause = WhereClause::Or(Box::new(clause), Box::new(Self::parse_and(iter)?));
+        }
+        Ok(clause)
+    }
+
+    /// Parses predicates joined by `AND`.
+    fn parse_and<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<WhereClause>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        let mut clause = Self::parse_not(iter)?;
+        while let Some(Token::Keyword { value, .. }) = iter.peek() {
+            if value != "AND" {
+                break;
+            }
+            iter.next();
+            clause = WhereClause::And(Box::new(clause), Box::new(Self::parse_not(iter)?));
+        }
+        Ok(clause)
+    }
+
+    /// Parses `NOT <condition>`, a parenthesized condition or a predicate.
+    fn parse_not<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<WhereClause>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        match iter.peek() {
+            Some(Token::Keyword { value, .. }) if value == "NOT" => {
+                iter.next();
+                Ok(WhereClause::Not(Box::new(Self::parse_not(iter)?)))
+            }
+            Some(Token::Symbol { value: '(', .. }) => {
+                iter.next();
+                let clause = Self::parse_where(iter)?;
+                match iter.next() {
+                    Some(Token::Symbol { value, .. }) if *value == ')' => Ok(clause),
+                    other => Err(Self::expected("')' after condition", other)),
+                }
+            }
+            _ => Self::parse_predicate(iter),
+        }
+    }
+
+    /// Parses `column <op> value`, `column IS [NOT] NULL`,
+    /// `column [NOT] LIKE pattern`, `column [NOT] IN (value, ...)` or
+    /// `column [NOT] BETWEEN low AND high`. Values may also reference
+    /// columns.
+    fn parse_predicate<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<WhereClause>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        let column = Self::parse_column_ref(iter)?;
+
+        if let Some(Token::Keyword { value, .. }) = iter.peek() {
+            if value == "IS" {
+                iter.next();
+                let negated = match iter.peek() {
+                    Some(Token::Keyword { value, .. }) if value == "NOT" => {
+                        iter.next();
+                        true
+                    }
+                    _ => false,
+                };
+                match iter.next() {
+                    Some(Token::Keyword { value, .. }) if value == "NULL" => {}
+                    other => return Err(Self::expected("NULL after IS", other)),
+                }
+                return Ok(WhereClause::IsNull { column, negated });
+            }
+            if matches!(value.as_str(), "NOT" | "LIKE" | "IN" | "BETWEEN") {
+                let negated = value == "NOT";
+                if negated {
+                    iter.next();
+                }
+                return match iter.next() {
+                    Some(Token::Keyword { value, .. }) if value == "LIKE" => {
+                        let pattern = Self::parse_term(iter, true)?;
+                        Ok(WhereClause::Like { column, pattern, negated })
+                    }
+                    Some(Token::Keyword { value, .. }) if value == "IN" => {
+                        let values = Self::parse_in_list(iter)?;
+                        Ok(WhereClause::In { column, values, negated })
+                    }
+                    Some(Token::Keyword { value, .. }) if value == "BETWEEN" => {
+                        let low = Self::parse_term(iter, true)?;
+                        match iter.next() {
+                            Some(Token::Keyword { value, .. }) if value == "AND" => {}
+                            other => return Err(Self::expected("AND in BETWEEN", other)),
+                        }
+                        let high = Self::parse_term(iter, true)?;
+                        Ok(WhereClause::Between { column, low, high, negated })
+                    }
+                    other => Err(Self::expected("LIKE, IN or BETWEEN after NOT", other)),
+                };
+            }
+        }
+
+        let op = Self::parse_compare_op(iter)?;
+        let value = Self::parse_term(iter, true)?;
+        Ok(WhereClause::Compare { column, op, value })
+    }
+
+    /// Parses the parenthesized, non-empty value list of an IN predicate.
+    fn parse_in_list<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<Vec<Expr>>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        match iter.next() {
+            Some(Token::Symbol { value, .. }) if *value == '(' => {}
+            other => return Err(Self::expected("'(' after IN", other)),
+        }
+        let mut values = Vec::new();
+        loop {
+            values.push(Self::parse_term(iter, true)?);
+            match iter.next() {
+                Some(Token::Symbol { value, .. }) if *value == ',' => continue,
+                Some(Token::Symbol { value, .. }) if *value == ')' => return Ok(values),
+                other => return Err(Self::expected("',' or ')' in IN list", other)),
+            }
+        }
+    }
+
+    /// Parses a comparison operator token.
+    fn parse_compare_op<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<CompareOp>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        match iter.next() {
+            Some(Token::Operator { value, .. }) => match value.as_str() {
+                "=" => Ok(CompareOp::Eq),
+                "!=" | "<>" => Ok(CompareOp::NotEq),
+                "<" => Ok(CompareOp::Lt),
+                ">" => Ok(CompareOp::Gt),
+                "<=" => Ok(CompareOp::LtEq),
+                ">=" => Ok(CompareOp::GtEq),
+                _ => Err(SqlError::new_parse(&format!("Unsupported operator '{}'", value))),
+            },
+            other => Err(Self::expected("comparison operator", other)),
+        }
+    }
+
+    /// Parses one SELECT list item: an aggregate call such as `COUNT(*)` or
+    /// `SUM(amount)`, a (possibly qualified) column, or any other expression.
+    fn parse_select_item<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<SelectItem>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        let func = match iter.peek() {
+            Some(Token::Identifier { value, .. }) => match value.to_uppercase().as_str() {
+                "COUNT" => Some(AggregateFunc::Count),
+                "SUM" => Some(AggregateFunc::Sum),
+                "MIN" => Some(AggregateFunc::Min),
+                "MAX" => Some(AggregateFunc::Max),
+                "AVG" => Some(AggregateFunc::Avg),
+                _ => None,
+            },
+            _ => None,
+        };
+        let expr = match func {
+            Some(func) => {
+                let name = match iter.next() {
+                    Some(Token::Identifier { value, .. }) => value,
+                    _ => unreachable!("peeked an identifier"),
+                };
+                if matches!(iter.peek(), Some(Token::Symbol { value: '(', .. })) {
+                    return Self::finish_aggregate(iter, func);
+                }
+                // Just a column that happens to be named like an aggregate
+                let column = Self::finish_column_ref(iter, name).map(Expr::Column)?;
+                Self::parse_infix(iter, column, 0, true)?
+            }
+            None => Self::parse_term(iter, true)?,
+        };
+
+        match expr {
+            Expr::Column(column) => Ok(SelectItem::Column(column)),
+            expr => Ok(SelectItem::Expr(expr)),
+        }
+    }
+
+    /// Parses the parenthesized argument of an aggregate call whose name
+    /// has already been consumed.
+    fn finish_aggregate<'a, I>(iter: &mut std::iter::Peekable<I>, func: AggregateFunc) -> SqlResult<SelectItem>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        iter.next(); // '('
+
+        let column = match iter.peek() {
+            Some(Token::Symbol { value: '*', .. }) if func == AggregateFunc::Count => {
+                iter.next();
+                None
+            }
+            _ => Some(Self::parse_column_ref(iter)?),
+        };
+        match iter.next() {
+            Some(Token::Symbol { value, .. }) if *value == ')' => {}
+            other => return Err(Self::expected("')' after aggregate argument", other)),
+        }
+
+        Ok(SelectItem::Aggregate { func, column })
+    }
+
+    /// Parses `GROUP BY col [, ...]` if present.
+    fn parse_optional_group_by<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<Vec<ColumnRef>>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        match iter.peek() {
+            Some(Token::Keyword { value, .. }) if value == "GROUP" => {
+                iter.next();
+            }
+            _ => return Ok(Vec::new()),
+        }
+        match iter.next() {
+            Some(Token::Keyword { value, .. }) if value == "BY" => {}
+            other => return Err(Self::expected("BY after GROUP", other)),
+        }
+
+        let mut columns = Vec::new();
+        loop {
+            columns.push(Self::parse_column_ref(iter)?);
+            match iter.peek() {
+                Some(Token::Symbol { value, .. }) if *value == ',' => {
+                    iter.next();
+                }
+                _ => break,
+            }
+        }
+        Ok(columns)
+    }
+
+    /// Parses `HAVING <item> <op> <value>` if present.
+    fn parse_optional_having<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<Option<HavingClause>>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        match iter.peek() {
+            Some(Token::Keyword { value, .. }) if value == "HAVING" => {
+                iter.next();
+            }
+            _ => return Ok(None),
+        }
+        let item = Self::parse_select_item(iter)?;
+        let op = Self::parse_compare_op(iter)?;
+        let value = Self::parse_expr(iter)?;
+        Ok(Some(HavingClause { item, op, value }))
+    }
+
+    /// Parses a column name identifier.
+    fn parse_column_name<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ColumnName>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        match iter.next() {
+            Some(Token::Identifier { value, .. }) => ColumnName::new(value)
+                .map_err(|e| SqlError::new_parse(&e)),
+            other => Err(Self::expected("column name", other)),
+        }
+    }
+
+    /// Parses a column reference: `column` or `table.column`.
+    fn parse_column_ref<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<ColumnRef>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        match iter.next() {
+            Some(Token::Identifier { value, .. }) => Self::finish_column_ref(iter, value),
+            other => Err(Self::expected("column name", other)),
+        }
+    }
+
+    /// Completes a column reference whose first identifier, `name`, has
+    /// already been consumed; a following `.column` makes it qualified.
+    fn finish_column_ref<'a, I>(iter: &mut std::iter::Peekable<I>, name: &str) -> SqlResult<ColumnRef>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        if !matches!(iter.peek(), Some(Token::Symbol { value: '.', .. })) {
+            return ColumnName::new(name)
+                .map(ColumnRef::from)
+                .map_err(|e| SqlError::new_parse(&e));
+        }
+        iter.next();
+        let table = TableName::new(name).map_err(|e| SqlError::new_parse(&e))?;
+        let column = Self::parse_column_name(iter)?;
+        Ok(ColumnRef { table: Some(table), column })
+    }
+
+    /// Parses a scalar expression that does not reference columns: literals,
+    /// typed literals such as `DATE '2024-01-31'` or
+    /// `TIMESTAMP '2024-01-31 12:00:00'`, function calls such as `NOW()`,
+    /// and arithmetic over them.
+    fn parse_expr<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<Expr>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        Self::parse_term(iter, false)
+    }
+
+    /// Parses an expression as [`Self::parse_expr`] does, additionally
+    /// accepting column references when `allow_columns` is set (the SELECT
+    /// list, the right-hand side of a WHERE or ON comparison, and SET
+    /// assignments).
+    fn parse_term<'a, I>(iter: &mut std::iter::Peekable<I>, allow_columns: bool) -> SqlResult<Expr>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        let left = Self::parse_unary(iter, allow_columns)?;
+        Self::parse_infix(iter, left, 0, allow_columns)
+    }
+
+    /// Extends `left` with binary operators of at least `min_precedence`
+    /// (precedence climbing); operators of equal precedence associate to
+    /// the left.
+    fn parse_infix<'a, I>(
+        iter: &mut std::iter::Peekable<I>,
+        mut left: Expr,
+        min_precedence: u8,
+        allow_columns: bool,
+    ) -> SqlResult<Expr>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        while let Some((op, precedence)) = iter.peek().and_then(|t| Self::binary_op(t)) {
+            if precedence < min_precedence {
+                break;
+            }
+            iter.next();
+            let right = Self::parse_unary(iter, allow_columns)?;
+            let right = Self::parse_infix(iter, right, precedence + 1, allow_columns)?;
+            left = Expr::Binary { op, left: Box::new(left), right: Box::new(right) };
+        }
+        Ok(left)
+    }
+
+    /// Maps an operator token to a binary operator and its precedence:
+    /// `||` binds loosest, then `+ -`, then `* / %`.
+    fn binary_op(token: &Token) -> Option<(BinaryOp, u8)> {
+        match token {
+            Token::Symbol { value: '*', .. } => Some((BinaryOp::Mul, 3)),
+            Token::Operator { value, .. } => match value.as_str() {
+                "||" => Some((BinaryOp::Concat, 1)),
+                "+" => Some((BinaryOp::Add, 2)),
+                "-" => Some((BinaryOp::Sub, 2)),
+                "/" => Some((BinaryOp::Div, 3)),
+                "%" => Some((BinaryOp::Mod, 3)),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+
+    /// Parses a unary minus, a parenthesized expression or a primary.
+    /// A minus in front of a numeric literal is folded into the literal.
+    fn parse_unary<'a, I>(iter: &mut std::iter::Peekable<I>, allow_columns: bool) -> SqlResult<Expr>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        match iter.peek() {
+            Some(Token::Operator { value, .. }) if value == "-" => {
+                iter.next();
+                Ok(match Self::parse_unary(iter, allow_columns)? {
+                    Expr::Literal(Value::Int(n)) => Expr::Literal(Value::Int(-n)),
+                    Expr::Literal(Value::Float(f)) => Expr::Literal(Value::Float(-f)),
+                    expr => Expr::Negate(Box::new(expr)),
+                })
+            }
+            Some(Token::Symbol { value: '(', .. }) => {
+                iter.next();
+                let expr = Self::parse_term(iter, allow_columns)?;
+                match iter.next() {
+                    Some(Token::Symbol { value, .. }) if *value == ')' => Ok(expr),
+                    other => Err(Self::expected("')' after expression", other)),
+                }
+            }
+            _ => Self::parse_primary(iter, allow_columns),
+        }
+    }
+
+    /// Parses a literal, typed literal, function call or, when
+    /// `allow_columns` is set, a column reference.
+    fn parse_primary<'a, I>(iter: &mut std::iter::Peekable<I>, allow_columns: bool) -> SqlResult<Expr>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        let name = match iter.peek() {
+            Some(Token::Identifier { value, .. }) => value.to_uppercase(),
+            _ => return Self::parse_value(iter).map(Expr::Literal),
+        };
+        let name_token = iter.next();
+
+        match iter.peek() {
+            Some(Token::Symbol { value, .. }) if *value == '(' => {
+                iter.next();
+                let mut args = Vec::new();
+                if let Some(Token::Symbol { value, .. }) = iter.peek() {
+                    if *value == ')' {
+                        iter.next();
+                        return Ok(Expr::Function { name, args });
+                    }
+                }
+                loop {
+                    args.push(Self::parse_expr(iter)?);
+                    match iter.next() {
+                        Some(Token::Symbol { value, .. }) if *value == ',' => continue,
+                        Some(Token::Symbol { value, .. }) if *value == ')' => break,
+                        other => return Err(Self::expected("',' or ')' in function call", other)),
+                    }
+                }
+                Ok(Expr::Function { name, args })
+            }
+            Some(Token::String { value: text, .. }) if name == "DATE" || name == "TIMESTAMP" => {
+                let value = if name == "DATE" {
+                    datetime::parse_date(text).map(Value::Date)
+                } else {
+                    datetime::parse_timestamp(text).map(Value::Timestamp)
+                };
+                iter.next();
+                value.map(Expr::Literal).map_err(|e| SqlError::new_parse(&e))
+            }
+            _ if allow_columns => match name_token {
+                Some(Token::Identifier { value, .. }) => Self::finish_column_ref(iter, value).map(Expr::Column),
+                other => Err(Self::expected("column name", other)),
+            },
+            _ => Err(Self::expected("literal value", name_token)),
+        }
+    }
+
+    /// Parses a literal value (integer, float, string, hex blob, boolean or NULL).
+    fn parse_value<'a, I>(iter: &mut std::iter::Peekable<I>) -> SqlResult<Value>
+    where
+        I: Iterator<Item = &'a Token>,
+    {
+        match iter.next() {
+            Some(Token::Number { value, .. }) => Ok(Value::Int(*value)),
+            Some(Token::Float { value, .. }) => Ok(Value::Float(*value)),
+            Some(Token::String { value, .. }) => Ok(Value::Text(value.clone())),
+            Some(Token::Blob { value, .. }) => Ok(Value::Blob(value.clone())),
+            Some(Token::Keyword { value, .. }) if value == "NULL" => Ok(Value::Null),
+            Some(Token::Keyword { value, .. }) if value == "TRUE" => Ok(Value::Bool(true)),
+            Some(Token::Keyword { value, .. }) if value == "FALSE" => Ok(Value::Bool(false)),
+            other => Err(Self::expected("literal value", other)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::span::Span;
+    use crate::frontend::lexer::lexer;
+    use crate::frontend::token::Token;
+
+    #[test]
+    fn parse_create_table_example() {
+        let sql_tokens = vec![
+            Token::Keyword { value: "CREATE".to_string(), span: Some(Span::new(0, 6)) },
+            Token::Keyword { value: "TABLE".to_string(), span: Some(Span::new(7, 12)) },
+            Token::Identifier { value: "users".to_string(), span: Some(Span::new(13, 18)) },
+            Token::Symbol { value: '(', span: Some(Span::new(19, 20)) },
+            Token::Identifier { value: "id".to_string(), span: Some(Span::new(20, 22)) },
+            Token::Identifier { value: "Int".to_string(), span: Some(Span::new(23, 26)) },
+            Token::Symbol { value: ',', span: Some(Span::new(26, 27)) },
+            Token::Identifier { value: "name".to_string(), span: Some(Span::new(28, 32)) },
+            Token::Identifier { value: "Text".to_string(), span: Some(Span::new(33, 37)) },
+            Token::Symbol { value: ')', span: Some(Span::new(37, 38)) },
+            Token::Symbol { value: ';', span: Some(Span::new(38, 39)) },
+            Token::Eof { span: Some(Span::at(39)) },
+        ];
+
+        let ast = Parser::default().parse(&sql_tokens).unwrap();
+        match ast {
+            ASTNode::CreateTable(stmt) => {
+                assert_eq!(stmt.name.as_str(), "users");
+                assert_eq!(stmt.columns.len(), 2);
+                assert_eq!(stmt.columns[0].name.as_str(), "id");
+                assert_eq!(stmt.columns[1].name.as_str(), "name");
+            }
+            _ => panic!("Expected CreateTable ASTNode"),
+        }
+    }
+
+    #[test]
+    fn parse_create_table_with_primary_key() {
+        match Parser::default().parse(&lexer("CREATE TABLE t (id INT PRIMARY KEY, name TEXT)").unwrap()).unwrap() {
+            ASTNode::CreateTable(stmt) => {
+                assert_eq!(stmt.primary_key, Some(ColumnName::new("id").unwrap()));
+                assert_eq!(stmt.columns.len(), 2);
+            }
+            _ => panic!("Expected CreateTable ASTNode"),
+        }
+        assert!(Parser::default().parse(&lexer("CREATE TABLE t (a INT PRIMARY KEY, b INT PRIMARY KEY)").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("CREATE TABLE t (a INT PRIMARY)").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_column_constraints() {
+        match Parser::default().parse(&lexer("CREATE TABLE t (a INT NOT NULL UNIQUE, b TEXT NULL, c TEXT)").unwrap()).unwrap() {
+            ASTNode::CreateTable(stmt) => {
+                assert!(!stmt.columns[0].nullable && stmt.columns[0].unique);
+                assert!(stmt.columns[1].nullable && !stmt.columns[1].unique);
+                assert!(stmt.columns[2].nullable && !stmt.columns[2].unique);
+            }
+            _ => panic!("Expected CreateTable ASTNode"),
+        }
+        assert!(Parser::default().parse(&lexer("CREATE TABLE t (a INT NOT UNIQUE)").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_transaction_statements() {
+        let parse = |sql: &str| Parser::default().parse(&lexer(sql).unwrap());
+
+        assert_eq!(parse("BEGIN").unwrap(), ASTNode::Transaction(TransactionStmt::Begin));
+        assert_eq!(parse("begin transaction;").unwrap(), ASTNode::Transaction(TransactionStmt::Begin));
+        assert_eq!(parse("COMMIT;").unwrap(), ASTNode::Transaction(TransactionStmt::Commit));
+        assert_eq!(parse("ROLLBACK").unwrap(), ASTNode::Transaction(TransactionStmt::Rollback));
+        assert!(parse("COMMIT TRANSACTION").is_err());
+
+        let savepoint = |stmt| Ok(ASTNode::Transaction(stmt));
+        assert_eq!(parse("SAVEPOINT before_update;"), savepoint(TransactionStmt::Savepoint("before_update".to_string())));
+        assert_eq!(parse("ROLLBACK TO sp"), savepoint(TransactionStmt::RollbackTo("sp".to_string())));
+        assert_eq!(parse("rollback to savepoint sp"), savepoint(TransactionStmt::RollbackTo("sp".to_string())));
+        assert_eq!(parse("RELEASE SAVEPOINT sp"), savepoint(TransactionStmt::Release("sp".to_string())));
+        assert_eq!(parse("RELEASE sp"), savepoint(TransactionStmt::Release("sp".to_string())));
+        assert!(parse("SAVEPOINT").is_err());
+        assert!(parse("ROLLBACK TO").is_err());
+    }
+
+    #[test]
+    fn parse_create_index() {
+        let parse = |sql: &str| Parser::default().parse(&lexer(sql).unwrap());
+
+        assert_eq!(
+            parse("CREATE INDEX idx_name ON users (name);").unwrap(),
+            ASTNode::CreateIndex(CreateIndexStmt {
+                name: "idx_name".to_string(),
+                table: TableName::new("users").unwrap(),
+                column: ColumnName::new("name").unwrap(),
+                kind: IndexKind::BTree,
+            })
+        );
+        match parse("CREATE INDEX idx_id ON users (id) USING hash").unwrap() {
+            ASTNode::CreateIndex(stmt) => assert_eq!(stmt.kind, IndexKind::Hash),
+            other => panic!("expected CREATE INDEX, got {:?}", other),
+        }
+        assert!(parse("CREATE INDEX idx_id ON users (id) USING gist").is_err());
+        assert!(parse("CREATE INDEX ON users (name)").is_err());
+        assert!(parse("CREATE INDEX i ON users (a, b)").is_err());
+    }
+
+    #[test]
+    fn parse_explain() {
+        let parse = |sql: &str| Parser::default().parse(&lexer(sql).unwrap());
+
+        match parse("EXPLAIN SELECT * FROM users WHERE id = 1;").unwrap() {
+            ASTNode::Explain(stmt) => {
+                assert_eq!(stmt.table, TableName::new("users").unwrap());
+                assert!(stmt.where_clause.is_some());
+            }
+            other => panic!("expected EXPLAIN, got {:?}", other),
+        }
+        assert!(parse("EXPLAIN DELETE FROM users").is_err());
+    }
+
+    #[test]
+    fn parse_script_splits_on_semicolons() {
+        let parse = |sql: &str| Parser::default().parse_script(&lexer(sql).unwrap());
+
+        let statements = parse("BEGIN; INSERT INTO t VALUES ('a;b');; COMMIT;").unwrap();
+        assert_eq!(statements.len(), 3);
+        assert_eq!(statements[0], ASTNode::Transaction(TransactionStmt::Begin));
+        assert!(matches!(statements[1], ASTNode::Insert(_)));
+        assert_eq!(statements[2], ASTNode::Transaction(TransactionStmt::Commit));
+
+        assert_eq!(parse("ANALYZE t").unwrap(), vec![ASTNode::Analyze(TableName::new("t").unwrap())]);
+        assert!(parse("  ; ").unwrap().is_empty());
+        // The statement before the ';' must be complete
+        let err = parse("SELECT * FROM; COMMIT").unwrap_err();
+        assert!(err.message().contains("end of input at position 13"), "{}", err.message());
+
+        // Each statement keeps where it was written
+        let sql = "BEGIN;\n  INSERT INTO t VALUES ('é;') ;COMMIT";
+        let spans: Vec<Option<Span>> =
+            Parser::default().parse_script_spans(&lexer(sql).unwrap()).unwrap().into_iter().map(|(_, span)| span).collect();
+        assert_eq!(spans, vec![Some(Span::new(0, 5)), Some(Span::new(9, 36)), Some(Span::new(38, 44))]);
+    }
+
+    #[test]
+    fn parse_analyze() {
+        let parse = |sql: &str| Parser::default().parse(&lexer(sql).unwrap());
+
+        assert_eq!(parse("ANALYZE users;").unwrap(), ASTNode::Analyze(TableName::new("users").unwrap()));
+        assert!(parse("ANALYZE").is_err());
+        assert!(parse("ANALYZE users orders").is_err());
+    }
+
+    #[test]
+    fn parse_show_tables_and_describe() {
+        let parse = |sql: &str| Parser::default().parse(&lexer(sql).unwrap());
+        assert_eq!(parse("SHOW TABLES;").unwrap(), ASTNode::ShowTables);
+        assert_eq!(parse("show tables").unwrap(), ASTNode::ShowTables);
+        assert_eq!(parse("DESCRIBE users").unwrap(), ASTNode::Describe(TableName::new("users").unwrap()));
+        assert_eq!(
+            parse("desc information_schema.tables").unwrap(),
+            ASTNode::Describe(TableName::qualified("information_schema", "tables").unwrap())
+        );
+        assert!(parse("SHOW users").is_err());
+        assert!(parse("DESCRIBE").is_err());
+    }
+
+    #[test]
+    fn parse_checkpoint() {
+        let parse = |sql: &str| Parser::default().parse(&lexer(sql).unwrap());
+        assert_eq!(parse("CHECKPOINT;").unwrap(), ASTNode::Checkpoint);
+        assert_eq!(parse("checkpoint").unwrap(), ASTNode::Checkpoint);
+        assert!(parse("CHECKPOINT users").is_err());
+    }
+
+    #[test]
+    fn parse_pragma() {
+        let parse = |sql: &str| Parser::default().parse(&lexer(sql).unwrap());
+        let pragma = |name: &str, value: Option<&str>| {
+            ASTNode::Pragma(PragmaStmt { name: name.to_string(), value: value.map(str::to_string) })
+        };
+        assert_eq!(parse("PRAGMA synchronous;").unwrap(), pragma("synchronous", None));
+        assert_eq!(parse("pragma Synchronous = off").unwrap(), pragma("synchronous", Some("off")));
+        assert_eq!(parse("PRAGMA synchronous = 'FULL'").unwrap(), pragma("synchronous", Some("FULL")));
+        assert_eq!(parse("PRAGMA cache_size = 10").unwrap(), pragma("cache_size", Some("10")));
+        assert!(parse("PRAGMA").is_err());
+        assert!(parse("PRAGMA synchronous =").is_err());
+        assert!(parse("PRAGMA synchronous off").is_err());
+    }
+
+    #[test]
+    fn parse_schemas_and_qualified_names() {
+        let parse = |sql: &str| Parser::default().parse(&lexer(sql).unwrap());
+        assert_eq!(parse("CREATE SCHEMA sales;").unwrap(), ASTNode::CreateSchema("sales".to_string()));
+        assert!(parse("CREATE SCHEMA").is_err());
+
+        let orders = TableName::qualified("sales", "orders").unwrap();
+        match parse("CREATE TABLE sales.orders (id Int)").unwrap() {
+            ASTNode::CreateTable(stmt) => assert_eq!(stmt.name, orders),
+            other => panic!("Expected CREATE TABLE, got {:?}", other),
+        }
+        match parse("INSERT INTO sales.orders VALUES (1)").unwrap() {
+            ASTNode::Insert(stmt) => assert_eq!(stmt.table, orders),
+            other => panic!("Expected INSERT, got {:?}", other),
+        }
+        match parse("DELETE FROM sales.orders WHERE id = 1").unwrap() {
+            ASTNode::Delete(stmt) => assert_eq!(stmt.table, orders),
+            other => panic!("Expected DELETE, got {:?}", other),
+        }
+        assert!(parse("UPDATE sales. SET id = 2").is_err());
+    }
+
+    #[test]
+    fn parse_create_view() {
+        let parse = |sql: &str| Parser::default().parse(&lexer(sql).unwrap());
+        match parse("CREATE VIEW sales.big AS SELECT id, 'x' FROM orders WHERE amount >= 1.0 AND id < 10;").unwrap() {
+            ASTNode::CreateView(stmt) => {
+                assert_eq!(stmt.name, TableName::qualified("sales", "big").unwrap());
+                assert_eq!(stmt.query.table, TableName::new("orders").unwrap());
+                assert_eq!(stmt.sql, "SELECT id , 'x' FROM orders WHERE amount >= 1.0 AND id < 10");
+                // The kept text parses back to the same query
+                assert_eq!(parse(&stmt.sql).unwrap(), ASTNode::Select(stmt.query));
+            }
+            other => panic!("Expected CREATE VIEW, got {:?}", other),
+        }
+        assert!(parse("CREATE VIEW v SELECT * FROM t").is_err());
+        assert!(parse("CREATE VIEW v AS DELETE FROM t").is_err());
+        assert!(parse("CREATE VIEW v AS SELECT FROM").is_err());
+    }
+
+    #[test]
+    fn parse_truncate() {
+        let parse = |sql: &str| Parser::default().parse(&lexer(sql).unwrap());
+        let users = ASTNode::Truncate(TableName::new("users").unwrap());
+        assert_eq!(parse("TRUNCATE users;").unwrap(), users);
+        assert_eq!(parse("truncate table users").unwrap(), users);
+        assert_eq!(parse("TRUNCATE sales.orders").unwrap(), ASTNode::Truncate(TableName::qualified("sales", "orders").unwrap()));
+        assert!(parse("TRUNCATE").is_err());
+        assert!(parse("TRUNCATE users WHERE id = 1").is_err());
+    }
+
+    #[test]
+    fn parse_attach_and_detach() {
+        let parse = |sql: &str| Parser::default().parse(&lexer(sql).unwrap());
+        let attach = ASTNode::Attach { path: "old/shop".to_string(), alias: "archive".to_string() };
+        assert_eq!(parse("ATTACH 'old/shop' AS archive;").unwrap(), attach);
+        assert_eq!(parse("attach database 'old/shop' as archive").unwrap(), attach);
+        assert_eq!(parse("DETACH archive").unwrap(), ASTNode::Detach("archive".to_string()));
+        assert_eq!(parse("DETACH DATABASE archive;").unwrap(), ASTNode::Detach("archive".to_string()));
+        assert_eq!(parse("DETACH database").unwrap(), ASTNode::Detach("database".to_string()));
+        assert!(parse("ATTACH old AS archive").is_err());
+        assert!(parse("ATTACH 'old/shop'").is_err());
+        assert!(parse("DETACH").is_err());
+    }
+
+    #[test]
+    fn parse_copy_from() {
+        let parse = |sql: &str| Parser::default().parse(&lexer(sql).unwrap());
+
+        assert_eq!(
+            parse("COPY users FROM 'data/users.csv';").unwrap(),
+            ASTNode::Copy(CopyStmt::From {
+                table: TableName::new("users").unwrap(),
+                path: "data/users.csv".to_string(),
+            })
+        );
+        assert!(parse("COPY users FROM data").is_err());
+
+        match parse("COPY (SELECT name FROM users WHERE id > 1) TO 'out.csv';").unwrap() {
+            ASTNode::Copy(CopyStmt::To { query, path }) => {
+                assert_eq!(query.table.as_str(), "users");
+                assert!(query.where_clause.is_some());
+                assert_eq!(path, "out.csv");
+            }
+            other => panic!("Expected COPY TO, got {:?}", other),
+        }
+        match parse("COPY users TO 'all.csv'").unwrap() {
+            ASTNode::Copy(CopyStmt::To { query, .. }) => assert!(query.columns.is_none()),
+            other => panic!("Expected COPY TO, got {:?}", other),
+        }
+        assert!(parse("COPY (SELECT * FROM users TO 'x.csv'").is_err());
+        assert!(parse("COPY FROM 'x.csv'").is_err());
+    }
+
+    #[test]
+    fn parse_create_table_with_type_aliases() {
+        let tokens = lexer("CREATE TABLE t (a INTEGER, b VARCHAR);").unwrap();
+        match Parser::default().parse(&tokens).unwrap() {
+            ASTNode::CreateTable(stmt) => {
+                assert_eq!(stmt.columns[0].dtype, DataType::Int);
+                assert_eq!(stmt.columns[1].dtype, DataType::Text);
+            }
+            _ => panic!("Expected CreateTable ASTNode"),
+        }
+    }
+
+    #[test]
+    fn parser_resolves_registered_aliases() {
+        let mut parser = Parser::default();
+        parser.register_alias("int8", DataType::Int).unwrap();
+        parser.register_alias("VARCHAR", DataType::Blob).unwrap();
+        assert!(parser.register_alias("INT", DataType::Text).is_err());
+        assert!(parser.register_alias("select", DataType::Text).is_err());
+        assert!(parser.register_alias("2x", DataType::Text).is_err());
+
+        let tokens = lexer("CREATE TABLE t (a INT8, b varchar); ALTER TABLE t ADD c INT8").unwrap();
+        let statements = parser.parse_script(&tokens).unwrap();
+        match &statements[0] {
+            ASTNode::CreateTable(stmt) => {
+                assert_eq!(stmt.columns[0].dtype, DataType::Int);
+                assert_eq!(stmt.columns[1].dtype, DataType::Blob);
+            }
+            _ => panic!("Expected CreateTable ASTNode"),
+        }
+        match &statements[1] {
+            ASTNode::AlterTable(stmt) => assert!(matches!(&stmt.action, AlterAction::AddColumn(c) if c.dtype == DataType::Int)),
+            _ => panic!("Expected AlterTable ASTNode"),
+        }
+
+        // The associated functions keep the default aliases
+        assert!(Parser::default().parse_script(&tokens).is_err());
+        let tokens = lexer("CREATE TABLE t (b VARCHAR)").unwrap();
+        assert!(matches!(Parser::default().parse(&tokens).unwrap(), ASTNode::CreateTable(stmt) if stmt.columns[0].dtype == DataType::Text));
+        let parser = Parser::with_aliases(parser.aliases().clone());
+        assert!(matches!(parser.parse(&tokens).unwrap(), ASTNode::CreateTable(stmt) if stmt.columns[0].dtype == DataType::Blob));
+    }
+
+    #[test]
+    fn parse_reports_end_of_input_position() {
+        let tokens = lexer("CREATE TABLE t (").unwrap();
+        let err = Parser::default().parse(&tokens).unwrap_err();
+        assert_eq!(err.message(), "Expected column name, found end of input at position 16");
+        assert_eq!(err.span(), Some(Span::at(16)));
+
+        let tokens = lexer("").unwrap();
+        assert_eq!(Parser::default().parse(&tokens).unwrap_err().message(), "Empty token stream");
+
+        // Tokens must end with the sentinel
+        let tokens = lexer("SELECT * FROM t").unwrap();
+        let err = Parser::default().parse(&tokens[..tokens.len() - 1]).unwrap_err();
+        assert!(matches!(err, SqlError::Internal { .. }), "{:?}", err);
+        assert!(matches!(Parser::default().parse(&[]), Err(SqlError::Internal { .. })));
+        assert!(matches!(Parser::default().parse_script(&tokens[..tokens.len() - 1]), Err(SqlError::Internal { .. })));
+    }
+
+    #[test]
+    fn parse_rejects_trailing_tokens() {
+        let tokens = lexer("CREATE TABLE t (a Int); extra").unwrap();
+        let err = Parser::default().parse(&tokens).unwrap_err();
+        assert_eq!(err.message(), "Expected end of input, found 'extra' at position 24");
+        assert_eq!(err.span(), Some(Span::new(24, 29)));
+    }
+
+    #[test]
+    fn parse_select_with_where() {
+        let tokens = lexer("SELECT id, name FROM users WHERE id > 1;").unwrap();
+        match Parser::default().parse(&tokens).unwrap() {
+            ASTNode::Select(stmt) => {
+                assert_eq!(stmt.table.as_str(), "users");
+                assert_eq!(stmt.columns.unwrap().len(), 2);
+                assert_eq!(stmt.where_clause, Some(WhereClause::Compare {
+                    column: ColumnName::new("id").unwrap().into(),
+                    op: CompareOp::Gt,
+                    value: Value::Int(1).into(),
+                }));
+            }
+            _ => panic!("Expected Select ASTNode"),
+        }
+    }
+
+    #[test]
+    fn parse_where_operators() {
+        for (sql_op, op) in [
+            ("=", CompareOp::Eq),
+            ("!=", CompareOp::NotEq),
+            ("<>", CompareOp::NotEq),
+            ("<", CompareOp::Lt),
+            (">", CompareOp::Gt),
+            ("<=", CompareOp::LtEq),
+            (">=", CompareOp::GtEq),
+        ] {
+            let sql = format!("SELECT * FROM t WHERE name {} 'Bob'", sql_op);
+            match Parser::default().parse(&lexer(&sql).unwrap()).unwrap() {
+                ASTNode::Select(stmt) => {
+                    assert_eq!(stmt.where_clause, Some(WhereClause::Compare {
+                        column: ColumnName::new("name").unwrap().into(),
+                        op,
+                        value: Value::Text("Bob".to_string()).into(),
+                    }));
+                }
+                _ => panic!("Expected Select ASTNode"),
+            }
+        }
+    }
+
+    #[test]
+    fn parse_where_missing_value_fails() {
+        let tokens = lexer("SELECT * FROM t WHERE a =").unwrap();
+        let err = Parser::default().parse(&tokens).unwrap_err();
+        assert_eq!(err.message(), "Expected literal value, found end of input at position 25");
+    }
+
+    #[test]
+    fn parse_update_with_where() {
+        let tokens = lexer("UPDATE users SET name = 'Bob', age = 30 WHERE id = 2;").unwrap();
+        match Parser::default().parse(&tokens).unwrap() {
+            ASTNode::Update(stmt) => {
+                assert_eq!(stmt.table.as_str(), "users");
+                assert_eq!(stmt.assignments, vec![
+                    (ColumnName::new("name").unwrap(), Value::Text("Bob".to_string()).into()),
+                    (ColumnName::new("age").unwrap(), Value::Int(30).into()),
+                ]);
+                assert!(matches!(stmt.where_clause, Some(WhereClause::Compare { value: Expr::Literal(Value::Int(2)), .. })));
+            }
+            _ => panic!("Expected Update ASTNode"),
+        }
+    }
+
+    #[test]
+    fn parse_update_with_column_references() {
+        match Parser::default().parse(&lexer("UPDATE t SET n = t.n + 1").unwrap()).unwrap() {
+            ASTNode::Update(stmt) => assert_eq!(stmt.assignments, vec![(
+                ColumnName::new("n").unwrap(),
+                Expr::Binary {
+                    op: BinaryOp::Add,
+                    left: Box::new(Expr::Column(ColumnRef {
+                        table: Some(TableName::new("t").unwrap()),
+                        column: ColumnName::new("n").unwrap(),
+                    })),
+                    right: Box::new(Value::Int(1).into()),
+                },
+            )]),
+            _ => panic!("Expected Update ASTNode"),
+        }
+    }
+
+    #[test]
+    fn parse_update_without_set_fails() {
+        let tokens = lexer("UPDATE users name = 'Bob'").unwrap();
+        let err = Parser::default().parse(&tokens).unwrap_err();
+        assert_eq!(err.message(), "Expected SET after table name, found 'name' at position 13");
+    }
+
+    #[test]
+    fn parse_delete_with_and_without_where() {
+        let tokens = lexer("DELETE FROM users WHERE id < 10;").unwrap();
+        match Parser::default().parse(&tokens).unwrap() {
+            ASTNode::Delete(stmt) => {
+                assert_eq!(stmt.table.as_str(), "users");
+                assert!(matches!(stmt.where_clause, Some(WhereClause::Compare { op: CompareOp::Lt, .. })));
+            }
+            _ => panic!("Expected Delete ASTNode"),
+        }
+
+        let tokens = lexer("DELETE FROM users").unwrap();
+        match Parser::default().parse(&tokens).unwrap() {
+            ASTNode::Delete(stmt) => assert!(stmt.where_clause.is_none()),
+            _ => panic!("Expected Delete ASTNode"),
+        }
+    }
+
+    #[test]
+    fn parse_alter_table_actions() {
+        let parse = |sql: &str| match Parser::default().parse(&lexer(sql).unwrap()).unwrap() {
+            ASTNode::AlterTable(stmt) => stmt.action,
+            _ => panic!("Expected AlterTable ASTNode"),
+        };
+
+        assert_eq!(
+            parse("ALTER TABLE users ADD COLUMN age Int;"),
+            AlterAction::AddColumn(Column::new(ColumnName::new("age").unwrap(), DataType::Int))
+        );
+        assert_eq!(
+            parse("ALTER TABLE users DROP age"),
+            AlterAction::DropColumn(ColumnName::new("age").unwrap())
+        );
+        assert_eq!(
+            parse("ALTER TABLE users RENAME COLUMN name TO full_name"),
+            AlterAction::RenameColumn {
+                from: ColumnName::new("name").unwrap(),
+                to: ColumnName::new("full_name").unwrap(),
+            }
+        );
+        assert_eq!(
+            parse("ALTER TABLE users RENAME TO customers;"),
+            AlterAction::RenameTable(TableName::new("customers").unwrap())
+        );
+        assert!(Parser::default().parse(&lexer("ALTER TABLE users RENAME TO").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("ALTER TABLE users MODIFY x").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_insert_values_with_null() {
+        let tokens = lexer("INSERT INTO users VALUES (1, NULL, 'x');").unwrap();
+        match Parser::default().parse(&tokens).unwrap() {
+            ASTNode::Insert(stmt) => {
+                assert_eq!(stmt.table.as_str(), "users");
+                assert_eq!(stmt.rows, vec![vec![Value::Int(1).into(), Value::Null.into(), Value::Text("x".to_string()).into()]]);
+            }
+            _ => panic!("Expected Insert ASTNode"),
+        }
+
+        assert!(Parser::default().parse(&lexer("INSERT INTO users VALUES (1").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_float_column_and_literal() {
+        match Parser::default().parse(&lexer("CREATE TABLE items (price Float)").unwrap()).unwrap() {
+            ASTNode::CreateTable(stmt) => assert_eq!(stmt.columns[0].dtype, DataType::Float),
+            _ => panic!("Expected CreateTable ASTNode"),
+        }
+        match Parser::default().parse(&lexer("INSERT INTO items VALUES (9.99)").unwrap()).unwrap() {
+            ASTNode::Insert(stmt) => assert_eq!(stmt.rows, vec![vec![Value::Float(9.99).into()]]),
+            _ => panic!("Expected Insert ASTNode"),
+        }
+    }
+
+    #[test]
+    fn parse_boolean_literals() {
+        match Parser::default().parse(&lexer("INSERT INTO flags VALUES (TRUE, false)").unwrap()).unwrap() {
+            ASTNode::Insert(stmt) => assert_eq!(stmt.rows, vec![vec![Value::Bool(true).into(), Value::Bool(false).into()]]),
+            _ => panic!("Expected Insert ASTNode"),
+        }
+        match Parser::default().parse(&lexer("SELECT * FROM flags WHERE active = TRUE").unwrap()).unwrap() {
+            ASTNode::Select(stmt) => assert_eq!(
+                stmt.where_clause,
+                Some(WhereClause::Compare {
+                    column: ColumnName::new("active").unwrap().into(),
+                    op: CompareOp::Eq,
+                    value: Value::Bool(true).into(),
+                })
+            ),
+            _ => panic!("Expected Select ASTNode"),
+        }
+    }
+
+    #[test]
+    fn parse_typed_date_literals_and_function_calls() {
+        let sql = "INSERT INTO events VALUES (DATE '2024-01-31', timestamp '2024-01-31 12:00:00', NOW())";
+        match Parser::default().parse(&lexer(sql).unwrap()).unwrap() {
+            ASTNode::Insert(stmt) => assert_eq!(stmt.rows, vec![vec![
+                Value::Date(datetime::parse_date("2024-01-31").unwrap()).into(),
+                Value::Timestamp(datetime::parse_timestamp("2024-01-31 12:00:00").unwrap()).into(),
+                Expr::Function { name: "NOW".to_string(), args: vec![] },
+            ]]),
+            _ => panic!("Expected Insert ASTNode"),
+        }
+
+        let err = Parser::default().parse(&lexer("INSERT INTO events VALUES (DATE '2024-02-30')").unwrap()).unwrap_err();
+        assert!(format!("{:?}", err).contains("Invalid date"));
+        assert!(Parser::default().parse(&lexer("INSERT INTO events VALUES (day)").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_blob_column_and_hex_literal() {
+        match Parser::default().parse(&lexer("CREATE TABLE files (data BLOB)").unwrap()).unwrap() {
+            ASTNode::CreateTable(stmt) => assert_eq!(stmt.columns[0].dtype, DataType::Blob),
+            _ => panic!("Expected CreateTable ASTNode"),
+        }
+        match Parser::default().parse(&lexer("INSERT INTO files VALUES (X'CAFE')").unwrap()).unwrap() {
+            ASTNode::Insert(stmt) => assert_eq!(stmt.rows, vec![vec![Value::Blob(vec![0xCA, 0xFE]).into()]]),
+            _ => panic!("Expected Insert ASTNode"),
+        }
+    }
+
+    #[test]
+    fn parse_insert_with_column_list() {
+        match Parser::default().parse(&lexer("INSERT INTO users (name, id) VALUES ('x', 1);").unwrap()).unwrap() {
+            ASTNode::Insert(stmt) => {
+                assert_eq!(stmt.columns, Some(vec![ColumnName::new("name").unwrap(), ColumnName::new("id").unwrap()]));
+                assert_eq!(stmt.rows, vec![vec![Value::Text("x".to_string()).into(), Value::Int(1).into()]]);
+            }
+            _ => panic!("Expected Insert ASTNode"),
+        }
+
+        assert!(Parser::default().parse(&lexer("INSERT INTO users (id, name) VALUES (1)").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("INSERT INTO users (id VALUES (1)").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("INSERT INTO users () VALUES (1)").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_insert_select() {
+        match Parser::default().parse(&lexer("INSERT INTO archive (id, msg) SELECT id, msg FROM logs WHERE id < 10;").unwrap()).unwrap() {
+            ASTNode::Insert(stmt) => {
+                assert_eq!(stmt.columns, Some(vec![ColumnName::new("id").unwrap(), ColumnName::new("msg").unwrap()]));
+                assert!(stmt.rows.is_empty());
+                let query = stmt.query.expect("a query");
+                assert_eq!(query.table.as_str(), "logs");
+                assert!(query.where_clause.is_some());
+            }
+            _ => panic!("Expected Insert ASTNode"),
+        }
+        match Parser::default().parse(&lexer("INSERT INTO archive SELECT * FROM logs").unwrap()).unwrap() {
+            ASTNode::Insert(stmt) => assert_eq!(stmt.query.unwrap().columns, None),
+            _ => panic!("Expected Insert ASTNode"),
+        }
+        assert!(Parser::default().parse(&lexer("INSERT INTO archive SELECT FROM logs").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("INSERT INTO archive logs").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_insert_conflict_handling() {
+        let conflict = |sql: &str| match Parser::default().parse(&lexer(sql).unwrap()) {
+            Ok(ASTNode::Insert(stmt)) => Ok(stmt.on_conflict),
+            Ok(other) => panic!("Expected Insert ASTNode, got {:?}", other),
+            Err(e) => Err(e),
+        };
+        let id = ColumnName::new("id").unwrap();
+        assert_eq!(conflict("INSERT INTO t VALUES (1)"), Ok(None));
+        assert_eq!(conflict("insert or ignore into t values (1)"), Ok(Some(OnConflict::Ignore(None))));
+        assert_eq!(conflict("INSERT OR REPLACE INTO t SELECT * FROM s;"), Ok(Some(OnConflict::Replace)));
+        assert_eq!(conflict("INSERT INTO t VALUES (1) ON CONFLICT DO NOTHING"), Ok(Some(OnConflict::Ignore(None))));
+        assert_eq!(
+            conflict("INSERT INTO t SELECT * FROM s ON CONFLICT (id) DO NOTHING;"),
+            Ok(Some(OnConflict::Ignore(Some(id.clone()))))
+        );
+        assert_eq!(
+            conflict("INSERT INTO t VALUES (1, 2) ON CONFLICT (id) DO UPDATE SET n = t.n + excluded.n, at = NOW()"),
+            Ok(Some(OnConflict::Update {
+                column: id,
+                assignments: vec![
+                    (
+                        ColumnName::new("n").unwrap(),
+                        Expr::Binary {
+                            op: BinaryOp::Add,
+                            left: Box::new(Expr::Column(ColumnRef {
+                                table: Some(TableName::new("t").unwrap()),
+                                column: ColumnName::new("n").unwrap(),
+                            })),
+                            right: Box::new(Expr::Column(ColumnRef {
+                                table: Some(TableName::new("excluded").unwrap()),
+                                column: ColumnName::new("n").unwrap(),
+                            })),
+                        },
+                    ),
+                    (ColumnName::new("at").unwrap(), Expr::Function { name: "NOW".to_string(), args: vec![] }),
+                ],
+            }))
+        );
+
+        assert!(conflict("INSERT OR UPDATE INTO t VALUES (1)").is_err());
+        assert!(conflict("INSERT OR IGNORE INTO t VALUES (1) ON CONFLICT DO NOTHING").is_err());
+        assert!(conflict("INSERT INTO t VALUES (1) ON CONFLICT DO UPDATE SET n = 1").is_err());
+        assert!(conflict("INSERT INTO t VALUES (1) ON CONFLICT (id) DO SOMETHING").is_err());
+        assert!(conflict("INSERT INTO t VALUES (1) ON DUPLICATE KEY").is_err());
+    }
+
+    #[test]
+    fn parse_multi_row_insert() {
+        match Parser::default().parse(&lexer("INSERT INTO t (id) VALUES (1), (2),(3);").unwrap()).unwrap() {
+            ASTNode::Insert(stmt) => assert_eq!(stmt.rows, vec![
+                vec![Value::Int(1).into()],
+                vec![Value::Int(2).into()],
+                vec![Value::Int(3).into()],
+            ]),
+            _ => panic!("Expected Insert ASTNode"),
+        }
+        assert!(Parser::default().parse(&lexer("INSERT INTO t VALUES (1),").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("INSERT INTO t (id) VALUES (1), (2, 3)").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_order_by_keys() {
+        let tokens = lexer("SELECT * FROM users WHERE id > 0 ORDER BY name DESC, id;").unwrap();
+        match Parser::default().parse(&tokens).unwrap() {
+            ASTNode::Select(stmt) => assert_eq!(stmt.order_by, vec![
+                (ColumnName::new("name").unwrap().into(), SortDirection::Desc),
+                (ColumnName::new("id").unwrap().into(), SortDirection::Asc),
+            ]),
+            _ => panic!("Expected Select ASTNode"),
+        }
+
+        assert!(Parser::default().parse(&lexer("SELECT * FROM users ORDER name").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("SELECT * FROM users ORDER BY").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_limit_and_offset() {
+        let tokens = lexer("SELECT * FROM users ORDER BY id LIMIT 10 OFFSET 20;").unwrap();
+        match Parser::default().parse(&tokens).unwrap() {
+            ASTNode::Select(stmt) => {
+                assert_eq!(stmt.limit, Some(10));
+                assert_eq!(stmt.offset, Some(20));
+            }
+            _ => panic!("Expected Select ASTNode"),
+        }
+        match Parser::default().parse(&lexer("SELECT * FROM users LIMIT 5").unwrap()).unwrap() {
+            ASTNode::Select(stmt) => assert_eq!((stmt.limit, stmt.offset), (Some(5), None)),
+            _ => panic!("Expected Select ASTNode"),
+        }
+
+        assert!(Parser::default().parse(&lexer("SELECT * FROM users LIMIT").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("SELECT * FROM users LIMIT 'x'").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("SELECT * FROM users OFFSET 1 LIMIT 1").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_select_distinct() {
+        match Parser::default().parse(&lexer("SELECT DISTINCT name FROM users").unwrap()).unwrap() {
+            ASTNode::Select(stmt) => {
+                assert!(stmt.distinct);
+                assert_eq!(stmt.columns, Some(vec![(SelectItem::Column(ColumnName::new("name").unwrap().into()), None)]));
+            }
+            _ => panic!("Expected Select ASTNode"),
+        }
+        match Parser::default().parse(&lexer("SELECT name FROM users").unwrap()).unwrap() {
+            ASTNode::Select(stmt) => assert!(!stmt.distinct),
+            _ => panic!("Expected Select ASTNode"),
+        }
+    }
+
+    #[test]
+    fn parse_group_by_with_aggregates_and_having() {
+        let sql = "SELECT dept, COUNT(*), sum(salary) FROM staff GROUP BY dept HAVING COUNT(*) > 1 ORDER BY dept";
+        match Parser::default().parse(&lexer(sql).unwrap()).unwrap() {
+            ASTNode::Select(stmt) => {
+                let dept = ColumnRef::from(ColumnName::new("dept").unwrap());
+                let count_all = SelectItem::Aggregate { func: AggregateFunc::Count, column: None };
+                assert_eq!(stmt.columns, Some(vec![
+                    (SelectItem::Column(dept.clone()), None),
+                    (count_all.clone(), None),
+                    (SelectItem::Aggregate { func: AggregateFunc::Sum, column: Some(ColumnName::new("salary").unwrap().into()) }, None),
+                ]));
+                assert_eq!(stmt.group_by, vec![dept]);
+                assert_eq!(stmt.having, Some(HavingClause {
+                    item: count_all,
+                    op: CompareOp::Gt,
+                    value: Value::Int(1).into(),
+                }));
+                assert_eq!(stmt.order_by.len(), 1);
+            }
+            _ => panic!("Expected Select ASTNode"),
+        }
+
+        assert!(Parser::default().parse(&lexer("SELECT SUM(*) FROM staff").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("SELECT MEDIAN(x) FROM staff").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("SELECT dept FROM staff GROUP dept").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_logical_operators_with_precedence() {
+        let parse = |sql: &str| match Parser::default().parse(&lexer(sql).unwrap()).unwrap() {
+            ASTNode::Select(stmt) => stmt.where_clause.unwrap(),
+            _ => panic!("Expected Select ASTNode"),
+        };
+        let cmp = |column: &str, n: i64| WhereClause::Compare {
+            column: ColumnName::new(column).unwrap().into(),
+            op: CompareOp::Eq,
+            value: Value::Int(n).into(),
+        };
+        let and = |l, r| WhereClause::And(Box::new(l), Box::new(r));
+        let or = |l, r| WhereClause::Or(Box::new(l), Box::new(r));
+
+        assert_eq!(
+            parse("SELECT * FROM t WHERE a = 1 OR b = 2 AND NOT c = 3"),
+            or(cmp("a", 1), and(cmp("b", 2), WhereClause::Not(Box::new(cmp("c", 3)))))
+        );
+        assert_eq!(
+            parse("SELECT * FROM t WHERE (a = 1 OR b = 2) AND c = 3 AND d = 4"),
+            and(and(or(cmp("a", 1), cmp("b", 2)), cmp("c", 3)), cmp("d", 4))
+        );
+        assert!(Parser::default().parse(&lexer("SELECT * FROM t WHERE (a = 1").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("SELECT * FROM t WHERE a = 1 AND").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_is_null_predicates() {
+        let parse = |sql: &str| match Parser::default().parse(&lexer(sql).unwrap()).unwrap() {
+            ASTNode::Select(stmt) => stmt.where_clause.unwrap(),
+            _ => panic!("Expected Select ASTNode"),
+        };
+
+        assert_eq!(
+            parse("SELECT * FROM t WHERE name IS NULL"),
+            WhereClause::IsNull { column: ColumnName::new("name").unwrap().into(), negated: false }
+        );
+        assert_eq!(
+            parse("SELECT * FROM t WHERE name IS NOT NULL"),
+            WhereClause::IsNull { column: ColumnName::new("name").unwrap().into(), negated: true }
+        );
+        assert!(Parser::default().parse(&lexer("SELECT * FROM t WHERE name IS 1").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_like_predicates() {
+        let parse = |sql: &str| match Parser::default().parse(&lexer(sql).unwrap()).unwrap() {
+            ASTNode::Select(stmt) => stmt.where_clause.unwrap(),
+            _ => panic!("Expected Select ASTNode"),
+        };
+        let like = |negated| WhereClause::Like {
+            column: ColumnName::new("name").unwrap().into(),
+            pattern: Value::Text("A%".into()).into(),
+            negated,
+        };
+
+        assert_eq!(parse("SELECT * FROM t WHERE name LIKE 'A%'"), like(false));
+        assert_eq!(parse("SELECT * FROM t WHERE name NOT LIKE 'A%'"), like(true));
+        assert!(Parser::default().parse(&lexer("SELECT * FROM t WHERE name NOT = 'A'").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_in_and_between_predicates() {
+        let parse = |sql: &str| match Parser::default().parse(&lexer(sql).unwrap()).unwrap() {
+            ASTNode::Select(stmt) => stmt.where_clause.unwrap(),
+            _ => panic!("Expected Select ASTNode"),
+        };
+        let id = || ColumnRef::from(ColumnName::new("id").unwrap());
+        let int = |n| Expr::Literal(Value::Int(n));
+
+        assert_eq!(
+            parse("SELECT * FROM t WHERE id NOT IN (1, 2, 3)"),
+            WhereClause::In { column: id(), values: vec![int(1), int(2), int(3)], negated: true }
+        );
+        // The AND inside BETWEEN belongs to the predicate, the next one joins clauses
+        assert_eq!(
+            parse("SELECT * FROM t WHERE id BETWEEN 1 AND 5 AND id IN (2)"),
+            WhereClause::And(
+                Box::new(WhereClause::Between { column: id(), low: int(1), high: int(5), negated: false }),
+                Box::new(WhereClause::In { column: id(), values: vec![int(2)], negated: false }),
+            )
+        );
+        assert!(Parser::default().parse(&lexer("SELECT * FROM t WHERE id IN ()").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("SELECT * FROM t WHERE id BETWEEN 1 OR 5").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_inner_join_with_qualified_columns() {
+        let sql = "SELECT users.name, orders.total FROM users INNER JOIN orders ON users.id = orders.user_id WHERE total > 10";
+        let qualified = |table: &str, column: &str| ColumnRef {
+            table: Some(TableName::new(table).unwrap()),
+            column: ColumnName::new(column).unwrap(),
+        };
+        match Parser::default().parse(&lexer(sql).unwrap()).unwrap() {
+            ASTNode::Select(stmt) => {
+                assert_eq!(stmt.columns, Some(vec![
+                    (SelectItem::Column(qualified("users", "name")), None),
+                    (SelectItem::Column(qualified("orders", "total")), None),
+                ]));
+                assert_eq!(stmt.joins, vec![Join {
+                    table: TableName::new("orders").unwrap(),
+                    alias: None,
+                    on: Some(WhereClause::Compare {
+                        column: qualified("users", "id"),
+                        op: CompareOp::Eq,
+                        value: Expr::Column(qualified("orders", "user_id")),
+                    }),
+                }]);
+                assert!(matches!(stmt.where_clause, Some(WhereClause::Compare { value: Expr::Literal(Value::Int(10)), .. })));
+            }
+            _ => panic!("Expected Select ASTNode"),
+        }
+
+        match Parser::default().parse(&lexer("SELECT * FROM a JOIN b ON x = y JOIN c ON c.z IS NULL").unwrap()).unwrap() {
+            ASTNode::Select(stmt) => assert_eq!(stmt.joins.len(), 2),
+            _ => panic!("Expected Select ASTNode"),
+        }
+        assert!(Parser::default().parse(&lexer("SELECT * FROM a JOIN b").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("SELECT * FROM a INNER b ON x = y").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("SELECT a. FROM a").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_arithmetic_select_items_with_precedence() {
+        let column = |name: &str| Expr::Column(ColumnName::new(name).unwrap().into());
+        let binary = |op, left, right| Expr::Binary { op, left: Box::new(left), right: Box::new(right) };
+
+        match Parser::default().parse(&lexer("SELECT id, id * 2 + 1, name || '!', -(price - 1.5) FROM t").unwrap()).unwrap() {
+            ASTNode::Select(stmt) => assert_eq!(stmt.columns.unwrap().into_iter().map(|(item, _)| item).collect::<Vec<_>>(), vec![
+                SelectItem::Column(ColumnName::new("id").unwrap().into()),
+                SelectItem::Expr(binary(
+                    BinaryOp::Add,
+                    binary(BinaryOp::Mul, column("id"), Value::Int(2).into()),
+                    Value::Int(1).into(),
+                )),
+                SelectItem::Expr(binary(BinaryOp::Concat, column("name"), Value::Text("!".to_string()).into())),
+                SelectItem::Expr(Expr::Negate(Box::new(binary(BinaryOp::Sub, column("price"), Value::Float(1.5).into())))),
+            ]),
+            _ => panic!("Expected Select ASTNode"),
+        }
+
+        match Parser::default().parse(&lexer("INSERT INTO t VALUES (-3, 10 - 2 - 1)").unwrap()).unwrap() {
+            ASTNode::Insert(stmt) => assert_eq!(stmt.rows[0], vec![
+                Value::Int(-3).into(),
+                binary(BinaryOp::Sub, binary(BinaryOp::Sub, Value::Int(10).into(), Value::Int(2).into()), Value::Int(1).into()),
+            ]),
+            _ => panic!("Expected Insert ASTNode"),
+        }
+        assert!(Parser::default().parse(&lexer("SELECT (a + 1 FROM t").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("SELECT a + FROM t").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_column_and_table_aliases() {
+        let sql = "SELECT u.id AS user_id, COUNT(*) n FROM users AS u JOIN orders o ON u.id = o.user_id GROUP BY u.id";
+        match Parser::default().parse(&lexer(sql).unwrap()).unwrap() {
+            ASTNode::Select(stmt) => {
+                let aliases: Vec<_> = stmt.columns.unwrap().into_iter().map(|(_, alias)| alias).collect();
+                assert_eq!(aliases, vec![Some(ColumnName::new("user_id").unwrap()), Some(ColumnName::new("n").unwrap())]);
+                assert_eq!(stmt.alias, Some(TableName::new("u").unwrap()));
+                assert_eq!(stmt.joins[0].alias, Some(TableName::new("o").unwrap()));
+            }
+            _ => panic!("Expected Select ASTNode"),
+        }
+
+        assert!(Parser::default().parse(&lexer("SELECT id AS FROM t").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("SELECT id AS 'x' FROM t").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_cross_joins() {
+        let joined = |sql: &str| match Parser::default().parse(&lexer(sql).unwrap()).unwrap() {
+            ASTNode::Select(stmt) => stmt.joins,
+            _ => panic!("Expected Select ASTNode"),
+        };
+        let cross = |table: &str| Join { table: TableName::new(table).unwrap(), alias: None, on: None };
+
+        assert_eq!(joined("SELECT * FROM a, b WHERE a.x = b.x"), vec![cross("b")]);
+        assert_eq!(joined("SELECT * FROM a CROSS JOIN b, c"), vec![cross("b"), cross("c")]);
+        assert!(Parser::default().parse(&lexer("SELECT * FROM a CROSS b").unwrap()).is_err());
+        assert!(Parser::default().parse(&lexer("SELECT * FROM a,").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_schema_qualified_tables() {
+        let sql = "SELECT column_name FROM information_schema.columns c JOIN information_schema.tables ON c.table_name = table_name";
+        match Parser::default().parse(&lexer(sql).unwrap()).unwrap() {
+            ASTNode::Select(stmt) => {
+                assert_eq!(stmt.table, TableName::qualified("information_schema", "columns").unwrap());
+                assert_eq!(stmt.alias, Some(TableName::new("c").unwrap()));
+                assert_eq!(stmt.joins[0].table, TableName::qualified("information_schema", "tables").unwrap());
+            }
+            _ => panic!("Expected Select ASTNode"),
+        }
+        assert!(Parser::default().parse(&lexer("SELECT * FROM information_schema.").unwrap()).is_err());
+    }
+
+    #[test]
+    fn parse_create_table_unknown_type_fails() {
+        let tokens = lexer("CREATE TABLE t (a NUMBERISH);").unwrap();
+        assert!(Parser::default().parse(&tokens).is_err());
     }
 }
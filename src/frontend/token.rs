@@ -2,26 +2,40 @@
 //!
 //! This module defines the `Token` enum representing all lexical tokens
 //! in SQL, including keywords, identifiers, literals, and symbols.
-//! Each token stores the raw value and optional position information
-//! for error reporting.
+//! Each token stores the raw value and the optional [`Span`] of the input
+//! it was read from, for error reporting.
+
+use crate::core::span::Span;
 
 /// Represents a lexical token in SQL.
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Token {
     /// SQL keywords like SELECT, INSERT, etc.
-    Keyword { value: String, pos: Option<usize> },
+    Keyword { value: String, span: Option<Span> },
 
     /// Identifiers: table names, column names, etc.
-    Identifier { value: String, pos: Option<usize> },
+    Identifier { value: String, span: Option<Span> },
 
     /// Numeric literal (integer)
-    Number { value: i64, pos: Option<usize> },
+    Number { value: i64, span: Option<Span> },
+
+    /// Numeric literal with a fractional part or exponent
+    Float { value: f64, span: Option<Span> },
+
+    /// Hex binary literal (`X'DEADBEEF'`), already decoded to bytes
+    Blob { value: Vec<u8>, span: Option<Span> },
 
     /// String literal (UTF-8)
-    String { value: String, pos: Option<usize> },
+    String { value: String, span: Option<Span> },
+
+    /// Symbols like (, ), ,, ;, *
+    Symbol { value: char, span: Option<Span> },
 
-    /// Symbols like (, ), ,, ;
-    Symbol { value: char, pos: Option<usize> },
+    /// Comparison operators like =, <, >, !=
+    Operator { value: String, span: Option<Span> },
+
+    /// End-of-input sentinel appended by the lexer
+    Eof { span: Option<Span> },
 }
 
 impl Token {
@@ -31,21 +45,47 @@ impl Token {
             Token::Keyword { value, .. } => value.clone(),
             Token::Identifier { value, .. } => value.clone(),
             Token::Number { value, .. } => value.to_string(),
+            Token::Float { value, .. } => value.to_string(),
+            Token::Blob { value, .. } => {
+                let hex: String = value.iter().map(|b| format!("{:02X}", b)).collect();
+                format!("X'{}'", hex)
+            }
             Token::String { value, .. } => value.clone(),
             Token::Symbol { value, .. } => value.to_string(),
+            Token::Operator { value, .. } => value.clone(),
+            Token::Eof { .. } => String::new(),
         }
     }
 
-    /// Returns the optional position
-    pub fn pos(&self) -> Option<usize> {
+    /// Returns the token written back as SQL, which the lexer reads as the
+    /// same token: strings are quoted, and floats keep their fraction.
+    pub fn to_sql(&self) -> String {
         match self {
-            Token::Keyword { pos, .. } => *pos,
-            Token::Identifier { pos, .. } => *pos,
-            Token::Number { pos, .. } => *pos,
-            Token::String { pos, .. } => *pos,
-            Token::Symbol { pos, .. } => *pos,
+            Token::Float { value, .. } => format!("{:?}", value),
+            Token::String { value, .. } => format!("'{}'", value),
+            other => other.value(),
         }
     }
+
+    /// Returns the optional span of the input the token was read from
+    pub fn span(&self) -> Option<Span> {
+        match self {
+            Token::Keyword { span, .. } => *span,
+            Token::Identifier { span, .. } => *span,
+            Token::Number { span, .. } => *span,
+            Token::Float { span, .. } => *span,
+            Token::Blob { span, .. } => *span,
+            Token::String { span, .. } => *span,
+            Token::Symbol { span, .. } => *span,
+            Token::Operator { span, .. } => *span,
+            Token::Eof { span } => *span,
+        }
+    }
+
+    /// Returns the optional position of the first character of the token
+    pub fn pos(&self) -> Option<usize> {
+        self.span().map(|span| span.start)
+    }
 }
 
 #[cfg(test)]
@@ -54,36 +94,65 @@ mod tests {
 
     #[test]
     fn keyword_token_value() {
-        let t = Token::Keyword { value: "SELECT".to_string(), pos: Some(0) };
+        let t = Token::Keyword { value: "SELECT".to_string(), span: Some(Span::new(0, 6)) };
         assert_eq!(t.value(), "SELECT");
         assert_eq!(t.pos(), Some(0));
+        assert_eq!(t.span(), Some(Span::new(0, 6)));
     }
 
     #[test]
     fn identifier_token_value() {
-        let t = Token::Identifier { value: "user_id".to_string(), pos: None };
+        let t = Token::Identifier { value: "user_id".to_string(), span: None };
         assert_eq!(t.value(), "user_id");
         assert_eq!(t.pos(), None);
     }
 
     #[test]
     fn number_token_value() {
-        let t = Token::Number { value: 42, pos: Some(5) };
+        let t = Token::Number { value: 42, span: Some(Span::new(5, 7)) };
         assert_eq!(t.value(), "42");
         assert_eq!(t.pos(), Some(5));
     }
 
+    #[test]
+    fn float_token_value() {
+        let t = Token::Float { value: 2.5, span: Some(Span::new(1, 4)) };
+        assert_eq!(t.value(), "2.5");
+        assert_eq!(t.pos(), Some(1));
+    }
+
+    #[test]
+    fn blob_token_value() {
+        let t = Token::Blob { value: vec![0xDE, 0xAD, 0x0F], span: Some(Span::new(4, 13)) };
+        assert_eq!(t.value(), "X'DEAD0F'");
+        assert_eq!(t.pos(), Some(4));
+    }
+
     #[test]
     fn string_token_value() {
-        let t = Token::String { value: "Alice".to_string(), pos: None };
+        let t = Token::String { value: "Alice".to_string(), span: None };
         assert_eq!(t.value(), "Alice");
         assert_eq!(t.pos(), None);
     }
 
     #[test]
     fn symbol_token_value() {
-        let t = Token::Symbol { value: '(', pos: Some(3) };
+        let t = Token::Symbol { value: '(', span: Some(Span::new(3, 4)) };
         assert_eq!(t.value(), "(");
         assert_eq!(t.pos(), Some(3));
     }
+
+    #[test]
+    fn operator_token_value() {
+        let t = Token::Operator { value: "!=".to_string(), span: Some(Span::new(7, 9)) };
+        assert_eq!(t.value(), "!=");
+        assert_eq!(t.pos(), Some(7));
+    }
+
+    #[test]
+    fn eof_token_value() {
+        let t = Token::Eof { span: Some(Span::at(10)) };
+        assert_eq!(t.value(), "");
+        assert_eq!(t.pos(), Some(10));
+    }
 }
@@ -0,0 +1,152 @@
+//! Tab completion of SQL in the REPL.
+//!
+//! [`complete`] looks at the text before the cursor to decide what the
+//! word being typed can be: a table name after `FROM`, `JOIN`, `INTO`,
+//! `UPDATE` and the like, a column name in a select list or a condition,
+//! and otherwise a keyword. Names come from a [`Catalog`], a snapshot of
+//! the database taken after each statement.
+
+use crate::core::db::Database;
+use crate::frontend::lexer::{lexer, KEYWORDS};
+use crate::frontend::token::Token;
+
+/// Keywords followed by a table name.
+const TABLE_KEYWORDS: &[&str] = &["FROM", "JOIN", "INTO", "UPDATE", "TABLE", "COPY", "ANALYZE", "DESCRIBE"];
+
+/// Keywords followed by expressions over columns.
+const COLUMN_KEYWORDS: &[&str] = &["SELECT", "DISTINCT", "WHERE", "BY", "HAVING", "SET", "AND", "OR", "NOT"];
+
+/// The names known to the completion: each table with its columns.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Catalog {
+    tables: Vec<(String, Vec<String>)>,
+}
+
+impl Catalog {
+    /// Takes the table and column names of `db`.
+    pub fn of(db: &Database) -> Self {
+        let tables = db
+            .table_names()
+            .into_iter()
+            .map(|name| {
+                let table = db.table(name).expect("listed table exists");
+                let columns = table.schema().columns().iter().map(|c| c.name.as_str().to_string()).collect();
+                (name.as_str().to_string(), columns)
+            })
+            .collect();
+        Catalog { tables }
+    }
+
+    /// Returns the columns of the table `name`, if it exists.
+    fn columns(&self, name: &str) -> Option<&[String]> {
+        self.tables.iter().find(|(table, _)| table.eq_ignore_ascii_case(name)).map(|(_, columns)| columns.as_slice())
+    }
+}
+
+/// Completes the word ending at byte offset `pos` of `line`.
+///
+/// # Returns
+/// The offset where the word starts, and the candidates to replace it
+/// with: names first, then keywords, each sorted. Keywords follow the case
+/// of the word: lower case if it has a lowercase letter, upper case
+/// otherwise.
+pub fn complete(line: &str, pos: usize, catalog: &Catalog) -> (usize, Vec<String>) {
+    let before = &line[..pos];
+    let start = before.rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).map_or(0, |i| i + 1);
+    let word = &before[start..];
+
+    // `table.col`: the columns of that table
+    if let Some(qualifier) = before[..start].strip_suffix('.') {
+        let table_start = qualifier.rfind(|c: char| !(c.is_ascii_alphanumeric() || c == '_')).map_or(0, |i| i + 1);
+        let columns = catalog.columns(&qualifier[table_start..]).unwrap_or_default();
+        return (start, matching(columns.iter().map(String::as_str), word));
+    }
+
+    // Unfinished input may not lex; the words read so far are enough
+    let tokens = lexer(&before[..start]).unwrap_or_default();
+    let previous = tokens.iter().rev().find(|t| !matches!(t, Token::Eof { .. }));
+    let clause = tokens.iter().rev().find_map(|t| match t {
+        Token::Keyword { value, .. } if TABLE_KEYWORDS.contains(&value.as_str()) || COLUMN_KEYWORDS.contains(&value.as_str()) => {
+            Some(value.as_str())
+        }
+        _ => None,
+    });
+
+    let mut candidates = Vec::new();
+    if matches!(previous, Some(Token::Keyword { value, .. }) if TABLE_KEYWORDS.contains(&value.as_str())) {
+        candidates = matching(catalog.tables.iter().map(|(name, _)| name.as_str()), word);
+    } else {
+        if clause.is_some_and(|c| COLUMN_KEYWORDS.contains(&c)) {
+            // Columns of the tables named in the line, or of every table
+            let named: Vec<&[String]> = line_tables(line).iter().filter_map(|name| catalog.columns(name)).collect();
+            let columns: Vec<&str> = if named.is_empty() {
+                catalog.tables.iter().flat_map(|(_, columns)| columns.iter().map(String::as_str)).collect()
+            } else {
+                named.into_iter().flatten().map(String::as_str).collect()
+            };
+            candidates = matching(columns.into_iter(), word);
+        }
+        let lower = word.chars().any(|c| c.is_ascii_lowercase());
+        let keywords = matching(KEYWORDS.iter().copied(), word)
+            .into_iter()
+            .map(|k| if lower { k.to_lowercase() } else { k });
+        candidates.extend(keywords);
+    }
+    (start, candidates)
+}
+
+/// Returns the names in `names` starting with `prefix`, ignoring case,
+/// sorted and without duplicates.
+fn matching<'a>(names: impl Iterator<Item = &'a str>, prefix: &str) -> Vec<String> {
+    let mut found: Vec<String> = names
+        .filter(|name| name.len() >= prefix.len() && name[..prefix.len()].eq_ignore_ascii_case(prefix))
+        .map(str::to_string)
+        .collect();
+    found.sort();
+    found.dedup();
+    found
+}
+
+/// Returns the identifiers following a table keyword anywhere in `line`.
+fn line_tables(line: &str) -> Vec<String> {
+    let tokens = lexer(line).unwrap_or_default();
+    tokens
+        .windows(2)
+        .filter_map(|pair| match pair {
+            [Token::Keyword { value, .. }, Token::Identifier { value: name, .. }] if TABLE_KEYWORDS.contains(&value.as_str()) => {
+                Some(name.clone())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catalog() -> Catalog {
+        Catalog {
+            tables: vec![
+                ("orders".to_string(), vec!["id".to_string(), "user_id".to_string(), "total".to_string()]),
+                ("users".to_string(), vec!["id".to_string(), "name".to_string()]),
+            ],
+        }
+    }
+
+    fn words(line: &str) -> Vec<String> {
+        complete(line, line.len(), &catalog()).1
+    }
+
+    #[test]
+    fn completes_tables_columns_and_keywords() {
+        assert_eq!(complete("SELECT * FROM us", 16, &catalog()), (14, vec!["users".to_string()]));
+        assert_eq!(words("INSERT INTO "), ["orders", "users"]);
+        assert_eq!(words("SELECT na"), ["name"]);
+        assert_eq!(words("SELECT * FROM users WHERE i"), ["id", "in", "index", "inner", "insert", "into", "is"]);
+        assert_eq!(words("SELECT * FROM orders WHERE U"), ["user_id", "UNIQUE", "UPDATE", "USING"]);
+        assert_eq!(words("SELECT users.n"), ["name"]);
+        assert_eq!(words("sel"), ["select"]);
+        assert_eq!(words("SELECT * FROM users WH"), ["WHERE"]);
+    }
+}
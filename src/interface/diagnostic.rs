@@ -0,0 +1,73 @@
+//! Rendering of errors for the user.
+//!
+//! [`render`] formats an error with its kind and code and, when the error
+//! carries a [`Span`](crate::core::span::Span), shows the line of SQL it
+//! is about with carets under the offending text:
+//!
+//! ```text
+//! Parse error E1002: Expected table name after FROM, found 'WHERE' at position 14
+//!   |
+//! 1 | SELECT * FROM WHERE a = 1
+//!   |               ^^^^^
+//! ```
+
+use crate::core::error::SqlError;
+
+/// Formats `error`, raised while running `source`, for display.
+///
+/// # Arguments
+/// * `source` - The SQL text the error's span refers to.
+/// * `error` - The error to show.
+///
+/// # Returns
+/// The header line `<kind> error <code>: <message>`, followed by the
+/// source excerpt when the error has a span inside `source`.
+pub fn render(source: &str, error: &SqlError) -> String {
+    let mut out = format!("{} error {}: {}", error.kind(), error.code(), error);
+    let Some(span) = error.span() else { return out };
+
+    // Find the line holding the start of the span; offsets count characters
+    let mut line_start = 0;
+    for (number, raw) in source.split('\n').enumerate() {
+        let line = raw.strip_suffix('\r').unwrap_or(raw);
+        let width = line.chars().count();
+        if span.start <= line_start + width {
+            let column = span.start - line_start;
+            // Underline at least one character, and no further than the line
+            let carets = span.len().min(width.saturating_sub(column)).max(1);
+            let padding: String = line.chars().take(column).map(|c| if c == '\t' { '\t' } else { ' ' }).collect();
+            let gutter = " ".repeat((number + 1).to_string().len());
+            out.push_str(&format!("\n{} |\n{} | {}\n{} | {}{}", gutter, number + 1, line, gutter, padding, "^".repeat(carets)));
+            return out;
+        }
+        // Skip the line and its '\n'
+        line_start += raw.chars().count() + 1;
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::span::Span;
+
+    #[test]
+    fn points_at_the_span_on_its_line() {
+        let sql = "SELECT *\nFROM WHERE";
+        let error = SqlError::new_parse("Expected table name").with_span(Span::new(14, 19));
+        assert_eq!(
+            render(sql, &error),
+            "Parse error E1002: Expected table name\n  |\n2 | FROM WHERE\n  |      ^^^^^"
+        );
+
+        // End of input gets a single caret past the last character
+        let error = SqlError::new_parse("Expected column name").with_span(Span::at(19));
+        assert!(render(sql, &error).ends_with("2 | FROM WHERE\n  |           ^"));
+    }
+
+    #[test]
+    fn errors_without_span_show_only_the_header() {
+        let error = SqlError::new_semantic("Table 'users' does not exist");
+        assert_eq!(render("SELECT * FROM users", &error), "Semantic error E2001: Table 'users' does not exist");
+    }
+}
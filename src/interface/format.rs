@@ -0,0 +1,207 @@
+//! Formatting of query results for display.
+//!
+//! Results are shown in one of the [`Mode`]s, picked with `.mode` in the
+//! REPL or `--format` on the command line. The default, [`table`], draws
+//! the rows of a SELECT as an ASCII table headed by the column names, with
+//! numbers right-aligned and the row count below:
+//!
+//! ```text
+//! +----+-------+
+//! | id | name  |
+//! +----+-------+
+//! |  1 | Alice |
+//! +----+-------+
+//! (1 row)
+//! ```
+//!
+//! The other modes are meant for other programs to read: CSV and tab
+//! separated values start with a header line, and JSON is an array of
+//! objects keyed by column name.
+
+use crate::core::row::Row;
+use crate::core::schema::Column;
+use crate::core::types::DataType;
+use crate::storage::csv::{value_to_field, write_record};
+use crate::storage::json::Json;
+use crate::storage::storage::value_to_json;
+
+/// A way of showing query results.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Mode {
+    /// An aligned ASCII table (see [`table`]).
+    #[default]
+    Table,
+    /// CSV records, as `COPY ... TO` writes them.
+    Csv,
+    /// A JSON array of objects, with values encoded as in JSON Lines.
+    Json,
+    /// Values separated by tabs, one row per line.
+    Plain,
+}
+
+impl Mode {
+    /// Every mode, in the order they are listed to users.
+    pub const ALL: [Mode; 4] = [Mode::Table, Mode::Csv, Mode::Json, Mode::Plain];
+
+    /// Returns the mode named `name` (`table`, `csv`, `json` or `plain`,
+    /// in any case), if there is one.
+    pub fn parse(name: &str) -> Option<Mode> {
+        Mode::ALL.into_iter().find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the name of the mode, as accepted by [`Mode::parse`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Mode::Table => "table",
+            Mode::Csv => "csv",
+            Mode::Json => "json",
+            Mode::Plain => "plain",
+        }
+    }
+}
+
+/// Formats `rows`, whose columns are `columns`, in the given mode.
+///
+/// # Returns
+/// The formatted rows, without a trailing newline.
+pub fn rows(mode: Mode, columns: &[Column], rows: &[Row]) -> String {
+    match mode {
+        Mode::Table => table(columns, rows),
+        Mode::Csv => {
+            let mut out = Vec::new();
+            let header: Vec<_> = columns.iter().map(|c| Some(c.name.as_str().to_string())).collect();
+            let records = std::iter::once(header).chain(rows.iter().map(|row| row.values().iter().map(value_to_field).collect()));
+            for record in records {
+                write_record(&mut out, &record).expect("writing to memory cannot fail");
+            }
+            String::from_utf8(out).expect("records are UTF-8").trim_end_matches('\n').to_string()
+        }
+        Mode::Json => {
+            let objects = rows
+                .iter()
+                .map(|row| {
+                    Json::Object(
+                        columns
+                            .iter()
+                            .zip(row.values())
+                            .map(|(column, value)| (column.name.as_str().to_string(), value_to_json(value)))
+                            .collect(),
+                    )
+                })
+                .collect();
+            Json::Array(objects).to_string()
+        }
+        Mode::Plain => {
+            let header = columns.iter().map(|c| c.name.as_str().to_string()).collect::<Vec<_>>().join("\t");
+            let lines = rows.iter().map(|row| row.values().iter().map(|v| v.to_string()).collect::<Vec<_>>().join("\t"));
+            std::iter::once(header).chain(lines).collect::<Vec<_>>().join("\n")
+        }
+    }
+}
+
+/// Formats `rows`, whose columns are `columns`, as an ASCII table.
+///
+/// # Arguments
+/// * `columns` - The columns of the result, giving the headers and types.
+/// * `rows` - The rows to show, in order.
+///
+/// # Returns
+/// The table followed by a `(N rows)` line, without a trailing newline.
+pub fn table(columns: &[Column], rows: &[Row]) -> String {
+    let cells: Vec<Vec<String>> = rows
+        .iter()
+        .map(|row| row.values().iter().map(|v| v.to_string()).collect())
+        .collect();
+    let widths: Vec<usize> = columns
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            cells.iter().map(|row| row[i].chars().count()).fold(column.name.as_str().chars().count(), usize::max)
+        })
+        .collect();
+
+    let rule = format!("+{}+", widths.iter().map(|w| "-".repeat(w + 2)).collect::<Vec<_>>().join("+"));
+    let line = |values: Vec<String>| format!("| {} |", values.join(" | "));
+
+    let mut lines = vec![rule.clone()];
+    lines.push(line(columns.iter().zip(&widths).map(|(c, &w)| pad(c.name.as_str(), w, false)).collect()));
+    lines.push(rule.clone());
+    for row in &cells {
+        let values = row
+            .iter()
+            .zip(columns.iter().zip(&widths))
+            .map(|(cell, (column, &w))| pad(cell, w, matches!(column.dtype, DataType::Int | DataType::Float)))
+            .collect();
+        lines.push(line(values));
+    }
+    if !cells.is_empty() {
+        lines.push(rule);
+    }
+    lines.push(match rows.len() {
+        1 => "(1 row)".to_string(),
+        n => format!("({} rows)", n),
+    });
+    lines.join("\n")
+}
+
+/// Pads `text` with spaces to `width` characters, on the left if `right`
+/// is set.
+fn pad(text: &str, width: usize, right: bool) -> String {
+    let fill = " ".repeat(width - text.chars().count());
+    if right {
+        fill + text
+    } else {
+        format!("{}{}", text, fill)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::schema::Schema;
+    use crate::core::types::{ColumnName, Value};
+
+    #[test]
+    fn draws_headers_and_aligns_numbers() {
+        let columns = vec![
+            Column::new(ColumnName::new("id").unwrap(), DataType::Int),
+            Column::new(ColumnName::new("name").unwrap(), DataType::Text),
+        ];
+        let schema = Schema::try_new(columns.clone()).unwrap();
+        let rows = vec![
+            Row::from_values(vec![Value::Int(7), Value::Text("Zoé".into())], &schema).unwrap(),
+            Row::from_values(vec![Value::Int(120), Value::Null], &schema).unwrap(),
+        ];
+
+        assert_eq!(
+            table(&columns, &rows),
+            "+-----+------+\n\
+             | id  | name |\n\
+             +-----+------+\n\
+             |   7 | Zoé  |\n\
+             | 120 | NULL |\n\
+             +-----+------+\n\
+             (2 rows)"
+        );
+        assert_eq!(table(&columns, &rows[..0]), "+----+------+\n| id | name |\n+----+------+\n(0 rows)");
+    }
+
+    #[test]
+    fn formats_rows_for_other_programs() {
+        let columns = vec![
+            Column::new(ColumnName::new("id").unwrap(), DataType::Int),
+            Column::new(ColumnName::new("name").unwrap(), DataType::Text),
+        ];
+        let schema = Schema::try_new(columns.clone()).unwrap();
+        let rows = vec![
+            Row::from_values(vec![Value::Int(1), Value::Text("Smith, J".into())], &schema).unwrap(),
+            Row::from_values(vec![Value::Int(2), Value::Null], &schema).unwrap(),
+        ];
+
+        assert_eq!(super::rows(Mode::Csv, &columns, &rows), "id,name\n1,\"Smith, J\"\n2,");
+        assert_eq!(super::rows(Mode::Json, &columns, &rows), r#"[{"id":1,"name":"Smith, J"},{"id":2,"name":null}]"#);
+        assert_eq!(super::rows(Mode::Plain, &columns, &rows), "id\tname\n1\tSmith, J\n2\tNULL");
+        assert_eq!(Mode::parse("CSV"), Some(Mode::Csv));
+        assert_eq!(Mode::parse("xml"), None);
+    }
+}
@@ -0,0 +1,183 @@
+//! Reading REPL input, with line editing when available.
+//!
+//! With the `readline` feature (on by default), [`LineReader`] uses
+//! `rustyline`: arrow keys move through the line and the history, Ctrl-R
+//! searches the history, Tab completes names and keywords (see
+//! [`completion`](crate::interface::completion)), and the history is kept
+//! in a file between sessions. Without it, lines are read from stdin as
+//! they come.
+//!
+//! [`is_complete`] decides when the text typed so far forms whole
+//! statements, so that one statement can span several lines.
+
+use std::io::{self, BufRead, Write};
+use std::path::PathBuf;
+
+use crate::frontend::lexer::lexer;
+use crate::frontend::token::Token;
+use crate::interface::completion::Catalog;
+
+/// Name of the history file, in the user's home directory.
+pub const HISTORY_FILE: &str = ".mini_rust_sgbd_history";
+
+/// What reading a line produced.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Input {
+    /// A line, without its line break.
+    Line(String),
+    /// The user pressed Ctrl-C: the text typed so far should be dropped.
+    Interrupted,
+    /// The input is exhausted, or the user pressed Ctrl-D.
+    Eof,
+}
+
+/// Reads lines for the REPL.
+pub struct LineReader {
+    #[cfg(feature = "readline")]
+    editor: Option<rustyline::Editor<helper::SqlHelper, rustyline::history::DefaultHistory>>,
+    history: Option<PathBuf>,
+}
+
+impl LineReader {
+    /// Creates a reader, loading the history from [`HISTORY_FILE`] in the
+    /// home directory if there is one.
+    ///
+    /// Falls back to plain stdin if the terminal cannot be edited.
+    pub fn new() -> Self {
+        let history = std::env::var_os("HOME").map(|home| PathBuf::from(home).join(HISTORY_FILE));
+        #[cfg(feature = "readline")]
+        {
+            let mut editor = rustyline::Editor::new().ok();
+            if let Some(editor) = &mut editor {
+                editor.set_helper(Some(helper::SqlHelper::default()));
+                if let Some(path) = &history {
+                    // A missing history file is a first session
+                    let _ = editor.load_history(path);
+                }
+            }
+            LineReader { editor, history }
+        }
+        #[cfg(not(feature = "readline"))]
+        LineReader { history }
+    }
+
+    /// Shows `prompt` and reads one line.
+    pub fn read_line(&mut self, prompt: &str) -> Input {
+        #[cfg(feature = "readline")]
+        if let Some(editor) = &mut self.editor {
+            use rustyline::error::ReadlineError;
+            return match editor.readline(prompt) {
+                Ok(line) => Input::Line(line),
+                Err(ReadlineError::Interrupted) => Input::Interrupted,
+                Err(_) => Input::Eof,
+            };
+        }
+        print!("{}", prompt);
+        let _ = io::stdout().flush();
+        let mut line = String::new();
+        match io::stdin().lock().read_line(&mut line) {
+            Ok(0) | Err(_) => Input::Eof,
+            Ok(_) => Input::Line(line.trim_end_matches(['\n', '\r']).to_string()),
+        }
+    }
+
+    /// Adds `entry`, such as a whole statement, to the history.
+    pub fn add_history(&mut self, entry: &str) {
+        #[cfg(feature = "readline")]
+        if let Some(editor) = &mut self.editor {
+            let _ = editor.add_history_entry(entry);
+        }
+        #[cfg(not(feature = "readline"))]
+        let _ = entry;
+    }
+
+    /// Sets the names offered by Tab completion.
+    pub fn set_catalog(&mut self, catalog: Catalog) {
+        #[cfg(feature = "readline")]
+        if let Some(helper) = self.editor.as_mut().and_then(|editor| editor.helper_mut()) {
+            helper.catalog = catalog;
+        }
+        #[cfg(not(feature = "readline"))]
+        let _ = catalog;
+    }
+
+    /// Writes the history back to its file.
+    pub fn save_history(&mut self) {
+        #[cfg(feature = "readline")]
+        if let (Some(editor), Some(path)) = (&mut self.editor, &self.history) {
+            if let Err(e) = editor.save_history(path) {
+                eprintln!("Cannot save history to '{}': {}", path.display(), e);
+            }
+        }
+        #[cfg(not(feature = "readline"))]
+        let _ = &self.history;
+    }
+}
+
+impl Default for LineReader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// The `rustyline` helper completing SQL from a [`Catalog`].
+#[cfg(feature = "readline")]
+mod helper {
+    use rustyline::completion::Completer;
+    use rustyline::highlight::Highlighter;
+    use rustyline::hint::Hinter;
+    use rustyline::validate::Validator;
+    use rustyline::{Context, Helper};
+
+    use crate::interface::completion::{complete, Catalog};
+
+    #[derive(Default)]
+    pub struct SqlHelper {
+        pub catalog: Catalog,
+    }
+
+    impl Completer for SqlHelper {
+        type Candidate = String;
+
+        fn complete(&self, line: &str, pos: usize, _: &Context<'_>) -> rustyline::Result<(usize, Vec<String>)> {
+            Ok(complete(line, pos, &self.catalog))
+        }
+    }
+
+    impl Hinter for SqlHelper {
+        type Hint = String;
+    }
+
+    impl Highlighter for SqlHelper {}
+
+    impl Validator for SqlHelper {}
+
+    impl Helper for SqlHelper {}
+}
+
+/// Returns `true` if `sql` is ready to run: it ends with a `;` outside any
+/// string or comment, or it does not lex, so that the error is shown
+/// rather than waiting for more lines.
+pub fn is_complete(sql: &str) -> bool {
+    match lexer(sql) {
+        Ok(tokens) => matches!(tokens.iter().rev().nth(1), Some(Token::Symbol { value: ';', .. })),
+        Err(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn statements_end_at_a_semicolon() {
+        assert!(is_complete("SELECT * FROM t;"));
+        assert!(is_complete("CREATE TABLE t (\n  a Int\n);  -- done"));
+        assert!(!is_complete("CREATE TABLE t (\n  a Int,"));
+        assert!(!is_complete("INSERT INTO t VALUES ('a;"));
+        assert!(!is_complete("SELECT 1; SELECT"));
+        assert!(!is_complete("  "));
+        // A lexical error is shown at once
+        assert!(is_complete("SELECT #"));
+    }
+}
@@ -5,81 +5,486 @@
 //! (lexer → parser → executor) with a database instance and
 //! prints query results back to the user.
 //!
-//! The REPL continues until the user types `exit`, `quit`, or
-//! an EOF signal is received.
+//! SQL is read until a line ends with `;`, so a statement may span several
+//! lines, and may hold several statements separated by `;`. A line starting
+//! with `.` is a meta-command instead (see [`COMMANDS`], or `.help`):
+//! `.read file.sql` runs the statements of a script file, `.tables` and
+//! `.schema` describe the database, `.mode` switches how query results
+//! are shown (see [`format::Mode`]), and `.timing on` reports how long each
+//! statement took and how many rows it returned or changed.
+//!
+//! When the database lives in a directory, each change is logged there as
+//! it is committed, through a [`Store`]. `.save` or `CHECKPOINT` writes the
+//! whole database and empties the log, which also happens when the log
+//! grows large and when the REPL ends.
+//!
+//! Lines are read through a [`LineReader`], which provides line editing
+//! and history. The REPL continues until the user types `exit`, `quit`,
+//! or an EOF signal is received.
+//!
+//! [`run_batch`] handles input the same way without prompting, for scripts
+//! piped to the program or given on the command line, and stops at the
+//! first error.
 
-use std::io::{self, Write};
+use std::fs;
+use std::time::{Duration, Instant};
+use crate::core::db::Database;
+use crate::core::table::Table;
+use crate::core::types::TableName;
 use crate::frontend::lexer::lexer;
 use crate::frontend::parser::Parser;
-use crate::executor::{Executor, Output, Database};
+use crate::executor::executor::{Executor, Output};
+use crate::interface::diagnostic::render;
+use crate::interface::completion::Catalog;
+use crate::interface::format::{self, Mode};
+use crate::interface::input::{is_complete, Input, LineReader};
+use crate::frontend::ast::ASTNode;
+use crate::storage::store::Store;
 
 /// Runs the SQL REPL loop.
 ///
-/// Prints a prompt (`sql>`), reads user input, processes it
-/// into an AST, executes it against the provided [`Database`],
-/// and prints results or errors.
+/// Prints a prompt (see [`prompt`]), reads user input until it forms
+/// whole statements (see [`is_complete`]), processes it into an AST,
+/// executes it against the provided [`Database`], and prints results or
+/// errors.
 ///
 /// # Arguments
 /// - `db`: a mutable reference to the active database
+/// - `store`: the directory `db` was opened from (see [`Store::open`]),
+///   or `None` for a database kept in memory
+/// - `settings`: how statements are read, and how results are shown at
+///   first
 ///
 /// # Behavior
 /// - Empty lines are ignored
 /// - `exit` or `quit` terminates the loop
+/// - Lines starting with `.` run a meta-command (see [`run_command`])
+/// - Ctrl-C drops the statement being typed
 /// - Errors are printed but do not stop the REPL
-pub fn run_repl(db: &mut Database) {
-    let stdin = io::stdin();
-    let mut stdout = io::stdout();
+/// - At the end of input, an unfinished statement still runs
+pub fn run_repl(db: &mut Database, mut store: Option<&mut Store>, mut settings: Settings) {
+    let mut reader = LineReader::new();
+    reader.set_catalog(Catalog::of(db));
+    let mut buffer = String::new();
 
     loop {
-        // Prompt
-        print!("sql> ");
-        stdout.flush().unwrap();
+        let line = match reader.read_line(if buffer.is_empty() { prompt(db) } else { CONTINUATION_PROMPT }) {
+            Input::Line(line) => line,
+            Input::Interrupted => {
+                buffer.clear();
+                continue;
+            }
+            Input::Eof => break,
+        };
 
-        // Read user input
-        let mut line = String::new();
-        if stdin.read_line(&mut line).unwrap() == 0 {
-            break; // EOF
+        match step(&line, &mut buffer) {
+            Step::Exit => break,
+            Step::Wait => continue,
+            Step::Command(command) => {
+                reader.add_history(&command);
+                run_command(&command, db, store.as_deref_mut(), &mut settings);
+            }
+            Step::Sql(sql) => {
+                reader.add_history(sql.trim_end());
+                run_sql(&sql, db, store.as_deref_mut(), &settings);
+            }
         }
-        let line = line.trim();
+        reader.set_catalog(Catalog::of(db));
+    }
+    if !buffer.trim().is_empty() {
+        run_sql(&buffer, db, store, &settings);
+    }
+    reader.save_history();
+}
 
-        // Exit handling
-        if line.eq_ignore_ascii_case("exit") || line.eq_ignore_ascii_case("quit") {
+/// Runs `input` as the REPL would, but without prompts and stopping at the
+/// first error.
+///
+/// # Arguments
+/// - `input`: the lines to run, e.g. a script piped to the program
+/// - `db`, `store`, `settings`: as for [`run_repl`]
+///
+/// # Returns
+/// `true` if every statement and meta-command succeeded. The changes made
+/// before the error are logged to `store` either way.
+pub fn run_batch(input: &str, db: &mut Database, mut store: Option<&mut Store>, mut settings: Settings) -> bool {
+    let mut buffer = String::new();
+    let mut ok = true;
+    for line in input.lines() {
+        ok = match step(line, &mut buffer) {
+            Step::Exit => break,
+            Step::Wait => true,
+            Step::Command(command) => run_command(&command, db, store.as_deref_mut(), &mut settings),
+            Step::Sql(sql) => run_sql(&sql, db, store.as_deref_mut(), &settings),
+        };
+        if !ok {
             break;
         }
+    }
+    if ok && !buffer.trim().is_empty() {
+        ok = run_sql(&buffer, db, store, &settings);
+    }
+    ok
+}
+
+/// Session settings: the parser statements are read with, and those
+/// changed by meta-commands.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct Settings {
+    /// The parser statements are read with, and so the type names they
+    /// may use.
+    pub parser: Parser,
+    /// How query results are shown (`.mode`).
+    pub mode: Mode,
+    /// Whether each statement is followed by its time and row count
+    /// (`.timing`).
+    pub timing: bool,
+}
+
+/// What to do after reading a line.
+enum Step {
+    /// Leave, on `exit` or `quit`.
+    Exit,
+    /// Nothing yet: the line was empty or the statement goes on.
+    Wait,
+    /// Run a meta-command.
+    Command(String),
+    /// Run whole statements, the lines read so far.
+    Sql(String),
+}
+
+/// Decides what `line` calls for, adding it to `buffer`, the unfinished
+/// statement, when it continues one.
+///
+/// `exit`, `quit` and meta-commands are only recognized at the start of a
+/// statement, never in the middle of one.
+fn step(line: &str, buffer: &mut String) -> Step {
+    if buffer.is_empty() {
+        let line = line.trim();
+        if line.eq_ignore_ascii_case("exit") || line.eq_ignore_ascii_case("quit") {
+            return Step::Exit;
+        }
         if line.is_empty() {
-            continue;
+            return Step::Wait;
+        }
+        if line.starts_with('.') {
+            return Step::Command(line.to_string());
+        }
+    }
+    buffer.push_str(line);
+    buffer.push('\n');
+    if is_complete(buffer) {
+        Step::Sql(std::mem::take(buffer))
+    } else {
+        Step::Wait
+    }
+}
+
+/// Meta-commands as `(name, arguments, description)`, listed by `.help`.
+pub const COMMANDS: &[(&str, &str, &str)] = &[
+    (".help", "", "List the meta-commands"),
+    (".mode", "[table|csv|json|plain]", "Show or set how query results are shown"),
+    (".read", "FILE", "Run the SQL statements in FILE"),
+    (".save", "", "Write the database to its directory"),
+    (".schema", "[TABLE]", "Show the definition of TABLE, or of every table"),
+    (".tables", "", "List the tables"),
+    (".timing", "[on|off]", "Show or set whether statements are timed"),
+];
+
+/// Runs the meta-command `line`, such as `.schema users`.
+///
+/// The command name is the first word of `line`; the rest, trimmed, is
+/// its argument.
+///
+/// # Returns
+/// `false` if the command is unknown or failed, after printing why.
+fn run_command(line: &str, db: &mut Database, store: Option<&mut Store>, settings: &mut Settings) -> bool {
+    let (name, arg) = match line.split_once(char::is_whitespace) {
+        Some((name, arg)) => (name, arg.trim()),
+        None => (line, ""),
+    };
+    match name {
+        ".help" => print_help(),
+        ".mode" => return set_mode(arg, &mut settings.mode),
+        ".read" => return read_script(arg, db, store, settings),
+        ".save" => return save(db, store),
+        ".schema" => return print_schema(arg, db),
+        ".tables" => {
+            for table in db.table_names() {
+                println!("{}", table.as_str());
+            }
+        }
+        ".timing" => return set_timing(arg, &mut settings.timing),
+        _ => {
+            eprintln!("Unknown command '{}'; type .help for the list", name);
+            return false;
+        }
+    }
+    true
+}
+
+/// Prints [`COMMANDS`] with their arguments and descriptions.
+fn print_help() {
+    let usages: Vec<String> = COMMANDS
+        .iter()
+        .map(|(name, args, _)| if args.is_empty() { name.to_string() } else { format!("{} {}", name, args) })
+        .collect();
+    let width = usages.iter().map(String::len).max().unwrap_or(0);
+    for (usage, (_, _, description)) in usages.iter().zip(COMMANDS) {
+        println!("{:width$}  {}", usage, description, width = width);
+    }
+    println!("{:width$}  Leave the REPL", "exit, quit", width = width);
+}
+
+/// Prints the definition of the table or view `name`, or of every schema,
+/// table and view if `name` is empty. Returns `false` if there is no such
+/// table or view.
+fn print_schema(name: &str, db: &Database) -> bool {
+    if name.is_empty() {
+        for schema in db.catalog().schemas() {
+            println!("CREATE SCHEMA {};", schema);
+        }
+        for table in db.table_names() {
+            println!("{}", table_definition(db.table(table).expect("listed table exists")));
+        }
+        for (name, sql) in db.catalog().views() {
+            println!("CREATE VIEW {} AS {};", name, sql);
+        }
+        return true;
+    }
+    let Ok(name) = TableName::parse(name) else {
+        eprintln!("Table '{}' does not exist", name);
+        return false;
+    };
+    match (db.table(&name), db.view(&name)) {
+        (Some(table), _) => {
+            println!("{}", table_definition(table));
+            true
+        }
+        (None, Some(sql)) => {
+            println!("CREATE VIEW {} AS {};", name.as_str(), sql);
+            true
+        }
+        (None, None) => {
+            eprintln!("Table '{}' does not exist", name.as_str());
+            false
+        }
+    }
+}
+
+/// Returns the statements creating `table` and its indexes, e.g.
+/// `CREATE TABLE users (id Int PRIMARY KEY, name Text NOT NULL);`.
+fn table_definition(table: &Table) -> String {
+    let schema = table.schema();
+    let columns: Vec<String> = schema
+        .columns()
+        .iter()
+        .enumerate()
+        .map(|(i, column)| {
+            let mut definition = format!("{} {:?}", column.name.as_str(), column.dtype);
+            if schema.primary_key() == Some(i) {
+                definition.push_str(" PRIMARY KEY");
+            } else {
+                if !column.nullable {
+                    definition.push_str(" NOT NULL");
+                }
+                if column.unique {
+                    definition.push_str(" UNIQUE");
+                }
+            }
+            definition
+        })
+        .collect();
+    let mut lines = vec![format!("CREATE TABLE {} ({});", table.name().as_str(), columns.join(", "))];
+    for index in table.indexes() {
+        lines.push(format!(
+            "CREATE INDEX {} ON {} ({}) USING {};",
+            index.name(),
+            table.name().as_str(),
+            schema.columns()[index.column()].name.as_str(),
+            index.kind().name().to_uppercase()
+        ));
+    }
+    lines.join("\n")
+}
+
+/// Writes `db` to `store` as a new checkpoint (see [`Store::checkpoint`]).
+/// Returns `false` if it could not be saved.
+fn save(db: &Database, store: Option<&mut Store>) -> bool {
+    let Some(store) = store else {
+        eprintln!("No database directory: start with a directory to save to");
+        return false;
+    };
+    match store.checkpoint(db) {
+        Ok(()) => {
+            println!("Saved to '{}'", store.dir().display());
+            true
+        }
+        Err(e) => {
+            eprintln!("{}", render("", &e));
+            false
         }
+    }
+}
 
-        // Process pipeline: lexer → parser → executor
-        match lexer(line) {
-            Ok(tokens) => match Parser::parse(&tokens) {
-                Ok(ast) => match Executor::execute(ast, db) {
-                    Ok(out) => print_output(out),
-                    Err(e) => eprintln!("Execution error: {e}"),
-                },
-                Err(e) => eprintln!("Parse error: {e}"),
-            },
-            Err(e) => eprintln!("Lex error: {e}"),
+/// Switches to the mode called `name`, or prints the current mode and the
+/// available ones if `name` is empty. Returns `false` for an unknown mode.
+fn set_mode(name: &str, mode: &mut Mode) -> bool {
+    let names: Vec<&str> = Mode::ALL.iter().map(|m| m.name()).collect();
+    if name.is_empty() {
+        println!("Current mode: {} (available: {})", mode.name(), names.join(", "));
+        return true;
+    }
+    match Mode::parse(name) {
+        Some(new) => {
+            *mode = new;
+            true
+        }
+        None => {
+            eprintln!("Unknown mode '{}': expected one of {}", name, names.join(", "));
+            false
+        }
+    }
+}
+
+/// Turns timing on or off as `arg` says, or prints whether it is on if
+/// `arg` is empty. Returns `false` for anything but `on` or `off`.
+fn set_timing(arg: &str, timing: &mut bool) -> bool {
+    if arg.is_empty() {
+        println!("Timing is {}", if *timing { "on" } else { "off" });
+        return true;
+    }
+    if arg.eq_ignore_ascii_case("on") {
+        *timing = true;
+    } else if arg.eq_ignore_ascii_case("off") {
+        *timing = false;
+    } else {
+        eprintln!("Usage: .timing on|off");
+        return false;
+    }
+    true
+}
+
+/// Runs the SQL script in the file at `path`, as [`run_sql`] does.
+fn read_script(path: &str, db: &mut Database, store: Option<&mut Store>, settings: &Settings) -> bool {
+    if path.is_empty() {
+        eprintln!("Usage: .read <file.sql>");
+        return false;
+    }
+    match fs::read_to_string(path) {
+        Ok(sql) => run_sql(&sql, db, store, settings),
+        Err(e) => {
+            eprintln!("Cannot read '{}': {}", path, e);
+            false
+        }
+    }
+}
+
+/// Runs the statements in `sql` and prints the result of each.
+///
+/// Nothing runs if `sql` does not lex or parse; otherwise statements run in
+/// order until one fails, as in [`Executor::execute_script`]. With timing
+/// on, each result is followed by the time the statement took to run.
+///
+/// With a `store`, each statement is logged once it has run, `CHECKPOINT`
+/// saves the database to it, and `PRAGMA` changes its settings.
+///
+/// # Returns
+/// `true` if every statement succeeded.
+fn run_sql(sql: &str, db: &mut Database, mut store: Option<&mut Store>, settings: &Settings) -> bool {
+    // Process pipeline: lexer → parser → executor
+    let statements = match lexer(sql).and_then(|tokens| settings.parser.parse_script_spans(&tokens)) {
+        Ok(statements) => statements,
+        Err(e) => {
+            eprintln!("{}", render(sql, &e));
+            return false;
+        }
+    };
+    for (ast, span) in statements {
+        let start = Instant::now();
+        let result = match (&ast, store.as_deref_mut()) {
+            (ASTNode::Checkpoint, Some(store)) => store.checkpoint(db).map(|_| Output::None),
+            (ASTNode::Pragma(stmt), Some(store)) => store.pragma(stmt),
+            (_, Some(store)) => {
+                let source: String = match span {
+                    Some(span) => sql.chars().skip(span.start).take(span.len()).collect(),
+                    None => sql.to_string(),
+                };
+                Executor::execute(ast.clone(), db).and_then(|out| store.record(&ast, &source, db).map(|_| out))
+            }
+            (_, None) => Executor::execute(ast, db),
+        };
+        let elapsed = start.elapsed();
+        match result {
+            Ok(out) => {
+                let summary = timing_summary(&out, elapsed);
+                print_output(out, settings.mode);
+                if settings.timing {
+                    println!("{}", summary);
+                }
+            }
+            Err(e) => {
+                eprintln!("{}", render(sql, &e));
+                return false;
+            }
         }
     }
+    true
+}
+
+/// Returns the line `.timing on` prints after `out`, e.g.
+/// `Time: 1.204 ms, 3 rows returned`.
+fn timing_summary(out: &Output, elapsed: Duration) -> String {
+    let plural = |n: usize| if n == 1 { "row" } else { "rows" };
+    let time = format!("Time: {:.3} ms", elapsed.as_secs_f64() * 1000.0);
+    match out {
+        Output::Rows { rows, .. } => format!("{}, {} {} returned", time, rows.len(), plural(rows.len())),
+        Output::Affected(n) => format!("{}, {} {} affected", time, n, plural(*n)),
+        Output::Copied { rows, .. } => format!("{}, {} {} copied", time, rows, plural(*rows)),
+        Output::None | Output::Plan(_) => time,
+    }
+}
+
+/// Prompt shown while reading the rest of a statement.
+const CONTINUATION_PROMPT: &str = "  ...> ";
+
+/// Returns the prompt for the next statement: `sql> `, or `sql*> ` while a
+/// transaction is open.
+fn prompt(db: &Database) -> &'static str {
+    if db.in_transaction() {
+        "sql*> "
+    } else {
+        "sql> "
+    }
 }
 
 /// Prints query results to stdout.
 ///
 /// # Arguments
-/// - `out`: the executor result, either no output or a set of rows
+/// - `out`: the executor result: no output, an affected-row count, or a set of rows
+/// - `mode`: how to show a set of rows
 ///
 /// # Behavior
 /// - For `Output::None`, prints `"OK"`
-/// - For `Output::Rows`, prints each row with values separated by `|`
-fn print_output(out: Output) {
+/// - For `Output::Affected`, prints the number of affected rows
+/// - For `Output::Rows`, prints the rows in `mode` (see [`format::rows`])
+/// - For `Output::Copied`, prints the number of rows copied, then each
+///   rejected line with its error
+/// - For `Output::Plan`, prints each step of the query plan
+fn print_output(out: Output, mode: Mode) {
     match out {
         Output::None => println!("OK"),
-        Output::Rows(rows) => {
-            for row in rows {
-                let values: Vec<String> =
-                    row.values.into_iter().map(|v| format!("{:?}", v)).collect();
-                println!("{}", values.join(" | "));
+        Output::Affected(n) => println!("{} row(s) affected", n),
+        Output::Rows { columns, rows } => println!("{}", format::rows(mode, &columns, &rows)),
+        Output::Copied { rows, failed } => {
+            println!("{} row(s) copied", rows);
+            for (line, error) in failed {
+                eprintln!("line {}: {}", line, error);
+            }
+        }
+        Output::Plan(steps) => {
+            for step in steps {
+                println!("{}", step);
             }
         }
     }
@@ -88,25 +493,438 @@ fn print_output(out: Output) {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::frontend::ast::{ASTNode, CreateTableStmt, TableName, Column, ColumnName, DataType};
+    use crate::core::error::SqlError;
+    use crate::core::row::Row;
+    use crate::core::types::{DataType, Value, TableName};
+    use crate::storage::wal::SyncMode;
+    use crate::storage::recovery::recover;
+    use crate::testing::TempDir;
+
+    /// Runs one statement through the same lexer → parser → executor pipeline as the REPL.
+    fn run(db: &mut Database, sql: &str) -> Output {
+        let tokens = lexer(sql).unwrap();
+        let ast = Parser::default().parse(&tokens).unwrap();
+        Executor::execute(ast, db).unwrap()
+    }
 
     #[test]
     fn create_table_pipeline_executes_ok() {
         let mut db = Database::new();
 
-        // Simulate "CREATE TABLE t (id INT)"
-        let stmt = CreateTableStmt {
-            name: TableName("t".into()),
-            columns: vec![Column {
-                name: ColumnName("id".into()),
-                ty: DataType::Int,
-            }],
-        };
-        let ast = ASTNode::CreateTable(stmt);
-        let out = Executor::execute(ast, &mut db).unwrap();
+        let out = run(&mut db, "CREATE TABLE t (id INT)");
 
         assert_eq!(out, Output::None);
-        assert!(db.get_table("t").is_some());
+        assert!(db.table(&TableName::new("t").unwrap()).is_some());
+    }
+
+    #[test]
+    fn select_pipeline_returns_rows() {
+        let mut db = Database::new();
+        run(&mut db, "CREATE TABLE users (id INT, name TEXT);");
+        run(&mut db, "INSERT INTO users VALUES (1, 'Ann'), (2, 'Bob');");
+
+        match run(&mut db, "SELECT * FROM users;") {
+            Output::Rows { rows, .. } => assert_eq!(rows.len(), 2),
+            other => panic!("Expected rows, got {:?}", other),
+        }
+        match run(&mut db, "SELECT name FROM users WHERE id = 2") {
+            Output::Rows { rows, .. } => assert_eq!(rows[0].values(), &[Value::Text("Bob".to_string())]),
+            other => panic!("Expected rows, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn select_pipeline_evaluates_expressions() {
+        let mut db = Database::new();
+        run(&mut db, "CREATE TABLE t (id INT, name TEXT, price FLOAT)");
+        run(&mut db, "INSERT INTO t VALUES (1, 'Ann', 2.5), (2, 'Bob', NULL)");
+
+        match run(&mut db, "SELECT id * 2, name || '!', price * id + 1 FROM t") {
+            Output::Rows { rows, .. } => {
+                assert_eq!(rows[0].values(), &[Value::Int(2), Value::Text("Ann!".to_string()), Value::Float(3.5)]);
+                assert_eq!(rows[1].values(), &[Value::Int(4), Value::Text("Bob!".to_string()), Value::Null]);
+            }
+            other => panic!("Expected rows, got {:?}", other),
+        }
+        let err = Parser::default().parse(&lexer("SELECT name - 1 FROM t").unwrap())
+            .and_then(|ast| Executor::execute(ast, &mut db))
+            .unwrap_err();
+        assert!(err.message().contains("Cannot apply '-'"));
+    }
+
+    #[test]
+    fn aliases_name_outputs_and_qualify_tables() {
+        let mut db = Database::new();
+        run(&mut db, "CREATE TABLE emp (id INT, name TEXT, boss INT)");
+        run(&mut db, "INSERT INTO emp VALUES (1, 'Ann', NULL), (2, 'Bob', 1), (3, 'Cid', 1), (4, 'Dee', 2)");
+
+        let texts = |out| match out {
+            Output::Rows { rows, .. } => rows.iter().map(|r: &Row| r.values().iter().map(|v| v.to_string()).collect::<Vec<_>>().join(" ")).collect::<Vec<_>>(),
+            other => panic!("Expected rows, got {:?}", other),
+        };
+
+        // Self-join: the aliases tell the two copies of the table apart
+        assert_eq!(
+            texts(run(&mut db, "SELECT e.name AS worker, b.name boss_name FROM emp AS e JOIN emp b ON e.boss = b.id ORDER BY worker DESC")),
+            vec!["Dee Bob", "Cid Ann", "Bob Ann"]
+        );
+        assert_eq!(
+            texts(run(&mut db, "SELECT id * 10 AS score FROM emp ORDER BY score DESC LIMIT 2")),
+            vec!["40", "30"]
+        );
+        assert_eq!(
+            texts(run(&mut db, "SELECT boss, COUNT(*) AS n FROM emp WHERE boss IS NOT NULL GROUP BY boss ORDER BY n DESC")),
+            vec!["1 2", "2 1"]
+        );
+
+        let err = Parser::default().parse(&lexer("SELECT emp.name FROM emp AS e").unwrap())
+            .and_then(|ast| Executor::execute(ast, &mut db))
+            .unwrap_err();
+        assert!(err.message().contains("Unknown table 'emp'"));
+    }
+
+    #[test]
+    fn cross_join_pipeline_resolves_qualified_names() {
+        let mut db = Database::new();
+        run(&mut db, "CREATE TABLE a (id INT, x TEXT)");
+        run(&mut db, "CREATE TABLE b (id INT, y TEXT)");
+        run(&mut db, "INSERT INTO a VALUES (1, 'p'), (2, 'q')");
+        run(&mut db, "INSERT INTO b VALUES (2, 'r'), (3, 's'), (1, 't')");
+
+        let rows = |out| match out {
+            Output::Rows { rows, .. } => rows.iter().map(|r: &Row| r.values().iter().map(|v| v.to_string()).collect::<Vec<_>>().join(" ")).collect::<Vec<_>>(),
+            other => panic!("Expected rows, got {:?}", other),
+        };
+
+        assert_eq!(rows(run(&mut db, "SELECT * FROM a, b")).len(), 6);
+        assert_eq!(
+            rows(run(&mut db, "SELECT a.id, x, y FROM a CROSS JOIN b WHERE a.id = b.id ORDER BY y")),
+            vec!["2 q r", "1 p t"]
+        );
+        let err = Parser::default().parse(&lexer("SELECT id FROM a, b").unwrap())
+            .and_then(|ast| Executor::execute(ast, &mut db))
+            .unwrap_err();
+        assert!(err.message().contains("ambiguous"));
+    }
+
+    #[test]
+    fn where_pipeline_combines_conditions() {
+        let mut db = Database::new();
+        run(&mut db, "CREATE TABLE t (a INT, b TEXT)");
+        run(&mut db, "INSERT INTO t VALUES (1, 'x'), (2, 'y'), (3, 'x'), (4, NULL)");
+
+        let ids = |out| match out {
+            Output::Rows { rows, .. } => rows.iter().map(|r: &Row| r.values()[0].to_string()).collect::<Vec<_>>(),
+            other => panic!("Expected rows, got {:?}", other),
+        };
+
+        assert_eq!(ids(run(&mut db, "SELECT a FROM t WHERE a >= 2 AND NOT (b = 'y' OR b IS NULL)")), vec!["3"]);
+        assert_eq!(ids(run(&mut db, "SELECT a FROM t WHERE a <= 1 OR b <> 'x'")), vec!["1", "2"]);
+    }
+
+    #[test]
+    fn like_pipeline_filters_text() {
+        let mut db = Database::new();
+        run(&mut db, "CREATE TABLE users (id INT, name TEXT)");
+        run(&mut db, "INSERT INTO users VALUES (1, 'Alice'), (2, 'Bob'), (3, 'alan'), (4, NULL)");
+
+        let ids = |out| match out {
+            Output::Rows { rows, .. } => rows.iter().map(|r: &Row| r.values()[0].to_string()).collect::<Vec<_>>(),
+            other => panic!("Expected rows, got {:?}", other),
+        };
+
+        assert_eq!(ids(run(&mut db, "SELECT id FROM users WHERE name LIKE 'A%'")), vec!["1"]);
+        assert_eq!(ids(run(&mut db, "SELECT id FROM users WHERE name LIKE '_l%'")), vec!["1", "3"]);
+        assert_eq!(ids(run(&mut db, "SELECT id FROM users WHERE name NOT LIKE '%l%'")), vec!["2"]);
+
+        let err = Parser::default().parse(&lexer("SELECT id FROM users WHERE id LIKE '1%'").unwrap())
+            .and_then(|ast| Executor::execute(ast, &mut db))
+            .unwrap_err();
+        assert!(err.message().contains("LIKE requires a Text column"));
+    }
+
+    #[test]
+    fn in_and_between_pipeline_filters_rows() {
+        let mut db = Database::new();
+        run(&mut db, "CREATE TABLE events (id INT, day DATE)");
+        run(&mut db, "INSERT INTO events VALUES (1, '2024-01-05'), (2, '2024-02-10'), (3, '2024-03-15')");
+
+        let ids = |out| match out {
+            Output::Rows { rows, .. } => rows.iter().map(|r: &Row| r.values()[0].to_string()).collect::<Vec<_>>(),
+            other => panic!("Expected rows, got {:?}", other),
+        };
+
+        assert_eq!(ids(run(&mut db, "SELECT id FROM events WHERE day BETWEEN '2024-02-01' AND '2024-03-31'")), vec!["2", "3"]);
+        assert_eq!(ids(run(&mut db, "SELECT id FROM events WHERE id NOT IN (1, 3) OR day < '2024-01-10'")), vec!["1", "2"]);
+
+        let err = Parser::default().parse(&lexer("SELECT id FROM events WHERE id IN (1, 'two')").unwrap())
+            .and_then(|ast| Executor::execute(ast, &mut db))
+            .unwrap_err();
+        assert!(err.message().contains("Type mismatch"));
+    }
+
+    #[test]
+    fn primary_key_pipeline_rejects_duplicates() {
+        let mut db = Database::new();
+        run(&mut db, "CREATE TABLE users (id INT PRIMARY KEY, name TEXT)");
+        run(&mut db, "INSERT INTO users VALUES (1, 'Ann'), (2, 'Bob')");
+
+        let exec = |db: &mut Database, sql: &str| {
+            Parser::default().parse(&lexer(sql).unwrap()).and_then(|ast| Executor::execute(ast, db))
+        };
+        let err = exec(&mut db, "INSERT INTO users VALUES (3, 'Cid'), (1, 'Dee')").unwrap_err();
+        assert!(err.message().contains("PRIMARY KEY constraint violated"));
+        assert!(exec(&mut db, "INSERT INTO users VALUES (NULL, 'Eve')").is_err());
+        assert!(exec(&mut db, "UPDATE users SET id = 1 WHERE id = 2").is_err());
+
+        run(&mut db, "DELETE FROM users WHERE id = 1");
+        run(&mut db, "INSERT INTO users VALUES (1, 'Dee')");
+        match run(&mut db, "SELECT name FROM users WHERE id = 1") {
+            Output::Rows { rows, .. } => assert_eq!(rows[0].values(), &[Value::Text("Dee".to_string())]),
+            other => panic!("Expected rows, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn column_constraints_pipeline_reports_violations() {
+        let mut db = Database::new();
+        run(&mut db, "CREATE TABLE users (id INT NOT NULL, email TEXT UNIQUE)");
+        run(&mut db, "INSERT INTO users VALUES (1, 'a@x'), (2, NULL), (3, NULL)");
+
+        let exec = |db: &mut Database, sql: &str| {
+            Parser::default().parse(&lexer(sql).unwrap()).and_then(|ast| Executor::execute(ast, db))
+        };
+        for sql in [
+            "INSERT INTO users VALUES (NULL, 'b@x')",
+            "INSERT INTO users VALUES (4, 'a@x')",
+            "UPDATE users SET email = 'c@x' WHERE email IS NULL",
+        ] {
+            assert!(matches!(exec(&mut db, sql), Err(SqlError::Constraint { .. })), "{}", sql);
+        }
+        run(&mut db, "UPDATE users SET email = 'b@x' WHERE id = 2");
+    }
+
+    #[test]
+    fn transaction_pipeline_rolls_back_changes() {
+        let mut db = Database::new();
+        run(&mut db, "CREATE TABLE t (id INT PRIMARY KEY)");
+        run(&mut db, "INSERT INTO t VALUES (1)");
+        assert_eq!(prompt(&db), "sql> ");
+
+        run(&mut db, "BEGIN;");
+        assert_eq!(prompt(&db), "sql*> ");
+        run(&mut db, "INSERT INTO t VALUES (2)");
+        run(&mut db, "DELETE FROM t WHERE id = 1");
+        run(&mut db, "ROLLBACK;");
+        assert_eq!(prompt(&db), "sql> ");
+
+        let t = TableName::new("t").unwrap();
+        let ids = |db: &Database| db.table(&t).unwrap().rows().iter().map(|r| r.values()[0].clone()).collect::<Vec<_>>();
+        assert_eq!(ids(&db), vec![Value::Int(1)]);
+        // The primary key index is restored too
+        assert!(Parser::default().parse(&lexer("INSERT INTO t VALUES (1)").unwrap()).and_then(|ast| Executor::execute(ast, &mut db)).is_err());
+
+        run(&mut db, "BEGIN TRANSACTION");
+        run(&mut db, "INSERT INTO t VALUES (2)");
+        run(&mut db, "COMMIT");
+        assert_eq!(ids(&db), vec![Value::Int(1), Value::Int(2)]);
+    }
+
+    #[test]
+    fn copy_from_csv_loads_rows_and_reports_failures() {
+        let tmp = TempDir::new("copy");
+        let path = tmp.join("copy.csv");
+        std::fs::write(&path, "name,id\n\"Smith, Ann\",1\nBob,x\nCid,1\n,3\n").unwrap();
+
+        let mut db = Database::new();
+        run(&mut db, "CREATE TABLE users (id INT PRIMARY KEY, name TEXT)");
+        let out = run(&mut db, &format!("COPY users FROM '{}'", path.display()));
+        match out {
+            Output::Copied { rows, failed } => {
+                assert_eq!(rows, 2);
+                assert_eq!(failed.iter().map(|(line, _)| *line).collect::<Vec<_>>(), vec![3, 4]);
+                assert!(failed[0].1.contains("Invalid Int value 'x'"));
+                assert!(failed[1].1.contains("PRIMARY KEY"));
+            }
+            other => panic!("Expected copy result, got {:?}", other),
+        }
+        let rows = db.table(&TableName::new("users").unwrap()).unwrap().rows().clone();
+        assert_eq!(rows[0].values(), &[Value::Int(1), Value::Text("Smith, Ann".to_string())]);
+        assert_eq!(rows[1].values(), &[Value::Int(3), Value::Null]);
+    }
+
+    #[test]
+    fn copy_to_csv_round_trips() {
+        let tmp = TempDir::new("export");
+        let path = tmp.join("export.csv");
+        let mut db = Database::new();
+        run(&mut db, "CREATE TABLE t (id INT, note TEXT, ok BOOL)");
+        run(&mut db, "INSERT INTO t VALUES (1, 'a, \"b\"', TRUE), (2, '', NULL), (3, NULL, FALSE)");
+
+        let out = run(&mut db, &format!("COPY (SELECT id AS n, note FROM t WHERE id < 3) TO '{}'", path.display()));
+        assert_eq!(out, Output::Copied { rows: 2, failed: Vec::new() });
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "n,note\n1,\"a, \"\"b\"\"\"\n2,\"\"\n");
+
+        run(&mut db, &format!("COPY t TO '{}'", path.display()));
+        run(&mut db, "CREATE TABLE u (id INT, note TEXT, ok BOOL)");
+        run(&mut db, &format!("COPY u FROM '{}'", path.display()));
+        let rows = |name: &str| db.table(&TableName::new(name).unwrap()).unwrap().rows().clone();
+        assert_eq!(rows("u"), rows("t"));
+    }
+
+    #[test]
+    fn explain_shows_index_use() {
+        let mut db = Database::new();
+        run(&mut db, "CREATE TABLE users (id INT, name TEXT)");
+        run(&mut db, "INSERT INTO users VALUES (1, 'Ann'), (2, 'Bob'), (3, 'Ann')");
+        let explain = "EXPLAIN SELECT id FROM users WHERE name = 'Ann'";
+        assert_eq!(run(&mut db, explain), Output::Plan(vec!["Scan users".to_string()]));
+
+        run(&mut db, "CREATE INDEX users_name ON users (name)");
+        assert_eq!(
+            run(&mut db, explain),
+            Output::Plan(vec!["Index lookup on users using users_name (name = 'Ann')".to_string()])
+        );
+        match run(&mut db, "SELECT id FROM users WHERE name = 'Ann' AND id > 1") {
+            Output::Rows { rows, .. } => assert_eq!(rows.iter().map(|r| r.values()[0].clone()).collect::<Vec<_>>(), vec![Value::Int(3)]),
+            other => panic!("Expected rows, got {:?}", other),
+        }
+
+        // A WHERE condition on a joined table filters it through its index
+        run(&mut db, "CREATE TABLE orders (user_id INT, amount INT)");
+        assert_eq!(
+            run(&mut db, "EXPLAIN SELECT amount FROM orders o JOIN users u ON u.id = o.user_id WHERE u.name = 'Bob'"),
+            Output::Plan(vec![
+                "Scan orders".to_string(),
+                "Nested loop join".to_string(),
+                "  Index lookup on users using users_name (name = 'Bob')".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn read_runs_a_script_file_until_an_error() {
+        let tmp = TempDir::new("script");
+        let path = tmp.join("script.sql");
+        std::fs::write(
+            &path,
+            "-- schema\nCREATE TABLE t (id INT);\nINSERT INTO t VALUES (1), (2);\n\
+             INSERT INTO t VALUES ('x');\nINSERT INTO t VALUES (3);\n",
+        )
+        .unwrap();
+        let mut db = Database::new();
+        read_script(path.to_str().unwrap(), &mut db, None, &Settings::default());
+
+        // The third statement fails, so the fourth does not run
+        let table = db.table(&TableName::new("t").unwrap()).unwrap();
+        assert_eq!(table.rows().len(), 2);
+    }
+
+    #[test]
+    fn analyze_lets_statistics_pick_the_access_path() {
+        let mut db = Database::new();
+        run(&mut db, "CREATE TABLE users (id INT, name TEXT)");
+        run(&mut db, "INSERT INTO users VALUES (1, 'Ann'), (2, 'Bob'), (3, 'Ann')");
+        run(&mut db, "CREATE INDEX users_name ON users (name)");
+        let explain = "EXPLAIN SELECT id FROM users WHERE name = 'Ann'";
+
+        // Two rows out of three are cheaper to scan
+        run(&mut db, "ANALYZE users");
+        assert_eq!(run(&mut db, explain), Output::Plan(vec!["Scan users".to_string()]));
+        assert!(Executor::execute(Parser::default().parse(&lexer("ANALYZE nope").unwrap()).unwrap(), &mut db).is_err());
+    }
+
+    #[test]
+    fn schema_shows_the_statements_creating_a_table() {
+        let mut db = Database::new();
+        run(&mut db, "CREATE TABLE users (id INT PRIMARY KEY, name TEXT NOT NULL, email TEXT UNIQUE, born DATE)");
+        run(&mut db, "CREATE INDEX users_name ON users (name) USING HASH");
+        let table = db.table(&TableName::new("users").unwrap()).unwrap();
+
+        assert_eq!(
+            table_definition(table),
+            "CREATE TABLE users (id Int PRIMARY KEY, name Text NOT NULL, email Text UNIQUE, born Date);\n\
+             CREATE INDEX users_name ON users (name) USING HASH;"
+        );
+        // The definition is valid SQL that recreates the table
+        let mut copy = Database::new();
+        run_sql(&table_definition(table), &mut copy, None, &Settings::default());
+        assert_eq!(copy.table(table.name()).unwrap().schema().columns(), table.schema().columns());
+    }
+
+    #[test]
+    fn batch_stops_at_the_first_error_and_saves() {
+        let tmp = TempDir::new("batch");
+        let dir = tmp.path();
+        let script = "CREATE TABLE t (\n  n INT\n);\n.mode csv\nINSERT INTO t VALUES (1);\nINSERT INTO nope VALUES (2);\nINSERT INTO t VALUES (3);\n";
+
+        let (mut db, mut store) = Store::open(dir).unwrap();
+        assert!(!run_batch(script, &mut db, Some(&mut store), Settings::default()));
+        // Changes made before the error are saved; the rest never ran
+        let saved = recover(dir).unwrap();
+        assert_eq!(saved.table(&TableName::new("t").unwrap()).unwrap().rows().len(), 1);
+
+        let mut db = Database::new();
+        assert!(run_batch("CREATE TABLE u (n INT); SELECT n FROM u", &mut db, None, Settings::default()));
+        assert!(!run_batch(".nope", &mut db, None, Settings::default()));
+    }
+
+    #[test]
+    fn changes_are_logged_as_they_run() {
+        let tmp = TempDir::new("logged");
+        let dir = tmp.path();
+        let settings = Settings::default();
+        let count = |db: &Database| db.table(&TableName::new("t").unwrap()).unwrap().rows().len();
+
+        let (mut db, mut store) = Store::open(dir).unwrap();
+        assert!(run_sql("CREATE TABLE t (n INT);\nINSERT INTO t VALUES (1); SELECT n FROM t", &mut db, Some(&mut store), &settings));
+        assert!(run_sql("BEGIN; INSERT INTO t VALUES (2)", &mut db, Some(&mut store), &settings));
+        // Never saved: the log alone holds what was committed
+        drop(store);
+        let (mut db, mut store) = Store::open(dir).unwrap();
+        assert_eq!(count(&db), 1);
+        assert!(!dir.join("CURRENT").exists());
+
+        assert!(run_sql("INSERT INTO t VALUES (2); CHECKPOINT", &mut db, Some(&mut store), &settings));
+        assert_eq!(fs::read_to_string(dir.join("CURRENT")).unwrap(), "1");
+        assert_eq!(fs::metadata(dir.join("wal-1.log")).unwrap().len(), 0);
+        assert_eq!(count(&recover(dir).unwrap()), 2);
+        assert!(!run_sql("BEGIN; CHECKPOINT", &mut db, Some(&mut store), &settings));
+        assert!(!run_sql("CHECKPOINT", &mut Database::new(), None, &settings));
+        assert!(run_sql("PRAGMA synchronous = normal", &mut db, Some(&mut store), &settings));
+        assert_eq!(store.sync_mode(), SyncMode::Normal);
+        assert!(!run_sql("PRAGMA synchronous = normal", &mut Database::new(), None, &settings));
+    }
+
+    #[test]
+    fn statements_are_read_with_the_settings_parser() {
+        let tmp = TempDir::new("repl_aliases");
+        let dir = tmp.path();
+        let mut parser = Parser::default();
+        parser.register_alias("NUMBER", DataType::Float).unwrap();
+        let settings = Settings { parser: parser.clone(), ..Settings::default() };
+
+        assert!(!run_sql("CREATE TABLE t (x NUMBER)", &mut Database::new(), None, &Settings::default()));
+        let (mut db, mut store) = Store::open_with(dir, &parser).unwrap();
+        assert!(run_sql("CREATE TABLE t (x NUMBER); INSERT INTO t VALUES (1.5)", &mut db, Some(&mut store), &settings));
+        drop(store);
+        // The log replays with the same aliases
+        let (db, _store) = Store::open_with(dir, &parser).unwrap();
+        assert_eq!(db.table(&TableName::new("t").unwrap()).unwrap().schema().columns()[0].dtype, DataType::Float);
+    }
+
+    #[test]
+    fn timing_reports_rows_returned_or_affected() {
+        let mut settings = Settings::default();
+        assert!(set_timing("ON", &mut settings.timing) && settings.timing);
+        assert!(!set_timing("maybe", &mut settings.timing) && settings.timing);
+        assert!(set_timing("off", &mut settings.timing) && !settings.timing);
+
+        let elapsed = Duration::from_micros(1500);
+        assert_eq!(timing_summary(&Output::Affected(1), elapsed), "Time: 1.500 ms, 1 row affected");
+        let rows = Output::Rows { columns: Vec::new(), rows: Vec::new() };
+        assert_eq!(timing_summary(&rows, elapsed), "Time: 1.500 ms, 0 rows returned");
+        assert_eq!(timing_summary(&Output::None, elapsed), "Time: 1.500 ms");
     }
 
     #[test]
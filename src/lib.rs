@@ -1,11 +1,17 @@
 
 pub mod core {
+    pub mod catalog;
     pub mod types;
     pub mod schema;
     pub mod row;
     pub mod table;
     pub mod db;
     pub mod error;
+    pub mod span;
+    pub mod index;
+    pub mod sketch;
+    pub mod stats;
+    pub mod datetime;
 }
 
 pub mod frontend {
@@ -13,4 +19,56 @@ pub mod frontend {
     pub mod lexer;
     pub mod parser;
     pub mod token;
-}
\ No newline at end of file
+}
+
+pub mod planner {
+    pub mod logical;
+    pub mod optimizer;
+    #[allow(clippy::module_inception)]
+    pub mod planner;
+}
+
+pub mod executor {
+    #[allow(clippy::module_inception)]
+    pub mod executor;
+    pub mod eval;
+    pub mod information_schema;
+    pub mod lock;
+    pub mod shared;
+    pub mod operator;
+    pub mod plan;
+}
+
+pub mod interface {
+    pub mod completion;
+    pub mod diagnostic;
+    pub mod format;
+    pub mod input;
+    pub mod repl;
+}
+
+pub mod server {
+    pub mod pgwire;
+    #[allow(clippy::module_inception)]
+    pub mod server;
+}
+
+pub mod storage {
+    pub mod binary;
+    pub mod buffer;
+    pub mod compression;
+    pub mod csv;
+    pub mod heap;
+    pub mod json;
+    pub mod page;
+    #[cfg(feature = "parquet")]
+    pub mod parquet;
+    pub mod recovery;
+    #[allow(clippy::module_inception)]
+    pub mod storage;
+    pub mod store;
+    pub mod wal;
+}
+
+#[cfg(test)]
+mod testing;
@@ -1,3 +1,205 @@
-fn main() {
-    println!("Hello, world!");
-}
\ No newline at end of file
+//! Command-line entry point: `mini_rust_sgbd [--format MODE] [-c SQL] [DIR]`,
+//! or `mini_rust_sgbd serve [--port PORT] [--protocol line|postgres] [DIR]`.
+//! `--sync` and `--compress` apply to both; `--format` and `-c` cannot be
+//! given to `serve`, nor `--sync off`, since the server runs until it is
+//! killed and would never write the commits that mode holds back.
+//!
+//! With a directory, the database stored there is opened (and the
+//! directory created if needed), each change is logged there as it is
+//! committed, and the database is saved back when the program ends. The
+//! directory stays locked meanwhile: a second process opening it fails with
+//! a "database is locked" error.
+//! Without one, the database only lives in memory. `--format` picks how
+//! query results are shown: `table` (the default), `csv`, `json` or
+//! `plain`. `--sync` sets how soon committed changes reach the disk (see
+//! [`SyncMode`](mini_rust_sgbd::storage::wal::SyncMode)): `full` (the
+//! default), `normal` or `off`. `--compress gzip` or `--compress zstd`
+//! compresses the saved table files, in builds with that feature.
+//!
+//! The interactive REPL runs when stdin is a terminal. `-c SQL` runs the
+//! given statements instead, and input piped to stdin runs as a script;
+//! both stop at the first error and exit with a failure status.
+//!
+//! `serve` makes the database available to network clients instead (see
+//! [`server`](mini_rust_sgbd::server::server)), on `127.0.0.1` and port
+//! 5433 unless `--port` says otherwise. Clients send lines of SQL, or speak
+//! the PostgreSQL protocol with `--protocol postgres`, so that `psql` can
+//! connect.
+
+use std::io::{self, IsTerminal, Read};
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use mini_rust_sgbd::core::db::Database;
+use mini_rust_sgbd::executor::shared::SharedDatabase;
+use mini_rust_sgbd::frontend::parser::Parser;
+use mini_rust_sgbd::interface::format::Mode;
+use mini_rust_sgbd::interface::repl::{run_batch, run_repl, Settings};
+use mini_rust_sgbd::server::server::{Protocol, Server, DEFAULT_PORT};
+use mini_rust_sgbd::storage::compression::Compression;
+use mini_rust_sgbd::storage::store::Store;
+use mini_rust_sgbd::storage::wal::SyncMode;
+
+/// Usage line printed on bad arguments.
+const USAGE: &str = "Usage: mini_rust_sgbd [--format table|csv|json|plain] [--sync full|normal|off] [--compress none|gzip|zstd] [-c SQL] [DIR]\n       mini_rust_sgbd serve [--port PORT] [--protocol line|postgres] [--sync full|normal] [--compress none|gzip|zstd] [DIR]";
+
+/// The parsed command line.
+struct Args {
+    dir: Option<PathBuf>,
+    mode: Mode,
+    sync: SyncMode,
+    compression: Compression,
+    /// Statements given with `-c`
+    command: Option<String>,
+    /// The port to serve on, with `serve`
+    port: Option<u16>,
+    protocol: Protocol,
+}
+
+/// Parses the arguments following the program name.
+fn parse_args(args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut parsed = Args {
+        dir: None,
+        mode: Mode::default(),
+        sync: SyncMode::default(),
+        compression: Compression::default(),
+        command: None,
+        port: None,
+        protocol: Protocol::default(),
+    };
+    let mut args = args.peekable();
+    let serving = args.next_if(|arg| arg == "serve").is_some();
+    if serving {
+        parsed.port = Some(DEFAULT_PORT);
+    }
+    // Output and batch options mean nothing to a server, and server options
+    // nothing without one
+    let check = |option: &str, for_server: bool| match (for_server, serving) {
+        (true, false) => Err(format!("{} can only be used with serve", option)),
+        (false, true) => Err(format!("{} cannot be used with serve", option)),
+        _ => Ok(()),
+    };
+    while let Some(arg) = args.next() {
+        if arg == "-c" {
+            check("-c", false)?;
+            parsed.command = Some(args.next().ok_or("-c needs SQL to run")?);
+        } else if let Some(name) = option_value(&arg, "--format", &mut args)? {
+            check("--format", false)?;
+            parsed.mode = Mode::parse(&name).ok_or(format!("Unknown format '{}'", name))?;
+        } else if let Some(name) = option_value(&arg, "--sync", &mut args)? {
+            parsed.sync = SyncMode::parse(&name).ok_or(format!("Unknown sync mode '{}'", name))?;
+        } else if let Some(name) = option_value(&arg, "--compress", &mut args)? {
+            parsed.compression = match Compression::parse(&name) {
+                Some(compression) if compression.is_available() => compression,
+                Some(_) => return Err(format!("{} compression needs a build with the '{}' feature", name, name)),
+                None => return Err(format!("Unknown compression '{}'", name)),
+            };
+        } else if let Some(port) = option_value(&arg, "--port", &mut args)? {
+            check("--port", true)?;
+            parsed.port = Some(port.parse().map_err(|_| format!("Invalid port '{}'", port))?);
+        } else if let Some(name) = option_value(&arg, "--protocol", &mut args)? {
+            check("--protocol", true)?;
+            parsed.protocol = Protocol::parse(&name).ok_or(format!("Unknown protocol '{}'", name))?;
+        } else if arg.starts_with('-') {
+            return Err(format!("Unknown option '{}'", arg));
+        } else if parsed.dir.is_none() {
+            parsed.dir = Some(PathBuf::from(arg));
+        } else {
+            return Err("Only one database directory can be given".to_string());
+        }
+    }
+    if serving && parsed.sync == SyncMode::Off {
+        return Err("--sync off cannot be used with serve: commits would be lost when the server is stopped".to_string());
+    }
+    Ok(parsed)
+}
+
+/// Returns the value of the option `name` if `arg` is that option, given
+/// as `name=value` or as `name` followed by the value in `args`.
+fn option_value(arg: &str, name: &str, args: &mut impl Iterator<Item = String>) -> Result<Option<String>, String> {
+    match arg.strip_prefix(name) {
+        Some("") => args.next().map(Some).ok_or(format!("{} needs a value", name)),
+        Some(value) if value.starts_with('=') => Ok(Some(value[1..].to_string())),
+        _ => Ok(None),
+    }
+}
+
+fn main() -> ExitCode {
+    let args = match parse_args(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}\n{}", e, USAGE);
+            return ExitCode::FAILURE;
+        }
+    };
+    // The log, the server and the REPL read statements with the same types
+    let parser = Parser::default();
+    let (mut db, mut store) = match &args.dir {
+        Some(dir) => match Store::open_with(dir, &parser) {
+            Ok((db, store)) => {
+                if let Some(rejected) = store.rejected() {
+                    eprintln!(
+                        "Warning: part of the log could not be replayed and was moved to '{}': {}",
+                        rejected.path.display(),
+                        rejected.error
+                    );
+                }
+                (db, Some(store.with_sync_mode(args.sync).with_compression(args.compression)))
+            }
+            Err(e) => {
+                eprintln!("Cannot open database '{}': {}", dir.display(), e);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => (Database::new(), None),
+    };
+    if let Some(port) = args.port {
+        let shared = match store {
+            Some(store) => SharedDatabase::with_store(db, store),
+            None => SharedDatabase::new(db),
+        };
+        let server = match Server::bind(("127.0.0.1", port), shared) {
+            Ok(server) => server.with_protocol(args.protocol).with_parser(parser),
+            Err(e) => {
+                eprintln!("{}", e);
+                return ExitCode::FAILURE;
+            }
+        };
+        if let Ok(addr) = server.local_addr() {
+            eprintln!("Listening on {}", addr);
+        }
+        server.run();
+        return ExitCode::SUCCESS;
+    }
+    let settings = Settings { parser, mode: args.mode, ..Settings::default() };
+    let ok = match args.command {
+        Some(sql) => run_batch(&sql, &mut db, store.as_mut(), settings),
+        None if io::stdin().is_terminal() => {
+            run_repl(&mut db, store.as_mut(), settings);
+            true
+        }
+        None => {
+            let mut script = String::new();
+            if let Err(e) = io::stdin().read_to_string(&mut script) {
+                eprintln!("Cannot read stdin: {}", e);
+                return ExitCode::FAILURE;
+            }
+            run_batch(&script, &mut db, store.as_mut(), settings)
+        }
+    };
+    if let Some(store) = store {
+        if db.in_transaction() {
+            eprintln!("Rolling back the open transaction");
+        }
+        let dir = store.dir().to_path_buf();
+        if let Err(e) = store.close(db) {
+            eprintln!("Cannot close database '{}': {}", dir.display(), e);
+            return ExitCode::FAILURE;
+        }
+    }
+    if ok {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::FAILURE
+    }
+}
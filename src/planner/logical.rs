@@ -0,0 +1,339 @@
+//! Logical query plans.
+//!
+//! A [`LogicalPlan`] is a tree of relational operators built by the
+//! [`Planner`](crate::planner::planner::Planner) from a parsed statement.
+//! Every name in it has already been resolved: columns are positions in
+//! the rows produced by the node's input, and expressions have been type
+//! checked. The executor runs a plan without looking at names again.
+//!
+//! Each node produces rows whose columns are described by
+//! [`LogicalPlan::columns`]; a join produces the columns of its left input
+//! followed by those of its right input.
+
+use std::collections::BTreeSet;
+use std::fmt;
+
+use crate::core::schema::{Column, Schema};
+use crate::core::types::{DataType, TableName, Value};
+use crate::frontend::ast::{AggregateFunc, BinaryOp, CompareOp, SortDirection};
+
+/// An expression whose column references are positions in the input row.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ScalarExpr {
+    /// A literal value.
+    Literal(Value),
+    /// The value of the input column at the index.
+    Column(usize),
+    /// `NOW()`.
+    Now,
+    /// Arithmetic negation.
+    Negate(Box<ScalarExpr>),
+    /// `left <op> right`.
+    Binary {
+        op: BinaryOp,
+        left: Box<ScalarExpr>,
+        right: Box<ScalarExpr>,
+    },
+}
+
+impl ScalarExpr {
+    /// Returns `true` if the expression reads no column.
+    pub fn is_constant(&self) -> bool {
+        match self {
+            ScalarExpr::Literal(_) | ScalarExpr::Now => true,
+            ScalarExpr::Column(_) => false,
+            ScalarExpr::Negate(inner) => inner.is_constant(),
+            ScalarExpr::Binary { left, right, .. } => left.is_constant() && right.is_constant(),
+        }
+    }
+
+    /// Adds the input columns the expression reads to `out`.
+    pub fn collect_columns(&self, out: &mut BTreeSet<usize>) {
+        match self {
+            ScalarExpr::Literal(_) | ScalarExpr::Now => {}
+            ScalarExpr::Column(idx) => {
+                out.insert(*idx);
+            }
+            ScalarExpr::Negate(inner) => inner.collect_columns(out),
+            ScalarExpr::Binary { left, right, .. } => {
+                left.collect_columns(out);
+                right.collect_columns(out);
+            }
+        }
+    }
+
+    /// Returns the expression with every column index `i` replaced by
+    /// `map(i)`.
+    pub fn remap(&self, map: &dyn Fn(usize) -> usize) -> ScalarExpr {
+        match self {
+            ScalarExpr::Column(idx) => ScalarExpr::Column(map(*idx)),
+            ScalarExpr::Negate(inner) => ScalarExpr::Negate(Box::new(inner.remap(map))),
+            ScalarExpr::Binary { op, left, right } => ScalarExpr::Binary {
+                op: *op,
+                left: Box::new(left.remap(map)),
+                right: Box::new(right.remap(map)),
+            },
+            other => other.clone(),
+        }
+    }
+}
+
+/// A column of the input row referenced by a [`Predicate`], with what is
+/// needed to compare against it and to report errors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BoundColumn {
+    /// Position in the input row.
+    pub index: usize,
+    /// The reference as written in the query, e.g. `u.name`.
+    pub name: String,
+    pub dtype: DataType,
+}
+
+/// A resolved WHERE or ON condition, evaluated with SQL's three-valued
+/// logic (see [`eval_predicate`](crate::executor::eval::eval_predicate)).
+///
+/// Values compared with a column are coerced to the column type; constant
+/// values are coerced and checked once, when planning.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Predicate {
+    IsNull { column: BoundColumn, negated: bool },
+    Compare { column: BoundColumn, op: CompareOp, value: ScalarExpr },
+    In { column: BoundColumn, values: Vec<ScalarExpr>, negated: bool },
+    Between { column: BoundColumn, low: ScalarExpr, high: ScalarExpr, negated: bool },
+    /// `column LIKE pattern`; `column` is a `Text` column.
+    Like { column: BoundColumn, pattern: ScalarExpr, negated: bool },
+    And(Box<Predicate>, Box<Predicate>),
+    Or(Box<Predicate>, Box<Predicate>),
+    Not(Box<Predicate>),
+}
+
+impl Predicate {
+    /// Adds the input columns the predicate reads to `out`.
+    pub fn collect_columns(&self, out: &mut BTreeSet<usize>) {
+        match self {
+            Predicate::IsNull { column, .. } => {
+                out.insert(column.index);
+            }
+            Predicate::Compare { column, value, .. } => {
+                out.insert(column.index);
+                value.collect_columns(out);
+            }
+            Predicate::In { column, values, .. } => {
+                out.insert(column.index);
+                values.iter().for_each(|v| v.collect_columns(out));
+            }
+            Predicate::Between { column, low, high, .. } => {
+                out.insert(column.index);
+                low.collect_columns(out);
+                high.collect_columns(out);
+            }
+            Predicate::Like { column, pattern, .. } => {
+                out.insert(column.index);
+                pattern.collect_columns(out);
+            }
+            Predicate::And(left, right) | Predicate::Or(left, right) => {
+                left.collect_columns(out);
+                right.collect_columns(out);
+            }
+            Predicate::Not(inner) => inner.collect_columns(out),
+        }
+    }
+
+    /// Returns the input columns the predicate reads.
+    pub fn columns(&self) -> BTreeSet<usize> {
+        let mut out = BTreeSet::new();
+        self.collect_columns(&mut out);
+        out
+    }
+
+    /// Returns the names of the columns compared by the predicate, in order
+    /// of first use, as written in the query.
+    pub fn column_names(&self) -> Vec<&str> {
+        let mut names = Vec::new();
+        self.collect_names(&mut names);
+        names
+    }
+
+    fn collect_names<'a>(&'a self, out: &mut Vec<&'a str>) {
+        let column = match self {
+            Predicate::IsNull { column, .. }
+            | Predicate::Compare { column, .. }
+            | Predicate::In { column, .. }
+            | Predicate::Between { column, .. }
+            | Predicate::Like { column, .. } => column,
+            Predicate::And(left, right) | Predicate::Or(left, right) => {
+                left.collect_names(out);
+                return right.collect_names(out);
+            }
+            Predicate::Not(inner) => return inner.collect_names(out),
+        };
+        if !out.contains(&column.name.as_str()) {
+            out.push(&column.name);
+        }
+    }
+
+    /// Returns the predicate with every column index `i` replaced by
+    /// `map(i)`.
+    pub fn remap(&self, map: &dyn Fn(usize) -> usize) -> Predicate {
+        let bind = |column: &BoundColumn| BoundColumn { index: map(column.index), ..column.clone() };
+        match self {
+            Predicate::IsNull { column, negated } => Predicate::IsNull { column: bind(column), negated: *negated },
+            Predicate::Compare { column, op, value } => {
+                Predicate::Compare { column: bind(column), op: *op, value: value.remap(map) }
+            }
+            Predicate::In { column, values, negated } => Predicate::In {
+                column: bind(column),
+                values: values.iter().map(|v| v.remap(map)).collect(),
+                negated: *negated,
+            },
+            Predicate::Between { column, low, high, negated } => Predicate::Between {
+                column: bind(column),
+                low: low.remap(map),
+                high: high.remap(map),
+                negated: *negated,
+            },
+            Predicate::Like { column, pattern, negated } => {
+                Predicate::Like { column: bind(column), pattern: pattern.remap(map), negated: *negated }
+            }
+            Predicate::And(left, right) => Predicate::And(Box::new(left.remap(map)), Box::new(right.remap(map))),
+            Predicate::Or(left, right) => Predicate::Or(Box::new(left.remap(map)), Box::new(right.remap(map))),
+            Predicate::Not(inner) => Predicate::Not(Box::new(inner.remap(map))),
+        }
+    }
+}
+
+/// A select-list or HAVING item of a grouped query.
+#[derive(Debug, Clone, PartialEq)]
+pub enum GroupItem {
+    /// Index of a GROUP BY column.
+    Column(usize),
+    /// Aggregate over the column at the index, or over rows for `COUNT(*)`.
+    Aggregate(AggregateFunc, Option<usize>),
+}
+
+/// A resolved HAVING clause: `item <op> value`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Having {
+    pub item: GroupItem,
+    pub op: CompareOp,
+    pub value: Value,
+}
+
+/// A node of a logical query plan.
+#[derive(Debug, Clone)]
+pub enum LogicalPlan {
+    /// Every row of a table.
+    Scan { table: TableName, schema: Schema },
+    /// Nested-loop join; without a condition, a cross join. `on` reads the
+    /// combined row.
+    Join {
+        left: Box<LogicalPlan>,
+        right: Box<LogicalPlan>,
+        on: Option<Predicate>,
+    },
+    /// Rows of the input for which the predicate is true.
+    Filter { input: Box<LogicalPlan>, predicate: Predicate },
+    /// One row per group of input rows sharing the `group_by` columns (a
+    /// single group without them), made of `items`, for the groups
+    /// satisfying `having`.
+    Aggregate {
+        input: Box<LogicalPlan>,
+        group_by: Vec<usize>,
+        items: Vec<GroupItem>,
+        having: Option<Having>,
+        schema: Schema,
+    },
+    /// The input sorted on keys computed from each row, first key first.
+    /// The sort is stable.
+    Sort {
+        input: Box<LogicalPlan>,
+        keys: Vec<(ScalarExpr, SortDirection)>,
+    },
+    /// One output row of `schema` computed from each input row.
+    Project {
+        input: Box<LogicalPlan>,
+        exprs: Vec<ScalarExpr>,
+        schema: Schema,
+    },
+    /// The input without duplicate rows, keeping first occurrences.
+    Distinct { input: Box<LogicalPlan> },
+    /// The input after skipping `offset` rows, with at most `limit` rows.
+    Limit {
+        input: Box<LogicalPlan>,
+        offset: usize,
+        limit: Option<usize>,
+    },
+}
+
+impl LogicalPlan {
+    /// Returns the columns of the rows this node produces.
+    ///
+    /// Joined tables may share column names, so this is a list of columns
+    /// rather than a [`Schema`].
+    pub fn columns(&self) -> Vec<Column> {
+        match self {
+            LogicalPlan::Scan { schema, .. }
+            | LogicalPlan::Aggregate { schema, .. }
+            | LogicalPlan::Project { schema, .. } => schema.columns().clone(),
+            LogicalPlan::Join { left, right, .. } => {
+                let mut columns = left.columns();
+                columns.extend(right.columns());
+                columns
+            }
+            LogicalPlan::Filter { input, .. }
+            | LogicalPlan::Sort { input, .. }
+            | LogicalPlan::Distinct { input }
+            | LogicalPlan::Limit { input, .. } => input.columns(),
+        }
+    }
+
+    /// Writes the plan as an indented tree, one node per line.
+    fn fmt_tree(&self, f: &mut fmt::Formatter<'_>, depth: usize) -> fmt::Result {
+        write!(f, "{:indent$}", "", indent = depth * 2)?;
+        match self {
+            LogicalPlan::Scan { table, .. } => return writeln!(f, "Scan {}", table.as_str()),
+            LogicalPlan::Join { left, right, on } => {
+                match on {
+                    Some(on) => writeln!(f, "Join on {}", on.column_names().join(", "))?,
+                    None => writeln!(f, "Join (cross)")?,
+                }
+                left.fmt_tree(f, depth + 1)?;
+                return right.fmt_tree(f, depth + 1);
+            }
+            LogicalPlan::Filter { predicate, .. } => writeln!(f, "Filter on {}", predicate.column_names().join(", "))?,
+            LogicalPlan::Aggregate { group_by, .. } => writeln!(f, "Aggregate by {:?}", group_by)?,
+            LogicalPlan::Sort { keys, .. } => writeln!(f, "Sort on {} key(s)", keys.len())?,
+            LogicalPlan::Project { schema, .. } => {
+                let names: Vec<&str> = schema.columns().iter().map(|c| c.name.as_str()).collect();
+                writeln!(f, "Project {}", names.join(", "))?
+            }
+            LogicalPlan::Distinct { .. } => writeln!(f, "Distinct")?,
+            LogicalPlan::Limit { offset, limit, .. } => match limit {
+                Some(limit) => writeln!(f, "Limit {} offset {}", limit, offset)?,
+                None => writeln!(f, "Offset {}", offset)?,
+            },
+        }
+        match self {
+            LogicalPlan::Filter { input, .. }
+            | LogicalPlan::Aggregate { input, .. }
+            | LogicalPlan::Sort { input, .. }
+            | LogicalPlan::Project { input, .. }
+            | LogicalPlan::Distinct { input }
+            | LogicalPlan::Limit { input, .. } => input.fmt_tree(f, depth + 1),
+            LogicalPlan::Scan { .. } | LogicalPlan::Join { .. } => Ok(()),
+        }
+    }
+}
+
+impl fmt::Display for LogicalPlan {
+    /// Formats the plan as an indented tree, e.g.
+    ///
+    /// ```text
+    /// Project name
+    ///   Filter on age
+    ///     Scan users
+    /// ```
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.fmt_tree(f, 0)
+    }
+}
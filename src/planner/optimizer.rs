@@ -0,0 +1,329 @@
+//! Rule-based optimizer for logical plans.
+//!
+//! [`optimize`] rewrites a plan built by the
+//! [`Planner`](crate::planner::planner::Planner) into an equivalent one that
+//! reads fewer rows and carries narrower rows. It applies two rules, in
+//! order:
+//!
+//! - **Predicate pushdown**: filters are split into their `AND`ed
+//!   conditions, and each condition moves below sorts, projections and
+//!   joins until it sits right above the input providing its columns. A
+//!   condition on a single table ends up right above its scan, where the
+//!   executor can answer it with an index; one reading both sides of a join
+//!   becomes part of the join condition.
+//! - **Column pruning**: every table is narrowed, right after it is read,
+//!   to the columns used above it.
+//!
+//! Joins are inner joins, so a condition has the same effect in WHERE and in
+//! ON. Conditions never move below a grouping or a LIMIT, which would change
+//! the rows those see.
+
+use std::collections::BTreeSet;
+
+use crate::core::schema::Schema;
+use crate::planner::logical::{GroupItem, Having, LogicalPlan, Predicate, ScalarExpr};
+
+/// Optimizes a logical plan; the result produces the same rows, in the same
+/// order.
+pub fn optimize(plan: LogicalPlan) -> LogicalPlan {
+    let plan = push_down(plan, Vec::new());
+    let width = plan.columns().len();
+    prune(plan, &(0..width).collect()).0
+}
+
+/// Returns `plan` filtered by `conditions`, with these and the filters
+/// inside `plan` moved as far down as they can go.
+fn push_down(plan: LogicalPlan, mut conditions: Vec<Predicate>) -> LogicalPlan {
+    match plan {
+        LogicalPlan::Filter { input, predicate } => {
+            split(predicate, &mut conditions);
+            push_down(*input, conditions)
+        }
+        LogicalPlan::Join { left, right, on } => {
+            // The join's own conditions keep coming first
+            let mut all = Vec::new();
+            if let Some(on) = on {
+                split(on, &mut all);
+            }
+            all.append(&mut conditions);
+            let width = left.columns().len();
+            let (mut to_left, mut to_right, mut on) = (Vec::new(), Vec::new(), Vec::new());
+            for condition in all {
+                let columns = condition.columns();
+                if columns.iter().all(|&i| i < width) {
+                    to_left.push(condition);
+                } else if columns.iter().all(|&i| i >= width) {
+                    to_right.push(condition.remap(&|i| i - width));
+                } else {
+                    on.push(condition);
+                }
+            }
+            LogicalPlan::Join {
+                left: Box::new(push_down(*left, to_left)),
+                right: Box::new(push_down(*right, to_right)),
+                on: conjoin(on),
+            }
+        }
+        LogicalPlan::Project { input, exprs, schema } => {
+            // A condition on projected columns reads the input columns they
+            // come from instead; one on a computed column stays above
+            let source = |i: usize| match &exprs[i] {
+                ScalarExpr::Column(j) => Some(*j),
+                _ => None,
+            };
+            let (mut below, mut above) = (Vec::new(), Vec::new());
+            for condition in conditions {
+                if condition.columns().iter().all(|&i| source(i).is_some()) {
+                    below.push(condition.remap(&|i| source(i).expect("checked above")));
+                } else {
+                    above.push(condition);
+                }
+            }
+            let input = push_down(*input, below);
+            filter(LogicalPlan::Project { input: Box::new(input), exprs, schema }, above)
+        }
+        LogicalPlan::Sort { input, keys } => LogicalPlan::Sort { input: Box::new(push_down(*input, conditions)), keys },
+        LogicalPlan::Distinct { input } => LogicalPlan::Distinct { input: Box::new(push_down(*input, conditions)) },
+        LogicalPlan::Aggregate { input, group_by, items, having, schema } => {
+            let input = Box::new(push_down(*input, Vec::new()));
+            filter(LogicalPlan::Aggregate { input, group_by, items, having, schema }, conditions)
+        }
+        LogicalPlan::Limit { input, offset, limit } => {
+            let input = Box::new(push_down(*input, Vec::new()));
+            filter(LogicalPlan::Limit { input, offset, limit }, conditions)
+        }
+        LogicalPlan::Scan { .. } => filter(plan, conditions),
+    }
+}
+
+/// Appends the conditions `AND`ed together in `predicate` to `out`.
+fn split(predicate: Predicate, out: &mut Vec<Predicate>) {
+    match predicate {
+        Predicate::And(left, right) => {
+            split(*left, out);
+            split(*right, out);
+        }
+        other => out.push(other),
+    }
+}
+
+/// `AND`s conditions together, or returns `None` if there are none.
+fn conjoin(conditions: Vec<Predicate>) -> Option<Predicate> {
+    conditions
+        .into_iter()
+        .reduce(|acc, condition| Predicate::And(Box::new(acc), Box::new(condition)))
+}
+
+/// Returns `plan` filtered by `conditions`, if there are any.
+fn filter(plan: LogicalPlan, conditions: Vec<Predicate>) -> LogicalPlan {
+    match conjoin(conditions) {
+        Some(predicate) => LogicalPlan::Filter { input: Box::new(plan), predicate },
+        None => plan,
+    }
+}
+
+/// Rewrites `plan` so that it carries only the columns it needs to produce
+/// the columns in `required` and to evaluate its own expressions.
+///
+/// # Returns
+/// The rewritten plan, and for each column of `plan` its position in the
+/// rows of the rewritten plan, or `None` if it was dropped.
+fn prune(plan: LogicalPlan, required: &BTreeSet<usize>) -> (LogicalPlan, Vec<Option<usize>>) {
+    match plan {
+        // A filter right above a scan stays there, where an index can
+        // answer it, and the table is narrowed above both
+        LogicalPlan::Scan { .. } => narrow(plan, required),
+        LogicalPlan::Filter { input, predicate } if matches!(*input, LogicalPlan::Scan { .. }) => {
+            narrow(LogicalPlan::Filter { input, predicate }, required)
+        }
+        LogicalPlan::Filter { input, predicate } => {
+            let mut needed = required.clone();
+            predicate.collect_columns(&mut needed);
+            let (input, map) = prune(*input, &needed);
+            let predicate = predicate.remap(&|i| kept(&map, i));
+            (LogicalPlan::Filter { input: Box::new(input), predicate }, map)
+        }
+        LogicalPlan::Join { left, right, on } => {
+            let mut needed = required.clone();
+            if let Some(on) = &on {
+                on.collect_columns(&mut needed);
+            }
+            let width = left.columns().len();
+            let (left, mut map) = prune(*left, &needed.iter().copied().filter(|&i| i < width).collect());
+            let right_needed = needed.iter().filter(|&&i| i >= width).map(|&i| i - width).collect();
+            let (right, right_map) = prune(*right, &right_needed);
+            let left_width = left.columns().len();
+            map.extend(right_map.into_iter().map(|pos| pos.map(|j| j + left_width)));
+            let on = on.map(|on| on.remap(&|i| kept(&map, i)));
+            (LogicalPlan::Join { left: Box::new(left), right: Box::new(right), on }, map)
+        }
+        LogicalPlan::Project { input, exprs, schema } => {
+            let mut needed = BTreeSet::new();
+            exprs.iter().for_each(|expr| expr.collect_columns(&mut needed));
+            let (input, map) = prune(*input, &needed);
+            let exprs = exprs.iter().map(|expr| expr.remap(&|i| kept(&map, i))).collect();
+            let width = schema.columns().len();
+            (LogicalPlan::Project { input: Box::new(input), exprs, schema }, (0..width).map(Some).collect())
+        }
+        LogicalPlan::Aggregate { input, group_by, items, having, schema } => {
+            let mut needed: BTreeSet<usize> = group_by.iter().copied().collect();
+            for item in items.iter().chain(having.iter().map(|h| &h.item)) {
+                if let GroupItem::Column(i) | GroupItem::Aggregate(_, Some(i)) = item {
+                    needed.insert(*i);
+                }
+            }
+            let (input, map) = prune(*input, &needed);
+            let remap_item = |item: &GroupItem| match item {
+                GroupItem::Column(i) => GroupItem::Column(kept(&map, *i)),
+                GroupItem::Aggregate(func, arg) => GroupItem::Aggregate(*func, arg.map(|i| kept(&map, i))),
+            };
+            let plan = LogicalPlan::Aggregate {
+                group_by: group_by.iter().map(|&i| kept(&map, i)).collect(),
+                items: items.iter().map(remap_item).collect(),
+                having: having.map(|h| Having { item: remap_item(&h.item), ..h }),
+                input: Box::new(input),
+                schema,
+            };
+            let width = plan.columns().len();
+            (plan, (0..width).map(Some).collect())
+        }
+        LogicalPlan::Sort { input, keys } => {
+            let mut needed = required.clone();
+            keys.iter().for_each(|(expr, _)| expr.collect_columns(&mut needed));
+            let (input, map) = prune(*input, &needed);
+            let keys = keys.iter().map(|(expr, dir)| (expr.remap(&|i| kept(&map, i)), *dir)).collect();
+            (LogicalPlan::Sort { input: Box::new(input), keys }, map)
+        }
+        LogicalPlan::Distinct { input } => {
+            // Rows are compared on all their columns
+            let width = input.columns().len();
+            let (input, map) = prune(*input, &(0..width).collect());
+            (LogicalPlan::Distinct { input: Box::new(input) }, map)
+        }
+        LogicalPlan::Limit { input, offset, limit } => {
+            let (input, map) = prune(*input, required);
+            (LogicalPlan::Limit { input: Box::new(input), offset, limit }, map)
+        }
+    }
+}
+
+/// Narrows the rows of a table read by `plan` to the columns in `required`,
+/// with a projection on top of it.
+///
+/// Nothing changes if every column is required, or none is (as for
+/// `COUNT(*)`), which leaves nothing worth narrowing to.
+fn narrow(plan: LogicalPlan, required: &BTreeSet<usize>) -> (LogicalPlan, Vec<Option<usize>>) {
+    let columns = plan.columns();
+    if required.is_empty() || required.len() == columns.len() {
+        return (plan, (0..columns.len()).map(Some).collect());
+    }
+    let mut map = vec![None; columns.len()];
+    for (pos, &i) in required.iter().enumerate() {
+        map[i] = Some(pos);
+    }
+    let schema = Schema::try_new(required.iter().map(|&i| columns[i].clone()).collect())
+        .expect("columns of one table have distinct names");
+    let exprs = required.iter().map(|&i| ScalarExpr::Column(i)).collect();
+    (LogicalPlan::Project { input: Box::new(plan), exprs, schema }, map)
+}
+
+/// Returns the new position of column `i`, which the caller required.
+fn kept(map: &[Option<usize>], i: usize) -> usize {
+    map[i].expect("required columns are kept")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::db::Database;
+    use crate::executor::executor::Executor;
+    use crate::frontend::ast::ASTNode;
+    use crate::frontend::lexer::lexer;
+    use crate::frontend::parser::Parser;
+    use crate::planner::planner::Planner;
+
+    fn shop_db() -> Database {
+        let mut db = Database::new();
+        for sql in [
+            "CREATE TABLE users (id INT, name TEXT, age INT)",
+            "CREATE TABLE orders (id INT, user_id INT, amount FLOAT)",
+        ] {
+            Executor::execute(Parser::default().parse(&lexer(sql).unwrap()).unwrap(), &mut db).unwrap();
+        }
+        db
+    }
+
+    /// Returns the plan of `sql` before and after optimization.
+    fn plans(db: &Database, sql: &str) -> (String, String) {
+        let ASTNode::Select(stmt) = Parser::default().parse(&lexer(sql).unwrap()).unwrap() else {
+            panic!("not a SELECT: {}", sql);
+        };
+        let plan = Planner::plan_select(&stmt, db).unwrap();
+        (plan.to_string(), optimize(plan).to_string())
+    }
+
+    #[test]
+    fn pushes_filters_below_joins_and_prunes_columns() {
+        let db = shop_db();
+        let (before, after) = plans(
+            &db,
+            "SELECT u.name, o.amount FROM users u JOIN orders o ON o.user_id = u.id \
+             WHERE u.age > 30 AND o.amount > 10 AND u.id < o.id ORDER BY o.amount",
+        );
+        assert_eq!(
+            before,
+            "Project name, amount\n\
+             \x20 Sort on 1 key(s)\n\
+             \x20   Filter on u.age, o.amount, u.id\n\
+             \x20     Join on o.user_id\n\
+             \x20       Scan users\n\
+             \x20       Scan orders\n"
+        );
+        assert_eq!(
+            after,
+            "Project name, amount\n\
+             \x20 Sort on 1 key(s)\n\
+             \x20   Join on o.user_id, u.id\n\
+             \x20     Project id, name\n\
+             \x20       Filter on u.age\n\
+             \x20         Scan users\n\
+             \x20     Filter on o.amount\n\
+             \x20       Scan orders\n"
+        );
+    }
+
+    #[test]
+    fn prunes_grouped_input_and_keeps_filters_below_limits() {
+        let db = shop_db();
+        let (_, after) = plans(&db, "SELECT user_id, SUM(amount) FROM orders WHERE id > 3 GROUP BY user_id");
+        assert_eq!(
+            after,
+            "Aggregate by [0]\n\
+             \x20 Project user_id, amount\n\
+             \x20   Filter on id\n\
+             \x20     Scan orders\n"
+        );
+
+        // COUNT(*) reads no column, so the rows are left whole
+        let (before, after) = plans(&db, "SELECT COUNT(*) FROM orders");
+        assert_eq!(before, after);
+
+        // A filter above a LIMIT must see only the limited rows
+        let ASTNode::Select(stmt) = Parser::default().parse(&lexer("SELECT * FROM users WHERE age > 1 LIMIT 2").unwrap()).unwrap() else {
+            unreachable!()
+        };
+        let plan = Planner::plan_select(&stmt, &db).unwrap();
+        let LogicalPlan::Limit { input, .. } = &plan else { panic!("expected a limit") };
+        let LogicalPlan::Project { input, .. } = input.as_ref() else { panic!("expected a projection") };
+        let LogicalPlan::Filter { predicate, .. } = input.as_ref() else { panic!("expected a filter") };
+        let above = LogicalPlan::Filter { input: Box::new(plan.clone()), predicate: predicate.clone() };
+        assert_eq!(
+            optimize(above).to_string(),
+            "Filter on age\n\
+             \x20 Limit 2 offset 0\n\
+             \x20   Project id, name, age\n\
+             \x20     Filter on age\n\
+             \x20       Scan users\n"
+        );
+    }
+}
@@ -0,0 +1,648 @@
+//! Planner: turns parsed statements into [`LogicalPlan`]s.
+//!
+//! The planner does the semantic work of a statement once, before any row
+//! is read: it resolves table and column names through a [`Scope`], checks
+//! the types of expressions and conditions, and folds constant operands of
+//! comparisons into literals of the column type. The executor then runs the
+//! resulting plan without looking at names again.
+//!
+//! A view read by a query is expanded there: the query it stands for is
+//! parsed and planned, and its plan takes the place of a table scan.
+
+use crate::core::db::Database;
+use crate::core::error::{SqlError, SqlResult};
+use crate::core::schema::{Column, Schema};
+use crate::core::types::{ColumnName, DataType, TableName};
+use crate::executor::eval::eval_scalar;
+use crate::executor::information_schema;
+use crate::frontend::ast::*;
+use crate::frontend::lexer::lexer;
+use crate::frontend::parser::Parser;
+use crate::planner::logical::{BoundColumn, GroupItem, Having, LogicalPlan, Predicate, ScalarExpr};
+
+/// Builds logical plans.
+pub struct Planner;
+
+impl Planner {
+    /// Plans a SELECT.
+    ///
+    /// The plan reads the FROM table, joins the others in order, filters
+    /// with WHERE and then either groups (for aggregates, GROUP BY or
+    /// HAVING) or sorts and projects; DISTINCT and OFFSET/LIMIT apply last.
+    ///
+    /// # Returns
+    /// The plan, or `Err(SqlError)` if a table or column does not exist or
+    /// an expression is ill-typed.
+    pub fn plan_select(stmt: &SelectStmt, db: &Database) -> SqlResult<LogicalPlan> {
+        let (mut plan, schema) = source_plan(&stmt.table, db)?;
+        let mut scope = Scope::default();
+        scope.push(stmt.alias.as_ref().unwrap_or(&stmt.table), &schema)?;
+
+        // An ON condition only sees the tables joined so far
+        for join in &stmt.joins {
+            let (right, schema) = source_plan(&join.table, db)?;
+            scope.push(join.alias.as_ref().unwrap_or(&join.table), &schema)?;
+            plan = LogicalPlan::Join {
+                left: Box::new(plan),
+                right: Box::new(right),
+                on: join.on.as_ref().map(|on| plan_predicate(on, &scope)).transpose()?,
+            };
+        }
+
+        if let Some(clause) = &stmt.where_clause {
+            plan = LogicalPlan::Filter { input: Box::new(plan), predicate: plan_predicate(clause, &scope)? };
+        }
+
+        let has_aggregate = stmt
+            .columns
+            .iter()
+            .flatten()
+            .any(|(item, _)| matches!(item, SelectItem::Aggregate { .. }));
+        plan = if has_aggregate || !stmt.group_by.is_empty() || stmt.having.is_some() {
+            let plan = plan_aggregate(stmt, &scope, plan)?;
+            let schema = match &plan {
+                LogicalPlan::Aggregate { schema, .. } => schema.clone(),
+                _ => unreachable!("plan_aggregate returns an aggregate"),
+            };
+            // Grouped query: ORDER BY names output columns
+            let keys = resolve_output_sort_keys(stmt, &scope, &schema)?;
+            if keys.is_empty() {
+                plan
+            } else {
+                LogicalPlan::Sort { input: Box::new(plan), keys }
+            }
+        } else {
+            let (exprs, schema) = resolve_projection(stmt.columns.as_deref(), &scope)?;
+            let keys = stmt
+                .order_by
+                .iter()
+                .map(|(key, dir)| Ok((resolve_sort_key(key, stmt.columns.as_deref(), &scope)?, *dir)))
+                .collect::<SqlResult<Vec<_>>>()?;
+            // Sort on the source rows, before projection drops key columns
+            if !keys.is_empty() {
+                plan = LogicalPlan::Sort { input: Box::new(plan), keys };
+            }
+            LogicalPlan::Project { input: Box::new(plan), exprs, schema }
+        };
+
+        // DISTINCT applies to output rows, and OFFSET/LIMIT to what remains
+        if stmt.distinct {
+            plan = LogicalPlan::Distinct { input: Box::new(plan) };
+        }
+        if stmt.offset.is_some() || stmt.limit.is_some() {
+            plan = LogicalPlan::Limit { input: Box::new(plan), offset: stmt.offset.unwrap_or(0), limit: stmt.limit };
+        }
+        Ok(plan)
+    }
+}
+
+/// The tables a statement reads from, in FROM/JOIN order.
+#[derive(Default, Clone)]
+pub struct Scope {
+    tables: Vec<(TableName, Schema)>,
+}
+
+impl Scope {
+    /// Appends a table's columns; a table may only appear once.
+    pub fn push(&mut self, name: &TableName, schema: &Schema) -> SqlResult<()> {
+        if self.tables.iter().any(|(t, _)| t == name) {
+            return Err(SqlError::new_semantic(&format!(
+                "Table '{}' appears more than once in the query",
+                name.as_str()
+            )));
+        }
+        self.tables.push((name.clone(), schema.clone()));
+        Ok(())
+    }
+
+    /// Resolves a column reference to its index in a combined row.
+    ///
+    /// An unqualified name must match a column of exactly one table. A
+    /// column of `sales.orders` can be qualified as `orders` too.
+    pub fn resolve(&self, col: &ColumnRef) -> SqlResult<usize> {
+        let qualifies = |qualifier: &TableName, name: &TableName| {
+            qualifier == name || (qualifier.schema().is_none() && qualifier.as_str() == name.table())
+        };
+        if let Some(table) = &col.table {
+            if !self.tables.iter().any(|(t, _)| qualifies(table, t)) {
+                return Err(SqlError::new_semantic(&format!(
+                    "Unknown table '{}' in column reference '{}'",
+                    table.as_str(),
+                    col
+                )));
+            }
+        }
+
+        let mut found = None;
+        let mut offset = 0;
+        for (name, schema) in &self.tables {
+            if col.table.as_ref().is_none_or(|t| qualifies(t, name)) {
+                if let Some(idx) = schema.index_of(&col.column) {
+                    if found.is_some() {
+                        return Err(SqlError::new_semantic(&format!("Column '{}' is ambiguous", col)));
+                    }
+                    found = Some(offset + idx);
+                }
+            }
+            offset += schema.columns().len();
+        }
+        found.ok_or_else(|| SqlError::new_semantic(&format!("Unknown column '{}'", col)))
+    }
+
+    /// Returns the column at `index` of a combined row, with the name of the
+    /// table it belongs to.
+    ///
+    /// # Returns
+    /// The table name and column, or `Err(SqlError)` if the row has fewer
+    /// columns.
+    pub fn column(&self, index: usize) -> SqlResult<(&TableName, &Column)> {
+        let mut rest = index;
+        for (name, schema) in &self.tables {
+            match schema.columns().get(rest) {
+                Some(column) => return Ok((name, column)),
+                None => rest -= schema.columns().len(),
+            }
+        }
+        Err(SqlError::new_semantic(&format!("Column {} is out of scope", index)))
+    }
+
+    /// Number of columns in a combined row.
+    pub fn width(&self) -> usize {
+        self.tables.iter().map(|(_, schema)| schema.columns().len()).sum()
+    }
+}
+
+
+/// Predicts the type of `expr` over rows of `scope`, or `None` when it is
+/// always `NULL`. Fails for operands an operator cannot accept.
+pub fn expr_type(expr: &Expr, scope: &Scope) -> SqlResult<Option<DataType>> {
+    match expr {
+        Expr::Literal(value) => Ok(value.data_type()),
+        Expr::Column(col) => Ok(Some(scope.column(scope.resolve(col)?)?.1.dtype.clone())),
+        Expr::Function { name, .. } => match name.as_str() {
+            "NOW" => Ok(Some(DataType::Timestamp)),
+            _ => Err(SqlError::new_semantic(&format!("Unknown function '{}'", name))),
+        },
+        Expr::Negate(inner) => match expr_type(inner, scope)? {
+            dtype @ (None | Some(DataType::Int | DataType::Float)) => Ok(dtype),
+            Some(other) => Err(SqlError::new_semantic(&format!("Cannot negate {:?}", other))),
+        },
+        Expr::Binary { op: BinaryOp::Concat, left, right } => {
+            // Validate the operands even though any type can be concatenated
+            expr_type(left, scope)?;
+            expr_type(right, scope)?;
+            Ok(Some(DataType::Text))
+        }
+        Expr::Binary { op, left, right } => {
+            match (expr_type(left, scope)?, expr_type(right, scope)?) {
+                (Some(DataType::Int), Some(DataType::Int)) => Ok(Some(DataType::Int)),
+                (Some(DataType::Int | DataType::Float), Some(DataType::Int | DataType::Float)) => {
+                    Ok(Some(DataType::Float))
+                }
+                (None, other) | (other, None) if matches!(other, None | Some(DataType::Int | DataType::Float)) => {
+                    Ok(None)
+                }
+                (l, r) => Err(SqlError::new_semantic(&format!(
+                    "Cannot apply '{}' to {:?} and {:?}",
+                    op.symbol(),
+                    l.map_or("NULL".to_string(), |t| format!("{:?}", t)),
+                    r.map_or("NULL".to_string(), |t| format!("{:?}", t))
+                ))),
+            }
+        }
+    }
+}
+
+/// Resolves the names of an expression.
+///
+/// # Returns
+/// The resolved expression, or `Err(SqlError)` for an unknown column or
+/// function.
+pub fn plan_expr(expr: &Expr, scope: &Scope) -> SqlResult<ScalarExpr> {
+    Ok(match expr {
+        Expr::Literal(value) => ScalarExpr::Literal(value.clone()),
+        Expr::Column(col) => ScalarExpr::Column(scope.resolve(col)?),
+        Expr::Function { name, args } => match name.as_str() {
+            "NOW" if args.is_empty() => ScalarExpr::Now,
+            "NOW" => return Err(SqlError::new_semantic("NOW() takes no arguments")),
+            _ => return Err(SqlError::new_semantic(&format!("Unknown function '{}'", name))),
+        },
+        Expr::Negate(inner) => ScalarExpr::Negate(Box::new(plan_expr(inner, scope)?)),
+        Expr::Binary { op, left, right } => ScalarExpr::Binary {
+            op: *op,
+            left: Box::new(plan_expr(left, scope)?),
+            right: Box::new(plan_expr(right, scope)?),
+        },
+    })
+}
+
+/// Resolves a WHERE or ON clause over the rows of `scope`.
+///
+/// A `LIKE` must apply to a `Text` column. Constant operands compared with
+/// a column are evaluated and coerced to the column type here, and must
+/// then be comparable with it (so comparing an `Int` column with a text
+/// literal is an error rather than a silent mismatch).
+pub fn plan_predicate(clause: &WhereClause, scope: &Scope) -> SqlResult<Predicate> {
+    Ok(match clause {
+        WhereClause::IsNull { column, negated } => {
+            Predicate::IsNull { column: bind(column, scope)?, negated: *negated }
+        }
+        WhereClause::Compare { column, op, value } => {
+            let column = bind(column, scope)?;
+            let value = plan_operand(value, &column, scope)?;
+            Predicate::Compare { column, op: *op, value }
+        }
+        WhereClause::In { column, values, negated } => {
+            let column = bind(column, scope)?;
+            let values = values
+                .iter()
+                .map(|value| plan_operand(value, &column, scope))
+                .collect::<SqlResult<Vec<_>>>()?;
+            Predicate::In { column, values, negated: *negated }
+        }
+        WhereClause::Between { column, low, high, negated } => {
+            let column = bind(column, scope)?;
+            let low = plan_operand(low, &column, scope)?;
+            let high = plan_operand(high, &column, scope)?;
+            Predicate::Between { column, low, high, negated: *negated }
+        }
+        WhereClause::Like { column, pattern, negated } => {
+            let bound = bind(column, scope)?;
+            if bound.dtype != DataType::Text {
+                return Err(SqlError::new_semantic(&format!(
+                    "LIKE requires a Text column, but '{}' is {:?}",
+                    column, bound.dtype
+                )));
+            }
+            Predicate::Like { column: bound, pattern: plan_expr(pattern, scope)?, negated: *negated }
+        }
+        WhereClause::And(left, right) => {
+            Predicate::And(Box::new(plan_predicate(left, scope)?), Box::new(plan_predicate(right, scope)?))
+        }
+        WhereClause::Or(left, right) => {
+            Predicate::Or(Box::new(plan_predicate(left, scope)?), Box::new(plan_predicate(right, scope)?))
+        }
+        WhereClause::Not(inner) => Predicate::Not(Box::new(plan_predicate(inner, scope)?)),
+    })
+}
+
+/// Resolves a column reference of a condition.
+fn bind(column: &ColumnRef, scope: &Scope) -> SqlResult<BoundColumn> {
+    let index = scope.resolve(column)?;
+    Ok(BoundColumn { index, name: column.to_string(), dtype: scope.column(index)?.1.dtype.clone() })
+}
+
+/// Resolves a value compared with `column`, folding it into a literal of
+/// the column type when it does not depend on the row.
+fn plan_operand(expr: &Expr, column: &BoundColumn, scope: &Scope) -> SqlResult<ScalarExpr> {
+    let expr = plan_expr(expr, scope)?;
+    if !expr.is_constant() {
+        return Ok(expr);
+    }
+    let value = column.dtype.coerce(eval_scalar(&expr, &[])?);
+    if !value.is_null() && column.dtype.default_value().compare(&value).is_none() {
+        return Err(SqlError::new_semantic(&format!(
+            "Type mismatch in WHERE: column '{}' is {:?}, got {:?}",
+            column.name, column.dtype, value
+        )));
+    }
+    Ok(ScalarExpr::Literal(value))
+}
+
+/// Resolves one ungrouped SELECT list item against the combined row.
+fn resolve_projected(item: &SelectItem, scope: &Scope) -> SqlResult<ScalarExpr> {
+    match item {
+        SelectItem::Column(col) => scope.resolve(col).map(ScalarExpr::Column),
+        SelectItem::Expr(expr) => plan_expr(expr, scope),
+        SelectItem::Aggregate { .. } => Err(SqlError::new_semantic("Aggregates require a grouped query")),
+    }
+}
+
+/// Resolves an ungrouped SELECT list (`None` for `SELECT *`) against the
+/// combined row and builds the output schema.
+///
+/// Output columns are named by their alias if they have one, and otherwise
+/// keep their names; when two would clash (typically the same column name in
+/// two joined tables) the later one is prefixed with its table, e.g.
+/// `orders_id`. Computed expressions without an alias are named `expr`.
+fn resolve_projection(
+    items: Option<&[(SelectItem, Option<ColumnName>)]>,
+    scope: &Scope,
+) -> SqlResult<(Vec<ScalarExpr>, Schema)> {
+    let (items, aliases): (Vec<_>, Vec<_>) = match items {
+        None => (0..scope.width()).map(|_| (None, None)).unzip(),
+        Some(items) => items.iter().map(|(item, alias)| (Some(item), alias.as_ref())).unzip(),
+    };
+
+    let mut exprs = Vec::with_capacity(items.len());
+    let mut out_cols: Vec<Column> = Vec::with_capacity(items.len());
+    for (idx, (item, alias)) in items.into_iter().zip(aliases).enumerate() {
+        let expr = match item {
+            Some(item) => resolve_projected(item, scope)?,
+            None => ScalarExpr::Column(idx),
+        };
+        let mut column = match (&expr, item) {
+            (ScalarExpr::Column(idx), _) => {
+                let (table, column) = scope.column(*idx)?;
+                let mut column = column.clone();
+                if out_cols.iter().any(|c| c.name == column.name) {
+                    let prefixed = ColumnName::new(&format!("{}_{}", table.as_str().replace('.', "_"), column.name.as_str()))
+                        .expect("table and column names join into a valid identifier");
+                    column.name = unique_name(&prefixed, &out_cols);
+                }
+                column
+            }
+            (_, Some(SelectItem::Expr(expr))) => {
+                // An expression that is always NULL gets an arbitrary type
+                let dtype = expr_type(expr, scope)?.unwrap_or(DataType::Text);
+                let base = ColumnName::new("expr").expect("valid identifier");
+                Column::new(unique_name(&base, &out_cols), dtype)
+            }
+            _ => unreachable!("only columns and expressions are projected"),
+        };
+        if let Some(alias) = alias {
+            column.name = alias.clone();
+        }
+        exprs.push(expr);
+        out_cols.push(column);
+    }
+    Ok((exprs, Schema::try_new(out_cols)?))
+}
+
+/// Resolves an ORDER BY key of an ungrouped query. An unqualified key that
+/// matches a select-list alias sorts by that item; any other key names a
+/// column of the combined row.
+fn resolve_sort_key(
+    key: &ColumnRef,
+    items: Option<&[(SelectItem, Option<ColumnName>)]>,
+    scope: &Scope,
+) -> SqlResult<ScalarExpr> {
+    let aliased = items
+        .unwrap_or_default()
+        .iter()
+        .find(|(_, alias)| key.table.is_none() && alias.as_ref() == Some(&key.column));
+    match aliased {
+        Some((item, _)) => resolve_projected(item, scope),
+        None => scope.resolve(key).map(ScalarExpr::Column),
+    }
+}
+
+/// Resolves the ORDER BY keys of a grouped query to output columns.
+///
+/// A key naming a GROUP BY column selected in the output sorts by that
+/// column; any other unqualified key is looked up by output name (such as
+/// `count` or `sum_salary`).
+fn resolve_output_sort_keys(
+    stmt: &SelectStmt,
+    scope: &Scope,
+    out_schema: &Schema,
+) -> SqlResult<Vec<(ScalarExpr, SortDirection)>> {
+    let items = stmt.columns.as_deref().unwrap_or_default();
+    stmt.order_by
+        .iter()
+        .map(|(key, dir)| {
+            let source = scope.resolve(key).ok();
+            let selected = items.iter().position(|(item, _)| match item {
+                SelectItem::Column(col) => source.is_some() && scope.resolve(col).ok() == source,
+                SelectItem::Aggregate { .. } | SelectItem::Expr(_) => false,
+            });
+            let idx = match (selected, &key.table) {
+                (Some(idx), _) => Some(idx),
+                (None, None) => out_schema.index_of(&key.column),
+                (None, Some(_)) => None,
+            };
+            idx.map(|idx| (ScalarExpr::Column(idx), *dir))
+                .ok_or_else(|| SqlError::new_semantic(&format!("Unknown column '{}'", key)))
+        })
+        .collect()
+}
+
+/// Plans the grouping of `input` for a query with aggregates, GROUP BY or
+/// HAVING.
+///
+/// Plain columns in the select list must appear in GROUP BY, and
+/// aggregates are named like `count` or `sum_amount` in the output.
+fn plan_aggregate(stmt: &SelectStmt, scope: &Scope, input: LogicalPlan) -> SqlResult<LogicalPlan> {
+    let select = stmt
+        .columns
+        .as_ref()
+        .ok_or_else(|| SqlError::new_semantic("SELECT * cannot be used with GROUP BY or aggregates"))?;
+    let group_by = stmt
+        .group_by
+        .iter()
+        .map(|col| scope.resolve(col))
+        .collect::<SqlResult<Vec<_>>>()?;
+
+    let mut items = Vec::with_capacity(select.len());
+    let mut out_cols: Vec<Column> = Vec::with_capacity(select.len());
+    for (item, alias) in select {
+        let (group_item, mut column) = resolve_group_item(item, scope, &group_by)?;
+        if let SelectItem::Aggregate { func, column: arg } = item {
+            let name = unique_name(&aggregate_name(*func, arg.as_ref()), &out_cols);
+            column = Column::new(name, column.dtype);
+        }
+        if let Some(alias) = alias {
+            column.name = alias.clone();
+        }
+        items.push(group_item);
+        out_cols.push(column);
+    }
+
+    let having = match &stmt.having {
+        Some(clause) => {
+            let (item, column) = resolve_group_item(&clause.item, scope, &group_by)?;
+            let value = column.dtype.coerce(eval_scalar(&plan_expr(&clause.value, &Scope::default())?, &[])?);
+            Some(Having { item, op: clause.op, value })
+        }
+        None => None,
+    };
+
+    Ok(LogicalPlan::Aggregate {
+        input: Box::new(input),
+        group_by,
+        items,
+        having,
+        schema: Schema::try_new(out_cols)?,
+    })
+}
+
+/// Resolves a SELECT list or HAVING item for a grouped query, returning it
+/// together with a nullable output column describing its result type.
+fn resolve_group_item(item: &SelectItem, scope: &Scope, key_indexes: &[usize]) -> SqlResult<(GroupItem, Column)> {
+    match item {
+        SelectItem::Column(col) => {
+            let idx = scope.resolve(col)?;
+            if !key_indexes.contains(&idx) {
+                return Err(SqlError::new_semantic(&format!(
+                    "Column '{}' must appear in GROUP BY or be used in an aggregate",
+                    col
+                )));
+            }
+            Ok((GroupItem::Column(idx), scope.column(idx)?.1.clone()))
+        }
+        SelectItem::Aggregate { func, column } => {
+            let idx = column.as_ref().map(|col| scope.resolve(col)).transpose()?;
+            let input = idx.map(|i| scope.column(i).map(|(_, column)| column)).transpose()?;
+            let dtype = match (func, input) {
+                (AggregateFunc::Count, _) => DataType::Int,
+                (AggregateFunc::Min | AggregateFunc::Max, Some(col)) => col.dtype.clone(),
+                (AggregateFunc::Sum, Some(col)) if matches!(col.dtype, DataType::Int | DataType::Float) => col.dtype.clone(),
+                (AggregateFunc::Avg, Some(col)) if matches!(col.dtype, DataType::Int | DataType::Float) => DataType::Float,
+                (_, Some(col)) => {
+                    return Err(SqlError::new_semantic(&format!(
+                        "{:?} requires a numeric column, '{}' is {:?}",
+                        func,
+                        col.name.as_str(),
+                        col.dtype
+                    )));
+                }
+                (_, None) => return Err(SqlError::new_semantic(&format!("{:?} requires a column argument", func))),
+            };
+            let name = aggregate_name(*func, column.as_ref());
+            Ok((GroupItem::Aggregate(*func, idx), Column::new(name, dtype)))
+        }
+        SelectItem::Expr(_) => Err(SqlError::new_semantic("Expressions are not supported in grouped queries")),
+    }
+}
+
+/// Default output name of an aggregate: `count` for `COUNT(*)`, otherwise
+/// the function and column, e.g. `sum_amount`.
+fn aggregate_name(func: AggregateFunc, column: Option<&ColumnRef>) -> ColumnName {
+    let func = format!("{:?}", func).to_lowercase();
+    let name = match column {
+        Some(col) => format!("{}_{}", func, col.column.as_str()),
+        None => func,
+    };
+    ColumnName::new(&name).expect("aggregate names are valid identifiers")
+}
+
+/// Returns `base`, or `base_2`, `base_3`, ... if an earlier output column
+/// already uses the name.
+fn unique_name(base: &ColumnName, taken: &[Column]) -> ColumnName {
+    let is_taken = |name: &ColumnName| taken.iter().any(|c| &c.name == name);
+    let mut name = base.clone();
+    let mut n = 2;
+    while is_taken(&name) {
+        name = ColumnName::new(&format!("{}_{}", base.as_str(), n)).expect("suffixed names stay valid");
+        n += 1;
+    }
+    name
+}
+
+/// Returns the plan reading the table `name` in a query, with the schema
+/// of its rows: a scan of a stored or virtual table, or the plan of the
+/// query of a view.
+fn source_plan(name: &TableName, db: &Database) -> SqlResult<(LogicalPlan, Schema)> {
+    match db.view(name) {
+        Some(sql) => {
+            let plan = plan_view(name, sql, db)?;
+            let schema = Schema::try_new(plan.columns())?;
+            Ok((plan, schema))
+        }
+        None => {
+            let schema = source_schema(name, db)?;
+            Ok((LogicalPlan::Scan { table: name.clone(), schema: schema.clone() }, schema))
+        }
+    }
+}
+
+/// Plans the query `sql` of the view `name`.
+fn plan_view(name: &TableName, sql: &str, db: &Database) -> SqlResult<LogicalPlan> {
+    let invalid = |e: SqlError| {
+        SqlError::new_semantic(&format!("View '{}' is no longer valid: {}", name.as_str(), e.message()))
+    };
+    match Parser::default().parse(&lexer(sql).map_err(invalid)?).map_err(invalid)? {
+        ASTNode::Select(query) => Planner::plan_select(&query, db).map_err(invalid),
+        _ => Err(invalid(SqlError::new_semantic("not a query"))),
+    }
+}
+
+/// Returns the schema of the table `name` read by a query: a stored table,
+/// a view, or one of the virtual [`information_schema`] tables.
+pub fn source_schema(name: &TableName, db: &Database) -> SqlResult<Schema> {
+    if let Some(sql) = db.view(name) {
+        return Schema::try_new(plan_view(name, sql, db)?.columns());
+    }
+    match db.table(name) {
+        Some(table) => Ok(table.schema().clone()),
+        None => information_schema::table(name, db)
+            .map(|table| table.schema().clone())
+            .ok_or_else(|| SqlError::unknown_table(name)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::types::Value;
+    use crate::executor::executor::Executor;
+    use crate::frontend::ast::ASTNode;
+    use crate::frontend::lexer::lexer;
+    use crate::frontend::parser::Parser;
+
+    fn run(db: &mut Database, sql: &str) {
+        Executor::execute(Parser::default().parse(&lexer(sql).unwrap()).unwrap(), db).unwrap();
+    }
+
+    fn plan(db: &Database, sql: &str) -> SqlResult<LogicalPlan> {
+        match Parser::default().parse(&lexer(sql).unwrap()).unwrap() {
+            ASTNode::Select(stmt) => Planner::plan_select(&stmt, db),
+            other => panic!("not a SELECT: {:?}", other),
+        }
+    }
+
+    fn shop_db() -> Database {
+        let mut db = Database::new();
+        run(&mut db, "CREATE TABLE users (id INT, name TEXT)");
+        run(&mut db, "CREATE TABLE orders (id INT, user_id INT, amount FLOAT)");
+        db
+    }
+
+    #[test]
+    fn plans_select_as_operator_tree() {
+        let db = shop_db();
+        let select = plan(
+            &db,
+            "SELECT DISTINCT u.name, o.amount FROM users u JOIN orders o ON o.user_id = u.id \
+             WHERE o.amount > 10 ORDER BY o.amount DESC LIMIT 5",
+        )
+        .unwrap();
+        assert_eq!(
+            select.to_string(),
+            "Limit 5 offset 0\n  Distinct\n    Project name, amount\n      Sort on 1 key(s)\n        Filter on o.amount\n          \
+             Join on o.user_id\n            Scan users\n            Scan orders\n"
+        );
+
+        let grouped = plan(&db, "SELECT user_id, COUNT(*) FROM orders GROUP BY user_id ORDER BY count").unwrap();
+        assert_eq!(grouped.to_string(), "Sort on 1 key(s)\n  Aggregate by [1]\n    Scan orders\n");
+        let names: Vec<_> = grouped.columns().iter().map(|c| c.name.as_str().to_string()).collect();
+        assert_eq!(names, ["user_id", "count"]);
+    }
+
+    #[test]
+    fn resolves_and_type_checks_before_running() {
+        let db = shop_db();
+        // Constants are coerced to the column type once, when planning
+        let LogicalPlan::Project { input, .. } = plan(&db, "SELECT * FROM orders WHERE amount = 1 + 2").unwrap() else {
+            panic!("expected a projection");
+        };
+        let LogicalPlan::Filter { predicate: Predicate::Compare { column, value, .. }, .. } = *input else {
+            panic!("expected a comparison");
+        };
+        assert_eq!((column.index, column.name.as_str()), (2, "amount"));
+        assert_eq!(value, ScalarExpr::Literal(Value::Float(3.0)));
+
+        // The tables are empty, so these can only fail in the planner
+        assert!(plan(&db, "SELECT nope FROM users").is_err());
+        assert!(plan(&db, "SELECT * FROM users WHERE id = 'x'").is_err());
+        assert!(plan(&db, "SELECT * FROM users WHERE id LIKE 'a%'").is_err());
+        assert!(plan(&db, "SELECT id FROM users u JOIN orders o ON o.user_id = u.id").is_err());
+        assert!(plan(&db, "SELECT name, COUNT(*) FROM users").is_err());
+
+        // A column past the end of the scope is an error, not a panic
+        let mut scope = Scope::default();
+        scope.push(&TableName::new("users").unwrap(), db.table(&TableName::new("users").unwrap()).unwrap().schema()).unwrap();
+        assert_eq!(scope.column(1).unwrap().1.name.as_str(), "name");
+        assert!(matches!(scope.column(2), Err(SqlError::Semantic { .. })));
+    }
+}
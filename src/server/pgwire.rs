@@ -0,0 +1,514 @@
+//! PostgreSQL frontend/backend protocol, version 3.
+//!
+//! Enough of the protocol for `psql` and the usual drivers to connect and
+//! run the SQL this engine supports:
+//!
+//! - **Startup**: SSL and GSS encryption requests are declined, the startup
+//!   message is accepted without authentication, and the server reports a
+//!   few parameters (`server_version`, `client_encoding`, ...).
+//! - **Simple query** (`Q`): the statements of the query run in order until
+//!   one fails. Each result is sent as a `RowDescription` and its
+//!   `DataRow`s when it has rows, then a `CommandComplete` such as
+//!   `SELECT 3` or `UPDATE 1`. An error is sent as an `ErrorResponse`,
+//!   with the position of lexical and syntax errors.
+//! - **Terminate** (`X`) closes the connection.
+//!
+//! Values are sent in text format, with the type of their column: `Int` as
+//! `int8`, `Float` as `float8`, `Text` as `text`, `Bool` as `bool`, `Date`
+//! as `date`, `Timestamp` as `timestamp` and `Blob` as `bytea`.
+//!
+//! The extended query protocol (`Parse`, `Bind`, `Execute`, ...) is not
+//! supported: its messages are answered with an error, and the following
+//! `Sync` with `ReadyForQuery`, so that a driver reports the error instead
+//! of hanging.
+
+use std::io::{self, BufReader, BufWriter, Read, Write};
+use std::net::TcpStream;
+
+use crate::core::error::SqlError;
+use crate::core::types::{DataType, Value};
+use crate::executor::executor::Output;
+use crate::executor::shared::{Session, SharedDatabase};
+use crate::frontend::ast::{ASTNode, TransactionStmt};
+use crate::frontend::parser::Parser;
+use crate::server::server::{execute, parse};
+
+/// Protocol version 3.0, the only one supported.
+const PROTOCOL_VERSION: i32 = 196_608;
+/// Request codes sent in place of a protocol version.
+const SSL_REQUEST: i32 = 80_877_103;
+const GSSENC_REQUEST: i32 = 80_877_104;
+const CANCEL_REQUEST: i32 = 80_877_102;
+
+/// Largest message accepted from a client, in bytes.
+const MAX_MESSAGE_LEN: usize = 64 << 20;
+
+/// Parameters reported to the client after startup.
+const PARAMETERS: &[(&str, &str)] = &[
+    ("server_version", "14.0"),
+    ("server_encoding", "UTF8"),
+    ("client_encoding", "UTF8"),
+    ("DateStyle", "ISO, MDY"),
+    ("TimeZone", "UTC"),
+    ("integer_datetimes", "on"),
+    ("standard_conforming_strings", "on"),
+];
+
+/// A backend message: its type byte and its body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Message {
+    pub tag: u8,
+    pub body: Vec<u8>,
+}
+
+impl Message {
+    /// Creates an empty message of type `tag`.
+    fn new(tag: u8) -> Self {
+        Message { tag, body: Vec::new() }
+    }
+
+    fn i16(mut self, value: i16) -> Self {
+        self.body.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    fn i32(mut self, value: i32) -> Self {
+        self.body.extend_from_slice(&value.to_be_bytes());
+        self
+    }
+
+    /// Appends `value` as a NUL-terminated string.
+    fn str(mut self, value: &str) -> Self {
+        self.body.extend_from_slice(value.as_bytes());
+        self.body.push(0);
+        self
+    }
+
+    /// Appends `value` as a length-prefixed field, or -1 for `NULL`.
+    fn field(self, value: Option<&[u8]>) -> Self {
+        match value {
+            Some(bytes) => {
+                let mut message = self.i32(bytes.len() as i32);
+                message.body.extend_from_slice(bytes);
+                message
+            }
+            None => self.i32(-1),
+        }
+    }
+
+    /// Writes the type byte, the length (which counts itself) and the body.
+    fn write_to(&self, out: &mut impl Write) -> io::Result<()> {
+        out.write_all(&[self.tag])?;
+        out.write_all(&(self.body.len() as i32 + 4).to_be_bytes())?;
+        out.write_all(&self.body)
+    }
+}
+
+/// Serves one PostgreSQL client until it terminates or disconnects.
+///
+/// # Arguments
+/// - `stream`: the client's connection
+/// - `db`: the database shared by every client
+/// - `parser`: the parser queries are read with
+pub fn serve_client(stream: TcpStream, db: &SharedDatabase, parser: &Parser) -> io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut writer = BufWriter::new(stream);
+
+    // Startup: answer encryption requests until the startup message comes
+    loop {
+        let Some(body) = read_startup(&mut reader)? else { return Ok(()) };
+        match body.get(..4).map(|code| i32::from_be_bytes(code.try_into().expect("4 bytes"))) {
+            Some(SSL_REQUEST | GSSENC_REQUEST) => {
+                writer.write_all(b"N")?;
+                writer.flush()?;
+            }
+            Some(PROTOCOL_VERSION) => break,
+            Some(CANCEL_REQUEST) => return Ok(()),
+            _ => {
+                error_response("08P01", "Unsupported frontend protocol", None).write_to(&mut writer)?;
+                return writer.flush();
+            }
+        }
+    }
+    Message::new(b'R').i32(0).write_to(&mut writer)?;
+    for (name, value) in PARAMETERS {
+        Message::new(b'S').str(name).str(value).write_to(&mut writer)?;
+    }
+
+    let mut session = db.session();
+    // After an extended query message, the rest of it is ignored until Sync
+    let mut skipping = false;
+    loop {
+        if !skipping {
+            ready_for_query(session.in_transaction()).write_to(&mut writer)?;
+            writer.flush()?;
+        }
+        let Some((tag, body)) = read_message(&mut reader)? else { return Ok(()) };
+        match tag {
+            b'Q' => {
+                let sql = String::from_utf8_lossy(body.strip_suffix(&[0]).unwrap_or(&body)).into_owned();
+                for message in simple_query(&sql, parser, &mut session) {
+                    message.write_to(&mut writer)?;
+                }
+            }
+            b'X' => return Ok(()),
+            b'S' => skipping = false,
+            b'H' => writer.flush()?,
+            b'P' | b'B' | b'D' | b'E' | b'C' => {
+                if !skipping {
+                    let message = "Only the simple query protocol is supported";
+                    error_response("0A000", message, None).write_to(&mut writer)?;
+                    writer.flush()?;
+                    skipping = true;
+                }
+            }
+            other => {
+                let message = format!("Unsupported message type '{}'", other as char);
+                error_response("0A000", &message, None).write_to(&mut writer)?;
+            }
+        }
+    }
+}
+
+/// Runs the statements of a simple query and returns the messages
+/// answering it, without the final `ReadyForQuery`. The query is read
+/// with `parser`.
+pub fn simple_query(sql: &str, parser: &Parser, session: &mut Session) -> Vec<Message> {
+    let statements = match parse(sql, parser) {
+        Ok(statements) => statements,
+        Err(e) => return vec![error_message(&e)],
+    };
+    if statements.is_empty() {
+        return vec![Message::new(b'I')];
+    }
+    let results = execute(statements.clone(), session);
+    let mut messages = Vec::new();
+    for ((ast, _), result) in statements.iter().zip(results) {
+        match result {
+            Ok(out) => {
+                let tag = command_tag(ast, &out);
+                push_output(out, &mut messages);
+                messages.push(Message::new(b'C').str(&tag));
+            }
+            Err(e) => messages.push(error_message(&e)),
+        }
+    }
+    messages
+}
+
+/// Appends the messages carrying the rows, plan lines or notices of `out`.
+fn push_output(out: Output, messages: &mut Vec<Message>) {
+    match out {
+        Output::Rows { columns, rows } => {
+            messages.push(row_description(columns.iter().map(|c| (c.name.as_str(), &c.dtype))));
+            for row in rows {
+                let mut message = Message::new(b'D').i16(row.values().len() as i16);
+                for value in row.values() {
+                    message = message.field(value_text(value).as_deref().map(str::as_bytes));
+                }
+                messages.push(message);
+            }
+        }
+        Output::Plan(steps) => {
+            messages.push(row_description([("QUERY PLAN", &DataType::Text)]));
+            for step in steps {
+                messages.push(Message::new(b'D').i16(1).field(Some(step.as_bytes())));
+            }
+        }
+        Output::Copied { failed, .. } => {
+            for (line, error) in failed {
+                let text = format!("line {}: {}", line, error);
+                messages.push(Message::new(b'N').str("SWARNING").str("VWARNING").str("C01000").str(&format!("M{}", text)).str(""));
+            }
+        }
+        Output::None | Output::Affected(_) => {}
+    }
+}
+
+/// Returns the tag of the `CommandComplete` message reporting `ast`.
+fn command_tag(ast: &ASTNode, out: &Output) -> String {
+    let count = match out {
+        Output::Rows { rows, .. } => rows.len(),
+        Output::Affected(n) => *n,
+        Output::Copied { rows, .. } => *rows,
+        Output::Plan(_) | Output::None => 0,
+    };
+    match ast {
+        ASTNode::CreateTable(_) => "CREATE TABLE".to_string(),
+        ASTNode::CreateSchema(_) => "CREATE SCHEMA".to_string(),
+        ASTNode::CreateView(_) => "CREATE VIEW".to_string(),
+        ASTNode::CreateIndex(_) => "CREATE INDEX".to_string(),
+        ASTNode::Insert(stmt) if stmt.query.is_some() || stmt.on_conflict.is_some() => format!("INSERT 0 {}", count),
+        ASTNode::Insert(stmt) => format!("INSERT 0 {}", stmt.rows.len()),
+        ASTNode::Select(_) | ASTNode::Describe(_) => format!("SELECT {}", count),
+        ASTNode::Update(_) => format!("UPDATE {}", count),
+        ASTNode::Delete(_) => format!("DELETE {}", count),
+        ASTNode::Truncate(_) => "TRUNCATE TABLE".to_string(),
+        ASTNode::AlterTable(_) => "ALTER TABLE".to_string(),
+        ASTNode::Transaction(TransactionStmt::Begin) => "BEGIN".to_string(),
+        ASTNode::Transaction(TransactionStmt::Commit) => "COMMIT".to_string(),
+        ASTNode::Transaction(TransactionStmt::Rollback | TransactionStmt::RollbackTo(_)) => "ROLLBACK".to_string(),
+        ASTNode::Transaction(TransactionStmt::Savepoint(_)) => "SAVEPOINT".to_string(),
+        ASTNode::Transaction(TransactionStmt::Release(_)) => "RELEASE".to_string(),
+        ASTNode::Copy(_) => format!("COPY {}", count),
+        ASTNode::Explain(_) => "EXPLAIN".to_string(),
+        ASTNode::Analyze(_) => "ANALYZE".to_string(),
+        ASTNode::ShowTables => "SHOW".to_string(),
+        ASTNode::Checkpoint => "CHECKPOINT".to_string(),
+        ASTNode::Pragma(_) => "PRAGMA".to_string(),
+        ASTNode::Attach { .. } => "ATTACH".to_string(),
+        ASTNode::Detach(_) => "DETACH".to_string(),
+    }
+}
+
+/// Describes the columns, given by name and type, of the rows that follow.
+fn row_description<'a>(columns: impl IntoIterator<Item = (&'a str, &'a DataType)>) -> Message {
+    let columns: Vec<_> = columns.into_iter().collect();
+    let mut message = Message::new(b'T').i16(columns.len() as i16);
+    for (name, dtype) in columns {
+        let (oid, size) = type_oid(dtype);
+        // No source table or column; text format, no type modifier
+        message = message.str(name).i32(0).i16(0).i32(oid).i16(size).i32(-1).i16(0);
+    }
+    message
+}
+
+/// Returns the OID and size (-1 when variable) of the PostgreSQL type
+/// used for `dtype`.
+fn type_oid(dtype: &DataType) -> (i32, i16) {
+    match dtype {
+        DataType::Int => (20, 8),
+        DataType::Float => (701, 8),
+        DataType::Text => (25, -1),
+        DataType::Bool => (16, 1),
+        DataType::Date => (1082, 4),
+        DataType::Timestamp => (1114, 8),
+        DataType::Blob => (17, -1),
+    }
+}
+
+/// Returns `value` in PostgreSQL text format, or `None` for `NULL`.
+fn value_text(value: &Value) -> Option<String> {
+    Some(match value {
+        Value::Null => return None,
+        Value::Bool(b) => if *b { "t" } else { "f" }.to_string(),
+        Value::Float(f) if f.is_infinite() => if *f > 0.0 { "Infinity" } else { "-Infinity" }.to_string(),
+        Value::Blob(bytes) => format!("\\x{}", bytes.iter().map(|b| format!("{:02x}", b)).collect::<String>()),
+        Value::Text(s) => s.clone(),
+        other => other.to_string(),
+    })
+}
+
+/// Builds the `ErrorResponse` for `error`.
+///
+/// | Kind                     | SQLSTATE | Meaning                         |
+/// |--------------------------|----------|---------------------------------|
+/// | `Lex`, `Parse`           | `42601`  | syntax error                    |
+/// | `Semantic`               | `42000`  | syntax error or access rule violation |
+/// | `Constraint`             | `23000`  | integrity constraint violation  |
+/// | `Execution`              | `22000`  | data exception                  |
+/// | `Deadlock`               | `40P01`  | deadlock detected               |
+/// | `Conflict`               | `40001`  | serialization failure           |
+/// | `Io`                     | `58000`  | system error                    |
+/// | `Internal`               | `XX000`  | internal error                  |
+fn error_message(error: &SqlError) -> Message {
+    let code = match error {
+        SqlError::Lex { .. } | SqlError::Parse { .. } => "42601",
+        SqlError::Semantic { .. } => "42000",
+        SqlError::Constraint { .. } => "23000",
+        SqlError::Execution { .. } => "22000",
+        SqlError::Deadlock { .. } => "40P01",
+        SqlError::Conflict { .. } => "40001",
+        SqlError::Io { .. } => "58000",
+        SqlError::Internal { .. } => "XX000",
+    };
+    // Positions count characters from 1
+    error_response(code, &error.to_string(), error.span().map(|span| span.start + 1))
+}
+
+/// Builds an `ErrorResponse` with severity `ERROR`.
+fn error_response(code: &str, message: &str, position: Option<usize>) -> Message {
+    let mut response = Message::new(b'E')
+        .str("SERROR")
+        .str("VERROR")
+        .str(&format!("C{}", code))
+        .str(&format!("M{}", message));
+    if let Some(position) = position {
+        response = response.str(&format!("P{}", position));
+    }
+    response.str("")
+}
+
+/// Tells the client the server waits for a query, and whether a
+/// transaction is open (`T`) or not (`I`).
+fn ready_for_query(in_transaction: bool) -> Message {
+    let mut message = Message::new(b'Z');
+    message.body.push(if in_transaction { b'T' } else { b'I' });
+    message
+}
+
+/// Reads the body of a startup packet, which has a length but no type
+/// byte. Returns `None` if the client disconnected first.
+fn read_startup(reader: &mut impl Read) -> io::Result<Option<Vec<u8>>> {
+    let mut length = [0; 4];
+    if !read_or_eof(reader, &mut length)? {
+        return Ok(None);
+    }
+    read_body(reader, length).map(Some)
+}
+
+/// Reads a message: its type byte and its body. Returns `None` if the
+/// client disconnected first.
+fn read_message(reader: &mut impl Read) -> io::Result<Option<(u8, Vec<u8>)>> {
+    let mut tag = [0; 1];
+    if !read_or_eof(reader, &mut tag)? {
+        return Ok(None);
+    }
+    let mut length = [0; 4];
+    reader.read_exact(&mut length)?;
+    Ok(Some((tag[0], read_body(reader, length)?)))
+}
+
+/// Reads the body following `length`, which counts itself.
+fn read_body(reader: &mut impl Read, length: [u8; 4]) -> io::Result<Vec<u8>> {
+    let length = i32::from_be_bytes(length);
+    let size = usize::try_from(length).ok().and_then(|n| n.checked_sub(4)).filter(|&n| n <= MAX_MESSAGE_LEN);
+    let size = size.ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("Invalid message length {}", length)))?;
+    let mut body = vec![0; size];
+    reader.read_exact(&mut body)?;
+    Ok(body)
+}
+
+/// Fills `buf`, or returns `false` if the input ends before its first byte.
+fn read_or_eof(reader: &mut impl Read, buf: &mut [u8]) -> io::Result<bool> {
+    match reader.read(&mut buf[..1])? {
+        0 => Ok(false),
+        _ => {
+            reader.read_exact(&mut buf[1..])?;
+            Ok(true)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::server::server::{Protocol, Server};
+    use std::net::SocketAddr;
+
+    /// Returns the tag and the body, as text, of each message.
+    fn describe(messages: &[Message]) -> Vec<(char, String)> {
+        messages.iter().map(|m| (m.tag as char, String::from_utf8_lossy(&m.body).replace('\0', "|"))).collect()
+    }
+
+    #[test]
+    fn answers_simple_queries() {
+        let db = SharedDatabase::default();
+        let mut session = db.session();
+        let parser = Parser::default();
+        let created = simple_query("CREATE TABLE t (n Int, ok Bool); INSERT INTO t VALUES (1, TRUE), (2, NULL);", &parser, &mut session);
+        assert_eq!(describe(&created), [('C', "CREATE TABLE|".to_string()), ('C', "INSERT 0 2|".to_string())]);
+
+        let selected = simple_query("SELECT * FROM t", &parser, &mut session);
+        assert_eq!(selected[0].tag, b'T');
+        // Two columns: n as int8 (OID 20), ok as bool (OID 16)
+        assert_eq!(&selected[0].body[..4], b"\0\x02n\0");
+        assert_eq!(&selected[0].body[10..14], &20_i32.to_be_bytes());
+        assert_eq!(selected[1], Message::new(b'D').i16(2).field(Some(b"1")).field(Some(b"t")));
+        assert_eq!(selected[2], Message::new(b'D').i16(2).field(Some(b"2")).field(None));
+        assert_eq!(describe(&selected[3..]), [('C', "SELECT 2|".to_string())]);
+
+        assert_eq!(describe(&simple_query("UPDATE t SET n = 5 WHERE n = 1", &parser, &mut session)), [('C', "UPDATE 1|".to_string())]);
+        assert_eq!(describe(&simple_query(" ; ", &parser, &mut session)), [('I', String::new())]);
+    }
+
+    #[test]
+    fn reports_errors_with_sqlstate_and_position() {
+        let db = SharedDatabase::default();
+        let mut session = db.session();
+        let parser = Parser::default();
+        let messages = simple_query("SELECT * FROM WHERE", &parser, &mut session);
+        assert_eq!(messages.len(), 1);
+        let (tag, body) = &describe(&messages)[0];
+        assert_eq!(*tag, 'E');
+        assert!(body.starts_with("SERROR|VERROR|C42601|MExpected table name after FROM"), "{}", body);
+        assert!(body.ends_with("|P15||"), "{}", body);
+
+        // Statements before the error keep their results
+        let messages = simple_query("CREATE TABLE t (n Int); SELECT x FROM t; CREATE TABLE u (n Int)", &parser, &mut session);
+        assert_eq!(describe(&messages)[0], ('C', "CREATE TABLE|".to_string()));
+        assert!(describe(&messages)[1].1.contains("C42000"));
+        assert_eq!(messages.len(), 2);
+        assert_eq!(db.read(|db| db.table_count()), 1);
+    }
+
+    /// A minimal client reading whole messages.
+    struct Client {
+        stream: TcpStream,
+    }
+
+    impl Client {
+        fn connect(addr: SocketAddr) -> Client {
+            let mut stream = TcpStream::connect(addr).unwrap();
+            // SSL is declined, then the startup message is accepted
+            stream.write_all(&8_i32.to_be_bytes()).unwrap();
+            stream.write_all(&SSL_REQUEST.to_be_bytes()).unwrap();
+            let mut answer = [0; 1];
+            stream.read_exact(&mut answer).unwrap();
+            assert_eq!(&answer, b"N");
+            let params = b"user\0me\0database\0db\0\0";
+            stream.write_all(&(8 + params.len() as i32).to_be_bytes()).unwrap();
+            stream.write_all(&PROTOCOL_VERSION.to_be_bytes()).unwrap();
+            stream.write_all(params).unwrap();
+            let mut client = Client { stream };
+            let startup = client.until_ready();
+            assert_eq!(startup[0], (b'R', 0_i32.to_be_bytes().to_vec()));
+            client
+        }
+
+        fn until_ready(&mut self) -> Vec<(u8, Vec<u8>)> {
+            let mut messages = Vec::new();
+            loop {
+                let message = read_message(&mut self.stream).unwrap().unwrap();
+                if message.0 == b'Z' {
+                    messages.push(message);
+                    return messages;
+                }
+                messages.push(message);
+            }
+        }
+
+        fn send(&mut self, tag: u8, body: &[u8]) {
+            Message { tag, body: body.to_vec() }.write_to(&mut self.stream).unwrap();
+        }
+    }
+
+    #[test]
+    fn serves_clients_over_tcp() {
+        let server = Server::bind("127.0.0.1:0", SharedDatabase::default()).unwrap().with_protocol(Protocol::Postgres);
+        let addr = server.local_addr().unwrap();
+        std::thread::spawn(move || server.run());
+        let mut client = Client::connect(addr);
+
+        client.send(b'Q', b"BEGIN; CREATE TABLE t (s Text); INSERT INTO t VALUES ('hi')\0");
+        let answer = client.until_ready();
+        let tags: Vec<u8> = answer.iter().map(|(tag, _)| *tag).collect();
+        assert_eq!(tags, b"CCCZ");
+        assert_eq!(answer[3].1, b"T");
+
+        client.send(b'Q', b"COMMIT; SELECT s FROM t\0");
+        let answer = client.until_ready();
+        let tags: Vec<u8> = answer.iter().map(|(tag, _)| *tag).collect();
+        assert_eq!(tags, b"CTDCZ");
+        assert_eq!(answer[2].1, [&1_i16.to_be_bytes()[..], &2_i32.to_be_bytes(), b"hi"].concat());
+        assert_eq!(answer[4].1, b"I");
+
+        // The extended protocol is refused once, and Sync ends the refusal
+        client.send(b'P', b"\0SELECT 1\0\0\0");
+        client.send(b'B', b"\0\0\0\0\0\0\0\0");
+        client.send(b'S', b"");
+        let tags: Vec<u8> = client.until_ready().iter().map(|(tag, _)| *tag).collect();
+        assert_eq!(tags, b"EZ");
+        client.send(b'X', b"");
+    }
+}
@@ -0,0 +1,400 @@
+//! TCP server: the database over the network.
+//!
+//! A [`Server`] accepts connections and serves each one on its own thread.
+//! All clients share one [`SharedDatabase`], each through its own
+//! [`Session`]: statements that only read run side by side on the last
+//! committed version, the others in transactions that run side by side
+//! too, locking the rows they use (see [`shared`](crate::executor::shared)).
+//! Clients speak the line protocol below or, with [`Protocol::Postgres`], the PostgreSQL one (see
+//! [`pgwire`](crate::server::pgwire)).
+//!
+//! # Line protocol
+//! A client sends SQL terminated by a newline; the line may hold several
+//! statements separated by `;`. For every line, the server writes back one
+//! line of JSON (see [`respond`]):
+//!
+//! ```text
+//! > CREATE TABLE t (n Int); INSERT INTO t VALUES (1); SELECT n FROM t
+//! < {"results":[{"status":"OK"},{"status":"OK"},{"columns":["n"],"rows":[[1]]}]}
+//! > SELECT x FROM t
+//! < {"results":[],"error":{"kind":"Semantic","code":"E2001","message":"Unknown column 'x'"}}
+//! ```
+//!
+//! Statements run in order until one fails, as in
+//! [`Session::execute_script`]; the results of those that ran come first,
+//! then the error.
+//!
+//! # Transactions
+//! Changes made by a client after `BEGIN` are seen by other clients once
+//! it runs `COMMIT`. Until then, other clients read the database as it was
+//! before the transaction, and wait to change the rows it changed (see
+//! [`Session`]). When transactions wait for each other, one of them fails
+//! with a `Deadlock` error and is rolled back; one changing a row that
+//! another client changed and committed since it began fails with a
+//! `Conflict` error and is rolled back. A transaction still open
+//! when its client disconnects is rolled back.
+//!
+//! When the server serves a database opened from a directory (see
+//! [`SharedDatabase::with_store`]), each commit is appended to its
+//! write-ahead log, which is checkpointed once it grows past the
+//! checkpoint size, or when a client runs `CHECKPOINT`.
+
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream, ToSocketAddrs};
+use std::thread;
+
+use crate::core::error::{SqlError, SqlResult};
+use crate::executor::executor::Output;
+use crate::executor::shared::{Session, SharedDatabase};
+use crate::frontend::ast::ASTNode;
+use crate::frontend::lexer::lexer;
+use crate::frontend::parser::Parser;
+use crate::storage::json::Json;
+use crate::server::pgwire;
+use crate::storage::storage::value_to_json;
+
+/// Port the server listens on unless told otherwise.
+pub const DEFAULT_PORT: u16 = 5433;
+
+/// The protocol a [`Server`] speaks with its clients.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Protocol {
+    /// Lines of SQL answered by lines of JSON (see the module docs).
+    #[default]
+    Line,
+    /// The PostgreSQL frontend/backend protocol, for `psql` and drivers.
+    Postgres,
+}
+
+impl Protocol {
+    /// Returns the protocol named `name` (`line`, or `postgres` or `pg`,
+    /// in any case), if there is one.
+    pub fn parse(name: &str) -> Option<Protocol> {
+        match name.to_ascii_lowercase().as_str() {
+            "line" => Some(Protocol::Line),
+            "postgres" | "pg" => Some(Protocol::Postgres),
+            _ => None,
+        }
+    }
+}
+
+/// A listening server.
+pub struct Server {
+    listener: TcpListener,
+    db: SharedDatabase,
+    protocol: Protocol,
+    parser: Parser,
+}
+
+impl Server {
+    /// Listens on `addr` for clients of `db`.
+    ///
+    /// # Arguments
+    /// - `addr`: the address to listen on, e.g. `("127.0.0.1", 5433)`
+    /// - `db`: the database to serve, possibly shared with other threads,
+    ///   and with the store it was opened from
+    ///
+    /// # Returns
+    /// The server, or `Err(SqlError)` if the address cannot be bound.
+    pub fn bind(addr: impl ToSocketAddrs, db: SharedDatabase) -> SqlResult<Server> {
+        let listener = TcpListener::bind(addr).map_err(|e| SqlError::new_io(&format!("Cannot listen: {}", e)))?;
+        Ok(Server { listener, db, protocol: Protocol::default(), parser: Parser::default() })
+    }
+
+    /// Speaks `protocol` with clients instead of the line protocol.
+    pub fn with_protocol(mut self, protocol: Protocol) -> Self {
+        self.protocol = protocol;
+        self
+    }
+
+    /// Reads the statements of clients with `parser`, and so with its type
+    /// aliases, instead of the default one.
+    pub fn with_parser(mut self, parser: Parser) -> Self {
+        self.parser = parser;
+        self
+    }
+
+    /// Returns the address the server listens on.
+    pub fn local_addr(&self) -> SqlResult<SocketAddr> {
+        self.listener.local_addr().map_err(|e| SqlError::new_io(&e.to_string()))
+    }
+
+    /// Accepts clients until the process ends, serving each on a new
+    /// thread. A failed connection is reported and does not stop the
+    /// server.
+    pub fn run(self) {
+        for stream in self.listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    eprintln!("Cannot accept a connection: {}", e);
+                    continue;
+                }
+            };
+            let db = self.db.clone();
+            let protocol = self.protocol;
+            let parser = self.parser.clone();
+            thread::spawn(move || {
+                let peer = stream.peer_addr().map(|a| a.to_string()).unwrap_or_else(|_| "client".to_string());
+                let served = match protocol {
+                    Protocol::Line => serve_client(stream, &db, &parser),
+                    Protocol::Postgres => pgwire::serve_client(stream, &db, &parser),
+                };
+                if let Err(e) = served {
+                    eprintln!("{}: {}", peer, e);
+                }
+            });
+        }
+    }
+}
+
+/// Answers the lines sent by one client until it disconnects.
+fn serve_client(stream: TcpStream, db: &SharedDatabase, parser: &Parser) -> io::Result<()> {
+    let lines = BufReader::new(stream.try_clone()?).lines();
+    let mut writer = stream;
+    let mut session = db.session();
+    for line in lines {
+        let line = line?;
+        let response = respond(&line, parser, &mut session);
+        writeln!(writer, "{}", response)?;
+    }
+    Ok(())
+}
+
+/// Runs the statements in `sql`, read with `parser`, and describes their
+/// results.
+///
+/// # Returns
+/// A JSON object holding `results`, one entry per statement run, and
+/// `error` if one failed. An entry is:
+/// - `{"columns": [...], "rows": [[...], ...]}` for rows
+/// - `{"affected": n}` for UPDATE, DELETE, and INSERT with a query or
+///   conflict handling
+/// - `{"copied": n, "failed": [{"line": l, "error": "..."}, ...]}` for COPY
+/// - `{"plan": ["...", ...]}` for EXPLAIN
+/// - `{"status": "OK"}` otherwise
+///
+/// The error is `{"kind": ..., "code": ..., "message": ...}`.
+pub fn respond(sql: &str, parser: &Parser, session: &mut Session) -> Json {
+    let statements = match parse(sql, parser) {
+        Ok(statements) => statements,
+        Err(e) => return response(Vec::new(), Some(&e)),
+    };
+    let mut results = Vec::new();
+    let mut error = None;
+    for result in execute(statements, session) {
+        match result {
+            Ok(out) => results.push(output_to_json(out)),
+            Err(e) => error = Some(e),
+        }
+    }
+    response(results, error.as_ref())
+}
+
+/// Parses the statements of a request with `parser`, each with its source
+/// text, which
+/// the log of the database keeps for statements that change more than
+/// rows (see [`Session::execute_sql`]).
+///
+/// # Returns
+/// The statements in order, or the first syntax error.
+pub fn parse(sql: &str, parser: &Parser) -> SqlResult<Vec<(ASTNode, String)>> {
+    let statements = lexer(sql).and_then(|tokens| parser.parse_script_spans(&tokens))?;
+    Ok(statements
+        .into_iter()
+        .map(|(ast, span)| {
+            let source = match span {
+                Some(span) => sql.chars().skip(span.start).take(span.len()).collect(),
+                None => sql.to_string(),
+            };
+            (ast, source)
+        })
+        .collect())
+}
+
+/// Executes the statements of a request in `session`, in order until one
+/// fails, as [`Session::execute_script`] does. `CHECKPOINT` writes the
+/// last committed version as a new snapshot (see
+/// [`SharedDatabase::checkpoint`]).
+///
+/// # Returns
+/// The results of the statements run; if one failed, its error is the
+/// last result.
+pub fn execute(statements: Vec<(ASTNode, String)>, session: &mut Session) -> Vec<SqlResult<Output>> {
+    let mut results = Vec::with_capacity(statements.len());
+    for (ast, sql) in statements {
+        let result = match ast {
+            ASTNode::Checkpoint if session.in_transaction() => {
+                Err(SqlError::new_execution("Cannot checkpoint during a transaction"))
+            }
+            ASTNode::Checkpoint => session.database().checkpoint().map(|_| Output::None),
+            ast => session.execute_sql(ast, &sql),
+        };
+        let failed = result.is_err();
+        results.push(result);
+        if failed {
+            break;
+        }
+    }
+    results
+}
+
+/// Builds the object answering a line.
+fn response(results: Vec<Json>, error: Option<&SqlError>) -> Json {
+    let mut members = vec![("results".to_string(), Json::Array(results))];
+    if let Some(e) = error {
+        members.push((
+            "error".to_string(),
+            Json::Object(vec![
+                ("kind".to_string(), Json::String(e.kind().to_string())),
+                ("code".to_string(), Json::String(e.code().to_string())),
+                ("message".to_string(), Json::String(e.to_string())),
+            ]),
+        ));
+    }
+    Json::Object(members)
+}
+
+/// Describes the result of one statement (see [`respond`]).
+fn output_to_json(out: Output) -> Json {
+    let number = |n: usize| Json::Number(n.to_string());
+    let member = |key: &str, value: Json| (key.to_string(), value);
+    Json::Object(match out {
+        Output::None => vec![member("status", Json::String("OK".to_string()))],
+        Output::Affected(n) => vec![member("affected", number(n))],
+        Output::Rows { columns, rows } => vec![
+            member("columns", Json::Array(columns.iter().map(|c| Json::String(c.name.as_str().to_string())).collect())),
+            member(
+                "rows",
+                Json::Array(rows.iter().map(|row| Json::Array(row.values().iter().map(value_to_json).collect())).collect()),
+            ),
+        ],
+        Output::Copied { rows, failed } => vec![
+            member("copied", number(rows)),
+            member(
+                "failed",
+                Json::Array(
+                    failed
+                        .into_iter()
+                        .map(|(line, error)| Json::Object(vec![member("line", number(line)), member("error", Json::String(error))]))
+                        .collect(),
+                ),
+            ),
+        ],
+        Output::Plan(steps) => vec![member("plan", Json::Array(steps.into_iter().map(Json::String).collect()))],
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::db::Database;
+    use crate::core::types::{DataType, TableName};
+    use crate::storage::recovery::recover;
+    use crate::storage::store::Store;
+    use crate::testing::TempDir;
+
+    /// Starts a server on a free port and returns its address.
+    fn start(db: Database) -> SocketAddr {
+        let server = Server::bind("127.0.0.1:0", SharedDatabase::new(db)).unwrap();
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+        addr
+    }
+
+    /// A connected client, sending lines and reading the answers.
+    struct Client {
+        reader: BufReader<TcpStream>,
+        writer: TcpStream,
+    }
+
+    impl Client {
+        fn connect(addr: SocketAddr) -> Client {
+            let writer = TcpStream::connect(addr).unwrap();
+            Client { reader: BufReader::new(writer.try_clone().unwrap()), writer }
+        }
+
+        fn send(&mut self, sql: &str) -> String {
+            writeln!(self.writer, "{}", sql).unwrap();
+            let mut line = String::new();
+            self.reader.read_line(&mut line).unwrap();
+            line.trim_end().to_string()
+        }
+    }
+
+    #[test]
+    fn answers_each_line_with_json() {
+        let addr = start(Database::new());
+        let mut client = Client::connect(addr);
+
+        assert_eq!(
+            client.send("CREATE TABLE t (n Int, s Text); INSERT INTO t VALUES (1, 'a\"b'), (2, NULL)"),
+            r#"{"results":[{"status":"OK"},{"status":"OK"}]}"#
+        );
+        assert_eq!(client.send("SELECT * FROM t"), r#"{"results":[{"columns":["n","s"],"rows":[[1,"a\"b"],[2,null]]}]}"#);
+        assert_eq!(
+            client.send("UPDATE t SET n = 3 WHERE n = 2; SELECT nope FROM t; DELETE FROM t"),
+            r#"{"results":[{"affected":1}],"error":{"kind":"Semantic","code":"E2001","message":"Unknown column 'nope'"}}"#
+        );
+        assert!(client.send("SELEC").contains(r#""kind":"Parse""#));
+        assert_eq!(client.send(""), r#"{"results":[]}"#);
+
+        // Another client sees the same database
+        let mut other = Client::connect(addr);
+        assert_eq!(other.send("SELECT n FROM t"), r#"{"results":[{"columns":["n"],"rows":[[1],[3]]}]}"#);
+    }
+
+    #[test]
+    fn reads_statements_with_its_parser() {
+        let mut parser = Parser::default();
+        parser.register_alias("NUMBER", DataType::Float).unwrap();
+        let server = Server::bind("127.0.0.1:0", SharedDatabase::new(Database::new())).unwrap().with_parser(parser);
+        let addr = server.local_addr().unwrap();
+        thread::spawn(move || server.run());
+
+        let mut client = Client::connect(addr);
+        assert_eq!(client.send("CREATE TABLE t (x NUMBER); INSERT INTO t VALUES (1.5)"), r#"{"results":[{"status":"OK"},{"status":"OK"}]}"#);
+        assert!(client.send("CREATE TABLE u (x DECIMAL)").contains("Unknown type"));
+    }
+
+    #[test]
+    fn a_transaction_is_isolated_and_rolled_back_on_disconnect() {
+        let addr = start(Database::new());
+        let mut first = Client::connect(addr);
+        first.send("CREATE TABLE t (n Int)");
+        first.send("BEGIN; INSERT INTO t VALUES (1)");
+
+        // Other clients read the committed rows, and write other rows
+        assert_eq!(Client::connect(addr).send("SELECT n FROM t"), r#"{"results":[{"columns":["n"],"rows":[]}]}"#);
+        let waiting = thread::spawn(move || Client::connect(addr).send("INSERT INTO t VALUES (3); SELECT n FROM t"));
+        first.send("INSERT INTO t VALUES (2)");
+        drop(first);
+        assert_eq!(waiting.join().unwrap(), r#"{"results":[{"status":"OK"},{"columns":["n"],"rows":[[3]]}]}"#);
+    }
+
+    #[test]
+    fn logs_commits_and_checkpoints_on_demand() {
+        let tmp = TempDir::new("server");
+        let dir = tmp.path();
+        let (db, store) = Store::open(dir).unwrap();
+        let db = SharedDatabase::with_store(db, store);
+        let mut session = db.session();
+        let parser = Parser::default();
+
+        // Commits are logged, not saved as snapshots
+        let generation = || std::fs::read_to_string(dir.join("CURRENT")).unwrap_or_default();
+        let before = generation();
+        respond("CREATE TABLE t (n Int); BEGIN; INSERT INTO t VALUES (1)", &parser, &mut session);
+        assert_eq!(recover(dir).unwrap().table(&TableName::new("t").unwrap()).unwrap().rows().len(), 0);
+        respond("COMMIT", &parser, &mut session);
+        assert_eq!(recover(dir).unwrap().table(&TableName::new("t").unwrap()).unwrap().rows().len(), 1);
+        assert_eq!(generation(), before);
+
+        // CHECKPOINT saves at once, but not in the middle of a transaction
+        assert_eq!(respond("CHECKPOINT", &parser, &mut session).to_string(), r#"{"results":[{"status":"OK"}]}"#);
+        assert_ne!(generation(), before);
+        assert!(respond("BEGIN; CHECKPOINT", &parser, &mut session).to_string().contains("Cannot checkpoint"));
+        let memory = SharedDatabase::new(Database::new());
+        assert!(respond("CHECKPOINT", &parser, &mut memory.session()).to_string().contains("needs a database stored"));
+    }
+}
@@ -0,0 +1,210 @@
+//! Buffer pool: an in-memory cache of heap file pages.
+//!
+//! Reading a page through the pool only touches the disk on a miss, so
+//! repeated scans of a table that fits in the pool are served from memory.
+//! [`HeapFile::scan`] reads every page through a pool.
+//!
+//! # Usage
+//! - [`BufferPool::pin`] loads a page if needed and keeps it in memory until
+//!   the matching [`BufferPool::unpin`].
+//! - A page modified through [`BufferPool::page_mut`] is marked dirty and
+//!   written back when it is evicted or on [`BufferPool::flush`].
+//! - When the pool is full, the least recently used unpinned page is
+//!   evicted. If every page is pinned, `pin` fails.
+
+use std::collections::HashMap;
+
+use crate::core::error::{SqlError, SqlResult};
+use crate::storage::heap::{HeapFile, PageId};
+use crate::storage::page::Page;
+
+/// Number of pages held by the pool of a table loaded from a heap file.
+pub const DEFAULT_CAPACITY: usize = 64;
+
+/// A cached page.
+#[derive(Debug)]
+struct Frame {
+    page: Page,
+    pins: usize,
+    dirty: bool,
+    last_used: u64,
+}
+
+/// A fixed-capacity page cache for one heap file, with LRU eviction.
+#[derive(Debug)]
+pub struct BufferPool {
+    capacity: usize,
+    frames: HashMap<PageId, Frame>,
+    clock: u64,
+    hits: u64,
+    misses: u64,
+}
+
+impl BufferPool {
+    /// Creates a pool holding at most `capacity` pages.
+    ///
+    /// # Panics
+    /// If `capacity` is 0.
+    pub fn new(capacity: usize) -> Self {
+        assert!(capacity > 0, "buffer pool capacity must be positive");
+        BufferPool { capacity, frames: HashMap::new(), clock: 0, hits: 0, misses: 0 }
+    }
+
+    /// Returns the maximum number of cached pages.
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// Returns the number of pages currently cached.
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Returns `true` if no page is cached.
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Returns the number of pins served from memory.
+    pub fn hits(&self) -> u64 {
+        self.hits
+    }
+
+    /// Returns the number of pins that read the disk.
+    pub fn misses(&self) -> u64 {
+        self.misses
+    }
+
+    /// Pins page `id` of `heap`, reading it from disk if it is not cached.
+    ///
+    /// # Returns
+    /// The page, or `Err(SqlError)` if it cannot be read or every cached
+    /// page is pinned.
+    pub fn pin(&mut self, heap: &mut HeapFile, id: PageId) -> SqlResult<&Page> {
+        self.clock += 1;
+        if self.frames.contains_key(&id) {
+            self.hits += 1;
+        } else {
+            if self.frames.len() >= self.capacity {
+                self.evict(heap)?;
+            }
+            let page = heap.read_page(id)?;
+            self.misses += 1;
+            self.frames.insert(id, Frame { page, pins: 0, dirty: false, last_used: 0 });
+        }
+        let frame = self.frames.get_mut(&id).expect("page was just cached");
+        frame.pins += 1;
+        frame.last_used = self.clock;
+        Ok(&frame.page)
+    }
+
+    /// Releases one pin on page `id`.
+    ///
+    /// # Returns
+    /// `Err(SqlError)` if the page is not pinned.
+    pub fn unpin(&mut self, id: PageId) -> SqlResult<()> {
+        match self.frames.get_mut(&id) {
+            Some(frame) if frame.pins > 0 => {
+                frame.pins -= 1;
+                Ok(())
+            }
+            _ => Err(SqlError::new_execution(&format!("Buffer pool: page {} is not pinned", id))),
+        }
+    }
+
+    /// Returns a pinned page for modification and marks it dirty.
+    pub fn page_mut(&mut self, id: PageId) -> Option<&mut Page> {
+        let frame = self.frames.get_mut(&id).filter(|frame| frame.pins > 0)?;
+        frame.dirty = true;
+        Some(&mut frame.page)
+    }
+
+    /// Writes every dirty page back to `heap`.
+    pub fn flush(&mut self, heap: &mut HeapFile) -> SqlResult<()> {
+        let mut ids: Vec<PageId> = self.frames.iter().filter(|(_, f)| f.dirty).map(|(id, _)| *id).collect();
+        ids.sort_unstable();
+        for id in ids {
+            let frame = self.frames.get_mut(&id).expect("listed page is cached");
+            heap.write_page(id, &frame.page)?;
+            frame.dirty = false;
+        }
+        Ok(())
+    }
+
+    /// Drops the least recently used unpinned page, writing it back first
+    /// if it is dirty.
+    fn evict(&mut self, heap: &mut HeapFile) -> SqlResult<()> {
+        let victim = self
+            .frames
+            .iter()
+            .filter(|(_, frame)| frame.pins == 0)
+            .min_by_key(|(_, frame)| frame.last_used)
+            .map(|(id, _)| *id)
+            .ok_or_else(|| SqlError::new_execution(&format!("Buffer pool: all {} pages are pinned", self.capacity)))?;
+        let frame = self.frames.remove(&victim).expect("victim is cached");
+        if frame.dirty {
+            heap.write_page(victim, &frame.page)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::row::Row;
+    use crate::core::schema::{Column, Schema};
+    use crate::core::table::Table;
+    use crate::core::types::{ColumnName, DataType, TableName, Value};
+    use crate::testing::TempDir;
+
+    fn heap(name: &str, rows: i64) -> (TempDir, HeapFile) {
+        let tmp = TempDir::new(name);
+        let path = tmp.join("t.heap");
+        let schema = Schema::try_new(vec![Column::new(ColumnName::new("note").unwrap(), DataType::Text)]).unwrap();
+        let mut table = Table::create(TableName::new("t").unwrap(), schema);
+        let rows = (0..rows)
+            .map(|i| Row::from_values(vec![Value::Text(format!("{:0500}", i))], table.schema()).unwrap())
+            .collect();
+        table.insert_many(rows).unwrap();
+        let heap = HeapFile::create(&path, &table).unwrap();
+        (tmp, heap)
+    }
+
+    #[test]
+    fn repeated_scans_hit_the_cache() {
+        let (_tmp, mut heap) = heap("pool_scan", 30);
+        let data_pages = heap.page_count() as u64 - 1;
+        let mut pool = BufferPool::new(16);
+
+        assert_eq!(heap.scan(&mut pool).count(), 30);
+        assert_eq!((pool.hits(), pool.misses()), (0, data_pages));
+        assert_eq!(heap.load_table(&mut pool).unwrap().rows().len(), 30);
+        assert_eq!((pool.hits(), pool.misses()), (data_pages, data_pages));
+        // Scans leave no page pinned
+        assert!(pool.unpin(1).is_err());
+    }
+
+    #[test]
+    fn lru_eviction_respects_pins_and_writes_back() {
+        let (_tmp, mut heap) = heap("pool_evict", 30);
+        let mut pool = BufferPool::new(2);
+
+        pool.pin(&mut heap, 1).unwrap();
+        pool.pin(&mut heap, 2).unwrap();
+        // Both frames pinned: nothing to evict
+        assert!(pool.pin(&mut heap, 3).is_err());
+
+        pool.page_mut(1).unwrap().delete(0);
+        pool.unpin(1).unwrap();
+        assert!(pool.unpin(1).is_err());
+        // Page 1 is the only unpinned page, so it is evicted and written back
+        pool.pin(&mut heap, 3).unwrap();
+        assert_eq!(pool.len(), 2);
+        assert_eq!(heap.read_page(1).unwrap().get(0), None);
+
+        pool.page_mut(2).unwrap().delete(0);
+        pool.flush(&mut heap).unwrap();
+        assert_eq!(heap.scan(&mut BufferPool::new(1)).count(), 28);
+    }
+}
@@ -0,0 +1,233 @@
+//! Compression of table files.
+//!
+//! Table files can be compressed with gzip (feature `gzip`) or zstd
+//! (feature `zstd`). Nothing records how a file was compressed: both
+//! formats start with a fixed header, so [`decompress`] recognizes them,
+//! and reads any other file as it is. Compressed and plain files can thus
+//! sit side by side, and a database saved with compression loads like any
+//! other (see [`decode_table`](crate::storage::storage::decode_table)).
+//!
+//! A file compressed with a format whose feature is disabled cannot be
+//! read; the error names the feature to enable.
+
+use std::borrow::Cow;
+
+use crate::core::error::{SqlError, SqlResult};
+
+/// First bytes of a gzip stream.
+pub const GZIP_MAGIC: &[u8] = &[0x1f, 0x8b];
+
+/// First bytes of a zstd frame.
+pub const ZSTD_MAGIC: &[u8] = &[0x28, 0xb5, 0x2f, 0xfd];
+
+/// How table files are compressed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Compression {
+    /// Files are written as they are encoded.
+    #[default]
+    None,
+    /// gzip, needing the `gzip` feature.
+    Gzip,
+    /// zstd, needing the `zstd` feature: faster, and usually smaller.
+    Zstd,
+}
+
+impl Compression {
+    /// Every compression, in the order they are listed to users.
+    pub const ALL: [Compression; 3] = [Compression::None, Compression::Gzip, Compression::Zstd];
+
+    /// Returns the compression named `name` (`none`, `gzip` or `zstd`, in
+    /// any case), if there is one.
+    pub fn parse(name: &str) -> Option<Compression> {
+        Compression::ALL.into_iter().find(|compression| compression.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the name of the compression, as accepted by
+    /// [`Compression::parse`].
+    pub fn name(self) -> &'static str {
+        match self {
+            Compression::None => "none",
+            Compression::Gzip => "gzip",
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    /// Returns `true` if this build can compress and decompress with it.
+    pub fn is_available(self) -> bool {
+        match self {
+            Compression::None => true,
+            Compression::Gzip => cfg!(feature = "gzip"),
+            Compression::Zstd => cfg!(feature = "zstd"),
+        }
+    }
+
+    /// Returns the compression whose header starts `bytes`, or
+    /// [`Compression::None`].
+    pub fn detect(bytes: &[u8]) -> Compression {
+        if bytes.starts_with(GZIP_MAGIC) {
+            Compression::Gzip
+        } else if bytes.starts_with(ZSTD_MAGIC) {
+            Compression::Zstd
+        } else {
+            Compression::None
+        }
+    }
+
+    /// The error for a compression this build cannot handle.
+    fn unavailable(self) -> SqlError {
+        SqlError::new_io(&format!(
+            "Storage: {} compression is not available; build with the '{}' feature",
+            self.name(),
+            self.name()
+        ))
+    }
+}
+
+/// Compresses `bytes` with `compression`.
+///
+/// # Returns
+/// The compressed bytes, `bytes` themselves for [`Compression::None`], or
+/// `Err(SqlError)` if the feature of `compression` is disabled.
+pub fn compress(bytes: &[u8], compression: Compression) -> SqlResult<Cow<'_, [u8]>> {
+    match compression {
+        Compression::None => Ok(Cow::Borrowed(bytes)),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => {
+            use std::io::Write;
+            let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+            encoder.write_all(bytes).and_then(|_| encoder.finish()).map(Cow::Owned).map_err(codec_error)
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => zstd::encode_all(bytes, 0).map(Cow::Owned).map_err(codec_error),
+        #[allow(unreachable_patterns)]
+        other => Err(other.unavailable()),
+    }
+}
+
+/// Decompresses `bytes`, detecting their compression from their header
+/// (see [`Compression::detect`]).
+///
+/// # Returns
+/// The decompressed bytes, `bytes` themselves if they are not compressed,
+/// or `Err(SqlError)` if they are corrupt or the feature of their
+/// compression is disabled.
+pub fn decompress(bytes: &[u8]) -> SqlResult<Cow<'_, [u8]>> {
+    match Compression::detect(bytes) {
+        Compression::None => Ok(Cow::Borrowed(bytes)),
+        #[cfg(feature = "gzip")]
+        Compression::Gzip => {
+            use std::io::Read;
+            let mut out = Vec::new();
+            flate2::read::GzDecoder::new(bytes).read_to_end(&mut out).map(|_| Cow::Owned(out)).map_err(codec_error)
+        }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => zstd::decode_all(bytes).map(Cow::Owned).map_err(codec_error),
+        #[allow(unreachable_patterns)]
+        other => Err(other.unavailable()),
+    }
+}
+
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+fn codec_error(e: std::io::Error) -> SqlError {
+    SqlError::new_io(&format!("Storage: corrupt compressed file: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::db::Database;
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    use crate::core::types::TableName;
+    use crate::executor::executor::Executor;
+    use crate::frontend::lexer::lexer;
+    use crate::frontend::parser::Parser;
+    use crate::storage::recovery::{checkpoint_compressed, recover};
+    use crate::storage::storage::StorageFormat;
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    use crate::storage::store::Store;
+    use crate::testing::TempDir;
+
+    /// Returns a database with a table `t` holding a few rows.
+    fn sample_db() -> Database {
+        let mut db = Database::new();
+        let sql = "CREATE TABLE t (id INT PRIMARY KEY, note TEXT, data BLOB); \
+                   INSERT INTO t VALUES (1, 'one', NULL), (2, 'two', NULL), (3, NULL, NULL)";
+        for result in Executor::execute_script(Parser::default().parse_script(&lexer(sql).unwrap()).unwrap(), &mut db) {
+            result.unwrap();
+        }
+        db
+    }
+
+    /// Checkpoints a database with `compression`, then recovers it and
+    /// opens it again.
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    fn checkpoint_and_recover(compression: Compression) {
+        let tmp = TempDir::new(compression.name());
+        let dir = tmp.join("db");
+        let db = sample_db();
+        checkpoint_compressed(&db, &dir, compression).unwrap();
+        let file = std::fs::read(dir.join("snapshot-1").join(format!("t.{}", StorageFormat::Heap.extension()))).unwrap();
+        assert_eq!(Compression::detect(&file), compression);
+
+        let rows = |db: &Database| db.table(&TableName::new("t").unwrap()).unwrap().rows().clone();
+        assert_eq!(rows(&recover(&dir).unwrap()), rows(&db));
+        let (reopened, _store) = Store::open(&dir).unwrap();
+        assert_eq!(rows(&reopened), rows(&db));
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn gzip_snapshots_are_recovered() {
+        checkpoint_and_recover(Compression::Gzip);
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn zstd_snapshots_are_recovered() {
+        checkpoint_and_recover(Compression::Zstd);
+    }
+
+    #[cfg(not(feature = "gzip"))]
+    #[test]
+    fn a_gzip_snapshot_needs_the_feature_to_recover() {
+        let tmp = TempDir::new("gzip_missing");
+        let dir = tmp.join("db");
+        checkpoint_compressed(&sample_db(), &dir, Compression::None).unwrap();
+        // As a build with the feature would have written it
+        std::fs::write(dir.join("snapshot-1").join(format!("t.{}", StorageFormat::Heap.extension())), [GZIP_MAGIC, b"rest"].concat()).unwrap();
+
+        let e = recover(&dir).unwrap_err();
+        assert!(e.message().contains("gzip compression is not available; build with the 'gzip' feature"), "{}", e);
+    }
+
+    #[test]
+    fn compressed_bytes_are_detected_and_restored() {
+        let data = "{\"name\":\"users\",\"rows\":[]}".repeat(50).into_bytes();
+        assert_eq!(Compression::detect(&data), Compression::None);
+        assert!(matches!(decompress(&data).unwrap(), Cow::Borrowed(_)));
+
+        for compression in Compression::ALL {
+            if !compression.is_available() {
+                assert!(compress(&data, compression).is_err());
+                continue;
+            }
+            let packed = compress(&data, compression).unwrap();
+            assert_eq!(Compression::detect(&packed), compression);
+            assert_eq!(decompress(&packed).unwrap(), data);
+            if compression != Compression::None {
+                assert!(packed.len() < data.len() / 4);
+            }
+        }
+        assert_eq!(Compression::parse("ZSTD"), Some(Compression::Zstd));
+        assert_eq!(Compression::parse("lz4"), None);
+    }
+
+    #[test]
+    fn a_disabled_compression_names_its_feature() {
+        let header = [GZIP_MAGIC, b"rest"].concat();
+        match decompress(&header) {
+            Err(e) if !Compression::Gzip.is_available() => assert!(e.message().contains("'gzip' feature")),
+            other => assert!(other.is_err(), "a truncated stream is corrupt"),
+        }
+    }
+}
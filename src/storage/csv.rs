@@ -0,0 +1,254 @@
+//! CSV reading and writing for `COPY ... FROM` and `COPY ... TO`.
+//!
+//! Records follow RFC 4180: fields are separated by commas, and a field
+//! containing a comma, a quote or a line break is enclosed in double quotes,
+//! with inner quotes doubled. Lines may end with `\n` or `\r\n`.
+//!
+//! An empty unquoted field is `NULL`; an empty quoted field (`""`) is the
+//! empty string.
+
+use std::io::{BufRead, Write};
+
+use crate::core::error::{SqlError, SqlResult};
+use crate::core::types::{DataType, Value};
+
+/// One field of a record: `None` for an empty unquoted field.
+pub type CsvField = Option<String>;
+
+/// A record with the line it starts on (1-based).
+#[derive(Debug, Clone, PartialEq)]
+pub struct CsvRecord {
+    pub line: usize,
+    pub fields: Vec<CsvField>,
+}
+
+/// Streaming reader yielding one [`CsvRecord`] at a time.
+///
+/// Blank lines are skipped. After a malformed record (an unterminated
+/// quote), the reader stops.
+pub struct CsvReader<R> {
+    input: R,
+    line: usize,
+    record_line: usize,
+    done: bool,
+}
+
+impl<R: BufRead> CsvReader<R> {
+    /// Creates a reader over `input`.
+    pub fn new(input: R) -> Self {
+        CsvReader { input, line: 0, record_line: 0, done: false }
+    }
+
+    /// Returns the line on which the last record read (or failed) starts.
+    pub fn record_line(&self) -> usize {
+        self.record_line
+    }
+
+    /// Reads the next physical line, without its line ending.
+    fn next_line(&mut self) -> SqlResult<Option<String>> {
+        let mut buf = String::new();
+        let read = self
+            .input
+            .read_line(&mut buf)
+            .map_err(|e| SqlError::new_io(&format!("CSV: cannot read line {}: {}", self.line + 1, e)))?;
+        if read == 0 {
+            return Ok(None);
+        }
+        self.line += 1;
+        if buf.ends_with('\n') {
+            buf.pop();
+            if buf.ends_with('\r') {
+                buf.pop();
+            }
+        }
+        Ok(Some(buf))
+    }
+
+    fn read_record(&mut self) -> SqlResult<Option<CsvRecord>> {
+        let mut text = loop {
+            match self.next_line()? {
+                None => return Ok(None),
+                Some(line) if line.is_empty() => continue,
+                Some(line) => break line,
+            }
+        };
+        let start = self.line;
+        self.record_line = start;
+
+        let mut fields = Vec::new();
+        let mut field = String::new();
+        let mut quoted = false;
+        let mut in_quotes = false;
+        let mut pos = 0;
+        loop {
+            let Some(c) = text[pos..].chars().next() else {
+                if in_quotes {
+                    // The quoted field continues on the next line
+                    match self.next_line()? {
+                        Some(next) => {
+                            field.push('\n');
+                            text = next;
+                            pos = 0;
+                            continue;
+                        }
+                        None => {
+                            return Err(SqlError::new_io(&format!("CSV: unterminated quoted field starting on line {}", start)))
+                        }
+                    }
+                }
+                fields.push((quoted || !field.is_empty()).then_some(field));
+                return Ok(Some(CsvRecord { line: start, fields }));
+            };
+            pos += c.len_utf8();
+            match c {
+                '"' if in_quotes => {
+                    if text[pos..].starts_with('"') {
+                        field.push('"');
+                        pos += 1;
+                    } else {
+                        in_quotes = false;
+                    }
+                }
+                '"' if field.is_empty() && !quoted => {
+                    quoted = true;
+                    in_quotes = true;
+                }
+                ',' if !in_quotes => {
+                    fields.push((quoted || !field.is_empty()).then(|| std::mem::take(&mut field)));
+                    quoted = false;
+                }
+                c => field.push(c),
+            }
+        }
+    }
+}
+
+impl<R: BufRead> Iterator for CsvReader<R> {
+    type Item = SqlResult<CsvRecord>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let record = self.read_record().transpose();
+        if !matches!(record, Some(Ok(_))) {
+            self.done = true;
+        }
+        record
+    }
+}
+
+/// Converts a field to a value of a column of type `dtype`.
+///
+/// Booleans accept `true`/`false`, `t`/`f` and `1`/`0` in any case, dates
+/// and timestamps use ISO-8601, and blobs are written in hex.
+///
+/// # Returns
+/// The value, `Value::Null` for a `None` field, or `Err(SqlError)` if the
+/// text does not parse as `dtype`.
+pub fn field_to_value(field: &CsvField, dtype: &DataType) -> SqlResult<Value> {
+    let Some(text) = field else { return Ok(Value::Null) };
+    let trimmed = text.trim();
+    let value = match dtype {
+        DataType::Text => Some(Value::Text(text.clone())),
+        DataType::Int => trimmed.parse().ok().map(Value::Int),
+        DataType::Float => trimmed.parse().ok().map(Value::Float),
+        DataType::Bool => match trimmed.to_ascii_lowercase().as_str() {
+            "true" | "t" | "1" => Some(Value::Bool(true)),
+            "false" | "f" | "0" => Some(Value::Bool(false)),
+            _ => None,
+        },
+        DataType::Date | DataType::Timestamp => {
+            Some(dtype.coerce(Value::Text(trimmed.to_string()))).filter(|v| dtype.matches(v))
+        }
+        DataType::Blob => decode_hex(trimmed).map(Value::Blob),
+    };
+    value.ok_or_else(|| SqlError::new_io(&format!("Invalid {:?} value '{}'", dtype, text)))
+}
+
+/// Converts a value to a field, the inverse of [`field_to_value`].
+pub fn value_to_field(value: &Value) -> CsvField {
+    match value {
+        Value::Null => None,
+        Value::Text(s) => Some(s.clone()),
+        Value::Blob(bytes) => Some(bytes.iter().map(|b| format!("{:02x}", b)).collect()),
+        other => Some(other.to_string()),
+    }
+}
+
+/// Writes one record followed by `\n`.
+///
+/// A field is quoted when it is empty, so that it is not read back as
+/// `NULL`, or when it contains a comma, a quote, a line break or
+/// surrounding spaces.
+pub fn write_record<W: Write>(out: &mut W, fields: &[CsvField]) -> std::io::Result<()> {
+    for (i, field) in fields.iter().enumerate() {
+        if i > 0 {
+            out.write_all(b",")?;
+        }
+        let Some(text) = field else { continue };
+        let needs_quotes = text.is_empty()
+            || text.trim() != text
+            || text.contains([',', '"', '\n', '\r']);
+        if needs_quotes {
+            write!(out, "\"{}\"", text.replace('"', "\"\""))?;
+        } else {
+            out.write_all(text.as_bytes())?;
+        }
+    }
+    out.write_all(b"\n")
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..hex.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn read(text: &str) -> Vec<SqlResult<CsvRecord>> {
+        CsvReader::new(text.as_bytes()).collect()
+    }
+
+    #[test]
+    fn reads_quoted_and_multiline_fields() {
+        let records = read("a,\"b, \"\"c\"\"\",\r\n\n\"two\nlines\",\"\",x\n");
+        let fields = |i: usize| records[i].as_ref().unwrap().fields.clone();
+        assert_eq!(fields(0), vec![Some("a".into()), Some("b, \"c\"".into()), None]);
+        assert_eq!(records[1].as_ref().unwrap().line, 3);
+        assert_eq!(fields(1), vec![Some("two\nlines".into()), Some(String::new()), Some("x".into())]);
+        assert_eq!(records.len(), 2);
+
+        let records = read("1\n\"open\n2\n");
+        assert!(records[1].is_err());
+        assert_eq!(records.len(), 2);
+    }
+
+    #[test]
+    fn written_records_read_back() {
+        let fields = vec![Some("a,b".into()), None, Some(String::new()), Some("say \"hi\"\n".into()), Some(" x".into())];
+        let mut out = Vec::new();
+        write_record(&mut out, &fields).unwrap();
+        assert_eq!(String::from_utf8(out.clone()).unwrap(), "\"a,b\",,\"\",\"say \"\"hi\"\"\n\",\" x\"\n");
+        assert_eq!(read(std::str::from_utf8(&out).unwrap())[0].as_ref().unwrap().fields, fields);
+    }
+
+    #[test]
+    fn fields_convert_to_column_types() {
+        let field = |s: &str| Some(s.to_string());
+        assert_eq!(field_to_value(&field(" 42 "), &DataType::Int).unwrap(), Value::Int(42));
+        assert_eq!(field_to_value(&field("T"), &DataType::Bool).unwrap(), Value::Bool(true));
+        assert_eq!(field_to_value(&field("1970-01-02"), &DataType::Date).unwrap(), Value::Date(1));
+        assert_eq!(field_to_value(&field("00ff"), &DataType::Blob).unwrap(), Value::Blob(vec![0, 255]));
+        assert_eq!(field_to_value(&None, &DataType::Int).unwrap(), Value::Null);
+        assert!(field_to_value(&field("abc"), &DataType::Int).is_err());
+        assert!(field_to_value(&field("2024-13-01"), &DataType::Date).is_err());
+    }
+}
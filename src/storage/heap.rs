@@ -0,0 +1,344 @@
+//! Heap files: tables stored as a sequence of fixed-size pages.
+//!
+//! Unlike the JSON and binary table files, which must be decoded as a whole,
+//! a heap file is read one [`Page`] at a time. Scans read their pages
+//! through a [`BufferPool`], so that repeated scans of the same file are
+//! served from memory. Database snapshots store their tables as heap files (see
+//! [`StorageFormat::Heap`](crate::storage::storage::StorageFormat::Heap)).
+//!
+//! # Layout
+//! - Page 0 holds the table header: the name, schema, indexes and next
+//!   rowid, encoded as an empty [`binary`] table
+//!   (see [`Table::emptied`]) in slot 0.
+//! - Every other page holds rows, one tuple per row, encoded with
+//!   [`binary::encode_row`], so that rows keep their rowids.
+//!
+//! The heap file stores rows as they are given. Constraints are enforced by
+//! [`Table`] when rows are inserted, and again when a heap is loaded with
+//! [`HeapFile::load_table`].
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::core::error::{SqlError, SqlResult};
+use crate::core::row::Row;
+use crate::core::schema::Schema;
+use crate::core::table::{Rowid, Table};
+use crate::core::types::TableName;
+use crate::storage::binary;
+use crate::storage::buffer::BufferPool;
+use crate::storage::page::{Page, MAX_TUPLE_SIZE, PAGE_SIZE};
+
+/// Number of a page within a heap file.
+pub type PageId = u32;
+
+/// An open heap file.
+#[derive(Debug)]
+pub struct HeapFile {
+    path: PathBuf,
+    file: File,
+    header: Table,
+    page_count: PageId,
+}
+
+impl HeapFile {
+    /// Writes `table` to a new heap file at `path`, replacing any existing
+    /// file.
+    ///
+    /// # Returns
+    /// The open heap file, or `Err(SqlError)` on I/O failure or if a row is
+    /// larger than a page.
+    pub fn create(path: &Path, table: &Table) -> SqlResult<Self> {
+        let mut file = File::create(path).map_err(|e| io_error("cannot create", path, e))?;
+        file.write_all(&encode(table)?)
+            .and_then(|_| file.sync_data())
+            .map_err(|e| io_error("cannot write", path, e))?;
+        Self::open(path)
+    }
+
+    /// Opens an existing heap file and reads its header page.
+    pub fn open(path: &Path) -> SqlResult<Self> {
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .open(path)
+            .map_err(|e| io_error("cannot open", path, e))?;
+        let len = file.metadata().map_err(|e| io_error("cannot read", path, e))?.len();
+        if len == 0 || !len.is_multiple_of(PAGE_SIZE as u64) {
+            return Err(corrupt(path, "size is not a whole number of pages"));
+        }
+        let page_count = PageId::try_from(len / PAGE_SIZE as u64).map_err(|_| corrupt(path, "too many pages"))?;
+
+        let mut data = [0; PAGE_SIZE];
+        (&file).read_exact(&mut data).map_err(|e| io_error("cannot read", path, e))?;
+        let header = Page::from_bytes(data).ok_or_else(|| corrupt(path, "invalid page 0"))?;
+        let header = decode_header(&header).map_err(|e| corrupt(path, e.message()))?;
+        Ok(HeapFile { path: path.to_path_buf(), file, header, page_count })
+    }
+
+    /// Returns the path of the file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the name of the stored table.
+    pub fn name(&self) -> &TableName {
+        self.header.name()
+    }
+
+    /// Returns the schema of the stored table.
+    pub fn schema(&self) -> &Schema {
+        self.header.schema()
+    }
+
+    /// Returns the number of pages, header page included.
+    pub fn page_count(&self) -> PageId {
+        self.page_count
+    }
+
+    /// Reads page `id` from disk.
+    pub fn read_page(&mut self, id: PageId) -> SqlResult<Page> {
+        if id >= self.page_count {
+            return Err(SqlError::new_io(&format!("Heap file: page {} out of range", id)));
+        }
+        let mut data = [0; PAGE_SIZE];
+        self.file
+            .seek(SeekFrom::Start(id as u64 * PAGE_SIZE as u64))
+            .and_then(|_| self.file.read_exact(&mut data))
+            .map_err(|e| io_error("cannot read", &self.path, e))?;
+        Page::from_bytes(data).ok_or_else(|| corrupt(&self.path, &format!("invalid page {}", id)))
+    }
+
+    /// Writes page `id` to disk, growing the file by one page if `id` is
+    /// the next page.
+    pub fn write_page(&mut self, id: PageId, page: &Page) -> SqlResult<()> {
+        if id > self.page_count {
+            return Err(SqlError::new_io(&format!("Heap file: page {} out of range", id)));
+        }
+        self.file
+            .seek(SeekFrom::Start(id as u64 * PAGE_SIZE as u64))
+            .and_then(|_| self.file.write_all(page.as_bytes()))
+            .map_err(|e| io_error("cannot write", &self.path, e))?;
+        if id == self.page_count {
+            self.page_count += 1;
+        }
+        Ok(())
+    }
+
+    /// Decodes the rows stored in a data page, with their rowids.
+    pub fn page_rows(&self, page: &Page) -> SqlResult<Vec<(Rowid, Row)>> {
+        page.tuples().map(|(_, tuple)| binary::decode_row(tuple, self.schema())).collect()
+    }
+
+    /// Iterates over the stored rows and their rowids, pinning one page at
+    /// a time in `pool`.
+    ///
+    /// # Arguments
+    /// * `pool` - The buffer pool of this heap file; a page it already
+    ///   holds is not read again
+    pub fn scan<'a>(&'a mut self, pool: &'a mut BufferPool) -> HeapScan<'a> {
+        HeapScan { heap: self, pool, next_page: 1, rows: Vec::new().into_iter() }
+    }
+
+    /// Reads every row through `pool` into an in-memory [`Table`], checking
+    /// constraints.
+    pub fn load_table(&mut self, pool: &mut BufferPool) -> SqlResult<Table> {
+        let rows = self.scan(pool).collect::<SqlResult<Vec<(Rowid, Row)>>>()?;
+        build_table(self.header.clone(), rows)
+    }
+}
+
+/// Iterator over the rows of a heap file, returned by [`HeapFile::scan`].
+pub struct HeapScan<'a> {
+    heap: &'a mut HeapFile,
+    pool: &'a mut BufferPool,
+    next_page: PageId,
+    rows: std::vec::IntoIter<(Rowid, Row)>,
+}
+
+impl Iterator for HeapScan<'_> {
+    type Item = SqlResult<(Rowid, Row)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(row) = self.rows.next() {
+                return Some(Ok(row));
+            }
+            if self.next_page >= self.heap.page_count {
+                return None;
+            }
+            let id = self.next_page;
+            self.next_page += 1;
+            let rows = self.pool.pin(self.heap, id).and_then(|page| self.heap.page_rows(page));
+            match rows.and_then(|rows| self.pool.unpin(id).map(|_| rows)) {
+                Ok(rows) => self.rows = rows.into_iter(),
+                Err(e) => {
+                    // Stop after the first error
+                    self.next_page = self.heap.page_count;
+                    return Some(Err(e));
+                }
+            }
+        }
+    }
+}
+
+/// Returns `true` if `bytes` start with the header page of a heap file.
+pub fn is_heap(bytes: &[u8]) -> bool {
+    let Some(data) = bytes.get(..PAGE_SIZE) else { return false };
+    let page = Page::from_bytes(data.try_into().expect("one page"));
+    page.and_then(|page| page.get(0).map(|tuple| tuple.starts_with(binary::MAGIC))).unwrap_or(false)
+}
+
+/// Returns `true` if the file at `path` is a heap file, reading only its
+/// first page.
+pub fn is_heap_file(path: &Path) -> SqlResult<bool> {
+    let mut data = vec![0; PAGE_SIZE];
+    let mut file = File::open(path).map_err(|e| io_error("cannot open", path, e))?;
+    match file.read_exact(&mut data) {
+        Ok(()) => Ok(is_heap(&data)),
+        Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => Ok(false),
+        Err(e) => Err(io_error("cannot read", path, e)),
+    }
+}
+
+/// Encodes `table` as the pages of a heap file, one after the other.
+///
+/// # Returns
+/// The bytes of the file, or `Err(SqlError)` if the header or a row is
+/// larger than a page.
+pub fn encode(table: &Table) -> SqlResult<Vec<u8>> {
+    let mut header = Page::new();
+    if header.insert(&binary::encode_table(&table.emptied())).is_none() {
+        return Err(SqlError::new_io("Heap file: table header is larger than a page"));
+    }
+    let mut out = header.as_bytes().to_vec();
+
+    let mut page = Page::new();
+    for (rowid, row) in table.rowids().iter().zip(table.rows()) {
+        let tuple = encode_tuple(*rowid, row)?;
+        if page.insert(&tuple).is_none() {
+            out.extend_from_slice(page.as_bytes());
+            page = Page::new();
+            page.insert(&tuple).expect("tuple fits in an empty page");
+        }
+    }
+    if page.slot_count() > 0 {
+        out.extend_from_slice(page.as_bytes());
+    }
+    Ok(out)
+}
+
+/// Decodes the bytes of a whole heap file, as written by [`encode`].
+pub fn decode(bytes: &[u8]) -> SqlResult<Table> {
+    if bytes.is_empty() || !bytes.len().is_multiple_of(PAGE_SIZE) {
+        return Err(SqlError::new_io("Heap file: size is not a whole number of pages"));
+    }
+    let mut pages = bytes.chunks_exact(PAGE_SIZE).enumerate().map(|(id, data)| {
+        Page::from_bytes(data.try_into().expect("one page"))
+            .ok_or_else(|| SqlError::new_io(&format!("Heap file: invalid page {}", id)))
+    });
+    let header = decode_header(&pages.next().expect("at least one page")?)?;
+    let mut rows = Vec::new();
+    for page in pages {
+        for (_, tuple) in page?.tuples() {
+            rows.push(binary::decode_row(tuple, header.schema())?);
+        }
+    }
+    build_table(header, rows)
+}
+
+/// Reads the empty table stored in the header page.
+fn decode_header(page: &Page) -> SqlResult<Table> {
+    binary::decode_table(page.get(0).ok_or_else(|| SqlError::new_io("missing table header"))?)
+}
+
+/// Fills `header`, an empty table, with `rows`, giving them back their
+/// rowids.
+fn build_table(mut header: Table, rows: Vec<(Rowid, Row)>) -> SqlResult<Table> {
+    let next_rowid = header.next_rowid();
+    let (rowids, rows): (Vec<Rowid>, Vec<Row>) = rows.into_iter().unzip();
+    header.insert_many(rows)?;
+    header.restore_rowids(rowids, next_rowid)?;
+    Ok(header)
+}
+
+/// Encodes a row as a tuple, checking that it fits in a page.
+fn encode_tuple(rowid: Rowid, row: &Row) -> SqlResult<Vec<u8>> {
+    let tuple = binary::encode_row(rowid, row.values());
+    if tuple.len() > MAX_TUPLE_SIZE {
+        return Err(SqlError::new_io(&format!(
+            "Heap file: row of {} bytes exceeds the maximum of {} bytes",
+            tuple.len(),
+            MAX_TUPLE_SIZE
+        )));
+    }
+    Ok(tuple)
+}
+
+fn io_error(action: &str, path: &Path, e: std::io::Error) -> SqlError {
+    SqlError::new_io(&format!("Heap file: {} '{}': {}", action, path.display(), e))
+}
+
+fn corrupt(path: &Path, what: &str) -> SqlError {
+    SqlError::new_io(&format!("Heap file: corrupt file '{}': {}", path.display(), what))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::schema::Column;
+    use crate::core::types::{ColumnName, DataType, Value};
+    use crate::testing::TempDir;
+    use std::fs;
+
+    fn table(rows: i64) -> Table {
+        let schema = Schema::try_new(vec![
+            Column::new(ColumnName::new("id").unwrap(), DataType::Int),
+            Column::new(ColumnName::new("note").unwrap(), DataType::Text),
+        ])
+        .unwrap();
+        let mut table = Table::create(TableName::new("t").unwrap(), schema);
+        let rows = (0..rows)
+            .map(|i| Row::from_values(vec![Value::Int(i), Value::Text("x".repeat(100))], table.schema()).unwrap())
+            .collect();
+        table.insert_many(rows).unwrap();
+        table
+    }
+
+    #[test]
+    fn create_open_scan() {
+        let tmp = TempDir::new("heap");
+        let path = tmp.join("t.heap");
+        let mut original = table(200);
+        original.delete_where(|row| Ok(row.values()[0] == Value::Int(0))).unwrap();
+        HeapFile::create(&path, &original).unwrap();
+
+        let mut heap = HeapFile::open(&path).unwrap();
+        assert_eq!(heap.name().as_str(), "t");
+        assert!(heap.page_count() > 2, "rows span several pages");
+        let loaded = heap.load_table(&mut BufferPool::new(4)).unwrap();
+        assert_eq!(loaded.rows(), original.rows());
+        // Rows keep their rowids, and new rows go on from them
+        assert_eq!((loaded.rowids(), loaded.next_rowid()), (original.rowids(), original.next_rowid()));
+
+        let bytes = fs::read(&path).unwrap();
+        assert!(is_heap(&bytes) && is_heap_file(&path).unwrap());
+        assert_eq!(decode(&bytes).unwrap().rowids(), original.rowids());
+    }
+
+    #[test]
+    fn rejects_bad_files_and_rows() {
+        let tmp = TempDir::new("heap_bad");
+        let path = tmp.join("bad.heap");
+        fs::write(&path, [0; 100]).unwrap();
+        assert!(HeapFile::open(&path).is_err());
+        assert!(!is_heap_file(&path).unwrap());
+
+        assert_eq!(HeapFile::create(&path, &table(0)).unwrap().page_count(), 1);
+        let mut huge = table(0);
+        let row = Row::from_values(vec![Value::Int(0), Value::Text("x".repeat(PAGE_SIZE))], huge.schema()).unwrap();
+        huge.insert_checked(row).unwrap();
+        assert!(HeapFile::create(&path, &huge).is_err());
+    }
+}
@@ -0,0 +1,282 @@
+//! Minimal JSON reader and writer for the storage layer.
+//!
+//! The crate has no dependencies, so files are encoded with this small
+//! [`Json`] tree instead of a serialization framework. Numbers keep their
+//! source text, so 64-bit integers round-trip without passing through `f64`.
+
+use std::fmt;
+
+use crate::core::error::{SqlError, SqlResult};
+
+/// A parsed JSON document.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Json {
+    Null,
+    Bool(bool),
+    /// A number, as written in the source.
+    Number(String),
+    String(String),
+    Array(Vec<Json>),
+    /// An object, with its members in source order.
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    /// Parses a complete JSON document.
+    ///
+    /// # Example
+    /// ```
+    /// use mini_rust_sgbd::storage::json::Json;
+    ///
+    /// let doc = Json::parse(r#"{"id": 1, "tags": ["a", null]}"#).unwrap();
+    /// assert_eq!(doc.get("id"), Some(&Json::Number("1".to_string())));
+    /// assert_eq!(doc.to_string(), r#"{"id":1,"tags":["a",null]}"#);
+    /// ```
+    pub fn parse(text: &str) -> SqlResult<Json> {
+        let mut parser = JsonParser { chars: text.char_indices().peekable(), text };
+        let value = parser.value()?;
+        parser.skip_whitespace();
+        match parser.chars.next() {
+            None => Ok(value),
+            Some((pos, _)) => Err(parser.error(pos, "trailing characters")),
+        }
+    }
+
+    /// Returns the member `key` of an object.
+    pub fn get(&self, key: &str) -> Option<&Json> {
+        match self {
+            Json::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    /// Returns the string content, if this is a string.
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            Json::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the elements, if this is an array.
+    pub fn as_array(&self) -> Option<&[Json]> {
+        match self {
+            Json::Array(items) => Some(items),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Json {
+    /// Writes compact JSON with no whitespace.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Json::Null => write!(f, "null"),
+            Json::Bool(b) => write!(f, "{}", b),
+            Json::Number(n) => write!(f, "{}", n),
+            Json::String(s) => write!(f, "{}", quote(s)),
+            Json::Array(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+            Json::Object(members) => {
+                write!(f, "{{")?;
+                for (i, (key, value)) in members.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ",")?;
+                    }
+                    write!(f, "{}:{}", quote(key), value)?;
+                }
+                write!(f, "}}")
+            }
+        }
+    }
+}
+
+/// Returns `s` as a JSON string literal, with quotes and escapes.
+pub fn quote(s: &str) -> String {
+    let mut out = String::with_capacity(s.len() + 2);
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+/// Recursive-descent parser over the characters of a document.
+struct JsonParser<'a> {
+    chars: std::iter::Peekable<std::str::CharIndices<'a>>,
+    text: &'a str,
+}
+
+impl JsonParser<'_> {
+    fn error(&self, pos: usize, what: &str) -> SqlError {
+        SqlError::new_io(&format!("Invalid JSON at position {}: {}", pos, what))
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.chars.peek(), Some((_, ' ' | '\n' | '\r' | '\t'))) {
+            self.chars.next();
+        }
+    }
+
+    fn value(&mut self) -> SqlResult<Json> {
+        self.skip_whitespace();
+        match self.chars.peek().copied() {
+            Some((_, '{')) => self.object(),
+            Some((_, '[')) => self.array(),
+            Some((_, '"')) => self.string().map(Json::String),
+            Some((_, '-' | '0'..='9')) => Ok(self.number()),
+            Some((pos, _)) => {
+                for (word, value) in [("null", Json::Null), ("true", Json::Bool(true)), ("false", Json::Bool(false))] {
+                    if self.text[pos..].starts_with(word) {
+                        for _ in 0..word.len() {
+                            self.chars.next();
+                        }
+                        return Ok(value);
+                    }
+                }
+                Err(self.error(pos, "unexpected character"))
+            }
+            None => Err(self.error(self.text.len(), "unexpected end of input")),
+        }
+    }
+
+    fn object(&mut self) -> SqlResult<Json> {
+        self.chars.next();
+        let mut members = Vec::new();
+        self.skip_whitespace();
+        if let Some((_, '}')) = self.chars.peek() {
+            self.chars.next();
+            return Ok(Json::Object(members));
+        }
+        loop {
+            self.skip_whitespace();
+            let key = self.string()?;
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ':')) => {}
+                Some((pos, _)) => return Err(self.error(pos, "expected ':'")),
+                None => return Err(self.error(self.text.len(), "unexpected end of input")),
+            }
+            members.push((key, self.value()?));
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, '}')) => return Ok(Json::Object(members)),
+                Some((pos, _)) => return Err(self.error(pos, "expected ',' or '}'")),
+                None => return Err(self.error(self.text.len(), "unexpected end of input")),
+            }
+        }
+    }
+
+    fn array(&mut self) -> SqlResult<Json> {
+        self.chars.next();
+        let mut items = Vec::new();
+        self.skip_whitespace();
+        if let Some((_, ']')) = self.chars.peek() {
+            self.chars.next();
+            return Ok(Json::Array(items));
+        }
+        loop {
+            items.push(self.value()?);
+            self.skip_whitespace();
+            match self.chars.next() {
+                Some((_, ',')) => continue,
+                Some((_, ']')) => return Ok(Json::Array(items)),
+                Some((pos, _)) => return Err(self.error(pos, "expected ',' or ']'")),
+                None => return Err(self.error(self.text.len(), "unexpected end of input")),
+            }
+        }
+    }
+
+    fn string(&mut self) -> SqlResult<String> {
+        match self.chars.next() {
+            Some((_, '"')) => {}
+            Some((pos, _)) => return Err(self.error(pos, "expected a string")),
+            None => return Err(self.error(self.text.len(), "unexpected end of input")),
+        }
+        let mut out = String::new();
+        loop {
+            match self.chars.next() {
+                Some((_, '"')) => return Ok(out),
+                Some((pos, '\\')) => match self.chars.next() {
+                    Some((_, '"')) => out.push('"'),
+                    Some((_, '\\')) => out.push('\\'),
+                    Some((_, '/')) => out.push('/'),
+                    Some((_, 'n')) => out.push('\n'),
+                    Some((_, 'r')) => out.push('\r'),
+                    Some((_, 't')) => out.push('\t'),
+                    Some((_, 'b')) => out.push('\u{8}'),
+                    Some((_, 'f')) => out.push('\u{c}'),
+                    Some((_, 'u')) => {
+                        let hex: String = (0..4).filter_map(|_| self.chars.next().map(|(_, c)| c)).collect();
+                        let c = u32::from_str_radix(&hex, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .ok_or_else(|| self.error(pos, "invalid \\u escape"))?;
+                        out.push(c);
+                    }
+                    _ => return Err(self.error(pos, "invalid escape")),
+                },
+                Some((_, c)) => out.push(c),
+                None => return Err(self.error(self.text.len(), "unterminated string")),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Json {
+        let mut out = String::new();
+        while let Some(&(_, c)) = self.chars.peek() {
+            if !matches!(c, '-' | '+' | '.' | 'e' | 'E' | '0'..='9') {
+                break;
+            }
+            out.push(c);
+            self.chars.next();
+        }
+        Json::Number(out)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn strings_round_trip_with_escapes() {
+        let text = "quote \" backslash \\ newline \n tab \t bell \u{7} é";
+        let encoded = Json::String(text.to_string()).to_string();
+        assert_eq!(Json::parse(&encoded).unwrap(), Json::String(text.to_string()));
+        assert_eq!(Json::parse(r#""é""#).unwrap(), Json::String("é".to_string()));
+    }
+
+    #[test]
+    fn malformed_documents_are_rejected() {
+        for text in ["", "{", "[1,]", r#"{"a" 1}"#, "nul", "[1] 2", r#""open"#] {
+            assert!(Json::parse(text).is_err(), "{}", text);
+        }
+        assert_eq!(
+            Json::parse(" [ 1 , -2.5e3 , true ] ").unwrap(),
+            Json::Array(vec![
+                Json::Number("1".to_string()),
+                Json::Number("-2.5e3".to_string()),
+                Json::Bool(true),
+            ])
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Fixed-size slotted pages, the unit of disk I/O.
+//!
+//! # Layout
+//! ```text
+//! +--------+----------------+------------> <------------------+
+//! | header | slot directory |  free space  |  tuple storage   |
+//! +--------+----------------+------------> <------------------+
+//! 0        4                                free_end          PAGE_SIZE
+//! ```
+//!
+//! - The header holds the slot count and the start of the tuple storage
+//!   (`free_end`), both as little-endian `u16`.
+//! - Each slot is 4 bytes: the offset and length of its tuple.
+//! - Tuples are packed from the end of the page towards the front, so the
+//!   directory and the tuples grow towards each other.
+//!
+//! A deleted slot keeps its position, so the slot numbers of the other
+//! tuples stay stable; its offset is set to 0, which no tuple can use.
+
+/// Size of a page in bytes.
+pub const PAGE_SIZE: usize = 4096;
+
+const HEADER_SIZE: usize = 4;
+const SLOT_SIZE: usize = 4;
+
+/// Largest tuple that fits in an empty page.
+pub const MAX_TUPLE_SIZE: usize = PAGE_SIZE - HEADER_SIZE - SLOT_SIZE;
+
+/// One page of a heap file.
+#[derive(Clone, PartialEq, Eq)]
+pub struct Page {
+    data: Box<[u8; PAGE_SIZE]>,
+}
+
+impl std::fmt::Debug for Page {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Page")
+            .field("slots", &self.slot_count())
+            .field("free_space", &self.free_space())
+            .finish()
+    }
+}
+
+impl Default for Page {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Page {
+    /// Creates an empty page.
+    pub fn new() -> Self {
+        let mut page = Page { data: Box::new([0; PAGE_SIZE]) };
+        page.set_u16(2, PAGE_SIZE as u16);
+        page
+    }
+
+    /// Wraps bytes read from disk.
+    ///
+    /// # Returns
+    /// `None` if the header is inconsistent with the page size.
+    pub fn from_bytes(data: [u8; PAGE_SIZE]) -> Option<Self> {
+        let page = Page { data: Box::new(data) };
+        let directory_end = HEADER_SIZE + page.slot_count() as usize * SLOT_SIZE;
+        let free_end = page.free_end();
+        (directory_end <= free_end && free_end <= PAGE_SIZE).then_some(page)
+    }
+
+    /// Returns the raw bytes, as written to disk.
+    pub fn as_bytes(&self) -> &[u8; PAGE_SIZE] {
+        &self.data
+    }
+
+    /// Returns the number of slots, deleted ones included.
+    pub fn slot_count(&self) -> u16 {
+        self.u16_at(0)
+    }
+
+    /// Returns the number of bytes still available for a new tuple and its
+    /// slot.
+    pub fn free_space(&self) -> usize {
+        self.free_end() - HEADER_SIZE - self.slot_count() as usize * SLOT_SIZE
+    }
+
+    /// Stores a tuple in the page.
+    ///
+    /// # Returns
+    /// The slot number of the tuple, or `None` if the page is full.
+    pub fn insert(&mut self, tuple: &[u8]) -> Option<u16> {
+        if tuple.len() + SLOT_SIZE > self.free_space() {
+            return None;
+        }
+        let slot = self.slot_count();
+        let offset = self.free_end() - tuple.len();
+        self.data[offset..offset + tuple.len()].copy_from_slice(tuple);
+
+        let entry = HEADER_SIZE + slot as usize * SLOT_SIZE;
+        self.set_u16(entry, offset as u16);
+        self.set_u16(entry + 2, tuple.len() as u16);
+        self.set_u16(0, slot + 1);
+        self.set_u16(2, offset as u16);
+        Some(slot)
+    }
+
+    /// Returns the tuple in `slot`, or `None` if it does not exist or was
+    /// deleted.
+    pub fn get(&self, slot: u16) -> Option<&[u8]> {
+        if slot >= self.slot_count() {
+            return None;
+        }
+        let entry = HEADER_SIZE + slot as usize * SLOT_SIZE;
+        let offset = self.u16_at(entry) as usize;
+        let len = self.u16_at(entry + 2) as usize;
+        if offset == 0 || offset + len > PAGE_SIZE {
+            return None;
+        }
+        Some(&self.data[offset..offset + len])
+    }
+
+    /// Marks the tuple in `slot` as deleted. Its space is not reclaimed.
+    ///
+    /// # Returns
+    /// `true` if a live tuple was deleted.
+    pub fn delete(&mut self, slot: u16) -> bool {
+        if self.get(slot).is_none() {
+            return false;
+        }
+        self.set_u16(HEADER_SIZE + slot as usize * SLOT_SIZE, 0);
+        true
+    }
+
+    /// Iterates over the live tuples, with their slot numbers.
+    pub fn tuples(&self) -> impl Iterator<Item = (u16, &[u8])> {
+        (0..self.slot_count()).filter_map(move |slot| self.get(slot).map(|tuple| (slot, tuple)))
+    }
+
+    fn free_end(&self) -> usize {
+        self.u16_at(2) as usize
+    }
+
+    fn u16_at(&self, pos: usize) -> u16 {
+        u16::from_le_bytes([self.data[pos], self.data[pos + 1]])
+    }
+
+    fn set_u16(&mut self, pos: usize, n: u16) {
+        self.data[pos..pos + 2].copy_from_slice(&n.to_le_bytes());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn insert_get_delete() {
+        let mut page = Page::new();
+        assert_eq!(page.free_space(), PAGE_SIZE - HEADER_SIZE);
+        assert_eq!(page.insert(b"hello"), Some(0));
+        assert_eq!(page.insert(b""), Some(1));
+        assert_eq!(page.insert(b"world"), Some(2));
+        assert_eq!(page.get(0), Some(&b"hello"[..]));
+        assert_eq!(page.get(1), Some(&b""[..]));
+        assert_eq!(page.get(3), None);
+
+        assert!(page.delete(0));
+        assert!(!page.delete(0));
+        assert_eq!(page.tuples().map(|(slot, _)| slot).collect::<Vec<_>>(), vec![1, 2]);
+
+        let reread = Page::from_bytes(*page.as_bytes()).unwrap();
+        assert_eq!(reread, page);
+    }
+
+    #[test]
+    fn page_fills_up() {
+        let mut page = Page::new();
+        assert!(page.insert(&[1; MAX_TUPLE_SIZE + 1]).is_none());
+        assert_eq!(page.insert(&[1; MAX_TUPLE_SIZE]), Some(0));
+        assert_eq!(page.free_space(), 0);
+        assert!(page.insert(b"").is_none());
+        assert_eq!(page.get(0).unwrap().len(), MAX_TUPLE_SIZE);
+    }
+}
@@ -0,0 +1,328 @@
+//! Crash recovery: rebuilding a database from its snapshot and its WAL.
+//!
+//! Opening a database directory, with
+//! [`Database::open`](crate::core::db::Database::open) or
+//! [`Store::open`](crate::storage::store::Store::open), recovers it here
+//! first, so an unclean shutdown never leaves half-applied changes visible.
+//!
+//! A database directory holds numbered generations. Generation `n` is made
+//! of the snapshot directory `snapshot-<n>`, whose tables are
+//! [`heap`](crate::storage::heap) files written by
+//! [`save_database_compressed`], and the write-ahead log `wal-<n>.log`,
+//! which holds every statement committed since that snapshot. The file
+//! `CURRENT` names the live generation; a directory without it is a new,
+//! empty database at generation 0, which has no snapshot.
+//!
+//! [`checkpoint`] writes the next generation in full and only then switches
+//! `CURRENT` to it with an atomic rename, so a crash at any point leaves
+//! one complete generation to recover from. Recovery loads the snapshot and
+//! replays the log, whose torn tail and unterminated transaction are
+//! dropped (see [`wal`](crate::storage::wal)).
+//!
+//! A log record that cannot be replayed does not make the directory
+//! unreadable: recovery keeps what was committed before it, and moves it
+//! and the rest of the log to `wal-<n>.rejected` (see [`Rejected`]), where
+//! they can be inspected.
+//!
+//! A process changing the directory first takes the advisory lock of its
+//! file `LOCK` (see [`DirLock`]), so that a second process gets a clear
+//! error instead of interleaving its writes with the first one's. The
+//! operating system releases the lock when the process ends, even if it
+//! crashes.
+
+use std::fs::{self, File, OpenOptions, TryLockError};
+use std::path::{Path, PathBuf};
+
+use crate::core::db::Database;
+use crate::core::error::{SqlError, SqlResult};
+use crate::frontend::parser::Parser;
+use crate::storage::compression::Compression;
+use crate::storage::storage::{load_database, save_database_compressed, StorageFormat};
+use crate::storage::wal;
+
+/// Name of the file holding the live generation number.
+pub const CURRENT_FILE: &str = "CURRENT";
+
+/// Name of the file locked by the process using the directory.
+pub const LOCK_FILE: &str = "LOCK";
+
+/// The lock of a database directory, held until dropped.
+#[derive(Debug)]
+pub struct DirLock {
+    _file: File,
+}
+
+impl DirLock {
+    /// Locks the database directory `dir`, creating it if needed.
+    ///
+    /// # Returns
+    /// The lock, or `Err(SqlError)` if another process holds it.
+    pub fn acquire(dir: &Path) -> SqlResult<DirLock> {
+        fs::create_dir_all(dir).map_err(|e| io_error("cannot create directory", dir, e))?;
+        let path = dir.join(LOCK_FILE);
+        let file = OpenOptions::new()
+            .create(true)
+            .truncate(false)
+            .write(true)
+            .open(&path)
+            .map_err(|e| io_error("cannot open", &path, e))?;
+        match file.try_lock() {
+            Ok(()) => Ok(DirLock { _file: file }),
+            Err(TryLockError::WouldBlock) => Err(SqlError::new_io(&format!(
+                "Database '{}' is locked: another process is using it",
+                dir.display()
+            ))),
+            Err(TryLockError::Error(e)) => Err(io_error("cannot lock", &path, e)),
+        }
+    }
+}
+
+/// Log records that recovery could not replay, moved out of the log.
+#[derive(Debug)]
+pub struct Rejected {
+    /// The file the records were appended to.
+    pub path: PathBuf,
+    /// Why the first of them could not be replayed.
+    pub error: SqlError,
+}
+
+/// Rebuilds the database stored in `dir` from its snapshot and its WAL.
+///
+/// The directory is created if it does not exist, but not locked: see
+/// [`Store::open`](crate::storage::store::Store::open) for that.
+///
+/// The log is cut after the last transaction replayed, so that changes
+/// logged next follow it. Records that failed are moved aside, see
+/// [`recover_with_rejects`]. Logged statements are read with the default
+/// [`Parser`].
+pub fn recover(dir: &Path) -> SqlResult<Database> {
+    recover_with_rejects(dir, &Parser::default()).map(|(db, _)| db)
+}
+
+/// Rebuilds the database stored in `dir` as [`recover`] does, reading
+/// the logged statements with `parser`.
+///
+/// # Returns
+/// The database, with the records moved aside if one failed to replay,
+/// or `Err(SqlError)` if the snapshot cannot be loaded or on I/O failure.
+pub fn recover_with_rejects(dir: &Path, parser: &Parser) -> SqlResult<(Database, Option<Rejected>)> {
+    fs::create_dir_all(dir).map_err(|e| io_error("cannot create directory", dir, e))?;
+    let generation = current_generation(dir)?;
+    let mut db = match generation {
+        0 => Database::new(),
+        n => load_database(&snapshot_dir(dir, n))?,
+    };
+    let log = wal_file(dir, generation);
+    let replayed = wal::replay(&log, parser, &mut db)?;
+    let rejected = replayed.error.map(|error| Rejected { path: dir.join(format!("wal-{}.rejected", generation)), error });
+    wal::cut(&log, replayed.kept, rejected.as_ref().map(|rejected| rejected.path.as_path()))?;
+    Ok((db, rejected))
+}
+
+/// Returns the path of the log to which changes to the database in `dir`
+/// must be appended.
+pub fn wal_path(dir: &Path) -> SqlResult<PathBuf> {
+    Ok(wal_file(dir, current_generation(dir)?))
+}
+
+/// Writes `db` as a new snapshot of `dir` and starts an empty log.
+///
+/// The previous generation is deleted once the new one is live.
+///
+/// # Returns
+/// The path of the new log, which replaces the one from [`wal_path`], or
+/// `Err(SqlError)` if a transaction is in progress or on I/O failure.
+pub fn checkpoint(db: &Database, dir: &Path) -> SqlResult<PathBuf> {
+    checkpoint_compressed(db, dir, Compression::None)
+}
+
+/// Checkpoints as [`checkpoint`] does, compressing the table files of the
+/// snapshot.
+pub fn checkpoint_compressed(db: &Database, dir: &Path, compression: Compression) -> SqlResult<PathBuf> {
+    if db.in_transaction() {
+        return Err(SqlError::new_execution("Cannot checkpoint during a transaction"));
+    }
+    let old = current_generation(dir)?;
+    let new = old + 1;
+    let snapshot = snapshot_dir(dir, new);
+    // Leftovers of a checkpoint interrupted before it went live
+    let _ = fs::remove_dir_all(&snapshot);
+    let _ = fs::remove_file(wal_file(dir, new));
+    save_database_compressed(db, &snapshot, StorageFormat::Heap, compression)?;
+
+    let current = dir.join(CURRENT_FILE);
+    let tmp = current.with_extension("tmp");
+    fs::write(&tmp, new.to_string()).map_err(|e| io_error("cannot write", &tmp, e))?;
+    fs::rename(&tmp, &current).map_err(|e| io_error("cannot write", &current, e))?;
+
+    let _ = fs::remove_dir_all(snapshot_dir(dir, old));
+    let _ = fs::remove_file(wal_file(dir, old));
+    Ok(wal_file(dir, new))
+}
+
+/// Reads the live generation number, 0 if there is none yet.
+fn current_generation(dir: &Path) -> SqlResult<u64> {
+    let path = dir.join(CURRENT_FILE);
+    match fs::read_to_string(&path) {
+        Ok(text) => text
+            .trim()
+            .parse()
+            .map_err(|_| SqlError::new_io(&format!("Recovery: corrupt '{}'", path.display()))),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(0),
+        Err(e) => Err(io_error("cannot read", &path, e)),
+    }
+}
+
+fn snapshot_dir(dir: &Path, generation: u64) -> PathBuf {
+    dir.join(format!("snapshot-{}", generation))
+}
+
+fn wal_file(dir: &Path, generation: u64) -> PathBuf {
+    dir.join(format!("wal-{}.log", generation))
+}
+
+fn io_error(action: &str, path: &Path, e: std::io::Error) -> SqlError {
+    SqlError::new_io(&format!("Recovery: {} '{}': {}", action, path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use crate::core::types::{DataType, TableName};
+    use crate::executor::executor::Executor;
+    use crate::frontend::lexer::lexer;
+    use crate::storage::wal::Wal;
+    use std::io::Write;
+
+    fn run(db: &mut Database, wal: &mut Wal, sql: &str) {
+        let ast = Parser::default().parse(&lexer(sql).unwrap()).unwrap();
+        db.record_changes(true);
+        Executor::execute(ast.clone(), db).unwrap();
+        wal.record(&ast, sql, db.take_changes()).unwrap();
+    }
+
+    fn count(db: &Database) -> usize {
+        db.table(&TableName::new("t").unwrap()).unwrap().rows().len()
+    }
+
+    #[test]
+    fn open_recovers_snapshot_and_log() {
+        let tmp = TempDir::new("recovery");
+        let dir = tmp.join("db");
+        let mut db = recover(&dir).unwrap();
+        let mut wal = Wal::open(&wal_path(&dir).unwrap()).unwrap();
+        run(&mut db, &mut wal, "CREATE TABLE t (id INT PRIMARY KEY)");
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (1), (2)");
+
+        let mut wal = Wal::open(&checkpoint(&db, &dir).unwrap()).unwrap();
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (3)");
+        // Never committed: lost in the crash
+        run(&mut db, &mut wal, "BEGIN");
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (4)");
+        drop(wal);
+
+        let recovered = recover(&dir).unwrap();
+        assert_eq!(count(&recovered), 3);
+        assert!(!dir.join("snapshot-0").exists() && !dir.join("wal-0.log").exists());
+    }
+
+    #[test]
+    fn database_open_drops_a_torn_or_uncommitted_log_tail() {
+        let tmp = TempDir::new("recovery_db_open");
+        let dir = tmp.join("db");
+        let mut db = recover(&dir).unwrap();
+        let mut wal = Wal::open(&wal_path(&dir).unwrap()).unwrap();
+        run(&mut db, &mut wal, "CREATE TABLE t (id INT)");
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (1)");
+        run(&mut db, &mut wal, "BEGIN");
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (2)");
+        drop(wal);
+        let db = Database::open(&dir).unwrap();
+        assert_eq!(count(&db), 1);
+        drop(db);
+
+        // Closing it saved the committed row; a record cut short by the
+        // next crash is dropped
+        let mut wal = Wal::open(&wal_path(&dir).unwrap()).unwrap();
+        run(&mut recover(&dir).unwrap(), &mut wal, "INSERT INTO t VALUES (3)");
+        drop(wal);
+        let mut log = OpenOptions::new().append(true).open(wal_path(&dir).unwrap()).unwrap();
+        write!(log, "30 INSERT INTO t VALUES").unwrap();
+        assert_eq!(count(&Database::open(&dir).unwrap()), 2);
+    }
+
+    #[test]
+    fn a_locked_directory_cannot_be_locked_again() {
+        let tmp = TempDir::new("recovery_lock");
+        let dir = tmp.join("db");
+        let lock = DirLock::acquire(&dir).unwrap();
+        let error = DirLock::acquire(&dir).unwrap_err();
+        assert!(error.message().contains("is locked"), "{}", error.message());
+        drop(lock);
+        DirLock::acquire(&dir).unwrap();
+    }
+
+    #[test]
+    fn records_that_fail_to_replay_are_moved_aside() {
+        let tmp = TempDir::new("recovery_rejected");
+        let dir = tmp.join("db");
+        let mut db = recover(&dir).unwrap();
+        let mut wal = Wal::open(&wal_path(&dir).unwrap()).unwrap();
+        run(&mut db, &mut wal, "CREATE TABLE t (id INT)");
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (1)");
+        drop(wal);
+        // An effect on a table the log never created, then a later change
+        let mut log = OpenOptions::new().append(true).open(wal_file(&dir, 0)).unwrap();
+        let effect = r#"{"table":"gone","changes":[["delete",1]]}"#;
+        write!(log, "{} {}\n34 INSERT INTO t VALUES (2), (3), (4)\n", effect.len(), effect).unwrap();
+
+        let (mut db, rejected) = recover_with_rejects(&dir, &Parser::default()).unwrap();
+        let rejected = rejected.unwrap();
+        assert_eq!(count(&db), 1);
+        assert!(rejected.error.message().contains("Unknown table 'gone'"), "{}", rejected.error.message());
+        assert!(fs::read_to_string(&rejected.path).unwrap().ends_with("(4)\n"));
+
+        // The log goes on from the records kept
+        let mut wal = Wal::open(&wal_path(&dir).unwrap()).unwrap();
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (5)");
+        let (db, rejected) = recover_with_rejects(&dir, &Parser::default()).unwrap();
+        assert_eq!((count(&db), rejected.is_none()), (2, true));
+    }
+
+    #[test]
+    fn logged_statements_are_replayed_with_the_given_parser() {
+        let tmp = TempDir::new("recovery_aliases");
+        let dir = tmp.join("db");
+        let mut parser = Parser::default();
+        parser.register_alias("NUMBER", DataType::Float).unwrap();
+        fs::create_dir_all(&dir).unwrap();
+        let mut wal = Wal::open(&wal_path(&dir).unwrap()).unwrap();
+        let sql = "CREATE TABLE t (x NUMBER)";
+        let ast = parser.parse(&lexer(sql).unwrap()).unwrap();
+        wal.record(&ast, sql, Vec::new()).unwrap();
+        drop(wal);
+
+        let (db, rejected) = recover_with_rejects(&dir, &parser).unwrap();
+        assert!(rejected.is_none());
+        assert_eq!(db.table(&TableName::new("t").unwrap()).unwrap().schema().columns()[0].dtype, DataType::Float);
+    }
+
+    #[test]
+    fn interrupted_checkpoint_is_ignored() {
+        let tmp = TempDir::new("recovery_interrupted");
+        let dir = tmp.join("db");
+        let mut db = recover(&dir).unwrap();
+        let mut wal = Wal::open(&wal_path(&dir).unwrap()).unwrap();
+        run(&mut db, &mut wal, "CREATE TABLE t (id INT)");
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (1)");
+
+        // A snapshot written without switching CURRENT is not live
+        save_database_compressed(&db, &snapshot_dir(&dir, 1), StorageFormat::Binary, Compression::None).unwrap();
+        assert_eq!(count(&recover(&dir).unwrap()), 1);
+
+        let mut wal = Wal::open(&checkpoint(&db, &dir).unwrap()).unwrap();
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (2)");
+        assert_eq!(count(&recover(&dir).unwrap()), 2);
+    }
+}
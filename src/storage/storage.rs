@@ -1,109 +1,834 @@
-//! Storage: persistence of tables to and from disk.
+//! Storage: persistence of tables and databases to and from disk.
 //!
 //! Responsibilities:
-//! - Serialize a [`Table`] into JSON and save it to a file
-//! - Load a [`Table`] from a JSON file
+//! - Serialize a [`Table`] into JSON, binary or heap pages and save it to a
+//!   file
+//! - Load a [`Table`] from a file, whatever its [`StorageFormat`]
+//! - Save and load a whole [`Database`] as a directory of table files
+//! - Export and import rows as JSON Lines, one object per row
 //! - Remain decoupled from the core Database logic
 //!
 //! Design:
 //! - Storage only uses serializable representations of Table, Column, and Row
 //! - Core never depends on disk I/O
 //!
+//! A table file holds the table name, its columns (name, type and
+//! constraints), the primary key, the rows and their rowids. Values are stored as JSON
+//! scalars and read back according to the column type: dates and
+//! timestamps as ISO-8601 strings, blobs as base64 strings and non-finite
+//! floats as strings. Table files written before base64 hold blobs as hex
+//! strings, and have no `"blobs"` field saying otherwise. The binary format is described in
+//! [`binary`](crate::storage::binary), and the heap format, which database
+//! snapshots use, in [`heap`](crate::storage::heap). Any of them can be
+//! compressed (see
+//! [`compression`](crate::storage::compression)); loading detects it.
+//!
 //! Example:
 //! ```ignore
 //! save_table(&table, Path::new("users.json")).unwrap();
 //! let table2 = load_table(Path::new("users.json")).unwrap();
 //! ```
 
-use std::fs::{File};
-use std::io::{BufReader, BufWriter};
+use std::fs;
+use std::io::{BufRead, BufReader, BufWriter, Write};
 use std::path::Path;
-use serde::{Serialize, Deserialize};
 
-use crate::frontend::ast::{Column, Value};
-use crate::executor::Row;
-use crate::executor::Table;
-use crate::frontend::token::SqlError;
+use crate::core::db::Database;
+use crate::core::error::{SqlError, SqlResult};
+use crate::core::index::IndexKind;
+use crate::core::row::Row;
+use crate::core::schema::{Column, Schema};
+use crate::core::table::{Rowid, Table};
+use crate::core::types::{ColumnName, DataType, TableName, Value};
+use crate::storage::binary;
+use crate::storage::buffer::{self, BufferPool};
+use crate::storage::compression::{self, Compression};
+use crate::storage::heap::{self, HeapFile};
+use crate::storage::json::Json;
+
+/// Name of the file listing the tables of a saved database. Table names
+/// start with a letter, so it cannot clash with a table file.
+pub const CATALOG_FILE: &str = "_catalog.json";
+
+/// On-disk encoding of a table.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StorageFormat {
+    /// Human-readable JSON document.
+    #[default]
+    Json,
+    /// Compact length-prefixed binary encoding.
+    Binary,
+    /// Heap file of fixed-size pages, read one page at a time.
+    Heap,
+}
+
+impl StorageFormat {
+    /// File extension of a table stored in this format.
+    pub fn extension(self) -> &'static str {
+        match self {
+            StorageFormat::Json => "json",
+            StorageFormat::Binary => "bin",
+            StorageFormat::Heap => "heap",
+        }
+    }
+
+    /// Name of the format in a database catalog.
+    fn name(self) -> &'static str {
+        match self {
+            StorageFormat::Json => "json",
+            StorageFormat::Binary => "binary",
+            StorageFormat::Heap => "heap",
+        }
+    }
+}
+
+/// Encodes a table in the given format.
+///
+/// # Returns
+/// The encoded table, or `Err(SqlError)` if the format is
+/// [`StorageFormat::Heap`] and a row is larger than a page.
+pub fn encode_table(table: &Table, format: StorageFormat) -> SqlResult<Vec<u8>> {
+    match format {
+        StorageFormat::Json => Ok(table_to_json(table).to_string().into_bytes()),
+        StorageFormat::Binary => Ok(binary::encode_table(table)),
+        StorageFormat::Heap => heap::encode(table),
+    }
+}
+
+/// Decodes a table written by [`encode_table`], detecting its format and
+/// its compression, if any.
+pub fn decode_table(bytes: &[u8]) -> SqlResult<Table> {
+    let bytes = compression::decompress(bytes)?;
+    let bytes = &bytes[..];
+    if bytes.starts_with(binary::MAGIC) {
+        return binary::decode_table(bytes);
+    }
+    if heap::is_heap(bytes) {
+        return heap::decode(bytes);
+    }
+    let text = std::str::from_utf8(bytes).map_err(|_| SqlError::new_io("Invalid table file: not UTF-8"))?;
+    table_from_json(&Json::parse(text)?)
+}
 
 /// Save a table to the given file path as JSON.
-pub fn save_table(table: &Table, path: &Path) -> Result<(), SqlError> {
-    let file = File::create(path)
-        .map_err(|e| SqlError::io(format!("cannot create file: {}", e)))?;
-    let writer = BufWriter::new(file);
-    serde_json::to_writer_pretty(writer, &SerializableTable::from_table(table))
-        .map_err(|e| SqlError::io(format!("serialization error: {}", e)))
-}
-
-/// Load a table from the given file path.
-pub fn load_table(path: &Path) -> Result<Table, SqlError> {
-    let file = File::open(path)
-        .map_err(|e| SqlError::io(format!("cannot open file: {}", e)))?;
-    let reader = BufReader::new(file);
-    let st: SerializableTable = serde_json::from_reader(reader)
-        .map_err(|e| SqlError::io(format!("deserialization error: {}", e)))?;
-    Ok(st.into_table())
-}
-
-/// Serializable version of Table, decoupled from Core internals.
-#[derive(Debug, Serialize, Deserialize)]
-struct SerializableTable {
-    columns: Vec<Column>,
-    rows: Vec<Vec<Value>>,
-}
-
-impl SerializableTable {
-    fn from_table(t: &Table) -> Self {
-        Self {
-            columns: t.columns.clone(),
-            rows: t.rows.iter().map(|r| r.values.clone()).collect(),
+pub fn save_table(table: &Table, path: &Path) -> SqlResult<()> {
+    save_table_as(table, path, StorageFormat::Json)
+}
+
+/// Save a table to the given file path in the given format.
+pub fn save_table_as(table: &Table, path: &Path, format: StorageFormat) -> SqlResult<()> {
+    save_table_compressed(table, path, format, Compression::None)
+}
+
+/// Save a table to the given file path in the given format, compressed.
+pub fn save_table_compressed(table: &Table, path: &Path, format: StorageFormat, compression: Compression) -> SqlResult<()> {
+    write_file(path, &compression::compress(&encode_table(table, format)?, compression)?)
+}
+
+/// Load a table from the given file path, in any format.
+///
+/// An uncompressed heap file is read one page at a time, through a
+/// [`BufferPool`]; any other file is read whole, then decoded.
+pub fn load_table(path: &Path) -> SqlResult<Table> {
+    // A file that cannot be read is reported by `fs::read` below
+    if heap::is_heap_file(path).unwrap_or(false) {
+        return HeapFile::open(path)?.load_table(&mut BufferPool::new(buffer::DEFAULT_CAPACITY));
+    }
+    let bytes = fs::read(path).map_err(|e| io_error("cannot read", path, e))?;
+    decode_table(&bytes)
+}
+
+/// Save every table of `db` into the directory `dir` as JSON, creating it
+/// if needed.
+pub fn save_database(db: &Database, dir: &Path) -> SqlResult<()> {
+    save_database_as(db, dir, StorageFormat::Json)
+}
+
+/// Save every table of `db` into the directory `dir` in the given format.
+///
+/// Each table is written to `<name>.<extension>`; the catalog file, listing
+/// the format, the schemas, the table names and the views, is written last
+/// so that an interrupted save never lists a table whose file was not
+/// written. The files the previous catalog listed, and this one does not,
+/// such as the file of a renamed table under its old name, are removed
+/// once it is.
+pub fn save_database_as(db: &Database, dir: &Path, format: StorageFormat) -> SqlResult<()> {
+    save_database_compressed(db, dir, format, Compression::None)
+}
+
+/// Save every table of `db` into the directory `dir` as
+/// [`save_database_as`] does, compressing the table files.
+pub fn save_database_compressed(db: &Database, dir: &Path, format: StorageFormat, compression: Compression) -> SqlResult<()> {
+    fs::create_dir_all(dir).map_err(|e| io_error("cannot create directory", dir, e))?;
+    let previous = listed_files(dir);
+    let mut names = Vec::new();
+    let mut files = Vec::new();
+    for name in db.table_names() {
+        let table = db.table(name).expect("listed table exists");
+        let file = table_file(name, format);
+        save_table_compressed(table, &dir.join(&file), format, compression)?;
+        names.push(Json::String(name.as_str().to_string()));
+        files.push(file);
+    }
+    let schemas = db.catalog().schemas().into_iter().map(|schema| Json::String(schema.to_string())).collect();
+    let views = db
+        .catalog()
+        .views()
+        .into_iter()
+        .map(|(name, sql)| {
+            Json::Object(vec![
+                ("name".to_string(), Json::String(name.to_string())),
+                ("sql".to_string(), Json::String(sql.to_string())),
+            ])
+        })
+        .collect();
+    let catalog = Json::Object(vec![
+        ("format".to_string(), Json::String(format.name().to_string())),
+        ("schemas".to_string(), Json::Array(schemas)),
+        ("tables".to_string(), Json::Array(names)),
+        ("views".to_string(), Json::Array(views)),
+    ]);
+    write_file(&dir.join(CATALOG_FILE), catalog.to_string().as_bytes())?;
+
+    // Files of tables dropped or renamed since the last save are no longer
+    // listed: removing them cannot lose data
+    for file in previous.into_iter().filter(|file| !files.contains(file)) {
+        let path = dir.join(&file);
+        fs::remove_file(&path).map_err(|e| io_error("cannot remove", &path, e))?;
+    }
+    Ok(())
+}
+
+/// Returns the names of the table files listed by the catalog in `dir`, or
+/// none if it has no readable catalog.
+fn listed_files(dir: &Path) -> Vec<String> {
+    let path = dir.join(CATALOG_FILE);
+    let Some(catalog) = fs::read_to_string(&path).ok().and_then(|text| Json::parse(&text).ok()) else {
+        return Vec::new();
+    };
+    let Ok(format) = catalog_format(&catalog, &path) else { return Vec::new() };
+    catalog
+        .get("tables")
+        .and_then(Json::as_array)
+        .unwrap_or(&[])
+        .iter()
+        .filter_map(|name| TableName::parse(name.as_str()?).ok())
+        .map(|name| table_file(&name, format))
+        .filter(|file| dir.join(file).is_file())
+        .collect()
+}
+
+/// Returns the format of the tables listed by `catalog`, read from `path`.
+fn catalog_format(catalog: &Json, path: &Path) -> SqlResult<StorageFormat> {
+    match catalog.get("format").and_then(Json::as_str) {
+        None | Some("json") => Ok(StorageFormat::Json),
+        Some("binary") => Ok(StorageFormat::Binary),
+        Some("heap") => Ok(StorageFormat::Heap),
+        Some(other) => Err(corrupt(path, &format!("unknown format '{}'", other))),
+    }
+}
+
+/// Load a database saved by [`save_database`] from the directory `dir`.
+///
+/// Only the tables listed in the catalog are read. A catalog without a
+/// format is a JSON database, and one without schemas or views has none
+/// besides the default schema.
+pub fn load_database(dir: &Path) -> SqlResult<Database> {
+    let path = dir.join(CATALOG_FILE);
+    let text = fs::read_to_string(&path).map_err(|e| io_error("cannot read", &path, e))?;
+    let catalog = Json::parse(&text)?;
+    let format = catalog_format(&catalog, &path)?;
+    let names = catalog
+        .get("tables")
+        .and_then(Json::as_array)
+        .ok_or_else(|| corrupt(&path, "missing table list"))?;
+
+    let mut db = Database::new();
+    let schemas = catalog.get("schemas").and_then(Json::as_array).unwrap_or(&[]);
+    for schema in schemas {
+        let schema = schema.as_str().ok_or_else(|| corrupt(&path, "schema name is not a string"))?;
+        db.catalog_mut().create_schema(schema).map_err(|e| corrupt(&path, e.message()))?;
+    }
+    for name in names {
+        let name = name.as_str().ok_or_else(|| corrupt(&path, "table name is not a string"))?;
+        let name = TableName::parse(name).map_err(|e| corrupt(&path, &e))?;
+        let table = load_table(&dir.join(table_file(&name, format)))?;
+        if table.name() != &name {
+            return Err(corrupt(&path, &format!("file for '{}' holds table '{}'", name.as_str(), table.name().as_str())));
         }
+        db.add_table(table)?;
+    }
+    for view in catalog.get("views").and_then(Json::as_array).unwrap_or(&[]) {
+        let (Some(name), Some(sql)) = (view.get("name").and_then(Json::as_str), view.get("sql").and_then(Json::as_str)) else {
+            return Err(corrupt(&path, "view without a name or a query"));
+        };
+        let name = TableName::parse(name).map_err(|e| corrupt(&path, &e))?;
+        db.create_view(&name, sql).map_err(|e| corrupt(&path, e.message()))?;
     }
-    fn into_table(self) -> Table {
-        Table {
-            columns: self.columns,
-            rows: self.rows.into_iter().map(|v| Row { values: v }).collect(),
+    Ok(db)
+}
+
+/// Writes the rows of `table` as JSON Lines: one JSON object per line,
+/// keyed by column name, with values encoded as in table files.
+///
+/// # Example
+/// ```
+/// use mini_rust_sgbd::core::{row::Row, schema::{Column, Schema}, table::Table};
+/// use mini_rust_sgbd::core::types::{ColumnName, DataType, TableName, Value};
+/// use mini_rust_sgbd::storage::storage::write_jsonl;
+///
+/// let schema = Schema::try_new(vec![
+///     Column::new(ColumnName::new("id").unwrap(), DataType::Int),
+///     Column::new(ColumnName::new("name").unwrap(), DataType::Text),
+/// ]).unwrap();
+/// let mut table = Table::create(TableName::new("users").unwrap(), schema);
+/// let row = Row::from_values(vec![Value::Int(1), Value::Text("Ann".into())], table.schema()).unwrap();
+/// table.insert_checked(row).unwrap();
+///
+/// let mut out = Vec::new();
+/// write_jsonl(&table, &mut out).unwrap();
+/// assert_eq!(String::from_utf8(out).unwrap(), "{\"id\":1,\"name\":\"Ann\"}\n");
+/// ```
+pub fn write_jsonl<W: Write>(table: &Table, out: &mut W) -> SqlResult<()> {
+    let columns = table.schema().columns();
+    for row in table.rows() {
+        let object = Json::Object(
+            columns
+                .iter()
+                .zip(row.values())
+                .map(|(column, value)| (column.name.as_str().to_string(), value_to_json(value)))
+                .collect(),
+        );
+        writeln!(out, "{}", object).map_err(|e| SqlError::new_io(&format!("JSON Lines: cannot write: {}", e)))?;
+    }
+    Ok(())
+}
+
+/// Reads JSON Lines rows into `table`.
+///
+/// Each non-blank line must be an object whose keys are columns of the
+/// table; a missing key is `NULL`. The import is all or nothing: if any
+/// line is invalid or breaks a constraint, no row is inserted.
+///
+/// # Returns
+/// The number of rows inserted, or `Err(SqlError)` naming the first bad line.
+pub fn read_jsonl<R: BufRead>(table: &mut Table, input: R) -> SqlResult<usize> {
+    let schema = table.schema();
+    let mut rows = Vec::new();
+    for (i, line) in input.lines().enumerate() {
+        let bad_line = |what: &str| SqlError::new_io(&format!("JSON Lines: line {}: {}", i + 1, what));
+        let line = line.map_err(|e| bad_line(&e.to_string()))?;
+        if line.trim().is_empty() {
+            continue;
         }
+        let Json::Object(members) = Json::parse(&line).map_err(|e| bad_line(e.message()))? else {
+            return Err(bad_line("expected an object"));
+        };
+
+        let mut values = vec![Value::Null; schema.columns().len()];
+        for (key, json) in &members {
+            let idx = ColumnName::new(key)
+                .ok()
+                .and_then(|name| schema.index_of(&name))
+                .ok_or_else(|| bad_line(&format!("unknown column '{}'", key)))?;
+            let dtype = &schema.columns()[idx].dtype;
+            values[idx] = value_from_json(json, dtype)
+                .ok_or_else(|| bad_line(&format!("bad value {} for column '{}'", json, key)))?;
+        }
+        rows.push(Row::from_values(values, schema).map_err(|e| bad_line(e.message()))?);
     }
+    table.insert_many(rows)
+}
+
+/// Exports the rows of `table` to the JSON Lines file at `path`.
+pub fn export_jsonl(table: &Table, path: &Path) -> SqlResult<()> {
+    let file = fs::File::create(path).map_err(|e| io_error("cannot write", path, e))?;
+    let mut out = BufWriter::new(file);
+    write_jsonl(table, &mut out)?;
+    out.flush().map_err(|e| io_error("cannot write", path, e))
+}
+
+/// Imports the rows of the JSON Lines file at `path` into `table`.
+pub fn import_jsonl(table: &mut Table, path: &Path) -> SqlResult<usize> {
+    let file = fs::File::open(path).map_err(|e| io_error("cannot read", path, e))?;
+    read_jsonl(table, BufReader::new(file))
+}
+
+/// File name of a table inside a database directory.
+fn table_file(name: &TableName, format: StorageFormat) -> String {
+    format!("{}.{}", name.as_str(), format.extension())
+}
+
+/// Encodes a table as a JSON document, decoupled from Core internals.
+fn table_to_json(table: &Table) -> Json {
+    let schema = table.schema();
+    let columns = schema
+        .columns()
+        .iter()
+        .map(|c| {
+            Json::Object(vec![
+                ("name".to_string(), Json::String(c.name.as_str().to_string())),
+                ("type".to_string(), Json::String(format!("{:?}", c.dtype))),
+                ("nullable".to_string(), Json::Bool(c.nullable)),
+                ("unique".to_string(), Json::Bool(c.unique)),
+            ])
+        })
+        .collect();
+    let primary_key = match schema.primary_key() {
+        Some(idx) => Json::String(schema.columns()[idx].name.as_str().to_string()),
+        None => Json::Null,
+    };
+    let rows = table
+        .rows()
+        .iter()
+        .map(|row| Json::Array(row.values().iter().map(value_to_json).collect()))
+        .collect();
+    let indexes = table
+        .indexes()
+        .iter()
+        .map(|index| {
+            Json::Object(vec![
+                ("name".to_string(), Json::String(index.name().to_string())),
+                ("column".to_string(), Json::String(schema.columns()[index.column()].name.as_str().to_string())),
+                ("kind".to_string(), Json::String(index.kind().name().to_string())),
+            ])
+        })
+        .collect();
+
+    Json::Object(vec![
+        ("name".to_string(), Json::String(table.name().as_str().to_string())),
+        ("columns".to_string(), Json::Array(columns)),
+        ("primary_key".to_string(), primary_key),
+        ("indexes".to_string(), Json::Array(indexes)),
+        ("rows".to_string(), Json::Array(rows)),
+        ("rowids".to_string(), Json::Array(table.rowids().iter().map(|&rowid| rowid_to_json(rowid)).collect())),
+        ("next_rowid".to_string(), rowid_to_json(table.next_rowid())),
+        ("blobs".to_string(), Json::String("base64".to_string())),
+    ])
+}
+
+/// Rebuilds a table from [`table_to_json`] output, validating every row.
+fn table_from_json(doc: &Json) -> SqlResult<Table> {
+    let invalid = |what: &str| SqlError::new_io(&format!("Invalid table file: {}", what));
+
+    let name = doc.get("name").and_then(Json::as_str).ok_or_else(|| invalid("missing name"))?;
+    let name = TableName::parse(name).map_err(|e| invalid(&e))?;
+
+    let mut columns = Vec::new();
+    for column in doc.get("columns").and_then(Json::as_array).ok_or_else(|| invalid("missing columns"))? {
+        let col_name = column.get("name").and_then(Json::as_str).ok_or_else(|| invalid("column without a name"))?;
+        let dtype = column.get("type").and_then(Json::as_str).ok_or_else(|| invalid("column without a type"))?;
+        let flag = |key: &str| column.get(key) == Some(&Json::Bool(true));
+        let col_name = ColumnName::new(col_name).map_err(|e| invalid(&e))?;
+        let dtype = dtype.parse::<DataType>().map_err(|e| invalid(&e))?;
+        columns.push(Column::new(col_name, dtype).with_nullable(flag("nullable")).with_unique(flag("unique")));
+    }
+
+    let mut schema = Schema::try_new(columns)?;
+    if let Some(key) = doc.get("primary_key").and_then(Json::as_str) {
+        schema = schema.with_primary_key(&ColumnName::new(key).map_err(|e| invalid(&e))?)?;
+    }
+
+    let encoding = match doc.get("blobs").and_then(Json::as_str) {
+        Some("base64") => BlobEncoding::Base64,
+        None => BlobEncoding::Hex,
+        Some(other) => return Err(invalid(&format!("unknown blob encoding '{}'", other))),
+    };
+    let mut rows = Vec::new();
+    for row in doc.get("rows").and_then(Json::as_array).ok_or_else(|| invalid("missing rows"))? {
+        let values = decode_values(row, &schema, encoding).map_err(|e| invalid(&e))?;
+        rows.push(Row::from_values(values, &schema)?);
+    }
+
+    let mut table = Table::create(name, schema);
+    table.insert_many(rows)?;
+    // Files written before rowids were saved number their rows from 1
+    if let Some(rowids) = doc.get("rowids").and_then(Json::as_array) {
+        let rowids = rowids.iter().map(rowid_from_json).collect::<Option<Vec<Rowid>>>();
+        let next_rowid = doc.get("next_rowid").and_then(rowid_from_json);
+        let (rowids, next_rowid) = rowids.zip(next_rowid).ok_or_else(|| invalid("bad rowids"))?;
+        table.restore_rowids(rowids, next_rowid)?;
+    }
+    // Files written before indexes existed have no "indexes" field
+    for index in doc.get("indexes").and_then(Json::as_array).unwrap_or_default() {
+        let index_name = index.get("name").and_then(Json::as_str).ok_or_else(|| invalid("index without a name"))?;
+        let column = index.get("column").and_then(Json::as_str).ok_or_else(|| invalid("index without a column"))?;
+        let kind = match index.get("kind").and_then(Json::as_str) {
+            Some(kind) => kind.parse::<IndexKind>().map_err(|e| invalid(&e))?,
+            None => IndexKind::default(),
+        };
+        table.create_index(index_name, &ColumnName::new(column).map_err(|e| invalid(&e))?, kind)?;
+    }
+    Ok(table)
+}
+
+/// Encodes one value as table files and JSON Lines store it.
+pub fn value_to_json(value: &Value) -> Json {
+    match value {
+        Value::Int(i) => Json::Number(i.to_string()),
+        Value::Float(f) if f.is_finite() => Json::Number(format!("{:?}", f)),
+        Value::Float(f) => Json::String(f.to_string()),
+        Value::Text(s) => Json::String(s.clone()),
+        Value::Bool(b) => Json::Bool(*b),
+        Value::Blob(bytes) => Json::String(base64_encode(bytes)),
+        Value::Date(_) | Value::Timestamp(_) => Json::String(value.to_string()),
+        Value::Null => Json::Null,
+    }
+}
+
+/// Decodes the values of a row of `schema`, stored as an array.
+///
+/// # Returns
+/// The values in column order, or what is wrong with `row`.
+pub fn values_from_json(row: &Json, schema: &Schema) -> Result<Vec<Value>, String> {
+    decode_values(row, schema, BlobEncoding::Base64)
+}
+
+/// How a blob is written as a JSON string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BlobEncoding {
+    Base64,
+    /// Used by table files written before base64.
+    Hex,
+}
+
+/// Decodes the values of a row as [`values_from_json`] does, with blobs
+/// in `encoding`.
+fn decode_values(row: &Json, schema: &Schema, encoding: BlobEncoding) -> Result<Vec<Value>, String> {
+    let cells = row.as_array().ok_or("row is not an array")?;
+    if cells.len() != schema.columns().len() {
+        return Err("row length does not match the columns".to_string());
+    }
+    cells
+        .iter()
+        .zip(schema.columns())
+        .map(|(cell, column)| {
+            decode_value(cell, &column.dtype, encoding)
+                .ok_or_else(|| format!("bad value {} for column '{}'", cell, column.name.as_str()))
+        })
+        .collect()
+}
+
+/// Encodes a rowid as a JSON number.
+pub fn rowid_to_json(rowid: Rowid) -> Json {
+    Json::Number(rowid.to_string())
+}
+
+/// Decodes a rowid, or `None` if `json` is not one.
+pub fn rowid_from_json(json: &Json) -> Option<Rowid> {
+    match json {
+        Json::Number(n) => n.parse().ok(),
+        _ => None,
+    }
+}
+
+/// Decodes one value of a column of type `dtype`, or `None` if it does
+/// not fit.
+fn value_from_json(json: &Json, dtype: &DataType) -> Option<Value> {
+    decode_value(json, dtype, BlobEncoding::Base64)
+}
+
+/// Decodes one value as [`value_from_json`] does, with blobs in
+/// `encoding`.
+fn decode_value(json: &Json, dtype: &DataType, encoding: BlobEncoding) -> Option<Value> {
+    let value = match (dtype, json) {
+        (_, Json::Null) => Value::Null,
+        (DataType::Int, Json::Number(n)) => Value::Int(n.parse().ok()?),
+        (DataType::Float, Json::Number(n) | Json::String(n)) => Value::Float(n.parse().ok()?),
+        (DataType::Text, Json::String(s)) => Value::Text(s.clone()),
+        (DataType::Bool, Json::Bool(b)) => Value::Bool(*b),
+        (DataType::Blob, Json::String(text)) => Value::Blob(match encoding {
+            BlobEncoding::Base64 => base64_decode(text)?,
+            BlobEncoding::Hex => hex_decode(text)?,
+        }),
+        (DataType::Date | DataType::Timestamp, Json::String(s)) => dtype.coerce(Value::Text(s.clone())),
+        _ => return None,
+    };
+    (value.is_null() || dtype.matches(&value)).then_some(value)
+}
+
+/// The alphabet of base64 (RFC 4648), in the order of the 6-bit values.
+const BASE64: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Encodes `bytes` as base64, padded with `=`.
+fn base64_encode(bytes: &[u8]) -> String {
+    let mut out = String::with_capacity(bytes.len().div_ceil(3) * 4);
+    for chunk in bytes.chunks(3) {
+        let n = chunk.iter().enumerate().fold(0u32, |n, (i, &b)| n | (b as u32) << (16 - 8 * i));
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(BASE64[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+/// Decodes padded base64, or `None` if `text` is not.
+fn base64_decode(text: &str) -> Option<Vec<u8>> {
+    let text = text.as_bytes();
+    if !text.len().is_multiple_of(4) {
+        return None;
+    }
+    let mut out = Vec::with_capacity(text.len() / 4 * 3);
+    for (i, chunk) in text.chunks(4).enumerate() {
+        let last = i == text.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && !last) {
+            return None;
+        }
+        let mut n = 0u32;
+        for &c in &chunk[..4 - padding] {
+            n = n << 6 | BASE64.iter().position(|&b| b == c)? as u32;
+        }
+        n <<= 6 * padding as u32;
+        out.extend_from_slice(&n.to_be_bytes()[1..4 - padding]);
+    }
+    Some(out)
+}
+
+/// Decodes a hex string, or `None` if `text` is not one.
+fn hex_decode(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..text.len()).step_by(2).map(|i| u8::from_str_radix(text.get(i..i + 2)?, 16).ok()).collect()
+}
+
+/// Writes `contents` to `path` through a temporary file and a rename, so a
+/// crash never leaves a half-written file behind.
+fn write_file(path: &Path, contents: &[u8]) -> SqlResult<()> {
+    let tmp = path.with_extension("tmp");
+    fs::write(&tmp, contents).map_err(|e| io_error("cannot write", &tmp, e))?;
+    fs::rename(&tmp, path).map_err(|e| io_error("cannot write", path, e))
+}
+
+fn io_error(action: &str, path: &Path, e: std::io::Error) -> SqlError {
+    SqlError::new_io(&format!("Storage: {} '{}': {}", action, path.display(), e))
+}
+
+fn corrupt(path: &Path, what: &str) -> SqlError {
+    SqlError::new_io(&format!("Storage: corrupt catalog '{}': {}", path.display(), what))
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use tempfile::tempdir;
-    use crate::frontend::ast::{ColumnName, DataType};
+    use crate::testing::TempDir;
 
     fn sample_table() -> Table {
-        Table {
-            columns: vec![
-                Column { name: ColumnName("id".into()), ty: DataType::Int },
-                Column { name: ColumnName("name".into()), ty: DataType::Text },
-            ],
-            rows: vec![
-                Row { values: vec![Value::Int(1), Value::Str("Alice".into())] },
-                Row { values: vec![Value::Int(2), Value::Str("Bob".into())] },
-            ],
+        let id = ColumnName::new("id").unwrap();
+        let schema = Schema::try_new(vec![
+            Column::new(id.clone(), DataType::Int),
+            Column::new(ColumnName::new("name").unwrap(), DataType::Text).with_unique(true),
+            Column::new(ColumnName::new("score").unwrap(), DataType::Float),
+            Column::new(ColumnName::new("born").unwrap(), DataType::Date),
+            Column::new(ColumnName::new("avatar").unwrap(), DataType::Blob),
+        ])
+        .unwrap()
+        .with_primary_key(&id)
+        .unwrap();
+        let mut table = Table::create(TableName::new("users").unwrap(), schema);
+        let rows = vec![
+            vec![Value::Int(i64::MAX), Value::Text("Al \"ice\"\n".into()), Value::Float(0.1), Value::Date(19_723), Value::Blob(vec![0, 255])],
+            vec![Value::Int(2), Value::Null, Value::Float(f64::NAN), Value::Null, Value::Null],
+        ];
+        for values in rows {
+            let row = Row::from_values(values, table.schema()).unwrap();
+            table.insert_checked(row).unwrap();
         }
+        table.create_index("users_score", &ColumnName::new("score").unwrap(), IndexKind::Hash).unwrap();
+        table
     }
 
     #[test]
     fn save_and_load_roundtrip() {
-        let tmp = tempdir().unwrap();
-        let path = tmp.path().join("users.json");
+        let tmp = TempDir::new("storage");
+        let path = tmp.join("users.json");
 
-        let t1 = sample_table();
+        let mut t1 = sample_table();
+        t1.delete_where(|row| Ok(row.values()[0] == Value::Int(i64::MAX))).unwrap();
         save_table(&t1, &path).unwrap();
         let t2 = load_table(&path).unwrap();
 
-        assert_eq!(t1.columns.len(), t2.columns.len());
-        assert_eq!(t1.rows.len(), t2.rows.len());
-        assert_eq!(t1.rows[0].values, t2.rows[0].values);
-        assert_eq!(t1.rows[1].values, t2.rows[1].values);
+        assert_eq!(t2.name(), t1.name());
+        assert_eq!(t2.schema().columns(), t1.schema().columns());
+        assert_eq!(t2.schema().primary_key(), Some(0));
+        assert_eq!(t2.index("users_score").map(|i| (i.column(), i.kind())), Some((2, IndexKind::Hash)));
+        assert_eq!(t2.rows(), t1.rows());
+        assert_eq!((t2.rowids(), t2.next_rowid()), (&[2][..], 3));
+    }
+
+    #[test]
+    fn blobs_are_base64_and_old_hex_files_still_load() {
+        for (bytes, text) in [(&b""[..], ""), (b"f", "Zg=="), (b"fo", "Zm8="), (b"foo", "Zm9v"), (b"foob\xff", "Zm9vYv8=")] {
+            assert_eq!(value_to_json(&Value::Blob(bytes.to_vec())), Json::String(text.to_string()));
+            assert_eq!(value_from_json(&Json::String(text.to_string()), &DataType::Blob), Some(Value::Blob(bytes.to_vec())));
+        }
+        for bad in ["Zg=", "Z===", "Zg==Zg==", "Zm9*"] {
+            assert_eq!(value_from_json(&Json::String(bad.to_string()), &DataType::Blob), None, "{}", bad);
+        }
+
+        // Files without the "blobs" field hold hex
+        let tmp = TempDir::new("storage");
+        let path = tmp.join("hex.json");
+        fs::write(&path, r#"{"name":"t","columns":[{"name":"b","type":"Blob"}],"rows":[["00ff"]]}"#).unwrap();
+        assert_eq!(load_table(&path).unwrap().rows()[0].values(), &vec![Value::Blob(vec![0, 255])]);
     }
 
     #[test]
     fn error_on_missing_file() {
-        let path = Path::new("nonexistent.json");
-        let e = load_table(path).unwrap_err();
-        assert!(matches!(e, SqlError::IoError { .. }));
+        let e = load_table(&TempDir::new("storage").join("nonexistent.json")).unwrap_err();
+        assert!(e.message().contains("cannot read"));
+    }
+
+    #[test]
+    fn save_and_load_whole_database() {
+        let tmp = TempDir::new("storage");
+        let dir = tmp.join("db");
+        let mut db = Database::new();
+        db.add_table(sample_table()).unwrap();
+        let schema = Schema::try_new(vec![Column::new(ColumnName::new("n").unwrap(), DataType::Int)]).unwrap();
+        db.create_table(TableName::new("empty").unwrap(), schema).unwrap();
+
+        save_database(&db, &dir).unwrap();
+        let loaded = load_database(&dir).unwrap();
+
+        assert_eq!(loaded.table_names(), db.table_names());
+        let users = TableName::new("users").unwrap();
+        assert_eq!(loaded.table(&users).unwrap().rows(), db.table(&users).unwrap().rows());
+        assert!(loaded.table(&TableName::new("empty").unwrap()).unwrap().rows().is_empty());
+
+        // Files are validated on load
+        fs::write(dir.join("empty.json"), r#"{"name":"empty","columns":[{"name":"n","type":"Int"}],"rows":[["x"]]}"#).unwrap();
+        assert!(load_database(&dir).is_err());
+    }
+
+    #[test]
+    fn schemas_and_views_are_saved_with_the_tables() {
+        for format in [StorageFormat::Json, StorageFormat::Binary, StorageFormat::Heap] {
+            let tmp = TempDir::new("storage");
+            let dir = tmp.join("schemas");
+            let mut db = Database::new();
+            db.catalog_mut().create_schema("sales").unwrap();
+            db.catalog_mut().create_schema("hr").unwrap();
+            let schema = Schema::try_new(vec![Column::new(ColumnName::new("n").unwrap(), DataType::Int)]).unwrap();
+            db.create_table(TableName::qualified("sales", "orders").unwrap(), schema.clone()).unwrap();
+            db.create_table(TableName::new("orders").unwrap(), schema).unwrap();
+            db.create_view(&TableName::qualified("sales", "big").unwrap(), "SELECT * FROM sales.orders WHERE n > 10").unwrap();
+
+            save_database_as(&db, &dir, format).unwrap();
+            assert!(dir.join(format!("sales.orders.{}", format.extension())).exists());
+            let loaded = load_database(&dir).unwrap();
+            assert_eq!(loaded.catalog().schemas(), vec!["hr", "sales"]);
+            assert_eq!(loaded.table_names(), db.table_names());
+            assert_eq!(loaded.catalog().views(), vec![("sales.big", "SELECT * FROM sales.orders WHERE n > 10")]);
+        }
+    }
+
+    #[test]
+    fn saving_after_a_rename_replaces_the_old_file() {
+        let tmp = TempDir::new("storage");
+        let dir = tmp.join("renamed");
+        let mut db = Database::new();
+        db.add_table(sample_table()).unwrap();
+        save_database(&db, &dir).unwrap();
+        fs::write(dir.join("notes.json"), "not a table").unwrap();
+
+        let (users, members) = (TableName::new("users").unwrap(), TableName::new("members").unwrap());
+        db.rename_table(&users, &members).unwrap();
+        save_database(&db, &dir).unwrap();
+        assert!(!dir.join("users.json").exists());
+        assert!(dir.join("members.json").exists());
+        // Files the catalog never listed are left alone
+        assert!(dir.join("notes.json").exists());
+
+        let loaded = load_database(&dir).unwrap();
+        assert!(loaded.table(&users).is_none());
+        assert_eq!(loaded.table(&members).unwrap().rows(), db.table(&members).unwrap().rows());
+    }
+
+    #[test]
+    fn jsonl_export_and_import() {
+        let tmp = TempDir::new("storage");
+        let path = tmp.join("users.jsonl");
+        let t1 = sample_table();
+        export_jsonl(&t1, &path).unwrap();
+        let text = fs::read_to_string(&path).unwrap();
+        assert_eq!(text.lines().count(), 2);
+        assert!(text.starts_with(r#"{"id":9223372036854775807,"name":"Al \"ice\"\n","#));
+
+        let mut t2 = Table::create(t1.name().clone(), t1.schema().clone());
+        assert_eq!(import_jsonl(&mut t2, &path).unwrap(), 2);
+        assert_eq!(t2.rows(), t1.rows());
+
+        // Missing keys are NULL; an unknown key rejects the whole input
+        let mut t3 = Table::create(t1.name().clone(), t1.schema().clone());
+        assert_eq!(read_jsonl(&mut t3, "{\"id\": 5}\n\n".as_bytes()).unwrap(), 1);
+        assert_eq!(t3.rows()[0].values()[1], Value::Null);
+        let err = read_jsonl(&mut t3, "{\"id\": 6}\n{\"id\": 7, \"age\": 1}\n".as_bytes()).unwrap_err();
+        assert!(err.message().contains("line 2: unknown column 'age'"));
+        assert_eq!(t3.rows().len(), 1);
+    }
+
+    #[test]
+    fn binary_database_roundtrip() {
+        let tmp = TempDir::new("storage");
+        let dir = tmp.join("db_binary");
+        let mut db = Database::new();
+        db.add_table(sample_table()).unwrap();
+
+        save_database_as(&db, &dir, StorageFormat::Binary).unwrap();
+        assert!(dir.join("users.bin").exists());
+        let loaded = load_database(&dir).unwrap();
+
+        let users = TableName::new("users").unwrap();
+        let (t1, t2) = (db.table(&users).unwrap(), loaded.table(&users).unwrap());
+        assert_eq!(t2.schema().columns(), t1.schema().columns());
+        assert_eq!(t2.rows(), t1.rows());
+        assert!(encode_table(t1, StorageFormat::Binary).unwrap().len() < encode_table(t1, StorageFormat::Json).unwrap().len());
+    }
+
+    #[test]
+    fn heap_database_roundtrip() {
+        let tmp = TempDir::new("storage");
+        let dir = tmp.join("db_heap");
+        let mut db = Database::new();
+        let mut table = sample_table();
+        table.delete_where(|row| Ok(row.values()[0] == Value::Int(2))).unwrap();
+        db.add_table(table).unwrap();
+
+        save_database_as(&db, &dir, StorageFormat::Heap).unwrap();
+        let path = dir.join("users.heap");
+        assert!(heap::is_heap_file(&path).unwrap());
+        let loaded = load_database(&dir).unwrap();
+
+        let users = TableName::new("users").unwrap();
+        let (t1, t2) = (db.table(&users).unwrap(), loaded.table(&users).unwrap());
+        assert_eq!(t2.schema().columns(), t1.schema().columns());
+        assert_eq!(t2.schema().primary_key(), Some(0));
+        assert_eq!(t2.index("users_score").map(|i| (i.column(), i.kind())), Some((2, IndexKind::Hash)));
+        assert_eq!(t2.rows(), t1.rows());
+        assert_eq!((t2.rowids(), t2.next_rowid()), (&[1][..], 3));
+        // Read whole, as a compressed heap file is
+        assert_eq!(decode_table(&fs::read(&path).unwrap()).unwrap().rows(), t1.rows());
+    }
+
+    #[test]
+    fn compressed_database_loads_like_any_other() {
+        let tmp = TempDir::new("storage");
+        let dir = tmp.join("db_compressed");
+        let mut db = Database::new();
+        db.add_table(sample_table()).unwrap();
+        let users = TableName::new("users").unwrap();
+
+        for compression in Compression::ALL.into_iter().filter(|c| c.is_available()) {
+            save_database_compressed(&db, &dir, StorageFormat::Json, compression).unwrap();
+            let file = fs::read(dir.join("users.json")).unwrap();
+            assert_eq!(Compression::detect(&file), compression);
+            assert_eq!(load_database(&dir).unwrap().table(&users).unwrap().rows(), db.table(&users).unwrap().rows());
+        }
     }
 }
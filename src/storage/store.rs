@@ -0,0 +1,416 @@
+//! A database directory kept up to date while a session changes it.
+//!
+//! A [`Store`] is the lifecycle of a database kept on disk:
+//! [`Store::open`] locks the directory and recovers the database,
+//! [`Store::record`] logs each statement executed on it, and
+//! [`Store::close`] saves it and releases the directory.
+//!
+//! The store appends each committed change to the write-ahead log of the
+//! directory (see [`wal`](crate::storage::wal)), so that nothing committed
+//! is lost in a crash, or if the store is dropped without being closed.
+//! Left alone, the log would grow forever and make recovery replay all of
+//! it: once it is larger than the checkpoint size, the store writes a new
+//! snapshot and starts an empty log (see [`checkpoint_compressed`]).
+//! `CHECKPOINT` does the same on demand.
+//!
+//! A store locks its directory (see [`DirLock`]) until it is closed or
+//! dropped, so only one process at a time changes a database.
+//!
+//! [`Database::open`] attaches a store to the database it opens, as its
+//! [`Backing`], for programs that do not log each statement: the database
+//! is then saved when it is flushed, closed or dropped.
+//!
+//! Checkpoints only happen between transactions, since a snapshot must not
+//! hold uncommitted changes.
+//!
+//! How soon a commit is safe on disk is the [`SyncMode`] of the log, set
+//! with [`Store::with_sync_mode`] or, during a session, with
+//! `PRAGMA synchronous = off | normal | full` (see [`Store::pragma`]).
+//! Checkpoints can also compress the snapshot (see
+//! [`Store::with_compression`]).
+
+use std::path::{Path, PathBuf};
+
+use crate::core::db::{Backing, Database};
+use crate::core::error::{SqlError, SqlResult};
+use crate::core::row::Row;
+use crate::core::schema::{Column, Schema};
+use crate::core::types::{ColumnName, DataType, Value};
+use crate::executor::executor::Output;
+use crate::frontend::ast::{ASTNode, PragmaStmt};
+use crate::frontend::parser::Parser;
+use crate::storage::compression::Compression;
+use crate::storage::recovery::{checkpoint_compressed, recover_with_rejects, wal_path, DirLock, Rejected};
+use crate::storage::wal::{SyncMode, Wal};
+
+/// Log size, in bytes, past which a [`Store`] checkpoints by default.
+pub const DEFAULT_CHECKPOINT_SIZE: u64 = 4 * 1024 * 1024;
+
+impl Database {
+    /// Opens the database stored in the directory `path`, recovering any
+    /// change committed since the last checkpoint (see [`Store::open`]),
+    /// and attaches the directory to it (see [`Database::attach`]).
+    ///
+    /// The directory is created if it does not exist, and locked until the
+    /// database is closed or dropped. Statements executed on the database
+    /// are not logged: its changes are saved by [`Database::flush`],
+    /// [`Database::close`] or dropping it. Use [`Store::open`] to log each
+    /// statement instead.
+    ///
+    /// # Example
+    /// ```
+    /// use mini_rust_sgbd::core::db::Database;
+    /// use mini_rust_sgbd::executor::executor::Executor;
+    /// use mini_rust_sgbd::frontend::{lexer::lexer, parser::Parser};
+    ///
+    /// let dir = std::env::temp_dir().join(format!("mini_rust_sgbd_doc_db_open_{}", std::process::id()));
+    /// let mut db = Database::open(&dir).unwrap();
+    /// let ast = Parser::default().parse(&lexer("CREATE TABLE t (n INT)").unwrap()).unwrap();
+    /// Executor::execute(ast, &mut db).unwrap();
+    /// db.close().unwrap();
+    ///
+    /// assert_eq!(Database::open(&dir).unwrap().table_count(), 1);
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    pub fn open(path: &Path) -> SqlResult<Database> {
+        let (mut db, store) = Store::open(path)?;
+        // Nothing takes the changes to log them
+        db.record_changes(false);
+        db.attach(Box::new(store));
+        Ok(db)
+    }
+}
+
+/// The directory a database is stored in, with its open log.
+#[derive(Debug)]
+pub struct Store {
+    dir: PathBuf,
+    wal: Wal,
+    checkpoint_size: u64,
+    compression: Compression,
+    rejected: Option<Rejected>,
+    _lock: DirLock,
+}
+
+impl Store {
+    /// Locks the directory `dir` and opens the database stored there,
+    /// recovering it (see [`recover_with_rejects`]). The directory is
+    /// created if it does not exist.
+    ///
+    /// The database records the rows each statement changes, for
+    /// [`Store::record`] to log.
+    ///
+    /// # Returns
+    /// The database and the store to record its changes in, or
+    /// `Err(SqlError)` if another process has the directory locked or the
+    /// database cannot be recovered.
+    ///
+    /// # Example
+    /// ```
+    /// use mini_rust_sgbd::executor::executor::Executor;
+    /// use mini_rust_sgbd::frontend::{lexer::lexer, parser::Parser};
+    /// use mini_rust_sgbd::storage::store::Store;
+    ///
+    /// let dir = std::env::temp_dir().join(format!("mini_rust_sgbd_doc_open_{}", std::process::id()));
+    /// let (mut db, mut store) = Store::open(&dir).unwrap();
+    /// let sql = "CREATE TABLE t (n INT)";
+    /// let ast = Parser::default().parse(&lexer(sql).unwrap()).unwrap();
+    /// Executor::execute(ast.clone(), &mut db).unwrap();
+    /// store.record(&ast, sql, &mut db).unwrap();
+    /// store.close(db).unwrap();
+    ///
+    /// assert_eq!(Store::open(&dir).unwrap().0.table_count(), 1);
+    /// # std::fs::remove_dir_all(&dir).unwrap();
+    /// ```
+    pub fn open(dir: &Path) -> SqlResult<(Database, Store)> {
+        Store::open_with(dir, &Parser::default())
+    }
+
+    /// Opens the database stored in `dir` as [`Store::open`] does, replaying
+    /// the logged statements with `parser`, which must know the type
+    /// aliases they were written with.
+    pub fn open_with(dir: &Path, parser: &Parser) -> SqlResult<(Database, Store)> {
+        let lock = DirLock::acquire(dir)?;
+        let (mut db, rejected) = recover_with_rejects(dir, parser)?;
+        db.record_changes(true);
+        let wal = Wal::open(&wal_path(dir)?)?;
+        let store = Store {
+            dir: dir.to_path_buf(),
+            wal,
+            checkpoint_size: DEFAULT_CHECKPOINT_SIZE,
+            compression: Compression::None,
+            rejected,
+            _lock: lock,
+        };
+        Ok((db, store))
+    }
+
+    /// Sets the log size, in bytes, past which the store checkpoints.
+    pub fn with_checkpoint_size(mut self, bytes: u64) -> Self {
+        self.checkpoint_size = bytes;
+        self
+    }
+
+    /// Sets when committed changes reach the disk, [`SyncMode::Full`] by
+    /// default.
+    pub fn with_sync_mode(mut self, sync: SyncMode) -> Self {
+        self.wal = self.wal.with_sync(sync);
+        self
+    }
+
+    /// Sets how checkpoints compress the table files, not at all by
+    /// default. Snapshots are read whatever their compression.
+    pub fn with_compression(mut self, compression: Compression) -> Self {
+        self.compression = compression;
+        self
+    }
+
+    /// Returns when committed changes reach the disk.
+    pub fn sync_mode(&self) -> SyncMode {
+        self.wal.sync()
+    }
+
+    /// Returns the directory of the database.
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// Returns the log records that failed to replay when the store was
+    /// opened, and were moved aside (see [`recover_with_rejects`]).
+    pub fn rejected(&self) -> Option<&Rejected> {
+        self.rejected.as_ref()
+    }
+
+    /// Records a statement that has just executed successfully on `db`,
+    /// with the row changes `db` recorded (see [`Wal::record`]), then
+    /// checkpoints if the log has grown past the checkpoint size and no
+    /// transaction is open.
+    ///
+    /// # Arguments
+    /// - `ast`: the executed statement
+    /// - `sql`: its source text
+    /// - `db`: the database, as the statement left it
+    ///
+    /// # Returns
+    /// `Ok(true)` if it checkpointed, `Ok(false)` if not, or
+    /// `Err(SqlError)` on I/O failure.
+    pub fn record(&mut self, ast: &ASTNode, sql: &str, db: &mut Database) -> SqlResult<bool> {
+        self.wal.record(ast, sql, db.take_changes())?;
+        if db.in_transaction() {
+            return Ok(false);
+        }
+        self.checkpoint_if_large(db)
+    }
+
+    /// Logs the records of a transaction committed on a database shared
+    /// between sessions (see [`Wal::append_transaction`]), which then
+    /// checkpoints with [`Store::checkpoint_if_large`].
+    ///
+    /// # Returns
+    /// `Err(SqlError)` on I/O failure.
+    pub fn record_transaction(&mut self, records: Vec<String>) -> SqlResult<()> {
+        self.wal.append_transaction(records)
+    }
+
+    /// Checkpoints `db`, which has no transaction open, if the log has
+    /// grown past the checkpoint size.
+    ///
+    /// # Returns
+    /// `Ok(true)` if it checkpointed, `Ok(false)` if not, or
+    /// `Err(SqlError)` on I/O failure.
+    pub fn checkpoint_if_large(&mut self, db: &Database) -> SqlResult<bool> {
+        if self.wal.size()? <= self.checkpoint_size {
+            return Ok(false);
+        }
+        self.checkpoint(db)?;
+        Ok(true)
+    }
+
+    /// Writes `db` as a new snapshot of the directory and empties the log.
+    ///
+    /// # Returns
+    /// `Err(SqlError)` if a transaction is in progress or on I/O failure.
+    pub fn checkpoint(&mut self, db: &Database) -> SqlResult<()> {
+        let sync = self.wal.sync();
+        self.wal = Wal::open(&checkpoint_compressed(db, &self.dir, self.compression)?)?.with_sync(sync);
+        Ok(())
+    }
+
+    /// Closes the database opened with the store: rolls back an open
+    /// transaction, writes `db` as a new snapshot, so that the next
+    /// [`Store::open`] has no log to replay, and unlocks the directory.
+    ///
+    /// Dropping the store instead loses nothing committed, which is in the
+    /// log, but leaves the log to replay.
+    ///
+    /// # Returns
+    /// `Err(SqlError)` if the snapshot cannot be written; the directory is
+    /// unlocked either way.
+    pub fn close(mut self, mut db: Database) -> SqlResult<()> {
+        if db.in_transaction() {
+            db.rollback()?;
+        }
+        self.checkpoint(&db)
+    }
+
+    /// Runs `PRAGMA`, which reads or changes a setting of the store.
+    ///
+    /// The only setting is `synchronous`, the [`SyncMode`] of the log.
+    ///
+    /// # Returns
+    /// The setting as a one-row result when it is read, `Output::None` when
+    /// it is changed, or `Err(SqlError)` for an unknown setting or value.
+    pub fn pragma(&mut self, stmt: &PragmaStmt) -> SqlResult<Output> {
+        if stmt.name != "synchronous" {
+            return Err(SqlError::new_semantic(&format!("Unknown pragma '{}'", stmt.name)));
+        }
+        let Some(value) = &stmt.value else {
+            let columns = vec![Column::new(ColumnName::new("synchronous").expect("valid column name"), DataType::Text)];
+            let schema = Schema::try_new(columns.clone()).expect("one column");
+            let row = Row::from_values(vec![Value::Text(self.sync_mode().name().to_string())], &schema)?;
+            return Ok(Output::Rows { columns, rows: vec![row] });
+        };
+        let sync = SyncMode::parse(value).ok_or_else(|| {
+            SqlError::new_semantic(&format!("Unknown synchronous mode '{}': expected off, normal or full", value))
+        })?;
+        self.wal.set_sync(sync)?;
+        Ok(Output::None)
+    }
+}
+
+/// Saving a database to its store checkpoints it.
+impl Backing for Store {
+    fn save(&mut self, db: &Database) -> SqlResult<()> {
+        self.checkpoint(db)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::types::TableName;
+    use crate::executor::executor::Executor;
+    use crate::executor::shared::SharedDatabase;
+    use crate::frontend::lexer::lexer;
+    use crate::storage::recovery::recover;
+    use crate::testing::TempDir;
+    use std::fs;
+
+    fn run(db: &mut Database, store: &mut Store, sql: &str) -> bool {
+        let ast = Parser::default().parse(&lexer(sql).unwrap()).unwrap();
+        Executor::execute(ast.clone(), db).unwrap();
+        store.record(&ast, sql, db).unwrap()
+    }
+
+    #[test]
+    fn the_log_is_checkpointed_once_it_grows() {
+        let tmp = TempDir::new("store");
+        let dir = tmp.path();
+        let (mut db, store) = Store::open(dir).unwrap();
+        let mut store = store.with_checkpoint_size(100);
+
+        assert!(!run(&mut db, &mut store, "CREATE TABLE t (id INT PRIMARY KEY)"));
+        assert!(!run(&mut db, &mut store, "BEGIN"));
+        for i in 0..5 {
+            assert!(!run(&mut db, &mut store, &format!("INSERT INTO t VALUES ({})", i)));
+        }
+        // The log is past the size once the transaction is written
+        assert!(run(&mut db, &mut store, "COMMIT"));
+        assert_eq!(fs::read_to_string(dir.join("CURRENT")).unwrap(), "1");
+        assert!(!run(&mut db, &mut store, "INSERT INTO t VALUES (5)"));
+
+        // Without a checkpoint, the last insert is recovered from the log
+        assert!(Store::open(dir).is_err());
+        drop(store);
+        let (db, mut store) = Store::open(dir).unwrap();
+        assert_eq!(db.table(&TableName::new("t").unwrap()).unwrap().rows().len(), 6);
+        store.checkpoint(&db).unwrap();
+        assert_eq!(store.wal.size().unwrap(), 0);
+        assert!(!dir.join("wal-1.log").exists());
+    }
+
+    #[test]
+    fn an_opened_database_is_saved_when_closed_or_dropped() {
+        let tmp = TempDir::new("store_db_open");
+        let dir = tmp.path();
+        let rows = |db: &Database| db.table(&TableName::new("t").unwrap()).map_or(0, |t| t.rows().len());
+        let execute = |db: &mut Database, sql: &str| {
+            Executor::execute(Parser::default().parse(&lexer(sql).unwrap()).unwrap(), db).unwrap();
+        };
+
+        let mut db = Database::open(dir).unwrap();
+        assert!(db.is_attached() && Database::open(dir).is_err());
+        execute(&mut db, "CREATE TABLE t (id INT)");
+        execute(&mut db, "INSERT INTO t VALUES (1)");
+        db.flush().unwrap();
+        assert_eq!(rows(&recover(dir).unwrap()), 1);
+        db.close().unwrap();
+
+        // Dropped in a transaction: only the committed rows are saved, and
+        // a clone never saves
+        let mut db = Database::open(dir).unwrap();
+        assert_eq!(rows(&db), 1);
+        execute(&mut db, "INSERT INTO t VALUES (2)");
+        drop(db.clone());
+        execute(&mut db, "BEGIN");
+        execute(&mut db, "INSERT INTO t VALUES (3)");
+        assert!(db.flush().is_err());
+        drop(db);
+
+        // Shared, it is saved once the last handle is gone
+        let shared = SharedDatabase::new(Database::open(dir).unwrap());
+        assert_eq!(shared.read(rows), 2);
+        shared.write(|db| execute(db, "INSERT INTO t VALUES (4)")).unwrap();
+        drop(shared);
+        assert_eq!(rows(&recover(dir).unwrap()), 3);
+    }
+
+    #[test]
+    fn closing_saves_the_database_and_unlocks_the_directory() {
+        let tmp = TempDir::new("store_close");
+        let dir = tmp.path();
+        let rows = |db: &Database| db.table(&TableName::new("t").unwrap()).map_or(0, |t| t.rows().len());
+
+        let (mut db, mut store) = Store::open(dir).unwrap();
+        assert!(Store::open(dir).is_err());
+        run(&mut db, &mut store, "CREATE TABLE t (id INT)");
+        run(&mut db, &mut store, "INSERT INTO t VALUES (1)");
+        run(&mut db, &mut store, "BEGIN");
+        run(&mut db, &mut store, "INSERT INTO t VALUES (2)");
+        store.close(db).unwrap();
+
+        // Closed in a transaction: only the committed rows are saved, and
+        // the log is empty
+        assert_eq!(fs::read_to_string(dir.join("CURRENT")).unwrap(), "1");
+        assert_eq!(fs::metadata(dir.join("wal-1.log")).unwrap().len(), 0);
+        let (db, store) = Store::open(dir).unwrap();
+        assert_eq!(rows(&db), 1);
+        drop(store);
+    }
+
+    #[test]
+    fn pragma_reads_and_sets_the_sync_mode() {
+        let tmp = TempDir::new("store_sync");
+        let dir = tmp.path();
+        let (mut db, store) = Store::open(dir).unwrap();
+        let mut store = store.with_sync_mode(SyncMode::Normal);
+        let pragma = |store: &mut Store, sql: &str| match Parser::default().parse(&lexer(sql).unwrap()).unwrap() {
+            ASTNode::Pragma(stmt) => store.pragma(&stmt),
+            other => panic!("Expected a pragma, got {:?}", other),
+        };
+
+        match pragma(&mut store, "PRAGMA synchronous").unwrap() {
+            Output::Rows { rows, .. } => assert_eq!(rows[0].values(), &vec![Value::Text("normal".to_string())]),
+            other => panic!("Expected rows, got {:?}", other),
+        }
+        assert_eq!(pragma(&mut store, "PRAGMA synchronous = OFF"), Ok(Output::None));
+        assert_eq!(store.sync_mode(), SyncMode::Off);
+        assert!(pragma(&mut store, "PRAGMA synchronous = sometimes").is_err());
+        assert!(pragma(&mut store, "PRAGMA cache_size = 10").is_err());
+
+        // The mode outlives checkpoints
+        run(&mut db, &mut store, "CREATE TABLE t (id INT)");
+        store.checkpoint(&db).unwrap();
+        assert_eq!(store.sync_mode(), SyncMode::Off);
+    }
+}
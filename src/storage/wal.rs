@@ -0,0 +1,619 @@
+//! Write-ahead log: an append-only file of committed mutations.
+//!
+//! Every statement that changes the database is appended to the log once
+//! it is committed. A statement that changes rows (INSERT, UPDATE, DELETE,
+//! COPY ... FROM) is logged as its effects: the rows it inserted, updated
+//! and deleted in each table, by rowid, with their values as stored. Other
+//! statements (CREATE TABLE, ALTER TABLE, TRUNCATE, ...) are logged as their
+//! SQL text. Replaying the log against the last snapshot applies those
+//! effects and re-executes those statements in order, and rebuilds the
+//! state lost in a crash.
+//!
+//! Replay never evaluates a row statement again, so it restores the rows
+//! as they were written: with the time `NOW()` returned then, and with
+//! the rows a `COPY ... FROM` file held then, even if it is gone since.
+//! Snapshots keep the rowids, so that effects logged after a checkpoint
+//! find their rows.
+//!
+//! # Format
+//! Each record is `<length> <body>\n`, where `<length>` is the byte length
+//! of `<body>` in decimal. A record cut short by a crash (missing bytes or
+//! missing newline) ends the log: it and anything after it are ignored.
+//!
+//! A body starting with `{` holds the effects of a statement on one table,
+//! as a JSON object such as
+//! `{"table":"t","changes":[["insert",3,[3,"c"]],["update",1,[1,"a"]],["delete",2]]}`.
+//! Values are encoded as in table files (see
+//! [`storage`](crate::storage::storage)). Any other body is a statement.
+//!
+//! A committed transaction is written between `BEGIN` and `COMMIT` records.
+//! If the log ends before the `COMMIT`, replay rolls the transaction back,
+//! so a crash while writing it never leaves part of it applied.
+//!
+//! Replay stops at a record that is malformed or fails, such as an effect
+//! on a table that does not exist, and keeps what was committed before it
+//! (see [`replay`]); recovery then moves the rest of the log aside (see
+//! [`recovery`](crate::storage::recovery)).
+//!
+//! # Durability
+//! How soon a committed statement is safe depends on the [`SyncMode`] of
+//! the log: with [`SyncMode::Full`], the default, it is on disk before
+//! `COMMIT` returns; with [`SyncMode::Normal`], it survives the program
+//! crashing but not the machine; with [`SyncMode::Off`], it is only written
+//! when the log is closed, and a crash loses it. The faster modes are meant
+//! for bulk loads that can be started over.
+
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use crate::core::db::Database;
+use crate::core::error::{SqlError, SqlResult};
+use crate::core::table::RowChange;
+use crate::core::types::TableName;
+use crate::executor::executor::Executor;
+use crate::frontend::ast::{ASTNode, TransactionStmt};
+use crate::frontend::lexer::lexer;
+use crate::frontend::parser::Parser;
+use crate::storage::json::Json;
+use crate::storage::storage::{rowid_from_json, rowid_to_json, value_to_json, values_from_json};
+
+/// When the records of a [`Wal`] reach the disk (see the module docs).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum SyncMode {
+    /// Records are kept in memory until the log is closed.
+    Off,
+    /// Records are handed to the operating system at each commit.
+    Normal,
+    /// Records are forced to disk at each commit.
+    #[default]
+    Full,
+}
+
+impl SyncMode {
+    /// Every mode, from the fastest to the safest.
+    pub const ALL: [SyncMode; 3] = [SyncMode::Off, SyncMode::Normal, SyncMode::Full];
+
+    /// Returns the mode named `name` (`off`, `normal` or `full`, in any
+    /// case), if there is one.
+    pub fn parse(name: &str) -> Option<SyncMode> {
+        SyncMode::ALL.into_iter().find(|mode| mode.name().eq_ignore_ascii_case(name))
+    }
+
+    /// Returns the name of the mode, as accepted by [`SyncMode::parse`].
+    pub fn name(self) -> &'static str {
+        match self {
+            SyncMode::Off => "off",
+            SyncMode::Normal => "normal",
+            SyncMode::Full => "full",
+        }
+    }
+}
+
+/// An open write-ahead log.
+///
+/// Records of statements executed inside a transaction are held back in
+/// `pending` and only written when the transaction commits. With [`SyncMode::Off`],
+/// written records wait in `unwritten` until the log is flushed or closed.
+#[derive(Debug)]
+pub struct Wal {
+    path: PathBuf,
+    file: File,
+    pending: Option<Vec<String>>,
+    sync: SyncMode,
+    unwritten: Vec<u8>,
+}
+
+impl Wal {
+    /// Opens the log at `path` for appending, creating the file if needed.
+    ///
+    /// # Returns
+    /// The open `Wal`, or `Err(SqlError)` if the file cannot be opened.
+    pub fn open(path: &Path) -> SqlResult<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| io_error("cannot open", path, e))?;
+        Ok(Wal { path: path.to_path_buf(), file, pending: None, sync: SyncMode::default(), unwritten: Vec::new() })
+    }
+
+    /// Sets when records reach the disk, [`SyncMode::Full`] by default.
+    pub fn with_sync(mut self, sync: SyncMode) -> Self {
+        self.sync = sync;
+        self
+    }
+
+    /// Changes when records reach the disk, first flushing those that
+    /// [`SyncMode::Off`] held back.
+    pub fn set_sync(&mut self, sync: SyncMode) -> SqlResult<()> {
+        self.flush()?;
+        self.sync = sync;
+        Ok(())
+    }
+
+    /// Returns when records reach the disk.
+    pub fn sync(&self) -> SyncMode {
+        self.sync
+    }
+
+    /// Returns the path of the log file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Returns the size of the log in bytes, counting the records not
+    /// written yet.
+    pub fn size(&self) -> SqlResult<u64> {
+        self.file
+            .metadata()
+            .map(|metadata| metadata.len() + self.unwritten.len() as u64)
+            .map_err(|e| io_error("cannot read", &self.path, e))
+    }
+
+    /// Writes the records held back by [`SyncMode::Off`] and forces the
+    /// log to disk.
+    pub fn flush(&mut self) -> SqlResult<()> {
+        let unwritten = std::mem::take(&mut self.unwritten);
+        self.file
+            .write_all(&unwritten)
+            .and_then(|_| self.file.sync_data())
+            .map_err(|e| io_error("cannot write", &self.path, e))
+    }
+
+    /// Records a statement that has just executed successfully.
+    ///
+    /// Mutations are written immediately outside a transaction, and on
+    /// `COMMIT` inside one; `ROLLBACK` discards them. Savepoint statements
+    /// are written with the transaction, so that replaying it undoes what
+    /// `ROLLBACK TO` undid. Queries, including `COPY ... TO`, are ignored.
+    ///
+    /// # Arguments
+    /// - `ast`: the executed statement
+    /// - `sql`: its source text, logged unless it only changes rows
+    /// - `changes`: the rows it changed, per table, as
+    ///   [`Database::take_changes`] returns them; logged if it only
+    ///   changes rows
+    pub fn record(&mut self, ast: &ASTNode, sql: &str, changes: Vec<(TableName, Vec<RowChange>)>) -> SqlResult<()> {
+        match ast {
+            ASTNode::Transaction(TransactionStmt::Begin) => {
+                self.pending = Some(Vec::new());
+                Ok(())
+            }
+            ASTNode::Transaction(TransactionStmt::Commit) => match self.pending.take() {
+                Some(statements) if !statements.is_empty() => self.append(&framed(statements)),
+                _ => Ok(()),
+            },
+            ASTNode::Transaction(TransactionStmt::Rollback) => {
+                self.pending = None;
+                Ok(())
+            }
+            _ if ast.is_read_only() => Ok(()),
+            _ => {
+                let records = if ast.changes_only_rows() {
+                    effect_records(&changes)
+                } else {
+                    vec![sql.to_string()]
+                };
+                match &mut self.pending {
+                    Some(statements) => {
+                        statements.extend(records);
+                        Ok(())
+                    }
+                    None if records.is_empty() => Ok(()),
+                    None => self.append(&records),
+                }
+            }
+        }
+    }
+
+    /// Appends the records of a transaction committed without going
+    /// through [`Wal::record`], e.g. by a
+    /// [`Session`](crate::executor::shared::Session), between `BEGIN` and
+    /// `COMMIT` records if there are several.
+    pub fn append_transaction(&mut self, records: Vec<String>) -> SqlResult<()> {
+        match records.len() {
+            0 => Ok(()),
+            1 => self.append(&records),
+            _ => self.append(&framed(records)),
+        }
+    }
+
+    /// Appends records, as far as the [`SyncMode`] says before returning.
+    fn append(&mut self, statements: &[String]) -> SqlResult<()> {
+        let mut buf = Vec::new();
+        for sql in statements {
+            buf.extend_from_slice(format!("{} {}\n", sql.len(), sql).as_bytes());
+        }
+        let written = match self.sync {
+            SyncMode::Off => {
+                self.unwritten.extend(buf);
+                return Ok(());
+            }
+            SyncMode::Normal => self.file.write_all(&buf),
+            SyncMode::Full => self.file.write_all(&buf).and_then(|_| self.file.sync_data()),
+        };
+        written.map_err(|e| io_error("cannot write", &self.path, e))
+    }
+}
+
+impl Drop for Wal {
+    fn drop(&mut self) {
+        if !self.unwritten.is_empty() {
+            let _ = self.flush();
+        }
+    }
+}
+
+/// Reads the records logged at `path`, oldest first.
+///
+/// A missing file is an empty log. A torn final record is dropped.
+///
+/// # Returns
+/// The bodies of the records, or `Err(SqlError)` if the file cannot be
+/// read or a complete record is malformed.
+pub fn read_log(path: &Path) -> SqlResult<Vec<String>> {
+    match read_records(path)? {
+        (_, Some(corrupt)) => Err(corrupt),
+        (records, None) => Ok(records),
+    }
+}
+
+/// Reads the records logged at `path` as [`read_log`] does, stopping at
+/// the first malformed one.
+///
+/// # Returns
+/// The records before it, with the error describing it if there is one.
+fn read_records(path: &Path) -> SqlResult<(Vec<String>, Option<SqlError>)> {
+    let mut data = Vec::new();
+    match File::open(path) {
+        Ok(mut file) => {
+            file.read_to_end(&mut data).map_err(|e| io_error("cannot read", path, e))?;
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok((Vec::new(), None)),
+        Err(e) => return Err(io_error("cannot open", path, e)),
+    }
+
+    let corrupt = || SqlError::new_io(&format!("Corrupt WAL record in '{}'", path.display()));
+    let mut records = Vec::new();
+    let mut rest = &data[..];
+    while !rest.is_empty() {
+        let Some(space) = rest.iter().position(|&b| b == b' ') else { break };
+        let Some(len) = std::str::from_utf8(&rest[..space]).ok().and_then(|s| s.parse::<usize>().ok()) else {
+            return Ok((records, Some(corrupt())));
+        };
+        let body = &rest[space + 1..];
+        if body.len() <= len || body[len] != b'\n' {
+            // Torn write at the end of the log
+            break;
+        }
+        let Ok(record) = std::str::from_utf8(&body[..len]) else {
+            return Ok((records, Some(corrupt())));
+        };
+        records.push(record.to_string());
+        rest = &body[len + 1..];
+    }
+    Ok((records, None))
+}
+
+/// What [`replay`] made of a log.
+#[derive(Debug)]
+pub struct Replayed {
+    /// The number of records replayed, up to the end of the last
+    /// transaction committed before the log ends or a record fails.
+    pub kept: usize,
+    /// The error of the record replay stopped at, if one failed or was
+    /// malformed.
+    pub error: Option<SqlError>,
+}
+
+/// Applies the effects and re-executes the statements logged at `path`
+/// against `db`, in order, until a record fails. Statements are read with
+/// `parser`, which must know the type aliases they were written with.
+///
+/// A record that fails, a malformed record, or the end of the log, stops
+/// replay: the transaction open then is rolled back, and `db` is left as
+/// the last record kept left it (see [`Replayed`]). The log is not
+/// changed; [`cut`] drops what was not kept.
+///
+/// # Returns
+/// What was replayed, or `Err(SqlError)` if the log cannot be read.
+pub fn replay(path: &Path, parser: &Parser, db: &mut Database) -> SqlResult<Replayed> {
+    let (records, corrupt) = read_records(path)?;
+    let mut replayed = Replayed { kept: 0, error: corrupt };
+    for (i, record) in records.iter().enumerate() {
+        if let Err(e) = replay_record(path, record, parser, db) {
+            replayed.error = Some(e);
+            break;
+        }
+        if !db.in_transaction() {
+            replayed.kept = i + 1;
+        }
+    }
+    if db.in_transaction() {
+        db.rollback()?;
+    }
+    Ok(replayed)
+}
+
+/// Applies one effect record, or re-executes one statement, on `db`.
+fn replay_record(path: &Path, record: &str, parser: &Parser, db: &mut Database) -> SqlResult<()> {
+    if record.starts_with('{') {
+        apply_effects(record, db).map_err(|e| {
+            SqlError::new_io(&format!("Corrupt WAL record in '{}': {}", path.display(), e.message()))
+        })
+    } else {
+        let ast = parser.parse(&lexer(record)?)?;
+        Executor::execute(ast, db).map(|_| ())
+    }
+}
+
+/// Cuts the log at `path` after its first `kept` records, so that the
+/// records appended next follow them.
+///
+/// # Arguments
+/// - `kept`: the number of records to keep, as [`replay`] counts them
+/// - `rejected`: the file to append the rest of the log to before it is
+///   cut, or `None` to drop it
+///
+/// # Returns
+/// `Err(SqlError)` on I/O failure.
+pub fn cut(path: &Path, kept: usize, rejected: Option<&Path>) -> SqlResult<()> {
+    let (records, _) = read_records(path)?;
+    let end: usize = records.iter().take(kept).map(|record| format!("{} ", record.len()).len() + record.len() + 1).sum();
+    let mut file = match OpenOptions::new().read(true).write(true).open(path) {
+        Ok(file) => file,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(io_error("cannot open", path, e)),
+    };
+    let len = file.metadata().map_err(|e| io_error("cannot read", path, e))?.len();
+    if len <= end as u64 {
+        return Ok(());
+    }
+    if let Some(rejected) = rejected {
+        let mut rest = Vec::new();
+        file.seek(SeekFrom::Start(end as u64))
+            .and_then(|_| file.read_to_end(&mut rest))
+            .map_err(|e| io_error("cannot read", path, e))?;
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(rejected)
+            .and_then(|mut out| out.write_all(&rest).and_then(|_| out.sync_data()))
+            .map_err(|e| io_error("cannot write", rejected, e))?;
+    }
+    file.set_len(end as u64).and_then(|_| file.sync_data()).map_err(|e| io_error("cannot write", path, e))
+}
+
+/// Encodes row changes, as [`Database::take_changes`] returns them, as
+/// effect records, one per table that changed.
+pub fn effect_records(changes: &[(TableName, Vec<RowChange>)]) -> Vec<String> {
+    changes.iter().filter(|(_, changes)| !changes.is_empty()).map(|(table, changes)| effect_record(table, changes)).collect()
+}
+
+/// Puts the records of a transaction between `BEGIN` and `COMMIT` records.
+fn framed(records: Vec<String>) -> Vec<String> {
+    let mut framed = Vec::with_capacity(records.len() + 2);
+    framed.push("BEGIN".to_string());
+    framed.extend(records);
+    framed.push("COMMIT".to_string());
+    framed
+}
+
+/// Encodes the changes a statement made to `table` as an effect record.
+fn effect_record(table: &TableName, changes: &[RowChange]) -> String {
+    let changes = changes
+        .iter()
+        .map(|change| {
+            let (kind, rowid, values) = match change {
+                RowChange::Insert(rowid, values) => ("insert", rowid, Some(values)),
+                RowChange::Update(rowid, values) => ("update", rowid, Some(values)),
+                RowChange::Delete(rowid) => ("delete", rowid, None),
+            };
+            let mut items = vec![Json::String(kind.to_string()), rowid_to_json(*rowid)];
+            items.extend(values.map(|values| Json::Array(values.iter().map(value_to_json).collect())));
+            Json::Array(items)
+        })
+        .collect();
+    Json::Object(vec![
+        ("table".to_string(), Json::String(table.as_str().to_string())),
+        ("changes".to_string(), Json::Array(changes)),
+    ])
+    .to_string()
+}
+
+/// Makes the changes of an effect record to its table in `db`, typing
+/// the values by the columns of the table.
+fn apply_effects(record: &str, db: &mut Database) -> SqlResult<()> {
+    let invalid = |what: &str| SqlError::new_io(what);
+    let doc = Json::parse(record)?;
+    let name = doc.get("table").and_then(Json::as_str).ok_or_else(|| invalid("missing table"))?;
+    let name = TableName::parse(name).map_err(|e| invalid(&e))?;
+    let table = db.table_mut(&name).ok_or_else(|| SqlError::unknown_table(&name))?;
+
+    let mut changes = Vec::new();
+    for change in doc.get("changes").and_then(Json::as_array).ok_or_else(|| invalid("missing changes"))? {
+        let change = change.as_array().unwrap_or_default();
+        let rowid = change.get(1).and_then(rowid_from_json).ok_or_else(|| invalid("change without a rowid"))?;
+        let values = || {
+            let row = change.get(2).ok_or_else(|| invalid("change without values"))?;
+            values_from_json(row, table.schema()).map_err(|e| invalid(&e))
+        };
+        changes.push(match change.first().and_then(Json::as_str) {
+            Some("insert") => RowChange::Insert(rowid, values()?),
+            Some("update") => RowChange::Update(rowid, values()?),
+            Some("delete") => RowChange::Delete(rowid),
+            _ => return Err(invalid("unknown change")),
+        });
+    }
+    table.apply(changes)
+}
+
+/// Builds the error for a failed file operation on the log.
+fn io_error(action: &str, path: &Path, e: std::io::Error) -> SqlError {
+    SqlError::new_io(&format!("WAL: {} '{}': {}", action, path.display(), e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempDir;
+    use crate::core::types::{TableName, Value};
+
+
+    /// Executes `sql` and records it in the log, as a REPL session would.
+    fn run(db: &mut Database, wal: &mut Wal, sql: &str) {
+        let ast = Parser::default().parse(&lexer(sql).unwrap()).unwrap();
+        db.record_changes(true);
+        Executor::execute(ast.clone(), db).unwrap();
+        wal.record(&ast, sql, db.take_changes()).unwrap();
+    }
+
+    #[test]
+    fn replay_rebuilds_committed_changes() {
+        let tmp = TempDir::new("replay");
+        let path = tmp.join("replay.wal");
+        let mut db = Database::new();
+        let mut wal = Wal::open(&path).unwrap();
+        run(&mut db, &mut wal, "CREATE TABLE t (id INT, note TEXT)");
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (1, 'line\nbreak'), (2, 'b')");
+        run(&mut db, &mut wal, "SELECT * FROM t");
+        run(&mut db, &mut wal, "BEGIN");
+        run(&mut db, &mut wal, "DELETE FROM t WHERE id = 1");
+        run(&mut db, &mut wal, "ROLLBACK");
+        run(&mut db, &mut wal, "BEGIN");
+        run(&mut db, &mut wal, "UPDATE t SET note = 'c' WHERE id = 2");
+        run(&mut db, &mut wal, "COMMIT");
+
+        // The committed UPDATE is framed by BEGIN and COMMIT
+        assert_eq!(read_log(&path).unwrap().len(), 5);
+
+        let mut recovered = Database::new();
+        assert_eq!(replay(&path, &Parser::default(), &mut recovered).unwrap().kept, 5);
+        let rows = recovered.table(&TableName::new("t").unwrap()).unwrap().rows();
+        assert_eq!(rows[0].values()[1], Value::Text("line\nbreak".to_string()));
+        assert_eq!(rows[1].values()[1], Value::Text("c".to_string()));
+    }
+
+    #[test]
+    fn replay_restores_rows_as_written() {
+        let tmp = TempDir::new("effects");
+        let path = tmp.join("effects.wal");
+        let csv = path.with_extension("csv");
+        std::fs::write(&csv, "1,a,\n2,b,\n").unwrap();
+        let mut db = Database::new();
+        let mut wal = Wal::open(&path).unwrap();
+        run(&mut db, &mut wal, "CREATE TABLE t (id INT, note TEXT, at TIMESTAMP)");
+        run(&mut db, &mut wal, &format!("COPY t FROM '{}'", csv.display()));
+        run(&mut db, &mut wal, "UPDATE t SET at = NOW() WHERE id = 2");
+        run(&mut db, &mut wal, "DELETE FROM t WHERE id = 1");
+        std::fs::remove_file(&csv).unwrap();
+
+        // Row statements are logged as their effects, not their text
+        let records = read_log(&path).unwrap();
+        assert_eq!(records[1], r#"{"table":"t","changes":[["insert",1,[1,"a",null]],["insert",2,[2,"b",null]]]}"#);
+        assert_eq!(records[3], r#"{"table":"t","changes":[["delete",1]]}"#);
+
+        let mut recovered = Database::new();
+        assert_eq!(replay(&path, &Parser::default(), &mut recovered).unwrap().kept, 4);
+        let name = TableName::new("t").unwrap();
+        let (table, original) = (recovered.table(&name).unwrap(), db.table(&name).unwrap());
+        assert_eq!(table.rows(), original.rows());
+        assert_eq!((table.rowids(), table.next_rowid()), (&[2][..], 3));
+    }
+
+    #[test]
+    fn replay_truncates_tables() {
+        let tmp = TempDir::new("truncate");
+        let path = tmp.join("truncate.wal");
+        let mut db = Database::new();
+        let mut wal = Wal::open(&path).unwrap();
+        run(&mut db, &mut wal, "CREATE TABLE t (id INT)");
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (1), (2)");
+        run(&mut db, &mut wal, "TRUNCATE TABLE t");
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (3)");
+
+        let mut recovered = Database::new();
+        assert_eq!(replay(&path, &Parser::default(), &mut recovered).unwrap().kept, 4);
+        let ids: Vec<Value> = recovered.table(&TableName::new("t").unwrap()).unwrap().rows().iter().map(|row| row.values()[0].clone()).collect();
+        assert_eq!(ids, [Value::Int(3)]);
+    }
+
+    #[test]
+    fn replay_undoes_what_savepoints_undid() {
+        let tmp = TempDir::new("savepoint");
+        let path = tmp.join("savepoint.wal");
+        let mut db = Database::new();
+        let mut wal = Wal::open(&path).unwrap();
+        run(&mut db, &mut wal, "CREATE TABLE t (id INT)");
+        run(&mut db, &mut wal, "BEGIN");
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (1)");
+        run(&mut db, &mut wal, "SAVEPOINT sp");
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (2)");
+        run(&mut db, &mut wal, "ROLLBACK TO sp");
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (3)");
+        run(&mut db, &mut wal, "RELEASE sp");
+        run(&mut db, &mut wal, "COMMIT");
+
+        let mut recovered = Database::new();
+        replay(&path, &Parser::default(), &mut recovered).unwrap();
+        let ids: Vec<Value> = recovered.table(&TableName::new("t").unwrap()).unwrap().rows().iter().map(|row| row.values()[0].clone()).collect();
+        assert_eq!(ids, [Value::Int(1), Value::Int(3)]);
+    }
+
+    #[test]
+    fn sync_modes_decide_when_records_are_written() {
+        let tmp = TempDir::new("sync");
+        let path = tmp.join("sync.wal");
+        let mut db = Database::new();
+        let mut wal = Wal::open(&path).unwrap().with_sync(SyncMode::Off);
+        run(&mut db, &mut wal, "CREATE TABLE t (id INT)");
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (1)");
+        // Held back, but counted in the size of the log
+        assert!(read_log(&path).unwrap().is_empty());
+        assert_eq!(wal.size().unwrap(), 73);
+
+        wal.set_sync(SyncMode::Normal).unwrap();
+        assert_eq!(read_log(&path).unwrap().len(), 2);
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (2)");
+        assert_eq!(read_log(&path).unwrap().len(), 3);
+
+        let mut wal = wal.with_sync(SyncMode::Off);
+        run(&mut db, &mut wal, "INSERT INTO t VALUES (3)");
+        drop(wal);
+        assert_eq!(read_log(&path).unwrap().len(), 4);
+
+        assert_eq!(SyncMode::parse("NORMAL"), Some(SyncMode::Normal));
+        assert_eq!(SyncMode::parse("sometimes"), None);
+    }
+
+    #[test]
+    fn torn_final_record_is_ignored() {
+        let tmp = TempDir::new("torn");
+        let path = tmp.join("torn.wal");
+        std::fs::write(&path, "22 CREATE TABLE t (a INT)\n30 INSERT INTO t VALUES").unwrap();
+        assert_eq!(read_log(&path).unwrap(), vec!["CREATE TABLE t (a INT)".to_string()]);
+
+        std::fs::write(&path, "x CREATE TABLE t (a INT)\n").unwrap();
+        assert!(read_log(&path).is_err());
+        std::fs::remove_file(&path).unwrap();
+
+        assert!(read_log(&path).unwrap().is_empty());
+    }
+
+    #[test]
+    fn unterminated_transaction_is_rolled_back() {
+        let tmp = TempDir::new("unterminated");
+        let path = tmp.join("unterminated.wal");
+        std::fs::write(&path, "22 CREATE TABLE t (a INT)\n5 BEGIN\n24 INSERT INTO t VALUES (1)\n").unwrap();
+
+        // The transaction is not kept, and cutting the log drops it
+        let mut db = Database::new();
+        let replayed = replay(&path, &Parser::default(), &mut db).unwrap();
+        assert_eq!((replayed.kept, replayed.error.is_none()), (1, true));
+        assert!(!db.in_transaction());
+        assert!(db.table(&TableName::new("t").unwrap()).unwrap().rows().is_empty());
+        cut(&path, replayed.kept, None).unwrap();
+        assert_eq!(read_log(&path).unwrap(), vec!["CREATE TABLE t (a INT)".to_string()]);
+    }
+}